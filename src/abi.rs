@@ -0,0 +1,840 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{CliError, ErrorKind};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub fn create_abi_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("abi")
+        .about("Contract ABI commands.")
+        .subcommand(SubCommand::with_name("diff")
+            .about("Compares two versions of contract ABI and reports breaking changes (e.g. before setcode).")
+            .arg(Arg::with_name("OLD")
+                .required(true)
+                .takes_value(true)
+                .help("Json file with old contract ABI."))
+            .arg(Arg::with_name("NEW")
+                .required(true)
+                .takes_value(true)
+                .help("Json file with new contract ABI.")))
+        .subcommand(SubCommand::with_name("lint")
+            .about("Checks ABI for constructs which are invalid or not supported by this build (unknown types, features of newer ABI versions).")
+            .arg(Arg::with_name("ABI")
+                .required(true)
+                .takes_value(true)
+                .help("Json file with contract ABI.")))
+}
+
+pub fn create_fixture_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("fixture")
+        .about("Generates parameter files with placeholder values for every contract function and a sample script calling them.")
+        .arg(Arg::with_name("ABI")
+            .required(true)
+            .takes_value(true)
+            .help("Json file with contract ABI."))
+        .arg(Arg::with_name("OUT")
+            .long("--out")
+            .short("-o")
+            .takes_value(true)
+            .help("Directory where to store generated files (default: ./fixtures)."))
+}
+
+pub async fn abi_command(m: &ArgMatches<'_>) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("diff") {
+        let old = read_abi(m.value_of("OLD").unwrap())?;
+        let new = read_abi(m.value_of("NEW").unwrap())?;
+        let changes = diff_abi(&old, &new);
+        if changes.is_empty() {
+            println!("ABIs are compatible, no changes found.");
+            return Ok(());
+        }
+        for change in &changes {
+            println!("{}", change);
+        }
+        let breaking = changes.iter().filter(|c| c.breaking).count();
+        if breaking > 0 {
+            return Err(CliError::new(ErrorKind::InvalidInput, format!("found {} breaking change(s)", breaking)));
+        }
+        println!("No breaking changes.");
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("lint") {
+        let abi = read_abi(m.value_of("ABI").unwrap())?;
+        let issues = lint_abi(&abi);
+        for issue in &issues {
+            println!("{}", issue);
+        }
+        let errors = issues.iter().filter(|i| i.error).count();
+        if errors > 0 {
+            return Err(CliError::new(ErrorKind::InvalidInput, format!("found {} error(s)", errors)));
+        }
+        println!("ABI is supported.");
+        return Ok(());
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown abi command"))
+}
+
+pub fn read_abi(path: &str) -> Result<Value, String> {
+    let abi = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read ABI file {}: {}", path, e))?;
+    serde_json::from_str(&abi)
+        .map_err(|e| format!("failed to parse ABI file {}: {}", path, e))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug)]
+pub struct AbiChange {
+    pub kind: ChangeKind,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl std::fmt::Display for AbiChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let sign = match self.kind {
+            ChangeKind::Added => "+",
+            ChangeKind::Removed => "-",
+            ChangeKind::Changed => "~",
+        };
+        write!(f, "{} {}{}", sign, self.description, if self.breaking { " [BREAKING]" } else { "" })
+    }
+}
+
+fn change(kind: ChangeKind, breaking: bool, description: String) -> AbiChange {
+    AbiChange { kind, breaking, description }
+}
+
+/// Returns textual signature of the parameter including tuple components.
+fn param_sig(param: &Value) -> String {
+    let ty = param["type"].as_str().unwrap_or("");
+    match param["components"].as_array() {
+        Some(components) => {
+            let inner: Vec<String> = components.iter().map(|c| param_sig(c)).collect();
+            format!("{}({})", ty, inner.join(","))
+        },
+        None => ty.to_owned(),
+    }
+}
+
+fn params_sig(params: &Value) -> String {
+    params.as_array()
+        .map(|p| p.iter()
+            .map(|p| format!("{} {}", param_sig(p), p["name"].as_str().unwrap_or("")))
+            .collect::<Vec<_>>()
+            .join(", "))
+        .unwrap_or_default()
+}
+
+fn by_name<'a>(abi: &'a Value, section: &str) -> BTreeMap<String, &'a Value> {
+    abi[section].as_array()
+        .map(|items| items.iter()
+            .map(|item| (item["name"].as_str().unwrap_or("").to_owned(), item))
+            .collect())
+        .unwrap_or_default()
+}
+
+fn diff_section(
+    changes: &mut Vec<AbiChange>,
+    old: &Value,
+    new: &Value,
+    section: &str,
+    kind: &str,
+    removal_breaks: bool,
+) {
+    let old_items = by_name(old, section);
+    let new_items = by_name(new, section);
+    for (name, item) in &old_items {
+        match new_items.get(name) {
+            None => changes.push(change(
+                ChangeKind::Removed,
+                removal_breaks,
+                format!("{} {}({})", kind, name, params_sig(&item["inputs"])),
+            )),
+            Some(new_item) => {
+                let (old_in, new_in) = (params_sig(&item["inputs"]), params_sig(&new_item["inputs"]));
+                if old_in != new_in {
+                    changes.push(change(
+                        ChangeKind::Changed,
+                        true,
+                        format!("{} {} inputs: ({}) -> ({})", kind, name, old_in, new_in),
+                    ));
+                }
+                let (old_out, new_out) = (params_sig(&item["outputs"]), params_sig(&new_item["outputs"]));
+                if old_out != new_out {
+                    changes.push(change(
+                        ChangeKind::Changed,
+                        true,
+                        format!("{} {} outputs: ({}) -> ({})", kind, name, old_out, new_out),
+                    ));
+                }
+            },
+        }
+    }
+    for (name, item) in &new_items {
+        if !old_items.contains_key(name) {
+            changes.push(change(
+                ChangeKind::Added,
+                false,
+                format!("{} {}({})", kind, name, params_sig(&item["inputs"])),
+            ));
+        }
+    }
+}
+
+/// Compares two ABIs. Removed or changed functions, header changes and
+/// changes of the data layout are considered breaking.
+pub fn diff_abi(old: &Value, new: &Value) -> Vec<AbiChange> {
+    let mut changes = vec![];
+
+    let version = |abi: &Value| abi["ABI version"].as_u64()
+        .map(|v| v.to_string())
+        .or(abi["version"].as_str().map(|v| v.to_owned()))
+        .unwrap_or_default();
+    if version(old) != version(new) {
+        changes.push(change(
+            ChangeKind::Changed,
+            false,
+            format!("ABI version: {} -> {}", version(old), version(new)),
+        ));
+    }
+
+    let header = |abi: &Value| abi["header"].as_array()
+        .map(|h| h.iter().map(|h| h.as_str().unwrap_or("").to_owned()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if header(old) != header(new) {
+        changes.push(change(
+            ChangeKind::Changed,
+            true,
+            format!("header: [{}] -> [{}]", header(old).join(", "), header(new).join(", ")),
+        ));
+    }
+
+    diff_section(&mut changes, old, new, "functions", "function", true);
+    diff_section(&mut changes, old, new, "events", "event", false);
+
+    let data = |abi: &Value| abi["data"].as_array()
+        .map(|d| d.iter()
+            .map(|d| (d["key"].as_u64().unwrap_or(0), format!("{} {}", param_sig(d), d["name"].as_str().unwrap_or(""))))
+            .collect::<BTreeMap<_, _>>())
+        .unwrap_or_default();
+    let (old_data, new_data) = (data(old), data(new));
+    for (key, old_item) in &old_data {
+        match new_data.get(key) {
+            None => changes.push(change(ChangeKind::Removed, true, format!("data {}: {}", key, old_item))),
+            Some(new_item) if new_item != old_item => changes.push(change(
+                ChangeKind::Changed,
+                true,
+                format!("data {}: {} -> {}", key, old_item, new_item),
+            )),
+            _ => {},
+        }
+    }
+    for (key, new_item) in &new_data {
+        if !old_data.contains_key(key) {
+            changes.push(change(ChangeKind::Added, false, format!("data {}: {}", key, new_item)));
+        }
+    }
+
+    // storage layout is described by `fields` since ABI 2.1, new fields can only be appended
+    let fields = |abi: &Value| abi["fields"].as_array()
+        .map(|f| f.iter().map(|f| format!("{} {}", param_sig(f), f["name"].as_str().unwrap_or(""))).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let (old_fields, new_fields) = (fields(old), fields(new));
+    for (i, old_field) in old_fields.iter().enumerate() {
+        match new_fields.get(i) {
+            None => changes.push(change(ChangeKind::Removed, true, format!("field #{}: {}", i, old_field))),
+            Some(new_field) if new_field != old_field => changes.push(change(
+                ChangeKind::Changed,
+                true,
+                format!("field #{}: {} -> {}", i, old_field, new_field),
+            )),
+            _ => {},
+        }
+    }
+    for (i, new_field) in new_fields.iter().enumerate().skip(old_fields.len()) {
+        changes.push(change(ChangeKind::Added, false, format!("field #{}: {}", i, new_field)));
+    }
+
+    changes
+}
+
+pub async fn fixture_command(m: &ArgMatches<'_>) -> Result<(), CliError> {
+    let abi_path = m.value_of("ABI").unwrap();
+    let out = std::path::Path::new(m.value_of("OUT").unwrap_or("fixtures"));
+    let abi = read_abi(abi_path)?;
+    std::fs::create_dir_all(out)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to create directory {}: {}", out.display(), e)))?;
+
+    let mut plan = vec![
+        "#!/bin/sh".to_owned(),
+        "# Sample plan generated by `tonos-cli fixture`. Set ADDRESS and KEYS and edit parameter files before running.".to_owned(),
+        "set -e".to_owned(),
+        "ADDRESS=${ADDRESS:?contract address is not set}".to_owned(),
+        "KEYS=${KEYS:-keys.json}".to_owned(),
+        format!("ABI={}", abi_path),
+    ];
+    for (name, function) in by_name(&abi, "functions") {
+        if name == "constructor" {
+            plan.push(format!("# deploy: tonos-cli deploy contract.tvc {}/constructor.json --abi $ABI --sign $KEYS", out.display()));
+        }
+        let params = placeholder_object(&function["inputs"]);
+        let file = out.join(format!("{}.json", name));
+        let text = serde_json::to_string_pretty(&params)
+            .map_err(|e| format!("failed to serialize parameters: {}", e))?;
+        std::fs::write(&file, text)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to write file {}: {}", file.display(), e)))?;
+        println!("{}", file.display());
+
+        if name == "constructor" {
+            continue;
+        }
+        let is_getter = function["inputs"].as_array().map(|i| i.is_empty()).unwrap_or(true)
+            && function["outputs"].as_array().map(|o| !o.is_empty()).unwrap_or(false);
+        if is_getter {
+            plan.push(format!("tonos-cli run $ADDRESS {} {} --abi $ABI", name, file.display()));
+        } else {
+            plan.push(format!("tonos-cli call $ADDRESS {} {} --abi $ABI --sign $KEYS", name, file.display()));
+        }
+    }
+    let plan_file = out.join("plan.sh");
+    std::fs::write(&plan_file, plan.join("\n") + "\n")
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to write file {}: {}", plan_file.display(), e)))?;
+    println!("{}", plan_file.display());
+    Ok(())
+}
+
+fn placeholder_object(params: &Value) -> Value {
+    let mut object = serde_json::Map::new();
+    for param in params.as_array().cloned().unwrap_or_default() {
+        let ty = param["type"].as_str().unwrap_or("");
+        object.insert(
+            param["name"].as_str().unwrap_or("").to_owned(),
+            placeholder(ty, &param["components"]),
+        );
+    }
+    Value::Object(object)
+}
+
+/// Returns placeholder value of the ABI type accepted by the encoder.
+fn placeholder(ty: &str, components: &Value) -> Value {
+    if ty.ends_with(']') {
+        let open = ty.rfind('[').unwrap_or(0);
+        let item = placeholder(&ty[..open], components);
+        let size = ty[open + 1..ty.len() - 1].parse::<usize>().unwrap_or(1);
+        return Value::Array(vec![item; size]);
+    }
+    if ty.starts_with("map(") && ty.ends_with(')') {
+        let mut types = ty[4..ty.len() - 1].splitn(2, ',');
+        let key = placeholder(types.next().unwrap_or("").trim(), &Value::Null);
+        let value = placeholder(types.next().unwrap_or("").trim(), components);
+        let key = key.as_str().map(|k| k.to_owned()).unwrap_or(key.to_string());
+        return json!({ key: value });
+    }
+    if ty.starts_with("optional(") {
+        return Value::Null;
+    }
+    if ty.starts_with("ref(") && ty.ends_with(')') {
+        return placeholder(&ty[4..ty.len() - 1], components);
+    }
+    match ty {
+        "tuple" => placeholder_object(components),
+        "bool" => json!(false),
+        "address" => json!(format!("0:{}", "0".repeat(64))),
+        "cell" | "bytes" | "string" => json!(""),
+        _ if ty.starts_with("fixedbytes") => {
+            json!("00".repeat(ty[10..].parse::<usize>().unwrap_or(1)))
+        },
+        _ => json!("0"),
+    }
+}
+
+/// Newest ABI version supported by the encoder of this build.
+const MAX_ABI_VERSION: (u32, u32) = (2, 4);
+const KNOWN_HEADERS: [&str; 3] = ["pubkey", "time", "expire"];
+
+/// Returns (major, minor) ABI version from `version` ("2.3") or `ABI version` (2) field.
+pub fn abi_version(abi: &Value) -> (u32, u32) {
+    if let Some(version) = abi["version"].as_str() {
+        let mut parts = version.splitn(2, '.');
+        let major = parts.next().and_then(|v| v.parse().ok()).unwrap_or(2);
+        let minor = parts.next().and_then(|v| v.parse().ok()).unwrap_or(0);
+        return (major, minor);
+    }
+    (abi["ABI version"].as_u64().unwrap_or(2) as u32, 0)
+}
+
+/// Parameters of contract initial data: `fields` marked with `init` since ABI 2.4,
+/// `data` section in older versions.
+pub fn initial_data_params(abi: &Value) -> Value {
+    if abi_version(abi) >= (2, 4) && abi["fields"].is_array() {
+        let fields: Vec<Value> = abi["fields"].as_array().unwrap().iter()
+            .filter(|f| f["init"].as_bool().unwrap_or(false) && f["name"] != "_pubkey")
+            .cloned()
+            .collect();
+        return Value::Array(fields);
+    }
+    abi["data"].clone()
+}
+
+/// Validates initial data against ABI. All init fields are required since ABI 2.4,
+/// older versions allow to set only some of data parameters.
+pub fn validate_initial_data(abi: &Value, data: &Value) -> Result<(), String> {
+    let params = initial_data_params(abi);
+    if abi_version(abi) >= (2, 4) {
+        return validate_object("", &params, data).map_err(|e| format!("initial data: {}", e));
+    }
+    let object = data.as_object()
+        .ok_or(format!("initial data must be a json object, got {}", json_kind(data)))?;
+    let params = by_name(abi, "data");
+    for (key, value) in object {
+        let param = params.get(key).ok_or(format!(
+            "initial data: unknown param `{}`{}",
+            key,
+            suggest(key, params.keys().map(|k| k.as_str())),
+        ))?;
+        let ty = param["type"].as_str().unwrap_or("");
+        validate_value(key, ty, &param["components"], value)
+            .map_err(|e| format!("initial data: {}", e))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct LintIssue {
+    pub error: bool,
+    pub message: String,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", if self.error { "error" } else { "warning" }, self.message)
+    }
+}
+
+/// Checks ABI type string, returns description of the problem if the type
+/// is unknown or requires newer ABI version than declared.
+fn lint_type(ty: &str, components: &Value, version: (u32, u32)) -> Option<String> {
+    let requires = |min: (u32, u32)| if version < min {
+        Some(format!("type `{}` requires ABI {}.{}", ty, min.0, min.1))
+    } else {
+        None
+    };
+    if ty.ends_with(']') {
+        let open = ty.rfind('[')?;
+        let size = &ty[open + 1..ty.len() - 1];
+        if !size.is_empty() && size.parse::<usize>().is_err() {
+            return Some(format!("invalid array size in `{}`", ty));
+        }
+        return lint_type(&ty[..open], components, version);
+    }
+    let inner = |prefix: &str| ty.strip_prefix(prefix).and_then(|t| t.strip_suffix(')'));
+    if let Some(types) = inner("map(") {
+        let mut types = types.splitn(2, ',');
+        let key = types.next().unwrap_or("").trim();
+        let value = types.next().unwrap_or("").trim();
+        if !(key.starts_with("int") || key.starts_with("uint") || key == "address") {
+            return Some(format!("invalid map key type `{}`", key));
+        }
+        return lint_type(value, components, version);
+    }
+    if let Some(inner) = inner("optional(") {
+        return requires((2, 1)).or(lint_type(inner, components, version));
+    }
+    if let Some(inner) = inner("ref(") {
+        return requires((2, 4)).or(lint_type(inner, components, version));
+    }
+    let bits = |prefix: &str, max: u32| ty.strip_prefix(prefix)
+        .and_then(|n| n.parse::<u32>().ok())
+        .map(|n| n >= 1 && n <= max);
+    match ty {
+        "tuple" => {
+            let components = match components.as_array() {
+                Some(components) => components,
+                None => return Some("tuple without components".to_owned()),
+            };
+            components.iter()
+                .find_map(|c| lint_type(c["type"].as_str().unwrap_or(""), &c["components"], version))
+        },
+        "bool" | "address" | "cell" | "bytes" => None,
+        "gram" => if version >= (2, 1) { Some("type `gram` is replaced with `token` since ABI 2.1".to_owned()) } else { None },
+        "string" | "token" => requires((2, 1)),
+        "varint16" | "varint32" | "varuint16" | "varuint32" => requires((2, 1)),
+        _ if ty.starts_with("uint") => match bits("uint", 256) {
+            Some(true) => None,
+            _ => Some(format!("invalid type `{}`", ty)),
+        },
+        _ if ty.starts_with("int") => match bits("int", 256) {
+            Some(true) => None,
+            _ => Some(format!("invalid type `{}`", ty)),
+        },
+        _ if ty.starts_with("fixedbytes") => match bits("fixedbytes", 32) {
+            Some(true) => None,
+            _ => Some(format!("invalid type `{}`", ty)),
+        },
+        _ => Some(format!("unknown type `{}`", ty)),
+    }
+}
+
+fn lint_params(issues: &mut Vec<LintIssue>, context: &str, params: &Value, version: (u32, u32)) {
+    for param in params.as_array().cloned().unwrap_or_default() {
+        let name = param["name"].as_str().unwrap_or("");
+        if let Some(problem) = lint_type(param["type"].as_str().unwrap_or(""), &param["components"], version) {
+            issues.push(LintIssue { error: true, message: format!("{} `{}`: {}", context, name, problem) });
+        }
+    }
+}
+
+/// Reports constructs which the encoder of this build can't handle.
+pub fn lint_abi(abi: &Value) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let version = abi_version(abi);
+    if version.0 != 2 || version > MAX_ABI_VERSION {
+        issues.push(LintIssue {
+            error: true,
+            message: format!(
+                "ABI version {}.{} is not supported (max {}.{})",
+                version.0, version.1, MAX_ABI_VERSION.0, MAX_ABI_VERSION.1,
+            ),
+        });
+    }
+    for header in abi["header"].as_array().cloned().unwrap_or_default() {
+        let header = header.as_str().unwrap_or("");
+        if !KNOWN_HEADERS.contains(&header) {
+            issues.push(LintIssue { error: true, message: format!("unknown header `{}`", header) });
+        }
+    }
+    for function in abi["functions"].as_array().cloned().unwrap_or_default() {
+        let name = function["name"].as_str().unwrap_or("");
+        lint_params(&mut issues, &format!("function {} input", name), &function["inputs"], version);
+        lint_params(&mut issues, &format!("function {} output", name), &function["outputs"], version);
+    }
+    for event in abi["events"].as_array().cloned().unwrap_or_default() {
+        let name = event["name"].as_str().unwrap_or("");
+        lint_params(&mut issues, &format!("event {} param", name), &event["inputs"], version);
+    }
+    lint_params(&mut issues, "data", &abi["data"], version);
+    if abi["fields"].is_array() {
+        if version < (2, 3) {
+            issues.push(LintIssue { error: false, message: "`fields` section requires ABI 2.3, it is ignored".to_owned() });
+        }
+        lint_params(&mut issues, "field", &abi["fields"], version);
+        let has_init = abi["fields"].as_array().unwrap().iter().any(|f| f.get("init").is_some());
+        if has_init && version < (2, 4) {
+            issues.push(LintIssue { error: false, message: "`init` flag of fields requires ABI 2.4, initial data is taken from `data` section".to_owned() });
+        }
+    }
+    if version >= (2, 4) && abi["data"].as_array().map(|d| !d.is_empty()).unwrap_or(false) {
+        issues.push(LintIssue { error: false, message: "`data` section is ignored since ABI 2.4, mark fields with `init` instead".to_owned() });
+    }
+    issues
+}
+
+/// Levenshtein distance, used to suggest near-miss names.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = std::cmp::min(std::cmp::min(prev[j + 1] + 1, cur[j] + 1), prev[j] + cost);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> String {
+    candidates
+        .map(|c| (edit_distance(&name.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| format!(", did you mean `{}`?", c))
+        .unwrap_or_default()
+}
+
+fn json_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn is_integer(value: &Value, signed: bool) -> bool {
+    match value {
+        Value::Number(n) => n.is_u64() || (signed && n.is_i64()),
+        Value::String(s) => {
+            let s = if signed { s.trim_start_matches('-') } else { s.as_str() };
+            if s.starts_with("0x") || s.starts_with("0X") {
+                s.len() > 2 && s[2..].chars().all(|c| c.is_ascii_hexdigit())
+            } else {
+                !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+            }
+        },
+        _ => false,
+    }
+}
+
+fn is_address(value: &Value) -> bool {
+    let s = match value.as_str() {
+        Some(s) => s,
+        None => return false,
+    };
+    match s.find(':') {
+        Some(pos) => s[..pos].parse::<i32>().is_ok()
+            && s.len() - pos - 1 == 64
+            && s[pos + 1..].chars().all(|c| c.is_ascii_hexdigit()),
+        // user-friendly base64 address
+        None => s.len() == 48,
+    }
+}
+
+/// Checks json value against ABI type (`ty` is the type string, `components`
+/// describe tuple fields).
+fn validate_value(path: &str, ty: &str, components: &Value, value: &Value) -> Result<(), String> {
+    let mismatch = |expected: &str| Err(format!("param `{}`: expected {}, got {}", path, expected, json_kind(value)));
+
+    if ty.ends_with(']') {
+        let open = ty.rfind('[').unwrap_or(0);
+        let inner = &ty[..open];
+        let size = &ty[open + 1..ty.len() - 1];
+        let items = match value.as_array() {
+            Some(items) => items,
+            None => return mismatch(&format!("array of {}", inner)),
+        };
+        if let Ok(size) = size.parse::<usize>() {
+            if items.len() != size {
+                return Err(format!("param `{}`: expected {} items, got {}", path, size, items.len()));
+            }
+        }
+        for (i, item) in items.iter().enumerate() {
+            validate_value(&format!("{}[{}]", path, i), inner, components, item)?;
+        }
+        return Ok(());
+    }
+    if ty.starts_with("map(") && ty.ends_with(')') {
+        let value_ty = ty[4..ty.len() - 1].splitn(2, ',').nth(1).unwrap_or("").trim();
+        let items = match value.as_object() {
+            Some(items) => items,
+            None => return mismatch("map (json object)"),
+        };
+        for (key, item) in items {
+            validate_value(&format!("{}.{}", path, key), value_ty, components, item)?;
+        }
+        return Ok(());
+    }
+    if ty.starts_with("optional(") && ty.ends_with(')') {
+        if value.is_null() {
+            return Ok(());
+        }
+        return validate_value(path, &ty[9..ty.len() - 1], components, value);
+    }
+    if ty.starts_with("ref(") && ty.ends_with(')') {
+        return validate_value(path, &ty[4..ty.len() - 1], components, value);
+    }
+    match ty {
+        "tuple" => match value.as_object() {
+            Some(_) => validate_object(path, components, value),
+            None => mismatch("tuple (json object)"),
+        },
+        "bool" => match value {
+            Value::Bool(_) => Ok(()),
+            Value::String(s) if s == "true" || s == "false" => Ok(()),
+            _ => mismatch("boolean"),
+        },
+        "address" => if is_address(value) { Ok(()) } else { mismatch("address") },
+        "cell" => if value.is_string() { Ok(()) } else { mismatch("cell (base64 string)") },
+        "string" => if value.is_string() { Ok(()) } else { mismatch("string") },
+        _ if ty == "bytes" || ty.starts_with("fixedbytes") => match value.as_str() {
+            Some(s) if s.chars().all(|c| c.is_ascii_hexdigit()) => Ok(()),
+            _ => mismatch("bytes (hex string)"),
+        },
+        _ if ty.starts_with("int") || ty.starts_with("varint") => {
+            if is_integer(value, true) { Ok(()) } else { mismatch("integer") }
+        },
+        _ if ty.starts_with("uint") || ty.starts_with("varuint") || ty == "gram" || ty == "token"
+            || ty == "time" || ty == "expire" => {
+            if is_integer(value, false) { Ok(()) } else { mismatch("unsigned integer") }
+        },
+        _ => Ok(()),
+    }
+}
+
+fn validate_object(path: &str, params: &Value, value: &Value) -> Result<(), String> {
+    let prefix = if path.is_empty() { String::new() } else { format!("{}.", path) };
+    let params = params.as_array().cloned().unwrap_or_default();
+    let object = value.as_object().cloned().unwrap_or_default();
+    let names = || params.iter().filter_map(|p| p["name"].as_str());
+    for key in object.keys() {
+        if !names().any(|n| n == key) {
+            return Err(format!("unknown param `{}{}`{}", prefix, key, suggest(key, names())));
+        }
+    }
+    for param in &params {
+        let name = param["name"].as_str().unwrap_or("");
+        let ty = param["type"].as_str().unwrap_or("");
+        match object.get(name) {
+            Some(v) => validate_value(&format!("{}{}", prefix, name), ty, &param["components"], v)?,
+            None => return Err(format!("missing param `{}{}` of type {}", prefix, name, ty)),
+        }
+    }
+    Ok(())
+}
+
+/// Validates call arguments against function inputs from ABI so that user
+/// gets a precise error instead of the encoder failure.
+pub fn validate_params(abi: &Value, method: &str, params: &Value) -> Result<(), String> {
+    let functions = by_name(abi, "functions");
+    let function = match functions.get(method) {
+        Some(function) => function,
+        None => return Err(format!(
+            "function `{}` not found in ABI{}",
+            method,
+            suggest(method, functions.keys().map(|k| k.as_str())),
+        )),
+    };
+    if !params.is_object() {
+        return Err(format!("arguments must be a json object, got {}", json_kind(params)));
+    }
+    validate_object("", &function["inputs"], params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_abi() {
+        let old = json!({
+            "ABI version": 2,
+            "header": ["time", "expire"],
+            "functions": [
+                { "name": "transfer", "inputs": [{ "name": "dest", "type": "address" }], "outputs": [] },
+                { "name": "getBalance", "inputs": [], "outputs": [{ "name": "value", "type": "uint128" }] },
+            ],
+            "data": [{ "key": 1, "name": "owner", "type": "uint256" }],
+        });
+        let new = json!({
+            "ABI version": 2,
+            "header": ["time", "expire"],
+            "functions": [
+                { "name": "transfer", "inputs": [{ "name": "dest", "type": "address" }], "outputs": [] },
+                { "name": "getOwner", "inputs": [], "outputs": [{ "name": "owner", "type": "uint256" }] },
+            ],
+            "data": [{ "key": 1, "name": "owner", "type": "uint256" }],
+        });
+        let changes = diff_abi(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().any(|c| c.kind == ChangeKind::Removed && c.breaking));
+        assert!(changes.iter().any(|c| c.kind == ChangeKind::Added && !c.breaking));
+        assert!(diff_abi(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_placeholder() {
+        assert_eq!(placeholder("uint128", &Value::Null), json!("0"));
+        assert_eq!(placeholder("bool[2]", &Value::Null), json!([false, false]));
+        assert_eq!(placeholder("map(uint32,bool)", &Value::Null), json!({ "0": false }));
+        let components = json!([{ "name": "a", "type": "address" }]);
+        let value = placeholder("tuple", &components);
+        assert!(validate_value("t", "tuple", &components, &value).is_ok());
+    }
+
+    #[test]
+    fn test_lint_abi() {
+        let abi = json!({
+            "ABI version": 2,
+            "version": "2.4",
+            "header": ["time", "expire"],
+            "functions": [{
+                "name": "f",
+                "inputs": [
+                    { "name": "a", "type": "ref(varuint16)" },
+                    { "name": "b", "type": "optional(map(address,uint128))" },
+                ],
+                "outputs": [],
+            }],
+            "fields": [
+                { "name": "_pubkey", "type": "uint256", "init": true },
+                { "name": "owner", "type": "address", "init": true },
+            ],
+        });
+        assert!(lint_abi(&abi).is_empty());
+        assert_eq!(initial_data_params(&abi), json!([{ "name": "owner", "type": "address", "init": true }]));
+        assert!(validate_initial_data(&abi, &json!({})).is_err());
+
+        let old = json!({
+            "ABI version": 2,
+            "version": "2.2",
+            "header": ["time", "nonce"],
+            "functions": [{
+                "name": "f",
+                "inputs": [{ "name": "a", "type": "ref(uint32)" }, { "name": "b", "type": "uint300" }],
+                "outputs": [],
+            }],
+            "data": [{ "key": 1, "name": "owner", "type": "address" }],
+        });
+        let errors: Vec<String> = lint_abi(&old).iter().filter(|i| i.error).map(|i| i.message.clone()).collect();
+        assert_eq!(errors, vec![
+            "unknown header `nonce`".to_owned(),
+            "function f input `a`: type `ref(uint32)` requires ABI 2.4".to_owned(),
+            "function f input `b`: invalid type `uint300`".to_owned(),
+        ]);
+        assert!(validate_initial_data(&old, &json!({})).is_ok());
+        assert!(validate_initial_data(&old, &json!({ "ownr": "x" })).is_err());
+    }
+
+    #[test]
+    fn test_validate_params() {
+        let abi = json!({
+            "functions": [{
+                "name": "submitTransaction",
+                "inputs": [
+                    { "name": "dest", "type": "address" },
+                    { "name": "value", "type": "uint128" },
+                    { "name": "bounce", "type": "bool" },
+                ],
+                "outputs": [],
+            }],
+        });
+        let addr = format!("0:{}", "1".repeat(64));
+        assert!(validate_params(&abi, "submitTransaction", &json!({ "dest": addr, "value": "0x10", "bounce": true })).is_ok());
+        assert_eq!(
+            validate_params(&abi, "submitTransaction", &json!({ "dest": 1, "value": 1, "bounce": true })).unwrap_err(),
+            "param `dest`: expected address, got integer",
+        );
+        assert_eq!(
+            validate_params(&abi, "submitTransaction", &json!({ "dest": addr, "bounce": true })).unwrap_err(),
+            "missing param `value` of type uint128",
+        );
+        assert_eq!(
+            validate_params(&abi, "submitTransaction", &json!({ "dest": addr, "valeu": 1, "bounce": true })).unwrap_err(),
+            "unknown param `valeu`, did you mean `value`?",
+        );
+        assert_eq!(
+            validate_params(&abi, "submitTransacton", &json!({})).unwrap_err(),
+            "function `submitTransacton` not found in ABI, did you mean `submitTransaction`?",
+        );
+    }
+}
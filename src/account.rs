@@ -10,10 +10,20 @@
 * See the License for the specific TON DEV software governing permissions and
 * limitations under the License.
 */
-use crate::helpers::create_client_verbose;
+use crate::helpers::{create_client_local, create_client_verbose, network_policy, now, with_policy, BulkQuery};
+use crate::call::{call_contract_with_result, query_account_boc};
 use crate::config::Config;
+use crate::convert::nano_to_tokens;
+use crate::multisig;
+use crate::getconfig::query_config_param;
+use crate::output;
+use crate::pretty::parse_uint;
+use crate::i18n::{tr, trf};
 use serde_json::json;
+use std::sync::Arc;
+use ton_client::boc::{parse_account, ParamsOfParse};
 use ton_client::net::{ParamsOfQueryCollection, query_collection};
+use ton_block::{Deserializable, Serializable, StateInit};
 
 const ACCOUNT_FIELDS: &str = r#"
     acc_type_name
@@ -21,13 +31,26 @@ const ACCOUNT_FIELDS: &str = r#"
     last_paid
     last_trans_lt
     data
+    due_payment(format: DEC)
+    state_hash
 "#;
 
+/// Extra value sent on unfreeze above the debt to pay for the unfreezing transaction.
+const UNFREEZE_RESERVE: u128 = 100_000_000;
+
+/// How many seconds to wait for the account to become active after the transfer.
+const UNFREEZE_WAIT_ATTEMPTS: u32 = 30;
+
+/// Amount which must be sent to the frozen account to pay off its storage debt.
+pub fn unfreeze_debt(balance: u128, due_payment: u128) -> u128 {
+    due_payment.saturating_sub(balance)
+}
+
 pub async fn get_account(conf: Config, addr: &str) -> Result<(), String> {
     let ton = create_client_verbose(&conf)?;
 
-    println!("Processing...");
-    let query_result = query_collection(
+    println!("{}", tr("Processing..."));
+    let query_result = with_policy(network_policy(), || query_collection(
         ton.clone(),
         ParamsOfQueryCollection {
             collection: "accounts".to_owned(),
@@ -36,13 +59,27 @@ pub async fn get_account(conf: Config, addr: &str) -> Result<(), String> {
             limit: Some(1),
             ..Default::default()
         },
-    ).await.map_err(|e| format!("failed to query account info: {}", e))?;
+    )).await.map_err(|e| format!("failed to query account info: {}", e))?;
     let accounts = query_result.result;
-    println!("Succeeded.");
+    println!("{}", tr("Succeeded."));
 
     if accounts.len() == 1 {
         let acc = &accounts[0];
         let acc_type = acc["acc_type_name"].as_str().unwrap();
+        if acc_type != "NonExist" && output::has_template() {
+            let data = acc["data"].as_str()
+                .and_then(|d| base64::decode(d).ok())
+                .map(|d| hex::encode(&d));
+            return output::print_value(&json!({
+                "address": addr,
+                "acc_type": acc_type,
+                "balance": acc["balance"],
+                "last_paid": acc["last_paid"],
+                "last_trans_lt": acc["last_trans_lt"],
+                "data": data,
+                "due_payment": acc["due_payment"],
+            }), || {});
+        }
         if acc_type != "NonExist" {
             println!("acc_type:      {}", acc_type);
             let balance_str = &acc["balance"].as_str().unwrap();
@@ -56,11 +93,429 @@ pub async fn get_account(conf: Config, addr: &str) -> Result<(), String> {
             } else {
                 println!("data(boc): null");
             }
+            if acc_type == "Frozen" {
+                print_frozen_info(addr, acc);
+            }
         } else {
-            println!("Account does not exist.");    
+            println!("{}", tr("Account does not exist."));    
         }
     } else {
-        println!("Account not found.");
+        println!("{}", tr("Account not found."));
+    }
+    Ok(())
+}
+
+fn print_frozen_info(addr: &str, acc: &serde_json::Value) {
+    let balance = parse_uint(&acc["balance"]).unwrap_or(0);
+    let due = parse_uint(&acc["due_payment"]).unwrap_or(0);
+    let debt = unfreeze_debt(balance, due);
+    println!();
+    println!("{}", tr("Account is frozen because of unpaid storage fees."));
+    println!("due_payment:   {}", nano_to_tokens(due as u64));
+    println!("state_hash:    {}", acc["state_hash"].as_str().unwrap_or("null"));
+    println!("{}", trf("To unfreeze it send at least {} tokens and a message with its original state init:", &[&nano_to_tokens(debt as u64)]));
+    println!("  tonos-cli account unfreeze {} --from <wallet> --tvc <state init file>", addr);
+}
+
+/// Pays off storage debt of the frozen account from the multisig wallet. The
+/// transfer carries the original state init, which unfreezes the account.
+pub async fn unfreeze_account(
+    conf: Config,
+    addr: &str,
+    wallet: &str,
+    keys: &str,
+    tvc: Option<&str>,
+) -> Result<(), String> {
+    let ton = create_client_verbose(&conf)?;
+    let accounts = BulkQuery::new(ton.clone(), &conf).query(
+        "accounts",
+        json!({ "id": { "eq": addr } }),
+        "acc_type_name balance(format: DEC) due_payment(format: DEC) state_hash",
+        None,
+        Some(1),
+    ).await?;
+    let acc = accounts.first().ok_or("account not found")?;
+    if acc["acc_type_name"].as_str() != Some("Frozen") {
+        return Err(format!("account is not frozen ({})", acc["acc_type_name"].as_str().unwrap_or("unknown")));
+    }
+
+    // check state init before sending any tokens
+    let state_init = tvc.map(|path| {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("failed to read state init file: {}", e))?;
+        let state_init = StateInit::construct_from_bytes(&bytes)
+            .map_err(|e| format!("failed to load StateInit from file: {}", e))?;
+        let hash = state_init.serialize()
+            .map_err(|e| format!("failed to serialize state init: {}", e))?
+            .repr_hash()
+            .to_hex_string();
+        if Some(hash.as_str()) != acc["state_hash"].as_str() {
+            return Err(format!(
+                "state init hash {} doesn't match hash of the frozen state {}",
+                hash, acc["state_hash"].as_str().unwrap_or("null"),
+            ));
+        }
+        Ok(state_init)
+    }).transpose()?;
+
+    let balance = parse_uint(&acc["balance"]).unwrap_or(0);
+    let due = parse_uint(&acc["due_payment"]).unwrap_or(0);
+    let value = nano_to_tokens((unfreeze_debt(balance, due) + UNFREEZE_RESERVE) as u64);
+    println!("{}", trf("Storage debt: {}, sending {} from {}", &[&nano_to_tokens(due as u64), &value, &wallet]));
+    let state_init = match state_init {
+        Some(state_init) => state_init,
+        None => {
+            multisig::send_nobounce(conf.clone(), wallet, addr, &value, keys).await?;
+            println!("{}", tr("Debt is paid. Account stays frozen until it receives a message with its original state init (use --tvc)."));
+            return Ok(());
+        },
+    };
+    // frozen account accepts only internal message with the state init, so it's
+    // attached to the transfer which pays the debt
+    let state_init = base64::encode(&state_init.write_to_bytes()
+        .map_err(|e| format!("failed to serialize state init: {}", e))?);
+    multisig::send_with_state_init(conf.clone(), wallet, addr, &value, keys, &state_init).await?;
+
+    let mut acc_type = String::new();
+    for _ in 0..UNFREEZE_WAIT_ATTEMPTS {
+        let accounts = BulkQuery::new(ton.clone(), &conf).query(
+            "accounts",
+            json!({ "id": { "eq": addr } }),
+            "acc_type_name",
+            None,
+            Some(1),
+        ).await?;
+        acc_type = accounts.first().and_then(|a| a["acc_type_name"].as_str()).unwrap_or("unknown").to_owned();
+        if acc_type == "Active" {
+            break;
+        }
+        tokio::time::delay_for(std::time::Duration::from_secs(1)).await;
+    }
+    println!("{}", trf("Account state: {}", &[&acc_type]));
+    if acc_type != "Active" {
+        return Err("account was not unfrozen. If the wallet requires several confirmations, the transfer is waiting for them".to_owned());
     }
+    println!("{}", tr("Succeeded."));
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Max number of addresses requested in one query.
+const ACCOUNTS_CHUNK_SIZE: usize = 50;
+
+/// Fetches balances of several accounts with batched queries and prints them
+/// as a table sorted by balance.
+pub async fn get_accounts(conf: Config, addrs: Vec<String>) -> Result<(), String> {
+    let ton = create_client_verbose(&conf)?;
+    let bulk = Arc::new(BulkQuery::new(ton, &conf));
+
+    println!("{}", tr("Processing..."));
+    let mut handles = vec![];
+    for chunk in addrs.chunks(ACCOUNTS_CHUNK_SIZE) {
+        let bulk = bulk.clone();
+        let chunk = chunk.to_vec();
+        handles.push(tokio::spawn(async move {
+            bulk.query(
+                "accounts",
+                json!({ "id": { "in": chunk } }),
+                "id acc_type_name balance(format: DEC)",
+                None,
+                Some(chunk.len() as u32),
+            ).await
+        }));
+    }
+    let mut found = std::collections::HashMap::new();
+    for handle in handles {
+        let accounts = handle.await
+            .map_err(|e| format!("failed to query accounts: {}", e))?
+            .map_err(|e| format!("failed to query accounts: {}", e))?;
+        for acc in accounts {
+            let id = acc["id"].as_str().unwrap_or("").to_owned();
+            found.insert(id, acc);
+        }
+    }
+    println!("{}", tr("Succeeded."));
+
+    let mut rows: Vec<(String, String, u64)> = addrs.iter().map(|addr| {
+        match found.get(addr) {
+            Some(acc) => (
+                addr.clone(),
+                acc["acc_type_name"].as_str().unwrap_or("unknown").to_owned(),
+                acc["balance"].as_str().and_then(|b| u64::from_str_radix(b, 10).ok()).unwrap_or(0),
+            ),
+            None => (addr.clone(), "NotFound".to_owned(), 0),
+        }
+    }).collect();
+    rows.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+
+    let total: u64 = rows.iter().map(|r| r.2).sum();
+    if output::has_template() {
+        for (addr, acc_type, balance) in rows {
+            output::print_value(&json!({
+                "address": addr,
+                "acc_type": acc_type,
+                "balance": balance.to_string(),
+            }), || {})?;
+        }
+        return Ok(());
+    }
+    println!("{:<68} {:<10} {:>20}", "address", "acc_type", "balance");
+    for (addr, acc_type, balance) in rows {
+        println!("{:<68} {:<10} {:>20}", addr, acc_type, balance);
+    }
+    println!("{:<68} {:<10} {:>20}", "total", "", total);
+    Ok(())
+}
+
+/// Parses duration like "365d", "12h", "30m", "45s" or plain number of seconds.
+pub fn parse_duration(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&s[..i], c),
+        _ => (s, 's'),
+    };
+    let number = u64::from_str_radix(number, 10)
+        .map_err(|e| format!("failed to parse duration \"{}\": {}", s, e))?;
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        'y' => 365 * 86400,
+        _ => return Err(format!("unknown duration unit '{}' in \"{}\"", unit, s)),
+    };
+    number.checked_mul(multiplier).ok_or(format!("duration \"{}\" is too long", s))
+}
+
+/// Storage fee in nanotokens for keeping `bits` and `cells` during `seconds`.
+/// Prices are given in 2^-16 nanotokens per bit (cell) per second.
+pub fn storage_fee(bits: u128, cells: u128, bit_price_ps: u128, cell_price_ps: u128, seconds: u128) -> u128 {
+    let fee = (bits * bit_price_ps + cells * cell_price_ps) * seconds;
+    (fee + 0xffff) >> 16
+}
+
+/// Storage prices actual at the time `at` from config param 18 as (bit, cell) prices.
+fn storage_prices(p18: &serde_json::Value, at: u32, masterchain: bool) -> Result<(u128, u128), String> {
+    let prices = p18.as_array()
+        .and_then(|list| list.iter()
+            .filter(|p| p["utime_since"].as_u64().unwrap_or(0) <= at as u64)
+            .max_by_key(|p| p["utime_since"].as_u64().unwrap_or(0)))
+        .ok_or("storage prices are not found in config param 18")?;
+    let (bit, cell) = if masterchain {
+        ("mc_bit_price_ps", "mc_cell_price_ps")
+    } else {
+        ("bit_price_ps", "cell_price_ps")
+    };
+    Ok((parse_uint(&prices[bit]).unwrap_or(0), parse_uint(&prices[cell]).unwrap_or(0)))
+}
+
+/// Projects storage fees of the account for `horizon` seconds and warns if
+/// the balance is exhausted (and the account gets frozen) within it.
+pub async fn account_fees(conf: Config, addr: &str, horizon: u64) -> Result<(), String> {
+    let ton = create_client_verbose(&conf)?;
+    let accounts = BulkQuery::new(ton.clone(), &conf).query(
+        "accounts",
+        json!({ "id": { "eq": addr } }),
+        "acc_type_name workchain_id balance(format: DEC) due_payment(format: DEC) bits(format: DEC) cells(format: DEC) last_paid",
+        None,
+        Some(1),
+    ).await?;
+    let acc = accounts.first().ok_or("account not found")?;
+    if acc["acc_type_name"].as_str() == Some("NonExist") {
+        return Err("account does not exist".to_owned());
+    }
+    let masterchain = acc["workchain_id"].as_i64() == Some(-1);
+    let p18 = query_config_param(ton.clone(), &conf, "p18").await?;
+    let (bit_price, cell_price) = storage_prices(&p18, now(), masterchain)?;
+    let gas_prices = query_config_param(ton, &conf, if masterchain { "p20" } else { "p21" }).await?;
+    let freeze_due_limit = parse_uint(&gas_prices["freeze_due_limit"]).unwrap_or(0);
+
+    let balance = parse_uint(&acc["balance"]).unwrap_or(0);
+    let due = parse_uint(&acc["due_payment"]).unwrap_or(0);
+    let bits = parse_uint(&acc["bits"]).unwrap_or(0);
+    let cells = parse_uint(&acc["cells"]).unwrap_or(0);
+    let per_day = storage_fee(bits, cells, bit_price, cell_price, 86400);
+    let projected = storage_fee(bits, cells, bit_price, cell_price, horizon as u128);
+    // account is frozen when unpaid storage fee exceeds freeze_due_limit
+    let funds = (balance + freeze_due_limit).saturating_sub(due);
+    let per_second = bits * bit_price + cells * cell_price;
+    let freeze_in = if per_second == 0 { None } else { Some(((funds << 16) / per_second) as u64) };
+    let freezes = freeze_in.map(|t| t <= horizon).unwrap_or(false);
+
+    let result = json!({
+        "address": addr,
+        "balance": balance.to_string(),
+        "due_payment": due.to_string(),
+        "bits": bits.to_string(),
+        "cells": cells.to_string(),
+        "fee_per_day": per_day.to_string(),
+        "horizon": horizon,
+        "projected_fee": projected.to_string(),
+        "freeze_in": freeze_in,
+        "freezes_within_horizon": freezes,
+    });
+    output::print_value(&result, || {
+        if conf.is_json {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default());
+            return;
+        }
+        println!("Storage:         {} bits, {} cells", bits, cells);
+        println!("Balance:         {}", nano_to_tokens(balance as u64));
+        if due > 0 {
+            println!("Due payment:     {}", nano_to_tokens(due as u64));
+        }
+        println!("Fee per day:     {}", nano_to_tokens(per_day as u64));
+        println!("Projected fee:   {} (for {} seconds)", nano_to_tokens(projected.min(u64::MAX as u128) as u64), horizon);
+        match freeze_in {
+            Some(t) => println!("Balance lasts:   {} days", t / 86400),
+            None => println!("Balance lasts:   forever"),
+        }
+        if freezes {
+            println!(
+                "WARNING: balance will be exhausted and the account frozen within the horizon. Top up at least {} to keep it active.",
+                nano_to_tokens((projected + due).saturating_sub(balance + freeze_due_limit).min(u64::MAX as u128) as u64),
+            );
+        }
+    })
+}
+
+pub async fn dump_account(conf: Config, addr: &str, output: &str) -> Result<(), String> {
+    let ton = create_client_verbose(&conf)?;
+
+    println!("{}", tr("Processing..."));
+    let boc = query_account_boc(ton, addr).await?;
+    let boc = base64::decode(&boc)
+        .map_err(|e| format!("failed to decode account boc: {}", e))?;
+    std::fs::write(output, boc)
+        .map_err(|e| format!("failed to write account state: {}", e))?;
+    println!("{}", tr("Succeeded."));
+    println!("{}", trf("Account state saved to file {}", &[&output]));
+    Ok(())
+}
+
+pub async fn load_account(path: &str) -> Result<(), String> {
+    let boc = std::fs::read(path)
+        .map_err(|e| format!("failed to read account state: {}", e))?;
+    let ton = create_client_local()?;
+    let acc = parse_account(
+        ton,
+        ParamsOfParse { boc: base64::encode(&boc) },
+    ).await
+    .map_err(|e| format!("failed to parse account state: {}", e))?
+    .parsed;
+
+    println!("address:       {}", acc["id"].as_str().unwrap_or("unknown"));
+    println!("acc_type:      {}", acc["acc_type_name"].as_str().unwrap_or("unknown"));
+    println!("balance:       {}", acc["balance"].as_str().unwrap_or("unknown"));
+    println!("last_paid:     {}", acc["last_paid"].as_u64().unwrap_or(0));
+    println!("last_trans_lt: {}", acc["last_trans_lt"].as_str().unwrap_or("unknown"));
+    println!("code_hash:     {}", acc["code_hash"].as_str().unwrap_or("null"));
+    println!("data_hash:     {}", acc["data_hash"].as_str().unwrap_or("null"));
+    Ok(())
+}
+
+/// Sends all remaining balance of the account to `dest` and deletes the account.
+/// Uses `sendTransaction` with flags 128 + 32 if contract ABI has it, otherwise
+/// `selfdestruct(dest)` function.
+pub async fn sweep_accounts(
+    conf: Config,
+    accounts: Vec<(String, String)>,
+    dest: &str,
+    abi: String,
+) -> Result<(), String> {
+    let contract: serde_json::Value = serde_json::from_str(&abi)
+        .map_err(|e| format!("ABI is not a valid json: {}", e))?;
+    let has_function = |name: &str| contract["functions"].as_array()
+        .map(|f| f.iter().any(|f| f["name"].as_str() == Some(name)))
+        .unwrap_or(false);
+    let (method, params) = if has_function("sendTransaction") {
+        ("sendTransaction", json!({
+            "dest": dest,
+            "value": 0,
+            "bounce": false,
+            "flags": 160,
+            "payload": "",
+        }))
+    } else if has_function("selfdestruct") {
+        ("selfdestruct", json!({ "dest": dest }))
+    } else {
+        return Err("contract ABI has neither sendTransaction nor selfdestruct function".to_owned());
+    };
+
+    let mut failed = 0;
+    for (addr, keys) in &accounts {
+        println!("{}", trf("Sweeping {}...", &[&addr]));
+        let result = call_contract_with_result(
+            conf.clone(),
+            addr,
+            abi.clone(),
+            method,
+            &params.to_string(),
+            Some(keys.clone()),
+            false,
+        ).await;
+        match result {
+            Ok(_) => println!("{}", trf("{}: swept to {}", &[&addr, &dest])),
+            Err(e) => {
+                failed += 1;
+                println!("{}", trf("{}: failed: {}", &[&addr, &e]));
+            },
+        }
+    }
+    println!("{}", trf("Swept {} of {} accounts.", &[&(accounts.len() - failed), &accounts.len()]));
+    if failed > 0 {
+        return Err(format!("failed to sweep {} account(s)", failed));
+    }
+    Ok(())
+}
+
+/// Reads `address[,keys]` lines. Lines starting with `#` and header line are skipped.
+pub fn load_sweep_csv(path: &str, default_keys: Option<&str>) -> Result<Vec<(String, String)>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read csv file: {}", e))?;
+    let mut accounts = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (i == 0 && line.starts_with("address")) {
+            continue;
+        }
+        let mut fields = line.split(',').map(|f| f.trim());
+        let addr = fields.next().unwrap_or("").to_owned();
+        let keys = fields.next()
+            .filter(|k| !k.is_empty())
+            .or(default_keys)
+            .ok_or(format!("line {}: keys are not defined for {}", i + 1, addr))?;
+        accounts.push((addr, keys.to_owned()));
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("365d").unwrap(), 365 * 86400);
+        assert_eq!(parse_duration("12h").unwrap(), 12 * 3600);
+        assert_eq!(parse_duration("90").unwrap(), 90);
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("d").is_err());
+    }
+
+    #[test]
+    fn test_unfreeze_debt() {
+        assert_eq!(unfreeze_debt(0, 1_500_000), 1_500_000);
+        assert_eq!(unfreeze_debt(500_000, 1_500_000), 1_000_000);
+        assert_eq!(unfreeze_debt(2_000_000, 1_500_000), 0);
+    }
+
+    #[test]
+    fn test_storage_fee() {
+        // 1 bit at price 65536 (1 nanotoken per second) for a day
+        assert_eq!(storage_fee(1, 0, 65536, 0, 86400), 86400);
+        // partial nanotokens are rounded up
+        assert_eq!(storage_fee(1, 0, 1, 0, 1), 1);
+        assert_eq!(storage_fee(0, 0, 1, 500, 1000), 0);
+    }
+}
@@ -0,0 +1,272 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Block exploration. `--shard` selects a range of the workchain rather than
+//! an exact shard id: blocks of all shards intersecting it are reported, so
+//! tailing keeps working when the shard splits or merges.
+use crate::config::Config;
+use crate::error::CliError;
+use crate::helpers::{create_client_verbose, query, subscribe};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use ton_client::net::{unsubscribe, OrderBy, SortDirection};
+
+const BLOCK_FIELDS: &str = "id seq_no workchain_id shard gen_utime tr_count after_split after_merge";
+/// Shard id of the whole workchain.
+const ROOT_SHARD: u64 = 0x8000_0000_0000_0000;
+
+pub fn create_block_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("block")
+        .about("Prints the latest blocks of the workchain or shard, or follows new ones. Shard splits and merges are reported and followed.")
+        .setting(AppSettings::AllowNegativeNumbers)
+        .arg(Arg::with_name("WC")
+            .long("--wc")
+            .takes_value(true)
+            .help("Workchain id (default: 0). Ignored if --shard contains workchain."))
+        .arg(Arg::with_name("SHARD")
+            .long("--shard")
+            .takes_value(true)
+            .help("Shard as <workchain>:<hex shard id> or hex shard id (default: the whole workchain), e.g. 0:8000000000000000."))
+        .arg(Arg::with_name("FOLLOW")
+            .long("--follow")
+            .short("-f")
+            .help("Waits for new blocks and prints them as they appear."))
+}
+
+fn lowest_bit(shard: u64) -> u64 {
+    shard & shard.wrapping_neg()
+}
+
+/// Checks that shard `outer` contains shard `inner` (or is the same shard).
+fn shard_contains(outer: u64, inner: u64) -> bool {
+    let bit = lowest_bit(outer);
+    if bit < lowest_bit(inner) {
+        return false;
+    }
+    let prefix_mask = !(bit.wrapping_shl(1).wrapping_sub(1));
+    (outer ^ inner) & prefix_mask == 0
+}
+
+fn shards_intersect(a: u64, b: u64) -> bool {
+    shard_contains(a, b) || shard_contains(b, a)
+}
+
+fn parse_shard(shard: &str, wc: i32) -> Result<(i32, u64), String> {
+    let (wc, hex) = match shard.find(':') {
+        Some(pos) => (
+            i32::from_str_radix(&shard[..pos], 10)
+                .map_err(|e| format!("invalid workchain in shard {}: {}", shard, e))?,
+            &shard[pos + 1..],
+        ),
+        None => (wc, shard),
+    };
+    let id = u64::from_str_radix(hex.trim_start_matches("0x"), 16)
+        .map_err(|e| format!("invalid shard id {}: {}", shard, e))?;
+    if id == 0 {
+        return Err(format!("invalid shard id {}", shard));
+    }
+    Ok((wc, id))
+}
+
+fn block_shard(block: &Value) -> u64 {
+    block["shard"].as_str()
+        .and_then(|s| u64::from_str_radix(s, 16).ok())
+        .unwrap_or(0)
+}
+
+/// Shards of the followed range seen in the latest blocks. Updated on splits
+/// and merges, so that they can be reported.
+struct ShardTracker {
+    range: u64,
+    shards: BTreeSet<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ShardEvent {
+    Split { parent: u64, child: u64 },
+    Merge { children: Vec<u64>, merged: u64 },
+}
+
+impl ShardTracker {
+    fn new(range: u64) -> Self {
+        Self { range, shards: BTreeSet::new() }
+    }
+
+    fn is_followed(&self, shard: u64) -> bool {
+        shards_intersect(self.range, shard)
+    }
+
+    /// Registers shard of a new block, returns split or merge it reveals.
+    fn update(&mut self, shard: u64) -> Option<ShardEvent> {
+        if self.shards.contains(&shard) {
+            return None;
+        }
+        let parent = self.shards.iter().cloned().find(|s| shard_contains(*s, shard));
+        let children: Vec<u64> = self.shards.iter().cloned().filter(|s| shard_contains(shard, *s)).collect();
+        self.shards.insert(shard);
+        if let Some(parent) = parent {
+            self.shards.remove(&parent);
+            return Some(ShardEvent::Split { parent, child: shard });
+        }
+        if !children.is_empty() {
+            for child in &children {
+                self.shards.remove(child);
+            }
+            return Some(ShardEvent::Merge { children, merged: shard });
+        }
+        None
+    }
+}
+
+fn print_block(config: &Config, block: &Value) {
+    if config.is_json {
+        println!("{}", block);
+        return;
+    }
+    println!(
+        "{}:{} seq_no {} gen_utime {} transactions {} id {}",
+        block["workchain_id"].as_i64().unwrap_or(0),
+        block["shard"].as_str().unwrap_or(""),
+        block["seq_no"].as_u64().unwrap_or(0),
+        block["gen_utime"].as_u64().unwrap_or(0),
+        block["tr_count"].as_u64().unwrap_or(0),
+        block["id"].as_str().unwrap_or(""),
+    );
+}
+
+fn print_shard_event(config: &Config, wc: i32, event: &ShardEvent) {
+    if config.is_json {
+        let event = match event {
+            ShardEvent::Split { parent, child } => json!({
+                "event": "split",
+                "parent": format!("{}:{:016x}", wc, parent),
+                "child": format!("{}:{:016x}", wc, child),
+            }),
+            ShardEvent::Merge { children, merged } => json!({
+                "event": "merge",
+                "children": children.iter().map(|s| format!("{}:{:016x}", wc, s)).collect::<Vec<_>>(),
+                "merged": format!("{}:{:016x}", wc, merged),
+            }),
+        };
+        println!("{}", event);
+        return;
+    }
+    match event {
+        ShardEvent::Split { parent, child } =>
+            println!("Shard {}:{:016x} split, following {}:{:016x}", wc, parent, wc, child),
+        ShardEvent::Merge { children, merged } => {
+            let children: Vec<String> = children.iter().map(|s| format!("{}:{:016x}", wc, s)).collect();
+            println!("Shards {} merged into {}:{:016x}", children.join(", "), wc, merged);
+        },
+    }
+}
+
+pub async fn block_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let wc = m.value_of("WC")
+        .map(|s| i32::from_str_radix(s, 10).map_err(|e| format!("invalid workchain id: {}", e)))
+        .transpose()?
+        .unwrap_or(0);
+    let (wc, range) = match m.value_of("SHARD") {
+        Some(shard) => parse_shard(shard, wc)?,
+        None => (wc, ROOT_SHARD),
+    };
+    let ton = create_client_verbose(&config)?;
+    let mut tracker = ShardTracker::new(range);
+    let follow = m.is_present("FOLLOW");
+
+    // Subscription is started before the latest blocks are queried so that no block is lost in between.
+    let subscription = if follow {
+        Some(subscribe(ton.clone(), "blocks", json!({ "workchain_id": { "eq": wc } }), BLOCK_FIELDS).await
+            .map_err(|e| format!("failed to subscribe to blocks: {}", e))?)
+    } else {
+        None
+    };
+
+    let latest = query(
+        ton.clone(),
+        "blocks",
+        json!({ "workchain_id": { "eq": wc } }),
+        BLOCK_FIELDS,
+        Some(vec![OrderBy { path: "gen_utime".to_owned(), direction: SortDirection::DESC }]),
+    ).await
+    .map_err(|e| format!("failed to query blocks: {}", e))?;
+    for block in latest.iter().rev() {
+        let shard = block_shard(block);
+        if !tracker.is_followed(shard) {
+            continue;
+        }
+        tracker.update(shard);
+        if !follow {
+            print_block(&config, block);
+        }
+    }
+    let (subscription, mut receiver) = match subscription {
+        Some(subscription) => subscription,
+        None => return Ok(()),
+    };
+
+    if !config.is_json {
+        println!("Following blocks of {}:{:016x}...", wc, range);
+    }
+    while let Some(block) = receiver.recv().await {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => {
+                eprintln!("Subscription error: {}", e);
+                continue;
+            },
+        };
+        let shard = block_shard(&block);
+        if !tracker.is_followed(shard) {
+            continue;
+        }
+        if let Some(event) = tracker.update(shard) {
+            print_shard_event(&config, wc, &event);
+        }
+        print_block(&config, &block);
+    }
+    let _ = unsubscribe(ton, subscription).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_tracker() {
+        assert!(shard_contains(ROOT_SHARD, 0x4000_0000_0000_0000));
+        assert!(shard_contains(0x4000_0000_0000_0000, 0x6000_0000_0000_0000));
+        assert!(!shard_contains(0x4000_0000_0000_0000, 0xc000_0000_0000_0000));
+        assert!(!shard_contains(0x6000_0000_0000_0000, 0x4000_0000_0000_0000));
+        assert_eq!(parse_shard("-1:8000000000000000", 0).unwrap(), (-1, ROOT_SHARD));
+        assert!(parse_shard("0:xyz", 0).is_err());
+
+        let mut tracker = ShardTracker::new(0x4000_0000_0000_0000);
+        assert!(tracker.is_followed(ROOT_SHARD));
+        assert!(!tracker.is_followed(0xc000_0000_0000_0000));
+        assert_eq!(tracker.update(0x4000_0000_0000_0000), None);
+        assert_eq!(
+            tracker.update(0x2000_0000_0000_0000),
+            Some(ShardEvent::Split { parent: 0x4000_0000_0000_0000, child: 0x2000_0000_0000_0000 }),
+        );
+        assert_eq!(tracker.update(0x6000_0000_0000_0000), None);
+        assert_eq!(
+            tracker.update(0x4000_0000_0000_0000),
+            Some(ShardEvent::Merge {
+                children: vec![0x2000_0000_0000_0000, 0x6000_0000_0000_0000],
+                merged: 0x4000_0000_0000_0000,
+            }),
+        );
+    }
+}
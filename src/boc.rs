@@ -0,0 +1,241 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::i18n::tr;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::path::Path;
+use ton_types::cells_serialization::{deserialize_cells_tree, BagOfCells};
+use ton_types::Cell;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const ZSTD_LEVEL: i32 = 19;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Base64,
+    Hex,
+    /// Raw bytes, can be written only to file.
+    Binary,
+}
+
+impl Encoding {
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "base64" => Ok(Encoding::Base64),
+            "hex" => Ok(Encoding::Hex),
+            "bin" | "file" => Ok(Encoding::Binary),
+            _ => Err(format!("unknown encoding: {}", s)),
+        }
+    }
+}
+
+fn output_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(Arg::with_name("TO")
+            .long("--to")
+            .takes_value(true)
+            .possible_values(&["base64", "hex", "bin"])
+            .help("Output encoding (default: bin if output file is set, base64 otherwise)."))
+        .arg(Arg::with_name("ZSTD")
+            .long("--zstd")
+            .help("Compresses output boc with zstd."))
+}
+
+pub fn create_boc_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("boc")
+        .about("BOC utilities. Input is a file (binary, base64 or hex) or a base64/hex string, zstd-compressed input is detected automatically.")
+        .subcommand(output_args(SubCommand::with_name("convert")
+            .about("Converts boc between encodings: base64, hex, binary file, zstd-compressed.")
+            .arg(Arg::with_name("INPUT")
+                .required(true)
+                .takes_value(true)
+                .help("Boc file or string."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--output")
+                .short("-o")
+                .takes_value(true)
+                .help("Output file. Boc is printed if omitted."))))
+        .subcommand(output_args(SubCommand::with_name("split")
+            .about("Splits multi-root boc into single-root bocs <dir>/<index>.boc.")
+            .arg(Arg::with_name("INPUT")
+                .required(true)
+                .takes_value(true)
+                .help("Boc file or string."))
+            .arg(Arg::with_name("OUT_DIR")
+                .long("--out-dir")
+                .required(true)
+                .takes_value(true)
+                .help("Output directory."))))
+        .subcommand(output_args(SubCommand::with_name("merge")
+            .about("Merges roots of several bocs into one multi-root boc.")
+            .arg(Arg::with_name("INPUTS")
+                .required(true)
+                .takes_value(true)
+                .multiple(true)
+                .help("Boc files or strings."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--output")
+                .short("-o")
+                .takes_value(true)
+                .help("Output file. Boc is printed if omitted."))))
+}
+
+pub async fn boc_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("convert") {
+        return convert_command(m, config).map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("split") {
+        return split_command(m, config).map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("merge") {
+        return merge_command(m, config).map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown boc command"))
+}
+
+fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !bytes.starts_with(&ZSTD_MAGIC) {
+        return Ok(bytes);
+    }
+    zstd::stream::decode_all(&bytes[..])
+        .map_err(|e| format!("failed to decompress zstd boc: {}", e))
+}
+
+/// Decodes boc bytes from binary, base64 or hex form, decompressing zstd if needed.
+pub fn decode_boc(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let data = decompress(data)?;
+    if deserialize_cells_tree(&mut std::io::Cursor::new(&data)).is_ok() {
+        return Ok(data);
+    }
+    let text = String::from_utf8_lossy(&data);
+    let text = text.trim();
+    let decoded = hex::decode(text).ok()
+        .or_else(|| base64::decode(text).ok())
+        .ok_or("input is neither boc nor base64/hex encoded boc".to_owned())?;
+    decompress(decoded)
+}
+
+fn read_input(input: &str) -> Result<Vec<u8>, String> {
+    let data = if Path::new(input).exists() {
+        std::fs::read(input).map_err(|e| format!("failed to read {}: {}", input, e))?
+    } else {
+        input.as_bytes().to_vec()
+    };
+    decode_boc(data)
+}
+
+fn read_roots(input: &str) -> Result<Vec<Cell>, String> {
+    let data = read_input(input)?;
+    deserialize_cells_tree(&mut std::io::Cursor::new(&data))
+        .map_err(|e| format!("failed to deserialize boc {}: {}", input, e))
+}
+
+fn serialize_roots(roots: &[Cell]) -> Result<Vec<u8>, String> {
+    let mut data = vec![];
+    BagOfCells::with_roots(roots.iter().collect())
+        .write_to(&mut data, false)
+        .map_err(|e| format!("failed to serialize boc: {}", e))?;
+    Ok(data)
+}
+
+/// Encodes boc for output, compressing it first if requested.
+pub fn encode_boc(data: &[u8], encoding: Encoding, zstd: bool) -> Result<Vec<u8>, String> {
+    let data = if zstd {
+        zstd::stream::encode_all(data, ZSTD_LEVEL)
+            .map_err(|e| format!("failed to compress boc: {}", e))?
+    } else {
+        data.to_vec()
+    };
+    Ok(match encoding {
+        Encoding::Base64 => base64::encode(&data).into_bytes(),
+        Encoding::Hex => hex::encode(&data).into_bytes(),
+        Encoding::Binary => data,
+    })
+}
+
+fn output_encoding(m: &ArgMatches, to_file: bool) -> Result<Encoding, String> {
+    match m.value_of("TO") {
+        Some(to) => Encoding::from_str(to),
+        None if to_file => Ok(Encoding::Binary),
+        None => Ok(Encoding::Base64),
+    }
+}
+
+fn write_output(m: &ArgMatches, data: &[u8], output: Option<&str>) -> Result<(), String> {
+    let encoding = output_encoding(m, output.is_some())?;
+    let encoded = encode_boc(data, encoding, m.is_present("ZSTD"))?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, &encoded)
+                .map_err(|e| format!("failed to write {}: {}", path, e))?;
+            println!("{}", tr("Succeeded."));
+        },
+        None => {
+            if encoding == Encoding::Binary {
+                return Err("binary boc can be written only to file, use --output".to_owned());
+            }
+            println!("{}", String::from_utf8_lossy(&encoded));
+        },
+    }
+    Ok(())
+}
+
+fn convert_command(m: &ArgMatches, _config: Config) -> Result<(), String> {
+    let data = read_input(m.value_of("INPUT").unwrap())?;
+    write_output(m, &data, m.value_of("OUTPUT"))
+}
+
+fn split_command(m: &ArgMatches, _config: Config) -> Result<(), String> {
+    let roots = read_roots(m.value_of("INPUT").unwrap())?;
+    let dir = Path::new(m.value_of("OUT_DIR").unwrap());
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("failed to create directory {}: {}", dir.display(), e))?;
+    let encoding = output_encoding(m, true)?;
+    for (index, root) in roots.iter().enumerate() {
+        let data = serialize_roots(&[root.clone()])?;
+        let path = dir.join(format!("{}.boc", index));
+        std::fs::write(&path, encode_boc(&data, encoding, m.is_present("ZSTD"))?)
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        println!("{} {}", path.display(), root.repr_hash().to_hex_string());
+    }
+    println!("Roots: {}", roots.len());
+    Ok(())
+}
+
+fn merge_command(m: &ArgMatches, _config: Config) -> Result<(), String> {
+    let mut roots = vec![];
+    for input in m.values_of("INPUTS").unwrap() {
+        roots.extend(read_roots(input)?);
+    }
+    let data = serialize_roots(&roots)?;
+    write_output(m, &data, m.value_of("OUTPUT"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boc_encodings() {
+        let root = Cell::default();
+        let boc = serialize_roots(&[root.clone(), root]).unwrap();
+        for encoding in &[Encoding::Base64, Encoding::Hex, Encoding::Binary] {
+            for zstd in &[false, true] {
+                let encoded = encode_boc(&boc, *encoding, *zstd).unwrap();
+                assert_eq!(decode_boc(encoded).unwrap(), boc);
+            }
+        }
+        let roots = deserialize_cells_tree(&mut std::io::Cursor::new(&boc)).unwrap();
+        assert_eq!(roots.len(), 2);
+    }
+}
@@ -0,0 +1,200 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::helpers::{now, query, TonClient};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::path::PathBuf;
+use ton_client::error::ClientError;
+
+const CACHE_DIR_NAME: &str = ".tonos-cli-cache";
+
+/// Local disk cache for results of queries which never change
+/// (blocks, finalized transactions, code by hash, etc.).
+pub struct Cache {
+    dir: PathBuf,
+    ttl: u64,
+    max_size: u64,
+}
+
+fn default_cache_dir() -> PathBuf {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir())
+        .join(CACHE_DIR_NAME)
+}
+
+impl Cache {
+    /// Returns None if cache is disabled (ttl is 0) or cache directory can't be created.
+    pub fn new(conf: &Config) -> Option<Self> {
+        if conf.cache_ttl == 0 {
+            return None;
+        }
+        let dir = conf.cache_dir.as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(default_cache_dir);
+        std::fs::create_dir_all(&dir).ok()?;
+        Some(Self {
+            dir,
+            ttl: conf.cache_ttl as u64,
+            max_size: conf.cache_size.saturating_mul(1024 * 1024),
+        })
+    }
+
+    pub fn key(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.input(part.as_bytes());
+            hasher.input(&[0u8]);
+        }
+        hex::encode(hasher.result())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub fn get(&self, key: &str) -> Option<serde_json::Value> {
+        let data = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: serde_json::Value = serde_json::from_str(&data).ok()?;
+        let created = entry["created"].as_u64()?;
+        if created.saturating_add(self.ttl) < now() as u64 {
+            let _ = std::fs::remove_file(self.entry_path(key));
+            return None;
+        }
+        Some(entry["value"].clone())
+    }
+
+    pub fn put(&self, key: &str, value: &serde_json::Value) {
+        let entry = json!({ "created": now(), "value": value });
+        if std::fs::write(self.entry_path(key), entry.to_string()).is_ok() {
+            self.evict();
+        }
+    }
+
+    /// Removes the oldest entries while total cache size exceeds the limit.
+    fn evict(&self) {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let mut files: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                Some((meta.modified().ok()?, meta.len(), e.path()))
+            })
+            .collect();
+        let mut total: u64 = files.iter().map(|f| f.1).sum();
+        files.sort();
+        for (_, len, path) in files {
+            if total <= self.max_size {
+                break;
+            }
+            if std::fs::remove_file(path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+}
+
+/// Queries collection records which never change after they are created,
+/// returning cached result if it exists.
+pub async fn query_immutable(
+    ton: TonClient,
+    conf: &Config,
+    collection: &str,
+    filter: serde_json::Value,
+    result: &str,
+) -> Result<Vec<serde_json::Value>, ClientError> {
+    let query_filter = filter.clone();
+    cached_records(conf, collection, &filter, result, || query(ton, collection, query_filter, result, None)).await
+}
+
+/// Returns cached records of the immutable query or fetches them with `fetch`.
+pub async fn cached_records<F, Fut, E>(
+    conf: &Config,
+    collection: &str,
+    filter: &serde_json::Value,
+    result: &str,
+    fetch: F,
+) -> Result<Vec<serde_json::Value>, E>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Vec<serde_json::Value>, E>>,
+{
+    let cache = Cache::new(conf);
+    let key = Cache::key(&[&conf.url, collection, &filter.to_string(), result]);
+    if let Some(value) = cache.as_ref().and_then(|c| c.get(&key)) {
+        if let Some(records) = value.as_array() {
+            return Ok(records.clone());
+        }
+    }
+    let records = fetch().await?;
+    // empty result may mean that data is not available yet
+    if !records.is_empty() {
+        if let Some(cache) = cache {
+            cache.put(&key, &json!(records));
+        }
+    }
+    Ok(records)
+}
+
+/// Reads ABI from the file or downloads it if `path` is http(s) URL.
+/// Downloaded ABIs are cached.
+pub async fn read_abi(conf: &Config, path: &str) -> Result<String, String> {
+    if !path.starts_with("http://") && !path.starts_with("https://") {
+        return std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read ABI file: {}", e));
+    }
+    let cache = Cache::new(conf);
+    let key = Cache::key(&["abi", path]);
+    if let Some(abi) = cache.as_ref().and_then(|c| c.get(&key)).and_then(|v| v.as_str().map(|s| s.to_owned())) {
+        return Ok(abi);
+    }
+    if conf.offline {
+        return Err(format!("ABI {} is not cached and can't be downloaded in offline mode", path));
+    }
+    let abi = reqwest::get(path)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("failed to download ABI: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("failed to download ABI: {}", e))?;
+    serde_json::from_str::<serde_json::Value>(&abi)
+        .map_err(|e| format!("downloaded ABI is not a valid json: {}", e))?;
+    if let Some(cache) = cache {
+        cache.put(&key, &json!(abi));
+    }
+    Ok(abi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_limits() {
+        let dir = std::env::temp_dir().join(format!("tonos-cli-cache-{}", std::process::id()));
+        let mut conf = Config::new();
+        conf.cache_dir = Some(dir.to_string_lossy().into_owned());
+        conf.cache_ttl = u32::MAX;
+        conf.cache_size = u64::MAX;
+        let cache = Cache::new(&conf).unwrap();
+        assert_eq!(cache.max_size, u64::MAX);
+        cache.put("key", &json!([1]));
+        assert_eq!(cache.get("key"), Some(json!([1])));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
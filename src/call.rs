@@ -10,15 +10,26 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
+use crate::abi::validate_params;
 use crate::config::Config;
 use crate::crypto::load_keypair;
 use crate::convert;
-use crate::helpers::{TonClient, now, create_client_verbose, create_client_local, query, load_ton_address, load_abi};
+use crate::error::{CliError, ErrorKind, ResultExt};
+use crate::history;
+use crate::output;
+use crate::pretty;
+use crate::relay;
+use crate::helpers::{TonClient, now, create_client_verbose, create_client_local, query, load_ton_address, load_abi, print_fees, skew_header, apply_header_options};
+use crate::i18n::{tr, trf};
+use futures::StreamExt;
+use std::collections::BTreeMap;
 use ton_abi::{Contract, ParamType};
 use chrono::{TimeZone, Local};
 use hex;
 use ton_client::abi::{
     encode_message,
+    encode_message_body,
+    ParamsOfEncodeMessageBody,
     decode_message,
     ParamsOfDecodeMessage,
     ParamsOfEncodeMessage,
@@ -28,12 +39,13 @@ use ton_client::abi::{
     Signer
 };
 use ton_client::processing::{
-    ParamsOfSendMessage,
     ParamsOfWaitForTransaction,
     wait_for_transaction,
-    send_message,
 };
-use ton_client::tvm::{run_tvm, run_get, ParamsOfRunTvm, ParamsOfRunGet};
+use ton_client::tvm::{run_tvm, run_get, ParamsOfRunTvm, ParamsOfRunGet, TransactionFees};
+
+const SEQNO_PARAM: &str = "seqno";
+const SEQNO_RETRIES: u8 = 3;
 
 struct EncodedMessage {
     message_id: String,
@@ -50,12 +62,17 @@ async fn prepare_message(
     params: &str,
     header: Option<FunctionHeader>,
     keys: Option<String>,
-) -> Result<EncodedMessage, String> {
-    println!("Generating external inbound message...");
+) -> Result<EncodedMessage, CliError> {
+    println!("{}", tr("Generating external inbound message..."));
 
-    let keys = keys.map(|k| load_keypair(&k)).transpose()?;
+    let keys = keys.map(|k| load_keypair(&k)).transpose().kind(ErrorKind::InvalidInput)?;
     let params = serde_json::from_str(&params)
-        .map_err(|e| format!("arguments are not in json format: {}", e))?;
+        .map_err(invalid_arguments)?;
+    if let Abi::Contract(contract) = &abi {
+        let contract = serde_json::to_value(contract)
+            .map_err(|e| format!("failed to serialize ABI: {}", e))?;
+        validate_params(&contract, method, &params).kind(ErrorKind::InvalidInput)?;
+    }
 
 
     let call_set = Some(CallSet {
@@ -78,7 +95,7 @@ async fn prepare_message(
             ..Default::default()
         },
     ).await
-    .map_err(|e| format!("failed to create inbound message: {}", e))?;
+    .map_err(|e| CliError::from(e).context("failed to create inbound message"))?;
 
     Ok(EncodedMessage {
         message: msg.message,
@@ -88,15 +105,23 @@ async fn prepare_message(
     })
 }
 
+fn invalid_arguments(e: serde_json::Error) -> CliError {
+    CliError::new(ErrorKind::InvalidInput, format!("arguments are not in json format: {}", e))
+}
+
+fn run_failed(e: &ton_client::error::ClientError) -> CliError {
+    CliError::new(ErrorKind::from_client_error(e), format!("run failed: {:#}", e))
+}
+
 fn print_encoded_message(msg: &EncodedMessage) {
     println!();
-    println!("MessageId: {}", msg.message_id);
-    print!("Expire at: ");
+    println!("{}", trf("MessageId: {}", &[&msg.message_id]));
+    print!("{}", tr("Expire at: "));
     if msg.expire.is_some() {
         let expire_at = Local.timestamp(msg.expire.unwrap() as i64 , 0);
         println!("{}", expire_at.to_rfc2822());
     } else {
-        println!("unknown");
+        println!("{}", tr("unknown"));
     }
 }
 
@@ -171,7 +196,7 @@ fn parse_integer_param(value: &str) -> Result<String, String> {
     let value = value.trim_matches('\"');
 
     if value.ends_with('T') {
-        convert::convert_token(value.trim_end_matches('T'))
+        Ok(convert::convert_token(value.trim_end_matches('T'), false)?)
     } else {
         Ok(value.to_owned())
     }
@@ -221,7 +246,7 @@ fn build_json_from_params(params_vec: Vec<&str>, abi: &str, method: &str) -> Res
     serde_json::to_string(&params_json).map_err(|e| format!("{}", e))
 }
 
-async fn query_account_boc(ton: TonClient, addr: &str) -> Result<String, String> {
+pub async fn query_account_boc(ton: TonClient, addr: &str) -> Result<String, CliError> {
     let accounts = query(
         ton,
         "accounts",
@@ -229,72 +254,82 @@ async fn query_account_boc(ton: TonClient, addr: &str) -> Result<String, String>
         "boc",
         None,
     ).await
-    .map_err(|e| format!("failed to query account: {}", e))?;
+    .map_err(|e| CliError::new(ErrorKind::Network, format!("failed to query account: {}", e)))?;
 
     if accounts.len() == 0 {
-        return Err(format!("account not found"));
+        return Err(CliError::new(ErrorKind::Contract, "account not found"));
     }
     let boc = accounts[0]["boc"].as_str();
     if boc.is_none() {
-        return Err(format!("account doesn't contain data"));
+        return Err(CliError::new(ErrorKind::Contract, "account doesn't contain data"));
     }
     Ok(boc.unwrap().to_owned())
 }
 
-async fn send_message_and_wait(
+pub async fn send_message_and_wait(
     ton: TonClient,
     addr: &str,
     abi: Abi,
     msg: String,
     local: bool,
-) -> Result<serde_json::Value, String> {
-    if local {
-        println!("Running get-method...");
-        let acc_boc = query_account_boc(ton.clone(), addr).await?;
+) -> Result<serde_json::Value, CliError> {
+    let (output, fees) = send_message_and_wait_with_fees(ton, addr, abi, msg, local).await?;
+    // json output is a single result object, so fees are printed in text mode only
+    if let Some(fees) = fees.filter(|_| !crate::error::is_json_output()) {
+        print_fees(&fees, false);
+    }
+    Ok(output)
+}
 
-        let result = run_tvm(
-            ton.clone(),
-            ParamsOfRunTvm {
-                message: msg,
-                account: acc_boc,
-                abi: Some(abi.clone()),
-                return_updated_account: Some(true),
-                ..Default::default()
-            },
-        ).await
-        .map_err(|e| format!("run failed: {:#}", e))?;
-        Ok(result.decoded.and_then(|d| d.output).unwrap_or(json!({})))
+/// Sends message (or runs it locally) and returns decoded output and fees of the transaction.
+async fn send_message_and_wait_with_fees(
+    ton: TonClient,
+    addr: &str,
+    abi: Abi,
+    msg: String,
+    local: bool,
+) -> Result<(serde_json::Value, Option<TransactionFees>), CliError> {
+    let result = process_message_with_fees(ton.clone(), addr, abi.clone(), msg.clone(), local).await;
+    if !local {
+        let status = result.as_ref().map(|_| ()).map_err(|e| e.to_string());
+        history::record_message(ton, Some(abi), &msg, &status).await;
+    }
+    result
+}
+
+async fn process_message_with_fees(
+    ton: TonClient,
+    addr: &str,
+    abi: Abi,
+    msg: String,
+    local: bool,
+) -> Result<(serde_json::Value, Option<TransactionFees>), CliError> {
+    if local {
+        println!("{}", tr("Running get-method..."));
+        Ok((run_message_locally(ton, addr, abi, msg).await?, None))
     } else {
-        println!("Processing... ");
+        println!("{}", tr("Processing..."));
         let callback = |_| {
             async move {}
         };
 
-        let result = send_message(
-            ton.clone(),
-            ParamsOfSendMessage {
-                message: msg.clone(),
-                abi: Some(abi.clone()),
-                send_events: false,
-                ..Default::default()
-            },
-            callback,
-        ).await
-        .map_err(|e| format!("Failed: {:#}", e))?;
+        let shard_block_id = relay::broadcast(ton.clone(), &msg, Some(abi.clone())).await
+            .map_err(|e| CliError::new(ErrorKind::Network, format!("Failed: {}", e)))?;
 
         let result = wait_for_transaction(
             ton.clone(),
             ParamsOfWaitForTransaction {
                 abi: Some(abi.clone()),
                 message: msg.clone(),
-                shard_block_id: result.shard_block_id,
+                shard_block_id,
                 send_events: true,
                 ..Default::default()
             },
             callback.clone(),
         ).await
-        .map_err(|e| format!("Failed: {:#}", e))?;
-        Ok(result.decoded.and_then(|d| d.output).unwrap_or(json!({})))
+        .map_err(|e| CliError::new(ErrorKind::from_client_error(&e), format!("Failed: {:#}", e)))?;
+        let fees = result.fees;
+        Ok((result.decoded.and_then(|d| d.output).unwrap_or(json!({})), Some(fees)))
     }
 }
 
@@ -306,23 +341,321 @@ pub async fn call_contract_with_result(
     params: &str,
     keys: Option<String>,
     local: bool,
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, CliError> {
     let ton = create_client_verbose(&conf)?;
+    let seqno = !local && uses_seqno(&abi, method, params);
     let abi = load_abi(&abi)?;
 
+    if seqno {
+        return call_with_seqno(ton, &conf, addr, abi, method, params, keys).await;
+    }
+
+    let (abi, header) = if local {
+        (abi, None)
+    } else {
+        apply_header_options(&conf, abi, skew_header(ton.clone(), &conf).await).kind(ErrorKind::InvalidInput)?
+    };
     let msg = prepare_message(
         ton.clone(),
         addr,
         abi.clone(),
         method,
         params,
-        None,
+        header,
         keys,
     ).await?;
 
     print_encoded_message(&msg);
 
-    send_message_and_wait(ton.clone(), addr, abi, msg.message, local).await
+    let (output, fees) = send_message_and_wait_with_fees(ton.clone(), addr, abi, msg.message, local).await?;
+    if let Some(fees) = fees.filter(|_| !conf.is_json) {
+        print_fees(&fees, false);
+    }
+    Ok(output)
+}
+
+/// Checks that contract has `seqno` getter and that the called method takes
+/// `seqno` argument which is not supplied by user.
+fn uses_seqno(abi: &str, method: &str, params: &str) -> bool {
+    let contract = match Contract::load(abi.as_bytes()) {
+        Ok(contract) => contract,
+        Err(_) => return false,
+    };
+    let functions = contract.functions();
+    let has_getter = functions.get(SEQNO_PARAM)
+        .map(|f| f.input_params().is_empty())
+        .unwrap_or(false);
+    let takes_seqno = functions.get(method)
+        .map(|f| f.input_params().iter().any(|p| p.name == SEQNO_PARAM))
+        .unwrap_or(false);
+    let supplied = serde_json::from_str::<serde_json::Value>(params)
+        .map(|v| v.get(SEQNO_PARAM).is_some())
+        .unwrap_or(true);
+    has_getter && takes_seqno && !supplied
+}
+
+fn parse_seqno(value: &serde_json::Value) -> Result<u64, String> {
+    let value = match value {
+        serde_json::Value::Object(map) => map.values().next().cloned().unwrap_or(json!(null)),
+        other => other.clone(),
+    };
+    let result = match &value {
+        serde_json::Value::Number(n) => n.as_u64().ok_or(()),
+        serde_json::Value::String(s) if s.starts_with("0x") => {
+            u64::from_str_radix(&s[2..], 16).map_err(|_| ())
+        },
+        serde_json::Value::String(s) => u64::from_str_radix(s, 10).map_err(|_| ()),
+        _ => Err(()),
+    };
+    result.map_err(|_| format!("failed to parse seqno: {}", value))
+}
+
+async fn query_seqno(ton: TonClient, addr: &str, abi: Abi) -> Result<u64, CliError> {
+    let msg = prepare_message(ton.clone(), addr, abi.clone(), SEQNO_PARAM, "{}", None, None).await?;
+    let result = send_message_and_wait(ton, addr, abi, msg.message, true).await?;
+    parse_seqno(&result).kind(ErrorKind::Contract)
+}
+
+/// Runs the message on the current account state to check that contract accepts it.
+async fn check_message_accepted(ton: TonClient, addr: &str, abi: Abi, message: &str) -> Result<(), CliError> {
+    let account = query_account_boc(ton.clone(), addr).await?;
+    run_tvm(
+        ton,
+        ParamsOfRunTvm {
+            message: message.to_owned(),
+            account,
+            abi: Some(abi),
+            ..Default::default()
+        },
+    ).await
+    .map(|_| ())
+    .map_err(|e| CliError::new(ErrorKind::from_client_error(&e), format!("message is not accepted: {:#}", e)))
+}
+
+/// Checks that the sent message is expired and has no transaction, so it can't be executed anymore.
+async fn is_message_rejected(ton: TonClient, msg: &EncodedMessage) -> bool {
+    match msg.expire {
+        Some(expire) if expire < now() => {},
+        _ => return false,
+    }
+    query(
+        ton,
+        "transactions",
+        json!({ "in_msg": { "eq": msg.message_id } }),
+        "id",
+        None,
+    ).await
+    .map(|transactions| transactions.is_empty())
+    .unwrap_or(false)
+}
+
+/// Reads current seqno from the contract, embeds it into the call and
+/// retries with a fresh seqno only if the message was surely not executed:
+/// contract doesn't accept it before sending or it expired without transaction.
+async fn call_with_seqno(
+    ton: TonClient,
+    conf: &Config,
+    addr: &str,
+    abi: Abi,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+) -> Result<serde_json::Value, CliError> {
+    let mut params: serde_json::Value = serde_json::from_str(params)
+        .map_err(invalid_arguments)?;
+    let mut attempt = 0;
+    loop {
+        let seqno = query_seqno(ton.clone(), addr, abi.clone()).await?;
+        println!("{}", trf("Using seqno {}", &[&seqno]));
+        params[SEQNO_PARAM] = json!(seqno);
+
+        let (msg_abi, header) = apply_header_options(conf, abi.clone(), skew_header(ton.clone(), conf).await)
+            .kind(ErrorKind::InvalidInput)?;
+        let msg = prepare_message(
+            ton.clone(),
+            addr,
+            msg_abi,
+            method,
+            &params.to_string(),
+            header,
+            keys.clone(),
+        ).await?;
+        print_encoded_message(&msg);
+
+        if let Err(e) = check_message_accepted(ton.clone(), addr, abi.clone(), &msg.message).await {
+            let fresh = query_seqno(ton.clone(), addr, abi.clone()).await?;
+            if fresh == seqno || attempt >= SEQNO_RETRIES {
+                return Err(e);
+            }
+            println!("{}", e);
+            println!("{}", tr("Seqno has changed, retrying..."));
+            attempt += 1;
+            continue;
+        }
+
+        match send_message_and_wait(ton.clone(), addr, abi.clone(), msg.message.clone(), false).await {
+            Err(e) if attempt < SEQNO_RETRIES && is_message_rejected(ton.clone(), &msg).await => {
+                println!("{}", e);
+                println!("{}", tr("Message expired without transaction. Re-reading seqno and retrying..."));
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Runs contract method locally on the current account state using existing client.
+/// With `quiet` nothing is printed, which is used for per-item runs in bulk commands.
+pub async fn run_local(
+    ton: TonClient,
+    addr: &str,
+    abi: &str,
+    method: &str,
+    params: &str,
+    quiet: bool,
+) -> Result<serde_json::Value, CliError> {
+    let abi = load_abi(abi)?;
+    if !quiet {
+        let msg = prepare_message(ton.clone(), addr, abi.clone(), method, params, None, None).await?;
+        return send_message_and_wait(ton, addr, abi, msg.message, true).await;
+    }
+    let params: serde_json::Value = serde_json::from_str(params)
+        .map_err(invalid_arguments)?;
+    let msg = encode_message(
+        ton.clone(),
+        ParamsOfEncodeMessage {
+            abi: abi.clone(),
+            address: Some(addr.to_owned()),
+            call_set: CallSet::some_with_function_and_input(method, params),
+            signer: Signer::None,
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| CliError::from(e).context("failed to create inbound message"))?;
+    run_message_locally(ton, addr, abi, msg.message).await
+}
+
+/// Runs message on the current state of the account and returns decoded output.
+async fn run_message_locally(
+    ton: TonClient,
+    addr: &str,
+    abi: Abi,
+    msg: String,
+) -> Result<serde_json::Value, CliError> {
+    let account = query_account_boc(ton.clone(), addr).await?;
+    let result = run_tvm(
+        ton,
+        ParamsOfRunTvm {
+            message: msg,
+            account,
+            abi: Some(abi),
+            return_updated_account: Some(true),
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| run_failed(&e))?;
+    Ok(result.decoded.and_then(|d| d.output).unwrap_or(json!({})))
+}
+
+/// Runs get-method on every address, up to `concurrency` accounts are
+/// downloaded and run at once. Results are returned by address.
+pub async fn run_local_batch(
+    ton: TonClient,
+    addrs: &[String],
+    abi: &str,
+    method: &str,
+    params: &str,
+    concurrency: usize,
+) -> Result<BTreeMap<String, Result<serde_json::Value, String>>, String> {
+    let params_value: serde_json::Value = serde_json::from_str(params)
+        .map_err(|e| format!("arguments are not in json format: {}", e))?;
+    if let Abi::Contract(contract) = &load_abi(abi)? {
+        let contract = serde_json::to_value(contract)
+            .map_err(|e| format!("failed to serialize ABI: {}", e))?;
+        validate_params(&contract, method, &params_value)?;
+    }
+    let results = futures::stream::iter(addrs.iter().cloned())
+        .map(|addr| {
+            let ton = ton.clone();
+            async move {
+                let result = run_local(ton, &addr, abi, method, params, true).await
+                    .map_err(|e| e.to_string());
+                (addr, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<BTreeMap<_, _>>()
+        .await;
+    Ok(results)
+}
+
+/// Parses list of addresses: one per line, empty lines and `#` comments are skipped.
+pub fn parse_addr_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_owned())
+        .collect()
+}
+
+pub async fn run_contract_on_boc(
+    addr: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    boc_path: &str,
+    raw: bool,
+) -> Result<(), CliError> {
+    let ton = create_client_local()?;
+    let abi_str = abi;
+    let abi = load_abi(&abi_str)?;
+    let acc_boc = std::fs::read(boc_path)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read account state file: {}", e)))?;
+
+    let msg = prepare_message(ton.clone(), addr, abi.clone(), method, params, None, None).await?;
+
+    println!("{}", tr("Running get-method..."));
+    let result = run_tvm(
+        ton,
+        ParamsOfRunTvm {
+            message: msg.message,
+            account: base64::encode(&acc_boc),
+            abi: Some(abi),
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| run_failed(&e))?;
+    let result = result.decoded.and_then(|d| d.output).unwrap_or(json!({}));
+
+    println!("{}", tr("Succeeded."));
+    Ok(print_run_result(&abi_str, method, &result, raw)?)
+}
+
+fn print_run_result(abi: &str, method: &str, result: &serde_json::Value, raw: bool) -> Result<(), String> {
+    if result.is_null() {
+        return Ok(());
+    }
+    let result = if raw { result.clone() } else { pretty::format_output(abi, method, result) };
+    print_result(&result)
+}
+
+fn print_result(result: &serde_json::Value) -> Result<(), String> {
+    output::print_value(result, || {
+        println!("{}", trf("Result: {}", &[&serde_json::to_string_pretty(result).unwrap()]));
+    })
+}
+
+pub async fn run_contract(
+    conf: Config,
+    addr: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    raw: bool,
+) -> Result<(), CliError> {
+    let result = call_contract_with_result(conf, addr, abi.clone(), method, params, None, true).await?;
+    println!("{}", tr("Succeeded."));
+    Ok(print_run_result(&abi, method, &result, raw)?)
 }
 
 pub async fn call_contract(
@@ -333,15 +666,35 @@ pub async fn call_contract(
     params: &str,
     keys: Option<String>,
     local: bool
-) -> Result<(), String> {
+) -> Result<(), CliError> {
     let result = call_contract_with_result(conf, addr, abi, method, params, keys, local).await?;
-    println!("Succeeded.");
+    println!("{}", tr("Succeeded."));
     if !result.is_null() {
-        println!("Result: {}", serde_json::to_string_pretty(&result).unwrap());
+        print_result(&result)?;
     }
     Ok(())
 }
 
+/// Encodes body of internal message calling the function, returns base64 cell.
+pub async fn encode_body(abi: &str, method: &str, params: &str) -> Result<String, String> {
+    let params: serde_json::Value = serde_json::from_str(params)
+        .map_err(|e| format!("arguments are not in json format: {}", e))?;
+    let contract: serde_json::Value = serde_json::from_str(abi)
+        .map_err(|e| format!("ABI is not a valid json: {}", e))?;
+    validate_params(&contract, method, &params)?;
+    encode_message_body(
+        create_client_local()?,
+        ParamsOfEncodeMessageBody {
+            abi: load_abi(abi)?,
+            call_set: CallSet::some_with_function_and_input(method, params).unwrap(),
+            is_internal: true,
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| format!("failed to encode body: {}", e))
+    .map(|r| r.body)
+}
+
 pub async fn generate_message(
     _conf: Config,
     addr: &str,
@@ -352,11 +705,11 @@ pub async fn generate_message(
     lifetime: u32,
     is_raw: bool,
     output: Option<&str>,
-) -> Result<(), String> {
+) -> Result<(), CliError> {
     let ton = create_client_local()?;
 
     let ton_addr = load_ton_address(addr, &_conf)
-        .map_err(|e| format!("failed to parse address: {}", e.to_string()))?;
+        .map_err(|e| e.context("failed to parse address"))?;
 
     let abi = load_abi(&abi)?;
 
@@ -366,6 +719,7 @@ pub async fn generate_message(
         expire: Some(expire_at),
         ..Default::default()
     };
+    let (abi, header) = apply_header_options(&_conf, abi, Some(header)).kind(ErrorKind::InvalidInput)?;
 
     let msg = prepare_message(
         ton.clone(),
@@ -373,7 +727,7 @@ pub async fn generate_message(
         abi,
         method,
         params,
-        Some(header),
+        header,
         keys,
     ).await?;
     print_encoded_message(&msg);
@@ -382,11 +736,11 @@ pub async fn generate_message(
     if output.is_some() {
         let out_file = output.unwrap();
         std::fs::write(out_file, msg_bytes)
-            .map_err(|e| format!("cannot write message to file: {}", e))?;
-        println!("Message saved to file {}", out_file);
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("cannot write message to file: {}", e)))?;
+        println!("{}", trf("Message saved to file {}", &[&out_file]));
     } else {
         let msg_hex = hex::encode(&msg_bytes);
-        println!("Message: {}", msg_hex);
+        println!("{}", trf("Message: {}", &[&msg_hex]));
         println!();
         qr2term::print_qr(msg_hex).unwrap();
         println!();
@@ -394,24 +748,24 @@ pub async fn generate_message(
     Ok(())
 }
 
-pub async fn call_contract_with_msg(conf: Config, str_msg: String, abi: String) -> Result<(), String> {
+pub async fn call_contract_with_msg(conf: Config, str_msg: String, abi: String) -> Result<(), CliError> {
     let ton = create_client_verbose(&conf)?;
     let abi = load_abi(&abi)?;
 
-    let (msg, _) = unpack_message(&str_msg)?;
+    let (msg, _) = unpack_message(&str_msg).kind(ErrorKind::InvalidInput)?;
     print_encoded_message(&msg);
 
     let params = decode_call_parameters(ton.clone(), &msg, abi.clone()).await?;
 
-    println!("Calling method {} with parameters:", params.0);
+    println!("{}", trf("Calling method {} with parameters:", &[&params.0]));
     println!("{}", params.1);
-    println!("Processing... ");
+    println!("{}", tr("Processing..."));
 
     let result = send_message_and_wait(ton, &msg.address, abi, msg.message, false).await?;
 
-    println!("Succeded.");
+    println!("{}", tr("Succeded."));
     if !result.is_null() {
-        println!("Result: {}", serde_json::to_string_pretty(&result).unwrap());
+        print_result(&result)?;
     }
     Ok(())
 }
@@ -425,20 +779,20 @@ pub fn parse_params(params_vec: Vec<&str>, abi: &str, method: &str) -> Result<St
     }
 }
 
-pub async fn run_get_method(conf: Config, addr: &str, method: &str, params: Option<String>) -> Result<(), String> {
-    let ton = create_client_verbose(&conf)?;
+pub async fn run_get_with_result(conf: &Config, addr: &str, method: &str, params: Option<String>) -> Result<serde_json::Value, CliError> {
+    let ton = create_client_verbose(conf)?;
 
-    let addr = load_ton_address(addr, &conf)
-        .map_err(|e| format!("failed to parse address: {}", e.to_string()))?;
+    let addr = load_ton_address(addr, conf)
+        .map_err(|e| e.context("failed to parse address"))?;
 
     let acc_boc = query_account_boc(ton.clone(), addr.as_str()).await?;
 
     let params = params.map(|p| serde_json::from_str(&p))
         .transpose()
-        .map_err(|e| format!("arguments are not in json format: {}", e))?;
+        .map_err(invalid_arguments)?;
 
-    println!("Running get-method...");
-    let result = run_get(
+    println!("{}", tr("Running get-method..."));
+    run_get(
         ton,
         ParamsOfRunGet {
             account: acc_boc,
@@ -447,10 +801,59 @@ pub async fn run_get_method(conf: Config, addr: &str, method: &str, params: Opti
             ..Default::default()
         },
     ).await
-    .map_err(|e| format!("run failed: {}", e.to_string()))?
-    .output;
+    .map_err(|e| run_failed(&e))
+    .map(|r| r.output)
+}
 
-    println!("Succeded.");
-    println!("Result: {}", result);
-    Ok(())
-}
\ No newline at end of file
+/// Returns get-method id of the function name: crc16 of the name with 0x10000 bit set.
+fn get_method_id(name: &str) -> u32 {
+    (crc16::State::<crc16::XMODEM>::calculate(name.as_bytes()) as u32 & 0xffff) | 0x10000
+}
+
+/// Get-method ids can't be converted back to names, so returns any name with the same id
+/// to run the get-method by its numeric id. Decimal and 0x-prefixed hex ids are accepted,
+/// other strings are returned as is.
+pub fn resolve_get_method(method: &str) -> Result<String, String> {
+    let id = match method.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => method.parse::<u32>().ok(),
+    };
+    let id = match id {
+        Some(id) => id,
+        None => return Ok(method.to_owned()),
+    };
+    if id & !0xffff != 0x10000 {
+        return Err(format!("get-method id {} is not in range 0x10000..0x1ffff of named get-methods", method));
+    }
+    (0..1u32 << 24)
+        .map(|n| format!("_{:x}", n))
+        .find(|name| get_method_id(name) == id)
+        .ok_or(format!("failed to find name for get-method id {}", method))
+}
+
+pub async fn run_get_method(conf: Config, addr: &str, method: &str, params: Option<String>) -> Result<(), CliError> {
+    let result = run_get_with_result(&conf, addr, method, params).await?;
+
+    println!("{}", tr("Succeded."));
+    Ok(output::print_value(&result, || println!("{}", trf("Result: {}", &[&result])))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addr_list() {
+        let text = "# depools\n0:1111\n\n  -1:2222  # elector\nwallet\n";
+        assert_eq!(parse_addr_list(text), vec!["0:1111", "-1:2222", "wallet"]);
+    }
+
+    #[test]
+    fn test_resolve_get_method() {
+        assert_eq!(get_method_id("seqno"), 85143);
+        assert_eq!(resolve_get_method("seqno").unwrap(), "seqno");
+        assert_eq!(get_method_id(&resolve_get_method("85143").unwrap()), 85143);
+        assert_eq!(get_method_id(&resolve_get_method("0x14c97").unwrap()), 0x14c97);
+        assert!(resolve_get_method("0").is_err());
+    }
+}
@@ -10,7 +10,21 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
+use crate::error::{CliError, ErrorKind, ResultExt};
+use crate::faucet::FaucetSettings;
+use crate::helpers::create_client_local;
+use crate::i18n::tr;
+use crate::macros::MacroDef;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use ton_client::crypto::{
+    generate_random_bytes, nacl_secret_box, nacl_secret_box_open,
+    ParamsOfGenerateRandomBytes, ParamsOfNaclSecretBox, ParamsOfNaclSecretBoxOpen,
+};
+
+const KEYCHAIN_SERVICE: &str = "tonos-cli";
+const ENCRYPTION_SCHEME: &str = "nacl_secret_box";
 
 const TESTNET: &'static str = "https://net.ton.dev";
 fn default_url() -> String {
@@ -33,10 +47,82 @@ fn default_timeout() -> u32 {
     60000
 }
 
+fn default_processing_timeout() -> u32 {
+    30000
+}
+
+fn default_wait_for_timeout() -> u32 {
+    30000
+}
+
+fn default_query_timeout() -> u32 {
+    0
+}
+
+fn default_rate_limit() -> u32 {
+    10
+}
+
+fn default_concurrency() -> u32 {
+    4
+}
+
+fn default_cache_ttl() -> u32 {
+    7 * 24 * 3600
+}
+
+fn default_cache_size() -> u64 {
+    100
+}
+
 fn default_false() -> bool {
     false
 }
 
+/// Header settings of external messages set by command line flags.
+#[derive(Clone, Default)]
+pub struct HeaderOptions {
+    /// Add (`true`) or remove (`false`) `pubkey` header regardless of the ABI.
+    pub pubkey: Option<bool>,
+    /// Add (`true`) or remove (`false`) `time` header regardless of the ABI.
+    pub time: Option<bool>,
+    /// Explicit value of `expire` header.
+    pub expire: Option<u32>,
+}
+
+/// Retry and timeout policy for network calls set by global `--retry` and
+/// `--timeout` flags. Overrides the corresponding config values.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct NetworkPolicy {
+    /// Number of retries of failed queries and of expired messages.
+    pub retries: Option<u8>,
+    /// Timeout in ms of a single query and of waiting for a message or record.
+    pub timeout: Option<u32>,
+}
+
+/// Debot functions allowed to sign messages without confirmation. The entry is
+/// bound to the debot code hash and stops applying once the code changes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DebotWhitelistEntry {
+    pub address: String,
+    pub code_hash: String,
+    /// Names of debot ABI functions run by actions, "*" allows all functions of the debot.
+    pub actions: Vec<String>,
+}
+
+/// Reads debot signing whitelist. Entries of the old format (actions per debot
+/// address only) are dropped: they would allow signing for any code deployed
+/// at the address.
+fn deserialize_whitelist<'de, D>(deserializer: D) -> Result<Vec<DebotWhitelistEntry>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        value @ Value::Array(_) => serde_json::from_value(value).map_err(serde::de::Error::custom),
+        _ => Ok(vec![]),
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     #[serde(default = "default_url")]
@@ -49,12 +135,90 @@ pub struct Config {
     pub keys_path: Option<String>,
     #[serde(default = "default_retries")]
     pub retries: u8,
+    /// Message expiration timeout in ms. Half of it is also used as the
+    /// threshold for the endpoint out of sync detection.
     #[serde(default = "default_timeout")]
     pub timeout: u32,
+    /// Time in ms to wait for the transaction of a message without `expire`
+    /// header. Messages with `expire` header are waited until expiration.
+    #[serde(default = "default_processing_timeout")]
+    pub processing_timeout: u32,
+    /// Default time in ms to wait for a record to appear in the blockchain
+    /// (e.g. new event, account balance change).
+    #[serde(default = "default_wait_for_timeout")]
+    pub wait_for_timeout: u32,
+    /// Time in ms for a single query in bulk query mode (0 - no limit).
+    /// Timed out queries are retried like failed ones.
+    #[serde(default = "default_query_timeout")]
+    pub query_timeout: u32,
     #[serde(default = "default_false")]
     pub is_json: bool,
+    /// Shift message time and expiration by the difference between local
+    /// clock and network time if it exceeds the threshold.
+    #[serde(default = "default_false")]
+    pub clock_compensation: bool,
+    /// Amounts must have a unit (EVER, T, nano or %), see `convert::parse_amount`.
+    #[serde(default = "default_false")]
+    pub strict_units: bool,
+    /// Set by `--offline` flag, never stored in the config file.
+    #[serde(skip)]
+    pub offline: bool,
+    /// Config file is encrypted with a key stored in OS keychain.
+    #[serde(skip)]
+    pub encrypted: bool,
+    /// Set by header flags, never stored in the config file.
+    #[serde(skip)]
+    pub header_options: HeaderOptions,
+    /// Set by `--retry` and `--timeout` flags, never stored in the config file.
+    #[serde(skip)]
+    pub network_policy: NetworkPolicy,
     #[serde(default = "default_depool_fee")]
     pub depool_fee: f32,
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Max number of queries per second in bulk query mode (0 - unlimited).
+    #[serde(default = "default_rate_limit")]
+    pub rate_limit: u32,
+    /// Max number of simultaneous queries in bulk query mode.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: u32,
+    /// Directory for cached results of immutable queries.
+    pub cache_dir: Option<String>,
+    /// Lifetime of cached entries in seconds (0 - cache is disabled).
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u32,
+    /// Max size of the cache in megabytes.
+    #[serde(default = "default_cache_size")]
+    pub cache_size: u64,
+    /// Debot actions (per debot address and code hash) allowed to sign messages
+    /// without confirmation.
+    #[serde(default, deserialize_with = "deserialize_whitelist")]
+    pub debot_signing_whitelist: Vec<DebotWhitelistEntry>,
+    /// Extra http headers (per endpoint url) sent to GraphQL endpoint, e.g. api keys.
+    /// Values may contain `${VAR}` which is replaced with environment variable at startup.
+    #[serde(default)]
+    pub endpoint_headers: BTreeMap<String, BTreeMap<String, String>>,
+    /// Named network endpoints used by `--networks` option.
+    #[serde(default)]
+    pub networks: BTreeMap<String, String>,
+    /// Language of user-facing messages (en, ru).
+    pub lang: Option<String>,
+    /// Maximum value (in nanotokens) debots may spend from the wallet during
+    /// one browser run without explicit confirmation.
+    #[serde(default)]
+    pub debot_session_limit: Option<u64>,
+    /// Url of token price history used by `account export`, see `export::PriceSource`.
+    pub price_url: Option<String>,
+    /// Json path to the price in the price source response.
+    pub price_path: Option<String>,
+    /// REST endpoint used to broadcast messages instead of GraphQL, see `relay`.
+    pub relay_url: Option<String>,
+    /// Command macros invoked as `x <name> <args>`, see `macros`.
+    #[serde(default)]
+    pub macros: BTreeMap<String, MacroDef>,
+    /// Test network faucets (per endpoint url) used by `faucet request`.
+    #[serde(default)]
+    pub faucets: BTreeMap<String, FaucetSettings>,
 }
 
 impl Config {
@@ -68,131 +232,594 @@ impl Config {
             keys_path: None,
             retries: default_retries(),
             timeout: default_timeout(),
+            processing_timeout: default_processing_timeout(),
+            wait_for_timeout: default_wait_for_timeout(),
+            query_timeout: default_query_timeout(),
             is_json: default_false(),
+            clock_compensation: default_false(),
+            strict_units: default_false(),
+            offline: false,
+            encrypted: false,
+            header_options: HeaderOptions::default(),
+            network_policy: NetworkPolicy::default(),
             depool_fee: default_depool_fee(),
+            aliases: BTreeMap::new(),
+            rate_limit: default_rate_limit(),
+            concurrency: default_concurrency(),
+            cache_dir: None,
+            cache_ttl: default_cache_ttl(),
+            cache_size: default_cache_size(),
+            debot_signing_whitelist: vec![],
+            endpoint_headers: BTreeMap::new(),
+            networks: BTreeMap::new(),
+            lang: None,
+            debot_session_limit: None,
+            price_url: None,
+            price_path: None,
+            relay_url: None,
+            macros: BTreeMap::new(),
+            faucets: BTreeMap::new(),
         }
     }
 
+    /// Returns configs for comma-separated list of network names (or urls),
+    /// each differs from this one by endpoint url only.
+    pub fn for_networks(&self, networks: &str) -> Result<Vec<(String, Config)>, CliError> {
+        networks.split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let url = match self.networks.get(name) {
+                    Some(url) => url.clone(),
+                    None if name.contains('.') || name.contains("://") => name.to_owned(),
+                    None => return Err(config_error(format!("network {} is not defined. Add it with `config --network {}=<url>`", name, name))),
+                };
+                let mut conf = self.clone();
+                conf.url = url;
+                Ok((name.to_owned(), conf))
+            })
+            .collect()
+    }
+
     pub fn from_file(path: &str) -> Option<Self> {
-        let conf_str = std::fs::read_to_string(path).ok()?;
-        let conf: Config = serde_json::from_str(&conf_str).ok()?;
-        Some(conf)
+        match Self::load(path) {
+            Ok(conf) => conf,
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                None
+            },
+        }
     }
-}
 
-pub fn clear_config(
-    mut conf: Config,
-    path: &str,
-    url: bool,
-    addr: bool,
-    wallet: bool,
-    abi: bool,
-    keys: bool,
-    wc: bool,
-    retries: bool,
-    timeout: bool,
-    depool_fee: bool,
-) -> Result<(), String> {
-    if url {
-        conf.url = default_url();
+    /// Reads config file decrypting it if needed. Returns `None` if file doesn't exist.
+    pub fn load(path: &str) -> Result<Option<Self>, CliError> {
+        Self::load_file(path, path)
     }
-    if addr {
-        conf.addr = None;
+
+    /// Loads config from `file` (e.g. backup) decrypting it with the key of the config at `path`.
+    fn load_file(file: &str, path: &str) -> Result<Option<Self>, CliError> {
+        let conf_str = match std::fs::read_to_string(file) {
+            Ok(s) => s,
+            Err(_) => return Ok(None),
+        };
+        let value: Value = serde_json::from_str(&conf_str)
+            .map_err(|e| config_error(format!("failed to parse config file: {}", e)))?;
+        if value.get("encrypted").is_none() {
+            let conf = serde_json::from_value(value)
+                .map_err(|e| config_error(format!("failed to parse config file: {}", e)))?;
+            return Ok(Some(conf));
+        }
+        let decrypted = decrypt_config(path, &value)?;
+        let mut conf: Config = serde_json::from_slice(&decrypted)
+            .map_err(|e| config_error(format!("failed to parse decrypted config file: {}", e)))?;
+        conf.encrypted = true;
+        Ok(Some(conf))
     }
-    if wallet {
-        conf.wallet = None;
+
+    /// Writes config file, encrypted if the config was loaded from encrypted file.
+    pub fn save(&self, path: &str) -> Result<(), CliError> {
+        let conf_str = serde_json::to_string(self)
+            .map_err(|_| CliError::new(ErrorKind::Internal, "failed to serialize config object"))?;
+        let conf_str = if self.encrypted {
+            encrypt_config(path, conf_str.as_bytes())?
+        } else {
+            conf_str
+        };
+        std::fs::write(path, conf_str)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to write config file: {}", e)))
     }
-    if abi {
-        conf.abi_path = None;
+
+    /// Headers configured for the current endpoint with environment variables substituted.
+    pub fn headers(&self) -> Result<BTreeMap<String, String>, CliError> {
+        self.headers_for(&self.url)
     }
-    if keys {
-        conf.keys_path = None;
+
+    /// Headers configured for the url with environment variables substituted.
+    pub fn headers_for(&self, url: &str) -> Result<BTreeMap<String, String>, CliError> {
+        let mut headers = BTreeMap::new();
+        if let Some(configured) = self.endpoint_headers.get(url) {
+            for (name, value) in configured {
+                headers.insert(name.clone(), interpolate_env(value).kind(ErrorKind::Config)?);
+            }
+        }
+        Ok(headers)
     }
-    if retries {
-        conf.retries = default_retries();
+}
+
+/// Keychain account name for the config file key (absolute path of the file).
+fn keychain_user(path: &str) -> Result<String, CliError> {
+    let path = std::fs::canonicalize(path)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to resolve config path: {}", e)))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+fn encrypt_config(path: &str, data: &[u8]) -> Result<String, CliError> {
+    let ton = create_client_local()?;
+    let user = keychain_user(path)?;
+    let entry = keyring::Keyring::new(KEYCHAIN_SERVICE, &user);
+    let key = match entry.get_password() {
+        Ok(key) => key,
+        Err(_) => {
+            let key = generate_random_bytes(ton.clone(), ParamsOfGenerateRandomBytes { length: 32 })
+                .map_err(|e| config_error(format!("failed to generate encryption key: {}", e)))?
+                .bytes;
+            let key = hex::encode(base64::decode(&key).map_err(|e| config_error(format!("{}", e)))?);
+            entry.set_password(&key)
+                .map_err(|e| config_error(format!("failed to store encryption key in keychain: {}", e)))?;
+            key
+        },
+    };
+    let nonce = generate_random_bytes(ton.clone(), ParamsOfGenerateRandomBytes { length: 24 })
+        .map_err(|e| config_error(format!("failed to generate nonce: {}", e)))?
+        .bytes;
+    let nonce = hex::encode(base64::decode(&nonce).map_err(|e| config_error(format!("{}", e)))?);
+    let encrypted = nacl_secret_box(
+        ton,
+        ParamsOfNaclSecretBox { decrypted: base64::encode(data), nonce: nonce.clone(), key },
+    ).map_err(|e| config_error(format!("failed to encrypt config: {}", e)))?;
+    serde_json::to_string(&json!({
+        "encrypted": ENCRYPTION_SCHEME,
+        "nonce": nonce,
+        "data": encrypted.encrypted,
+    })).map_err(|_| CliError::new(ErrorKind::Internal, "failed to serialize config object"))
+}
+
+fn decrypt_config(path: &str, value: &Value) -> Result<Vec<u8>, CliError> {
+    if value["encrypted"].as_str() != Some(ENCRYPTION_SCHEME) {
+        return Err(config_error(format!("unsupported config encryption: {}", value["encrypted"])));
     }
-    if timeout {
-        conf.timeout = default_timeout();
+    let user = keychain_user(path)?;
+    let key = keyring::Keyring::new(KEYCHAIN_SERVICE, &user).get_password()
+        .map_err(|e| config_error(format!("failed to get config encryption key from keychain: {}", e)))?;
+    let decrypted = nacl_secret_box_open(
+        create_client_local()?,
+        ParamsOfNaclSecretBoxOpen {
+            encrypted: value["data"].as_str().unwrap_or("").to_owned(),
+            nonce: value["nonce"].as_str().unwrap_or("").to_owned(),
+            key,
+        },
+    ).map_err(|e| config_error(format!("failed to decrypt config file: {}", e)))?;
+    base64::decode(&decrypted.decrypted)
+        .map_err(|e| config_error(format!("failed to decode decrypted config: {}", e)))
+}
+
+/// Backup of the previous config version used by `config undo`.
+fn backup_path(path: &str) -> String {
+    format!("{}.bak", path)
+}
+
+fn config_lines(conf: &Config) -> Result<Vec<String>, CliError> {
+    serde_json::to_string_pretty(conf)
+        .map(|text| text.lines().map(|line| line.to_owned()).collect())
+        .map_err(|e| CliError::new(ErrorKind::Internal, format!("failed to serialize config: {}", e)))
+}
+
+/// Line diff of two texts (longest common subsequence).
+/// Returns lines prefixed with ' ' (unchanged), '-' (removed) or '+' (added).
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<(char, String)> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
     }
-    if wc {
-        conf.wc = default_wc();
+    let (mut i, mut j) = (0, 0);
+    let mut diff = vec![];
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            diff.push((' ', old[i].clone()));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push(('+', new[j].clone()));
+            j += 1;
+        } else {
+            diff.push(('-', old[i].clone()));
+            i += 1;
+        }
     }
-    if depool_fee {
-        conf.depool_fee = default_depool_fee();
+    diff
+}
+
+fn print_diff(diff: &[(char, String)]) {
+    let color = atty::is(atty::Stream::Stdout);
+    for (mark, line) in diff {
+        match (mark, color) {
+            ('-', true) => println!("\x1b[31m- {}\x1b[0m", line),
+            ('+', true) => println!("\x1b[32m+ {}\x1b[0m", line),
+            _ => println!("{} {}", mark, line),
+        }
     }
-    if (url || addr || wallet || abi || keys || retries || timeout || wc || depool_fee) == false {
-        conf = Config {
-            url: default_url(),
-            wc: default_wc(),
-            addr: None,
-            wallet: None,
-            abi_path: None,
-            keys_path: None,
-            retries: default_retries(),
-            timeout: default_timeout(),
-            is_json: default_false(),
-            depool_fee: default_depool_fee(),
-        };
+}
+
+/// Saves changed config keeping the previous version for `config undo`,
+/// in dry run mode only prints the difference.
+fn store_config(conf: &Config, path: &str, dry_run: bool) -> Result<(), CliError> {
+    // malformed file is replaced as a whole, the warning is printed at startup
+    let old = Config::load(path).ok().flatten().unwrap_or(Config::new());
+    let diff = diff_lines(&config_lines(&old)?, &config_lines(conf)?);
+    if dry_run {
+        if diff.iter().all(|(mark, _)| *mark == ' ') {
+            println!("No changes.");
+        } else {
+            print_diff(&diff);
+        }
+        println!("Dry run, config file is not changed.");
+        return Ok(());
+    }
+    if std::path::Path::new(path).exists() {
+        std::fs::copy(path, backup_path(path))
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to back up config file: {}", e)))?;
+    }
+    conf.save(path)?;
+    println!("{}", tr("Succeeded."));
+    Ok(())
+}
+
+/// Restores the config version saved before the last change. The current
+/// version becomes the backup, so repeated undo redoes the change.
+pub fn undo_config(path: &str) -> Result<(), CliError> {
+    let backup = backup_path(path);
+    if !std::path::Path::new(&backup).exists() {
+        return Err(config_error("no previous config version to restore"));
+    }
+    let current = Config::load(path)?.unwrap_or(Config::new());
+    // backup is encrypted with the key of the config file
+    let previous = Config::load_file(&backup, path)?.unwrap_or(Config::new());
+    print_diff(&diff_lines(&config_lines(&current)?, &config_lines(&previous)?));
+    let swap = format!("{}.swap", path);
+    let exists = std::path::Path::new(path).exists();
+    if exists {
+        std::fs::rename(path, &swap)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to restore config file: {}", e)))?;
     }
-    let conf_str = serde_json::to_string(&conf)
-        .map_err(|_| "failed to serialize config object".to_string())?;
+    std::fs::rename(&backup, path)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to restore config file: {}", e)))?;
+    if exists {
+        std::fs::rename(&swap, &backup)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to keep current config as backup: {}", e)))?;
+    }
+    println!("{}", tr("Succeeded."));
+    Ok(())
+}
 
-    std::fs::write(path, conf_str).map_err(|e| format!("failed to write config file: {}", e))?;
-    println!("Succeeded.");
+/// Turns encryption of the config file on or off. Encryption key is kept in OS keychain.
+pub fn encrypt_config_file(mut conf: Config, path: &str, encrypt: bool) -> Result<(), CliError> {
+    if conf.encrypted == encrypt {
+        println!("Config is already {}.", if encrypt { "encrypted" } else { "not encrypted" });
+        return Ok(());
+    }
+    if !std::path::Path::new(path).exists() {
+        conf.save(path)?;
+    }
+    conf.encrypted = encrypt;
+    conf.save(path)?;
+    if !encrypt {
+        let user = keychain_user(path)?;
+        let _ = keyring::Keyring::new(KEYCHAIN_SERVICE, &user).delete_password();
+    }
+    println!("{}", tr("Succeeded."));
     Ok(())
 }
 
-pub fn set_config(
-    mut conf: Config,
-    path: &str,
-    url: Option<&str>,
-    addr: Option<&str>,
-    wallet: Option<&str>,
-    abi: Option<&str>,
-    keys: Option<&str>,
-    wc: Option<&str>,
-    retries: Option<&str>,
-    timeout: Option<&str>,
-    depool_fee: Option<&str>,
-) -> Result<(), String> {
-        if let Some(s) = url {
-            conf.url = s.to_string();
-        }
-        if let Some(s) = addr {
-            conf.addr = Some(s.to_string());
-        }
-        if let Some(s) = wallet {
-            conf.wallet = Some(s.to_string());
-        }
-        if let Some(s) = abi {
-            conf.abi_path = Some(s.to_string());
-        }
-        if let Some(s) = keys {
-            conf.keys_path = Some(s.to_string());
-        }
-        if let Some(retries) = retries {
-            conf.retries = u8::from_str_radix(retries, 10)
-                .map_err(|e| format!(r#"failed to parse "retries": {}"#, e))?;
-        }
-        if let Some(timeout) = timeout {
-            conf.timeout = u32::from_str_radix(timeout, 10)
-                .map_err(|e| format!(r#"failed to parse "timeout": {}"#, e))?;
-        }
-        if let Some(wc) = wc {
-            conf.wc = i32::from_str_radix(wc, 10)
-                .map_err(|e| format!(r#"failed to parse "workchain id": {}"#, e))?;
-        }
-        if let Some(depool_fee) = depool_fee {
-            conf.depool_fee = depool_fee.parse::<f32>()
-                .map_err(|e| format!(r#"failed to parse "depool_fee": {}"#, e))?;
+fn config_error(message: impl Into<String>) -> CliError {
+    CliError::new(ErrorKind::Config, message)
+}
+
+fn invalid_value(message: impl Into<String>) -> CliError {
+    CliError::new(ErrorKind::InvalidInput, message)
+}
+
+/// Replaces `${VAR}` with the value of environment variable `VAR`.
+pub fn interpolate_env(value: &str) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find('}')
+            .ok_or(format!(r#"unterminated variable in "{}""#, value))?;
+        let var = &rest[start + 2..start + end];
+        let var_value = std::env::var(var)
+            .map_err(|_| format!("environment variable {} is not set", var))?;
+        result.push_str(&var_value);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Options of `config clear`, each one resets its group of parameters.
+pub const CLEAR_OPTIONS: [&str; 23] = [
+    "URL", "ADDR", "WALLET", "ABI", "KEYS", "WC", "RETRIES", "TIMEOUT", "DEPOOL_FEE",
+    "ALIAS", "RATE_LIMIT", "CONCURRENCY", "CACHE", "CLOCK_COMPENSATION", "STRICT_UNITS",
+    "HEADER", "NETWORK", "LANG", "DEBOT_SESSION_LIMIT", "PRICE_SOURCE", "RELAY_URL",
+    "MACRO", "FAUCET",
+];
+
+/// Options of `config` in the order they are applied: endpoint url goes
+/// first because headers and faucet are stored per endpoint, strict units
+/// before the amounts they apply to.
+pub const SET_OPTIONS: [&str; 30] = [
+    "URL", "STRICT_UNITS", "HEADER", "NETWORK", "LANG", "DEBOT_SESSION_LIMIT", "PRICE_URL",
+    "PRICE_PATH", "RELAY_URL", "FAUCET_URL", "FAUCET_KEY", "MACRO", "ADDR", "WALLET", "ABI",
+    "KEYS", "RETRIES", "TIMEOUT", "PROCESSING_TIMEOUT", "WAIT_FOR_TIMEOUT", "QUERY_TIMEOUT",
+    "CLOCK_COMPENSATION", "WC", "DEPOOL_FEE", "ALIAS", "RATE_LIMIT",
+    "CONCURRENCY", "CACHE_DIR", "CACHE_TTL", "CACHE_SIZE",
+];
+
+/// Resets parameters of the given `CLEAR_OPTIONS`, all parameters if there are none.
+pub fn clear_config(mut conf: Config, path: &str, options: &[&str], dry_run: bool) -> Result<(), CliError> {
+    if options.is_empty() {
+        conf = Config { encrypted: conf.encrypted, ..Config::new() };
+    }
+    for option in options {
+        match *option {
+            "URL" => conf.url = default_url(),
+            "ADDR" => conf.addr = None,
+            "WALLET" => conf.wallet = None,
+            "ABI" => conf.abi_path = None,
+            "KEYS" => conf.keys_path = None,
+            "RETRIES" => conf.retries = default_retries(),
+            "TIMEOUT" => {
+                conf.timeout = default_timeout();
+                conf.processing_timeout = default_processing_timeout();
+                conf.wait_for_timeout = default_wait_for_timeout();
+                conf.query_timeout = default_query_timeout();
+            },
+            "WC" => conf.wc = default_wc(),
+            "DEPOOL_FEE" => conf.depool_fee = default_depool_fee(),
+            "ALIAS" => conf.aliases.clear(),
+            "RATE_LIMIT" => conf.rate_limit = default_rate_limit(),
+            "CONCURRENCY" => conf.concurrency = default_concurrency(),
+            "CACHE" => {
+                conf.cache_dir = None;
+                conf.cache_ttl = default_cache_ttl();
+                conf.cache_size = default_cache_size();
+            },
+            "CLOCK_COMPENSATION" => conf.clock_compensation = default_false(),
+            "STRICT_UNITS" => conf.strict_units = default_false(),
+            "HEADER" => { conf.endpoint_headers.remove(&conf.url); },
+            "NETWORK" => conf.networks.clear(),
+            "LANG" => conf.lang = None,
+            "DEBOT_SESSION_LIMIT" => conf.debot_session_limit = None,
+            "PRICE_SOURCE" => {
+                conf.price_url = None;
+                conf.price_path = None;
+            },
+            "RELAY_URL" => conf.relay_url = None,
+            "MACRO" => conf.macros.clear(),
+            "FAUCET" => { conf.faucets.remove(&conf.url); },
+            other => return Err(CliError::new(ErrorKind::Internal, format!("unknown config option {}", other))),
         }
-        if conf.depool_fee < 0.5 {
-            return Err("Minimal value for depool fee is 0.5".to_string());
+    }
+    store_config(&conf, path, dry_run)
+}
+
+fn parse_option<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, CliError>
+where
+    T::Err: std::fmt::Display,
+{
+    value.parse::<T>()
+        .map_err(|e| invalid_value(format!(r#"failed to parse "{}": {}"#, name, e)))
+}
+
+/// Sets parameters from `(option, value)` pairs of `SET_OPTIONS`.
+/// Options are applied in the order of `SET_OPTIONS`.
+pub fn set_config(mut conf: Config, path: &str, values: &[(&str, &str)], dry_run: bool) -> Result<(), CliError> {
+    let mut values = values.to_vec();
+    values.sort_by_key(|(option, _)| SET_OPTIONS.iter().position(|o| o == option));
+    for (option, value) in values {
+        match option {
+            "URL" => conf.url = value.to_string(),
+            "HEADER" => {
+                let mut parts = value.splitn(2, ':');
+                let name = parts.next().unwrap_or("").trim();
+                let value = parts.next()
+                    .ok_or(invalid_value(r#"header must be specified as "<name>: <value>""#))?
+                    .trim();
+                if name.is_empty() {
+                    return Err(invalid_value("header name is empty"));
+                }
+                let headers = conf.endpoint_headers.entry(conf.url.clone()).or_default();
+                if value.is_empty() {
+                    headers.remove(name);
+                } else {
+                    headers.insert(name.to_string(), value.to_string());
+                }
+                if headers.is_empty() {
+                    conf.endpoint_headers.remove(&conf.url);
+                }
+            },
+            "NETWORK" => {
+                let mut parts = value.splitn(2, '=');
+                let name = parts.next().unwrap_or("").trim();
+                let url = parts.next()
+                    .ok_or(invalid_value(r#"network must be specified as <name>=<url>"#))?
+                    .trim();
+                if name.is_empty() || name.contains(',') {
+                    return Err(invalid_value(format!(r#"invalid network name "{}""#, name)));
+                }
+                if url.is_empty() {
+                    conf.networks.remove(name);
+                } else {
+                    conf.networks.insert(name.to_string(), url.to_string());
+                }
+            },
+            "LANG" => {
+                crate::i18n::parse_lang(value).kind(ErrorKind::InvalidInput)?;
+                conf.lang = Some(value.to_string());
+            },
+            "DEBOT_SESSION_LIMIT" => {
+                let nanotokens = crate::convert::convert_token(value, conf.strict_units).kind(ErrorKind::InvalidInput)?;
+                conf.debot_session_limit = Some(parse_option("debot_session_limit", &nanotokens)?);
+            },
+            "PRICE_URL" => conf.price_url = Some(value.to_string()),
+            "PRICE_PATH" => conf.price_path = Some(value.to_string()),
+            "RELAY_URL" => conf.relay_url = if value.is_empty() { None } else { Some(value.to_string()) },
+            "FAUCET_URL" => {
+                if value.is_empty() {
+                    conf.faucets.remove(&conf.url);
+                } else {
+                    let api_key = conf.faucets.get(&conf.url).and_then(|f| f.api_key.clone());
+                    conf.faucets.insert(conf.url.clone(), FaucetSettings { url: value.to_string(), api_key });
+                }
+            },
+            "FAUCET_KEY" => {
+                let faucet = conf.faucets.get_mut(&conf.url)
+                    .ok_or(invalid_value("set faucet url for the endpoint first (--faucet_url)"))?;
+                faucet.api_key = if value.is_empty() { None } else { Some(value.to_string()) };
+            },
+            "MACRO" => {
+                match crate::macros::parse_definition(value).kind(ErrorKind::InvalidInput)? {
+                    (name, Some(def)) => { conf.macros.insert(name, def); },
+                    (name, None) => { conf.macros.remove(&name); },
+                }
+            },
+            "ADDR" => conf.addr = Some(value.to_string()),
+            "WALLET" => conf.wallet = Some(value.to_string()),
+            "ABI" => conf.abi_path = Some(value.to_string()),
+            "KEYS" => conf.keys_path = Some(value.to_string()),
+            "RETRIES" => conf.retries = parse_option("retries", value)?,
+            "TIMEOUT" => conf.timeout = parse_option("timeout", value)?,
+            "PROCESSING_TIMEOUT" => conf.processing_timeout = parse_option("processing_timeout", value)?,
+            "WAIT_FOR_TIMEOUT" => conf.wait_for_timeout = parse_option("wait_for_timeout", value)?,
+            "QUERY_TIMEOUT" => conf.query_timeout = parse_option("query_timeout", value)?,
+            "CLOCK_COMPENSATION" => conf.clock_compensation = parse_option("clock_compensation", value)?,
+            "STRICT_UNITS" => conf.strict_units = parse_option("strict_units", value)?,
+            "WC" => {
+                conf.wc = parse_option("workchain id", value)?;
+                if conf.wc < -1 || conf.wc > 255 {
+                    return Err(invalid_value(format!("invalid workchain id: {}", conf.wc)));
+                }
+            },
+            "DEPOOL_FEE" => conf.depool_fee = parse_option("depool_fee", value)?,
+            "ALIAS" => {
+                let mut parts = value.splitn(2, '=');
+                let name = parts.next().unwrap_or("").trim();
+                let address = parts.next()
+                    .ok_or(invalid_value(r#"alias must be specified as <name>=<address>"#))?
+                    .trim();
+                if name.is_empty() {
+                    return Err(invalid_value("alias name is empty"));
+                }
+                if address.is_empty() {
+                    conf.aliases.remove(name);
+                } else {
+                    conf.aliases.insert(name.to_string(), address.to_string());
+                }
+            },
+            "RATE_LIMIT" => conf.rate_limit = parse_option("rate_limit", value)?,
+            "CONCURRENCY" => {
+                conf.concurrency = parse_option("concurrency", value)?;
+                if conf.concurrency == 0 {
+                    return Err(invalid_value("concurrency must be greater than 0"));
+                }
+            },
+            "CACHE_DIR" => conf.cache_dir = Some(value.to_string()),
+            "CACHE_TTL" => conf.cache_ttl = parse_option("cache_ttl", value)?,
+            "CACHE_SIZE" => conf.cache_size = parse_option("cache_size", value)?,
+            other => return Err(CliError::new(ErrorKind::Internal, format!("unknown config option {}", other))),
         }
-        let conf_str = serde_json::to_string(&conf)
-            .map_err(|_| "failed to serialize config object".to_string())?;
+    }
+    if conf.depool_fee < 0.5 {
+        return Err(invalid_value("Minimal value for depool fee is 0.5"));
+    }
+    store_config(&conf, path, dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_env() {
+        std::env::set_var("TONOS_CLI_TEST_KEY", "secret");
+        assert_eq!(interpolate_env("Bearer ${TONOS_CLI_TEST_KEY}").unwrap(), "Bearer secret");
+        assert_eq!(interpolate_env("plain").unwrap(), "plain");
+        assert!(interpolate_env("${TONOS_CLI_TEST_KEY").is_err());
+        assert!(interpolate_env("${TONOS_CLI_TEST_UNDEFINED}").is_err());
+    }
+
+    #[test]
+    fn test_for_networks() {
+        let mut conf = Config::new();
+        conf.networks.insert("dev".to_owned(), "net.ton.dev".to_owned());
+        let configs = conf.for_networks("dev, https://main.ton.dev").unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!((configs[0].0.as_str(), configs[0].1.url.as_str()), ("dev", "net.ton.dev"));
+        assert_eq!(configs[1].1.url, "https://main.ton.dev");
+        assert!(conf.for_networks("main").is_err());
+    }
 
-        std::fs::write(path, conf_str).map_err(|e| format!("failed to write config file: {}", e))?;
-        println!("Succeeded.");
-        Ok(())
-    }
\ No newline at end of file
+    #[test]
+    fn test_diff_lines() {
+        let lines = |s: &str| s.lines().map(|l| l.to_owned()).collect::<Vec<_>>();
+        let diff = diff_lines(&lines("{\n\"url\": \"a\",\n\"wc\": 0\n}"), &lines("{\n\"url\": \"b\",\n\"wc\": 0\n}"));
+        let marks: String = diff.iter().map(|(mark, _)| *mark).collect();
+        assert_eq!(marks, " +-  ");
+        assert_eq!(diff[1].1, "\"url\": \"b\",");
+        assert_eq!(diff[2].1, "\"url\": \"a\",");
+    }
+
+    #[test]
+    fn test_set_and_clear_config() {
+        let path = std::env::temp_dir().join("tonos-cli-test-config.json");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        // header is stored for the new url although it is given first
+        set_config(Config::new(), path, &[("HEADER", "X-Key: 1"), ("URL", "net.ton.dev"), ("RETRIES", "7")], false).unwrap();
+        let conf = Config::load(path).unwrap().unwrap();
+        assert_eq!(conf.url, "net.ton.dev");
+        assert_eq!(conf.retries, 7);
+        assert_eq!(conf.endpoint_headers["net.ton.dev"]["X-Key"], "1");
+        assert!(set_config(conf.clone(), path, &[("RETRIES", "x")], false).is_err());
+        assert!(set_config(conf.clone(), path, &[("WC", "256")], false).is_err());
+
+        clear_config(conf, path, &["RETRIES"], false).unwrap();
+        let conf = Config::load(path).unwrap().unwrap();
+        assert_eq!(conf.retries, default_retries());
+        assert_eq!(conf.url, "net.ton.dev");
+
+        clear_config(conf, path, &[], false).unwrap();
+        let conf = Config::load(path).unwrap().unwrap();
+        assert_eq!(conf.url, default_url());
+        assert!(conf.endpoint_headers.is_empty());
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(backup_path(path));
+    }
+
+    #[test]
+    fn test_whitelist_format() {
+        let conf: Config = serde_json::from_str(r#"{"debot_signing_whitelist": {"0:11": ["*"]}}"#).unwrap();
+        assert!(conf.debot_signing_whitelist.is_empty());
+        let conf: Config = serde_json::from_str(
+            r#"{"debot_signing_whitelist": [{"address": "0:11", "code_hash": "aa", "actions": ["*"]}]}"#
+        ).unwrap();
+        assert_eq!(conf.debot_signing_whitelist[0].code_hash, "aa");
+    }
+}
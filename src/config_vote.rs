@@ -0,0 +1,303 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::crypto::load_keypair;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_local, create_client_verbose, load_ton_address, now};
+use crate::history;
+use crate::relay;
+use crate::multisig::send_with_body;
+use crate::system::CONFIG_ADDR;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use ton_block::{ExternalInboundMessageHeader, Message, MsgAddressInt, Serializable};
+use ton_client::crypto::{sign, ParamsOfSign};
+use ton_types::{BuilderData, Cell, IBitstring};
+
+const OP_NEW_PROPOSAL: u32 = 0x6e565052;
+const OP_VOTE: u32 = 0x566f7465;
+const PROPOSAL_TAG: u8 = 0xf3;
+
+/// Validator vote for config proposal, ready to be sent to config contract.
+#[derive(Serialize, Deserialize)]
+struct ProposalVote {
+    idx: u16,
+    phash: String,
+    signature: String,
+}
+
+pub fn create_config_vote_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("config-vote")
+        .about("Config parameters voting for validators.")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .subcommand(SubCommand::with_name("propose")
+            .about("Creates proposal to change config parameter and optionally sends it through multisig wallet.")
+            .arg(Arg::with_name("PARAM")
+                .long("--param")
+                .takes_value(true)
+                .required(true)
+                .allow_hyphen_values(true)
+                .help("Config parameter index."))
+            .arg(Arg::with_name("VALUE")
+                .long("--value")
+                .takes_value(true)
+                .help("Boc file with new parameter value. If omitted, parameter is removed."))
+            .arg(Arg::with_name("EXPIRE")
+                .long("--expire")
+                .takes_value(true)
+                .required(true)
+                .help("Unix time when the proposal expires."))
+            .arg(Arg::with_name("CRITICAL")
+                .long("--critical")
+                .help("Proposal changes critical parameter."))
+            .arg(Arg::with_name("WALLET")
+                .long("--wallet")
+                .takes_value(true)
+                .requires_all(&["SIGN", "PRICE"])
+                .help("Multisig wallet used to send the proposal to config contract."))
+            .arg(Arg::with_name("SIGN")
+                .long("--sign")
+                .takes_value(true)
+                .help("Seed phrase or path to wallet keypair file."))
+            .arg(Arg::with_name("PRICE")
+                .long("--price")
+                .takes_value(true)
+                .help("Value (in tokens) attached to the proposal to pay its storage price.")))
+        .subcommand(SubCommand::with_name("sign")
+            .about("Signs vote for the proposal with validator key and saves it to file.")
+            .arg(Arg::with_name("PHASH")
+                .long("--phash")
+                .takes_value(true)
+                .required(true)
+                .help("Proposal hash (hex)."))
+            .arg(Arg::with_name("IDX")
+                .long("--idx")
+                .takes_value(true)
+                .required(true)
+                .help("Index of the validator in current validator set."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .required(true)
+                .help("Validator keypair file or seed phrase."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--out")
+                .short("-o")
+                .takes_value(true)
+                .required(true)
+                .help("File where to store the vote.")))
+        .subcommand(SubCommand::with_name("submit")
+            .about("Sends signed votes to config contract.")
+            .arg(Arg::with_name("VOTES")
+                .required(true)
+                .takes_value(true)
+                .multiple(true)
+                .help("Files with signed votes.")))
+}
+
+pub async fn config_vote_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("propose") {
+        return propose_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("sign") {
+        return sign_command(m).map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("submit") {
+        return submit_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown config-vote command"))
+}
+
+fn cell_to_base64(cell: &Cell) -> Result<String, String> {
+    ton_types::serialize_toc(cell)
+        .map(|bytes| base64::encode(&bytes))
+        .map_err(|e| format!("failed to serialize cell: {}", e))
+}
+
+/// cfg_proposal#f3 param_id:int32 param_value:(Maybe ^Cell) if_hash_equal:(Maybe uint256)
+fn build_proposal(param: i32, value: Option<Cell>) -> ton_types::Result<Cell> {
+    let mut b = BuilderData::new();
+    b.append_u8(PROPOSAL_TAG)?;
+    b.append_i32(param)?;
+    match value {
+        Some(cell) => {
+            b.append_bit_one()?;
+            b.append_reference_cell(cell);
+        },
+        None => { b.append_bit_zero()?; },
+    }
+    b.append_bit_zero()?;
+    b.into_cell()
+}
+
+fn build_proposal_body(proposal: Cell, expire_at: u32, critical: bool) -> ton_types::Result<Cell> {
+    let mut b = BuilderData::new();
+    b.append_u32(OP_NEW_PROPOSAL)?;
+    b.append_u64(now() as u64)?;
+    b.append_u32(expire_at)?;
+    b.append_reference_cell(proposal);
+    if critical { b.append_bit_one()?; } else { b.append_bit_zero()?; }
+    b.into_cell()
+}
+
+async fn propose_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let param = i32::from_str_radix(m.value_of("PARAM").unwrap(), 10)
+        .map_err(|e| format!("failed to parse parameter index: {}", e))?;
+    let expire_at = u32::from_str_radix(m.value_of("EXPIRE").unwrap(), 10)
+        .map_err(|e| format!("failed to parse expire time: {}", e))?;
+    let value = m.value_of("VALUE")
+        .map(|path| {
+            let bytes = std::fs::read(path)
+                .map_err(|e| format!("failed to read parameter value: {}", e))?;
+            ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes))
+                .map_err(|e| format!("failed to parse parameter value boc: {}", e))
+        })
+        .transpose()?;
+
+    let proposal = build_proposal(param, value)
+        .map_err(|e| format!("failed to build proposal: {}", e))?;
+    let phash = proposal.repr_hash().to_hex_string();
+    let body = build_proposal_body(proposal, expire_at, m.is_present("CRITICAL"))
+        .map_err(|e| format!("failed to build proposal message: {}", e))?;
+    let body = cell_to_base64(&body)?;
+
+    println!("Proposal hash: {}", phash);
+    match m.value_of("WALLET") {
+        Some(wallet) => {
+            let wallet = load_ton_address(wallet, &config)?;
+            send_with_body(
+                config,
+                &wallet,
+                CONFIG_ADDR,
+                m.value_of("PRICE").unwrap(),
+                m.value_of("SIGN").unwrap(),
+                &body,
+            ).await
+        },
+        None => {
+            println!("Message body: {}", body);
+            Ok(())
+        },
+    }
+}
+
+fn parse_hash(value: &str) -> Result<Vec<u8>, String> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| format!("failed to parse hash: {}", e))?;
+    if bytes.len() != 32 {
+        return Err("hash must be 32 bytes long".to_owned());
+    }
+    Ok(bytes)
+}
+
+/// Vote data: op:uint32 idx:uint16 phash:uint256
+fn vote_data(idx: u16, phash: &[u8]) -> Vec<u8> {
+    let mut data = vec![];
+    data.extend_from_slice(&OP_VOTE.to_be_bytes());
+    data.extend_from_slice(&idx.to_be_bytes());
+    data.extend_from_slice(phash);
+    data
+}
+
+/// Validator signs hash of the cell with vote data, config contract checks
+/// the signature against hash of the message body after the signature.
+fn vote_hash(idx: u16, phash: &[u8]) -> Result<Vec<u8>, String> {
+    let data = vote_data(idx, phash);
+    let cell = (|| -> ton_types::Result<Cell> {
+        let mut b = BuilderData::new();
+        b.append_raw(&data, data.len() * 8)?;
+        b.into_cell()
+    })().map_err(|e| format!("failed to build vote cell: {}", e))?;
+    Ok(cell.repr_hash().as_slice().to_vec())
+}
+
+fn sign_command(m: &ArgMatches) -> Result<(), String> {
+    let idx = u16::from_str_radix(m.value_of("IDX").unwrap(), 10)
+        .map_err(|e| format!("failed to parse validator index: {}", e))?;
+    let phash = m.value_of("PHASH").unwrap();
+    let keys = load_keypair(m.value_of("KEYS").unwrap())?;
+    let output = m.value_of("OUTPUT").unwrap();
+
+    let hash = vote_hash(idx, &parse_hash(phash)?)?;
+    let signature = sign(
+        create_client_local()?,
+        ParamsOfSign { unsigned: base64::encode(&hash), keys },
+    )
+    .map_err(|e| format!("failed to sign vote: {}", e))?
+    .signature;
+
+    let vote = ProposalVote {
+        idx,
+        phash: phash.to_owned(),
+        signature,
+    };
+    std::fs::write(output, serde_json::to_string_pretty(&vote).unwrap())
+        .map_err(|e| format!("failed to write vote: {}", e))?;
+    println!("Vote saved to file {}", output);
+    Ok(())
+}
+
+fn build_vote_message(vote: &ProposalVote) -> Result<String, String> {
+    let signature = hex::decode(&vote.signature)
+        .map_err(|e| format!("failed to parse signature: {}", e))?;
+    let data = vote_data(vote.idx, &parse_hash(&vote.phash)?);
+    let body = (|| -> ton_types::Result<Cell> {
+        let mut b = BuilderData::new();
+        b.append_raw(&signature, signature.len() * 8)?;
+        b.append_raw(&data, data.len() * 8)?;
+        b.into_cell()
+    })().map_err(|e| format!("failed to build vote message: {}", e))?;
+
+    let dst = MsgAddressInt::from_str(CONFIG_ADDR)
+        .map_err(|e| format!("invalid config address: {}", e))?;
+    let mut msg = Message::with_ext_in_header(ExternalInboundMessageHeader {
+        dst,
+        ..Default::default()
+    });
+    msg.set_body(body.into());
+    let cell = msg.serialize()
+        .map_err(|e| format!("failed to serialize message: {}", e))?;
+    cell_to_base64(&cell)
+}
+
+async fn submit_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let ton = create_client_verbose(&config)?;
+    for path in m.values_of("VOTES").unwrap() {
+        let vote: ProposalVote = serde_json::from_str(
+            &std::fs::read_to_string(path).map_err(|e| format!("failed to read vote: {}", e))?
+        ).map_err(|e| format!("failed to parse vote: {}", e))?;
+        let message = build_vote_message(&vote)?;
+        let result = relay::broadcast(ton.clone(), &message, None).await
+        .map(|_| ())
+        .map_err(|e| format!("failed to send vote {}: {}", path, e));
+        history::record_message(ton.clone(), None, &message, &result).await;
+        result?;
+        println!("Vote of validator {} sent.", vote.idx);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vote_hash() {
+        let phash = [0x11u8; 32];
+        let hash = vote_hash(3, &phash).unwrap();
+        assert_eq!(hash.len(), 32);
+        assert_ne!(hash, vote_data(3, &phash));
+        assert_ne!(hash, vote_hash(4, &phash).unwrap());
+    }
+}
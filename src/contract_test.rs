@@ -0,0 +1,198 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::sandbox::{Sandbox, SandboxTransaction};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use std::path::Path;
+
+pub fn create_test_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("test")
+        .about("Contract unit tests executed in local sandbox.")
+        .subcommand(SubCommand::with_name("run")
+            .about("Runs tests from yaml file and prints pass/fail summary.")
+            .arg(Arg::with_name("TESTS")
+                .required(true)
+                .takes_value(true)
+                .help("Yaml file with tests. Every test declares accounts (initial states), a call and expected exit code, events and getter outputs. Calls may be interleaved with {advance: {seconds, lt}} directives, start time is set with \"now\" field."))
+            .arg(Arg::with_name("FILTER")
+                .long("--filter")
+                .takes_value(true)
+                .help("Runs only tests whose name contains the string.")))
+}
+
+pub async fn test_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("run") {
+        return run_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown test command"))
+}
+
+/// Compares expected value with actual one. Objects are compared as subsets:
+/// fields missing in expected value are not checked.
+pub fn diff_json(path: &str, expected: &Value, actual: &Value) -> Vec<String> {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected.iter()
+            .flat_map(|(key, value)| {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match actual.get(key) {
+                    Some(actual) => diff_json(&path, value, actual),
+                    None => vec![format!("{}: expected {}, got nothing", path, value)],
+                }
+            })
+            .collect(),
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => expected.iter()
+            .zip(actual.iter())
+            .enumerate()
+            .flat_map(|(i, (e, a))| diff_json(&format!("{}[{}]", path, i), e, a))
+            .collect(),
+        // numbers may be returned as strings by the decoder
+        (Value::Number(n), Value::String(s)) | (Value::String(s), Value::Number(n))
+            if n.to_string() == *s => vec![],
+        (expected, actual) if expected == actual => vec![],
+        (expected, actual) => vec![format!("{}: expected {}, got {}", path, expected, actual)],
+    }
+}
+
+/// Runs the test in a fresh sandbox and returns list of failed expectations.
+async fn run_test(config: &Config, shared: &Value, test: &Value, dir: &Path) -> Result<Vec<String>, String> {
+    let accounts = test_accounts(&shared["accounts"], test);
+    let mut sandbox = Sandbox::load(config, &accounts, dir).await?;
+    sandbox.verbose = false;
+    if let Some(now) = start_time(shared, test) {
+        sandbox.set_now(now);
+    }
+    let mut failures = vec![];
+    let mut transactions: Vec<SandboxTransaction> = vec![];
+    for call in call_list(&test["call"]) {
+        transactions.extend(sandbox.step(call, dir).await?);
+    }
+
+    let expect = &test["expect"];
+    let first = transactions.first();
+    if !expect["exit_code"].is_null() {
+        let actual = first.map(|t| t.exit_code.clone()).unwrap_or(Value::Null);
+        failures.extend(diff_json("exit_code", &expect["exit_code"], &actual));
+    }
+    if let Some(aborted) = expect["aborted"].as_bool() {
+        let actual = transactions.iter().any(|t| t.aborted);
+        if actual != aborted {
+            failures.push(format!("aborted: expected {}, got {}", aborted, actual));
+        }
+    }
+    if !expect["output"].is_null() {
+        let actual = first.and_then(|t| t.output.clone()).unwrap_or(Value::Null);
+        failures.extend(diff_json("output", &expect["output"], &actual));
+    }
+    for event in expect["events"].as_array().cloned().unwrap_or_default() {
+        let found = transactions.iter()
+            .flat_map(|t| t.events.iter())
+            .any(|actual| diff_json("", &event, actual).is_empty());
+        if !found {
+            failures.push(format!("event {} was not emitted", event));
+        }
+    }
+    for getter in expect["getters"].as_array().cloned().unwrap_or_default() {
+        let name = format!("{}.{}", getter["account"].as_str().unwrap_or(""), getter["method"].as_str().unwrap_or(""));
+        match sandbox.run_getter(&getter, dir).await {
+            Ok(actual) => failures.extend(diff_json(&name, &getter["output"], &actual)),
+            Err(e) => failures.push(format!("{}: {}", name, e)),
+        }
+    }
+    Ok(failures)
+}
+
+/// Test call may be a single call or a list of calls executed in order.
+fn call_list(call: &Value) -> Vec<&Value> {
+    match call {
+        Value::Array(calls) => calls.iter().collect(),
+        Value::Null => vec![],
+        call => vec![call],
+    }
+}
+
+/// Test start time overrides the one shared by all tests.
+fn start_time(shared: &Value, test: &Value) -> Option<u32> {
+    test["now"].as_u64().or(shared["now"].as_u64()).map(|now| now as u32)
+}
+
+/// Merges accounts shared by all tests with accounts overridden by the test.
+fn test_accounts(shared: &Value, test: &Value) -> Value {
+    let mut accounts = shared.as_object().cloned().unwrap_or_default();
+    for (name, account) in test["accounts"].as_object().cloned().unwrap_or_default() {
+        accounts.insert(name, account);
+    }
+    Value::Object(accounts)
+}
+
+async fn run_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let path = m.value_of("TESTS").unwrap();
+    let tests: Value = serde_yaml::from_str(
+        &std::fs::read_to_string(path).map_err(|e| format!("failed to read tests file: {}", e))?
+    ).map_err(|e| format!("failed to parse tests file: {}", e))?;
+    let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+    let filter = m.value_of("FILTER").unwrap_or("");
+
+    let mut passed = 0;
+    let mut failed = vec![];
+    for (i, test) in tests["tests"].as_array().cloned().unwrap_or_default().iter().enumerate() {
+        let name = test["name"].as_str().map(|s| s.to_owned()).unwrap_or(format!("test #{}", i + 1));
+        if !name.contains(filter) {
+            continue;
+        }
+        match run_test(&config, &tests, test, dir).await {
+            Ok(failures) if failures.is_empty() => {
+                println!("PASS {}", name);
+                passed += 1;
+            },
+            Ok(failures) => {
+                println!("FAIL {}", name);
+                for failure in failures {
+                    println!("    {}", failure);
+                }
+                failed.push(name);
+            },
+            Err(e) => {
+                println!("FAIL {}", name);
+                println!("    error: {}", e);
+                failed.push(name);
+            },
+        }
+    }
+    println!();
+    println!("{} passed, {} failed", passed, failed.len());
+    if !failed.is_empty() {
+        return Err(format!("failed tests: {}", failed.join(", ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_json() {
+        let actual = json!({ "balance": "100", "owner": { "key": "0x1", "index": 0 }, "list": [1, 2] });
+        assert!(diff_json("", &json!({ "balance": 100 }), &actual).is_empty());
+        assert!(diff_json("", &json!({ "owner": { "key": "0x1" }, "list": [1, 2] }), &actual).is_empty());
+        assert_eq!(
+            diff_json("", &json!({ "owner": { "key": "0x2" }, "missing": true }), &actual),
+            vec![
+                "missing: expected true, got nothing".to_owned(),
+                "owner.key: expected \"0x2\", got \"0x1\"".to_owned(),
+            ],
+        );
+    }
+}
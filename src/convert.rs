@@ -11,28 +11,175 @@
  * limitations under the License.
  */
 
+use crate::error::{CliError, ErrorKind, ResultExt};
 use sha2::{Sha256, Digest};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-pub fn convert_token(amount: &str) -> Result<String, String> {
-    let parts: Vec<&str> = amount.split(".").collect();
-    if parts.len() >= 1 && parts.len() <= 2 {
-        let mut result = String::new();
-        result += parts[0];
-        if parts.len() == 2 {
-            let fraction = format!("{:0<9}", parts[1]);
-            if fraction.len() != 9 {
-                return Err("invalid fractional part".to_string());
-            }
-            result += &fraction;
-        } else {
-            result += "000000000";
+/// Number of decimal places of a token.
+const TOKEN_DECIMALS: i32 = 9;
+
+/// Units accepted after the number, longer ones first so that `nanoton`
+/// is not taken for `ton`. Value is the number of decimal places of the unit.
+const UNITS: [(&str, i32); 10] = [
+    ("nanotokens", 0),
+    ("nanoevers", 0),
+    ("nanoever", 0),
+    ("nanotons", 0),
+    ("nanoton", 0),
+    ("evers", TOKEN_DECIMALS),
+    ("nano", 0),
+    ("ever", TOKEN_DECIMALS),
+    ("ton", TOKEN_DECIMALS),
+    ("t", TOKEN_DECIMALS),
+];
+
+static STRICT_UNITS: AtomicBool = AtomicBool::new(false);
+
+/// In strict mode amounts given by user must have a unit (config `strict_units`).
+pub fn set_strict_units(strict: bool) {
+    STRICT_UNITS.store(strict, Ordering::Relaxed);
+}
+
+pub fn strict_units() -> bool {
+    STRICT_UNITS.load(Ordering::Relaxed)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Amount {
+    Nano(u64),
+    /// Part of the current balance in billionths of percent.
+    Percent(u64),
+}
+
+impl Amount {
+    /// Returns amount in nanotokens, percent of the balance is rounded down.
+    pub fn to_nano(&self, balance: u64) -> u64 {
+        match self {
+            Amount::Nano(nano) => *nano,
+            Amount::Percent(part) =>
+                (balance as u128 * *part as u128 / (100 * 10u128.pow(TOKEN_DECIMALS as u32))) as u64,
+        }
+    }
+
+    /// Checks that the amount is the whole balance (100%).
+    pub fn is_all(&self) -> bool {
+        *self == Amount::Percent(100 * 10u64.pow(TOKEN_DECIMALS as u32))
+    }
+}
+
+/// Parses decimal number with optional exponent (`1.5`, `1e9`, `2.5E-3`) into
+/// an integer number of `10^-decimals` units.
+fn parse_decimal(number: &str, decimals: i32) -> Result<u64, String> {
+    let mut parts = number.splitn(2, |c| c == 'e' || c == 'E');
+    let mantissa = parts.next().unwrap_or("");
+    let exponent = match parts.next() {
+        Some(exp) => i32::from_str_radix(exp.trim_start_matches('+'), 10)
+            .map_err(|_| format!("invalid exponent in {}", number))?,
+        None => 0,
+    };
+    let (int, fraction) = match mantissa.find('.') {
+        Some(pos) => (&mantissa[..pos], &mantissa[pos + 1..]),
+        None => (mantissa, ""),
+    };
+    if (int.is_empty() && fraction.is_empty())
+        || !int.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(format!("invalid number {}", number));
+    }
+    let mut digits = format!("{}{}", int, fraction);
+    let shift = i32::try_from(fraction.len()).ok()
+        .and_then(|len| decimals.checked_add(exponent)?.checked_sub(len))
+        .ok_or_else(|| format!("invalid exponent in {}", number))?;
+    if shift > 20 {
+        return Err(format!("amount {} is too big", number));
+    }
+    if shift >= 0 {
+        digits.push_str(&"0".repeat(shift as usize));
+    } else {
+        let cut = digits.len().saturating_sub((-(shift as i64)) as usize);
+        if digits[cut..].chars().any(|c| c != '0') {
+            return Err(format!("amount {} is less than 1 nanotoken or has too many decimal places", number));
+        }
+        digits.truncate(cut);
+    }
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 10)
+        .map_err(|_| format!("amount {} is too big", number))
+}
+
+fn parse_amount_with(amount: &str, strict: bool) -> Result<Amount, String> {
+    let text = amount.trim().to_lowercase();
+    if text.starts_with('-') {
+        return Err(format!("amount can't be negative: {}", amount));
+    }
+    if text.contains(',') {
+        return Err(format!("invalid amount {}: use '.' as decimal separator", amount));
+    }
+    if let Some(number) = text.strip_suffix('%') {
+        let part = parse_decimal(number.trim(), TOKEN_DECIMALS)?;
+        if part > 100 * 10u64.pow(TOKEN_DECIMALS as u32) {
+            return Err(format!("percent of balance can't exceed 100%: {}", amount));
         }
-        u64::from_str_radix(&result, 10)
-            .map_err(|e| format!("failed to parse amount: {}", e))?;
-        
-        return Ok(result);
+        return Ok(Amount::Percent(part));
+    }
+    let unit = UNITS.iter().find(|(unit, _)| {
+        text.strip_suffix(unit)
+            .map(|number| number.trim_end().ends_with(|c: char| c.is_ascii_digit() || c == '.'))
+            .unwrap_or(false)
+    });
+    match unit {
+        Some((unit, decimals)) => {
+            let number = text[..text.len() - unit.len()].trim_end();
+            parse_decimal(number, *decimals).map(Amount::Nano)
+        },
+        None if strict => Err(format!(
+            "amount {} has no unit. Specify it as tokens (1.5 EVER, 10T), nanotokens (1e9 nano) or percent of balance (50%)",
+            amount,
+        )),
+        None => parse_decimal(&text, TOKEN_DECIMALS).map(Amount::Nano),
+    }
+}
+
+/// Parses amount given by user: `1.5`, `1.5 EVER`, `10T`, `1e9 nano` or
+/// percent of the current balance `50%`. Number without a unit is taken as
+/// tokens, in strict mode it is rejected.
+pub fn parse_amount(amount: &str) -> Result<Amount, CliError> {
+    parse_amount_with(amount, strict_units()).kind(ErrorKind::InvalidInput)
+}
+
+/// Converts amount given by user to nanotokens. Percent of balance is not accepted.
+pub fn amount_to_nano(amount: &str) -> Result<u64, CliError> {
+    match parse_amount(amount)? {
+        Amount::Nano(nano) => Ok(nano),
+        Amount::Percent(_) => Err(percent_not_supported(amount)),
+    }
+}
+
+/// Converts tokens to nanotokens. Units are accepted as well, with `strict`
+/// set they are required. Values computed by the program itself or with
+/// an explicit unit suffix are converted with `strict` unset.
+pub fn convert_token(amount: &str, strict: bool) -> Result<String, CliError> {
+    match parse_amount_with(amount, strict).kind(ErrorKind::InvalidInput)? {
+        Amount::Nano(nano) => Ok(nano.to_string()),
+        Amount::Percent(_) => Err(percent_not_supported(amount)),
     }
-    Err("Invalid amout value".to_string())
+}
+
+fn percent_not_supported(amount: &str) -> CliError {
+    CliError::new(ErrorKind::InvalidInput, format!("percent of balance is not supported here: {}", amount))
+}
+
+pub fn nano_to_tokens(nano: u64) -> String {
+    nano128_to_tokens(nano as u128)
+}
+
+/// Same as `nano_to_tokens` for values read from contracts, which may not fit u64.
+pub fn nano128_to_tokens(nano: u128) -> String {
+    format!("{}.{:09}", nano / 1_000_000_000, nano % 1_000_000_000)
 }
 
 pub fn nodeid_from_pubkey(key: &[u8]) -> Result<String, String> {
@@ -47,3 +194,47 @@ pub fn nodeid_from_pubkey(key: &[u8]) -> Result<String, String> {
 
     Ok(hex::encode(&hasher.result()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(convert_token("1.5", false).unwrap(), "1500000000");
+        assert_eq!(convert_token("0.000000001", false).unwrap(), "1");
+        assert!(convert_token("0.0000000001", false).is_err());
+        assert_eq!(convert_token("1.5 EVER", true).unwrap(), "1500000000");
+        assert_eq!(convert_token("1.5", true).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_amount_with("1.5 EVER", true).unwrap(), Amount::Nano(1_500_000_000));
+        assert_eq!(parse_amount_with("10T", true).unwrap(), Amount::Nano(10_000_000_000));
+        assert_eq!(parse_amount_with("2 ton", true).unwrap(), Amount::Nano(2_000_000_000));
+        assert_eq!(parse_amount_with("1e9 nano", true).unwrap(), Amount::Nano(1_000_000_000));
+        assert_eq!(parse_amount_with("15nanoton", true).unwrap(), Amount::Nano(15));
+        assert_eq!(parse_amount_with("2.5e-3 EVER", true).unwrap(), Amount::Nano(2_500_000));
+        assert!(parse_amount_with("1.5 nano", true).is_err());
+        assert!(parse_amount_with("1.5", true).is_err());
+        assert!(parse_amount_with("1,5 EVER", false).is_err());
+        assert!(parse_amount_with("-1 EVER", false).is_err());
+        assert!(parse_amount_with("EVER", false).is_err());
+        assert!(parse_amount_with("1e30 EVER", false).is_err());
+        assert!(parse_amount_with("1e2147483647 EVER", false).is_err());
+        assert!(parse_amount_with("1.5e-2147483648", false).is_err());
+        assert!(parse_amount_with("1e-2147483648", false).is_err());
+        assert_eq!(parse_amount_with("0e-2147483648", false).unwrap(), Amount::Nano(0));
+
+        let half = parse_amount_with("50%", true).unwrap();
+        assert_eq!(half.to_nano(3_000_000_001), 1_500_000_000);
+        assert_eq!(parse_amount_with("12.5 %", true).unwrap().to_nano(8), 1);
+        assert!(parse_amount_with("101%", true).is_err());
+        assert!(parse_amount_with("100 %", true).unwrap().is_all());
+        assert!(!half.is_all());
+        assert_eq!(convert_token("50%", false).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_nano_to_tokens() {
+        assert_eq!(nano_to_tokens(1_500_000_000), "1.500000000");
+        assert_eq!(nano128_to_tokens(u64::MAX as u128 + 1), "18446744073.709551616");
+    }
+}
@@ -10,7 +10,15 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
-use crate::helpers::{create_client_local, read_keys, WORD_COUNT, HD_PATH};
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_local, read_keys, load_abi, calc_acc_address, TonClient, WORD_COUNT, HD_PATH};
+use crate::i18n::tr;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use std::sync::Mutex;
+use unicode_normalization::UnicodeNormalization;
 use ton_client::crypto::{
     KeyPair,
     mnemonic_from_random,
@@ -25,8 +33,117 @@ use ton_client::crypto::{
     ParamsOfMnemonicFromRandom
 };
 
+/// Value of keys argument which means that seed phrase must be taken from
+/// `--phrase-file`/`--phrase-fd` or entered in terminal without echo.
+pub const SECRET_PHRASE_INPUT: &str = "-";
+
+/// Version bytes of serialized BIP-32 private key (xprv).
+const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+const BIP39_ROUNDS: usize = 2048;
+
+lazy_static! {
+    static ref SECRET_PHRASE: Mutex<Option<String>> = Mutex::new(None);
+    /// `None` means the passphrase is read from terminal when it's needed first.
+    static ref MNEMONIC_PASSPHRASE: Mutex<Option<String>> = Mutex::new(Some(String::new()));
+}
+
+#[cfg(unix)]
+fn read_fd(fd: &str) -> Result<String, String> {
+    use std::io::Read;
+    use std::os::unix::io::FromRawFd;
+    let fd = i32::from_str_radix(fd, 10)
+        .map_err(|e| format!("failed to parse file descriptor: {}", e))?;
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    let mut phrase = String::new();
+    file.read_to_string(&mut phrase)
+        .map_err(|e| format!("failed to read seed phrase from file descriptor: {}", e))?;
+    Ok(phrase)
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: &str) -> Result<String, String> {
+    Err("reading seed phrase from file descriptor is supported only on unix systems".to_owned())
+}
+
+/// Writes file with secret keys readable only by the owner. Permissions of an
+/// existing file are restricted as well.
+#[cfg(unix)]
+pub fn write_secret_file(path: &str, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(data)
+}
+
+#[cfg(not(unix))]
+pub fn write_secret_file(path: &str, data: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, data)
+}
+
+/// Reads seed phrase from file or file descriptor once at startup so that
+/// it doesn't appear in process arguments or shell history.
+pub fn set_secret_phrase_source(file: Option<&str>, fd: Option<&str>) -> Result<(), String> {
+    let phrase = match (file, fd) {
+        (Some(path), _) => std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read seed phrase file: {}", e))?,
+        (None, Some(fd)) => read_fd(fd)?,
+        (None, None) => return Ok(()),
+    };
+    *SECRET_PHRASE.lock().unwrap() = Some(phrase.trim().to_owned());
+    Ok(())
+}
+
+/// Sets BIP-39 passphrase ("25th word") applied to every seed phrase, so that
+/// keys of hidden wallets protected by it can be derived. `-` means the
+/// passphrase is entered in terminal without echo, it's asked only if the
+/// command derives keys from a seed phrase.
+pub fn set_mnemonic_passphrase(passphrase: Option<&str>) {
+    let passphrase = match passphrase {
+        Some(SECRET_PHRASE_INPUT) => None,
+        Some(passphrase) => Some(passphrase.to_owned()),
+        None => return,
+    };
+    *MNEMONIC_PASSPHRASE.lock().unwrap() = passphrase;
+}
+
+pub fn has_mnemonic_passphrase() -> bool {
+    MNEMONIC_PASSPHRASE.lock().unwrap().as_ref().map(|p| !p.is_empty()).unwrap_or(true)
+}
+
+fn mnemonic_passphrase() -> Result<String, String> {
+    let mut passphrase = MNEMONIC_PASSPHRASE.lock().unwrap();
+    if passphrase.is_none() {
+        // spaces are significant in passphrase, so it isn't trimmed
+        *passphrase = Some(rpassword::read_password_from_tty(Some("BIP-39 passphrase: "))
+            .map_err(|e| format!("failed to read from terminal: {}", e))?);
+    }
+    Ok(passphrase.clone().unwrap_or_default())
+}
+
+/// Reads secret value from terminal without echo.
+pub fn read_secret(prompt: &str) -> Result<String, String> {
+    rpassword::read_password_from_tty(Some(prompt))
+        .map(|s| s.trim().to_owned())
+        .map_err(|e| format!("failed to read from terminal: {}", e))
+}
+
+pub fn secret_phrase() -> Result<String, String> {
+    if let Some(phrase) = SECRET_PHRASE.lock().unwrap().clone() {
+        return Ok(phrase);
+    }
+    read_secret("Seed phrase: ")
+}
+
 pub fn load_keypair(keys: &str) -> Result<KeyPair, String> {
-    if keys.find(' ').is_none() {
+    if keys == SECRET_PHRASE_INPUT {
+        generate_keypair_from_mnemonic(&secret_phrase()?)
+    } else if keys.find(' ').is_none() {
         let keys = read_keys(&keys)?;
         Ok(keys)
     } else {
@@ -50,21 +167,70 @@ pub fn gen_seed_phrase() -> Result<String, String> {
 
 pub fn generate_keypair_from_mnemonic(mnemonic: &str) -> Result<KeyPair, String> {
     let client = create_client_local()?;
-    let hdk_master = hdkey_xprv_from_mnemonic(
-        client.clone(),
+    let xprv = master_xprv_from_mnemonic(client.clone(), mnemonic)?;
+    keypair_from_xprv(client, xprv, HD_PATH)
+}
+
+/// Derives keys by several HD paths, the master key is derived from the phrase once.
+pub fn derive_keypairs(mnemonic: &str, paths: &[String]) -> Result<Vec<KeyPair>, String> {
+    let client = create_client_local()?;
+    let xprv = master_xprv_from_mnemonic(client.clone(), mnemonic)?;
+    paths.iter()
+        .map(|path| keypair_from_xprv(client.clone(), xprv.clone(), path))
+        .collect()
+}
+
+fn master_xprv_from_mnemonic(client: TonClient, mnemonic: &str) -> Result<String, String> {
+    // SDK also validates the phrase, its key is used as is if there is no passphrase
+    let xprv = hdkey_xprv_from_mnemonic(
+        client,
         ParamsOfHDKeyXPrvFromMnemonic {
             dictionary: Some(1),
             word_count: Some(WORD_COUNT),
             phrase: mnemonic.to_string(),
             ..Default::default()
         },
-    ).map_err(|e| format!("{}", e))?;
+    )
+    .map_err(|e| format!("{}", e))
+    .map(|r| r.xprv)?;
+    let passphrase = mnemonic_passphrase()?;
+    if passphrase.is_empty() {
+        return Ok(xprv);
+    }
+    Ok(xprv_from_seed(&bip39_seed(mnemonic, &passphrase)))
+}
 
+/// BIP-39 seed: PBKDF2-HMAC-SHA512 of the phrase salted with "mnemonic" and passphrase.
+fn bip39_seed(mnemonic: &str, passphrase: &str) -> [u8; 64] {
+    let phrase: String = mnemonic.nfkd().collect();
+    let salt: String = format!("mnemonic{}", passphrase).nfkd().collect();
+    let mut seed = [0u8; 64];
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(phrase.as_bytes(), salt.as_bytes(), BIP39_ROUNDS, &mut seed);
+    seed
+}
+
+/// BIP-32 master key of the seed serialized as xprv.
+fn xprv_from_seed(seed: &[u8]) -> String {
+    let mut mac = Hmac::<Sha512>::new_varkey(b"Bitcoin seed").unwrap();
+    mac.input(seed);
+    let master = mac.result().code();
+    let mut data = Vec::with_capacity(78);
+    data.extend_from_slice(&XPRV_VERSION);
+    data.push(0); // depth
+    data.extend_from_slice(&[0; 4]); // parent fingerprint
+    data.extend_from_slice(&[0; 4]); // child number
+    data.extend_from_slice(&master[32..]); // chain code
+    data.push(0);
+    data.extend_from_slice(&master[..32]); // private key
+    bs58::encode(data).with_check().into_string()
+}
+
+fn keypair_from_xprv(client: TonClient, xprv: String, path: &str) -> Result<KeyPair, String> {
     let hdk_root = hdkey_derive_from_xprv_path(
         client.clone(),
         ParamsOfHDKeyDeriveFromXPrvPath {
-            xprv: hdk_master.xprv,
-            path: HD_PATH.to_string(),
+            xprv,
+            path: path.to_string(),
             ..Default::default()
         },
     ).map_err(|e| format!("{}", e))?;
@@ -95,14 +261,14 @@ pub fn generate_keypair_from_mnemonic(mnemonic: &str) -> Result<KeyPair, String>
 
 pub fn generate_mnemonic() -> Result<(), String> {
     let mnemonic = gen_seed_phrase()?;
-    println!("Succeeded.");
+    println!("{}", tr("Succeeded."));
     println!(r#"Seed phrase: "{}""#, mnemonic);
     Ok(())
 }
 
 pub fn extract_pubkey(mnemonic: &str) -> Result<(), String> {
     let keypair = generate_keypair_from_mnemonic(mnemonic)?;
-    println!("Succeeded.");
+    println!("{}", tr("Succeeded."));
     println!("Public key: {}", keypair.public);
     println!();
     qr2term::print_qr(&keypair.public).unwrap();
@@ -113,9 +279,114 @@ pub fn extract_pubkey(mnemonic: &str) -> Result<(), String> {
 pub fn generate_keypair(keys_path: &str, mnemonic: &str) -> Result<(), String> {
     let keys = generate_keypair_from_mnemonic(mnemonic)?;
     let keys_json = serde_json::to_string_pretty(&keys).unwrap();
-    std::fs::write(keys_path, &keys_json)
+    write_secret_file(keys_path, keys_json.as_bytes())
         .map_err(|e| format!("failed to create file with keys: {}", e))?;
-    println!("Succeeded.");
+    println!("{}", tr("Succeeded."));
+    Ok(())
+}
+
+pub fn create_crypto_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("crypto")
+        .about("Crypto commands.")
+        .subcommand(SubCommand::with_name("derive")
+            .about("Prints a table of keys derived from seed phrase by HD path (and corresponding contract addresses if tvc is supplied).")
+            .arg(Arg::with_name("PHRASE")
+                .long("--phrase")
+                .takes_value(true)
+                .help("Seed phrase (12 words). If omitted or \"-\", it's read from --phrase-file/--phrase-fd (--phrase-fd 0 for stdin) or entered without echo."))
+            .arg(Arg::with_name("PATH")
+                .long("--path")
+                .takes_value(true)
+                .help("Derivation path. Can contain a range of indexes, e.g. \"m/44'/396'/0'/0/{0..9}\"."))
+            .arg(Arg::with_name("TVC")
+                .long("--tvc")
+                .takes_value(true)
+                .requires("ABI")
+                .help("Wallet contract tvc file used to calculate addresses."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Wallet contract ABI file."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .help("Workchain id of the wallet address (default from config).")))
+}
+
+pub async fn crypto_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("derive") {
+        return derive_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown crypto command"))
+}
+
+/// Expands derivation path with range of indexes ("{from..to}", bounds inclusive)
+/// into the list of paths.
+pub fn expand_hd_path(path: &str) -> Result<Vec<String>, String> {
+    let start = match path.find('{') {
+        Some(pos) => pos,
+        None => return Ok(vec![path.to_owned()]),
+    };
+    let end = path[start..].find('}')
+        .map(|pos| start + pos)
+        .ok_or(format!("invalid path range: closing brace not found"))?;
+    let range = &path[start + 1..end];
+    let mut bounds = range.splitn(2, "..");
+    let parse_bound = |s: Option<&str>| {
+        s.ok_or(format!("invalid path range: {}", range))
+            .and_then(|s| u32::from_str_radix(s.trim(), 10).map_err(|e| format!("invalid path range {}: {}", range, e)))
+    };
+    let from = parse_bound(bounds.next())?;
+    let to = parse_bound(bounds.next())?;
+    if from > to {
+        return Err(format!("invalid path range: {}", range));
+    }
+    let mut paths = vec![];
+    for i in from..=to {
+        let suffix = expand_hd_path(&path[end + 1..])?;
+        for tail in suffix {
+            paths.push(format!("{}{}{}", &path[..start], i, tail));
+        }
+    }
+    Ok(paths)
+}
+
+async fn derive_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let phrase = match m.value_of("PHRASE") {
+        Some(phrase) if phrase != SECRET_PHRASE_INPUT => phrase.to_owned(),
+        _ => secret_phrase()?,
+    };
+    let path = m.value_of("PATH").unwrap_or(HD_PATH);
+    let wc = m.value_of("WC")
+        .map(|wc| i32::from_str_radix(wc, 10))
+        .transpose()
+        .map_err(|e| format!("failed to parse workchain id: {}", e))?
+        .unwrap_or(config.wc);
+    let contract = match (m.value_of("TVC"), m.value_of("ABI")) {
+        (Some(tvc), Some(abi)) => {
+            let tvc = std::fs::read(tvc)
+                .map_err(|e| format!("failed to read smart contract file: {}", e))?;
+            let abi = std::fs::read_to_string(abi)
+                .map_err(|e| format!("failed to read ABI file: {}", e))?;
+            Some((tvc, load_abi(&abi)?))
+        },
+        _ => None,
+    };
+
+    let paths = expand_hd_path(path)?;
+    let client = create_client_local()?;
+    let xprv = master_xprv_from_mnemonic(client.clone(), &phrase)?;
+    for path in paths {
+        let keys = keypair_from_xprv(client.clone(), xprv.clone(), &path)?;
+        match &contract {
+            Some((tvc, abi)) => {
+                let addr = calc_acc_address(tvc, wc, keys.public.clone(), None, abi.clone()).await?;
+                println!("{:<24} {} {}", path, keys.public, addr);
+            },
+            None => println!("{:<24} {}", path, keys.public),
+        }
+    }
     Ok(())
 }
 
@@ -123,6 +394,21 @@ pub fn generate_keypair(keys_path: &str, mnemonic: &str) -> Result<(), String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_expand_hd_path() {
+        assert_eq!(expand_hd_path(HD_PATH).unwrap(), vec![HD_PATH]);
+        assert_eq!(
+            expand_hd_path("m/44'/396'/0'/0/{0..2}").unwrap(),
+            vec!["m/44'/396'/0'/0/0", "m/44'/396'/0'/0/1", "m/44'/396'/0'/0/2"]
+        );
+        assert_eq!(
+            expand_hd_path("m/44'/396'/{0..1}'/0/{3..4}").unwrap(),
+            vec!["m/44'/396'/0'/0/3", "m/44'/396'/0'/0/4", "m/44'/396'/1'/0/3", "m/44'/396'/1'/0/4"]
+        );
+        assert!(expand_hd_path("m/44'/396'/0'/0/{2..1}").is_err());
+        assert!(expand_hd_path("m/44'/396'/0'/0/{2..").is_err());
+    }
+
     #[test]
     fn test_generate_keypair() {
         let mnemonic = "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist";
@@ -136,6 +422,27 @@ mod tests {
         assert_eq!(&keypair.secret, "f63d3d11e0dc91f730f22d5397f269e01f1a5f984879c8581ac87f099bfd3b3a");
     }
 
+    #[test]
+    fn test_mnemonic_passphrase() {
+        let mnemonic = "multiply extra monitor fog rocket defy attack right night jaguar hollow enlist";
+        let client = create_client_local().unwrap();
+        let sdk_xprv = master_xprv_from_mnemonic(client.clone(), mnemonic).unwrap();
+        assert_eq!(xprv_from_seed(&bip39_seed(mnemonic, "")), sdk_xprv);
+
+        // BIP-39 test vector with "TREZOR" passphrase
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = bip39_seed(mnemonic, "TREZOR");
+        assert_eq!(
+            hex::encode(&seed[..]),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        );
+        assert_eq!(
+            xprv_from_seed(&seed),
+            "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF",
+        );
+        assert!(keypair_from_xprv(client, xprv_from_seed(&seed), HD_PATH).is_ok());
+    }
+
     #[test]
     fn test_invalid_mnemonic() {
         let invalid_phrases = vec![
@@ -0,0 +1,162 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+use crate::config::Config;
+use crate::i18n::{tr, trf};
+use crate::convert::nano_to_tokens;
+use crate::exit_codes::is_expiration_error;
+use crate::helpers::{query, TonClient};
+use std::sync::Arc;
+
+/// Budget shared by signing boxes of all debots in the session.
+pub(super) type SharedBudget = Arc<tokio::sync::Mutex<SessionBudget>>;
+
+/// Spending limit for all debots running in one browser session.
+///
+/// NOTE: SDK doesn't show browser the value of messages sent by debots
+/// (a signing box gets only the data hash), so spendings are tracked as
+/// decreases of the configured wallet balance observed before every signature.
+/// Once the limit is exceeded every further signature needs explicit override.
+#[derive(Default)]
+pub(super) struct SessionBudget {
+    limit: Option<u64>,
+    wallet: Option<String>,
+    last_balance: Option<u64>,
+    spent: u64,
+    /// Number of messages signed in the session.
+    signed: u64,
+}
+
+impl SessionBudget {
+    pub fn new(conf: &Config) -> Self {
+        let limit = conf.debot_session_limit;
+        if limit.is_some() && conf.wallet.is_none() {
+            debot_println!("{}", tr("WARNING! Debot session limit is set, but wallet is not configured (see config --wallet), spendings are not tracked."));
+        }
+        Self { limit, wallet: conf.wallet.clone(), last_balance: None, spent: 0, signed: 0 }
+    }
+
+    /// Accounts balance decrease since the previous observation.
+    /// Incoming transfers don't compensate already spent value.
+    fn observe(&mut self, balance: u64) {
+        if let Some(last) = self.last_balance {
+            self.spent = self.spent.saturating_add(last.saturating_sub(balance));
+        }
+        self.last_balance = Some(balance);
+    }
+
+    /// Value of the message being signed is unknown, so the limit is treated
+    /// as exceeded once it's reached: any further signature may overspend it.
+    fn exceeded(&self) -> bool {
+        self.limit.map(|limit| self.spent >= limit).unwrap_or(false)
+    }
+
+    pub fn record_signature(&mut self) {
+        self.signed += 1;
+    }
+
+    fn warning(&self, wallet: &str) -> String {
+        trf(
+            "WARNING! Debots have spent {} from wallet {} in this session, limit is {}.",
+            &[&nano_to_tokens(self.spent), &wallet, &nano_to_tokens(self.limit.unwrap_or(0))],
+        )
+    }
+}
+
+/// Decides if debot action failed with expired message can be run again.
+/// Expired message wasn't delivered, but the action is resent only if nothing
+/// else was signed during the attempt, otherwise the retry would repeat
+/// messages which have already been delivered.
+pub(super) struct ResendPolicy {
+    budget: SharedBudget,
+    retries: u8,
+    resent: u8,
+    signed_before: u64,
+}
+
+impl ResendPolicy {
+    pub fn new(budget: SharedBudget, retries: u8) -> Self {
+        Self { budget, retries, resent: 0, signed_before: 0 }
+    }
+
+    pub fn retries(&self) -> u8 {
+        self.retries
+    }
+
+    /// Must be called before every attempt.
+    pub async fn start_attempt(&mut self) {
+        self.signed_before = self.budget.lock().await.signed;
+    }
+
+    /// Returns number of the resend if the action can be run again.
+    pub async fn next(&mut self, error: &str) -> Option<u8> {
+        if !is_expiration_error(error) || self.resent >= self.retries {
+            return None;
+        }
+        let signed = self.budget.lock().await.signed;
+        if signed.saturating_sub(self.signed_before) > 1 {
+            return None;
+        }
+        self.resent += 1;
+        Some(self.resent)
+    }
+}
+
+/// Updates spendings before debot gets a signature. Returns a warning if the
+/// limit is reached, then the signature needs explicit override. The budget is
+/// not locked while the wallet balance is queried.
+pub(super) async fn check_budget(budget: &SharedBudget, client: TonClient) -> Result<Option<String>, String> {
+    let wallet = {
+        let budget = budget.lock().await;
+        match (&budget.limit, &budget.wallet) {
+            (Some(_), Some(wallet)) => wallet.clone(),
+            _ => return Ok(None),
+        }
+    };
+    let accounts = query(
+        client,
+        "accounts",
+        json!({ "id": { "eq": wallet } }),
+        "balance(format: DEC)",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query wallet balance: {}", e))?;
+    let balance = accounts.first()
+        .and_then(|acc| acc["balance"].as_str())
+        .and_then(|b| u64::from_str_radix(b, 10).ok())
+        .unwrap_or(0);
+    let mut budget = budget.lock().await;
+    budget.observe(balance);
+    Ok(if budget.exceeded() { Some(budget.warning(&wallet)) } else { None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_budget() {
+        let mut budget = SessionBudget { limit: Some(1_000), wallet: None, last_balance: None, spent: 0, signed: 0 };
+        budget.observe(5_000);
+        assert_eq!(budget.spent, 0);
+        budget.observe(4_400);
+        budget.observe(6_000);
+        assert_eq!(budget.spent, 600);
+        assert!(!budget.exceeded());
+        budget.observe(5_600);
+        assert_eq!(budget.spent, 1_000);
+        assert!(budget.exceeded());
+        budget.observe(5_000);
+        assert_eq!(budget.spent, 1_600);
+        assert!(budget.exceeded());
+    }
+}
@@ -0,0 +1,148 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+//! On-disk cache of debot ABIs keyed by debot code hash. Debots with the same
+//! code have the same ABI, so it is downloaded once and then passed to the
+//! engine instead of being fetched on every start or invoke.
+//!
+//! Debot state is cached only for the session (initialized engines of invoked
+//! debots are kept by address): the engine always loads the account itself and
+//! the state changes with every debot transaction.
+use super::trust_store::query_code_hash;
+use crate::call::run_local;
+use crate::helpers::TonClient;
+use crate::pretty::parse_uint;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const CACHE_DIR_NAME: &str = ".tonos-cli/debot-cache";
+/// `getDebotOptions` flag: debot provides its ABI.
+const OPTION_ABI: u128 = 1;
+
+const DEBOT_OPTIONS_ABI: &str = r#"{
+    "ABI version": 2,
+    "header": ["pubkey", "time", "expire"],
+    "functions": [
+        {
+            "name": "getDebotOptions",
+            "inputs": [],
+            "outputs": [
+                {"name":"options","type":"uint8"},
+                {"name":"debotAbi","type":"bytes"},
+                {"name":"targetAbi","type":"bytes"},
+                {"name":"targetAddr","type":"address"}
+            ]
+        }
+    ],
+    "data": [],
+    "events": []
+}"#;
+
+lazy_static! {
+    static ref CACHE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+pub fn default_cache_dir() -> PathBuf {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(CACHE_DIR_NAME)
+}
+
+/// Enables the disk cache. Without it ABIs are cached only for the session.
+pub fn init_disk_cache(dir: PathBuf) {
+    *CACHE_DIR.lock().unwrap() = Some(dir);
+}
+
+fn abi_file(dir: &Path, code_hash: &str) -> PathBuf {
+    dir.join(format!("{}.abi.json", code_hash))
+}
+
+/// Extracts debot ABI from `getDebotOptions` output.
+fn decode_debot_abi(options: &Value) -> Result<Option<String>, String> {
+    let flags = parse_uint(&options["options"])
+        .ok_or("invalid debot options".to_owned())?;
+    if flags & OPTION_ABI == 0 {
+        return Ok(None);
+    }
+    let abi = options["debotAbi"].as_str()
+        .ok_or("debot ABI is missing in debot options".to_owned())?;
+    let abi = hex::decode(abi)
+        .map_err(|e| format!("failed to decode debot ABI: {}", e))?;
+    let abi = String::from_utf8(abi)
+        .map_err(|e| format!("debot ABI is not a valid string: {}", e))?;
+    serde_json::from_str::<Value>(&abi)
+        .map_err(|e| format!("debot ABI is not a valid json: {}", e))?;
+    Ok(Some(abi))
+}
+
+async fn fetch_debot_abi(ton: TonClient, debot: &str) -> Result<Option<String>, String> {
+    let options = run_local(ton, debot, DEBOT_OPTIONS_ABI, "getDebotOptions", "{}", true).await?;
+    decode_debot_abi(&options)
+}
+
+/// Returns debot ABI from the disk cache, downloading and saving it on a miss.
+/// Returns `None` if the cache is disabled or ABI can't be obtained: the engine
+/// then fetches ABI itself.
+pub async fn load_debot_abi(ton: TonClient, debot: &str) -> Option<String> {
+    let dir = CACHE_DIR.lock().unwrap().clone()?;
+    let result: Result<Option<String>, String> = async {
+        let code_hash = query_code_hash(ton.clone(), debot).await?;
+        let path = abi_file(&dir, &code_hash);
+        if let Ok(abi) = std::fs::read_to_string(&path) {
+            debug!("debot {} ABI is loaded from cache {}", debot, path.display());
+            return Ok(Some(abi));
+        }
+        let abi = fetch_debot_abi(ton, debot).await?;
+        if let Some(abi) = &abi {
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("failed to create directory {}: {}", dir.display(), e))?;
+            std::fs::write(&path, abi)
+                .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        }
+        Ok(abi)
+    }.await;
+    result.unwrap_or_else(|e| {
+        debug!("debot {} ABI is not cached: {}", debot, e);
+        None
+    })
+}
+
+/// Returns debot ABI from the disk cache if it is enabled, otherwise downloads it.
+pub async fn get_debot_abi(ton: TonClient, debot: &str) -> Option<String> {
+    if let Some(abi) = load_debot_abi(ton.clone(), debot).await {
+        return Some(abi);
+    }
+    fetch_debot_abi(ton, debot).await.unwrap_or_else(|e| {
+        debug!("failed to load debot {} ABI: {}", debot, e);
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_debot_abi() {
+        let abi = r#"{"ABI version": 2}"#;
+        let options = json!({ "options": "1", "debotAbi": hex::encode(abi), "targetAbi": "", "targetAddr": "0:00" });
+        assert_eq!(decode_debot_abi(&options).unwrap(), Some(abi.to_owned()));
+        assert_eq!(decode_debot_abi(&json!({ "options": "0", "debotAbi": "" })).unwrap(), None);
+        assert!(decode_debot_abi(&json!({ "options": "1", "debotAbi": hex::encode("not json") })).is_err());
+        assert_eq!(
+            abi_file(Path::new("/tmp/cache"), "ab12"),
+            PathBuf::from("/tmp/cache/ab12.abi.json"),
+        );
+    }
+}
@@ -1,12 +1,14 @@
-use crate::debot::term_browser::terminal_input;
+use crate::debot::line_editor::Completion;
+use crate::debot::term_browser::terminal_input_with;
 use crate::helpers::load_ton_address;
+use crate::i18n::trf;
 use serde_json::Value;
 use ton_client::abi::Abi;
 use ton_client::debot::{DebotInterface, InterfaceResult};
 use super::dinterface::decode_answer_id;
 use crate::config::Config;
 
-const ID: &'static str = "d7ed1bd8e6230871116f4522e58df0a93c5520c56f4ade23ef3d8919a984653b";
+pub const ID: &'static str = "d7ed1bd8e6230871116f4522e58df0a93c5520c56f4ade23ef3d8919a984653b";
 
 pub const ABI: &str = r#"
 {
@@ -44,13 +46,20 @@ impl AddressInput {
     pub fn new(conf: Config) -> Self {
         Self {conf}
     }
-    fn select(&self, args: &Value) -> InterfaceResult {
+    async fn select(&self, args: &Value) -> InterfaceResult {
         let answer_id = decode_answer_id(args)?;
-        let value = terminal_input("", |val| {
-            let _ = load_ton_address(val, &self.conf).map_err(|e| format!("Invalid address: {}", e))?;
+        let completion = Completion::Words(
+            self.conf.aliases.iter()
+                .map(|(name, addr)| (name.clone(), addr.clone()))
+                .collect()
+        );
+        let mut address = String::new();
+        let _ = terminal_input_with("", completion, |val| {
+            let val = self.conf.aliases.get(val).unwrap_or(val);
+            address = load_ton_address(val, &self.conf).map_err(|e| trf("Invalid address: {}", &[&e]))?;
             Ok(())
-        });
-        Ok((answer_id, json!({ "value": value })))
+        }).await;
+        Ok((answer_id, json!({ "value": address })))
     }
 }
 
@@ -66,7 +75,7 @@ impl DebotInterface for AddressInput {
 
     async fn call(&self, func: &str, args: &Value) -> InterfaceResult {
         match func {
-            "select" => self.select(args),
+            "select" => self.select(args).await,
             _ => Err(format!("function \"{}\" is not implemented", func)),
         }
     }
@@ -3,8 +3,10 @@ use super::echo::Echo;
 use super::stdout::Stdout;
 use super::terminal::Terminal;
 use super::menu::Menu;
+use super::plugin::{enabled_plugins_dir, load_plugins};
 use crate::config::Config;
 use crate::helpers::TonClient;
+use crate::i18n::trf;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -44,8 +46,32 @@ impl SupportedInterfaces {
         let iface: Arc<dyn DebotInterface + Send + Sync> = Arc::new(Menu::new());
         interfaces.insert(iface.get_id(), iface);
 
+        let plugins = enabled_plugins_dir()
+            .map(|dir| load_plugins(&dir))
+            .unwrap_or_default();
+        for plugin in plugins {
+            let iface: Arc<dyn DebotInterface + Send + Sync> = Arc::new(plugin);
+            if interfaces.contains_key(&iface.get_id()) {
+                debot_println!("{}", trf("Debot plugin interface {} is skipped: it is implemented by tonos-cli", &[&iface.get_id()]));
+                continue;
+            }
+            interfaces.insert(iface.get_id(), iface);
+        }
+
         Self { client, interfaces }
     }
+
+    /// Replaces interface implementations with the ones returned by `wrap`.
+    /// Used by browsers which can't run interfaces in the terminal.
+    pub fn wrap<F>(mut self, wrap: F) -> Self
+    where
+        F: Fn(Arc<dyn DebotInterface + Send + Sync>) -> Arc<dyn DebotInterface + Send + Sync>,
+    {
+        self.interfaces = self.interfaces.into_iter()
+            .map(|(id, iface)| (id, wrap(iface)))
+            .collect();
+        self
+    }
 }
 
 pub fn decode_answer_id(args: &Value) -> Result<u32, String> {
@@ -1,5 +1,9 @@
 use super::dinterface::{decode_string_arg};
 use crate::debot::term_browser::{action_input};
+use crate::debot::line_editor::{is_interrupted, Completion};
+use crate::debot::tui;
+use crate::error::ErrorKind;
+use crate::i18n::tr;
 use serde_json::Value;
 use serde::{de, Deserialize, Deserializer};
 use ton_client::abi::Abi;
@@ -8,7 +12,7 @@ use std::fmt::Display;
 use std::str::FromStr;
 use ton_client::encoding::decode_abi_number;
 
-const ID: &'static str = "ac1a4d3ecea232e49783df4a23a81823cdca3205dc58cd20c4db259c25605b48";
+pub const ID: &'static str = "ac1a4d3ecea232e49783df4a23a81823cdca3205dc58cd20c4db259c25605b48";
 
 const ABI: &str = r#"
 {
@@ -83,27 +87,36 @@ impl Menu {
 		Self{}
 	}
 	
-    fn select(&self, args: &Value) -> InterfaceResult {
+    async fn select(&self, args: &Value) -> InterfaceResult {
 		let menu_items: Vec<MenuItem> = serde_json::from_value(args["items"].clone()).unwrap();
         let title = decode_string_arg(args, "title")?;
         let description = decode_string_arg(args, "description")?;
-        println!("{}\n{}", title, description);
+        debot_println!("{}\n{}", title, description);
+        tui::clear_menu();
         for (i, menu) in menu_items.iter().enumerate() {
-            println!("{}) {}", i + 1, menu.title);
+            tui::show_action(format!("{}) {}", i + 1, menu.title));
             if menu.description != "" {
-                println!("   {}", menu.description);
+                tui::show_action(format!("   {}", menu.description));
             }            
         }
+        let completion = Completion::Words(
+            menu_items.iter().enumerate()
+                .map(|(i, menu)| (format!("{}", i + 1), menu.title.clone()))
+                .collect()
+        );
         loop {
-            let res = action_input(menu_items.len());
-            if res.is_err() {
-                println!("{}", res.unwrap_err());
-                continue;
-            }
-            let (n, _, _) = res.unwrap();
-            let menu = menu_items.get(n - 1);
+            let (n, _, _) = match action_input(menu_items.len(), completion.clone()).await {
+                Ok(res) => res,
+                // every further read fails at once after Ctrl-C or Ctrl-D
+                Err(e) if e.kind() == ErrorKind::Aborted || is_interrupted() => return Err(e.to_string()),
+                Err(e) => {
+                    debot_println!("{}", e);
+                    continue;
+                },
+            };
+            let menu = n.checked_sub(1).and_then(|i| menu_items.get(i));
             if menu.is_none() {
-                println!("Invalid menu. Try again.");
+                debot_println!("{}", tr("Invalid menu. Try again."));
                 continue;
             }
 
@@ -125,7 +138,7 @@ impl DebotInterface for Menu {
 
     async fn call(&self, func: &str, args: &Value) -> InterfaceResult {
         match func {
-            "select" => self.select(args),
+            "select" => self.select(args).await,
             _ => Err(format!("function \"{}\" is not implemented", func)),
         }
     }
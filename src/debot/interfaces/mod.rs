@@ -16,4 +16,5 @@ pub mod echo;
 pub mod stdout;
 pub mod address_input;
 pub mod terminal;
-pub mod menu;
\ No newline at end of file
+pub mod menu;
+pub mod plugin;
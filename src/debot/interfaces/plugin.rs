@@ -0,0 +1,267 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+//! Debot interfaces implemented by `cdylib` plugins.
+//!
+//! Plugins are loaded only with `debot fetch/serve --plugins` from `~/.tonos-cli/plugins`
+//! (or `$TONOSCLI_DEBOT_PLUGINS`). On unix the directory and the libraries must be owned
+//! by the current user and not writable by group or others, otherwise they are skipped.
+//!
+//! A plugin exports four C functions, all strings are NUL-terminated UTF-8 json:
+//!
+//! - `uint32_t tonos_plugin_abi_version()` returns `PLUGIN_ABI_VERSION`,
+//!   plugins with another version are skipped;
+//! - `char* tonos_plugin_describe()` returns
+//!   `{"interfaces": [{"id": "<interface id>", "abi": <ABI json>}]}`;
+//! - `char* tonos_plugin_call(const char* request)` gets
+//!   `{"interface": "<id>", "function": "<name>", "args": {...}}` with arguments
+//!   decoded by the ABI and returns `{"answer_id": <n>, "result": {...}}` or
+//!   `{"error": "<message>"}`;
+//! - `void tonos_plugin_free(char*)` frees strings returned by the plugin.
+//!
+//! Plugins must be thread-safe: several debots of a session can call
+//! `tonos_plugin_call` concurrently from different threads.
+use crate::i18n::trf;
+use libloading::{Library, Symbol};
+use serde::Deserialize;
+use serde_json::Value;
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use ton_client::abi::Abi;
+use ton_client::debot::{DebotInterface, InterfaceResult};
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+const PLUGINS_DIR_NAME: &str = ".tonos-cli/plugins";
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type DescribeFn = unsafe extern "C" fn() -> *mut c_char;
+type CallFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+lazy_static! {
+    static ref PLUGINS_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct InterfaceDescription {
+    id: String,
+    abi: Value,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct PluginDescription {
+    interfaces: Vec<InterfaceDescription>,
+}
+
+/// Loaded plugin library with resolved entry points.
+struct Plugin {
+    path: PathBuf,
+    call: CallFn,
+    free: FreeFn,
+    // must outlive the function pointers above
+    _library: Library,
+}
+
+impl Plugin {
+    fn load(path: &Path) -> Result<(Self, PluginDescription), String> {
+        let library = Library::new(path)
+            .map_err(|e| format!("failed to load library: {}", e))?;
+        unsafe {
+            let version: Symbol<AbiVersionFn> = library.get(b"tonos_plugin_abi_version\0")
+                .map_err(|e| format!("no version handshake: {}", e))?;
+            let version = version();
+            if version != PLUGIN_ABI_VERSION {
+                return Err(format!("plugin ABI version {} is not supported (expected {})", version, PLUGIN_ABI_VERSION));
+            }
+            let describe: DescribeFn = *library.get::<DescribeFn>(b"tonos_plugin_describe\0")
+                .map_err(|e| format!("no describe function: {}", e))?;
+            let call: CallFn = *library.get::<CallFn>(b"tonos_plugin_call\0")
+                .map_err(|e| format!("no call function: {}", e))?;
+            let free: FreeFn = *library.get::<FreeFn>(b"tonos_plugin_free\0")
+                .map_err(|e| format!("no free function: {}", e))?;
+            let plugin = Plugin { path: path.to_owned(), call, free, _library: library };
+            let description = plugin.take_string(describe())?;
+            let description = parse_description(&description)?;
+            Ok((plugin, description))
+        }
+    }
+
+    /// Copies string returned by the plugin and frees it.
+    unsafe fn take_string(&self, ptr: *mut c_char) -> Result<String, String> {
+        if ptr.is_null() {
+            return Err("plugin returned null".to_owned());
+        }
+        let result = CStr::from_ptr(ptr).to_str()
+            .map(|s| s.to_owned())
+            .map_err(|e| format!("plugin returned invalid UTF-8: {}", e));
+        (self.free)(ptr);
+        result
+    }
+
+    fn call(&self, request: &Value) -> Result<String, String> {
+        let request = CString::new(request.to_string())
+            .map_err(|e| format!("invalid request: {}", e))?;
+        // plugin may block (e.g. waiting for a fingerprint), keep other tasks running
+        tokio::task::block_in_place(|| unsafe {
+            let response = (self.call)(request.as_ptr());
+            self.take_string(response)
+        })
+    }
+}
+
+fn parse_description(json: &str) -> Result<PluginDescription, String> {
+    let description: PluginDescription = serde_json::from_str(json)
+        .map_err(|e| format!("invalid plugin description: {}", e))?;
+    for iface in &description.interfaces {
+        if iface.id.len() != 64 || hex::decode(&iface.id).is_err() {
+            return Err(format!("invalid interface id {}", iface.id));
+        }
+    }
+    Ok(description)
+}
+
+fn parse_response(json: &str) -> InterfaceResult {
+    let response: Value = serde_json::from_str(json)
+        .map_err(|e| format!("invalid plugin response: {}", e))?;
+    if let Some(error) = response.get("error") {
+        return Err(error.as_str().map(|s| s.to_owned()).unwrap_or(error.to_string()));
+    }
+    let answer_id = response["answer_id"].as_u64()
+        .ok_or("plugin response has no answer_id".to_owned())?;
+    let answer_id = u32::try_from(answer_id)
+        .map_err(|_| format!("plugin response has invalid answer_id {}", answer_id))?;
+    Ok((answer_id, response.get("result").cloned().unwrap_or(json!({}))))
+}
+
+pub struct PluginInterface {
+    id: String,
+    abi: String,
+    plugin: Arc<Plugin>,
+}
+
+#[async_trait::async_trait]
+impl DebotInterface for PluginInterface {
+    fn get_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn get_abi(&self) -> Abi {
+        Abi::Json(self.abi.clone())
+    }
+
+    async fn call(&self, func: &str, args: &Value) -> InterfaceResult {
+        let request = json!({ "interface": self.id, "function": func, "args": args });
+        let response = self.plugin.call(&request)
+            .map_err(|e| format!("plugin {}: {}", self.plugin.path.display(), e))?;
+        parse_response(&response)
+    }
+}
+
+pub fn plugins_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("TONOSCLI_DEBOT_PLUGINS") {
+        return PathBuf::from(dir);
+    }
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(PLUGINS_DIR_NAME)
+}
+
+/// Enables loading of plugins from the directory for debot sessions.
+pub fn enable_plugins(dir: PathBuf) {
+    *PLUGINS_DIR.lock().unwrap() = Some(dir);
+}
+
+pub fn enabled_plugins_dir() -> Option<PathBuf> {
+    PLUGINS_DIR.lock().unwrap().clone()
+}
+
+/// Checks that the file can't be replaced by other users.
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::MetadataExt;
+    extern "C" {
+        fn getuid() -> u32;
+    }
+    let meta = std::fs::metadata(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    if meta.uid() != unsafe { getuid() } {
+        return Err(format!("{} is not owned by the current user", path.display()));
+    }
+    if meta.mode() & 0o022 != 0 {
+        return Err(format!("{} is writable by group or others", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+fn is_library(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    ["so", "dylib", "dll"].contains(&ext)
+}
+
+/// Loads all plugins from the directory. Broken or unsafe plugins are reported and skipped.
+pub fn load_plugins(dir: &Path) -> Vec<PluginInterface> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    if let Err(e) = check_permissions(dir) {
+        debot_println!("{}", trf("Debot plugins are skipped: {}", &[&e]));
+        return vec![];
+    }
+    let mut interfaces = vec![];
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| is_library(p)) {
+        match check_permissions(&path).and_then(|_| Plugin::load(&path)) {
+            Ok((plugin, description)) => {
+                let plugin = Arc::new(plugin);
+                for iface in description.interfaces {
+                    debug!("plugin {} implements interface {}", path.display(), iface.id);
+                    let abi = match iface.abi {
+                        Value::String(abi) => abi,
+                        abi => abi.to_string(),
+                    };
+                    interfaces.push(PluginInterface { id: iface.id, abi, plugin: plugin.clone() });
+                }
+            },
+            Err(e) => debot_println!("{}", trf("Debot plugin {} is skipped: {}", &[&path.display(), &e])),
+        }
+    }
+    interfaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugin_messages() {
+        let id = "a".repeat(64);
+        let description = parse_description(&format!(r#"{{"interfaces": [{{"id": "{}", "abi": {{"ABI version": 2}}}}]}}"#, id)).unwrap();
+        assert_eq!(description.interfaces[0].id, id);
+        assert!(parse_description(r#"{"interfaces": [{"id": "xyz", "abi": {}}]}"#).is_err());
+
+        assert_eq!(parse_response(r#"{"answer_id": 5, "result": {"value": "01"}}"#).unwrap(), (5, json!({"value": "01"})));
+        assert_eq!(parse_response(r#"{"error": "sensor unavailable"}"#).unwrap_err(), "sensor unavailable");
+        assert!(parse_response(r#"{"result": {}}"#).is_err());
+        assert!(parse_response(r#"{"answer_id": 4294967296, "result": {}}"#).is_err());
+        assert!(is_library(Path::new("/tmp/libsigner.so")));
+        assert!(!is_library(Path::new("/tmp/readme.txt")));
+    }
+}
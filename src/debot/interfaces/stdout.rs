@@ -2,7 +2,7 @@ use serde_json::Value;
 use ton_client::debot::{DebotInterface, InterfaceResult};
 use ton_client::abi::Abi;
 
-const STDOUT_ID: &'static str = "c91dcc3fddb30485a3a07eb7c1e5e2aceaf75f4bc2678111de1f25291cdda80b";
+pub const STDOUT_ID: &'static str = "c91dcc3fddb30485a3a07eb7c1e5e2aceaf75f4bc2678111de1f25291cdda80b";
 
 pub const STDOUT_ABI: &str = r#"{
 	"ABI version": 2,
@@ -39,7 +39,7 @@ impl Stdout {
     pub fn print(&self, args: &Value) -> InterfaceResult {
 		let text_vec = hex::decode(args["message"].as_str().unwrap()).unwrap();
 		let text = std::str::from_utf8(&text_vec).unwrap();
-		println!("{}", text);
+		debot_println!("{}", text);
 		Ok((0, json!({})))
     }
 }
@@ -1,13 +1,14 @@
 use super::dinterface::{decode_answer_id, decode_bool_arg, decode_prompt, decode_string_arg};
+use crate::debot::line_editor::read_text;
 use crate::debot::term_browser::terminal_input;
+use crate::i18n::tr;
 use serde_json::Value;
 use ton_client::abi::Abi;
 use ton_client::debot::{DebotInterface, InterfaceResult};
-use crate::convert::convert_token;
+use crate::convert::{convert_token, strict_units};
 use ton_client::encoding::decode_abi_bigint;
-use std::io::{Read};
 
-const ID: &'static str = "8796536366ee21852db56dccb60bc564598b618c865fc50c8b1ab740bba128e3";
+pub const ID: &'static str = "8796536366ee21852db56dccb60bc564598b618c865fc50c8b1ab740bba128e3";
 
 const ABI: &str = r#"
 {
@@ -94,74 +95,74 @@ impl Terminal {
     pub fn new() -> Self {
         Self {}
     }
-    fn input_str(&self, args: &Value) -> InterfaceResult {
+    async fn input_str(&self, args: &Value) -> InterfaceResult {
         let answer_id = decode_answer_id(args)?;
         let prompt = decode_prompt(args)?;
         let multiline = decode_bool_arg(args, "multiline")?;
         let mut value = String::new();
         if multiline {
-            println!("{}", &prompt);
+            debot_println!("{}", &prompt);
             if cfg!(windows) {
-                println!("(Ctrl+Z to exit)");
+                debot_println!("{}", tr("(Ctrl+Z to exit)"));
             } else {
-                println!("(Ctrl+D to exit)");
+                debot_println!("{}", tr("(Ctrl+D to exit)"));
             }
-            std::io::stdin().read_to_string(&mut value)
+            value = read_text().await
                 .map_err(|e| format!("input error: {}", e))?;
-            println!();
+            debot_println!();
         } else {
-            value = terminal_input(&prompt, |_val| Ok(()));
+            value = terminal_input(&prompt, |_val| Ok(())).await;
         }
         Ok((answer_id, json!({ "value": hex::encode(value.as_bytes()) })))
     }
 
-    fn input_int(&self, args: &Value) -> InterfaceResult {
+    async fn input_int(&self, args: &Value) -> InterfaceResult {
         let answer_id = decode_answer_id(args)?;
         let value = terminal_input(&decode_prompt(args)?, |val| {
             let _ = decode_abi_bigint(val).map_err(|e| format!("{}", e))?;
             Ok(())
-        });
+        }).await;
         Ok((answer_id, json!({ "value": value })))
     }
 
-    fn input_uint(&self, args: &Value) -> InterfaceResult {
+    async fn input_uint(&self, args: &Value) -> InterfaceResult {
         let answer_id = decode_answer_id(args)?;
         let value = terminal_input(&decode_prompt(args)?, |val| {
             let _ = decode_abi_bigint(val).map_err(|e| format!("{}", e))?;
             Ok(())
-        });
+        }).await;
         Ok((answer_id, json!({ "value": value })))
     }
 
-    fn input_tokens(&self, args: &Value) -> InterfaceResult {
+    async fn input_tokens(&self, args: &Value) -> InterfaceResult {
         let answer_id = decode_answer_id(args)?;
         let mut nanotokens = String::new();
         let _ = terminal_input(&decode_prompt(args)?, |val| {
-            nanotokens = convert_token(val)?;
+            nanotokens = convert_token(val, strict_units())?;
             Ok(())
-        });
+        }).await;
         Ok((answer_id, json!({ "value": nanotokens })))
     }
 
-    fn input_boolean(&self, args: &Value) -> InterfaceResult {
+    async fn input_boolean(&self, args: &Value) -> InterfaceResult {
         let answer_id = decode_answer_id(args)?;
-        println!("{}", decode_prompt(args)?);
+        debot_println!("{}", decode_prompt(args)?);
         let mut yes_no = false;
         let _ = terminal_input("(y/n)", |val| {
             yes_no = match val.as_str() {
                 "y" => true,
                 "n" => false,
-                _ => Err(format!("invalid enter"))?,
+                _ => Err(tr("invalid enter").to_owned())?,
             };
             Ok(())
-        });
+        }).await;
         Ok((answer_id, json!({ "value": yes_no })))
     }
 
     pub fn print(&self, args: &Value) -> InterfaceResult {
         let answer_id = decode_answer_id(args)?;
         let message = decode_string_arg(args, "message")?;
-		println!("{}", message);
+		debot_println!("{}", message);
 		Ok((answer_id, json!({})))
     }
 }
@@ -178,11 +179,11 @@ impl DebotInterface for Terminal {
 
     async fn call(&self, func: &str, args: &Value) -> InterfaceResult {
         match func {
-            "inputStr" => self.input_str(args),
-            "inputInt" => self.input_int(args),
-            "inputUint" => self.input_uint(args),
-            "inputTons" => self.input_tokens(args),
-            "inputBoolean" => self.input_boolean(args),
+            "inputStr" => self.input_str(args).await,
+            "inputInt" => self.input_int(args).await,
+            "inputUint" => self.input_uint(args).await,
+            "inputTons" => self.input_tokens(args).await,
+            "inputBoolean" => self.input_boolean(args).await,
             "print" => self.print(args),
             _ => Err(format!("function \"{}\" is not implemented", func)),
         }
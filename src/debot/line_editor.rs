@@ -0,0 +1,254 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::sync::{mpsc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const MAX_HISTORY_SIZE: usize = 1000;
+pub const INPUT_INTERRUPTED: &str = "input interrupted";
+pub const END_OF_INPUT: &str = "end of input";
+
+/// Set once user presses Ctrl-C or Ctrl-D. No more input is read after that,
+/// so prompts repeated on invalid input can't loop forever.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Describes what can be completed by Tab key in the current prompt.
+#[derive(Clone)]
+pub enum Completion {
+    None,
+    /// List of (replacement, description) pairs, e.g. action numbers with action names.
+    Words(Vec<(String, String)>),
+    /// Paths in the file system.
+    Files,
+}
+
+struct InputHelper {
+    completion: Completion,
+    files: FilenameCompleter,
+}
+
+impl InputHelper {
+    fn complete_words(words: &[(String, String)], line: &str) -> Vec<Pair> {
+        let typed = line.trim_start().to_lowercase();
+        words.iter()
+            .filter(|(word, desc)| {
+                word.to_lowercase().starts_with(&typed) || desc.to_lowercase().contains(&typed)
+            })
+            .map(|(word, desc)| Pair {
+                display: if desc.is_empty() { word.clone() } else { format!("{}) {}", word, desc) },
+                replacement: word.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Completer for InputHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        match &self.completion {
+            Completion::None => Ok((pos, vec![])),
+            Completion::Words(words) => Ok((0, Self::complete_words(words, &line[..pos]))),
+            Completion::Files => self.files.complete(line, pos, ctx),
+        }
+    }
+}
+
+impl Hinter for InputHelper {
+    type Hint = String;
+}
+
+impl Highlighter for InputHelper {}
+
+impl Validator for InputHelper {}
+
+impl Helper for InputHelper {}
+
+struct LineEditor {
+    editor: Editor<InputHelper>,
+    history_file: Option<String>,
+}
+
+lazy_static! {
+    static ref EDITOR: Mutex<LineEditor> = Mutex::new({
+        let mut editor = Editor::<InputHelper>::new();
+        editor.set_helper(Some(InputHelper {
+            completion: Completion::None,
+            files: FilenameCompleter::new(),
+        }));
+        LineEditor { editor, history_file: None }
+    });
+}
+
+/// Where the input thread sends the entered line.
+type Reply = tokio::sync::oneshot::Sender<Result<String, String>>;
+
+/// Request to read a line, served by the input thread.
+struct Prompt {
+    text: String,
+    completion: Completion,
+    save_history: bool,
+    /// Line of a multiline text: Ctrl-D ends the text, not the session.
+    multiline: bool,
+    reply: Reply,
+}
+
+lazy_static! {
+    static ref PROMPTS: Mutex<mpsc::Sender<Prompt>> = Mutex::new(spawn_input_thread());
+}
+
+/// Terminal is read only by a dedicated thread, so prompts don't block the
+/// async runtime: subscriptions, timeouts and message processing keep running
+/// while the user is typing. Prompts are served one by one in request order.
+fn spawn_input_thread() -> mpsc::Sender<Prompt> {
+    let (sender, receiver) = mpsc::channel::<Prompt>();
+    std::thread::Builder::new()
+        .name("terminal-input".to_owned())
+        .spawn(move || {
+            for prompt in receiver {
+                let result = read_terminal_line(&prompt.text, prompt.completion, prompt.save_history, prompt.multiline);
+                // requester may be gone (e.g. cancelled by timeout), the line is dropped then
+                let _ = prompt.reply.send(result);
+            }
+        })
+        .expect("failed to start terminal input thread");
+    sender
+}
+
+fn submit(prompt: &str, completion: Completion, save_history: bool, multiline: bool, reply: Reply) -> Result<(), String> {
+    PROMPTS.lock().unwrap()
+        .send(Prompt { text: prompt.to_owned(), completion, save_history, multiline, reply })
+        .map_err(|_| "terminal input thread has stopped".to_owned())
+}
+
+/// Returns path to the history file which belongs to the config file (profile).
+pub fn history_file_name(config_file: &str) -> String {
+    format!("{}.history", config_file.trim_end_matches(".json"))
+}
+
+/// Loads input history from file. History is saved to the same file after every entered line.
+pub fn init_history(path: &str) {
+    let mut line_editor = EDITOR.lock().unwrap();
+    line_editor.editor.history_mut().set_max_len(MAX_HISTORY_SIZE);
+    if let Err(e) = line_editor.editor.load_history(path) {
+        debug!("failed to load input history: {}", e);
+    }
+    line_editor.history_file = Some(path.to_owned());
+}
+
+/// Reads a line from terminal with line editing (arrows, Ctrl-U, Ctrl-W, etc.), history
+/// and Tab completion. If `save_history` is false, the entered line is not stored
+/// in history (used for secrets). In TUI mode the line is read in the input box.
+/// The calling task is suspended until the line is entered.
+pub async fn read_line(prompt: &str, completion: Completion, save_history: bool) -> Result<String, String> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    submit(prompt, completion, save_history, false, sender)?;
+    receiver.await.map_err(|_| "terminal input thread has stopped".to_owned())?
+}
+
+/// Reads multiline text until Ctrl-D (Ctrl-Z on Windows) is pressed on an empty line.
+/// Unlike `read_line`, end of input here doesn't stop further prompts.
+pub async fn read_text() -> Result<String, String> {
+    let mut text = String::new();
+    loop {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        submit("", Completion::None, false, true, sender)?;
+        match receiver.await.map_err(|_| "terminal input thread has stopped".to_owned())? {
+            Ok(line) => {
+                text.push_str(&line);
+                text.push('\n');
+            },
+            Err(e) if e == END_OF_INPUT => return Ok(text),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn read_terminal_line(prompt: &str, completion: Completion, save_history: bool, multiline: bool) -> Result<String, String> {
+    if is_interrupted() {
+        return Err(INPUT_INTERRUPTED.to_owned());
+    }
+    if super::tui::is_active() {
+        let result = super::tui::read_line(prompt, false);
+        match &result {
+            Err(e) if e == INPUT_INTERRUPTED || (e == END_OF_INPUT && !multiline) => {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+            },
+            _ => {},
+        }
+        return result;
+    }
+    let mut line_editor = EDITOR.lock().unwrap();
+    if let Some(helper) = line_editor.editor.helper_mut() {
+        helper.completion = completion;
+    }
+    let result = line_editor.editor.readline(prompt);
+    if let Some(helper) = line_editor.editor.helper_mut() {
+        helper.completion = Completion::None;
+    }
+    match result {
+        Ok(line) => {
+            if save_history && !line.trim().is_empty() {
+                line_editor.editor.add_history_entry(line.as_str());
+                if let Some(path) = line_editor.history_file.clone() {
+                    if let Err(e) = line_editor.editor.save_history(&path) {
+                        debug!("failed to save input history: {}", e);
+                    }
+                }
+            }
+            Ok(line)
+        },
+        Err(ReadlineError::Interrupted) => {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+            Err(INPUT_INTERRUPTED.to_owned())
+        },
+        Err(ReadlineError::Eof) => {
+            if !multiline {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+            }
+            Err(END_OF_INPUT.to_owned())
+        },
+        Err(e) => Err(format!("failed to read line: {}", e)),
+    }
+}
+
+/// Checks if user has interrupted input (Ctrl-C or Ctrl-D) at one of the prompts.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_words() {
+        let words = vec![
+            ("1".to_owned(), "Deposit stake".to_owned()),
+            ("2".to_owned(), "Withdraw stake".to_owned()),
+            ("10".to_owned(), "Quit".to_owned()),
+        ];
+        let result: Vec<String> = InputHelper::complete_words(&words, "1")
+            .into_iter().map(|p| p.replacement).collect();
+        assert_eq!(result, vec!["1", "10"]);
+        let result: Vec<String> = InputHelper::complete_words(&words, "with")
+            .into_iter().map(|p| p.replacement).collect();
+        assert_eq!(result, vec!["2"]);
+        assert_eq!(InputHelper::complete_words(&words, "").len(), 3);
+    }
+}
@@ -12,14 +12,31 @@
 */
 use crate::config::Config;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use interfaces::plugin;
 use simplelog::*;
 use term_browser::run_debot_browser;
+use crate::crypto::load_keypair;
+use crate::error::{CliError, ErrorKind};
 use crate::helpers::load_ton_address;
+use crate::metrics;
+
+/// Prints line to stdout or to the log pane if the browser runs in TUI mode.
+macro_rules! debot_println {
+    () => { crate::debot::tui::print(String::new()) };
+    ($($arg:tt)*) => { crate::debot::tui::print(format!($($arg)*)) };
+}
 
 pub mod term_browser;
+mod budget;
+mod cache;
 mod interfaces;
+mod line_editor;
+mod signing_policy;
 pub use interfaces::dinterface::SupportedInterfaces;
 mod term_signing_box;
+mod trust_store;
+mod tui;
+mod ws_browser;
 
 pub fn create_debot_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("debot")
@@ -31,15 +48,63 @@ pub fn create_debot_command<'a, 'b>() -> App<'a, 'b> {
         .subcommand(
             SubCommand::with_name("fetch")
                 .setting(AppSettings::AllowLeadingHyphen)
+                .arg(
+                    Arg::with_name("ADDRESS")
+                        .required(true)
+                        .multiple(true)
+                        .help("Debot address. Several debots can be run simultaneously, their actions are selected as <debot>.<action>."),
+                )
+                .arg(
+                    Arg::with_name("TUI")
+                        .long("--tui")
+                        .help("Runs browser in full-screen terminal UI with panes for debot log, menu, pending messages and status bar."),
+                )
+                .arg(
+                    Arg::with_name("CACHE")
+                        .long("--cache")
+                        .help("Caches debot ABIs on disk (~/.tonos-cli/debot-cache) by code hash, so they are not downloaded on every start or invoke. Invoked debots are always cached for the session."),
+                )
+                .arg(
+                    Arg::with_name("PLUGINS")
+                        .long("--plugins")
+                        .help("Loads debot interface implementations from plugin libraries in ~/.tonos-cli/plugins (or $TONOSCLI_DEBOT_PLUGINS). The directory and libraries must be owned by the current user and not writable by others."),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Runs debot browser driven by remote UI over websocket: browser callbacks (log, show_action, input, approve) and calls of interactive debot interfaces (Terminal, Menu, AddressInput) are sent to the UI as json frames. Debots which invoke other debots are not supported.")
                 .arg(
                     Arg::with_name("ADDRESS")
                         .required(true)
                         .help("Debot address."),
                 )
+                .arg(
+                    Arg::with_name("WS")
+                        .long("--ws")
+                        .takes_value(true)
+                        .help("Loopback address to listen for websocket connections (default: 127.0.0.1:8080). UI connects to the printed URL with access token."),
+                )
+                .arg(
+                    Arg::with_name("SIGN")
+                        .long("--sign")
+                        .takes_value(true)
+                        .help("Keypair used to sign messages requested by debot (default: keys from config). Signatures must be approved by the UI unless the action is whitelisted."),
+                )
+                .arg(
+                    Arg::with_name("METRICS")
+                        .long("--metrics")
+                        .takes_value(true)
+                        .help("Address (host:port) where Prometheus metrics endpoint /metrics with debot session stats is served."),
+                )
+                .arg(
+                    Arg::with_name("PLUGINS")
+                        .long("--plugins")
+                        .help("Loads debot interface implementations from plugin libraries in ~/.tonos-cli/plugins (or $TONOSCLI_DEBOT_PLUGINS). The directory and libraries must be owned by the current user and not writable by others."),
+                )
         )
 }
 
-pub async fn debot_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+pub async fn debot_command(m: &ArgMatches<'_>, config: Config, config_file: &str) -> Result<(), CliError> {
     let debug = m.is_present("DEBUG");
     let log_conf = ConfigBuilder::new()
         .add_filter_ignore_str("executor")
@@ -65,15 +130,44 @@ pub async fn debot_command(m: &ArgMatches<'_>, config: Config) -> Result<(), Str
         ));
     }
     CombinedLogger::init(loggers).unwrap();
+    line_editor::init_history(&line_editor::history_file_name(config_file));
+    trust_store::init_trust_store(&trust_store::trust_file_name(config_file));
 
     if let Some(m) = m.subcommand_matches("fetch") {
         return fetch_command(m, config).await;
     }
-    Err("unknown debot command".to_owned())
+    if let Some(m) = m.subcommand_matches("serve") {
+        return Ok(serve_command(m, config).await?);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown debot command"))
 }
 
-async fn fetch_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
-    let addr = m.value_of("ADDRESS");
-    let addr = load_ton_address(addr.unwrap(), &config)?;
-    return run_debot_browser(addr.as_str(), config).await;
+async fn fetch_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let addrs = m.values_of("ADDRESS").unwrap()
+        .map(|addr| load_ton_address(addr, &config))
+        .collect::<Result<Vec<_>, _>>()?;
+    if m.is_present("CACHE") {
+        cache::init_disk_cache(cache::default_cache_dir());
+    }
+    if m.is_present("PLUGINS") {
+        plugin::enable_plugins(plugin::plugins_dir());
+    }
+    return run_debot_browser(&addrs, config, m.is_present("TUI")).await;
+}
+
+async fn serve_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let addr = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+    let listen = m.value_of("WS").unwrap_or("127.0.0.1:8080");
+    let keys = m.value_of("SIGN")
+        .map(|s| s.to_owned())
+        .or(config.keys_path.clone())
+        .map(|keys| load_keypair(&keys))
+        .transpose()?;
+    if let Some(metrics_addr) = m.value_of("METRICS") {
+        metrics::start_metrics_server(metrics_addr).await?;
+    }
+    if m.is_present("PLUGINS") {
+        plugin::enable_plugins(plugin::plugins_dir());
+    }
+    ws_browser::serve_debot_browser(listen, &addr, keys, config).await
 }
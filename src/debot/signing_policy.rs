@@ -0,0 +1,214 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+use super::budget::{check_budget, SharedBudget};
+use super::line_editor::{read_line, Completion};
+use crate::config::{Config, DebotWhitelistEntry};
+use crate::helpers::TonClient;
+use crate::i18n::{tr, trf};
+use serde_json::Value;
+use std::sync::Arc;
+use ton_client::crypto::{sign, KeyPair, ParamsOfSign, SigningBox};
+use ton_client::debot::DAction;
+use ton_client::error::{ClientError, ClientResult};
+use ton_client::ClientContext;
+
+const ANY_ACTION: &str = "*";
+
+/// Signing policy for debot sessions. Config contains a whitelist of debot
+/// functions per debot address and code hash, signatures requested by other
+/// functions must be confirmed by user.
+///
+/// NOTE: SDK passes only the data hash to a signing box, so the policy
+/// is checked against the debot function which requested the signature,
+/// decoded with the debot ABI.
+#[derive(Clone, Default)]
+pub(super) struct SigningPolicy {
+    whitelist: Vec<DebotWhitelistEntry>,
+}
+
+impl SigningPolicy {
+    pub fn new(conf: &Config) -> Self {
+        Self { whitelist: conf.debot_signing_whitelist.clone() }
+    }
+
+    /// Checks the whitelist, `call` is `None` if the function is not found in the debot ABI.
+    pub fn is_allowed(&self, debot: &str, code_hash: &str, call: Option<&DebotCall>) -> bool {
+        let function = call.map(|call| call.function.as_str());
+        self.whitelist.iter()
+            .filter(|entry| entry.address == debot && entry.code_hash == code_hash)
+            .any(|entry| entry.actions.iter().any(|a| a == ANY_ACTION || Some(a.as_str()) == function))
+    }
+}
+
+/// Debot function called by the action which requests a signature.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct DebotCall {
+    pub function: String,
+    /// Function inputs from the debot ABI as `name: type`.
+    pub inputs: Vec<String>,
+}
+
+impl DebotCall {
+    /// Finds the function of the action in the debot ABI.
+    pub fn decode(abi: Option<&str>, action: Option<&DAction>) -> Option<Self> {
+        let action = action?;
+        let abi: Value = serde_json::from_str(abi?).ok()?;
+        let function = abi["functions"].as_array()?.iter()
+            .find(|f| f["name"].as_str() == Some(action.name.as_str()))?;
+        let inputs = function["inputs"].as_array().cloned().unwrap_or_default().iter()
+            .map(|input| format!("{}: {}", input["name"].as_str().unwrap_or(""), input["type"].as_str().unwrap_or("")))
+            .collect();
+        Some(Self { function: action.name.clone(), inputs })
+    }
+
+    pub fn signature(&self) -> String {
+        format!("{}({})", self.function, self.inputs.join(", "))
+    }
+}
+
+/// Describes the called function for approval prompts.
+pub(super) fn describe_call(call: Option<&DebotCall>) -> String {
+    match call {
+        Some(call) => call.signature(),
+        None => tr("unknown (not found in debot ABI)").to_owned(),
+    }
+}
+
+/// Describes the action for approval prompts: name and description shown in the menu.
+pub(super) fn describe_action(action: Option<&DAction>) -> String {
+    match action {
+        Some(action) if action.desc.is_empty() => action.name.clone(),
+        Some(action) => format!("{} ({})", action.name, action.desc),
+        None => tr("unknown").to_owned(),
+    }
+}
+
+/// Signing box which asks user for confirmation before every signature
+/// if the requesting debot action is not whitelisted or the session
+/// spending limit is exceeded.
+pub(super) struct PolicySigningBox {
+    keys: KeyPair,
+    debot: String,
+    code_hash: String,
+    action: Option<DAction>,
+    call: Option<DebotCall>,
+    allowed: bool,
+    client: TonClient,
+    budget: SharedBudget,
+}
+
+impl PolicySigningBox {
+    pub fn new(
+        keys: KeyPair,
+        policy: &SigningPolicy,
+        debot: &str,
+        code_hash: &str,
+        action: Option<DAction>,
+        debot_abi: Option<&str>,
+        client: TonClient,
+        budget: SharedBudget,
+    ) -> Self {
+        let call = DebotCall::decode(debot_abi, action.as_ref());
+        let allowed = policy.is_allowed(debot, code_hash, call.as_ref());
+        Self { keys, debot: debot.to_owned(), code_hash: code_hash.to_owned(), action, call, allowed, client, budget }
+    }
+
+    async fn confirm(&self, unsigned: &[u8]) -> bool {
+        debot_println!("{}", tr("Debot requests a signature:"));
+        debot_println!("{}", trf("  debot:      {}", &[&self.debot]));
+        debot_println!("{}", trf("  code hash:  {}", &[&self.code_hash]));
+        debot_println!("{}", trf("  action:     {}", &[&describe_action(self.action.as_ref())]));
+        debot_println!("{}", trf("  function:   {}", &[&describe_call(self.call.as_ref())]));
+        debot_println!("{}", trf("  destination: {}", &[&self.debot]));
+        debot_println!("{}", trf("  public key: {}", &[&self.keys.public]));
+        debot_println!("{}", trf("  data hash:  {}", &[&hex::encode(unsigned)]));
+        loop {
+            match read_line(tr("Sign? (y/n) "), Completion::None, false).await {
+                Ok(answer) => match answer.trim() {
+                    "y" => return true,
+                    "n" => return false,
+                    _ => debot_println!("{}", tr("invalid enter")),
+                },
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningBox for PolicySigningBox {
+    async fn get_public_key(&self, _context: Arc<ClientContext>) -> ClientResult<Vec<u8>> {
+        hex::decode(&self.keys.public)
+            .map_err(|e| ClientError::with_code_message(0, format!("invalid public key: {}", e)))
+    }
+
+    async fn sign(&self, context: Arc<ClientContext>, unsigned: &Vec<u8>) -> ClientResult<Vec<u8>> {
+        let warning = check_budget(&self.budget, self.client.clone()).await
+            .map_err(|e| ClientError::with_code_message(0, e))?;
+        if let Some(warning) = &warning {
+            debot_println!("{}", warning);
+        }
+        // whitelist doesn't apply once the session limit is exceeded
+        if (!self.allowed || warning.is_some()) && !self.confirm(unsigned).await {
+            return Err(ClientError::with_code_message(0, tr("signature rejected by user").to_owned()));
+        }
+        let result = sign(
+            context,
+            ParamsOfSign {
+                unsigned: base64::encode(unsigned),
+                keys: self.keys.clone(),
+            },
+        )?;
+        self.budget.lock().await.record_signature();
+        hex::decode(&result.signature)
+            .map_err(|e| ClientError::with_code_message(0, format!("invalid signature: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_whitelist() {
+        let entry = |address: &str, code_hash: &str, action: &str| DebotWhitelistEntry {
+            address: address.to_owned(),
+            code_hash: code_hash.to_owned(),
+            actions: vec![action.to_owned()],
+        };
+        let call = |function: &str| DebotCall { function: function.to_owned(), inputs: vec![] };
+        let whitelist = vec![entry("0:1111", "aa", "deposit"), entry("0:2222", "bb", ANY_ACTION)];
+        let policy = SigningPolicy { whitelist };
+        assert!(policy.is_allowed("0:1111", "aa", Some(&call("deposit"))));
+        assert!(!policy.is_allowed("0:1111", "aa", Some(&call("withdraw"))));
+        assert!(!policy.is_allowed("0:1111", "aa", None));
+        assert!(!policy.is_allowed("0:1111", "cc", Some(&call("deposit"))));
+        assert!(policy.is_allowed("0:2222", "bb", None));
+        assert!(!policy.is_allowed("0:2222", "aa", None));
+        assert!(!policy.is_allowed("0:3333", "aa", Some(&call("deposit"))));
+    }
+
+    #[test]
+    fn test_decode_call() {
+        let abi = r#"{"ABI version": 2, "functions": [
+            {"name": "deposit", "inputs": [{"name": "dest", "type": "address"}, {"name": "value", "type": "uint128"}], "outputs": []}
+        ]}"#;
+        let action = |name: &str| DAction { name: name.to_owned(), ..Default::default() };
+        let call = DebotCall::decode(Some(abi), Some(&action("deposit"))).unwrap();
+        assert_eq!(call.signature(), "deposit(dest: address, value: uint128)");
+        assert!(DebotCall::decode(Some(abi), Some(&action("withdraw"))).is_none());
+        assert!(DebotCall::decode(None, Some(&action("deposit"))).is_none());
+        assert!(DebotCall::decode(Some(abi), None).is_none());
+        assert!(describe_call(None).contains("unknown"));
+    }
+}
@@ -10,56 +10,312 @@
 * See the License for the specific TON DEV software governing permissions and
 * limitations under the License.
 */
+use super::budget::{ResendPolicy, SessionBudget, SharedBudget};
+use super::cache;
+use super::signing_policy::{PolicySigningBox, SigningPolicy};
 use super::term_signing_box::TerminalSigningBox;
+use super::trust_store::{changed_warning, query_code_hash, TrustStatus, TrustStore};
+use super::tui;
 use crate::config::Config;
-use crate::helpers::{create_client, load_ton_address, TonClient};
-use std::io::{self, BufRead, Write};
+use crate::convert::nano_to_tokens;
+use crate::helpers::{create_client, load_ton_address, query, TonClient};
 use std::sync::{Arc, RwLock};
 use ton_client::boc::{ParamsOfParse, parse_message};
-use ton_client::crypto::SigningBoxHandle;
+use ton_client::crypto::{remove_signing_box, RegisteredSigningBox, SigningBoxHandle};
 use ton_client::debot::{DebotInterfaceExecutor, BrowserCallbacks, DAction, DEngine, STATE_EXIT};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use super::{SupportedInterfaces};
+use super::line_editor::{is_interrupted, read_line, Completion, END_OF_INPUT, INPUT_INTERRUPTED};
+use crate::error::{CliError, ErrorKind};
+use crate::exit_codes::explain_error;
+use crate::i18n::{tr, trf};
+
+type MsgQueue = Arc<RwLock<VecDeque<(usize, String)>>>;
+
+/// Invoked debot kept for the rest of the session, so that next invocations
+/// don't download and initialize it again.
+struct InvokedDebot {
+    browser: Arc<RwLock<TerminalBrowser>>,
+    engine: Arc<tokio::sync::Mutex<DEngine>>,
+}
+
+/// Invoked debots by address, shared by all debots running in the same browser.
+type InvokedDebots = Arc<tokio::sync::Mutex<HashMap<String, InvokedDebot>>>;
+
+impl InvokedDebot {
+    async fn fetch(
+        client: TonClient,
+        debot: &str,
+        signing_boxes: Arc<tokio::sync::Mutex<SigningBoxManager>>,
+        invoked: InvokedDebots,
+    ) -> Result<Self, String> {
+        let mut terminal = TerminalBrowser::new(client.clone(), debot);
+        terminal.signing_boxes = signing_boxes;
+        terminal.invoked = invoked;
+        let browser = Arc::new(RwLock::new(terminal));
+        let callbacks = Arc::new(Callbacks::new(Arc::clone(&browser)));
+        let abi = cache::load_debot_abi(client.clone(), debot).await;
+        let mut engine = DEngine::new_with_client(debot.to_owned(), abi, client, callbacks);
+        engine.fetch().await?;
+        Ok(Self { browser, engine: Arc::new(tokio::sync::Mutex::new(engine)) })
+    }
+}
+
+/// Keeps track of signing boxes registered in the SDK during a browser session.
+/// Shared by all debots running in the same browser.
+#[derive(Default)]
+pub(crate) struct SigningBoxManager {
+    handles: Vec<SigningBoxHandle>,
+    policy: SigningPolicy,
+    trust: TrustStore,
+    /// Code hashes of debots approved in this session.
+    approved: HashMap<String, String>,
+    budget: SharedBudget,
+}
+
+impl SigningBoxManager {
+    fn new(conf: &Config) -> Self {
+        Self {
+            handles: vec![],
+            policy: SigningPolicy::new(conf),
+            trust: TrustStore::load(),
+            approved: HashMap::new(),
+            budget: Arc::new(tokio::sync::Mutex::new(SessionBudget::new(conf))),
+        }
+    }
+
+    /// Warns at debot start if its code differs from the approved one.
+    async fn warn_if_changed(&self, client: TonClient, debot: &str) {
+        if let Ok(code_hash) = query_code_hash(client, debot).await {
+            if let TrustStatus::Changed(approved) = self.trust.status(debot, &code_hash) {
+                debot_println!("{}", changed_warning(debot, &approved, &code_hash));
+            }
+        }
+    }
+
+    /// Asks user to approve the debot code hash unless it is already pinned.
+    /// Returns the approved code hash.
+    async fn check_trust(&mut self, client: TonClient, debot: &str) -> Result<String, CliError> {
+        if let Some(code_hash) = self.approved.get(debot) {
+            return Ok(code_hash.clone());
+        }
+        let code_hash = query_code_hash(client, debot).await
+            .map_err(|e| CliError::from(e).context("failed to check debot code"))?;
+        match self.trust.status(debot, &code_hash) {
+            TrustStatus::Trusted => {},
+            status => {
+                match status {
+                    TrustStatus::Changed(approved) => debot_println!("{}", changed_warning(debot, &approved, &code_hash)),
+                    _ => debot_println!("{}", trf("Debot {} (code hash {}) requests signing keys for the first time.", &[&debot, &code_hash])),
+                }
+                if !confirm(tr("Trust this debot? (y/n) ")).await {
+                    return Err(CliError::aborted(format!("debot {} is not trusted", debot)));
+                }
+                self.trust.approve(debot, &code_hash)
+                    .map_err(|e| CliError::new(ErrorKind::Io, e).context("failed to save trusted debot"))?;
+            },
+        }
+        self.approved.insert(debot.to_owned(), code_hash.clone());
+        Ok(code_hash)
+    }
+
+    async fn register(
+        &mut self,
+        client: TonClient,
+        debot: &str,
+        action: Option<DAction>,
+    ) -> Result<SigningBoxHandle, CliError> {
+        let code_hash = self.check_trust(client.clone(), debot).await?;
+        let debot_abi = cache::get_debot_abi(client.clone(), debot).await;
+        let terminal_box = TerminalSigningBox::new().await?;
+        let policy_box = PolicySigningBox::new(
+            terminal_box.keys,
+            &self.policy,
+            debot,
+            &code_hash,
+            action,
+            debot_abi.as_deref(),
+            client.clone(),
+            self.budget.clone(),
+        );
+        let handle = ton_client::crypto::register_signing_box(
+            client,
+            policy_box,
+        )
+        .await
+        .map(|r| r.handle)
+        .map_err(|e| CliError::new(ErrorKind::Internal, e.to_string()).context("failed to register signing box"))?;
+        self.handles.push(handle.clone());
+        Ok(handle)
+    }
+
+    /// Removes registered signing boxes from the SDK client, returns their number.
+    fn release(&mut self, client: TonClient) -> usize {
+        let count = self.handles.len();
+        for handle in self.handles.drain(..) {
+            if let Err(e) = remove_signing_box(client.clone(), RegisteredSigningBox { handle }) {
+                debug!("failed to remove signing box: {}", e);
+            }
+        }
+        count
+    }
+}
+
+/// Owns resources of a browser session: started debot engines, invoked debots
+/// and signing boxes registered in the SDK client. `close` releases them when
+/// the session ends, `Drop` does the same on early return or panic, skipping
+/// what is locked at that moment.
+struct BrowserSession {
+    client: TonClient,
+    signing_boxes: Arc<tokio::sync::Mutex<SigningBoxManager>>,
+    /// Cached invoked debots reference the map through their browsers, the
+    /// cycle is broken only by clearing it.
+    invoked: InvokedDebots,
+    debots: Vec<DEngine>,
+    closed: bool,
+}
+
+impl BrowserSession {
+    fn new(
+        client: TonClient,
+        signing_boxes: Arc<tokio::sync::Mutex<SigningBoxManager>>,
+        invoked: InvokedDebots,
+    ) -> Self {
+        Self { client, signing_boxes, invoked, debots: vec![], closed: false }
+    }
+
+    async fn close(mut self) {
+        let invoked = std::mem::take(&mut *self.invoked.lock().await);
+        drop(invoked);
+        self.debots.clear();
+        let released = self.signing_boxes.lock().await.release(self.client.clone());
+        debug!("debot session closed, {} signing boxes released", released);
+        self.closed = true;
+    }
+}
+
+impl Drop for BrowserSession {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        if let Ok(mut invoked) = self.invoked.try_lock() {
+            invoked.clear();
+        }
+        self.debots.clear();
+        if let Ok(mut manager) = self.signing_boxes.try_lock() {
+            manager.release(self.client.clone());
+        }
+    }
+}
 
 struct TerminalBrowser {
     state_id: u8,
     active_actions: Vec<DAction>,
     client: TonClient,
-    msg_queue: VecDeque<String>,
+    msg_queue: MsgQueue,
+    signing_boxes: Arc<tokio::sync::Mutex<SigningBoxManager>>,
+    /// Index of the debot in the browser, used as a namespace for its actions.
+    index: usize,
+    /// Print action numbers with debot namespace prefix (e.g. `1.3`).
+    namespaced: bool,
+    /// Debot address.
+    address: String,
+    /// Action being executed, used by signing policy.
+    current_action: Option<DAction>,
+    invoked: InvokedDebots,
 }
 
 impl TerminalBrowser {
-    pub fn new(client: TonClient) -> Self {
+    pub fn new(client: TonClient, address: &str) -> Self {
         Self {
             state_id: 0,
             active_actions: vec![],
             client: client.clone(),
             msg_queue: Default::default(),
+            signing_boxes: Default::default(),
+            index: 0,
+            namespaced: false,
+            address: address.to_owned(),
+            current_action: None,
+            invoked: Default::default(),
         }
     }
 
-    pub fn select_action(&self) -> Option<DAction> {
-        if self.state_id == STATE_EXIT {
-            return None;
-        }
-        if self.active_actions.len() == 0 {
-            debug!("no more actions, exit loop");
-            return None;
+    fn new_in_session(
+        client: TonClient,
+        address: &str,
+        msg_queue: MsgQueue,
+        signing_boxes: Arc<tokio::sync::Mutex<SigningBoxManager>>,
+        invoked: InvokedDebots,
+        index: usize,
+        namespaced: bool,
+    ) -> Self {
+        Self {
+            state_id: 0,
+            active_actions: vec![],
+            client,
+            msg_queue,
+            signing_boxes,
+            index,
+            namespaced,
+            address: address.to_owned(),
+            current_action: None,
+            invoked,
         }
+    }
+
+    fn action_completion(&self) -> Completion {
+        Completion::Words(
+            self.active_actions.iter().enumerate()
+                .map(|(i, act)| {
+                    let n = if self.namespaced {
+                        format!("{}.{}", self.index + 1, i + 1)
+                    } else {
+                        format!("{}", i + 1)
+                    };
+                    (n, act.desc.clone())
+                })
+                .collect()
+        )
+    }
+
+    fn is_active(&self) -> bool {
+        self.state_id != STATE_EXIT && self.active_actions.len() != 0
+    }
+
+    /// Asks user to select one of the active actions. Browser isn't locked
+    /// while waiting for input.
+    pub async fn select_action(browser: &Arc<RwLock<Self>>) -> Option<DAction> {
+        let (count, completion) = {
+            let browser = browser.read().unwrap();
+            if browser.state_id == STATE_EXIT {
+                return None;
+            }
+            if browser.active_actions.len() == 0 {
+                debug!("no more actions, exit loop");
+                return None;
+            }
+            (browser.active_actions.len(), browser.action_completion())
+        };
 
         loop {
-            let res = action_input(self.active_actions.len());
-            if res.is_err() {
-                println!("{}", res.unwrap_err());
-                continue;
+            let res = action_input(count, completion.clone()).await;
+            match res {
+                Err(e) if e.kind() == ErrorKind::Aborted => return None,
+                Err(e) => {
+                    debot_println!("{}", e);
+                    continue;
+                },
+                _ => {},
             }
             let (n, _, _) = res.unwrap();
-            let act = self.active_actions.get(n - 1);
+            let act = browser.read().unwrap().active_actions.get(n - 1).cloned();
             if act.is_none() {
-                println!("Invalid action. Try again.");
+                debot_println!("{}", tr("Invalid action. Try again."));
                 continue;
             }
-            return act.map(|a| a.clone());
+            return act;
         }
     }
 
@@ -68,7 +324,8 @@ impl TerminalBrowser {
         msg: String,
         debot: &mut DEngine,
         interfaces: &SupportedInterfaces,
-    ) -> Result<(), String> {
+        mut resend: ResendPolicy,
+    ) -> Result<(), CliError> {
 
         let parsed = parse_message(
             client.clone(),
@@ -78,22 +335,36 @@ impl TerminalBrowser {
             },
         )
         .await
-        .map_err(|e| format!("{}", e))?;
+        .map_err(|e| CliError::new(ErrorKind::Contract, e.to_string()).context("failed to parse debot message"))?;
 
         let iface_addr = parsed.parsed["dst"]
             .as_str()
-            .ok_or(format!("parsed message has no dst address"))?;
+            .ok_or(CliError::new(ErrorKind::Contract, "parsed message has no dst address"))?;
         let wc_and_addr: Vec<_> = iface_addr.split(':').collect();
         let interface_id = wc_and_addr[1].to_string();
 
         if let Some(result) = interfaces.try_execute(&msg, &interface_id).await {
-            let (func_id, return_args) = result?;
+            let (func_id, return_args) = result
+                .map_err(|e| CliError::from(e).context(format!("interface {} failed", interface_id)))?;
             debug!("response: {} ({})", func_id, return_args);
-            let result = debot.send(
-                iface_addr.to_owned(), func_id, return_args.to_string()
-            ).await;
-            if let Err(e) = result {
-                println!("debot call failed: {}", e);
+            loop {
+                resend.start_attempt().await;
+                let result = debot.send(
+                    iface_addr.to_owned(), func_id, return_args.to_string()
+                ).await;
+                let e = match result {
+                    Ok(_) => break,
+                    Err(e) => e,
+                };
+                if let Some(n) = resend.next(&e).await {
+                    debot_println!("{}", trf("Message expired, resending ({} of {})...", &[&n, &resend.retries()]));
+                    continue;
+                }
+                match ask_on_error("debot call failed", &e).await {
+                    ErrorChoice::Retry => continue,
+                    ErrorChoice::Continue => break,
+                    ErrorChoice::Abort => return Err(CliError::aborted(SESSION_ABORTED)),
+                }
             }
         }
 
@@ -116,7 +387,7 @@ impl Callbacks {
 impl BrowserCallbacks for Callbacks {
     /// Debot asks browser to print message to user
     async fn log(&self, msg: String) {
-        println!("{}", msg);
+        debot_println!("{}", msg);
     }
 
     /// Debot is switched to another context.
@@ -129,6 +400,7 @@ impl BrowserCallbacks for Callbacks {
         }
 
         browser.active_actions = vec![];
+        tui::clear_menu();
     }
 
     async fn switch_completed(&self) {
@@ -137,61 +409,83 @@ impl BrowserCallbacks for Callbacks {
     /// Debot asks browser to show user an action from the context
     async fn show_action(&self, act: DAction) {
         let mut browser = self.browser.write().unwrap();
-        println!("{}) {}", browser.active_actions.len() + 1, act.desc);
+        if browser.namespaced {
+            tui::show_action(format!("{}.{}) {}", browser.index + 1, browser.active_actions.len() + 1, act.desc));
+        } else {
+            tui::show_action(format!("{}) {}", browser.active_actions.len() + 1, act.desc));
+        }
         browser.active_actions.push(act);
     }
 
     // Debot engine asks user to enter argument for an action.
     async fn input(&self, prefix: &str, value: &mut String) {
-        let stdio = io::stdin();
-        let mut reader = stdio.lock();
-        let mut writer = io::stdout();
-        *value = input(prefix, &mut reader, &mut writer);
+        *value = editor_input(prefix).await;
     }
 
     /// Debot engine requests keys to sign something
     async fn get_signing_box(&self) -> Result<SigningBoxHandle, String> {
-        let terminal_box = TerminalSigningBox::new()?;
-        let client = self.browser.read().unwrap().client.clone();
-        let handle = ton_client::crypto::get_signing_box(
-            client,
-            terminal_box.keys,
-        )
-        .await
-        .map(|r| r.handle)
-        .map_err(|e| e.to_string())?;
-        Ok(handle)
+        let (client, signing_boxes, debot, action) = {
+            let browser = self.browser.read().unwrap();
+            (
+                browser.client.clone(),
+                browser.signing_boxes.clone(),
+                browser.address.clone(),
+                browser.current_action.clone(),
+            )
+        };
+        let mut manager = signing_boxes.lock().await;
+        Ok(manager.register(client, &debot, action).await?)
     }
 
     /// Debot asks to run action of another debot
     async fn invoke_debot(&self, debot: String, action: DAction) -> Result<(), String> {
         debug!("fetching debot {} action {}", &debot, action.name);
-        println!("Invoking debot {}", &debot);
-        let ton_cl = self.browser.read().unwrap().client.clone();
-        let browser = Arc::new(
-            RwLock::new(
-                TerminalBrowser::new(ton_cl.clone())
-            )
-        );
-        let callbacks = Arc::new(Callbacks::new(Arc::clone(&browser)));
-        let mut debot_eng = DEngine::new_with_client(
-            debot.clone(),
-            None,
-            ton_cl.clone(),
-            callbacks,
-        );
-        debot_eng.fetch().await?;
+        debot_println!("{}", trf("Invoking debot {}", &[&debot]));
+        let (ton_cl, signing_boxes, invoked_debots) = {
+            let browser = self.browser.read().unwrap();
+            (browser.client.clone(), browser.signing_boxes.clone(), browser.invoked.clone())
+        };
+        let cached = invoked_debots.lock().await.get(&debot)
+            .map(|cached| (cached.browser.clone(), cached.engine.clone()));
+        let (browser, engine) = match cached {
+            Some(cached) => {
+                debug!("debot {} is taken from session cache", &debot);
+                cached
+            },
+            None => {
+                let invoked = InvokedDebot::fetch(ton_cl.clone(), &debot, signing_boxes.clone(), invoked_debots.clone()).await?;
+                let cached = (invoked.browser.clone(), invoked.engine.clone());
+                invoked_debots.lock().await.insert(debot.clone(), invoked);
+                cached
+            },
+        };
+        // engine is busy if debot invokes itself, such calls get a separate engine which is not cached
+        let uncached;
+        let (browser, mut debot_eng) = match engine.try_lock() {
+            Ok(guard) => (browser, guard),
+            Err(_) => {
+                let invoked = InvokedDebot::fetch(ton_cl, &debot, signing_boxes, invoked_debots).await?;
+                uncached = invoked.engine;
+                (invoked.browser, uncached.lock().await)
+            },
+        };
+        {
+            let mut browser = browser.write().unwrap();
+            browser.current_action = Some(action.clone());
+            browser.active_actions = vec![];
+        }
         if let Err(e) = debot_eng.execute_action(&action).await {
-            println!("Error. {}", e);
+            debot_println!("{}", trf("Error. {}", &[&e]));
             return Ok(());
         }
 
         loop {
-            let action = browser.read().unwrap().select_action();
+            let action = TerminalBrowser::select_action(&browser).await;
             match action {
                 Some(act) => {
+                    browser.write().unwrap().current_action = Some(act.clone());
                     if let Err(e) = debot_eng.execute_action(&act).await {
-                        println!("Error. {}", e);
+                        debot_println!("{}", trf("Error. {}", &[&e]));
                         break;
                     }
                 },
@@ -202,62 +496,76 @@ impl BrowserCallbacks for Callbacks {
     }
 
     async fn send(&self, message: String) {
-        let mut browser = self.browser.write().unwrap();
-        browser.msg_queue.push_back(message);
+        let (client, index, msg_queue) = {
+            let browser = self.browser.read().unwrap();
+            (browser.client.clone(), browser.index, browser.msg_queue.clone())
+        };
+        if tui::is_active() {
+            let dst = parse_message(client, ParamsOfParse { boc: message.clone(), ..Default::default() })
+                .await
+                .ok()
+                .and_then(|parsed| parsed.parsed["dst"].as_str().map(|s| s.to_owned()))
+                .unwrap_or_default();
+            tui::push_pending(format!("debot {} -> {}", index + 1, dst));
+        }
+        msg_queue.write().unwrap().push_back((index, message));
     }
 }
 
-pub(crate) fn input<R, W>(prefix: &str, reader: &mut R, writer: &mut W) -> String
-where
-    R: BufRead,
-    W: Write,
-{
-    let mut input_str = "".to_owned();
-    let mut argc = 0;
-    while argc == 0 {
-        print!("{} > ", prefix);
-        if let Err(e) = writer.flush() {
-            println!("failed to flush: {}", e);
-            return input_str;
-        }
-        if let Err(e) = reader.read_line(&mut input_str) {
-            println!("failed to read line: {}", e);
-            return input_str;
+/// Reads a non-empty line from terminal using line editor with history.
+pub(crate) async fn editor_input(prefix: &str) -> String {
+    editor_input_async(prefix, Completion::None, true).await
+}
+
+pub(crate) async fn editor_input_async(prefix: &str, completion: Completion, save_history: bool) -> String {
+    loop {
+        match read_line(&format!("{} > ", prefix), completion.clone(), save_history).await {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    return line.trim().to_owned();
+                }
+            },
+            Err(e) => {
+                debot_println!("{}", e);
+                return String::new();
+            },
         }
-        argc = input_str
-            .split_whitespace()
-            .map(|x| x.parse::<String>().unwrap())
-            .collect::<Vec<String>>()
-            .len();
     }
-    input_str.trim().to_owned()
 }
 
-pub(crate) fn terminal_input<F>(prompt: &str, mut validator: F) -> String
+pub(crate) async fn terminal_input<F>(prompt: &str, validator: F) -> String
+where
+    F: FnMut(&String) -> Result<(), String>
+{
+    terminal_input_with(prompt, Completion::None, validator).await
+}
+
+pub(crate) async fn terminal_input_with<F>(prompt: &str, completion: Completion, mut validator: F) -> String
 where
     F: FnMut(&String) -> Result<(), String>
 {
-    let stdio = io::stdin();
-    let mut reader = stdio.lock();
-    let mut writer = io::stdout();
-    let mut value = input(prompt, &mut reader, &mut writer);
+    let mut value = editor_input_async(prompt, completion.clone(), true).await;
     while let Err(e) = validator(&value) {
-        println!("{}. Try again.", e);
-        value = input(prompt, &mut reader, &mut writer);
+        if is_interrupted() {
+            break;
+        }
+        debot_println!("{}", trf("{}. Try again.", &[&e]));
+        value = editor_input_async(prompt, completion.clone(), true).await;
     }
     value
 }
-pub fn action_input(max: usize) -> Result<(usize, usize, Vec<String>), String> {
-    let mut a_str = String::new();
+pub async fn action_input(max: usize, completion: Completion) -> Result<(usize, usize, Vec<String>), CliError> {
     let mut argc = 0;
     let mut argv = vec![];
-    println!();
+    debot_println!();
     while argc == 0 {
-        print!("debash$ ");
-        let _ = io::stdout().flush();
-        io::stdin()
-            .read_line(&mut a_str)
-            .map_err(|e| format!("failed to read line: {}", e))?;
+        let a_str = read_line("debash$ ", completion.clone(), true).await.map_err(|e| {
+            if e == END_OF_INPUT || e == INPUT_INTERRUPTED {
+                CliError::aborted(e)
+            } else {
+                CliError::new(ErrorKind::Io, e)
+            }
+        })?;
         argv = a_str
             .split_whitespace()
             .map(|x| x.parse::<String>().expect("parse error"))
@@ -265,48 +573,276 @@ pub fn action_input(max: usize) -> Result<(usize, usize, Vec<String>), String> {
         argc = argv.len();
     }
     let n = usize::from_str_radix(&argv[0], 10)
-        .map_err(|_| format!("Oops! Invalid action. Try again, please."))?;
+        .map_err(|_| invalid_input(tr("Oops! Invalid action. Try again, please.")))?;
     if n > max {
-        Err(format!("Auch! Invalid action. Try again, please."))?;
+        return Err(invalid_input(tr("Auch! Invalid action. Try again, please.")));
     }
 
     Ok((n, argc, argv))
 }
 
+const SESSION_ABORTED: &str = "debot session aborted";
+
+fn invalid_input(message: &str) -> CliError {
+    CliError::new(ErrorKind::InvalidInput, message)
+}
+
+async fn confirm(prompt: &str) -> bool {
+    loop {
+        match read_line(prompt, Completion::None, false).await {
+            Ok(answer) => match answer.trim() {
+                "y" => return true,
+                "n" => return false,
+                _ => debot_println!("{}", tr("invalid enter")),
+            },
+            Err(_) => return false,
+        }
+    }
+}
+
+enum ErrorChoice {
+    Retry,
+    Continue,
+    Abort,
+}
+
+/// Prints debot engine error with explanation of exit code (if it is known)
+/// and asks user what to do next.
+async fn ask_on_error(context: &str, error: &str) -> ErrorChoice {
+    debot_println!("{}: {}", context, error);
+    if let Some(explanation) = explain_error(error) {
+        debot_println!("{}", trf("Reason: {}", &[&explanation]));
+    }
+    loop {
+        let answer = read_line(tr("(r)etry, (c)ontinue or (a)bort session? "), Completion::None, false).await;
+        match answer.as_ref().map(|s| s.trim().to_lowercase()) {
+            Ok(s) if s == "r" || s == "retry" => return ErrorChoice::Retry,
+            Ok(s) if s == "c" || s == "continue" => return ErrorChoice::Continue,
+            Ok(s) if s == "a" || s == "abort" => return ErrorChoice::Abort,
+            Ok(_) => debot_println!("{}", tr("Invalid answer. Try again.")),
+            Err(_) => return ErrorChoice::Abort,
+        }
+    }
+}
+
+/// Parses action reference entered by user. If several debots are running,
+/// action must be prefixed with debot number: `<debot>.<action>`.
+/// Returns zero-based debot index and one-based action number.
+pub(crate) fn parse_action_ref(input: &str, debot_count: usize) -> Result<(usize, usize), CliError> {
+    let input = input.trim();
+    let (debot, action) = match input.find('.') {
+        Some(pos) => (&input[..pos], &input[pos + 1..]),
+        None if debot_count == 1 => ("1", input),
+        None => return Err(invalid_input(tr("Select action as <debot>.<action>, e.g. 1.3"))),
+    };
+    let debot = usize::from_str_radix(debot, 10)
+        .map_err(|_| invalid_input(tr("Oops! Invalid debot number. Try again, please.")))?;
+    let action = usize::from_str_radix(action, 10)
+        .map_err(|_| invalid_input(tr("Oops! Invalid action. Try again, please.")))?;
+    if debot == 0 || debot > debot_count {
+        return Err(invalid_input(tr("Auch! Invalid debot number. Try again, please.")));
+    }
+    if action == 0 {
+        return Err(invalid_input(tr("Auch! Invalid action. Try again, please.")));
+    }
+    Ok((debot - 1, action))
+}
+
+async fn select_namespaced_action(browsers: &[Arc<RwLock<TerminalBrowser>>]) -> Option<(usize, DAction)> {
+    if browsers.iter().all(|b| !b.read().unwrap().is_active()) {
+        debug!("no more actions, exit loop");
+        return None;
+    }
+    let completion = Completion::Words(
+        browsers.iter()
+            .flat_map(|b| match b.read().unwrap().action_completion() {
+                Completion::Words(words) => words,
+                _ => vec![],
+            })
+            .collect()
+    );
+    loop {
+        debot_println!();
+        let a_str = match read_line("debash$ ", completion.clone(), true).await {
+            Ok(line) => line,
+            Err(e) => {
+                debot_println!("{}", e);
+                return None;
+            },
+        };
+        if a_str.trim().is_empty() {
+            continue;
+        }
+        let (index, n) = match parse_action_ref(&a_str, browsers.len()) {
+            Ok(res) => res,
+            Err(e) => {
+                debot_println!("{}", e);
+                continue;
+            },
+        };
+        let action = {
+            let browser = browsers[index].read().unwrap();
+            if !browser.is_active() {
+                debot_println!("{}", trf("Debot {} has finished. Select another one.", &[&(index + 1)]));
+                continue;
+            }
+            browser.active_actions.get(n - 1).cloned()
+        };
+        match action {
+            Some(act) => return Some((index, act)),
+            None => debot_println!("{}", tr("Invalid action. Try again.")),
+        }
+    }
+}
+
+/// Shows network, wallet and its balance in the TUI status bar.
+async fn update_status(ton: TonClient, config: &Config) {
+    let account = match &config.wallet {
+        Some(wallet) => {
+            let balance = query(
+                ton,
+                "accounts",
+                json!({ "id": { "eq": wallet } }),
+                "balance(format: DEC)",
+                None,
+            ).await
+            .ok()
+            .and_then(|accounts| accounts.first().and_then(|acc| acc["balance"].as_str().map(|b| b.to_owned())))
+            .and_then(|b| u64::from_str_radix(&b, 10).ok())
+            .map(nano_to_tokens)
+            .unwrap_or(tr("unknown").to_owned());
+            trf("Account: {} | Balance: {}", &[&wallet, &balance])
+        },
+        None => tr("Account: not configured").to_owned(),
+    };
+    tui::set_status(trf(" Network: {} | {} | PgUp/PgDn scroll log, Ctrl+D exit", &[&config.url, &account]));
+}
+
 pub async fn run_debot_browser(
-    addr: &str,
+    addrs: &[String],
     config: Config,
-) -> Result<(), String> {
-    println!("Connecting to {}", config.url);
+    tui_mode: bool,
+) -> Result<(), CliError> {
+    let _tui = if tui_mode { Some(tui::start()?) } else { None };
+    debot_println!("{}", trf("Connecting to {}", &[&config.url]));
     let ton = create_client(&config)?;
     let interfaces = SupportedInterfaces::new(ton.clone(), &config);
 
-    let browser = Arc::new(RwLock::new(TerminalBrowser::new(ton.clone())));
+    let msg_queue: MsgQueue = Default::default();
+    let signing_boxes = Arc::new(tokio::sync::Mutex::new(SigningBoxManager::new(&config)));
+    let invoked: InvokedDebots = Default::default();
+    let namespaced = addrs.len() > 1;
+    let mut session = BrowserSession::new(ton.clone(), signing_boxes.clone(), invoked.clone());
 
-    let callbacks = Arc::new(Callbacks::new(Arc::clone(&browser)));
-    let mut debot = DEngine::new_with_client(load_ton_address(addr, &config)?, None, ton.clone(), callbacks);
-    debot.start().await?;
+    let mut browsers = vec![];
+    for (index, addr) in addrs.iter().enumerate() {
+        let browser = Arc::new(RwLock::new(TerminalBrowser::new_in_session(
+            ton.clone(),
+            addr,
+            msg_queue.clone(),
+            signing_boxes.clone(),
+            invoked.clone(),
+            index,
+            namespaced,
+        )));
+        let callbacks = Arc::new(Callbacks::new(Arc::clone(&browser)));
+        let abi = cache::load_debot_abi(ton.clone(), &load_ton_address(addr, &config)?).await;
+        let mut debot = DEngine::new_with_client(load_ton_address(addr, &config)?, abi, ton.clone(), callbacks);
+        if namespaced {
+            debot_println!("{}", trf("Debot {}: {}", &[&(index + 1), addr]));
+        }
+        signing_boxes.lock().await.warn_if_changed(ton.clone(), &load_ton_address(addr, &config)?).await;
+        debot.start().await
+            .map_err(|e| CliError::from(e).context(format!("failed to start debot {}", addr)))?;
+        browsers.push(browser);
+        session.debots.push(debot);
+    }
+
+    let result = browser_loop(ton, &interfaces, msg_queue, &browsers, &mut session.debots, namespaced, &config).await;
+    session.close().await;
+    debot_println!("{}", tr("Debot Browser shutdown"));
+    match result {
+        Err(e) if e.kind() == ErrorKind::Aborted => Ok(()),
+        _ => result,
+    }
+}
 
+async fn browser_loop(
+    ton: TonClient,
+    interfaces: &SupportedInterfaces,
+    msg_queue: MsgQueue,
+    browsers: &[Arc<RwLock<TerminalBrowser>>],
+    debots: &mut Vec<DEngine>,
+    namespaced: bool,
+    config: &Config,
+) -> Result<(), CliError> {
+    let budget = {
+        let boxes = browsers[0].read().unwrap().signing_boxes.clone();
+        let budget = boxes.lock().await.budget.clone();
+        budget
+    };
     loop {
-        let mut next_msg = browser.write().unwrap().msg_queue.pop_front();
-        while let Some(msg) = next_msg {
+        if tui::is_active() {
+            update_status(ton.clone(), config).await;
+        }
+        let mut next_msg = msg_queue.write().unwrap().pop_front();
+        while let Some((index, msg)) = next_msg {
             TerminalBrowser::handle_interface_call(
                 ton.clone(),
                 msg,
-                &mut debot,
-                &interfaces,
+                &mut debots[index],
+                interfaces,
+                ResendPolicy::new(budget.clone(), config.retries),
             ).await?;
-            next_msg = browser.write().unwrap().msg_queue.pop_front();
+            tui::pop_pending();
+            next_msg = msg_queue.write().unwrap().pop_front();
         }
-        let action = browser.read().unwrap().select_action();
-        match action {
-            Some(act) => debot.execute_action(&act).await?,
+        let action = if namespaced {
+            select_namespaced_action(browsers).await
+        } else {
+            TerminalBrowser::select_action(&browsers[0]).await.map(|act| (0, act))
+        };
+        let (index, act) = match action {
+            Some(action) => action,
             None => break,
+        };
+        browsers[index].write().unwrap().current_action = Some(act.clone());
+        let mut resend = ResendPolicy::new(budget.clone(), config.retries);
+        loop {
+            resend.start_attempt().await;
+            let e = match debots[index].execute_action(&act).await {
+                Ok(_) => break,
+                Err(e) => e,
+            };
+            // engine builds messages again with fresh timestamps on every attempt
+            if let Some(n) = resend.next(&e).await {
+                debot_println!("{}", trf("Message expired, resending ({} of {})...", &[&n, &resend.retries()]));
+                continue;
+            }
+            match ask_on_error("action failed", &e).await {
+                ErrorChoice::Retry => continue,
+                ErrorChoice::Continue => break,
+                ErrorChoice::Abort => return Err(CliError::aborted(SESSION_ABORTED)),
+            }
         }
     }
-    println!("Debot Browser shutdown");
     Ok(())
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_action_ref() {
+        assert_eq!(parse_action_ref("3", 1).unwrap(), (0, 3));
+        assert_eq!(parse_action_ref("1.3", 1).unwrap(), (0, 3));
+        assert_eq!(parse_action_ref(" 2.1\n", 2).unwrap(), (1, 1));
+        assert!(parse_action_ref("3", 2).is_err());
+        assert!(parse_action_ref("3.1", 2).is_err());
+        assert!(parse_action_ref("0.1", 2).is_err());
+        assert!(parse_action_ref("1.0", 2).is_err());
+        assert!(parse_action_ref("a.b", 2).is_err());
+        assert_eq!(parse_action_ref("0.1", 2).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
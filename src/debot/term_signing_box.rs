@@ -1,37 +1,59 @@
-use crate::crypto::load_keypair;
-use super::term_browser::input;
-use ton_client::crypto::KeyPair;
+use crate::crypto::{load_keypair, read_secret};
+use crate::i18n::{tr, trf};
+use super::line_editor::{Completion, END_OF_INPUT};
+use super::term_browser::editor_input_async;
 use std::io::{self, BufRead, Write};
+use ton_client::crypto::KeyPair;
+
+const ENTER_KEYS_STR: &str = "enter seed phrase or path to keypair file";
+const ENTER_KEYS_FILE_STR: &str = "enter path to keypair file (leave empty to enter seed phrase without echo)";
 
 pub(super) struct TerminalSigningBox {
     pub keys: KeyPair
 }
 
 impl TerminalSigningBox {
-    pub fn new() -> Result<Self, String> {
-        let stdio = io::stdin();
-        let mut reader = stdio.lock();    
-        let mut writer = io::stdout();
-        let keys = input_keys(&mut reader, &mut writer, 3)?;
+    /// Keys are read by the terminal input thread, so the session keeps running meanwhile.
+    /// Piped input is read directly, other tasks are moved off this worker meanwhile.
+    pub async fn new() -> Result<Self, String> {
+        if !atty::is(atty::Stream::Stdin) {
+            let keys = tokio::task::block_in_place(|| {
+                let stdin = io::stdin();
+                read_keys_with(&mut stdin.lock(), &mut io::stdout(), 3)
+            })?;
+            return Ok(Self { keys });
+        }
+        let mut pair = Err("no keypair".to_string());
+        for _ in 0..3 {
+            // Seed phrase must not be echoed or saved in input history,
+            // so it's entered separately from keypair file path.
+            let path = editor_input_async(tr(ENTER_KEYS_FILE_STR), Completion::Files, false).await;
+            let value = if path.is_empty() {
+                tokio::task::block_in_place(read_seed_phrase)
+            } else {
+                path
+            };
+            pair = check_keys(&value);
+            if pair.is_ok() {
+                break;
+            }
+        }
         Ok(Self {
-            keys
+            keys: pair?
         })
     }
 }
 
-pub(super) fn input_keys<R, W>(reader: &mut R, writer: &mut W, tries: u8) -> Result<KeyPair, String>
+/// Reads seed phrase or keypair file path from `reader`, up to `tries` times.
+fn read_keys_with<R, W>(reader: &mut R, writer: &mut W, tries: u8) -> Result<KeyPair, String>
 where
     R: BufRead,
     W: Write,
 {
-    let enter_str = "enter seed phrase or path to keypair file";
     let mut pair = Err("no keypair".to_string());
     for _ in 0..tries {
-        let value = input(enter_str, reader, writer);
-        pair = load_keypair(&value).map_err(|e| {
-            println!("Invalid keys: {}. Try again.", e);
-            e.to_string()
-        });
+        let value = input(tr(ENTER_KEYS_STR), reader, writer)?;
+        pair = check_keys(&value);
         if pair.is_ok() {
             break;
         }
@@ -39,6 +61,42 @@ where
     pair
 }
 
+/// Reads a non-empty line, fails at the end of input.
+fn input<R, W>(prefix: &str, reader: &mut R, writer: &mut W) -> Result<String, String>
+where
+    R: BufRead,
+    W: Write,
+{
+    loop {
+        write!(writer, "{} > ", prefix)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("failed to flush: {}", e))?;
+        let mut line = String::new();
+        let len = reader.read_line(&mut line)
+            .map_err(|e| format!("failed to read line: {}", e))?;
+        if len == 0 {
+            return Err(END_OF_INPUT.to_owned());
+        }
+        if !line.trim().is_empty() {
+            return Ok(line.trim().to_owned());
+        }
+    }
+}
+
+fn read_seed_phrase() -> String {
+    if super::tui::is_active() {
+        super::tui::read_line(tr("Seed phrase: "), true).unwrap_or_default()
+    } else {
+        read_secret(tr("Seed phrase: ")).unwrap_or_default()
+    }
+}
+
+fn check_keys(value: &str) -> Result<KeyPair, String> {
+    load_keypair(value).map_err(|e| {
+        debot_println!("{}", trf("Invalid keys: {}. Try again.", &[&e]));
+        e.to_string()
+    })
+}
 
 #[cfg(test)]
 mod tests {
@@ -64,7 +122,7 @@ mod tests {
         let mut out_data = vec![];
 
         create_keypair_file(KEYS_FILE);
-        let keys = input_keys(&mut in_data, &mut out_data, 1).unwrap();
+        let keys = read_keys_with(&mut in_data, &mut out_data, 1).unwrap();
         
         assert_eq!(format!("{}", keys.public), PUBLIC);
         assert_eq!(format!("{}", keys.secret), PRIVATE);        
@@ -75,9 +133,16 @@ mod tests {
         let mut in_data = SEED.as_bytes();
         let mut out_data = vec![];
 
-        let keys = input_keys(&mut in_data, &mut out_data, 1).unwrap();
+        let keys = read_keys_with(&mut in_data, &mut out_data, 1).unwrap();
         
         assert_eq!(format!("{}", keys.public), PUBLIC);
         assert_eq!(format!("{}", keys.secret), PRIVATE);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fail_at_end_of_input() {
+        let mut in_data = "\n\n".as_bytes();
+        let mut out_data = vec![];
+        assert_eq!(read_keys_with(&mut in_data, &mut out_data, 3).unwrap_err(), END_OF_INPUT);
+    }
+}
@@ -0,0 +1,112 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+use crate::helpers::{query, TonClient};
+use crate::i18n::trf;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref TRUST_FILE: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Returns path to the trust store which belongs to the config file (profile).
+pub fn trust_file_name(config_file: &str) -> String {
+    format!("{}.debots", config_file.trim_end_matches(".json"))
+}
+
+pub fn init_trust_store(path: &str) {
+    *TRUST_FILE.lock().unwrap() = Some(path.to_owned());
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum TrustStatus {
+    /// Code hash matches the approved one.
+    Trusted,
+    /// Debot was never approved.
+    Unknown,
+    /// Debot code was changed since approval, contains the approved hash.
+    Changed(String),
+}
+
+/// Code hashes of debots approved by user, pinned per debot address.
+#[derive(Default)]
+pub(super) struct TrustStore {
+    hashes: BTreeMap<String, String>,
+}
+
+impl TrustStore {
+    pub fn load() -> Self {
+        let hashes = TRUST_FILE.lock().unwrap().as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self { hashes }
+    }
+
+    pub fn status(&self, debot: &str, code_hash: &str) -> TrustStatus {
+        match self.hashes.get(debot) {
+            Some(hash) if hash == code_hash => TrustStatus::Trusted,
+            Some(hash) => TrustStatus::Changed(hash.clone()),
+            None => TrustStatus::Unknown,
+        }
+    }
+
+    pub fn approve(&mut self, debot: &str, code_hash: &str) -> Result<(), String> {
+        self.hashes.insert(debot.to_owned(), code_hash.to_owned());
+        let path = TRUST_FILE.lock().unwrap().clone();
+        if let Some(path) = path {
+            let text = serde_json::to_string_pretty(&self.hashes)
+                .map_err(|e| format!("failed to serialize trust store: {}", e))?;
+            std::fs::write(&path, text)
+                .map_err(|e| format!("failed to save trust store {}: {}", path, e))?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) async fn query_code_hash(client: TonClient, debot: &str) -> Result<String, String> {
+    let accounts = query(
+        client,
+        "accounts",
+        json!({ "id": { "eq": debot } }),
+        "code_hash",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query debot code hash: {}", e))?;
+    accounts.first()
+        .and_then(|acc| acc["code_hash"].as_str())
+        .map(|hash| hash.to_owned())
+        .ok_or(format!("debot {} has no code", debot))
+}
+
+/// Prominent warning printed when pinned code hash doesn't match.
+pub(super) fn changed_warning(debot: &str, approved: &str, actual: &str) -> String {
+    trf(
+        "WARNING! Code of debot {} has changed since you approved it.\n  approved code hash: {}\n  current code hash:  {}\nThe debot may have been upgraded by its owner or replaced by a malicious one. It gets no signing keys until approved again.",
+        &[&debot, &approved, &actual],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trust_status() {
+        let mut store = TrustStore::default();
+        assert_eq!(store.status("0:1", "aa"), TrustStatus::Unknown);
+        store.hashes.insert("0:1".to_owned(), "aa".to_owned());
+        assert_eq!(store.status("0:1", "aa"), TrustStatus::Trusted);
+        assert_eq!(store.status("0:1", "bb"), TrustStatus::Changed("aa".to_owned()));
+    }
+}
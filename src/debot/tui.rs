@@ -0,0 +1,299 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+//! Full-screen terminal UI for the debot browser (`debot fetch --tui`).
+//!
+//! Browser output is routed here by `debot_println!`, user input by
+//! the input thread of `line_editor`, so the browser logic is the same in both modes.
+use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Span, Spans};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use ratatui::{Frame, Terminal};
+use super::line_editor::{END_OF_INPUT, INPUT_INTERRUPTED};
+use crate::i18n::{tr, trf};
+use std::io::Stdout;
+use std::sync::Mutex;
+
+const MAX_LOG_LINES: usize = 10_000;
+
+#[derive(Default)]
+struct Panes {
+    log: Vec<String>,
+    /// Lines scrolled back from the end of the log.
+    scroll: usize,
+    menu: Vec<String>,
+    pending: Vec<String>,
+    status: String,
+    prompt: String,
+    input: String,
+    hidden: bool,
+    history: Vec<String>,
+}
+
+impl Panes {
+    fn push_log(&mut self, text: &str) {
+        self.log.extend(text.lines().map(|line| line.to_owned()));
+        if text.is_empty() {
+            self.log.push(String::new());
+        }
+        if self.log.len() > MAX_LOG_LINES {
+            let extra = self.log.len() - MAX_LOG_LINES;
+            self.log.drain(..extra);
+        }
+    }
+
+    /// Returns log lines visible in the pane of given height.
+    fn visible_log(&self, height: usize) -> &[String] {
+        let end = self.log.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(height);
+        &self.log[start..end]
+    }
+}
+
+struct Screen {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    panes: Panes,
+}
+
+lazy_static! {
+    static ref SCREEN: Mutex<Option<Screen>> = Mutex::new(None);
+}
+
+/// Restores the terminal when TUI session ends (also on error).
+pub struct TuiGuard;
+
+impl Drop for TuiGuard {
+    fn drop(&mut self) {
+        stop();
+    }
+}
+
+pub fn start() -> Result<TuiGuard, String> {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
+        return Err("TUI mode requires interactive terminal".to_owned());
+    }
+    enable_raw_mode().map_err(|e| format!("failed to enable raw mode: {}", e))?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)
+        .map_err(|e| format!("failed to enter alternate screen: {}", e))?;
+    let terminal = Terminal::new(CrosstermBackend::new(stdout))
+        .map_err(|e| format!("failed to init terminal: {}", e))?;
+    *SCREEN.lock().unwrap() = Some(Screen { terminal, panes: Panes::default() });
+    redraw();
+    Ok(TuiGuard)
+}
+
+fn stop() {
+    if let Some(mut screen) = SCREEN.lock().unwrap().take() {
+        let _ = disable_raw_mode();
+        let _ = execute!(screen.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = screen.terminal.show_cursor();
+        // keep the session log in the terminal after exit
+        for line in &screen.panes.log {
+            println!("{}", line);
+        }
+    }
+}
+
+pub fn is_active() -> bool {
+    SCREEN.lock().unwrap().is_some()
+}
+
+fn update<F: FnOnce(&mut Panes)>(f: F) -> bool {
+    let mut screen = SCREEN.lock().unwrap();
+    match screen.as_mut() {
+        Some(screen) => {
+            f(&mut screen.panes);
+            draw(screen);
+            true
+        },
+        None => false,
+    }
+}
+
+fn redraw() {
+    update(|_| {});
+}
+
+/// Prints text to the log pane or to stdout if TUI is not active.
+pub fn print(text: String) {
+    if !update(|panes| panes.push_log(&text)) {
+        println!("{}", text);
+    }
+}
+
+/// Shows debot action in the menu pane. Menu is cleared on context switch.
+pub fn show_action(text: String) {
+    if !update(|panes| panes.menu.push(text.clone())) {
+        println!("{}", text);
+    }
+}
+
+pub fn clear_menu() {
+    update(|panes| panes.menu.clear());
+}
+
+pub fn push_pending(text: String) {
+    update(|panes| panes.pending.push(text));
+}
+
+pub fn pop_pending() {
+    update(|panes| {
+        if !panes.pending.is_empty() {
+            panes.pending.remove(0);
+        }
+    });
+}
+
+pub fn set_status(text: String) {
+    update(|panes| panes.status = text);
+}
+
+/// Reads a line in the input box. Hidden input (secrets) is masked
+/// and not saved in history.
+pub fn read_line(prompt: &str, hidden: bool) -> Result<String, String> {
+    update(|panes| {
+        panes.prompt = prompt.to_owned();
+        panes.input.clear();
+        panes.hidden = hidden;
+    });
+    let mut history_pos: Option<usize> = None;
+    loop {
+        let event = read().map_err(|e| format!("failed to read terminal event: {}", e))?;
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Resize(_, _) => {
+                redraw();
+                continue;
+            },
+            _ => continue,
+        };
+        let mut result = None;
+        update(|panes| match key {
+            KeyEvent { code: KeyCode::Char('c'), modifiers, .. } if modifiers.contains(KeyModifiers::CONTROL) => {
+                result = Some(Err(INPUT_INTERRUPTED.to_owned()));
+            },
+            KeyEvent { code: KeyCode::Char('d'), modifiers, .. } if modifiers.contains(KeyModifiers::CONTROL) => {
+                result = Some(Err(END_OF_INPUT.to_owned()));
+            },
+            KeyEvent { code: KeyCode::Char('u'), modifiers, .. } if modifiers.contains(KeyModifiers::CONTROL) => {
+                panes.input.clear();
+            },
+            KeyEvent { code: KeyCode::Char(c), .. } => panes.input.push(c),
+            KeyEvent { code: KeyCode::Backspace, .. } => { panes.input.pop(); },
+            KeyEvent { code: KeyCode::Enter, .. } => {
+                let line = std::mem::take(&mut panes.input);
+                if !hidden {
+                    panes.push_log(&format!("{}{}", panes.prompt, line));
+                    if !line.trim().is_empty() {
+                        panes.history.push(line.clone());
+                    }
+                }
+                panes.prompt.clear();
+                panes.scroll = 0;
+                result = Some(Ok(line));
+            },
+            KeyEvent { code: KeyCode::Up, .. } if !hidden && !panes.history.is_empty() => {
+                let pos = history_pos.map(|p: usize| p.saturating_sub(1)).unwrap_or(panes.history.len() - 1);
+                history_pos = Some(pos);
+                panes.input = panes.history[pos].clone();
+            },
+            KeyEvent { code: KeyCode::Down, .. } if !hidden => {
+                match history_pos {
+                    Some(pos) if pos + 1 < panes.history.len() => {
+                        history_pos = Some(pos + 1);
+                        panes.input = panes.history[pos + 1].clone();
+                    },
+                    _ => {
+                        history_pos = None;
+                        panes.input.clear();
+                    },
+                }
+            },
+            KeyEvent { code: KeyCode::PageUp, .. } => {
+                panes.scroll = (panes.scroll + 10).min(panes.log.len());
+            },
+            KeyEvent { code: KeyCode::PageDown, .. } => {
+                panes.scroll = panes.scroll.saturating_sub(10);
+            },
+            _ => {},
+        });
+        if let Some(result) = result {
+            return result;
+        }
+    }
+}
+
+fn draw(screen: &mut Screen) {
+    let panes = &screen.panes;
+    let _ = screen.terminal.draw(|f| render(f, panes));
+}
+
+fn render<B: Backend>(f: &mut Frame<B>, panes: &Panes) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(3), Constraint::Length(1)].as_ref())
+        .split(f.size());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)].as_ref())
+        .split(rows[0]);
+    let side = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)].as_ref())
+        .split(columns[1]);
+
+    let log_title = if panes.scroll > 0 { tr("Debot log (scrolled, PgDn to return)") } else { tr("Debot log") };
+    render_lines(f, columns[0], log_title, panes.visible_log(columns[0].height.saturating_sub(2) as usize));
+    render_lines(f, side[0], tr("Menu"), &panes.menu);
+    render_lines(f, side[1], &trf("Pending messages ({})", &[&panes.pending.len()]), &panes.pending);
+
+    let input = if panes.hidden { "*".repeat(panes.input.chars().count()) } else { panes.input.clone() };
+    let input_box = Paragraph::new(Spans::from(vec![
+        Span::styled(panes.prompt.clone(), Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(input.clone()),
+    ]))
+    .block(Block::default().borders(Borders::ALL).title("Input"));
+    f.render_widget(input_box, rows[1]);
+    let cursor = (panes.prompt.chars().count() + input.chars().count()) as u16;
+    f.set_cursor((rows[1].x + 1 + cursor).min(rows[1].right().saturating_sub(2)), rows[1].y + 1);
+
+    let status = Paragraph::new(Span::styled(panes.status.clone(), Style::default().add_modifier(Modifier::REVERSED)));
+    f.render_widget(status, rows[2]);
+}
+
+fn render_lines<B: Backend>(f: &mut Frame<B>, area: Rect, title: &str, lines: &[String]) {
+    let items: Vec<ListItem> = lines.iter().map(|line| ListItem::new(line.as_str())).collect();
+    let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title.to_owned()));
+    f.render_widget(list, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_log() {
+        let mut panes = Panes::default();
+        panes.push_log("1\n2\n3\n4");
+        assert_eq!(panes.visible_log(2), ["3", "4"]);
+        panes.scroll = 1;
+        assert_eq!(panes.visible_log(2), ["2", "3"]);
+        panes.scroll = 10;
+        assert!(panes.visible_log(2).is_empty());
+    }
+}
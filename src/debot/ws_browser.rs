@@ -0,0 +1,655 @@
+/*
+* Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+*
+* Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+* this file except in compliance with the License.
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific TON DEV software governing permissions and
+* limitations under the License.
+*/
+
+//! Debot browser driven by a remote UI over websocket.
+//!
+//! Every frame is a json object with "type" field. Browser sends notifications
+//! `log {text}`, `switch {context}`, `show_action {index, name, desc}`,
+//! `error {text}`, `finished {}` and requests `input {id, prompt}`,
+//! `approve {id, debot, code_hash, action, action_desc, function, inputs,
+//! destination, public, data}`,
+//! `select {id, actions}`, `interface {id, interface, function, args}`.
+//! Approve request with `trust: true` asks to pin debot code hash
+//! (`approved_code_hash` is set if the code has changed since last approval).
+//! UI answers requests with frames containing the same "id" and
+//! `value` (input), `approved` (approve) or `index` (select, one-based).
+//!
+//! Calls of interactive debot interfaces (Terminal, Menu, AddressInput) are
+//! forwarded to the UI as `interface` requests with ABI-decoded arguments
+//! (bytes in hex). UI answers with `value` holding the function outputs in the
+//! same form, e.g. `{"value": "<hex>"}` for `Terminal.inputStr` or
+//! `{"index": 0}` for `Menu.select`, or with `error` to fail the call.
+//! Answers to `Menu.select` may set `answer_id`, by default the handler of the
+//! selected item is called. Output of Stdout and `Terminal.print` is sent as
+//! `log` notifications.
+//!
+//! The server listens on loopback interface only. UI must connect to the URL
+//! printed at startup, which contains a random per-run token, and browsers may
+//! connect only from local pages (`Origin` with localhost host).
+//!
+//! Invoking other debots is not supported.
+use super::budget::{check_budget, ResendPolicy, SessionBudget, SharedBudget};
+use super::interfaces::dinterface::{decode_answer_id, decode_string_arg};
+use super::interfaces::{address_input, menu, stdout, terminal};
+use super::cache;
+use super::signing_policy::{describe_action, describe_call, DebotCall, SigningPolicy};
+use super::trust_store::{query_code_hash, TrustStatus, TrustStore};
+use super::SupportedInterfaces;
+use crate::config::Config;
+use crate::helpers::{create_client, create_client_local, TonClient};
+use crate::i18n::{tr, trf};
+use crate::metrics;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::{http, Message};
+use ton_client::boc::{parse_message, ParamsOfParse};
+use ton_client::crypto::{
+    generate_random_bytes, remove_signing_box, sign, KeyPair, ParamsOfGenerateRandomBytes, ParamsOfSign,
+    RegisteredSigningBox, SigningBox, SigningBoxHandle,
+};
+use ton_client::abi::Abi;
+use ton_client::debot::{BrowserCallbacks, DAction, DEngine, DebotInterface, InterfaceResult, STATE_EXIT};
+use ton_client::encoding::decode_abi_number;
+use ton_client::error::{ClientError, ClientResult};
+use ton_client::ClientContext;
+
+/// Connection to the remote UI. Replies are matched to requests by id.
+#[derive(Clone)]
+struct RemoteUi {
+    out: mpsc::UnboundedSender<String>,
+    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl RemoteUi {
+    fn new(out: mpsc::UnboundedSender<String>) -> Self {
+        Self { out, pending: Default::default(), next_id: Arc::new(AtomicU64::new(1)) }
+    }
+
+    fn notify(&self, kind: &str, mut value: Value) {
+        value["type"] = json!(kind);
+        let _ = self.out.send(value.to_string());
+    }
+
+    async fn request(&self, kind: &str, mut value: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        value["id"] = json!(id);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+        self.notify(kind, value);
+        receiver.await.map_err(|_| "remote UI disconnected".to_owned())
+    }
+
+    fn reply(&self, value: Value) {
+        let sender = value["id"].as_u64()
+            .and_then(|id| self.pending.lock().unwrap().remove(&id));
+        match sender {
+            Some(sender) => { let _ = sender.send(value); },
+            None => debug!("unexpected frame from remote UI: {}", value),
+        }
+    }
+
+    /// Fails all pending requests when the UI disconnects.
+    fn close(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+/// Names of interfaces executed by the remote UI, by interface id.
+fn remote_interface_name(id: &str) -> Option<&'static str> {
+    match id {
+        terminal::ID => Some("Terminal"),
+        menu::ID => Some("Menu"),
+        address_input::ID => Some("AddressInput"),
+        stdout::STDOUT_ID => Some("Stdout"),
+        _ => None,
+    }
+}
+
+/// Debot interface which forwards calls to the remote UI instead of
+/// reading from the server terminal.
+struct RemoteInterface {
+    ui: RemoteUi,
+    name: &'static str,
+    inner: Arc<dyn DebotInterface + Send + Sync>,
+}
+
+/// Returns id of the debot function which receives the answer of the UI.
+fn remote_answer_id(args: &Value, answer: &Value) -> Result<u32, String> {
+    if let Some(answer_id) = answer["answer_id"].as_u64() {
+        return Ok(answer_id as u32);
+    }
+    if let Some(items) = args["items"].as_array() {
+        let index = answer["value"]["index"].as_u64()
+            .ok_or("menu answer has no index".to_owned())?;
+        let item = items.get(index as usize)
+            .ok_or(format!("menu item {} not found", index))?;
+        let handler_id = item["handlerId"].as_str()
+            .ok_or("menu item has no handler id".to_owned())?;
+        return decode_abi_number(handler_id).map_err(|e| format!("{}", e));
+    }
+    decode_answer_id(args)
+}
+
+#[async_trait::async_trait]
+impl DebotInterface for RemoteInterface {
+    fn get_id(&self) -> String {
+        self.inner.get_id()
+    }
+
+    fn get_abi(&self) -> Abi {
+        self.inner.get_abi()
+    }
+
+    async fn call(&self, func: &str, args: &Value) -> InterfaceResult {
+        if func == "print" {
+            let answer_id = if self.name == "Stdout" { 0 } else { decode_answer_id(args)? };
+            self.ui.notify("log", json!({ "text": decode_string_arg(args, "message")? }));
+            return Ok((answer_id, json!({})));
+        }
+        let answer = self.ui.request("interface", json!({
+            "interface": self.name,
+            "function": func,
+            "args": args,
+        })).await?;
+        if let Some(error) = answer["error"].as_str() {
+            return Err(format!("{}.{} failed in remote UI: {}", self.name, func, error));
+        }
+        let answer_id = remote_answer_id(args, &answer)?;
+        Ok((answer_id, answer["value"].clone()))
+    }
+}
+
+/// Signing box which asks remote UI to approve every signature
+/// if the requesting debot action is not whitelisted or the session
+/// spending limit is exceeded.
+struct RemoteSigningBox {
+    ui: RemoteUi,
+    keys: KeyPair,
+    debot: String,
+    code_hash: String,
+    action: Option<DAction>,
+    call: Option<DebotCall>,
+    allowed: bool,
+    client: TonClient,
+    budget: SharedBudget,
+}
+
+#[async_trait::async_trait]
+impl SigningBox for RemoteSigningBox {
+    async fn get_public_key(&self, _context: Arc<ClientContext>) -> ClientResult<Vec<u8>> {
+        hex::decode(&self.keys.public)
+            .map_err(|e| ClientError::with_code_message(0, format!("invalid public key: {}", e)))
+    }
+
+    async fn sign(&self, context: Arc<ClientContext>, unsigned: &Vec<u8>) -> ClientResult<Vec<u8>> {
+        let warning = check_budget(&self.budget, self.client.clone()).await
+            .map_err(|e| ClientError::with_code_message(0, e))?;
+        if !self.allowed || warning.is_some() {
+            let answer = self.ui.request("approve", json!({
+                "debot": self.debot,
+                "code_hash": self.code_hash,
+                "action": self.action.as_ref().map(|a| a.name.clone()),
+                "action_desc": describe_action(self.action.as_ref()),
+                "function": describe_call(self.call.as_ref()),
+                "inputs": self.call.as_ref().map(|c| c.inputs.clone()).unwrap_or_default(),
+                "destination": self.debot,
+                "public": self.keys.public,
+                "data": hex::encode(unsigned),
+                "budget_exceeded": warning,
+            })).await
+            .map_err(|e| ClientError::with_code_message(0, e))?;
+            if answer["approved"].as_bool() != Some(true) {
+                return Err(ClientError::with_code_message(0, "signature rejected by user".to_owned()));
+            }
+        }
+        let result = sign(
+            context,
+            ParamsOfSign {
+                unsigned: base64::encode(unsigned),
+                keys: self.keys.clone(),
+            },
+        )?;
+        self.budget.lock().await.record_signature();
+        hex::decode(&result.signature)
+            .map_err(|e| ClientError::with_code_message(0, format!("invalid signature: {}", e)))
+    }
+}
+
+#[derive(Default)]
+struct SessionState {
+    state_id: u8,
+    active_actions: Vec<DAction>,
+    current_action: Option<DAction>,
+    msg_queue: VecDeque<String>,
+}
+
+struct WsCallbacks {
+    ui: RemoteUi,
+    client: TonClient,
+    address: String,
+    keys: Option<KeyPair>,
+    policy: SigningPolicy,
+    budget: SharedBudget,
+    state: Arc<RwLock<SessionState>>,
+    /// Signing boxes registered in the SDK client during the session.
+    handles: Arc<Mutex<Vec<SigningBoxHandle>>>,
+}
+
+/// Owns resources of a remote UI session: the debot engine and signing boxes
+/// registered in the SDK client. Registered boxes hold the client, so they are
+/// removed explicitly when the session ends, successfully or not.
+struct WsSession {
+    client: TonClient,
+    handles: Arc<Mutex<Vec<SigningBoxHandle>>>,
+    debot: Option<DEngine>,
+}
+
+impl Drop for WsSession {
+    fn drop(&mut self) {
+        self.debot = None;
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+        let count = handles.len();
+        for handle in handles {
+            if let Err(e) = remove_signing_box(self.client.clone(), RegisteredSigningBox { handle }) {
+                debug!("failed to remove signing box: {}", e);
+            }
+        }
+        debug!("debot session closed, {} signing boxes released", count);
+    }
+}
+
+#[async_trait::async_trait]
+impl BrowserCallbacks for WsCallbacks {
+    async fn log(&self, msg: String) {
+        self.ui.notify("log", json!({ "text": msg }));
+    }
+
+    async fn switch(&self, ctx_id: u8) {
+        let mut state = self.state.write().unwrap();
+        state.state_id = ctx_id;
+        state.active_actions = vec![];
+        self.ui.notify("switch", json!({ "context": ctx_id }));
+    }
+
+    async fn switch_completed(&self) {
+    }
+
+    async fn show_action(&self, act: DAction) {
+        let mut state = self.state.write().unwrap();
+        self.ui.notify("show_action", json!({
+            "index": state.active_actions.len() + 1,
+            "name": act.name,
+            "desc": act.desc,
+        }));
+        state.active_actions.push(act);
+    }
+
+    async fn input(&self, prefix: &str, value: &mut String) {
+        match self.ui.request("input", json!({ "prompt": prefix })).await {
+            Ok(answer) => *value = answer["value"].as_str().unwrap_or("").to_owned(),
+            Err(e) => debug!("input failed: {}", e),
+        }
+    }
+
+    async fn get_signing_box(&self) -> Result<SigningBoxHandle, String> {
+        let keys = self.keys.clone()
+            .ok_or("browser has no keys to sign messages (use --sign)".to_owned())?;
+        let code_hash = self.check_trust().await?;
+        let action = self.state.read().unwrap().current_action.clone();
+        let debot_abi = cache::get_debot_abi(self.client.clone(), &self.address).await;
+        let call = DebotCall::decode(debot_abi.as_deref(), action.as_ref());
+        let allowed = self.policy.is_allowed(&self.address, &code_hash, call.as_ref());
+        let signing_box = RemoteSigningBox {
+            ui: self.ui.clone(),
+            keys,
+            debot: self.address.clone(),
+            code_hash,
+            action,
+            call,
+            allowed,
+            client: self.client.clone(),
+            budget: self.budget.clone(),
+        };
+        let handle = ton_client::crypto::register_signing_box(self.client.clone(), signing_box).await
+            .map(|r| r.handle)
+            .map_err(|e| e.to_string())?;
+        self.handles.lock().unwrap().push(handle.clone());
+        Ok(handle)
+    }
+
+    async fn invoke_debot(&self, debot: String, _action: DAction) -> Result<(), String> {
+        Err(format!("invoking debot {} is not supported by websocket browser", debot))
+    }
+
+    async fn send(&self, message: String) {
+        self.state.write().unwrap().msg_queue.push_back(message);
+    }
+}
+
+impl WsCallbacks {
+    /// Asks the UI to approve debot code hash unless it is already pinned.
+    /// Returns the approved code hash.
+    async fn check_trust(&self) -> Result<String, String> {
+        let code_hash = query_code_hash(self.client.clone(), &self.address).await?;
+        let mut store = TrustStore::load();
+        let approved = match store.status(&self.address, &code_hash) {
+            TrustStatus::Trusted => return Ok(code_hash),
+            TrustStatus::Changed(approved) => Some(approved),
+            TrustStatus::Unknown => None,
+        };
+        let answer = self.ui.request("approve", json!({
+            "trust": true,
+            "debot": self.address,
+            "code_hash": code_hash,
+            "approved_code_hash": approved,
+        })).await?;
+        if answer["approved"].as_bool() != Some(true) {
+            return Err(format!("debot {} is not trusted", self.address));
+        }
+        store.approve(&self.address, &code_hash)?;
+        Ok(code_hash)
+    }
+}
+
+/// Parses listen address, only loopback addresses are allowed.
+fn parse_listen_addr(listen: &str) -> Result<SocketAddr, String> {
+    let addr: SocketAddr = listen.parse()
+        .map_err(|e| format!("invalid listen address {}: {}", listen, e))?;
+    if !addr.ip().is_loopback() {
+        return Err(format!("debot browser can listen only on loopback address, got {}", addr.ip()));
+    }
+    Ok(addr)
+}
+
+fn generate_token() -> Result<String, String> {
+    let bytes = generate_random_bytes(create_client_local()?, ParamsOfGenerateRandomBytes { length: 16 })
+        .map_err(|e| format!("failed to generate access token: {}", e))?
+        .bytes;
+    base64::decode(&bytes)
+        .map(hex::encode)
+        .map_err(|e| format!("failed to generate access token: {}", e))
+}
+
+/// Pages opened from other sites must not drive the session. Clients which
+/// are not browsers don't send `Origin`.
+fn is_local_origin(origin: Option<&str>) -> bool {
+    let origin = match origin {
+        Some(origin) => origin,
+        None => return true,
+    };
+    let host = origin.splitn(2, "://").nth(1).unwrap_or("");
+    let host = if host.starts_with('[') {
+        host.split(']').next().map(|h| format!("{}]", h)).unwrap_or_default()
+    } else {
+        host.split(':').next().unwrap_or("").to_owned()
+    };
+    ["localhost", "127.0.0.1", "[::1]"].contains(&host.as_str())
+}
+
+fn has_token(uri: &str, token: &str) -> bool {
+    uri.splitn(2, '?').nth(1).unwrap_or("")
+        .split('&')
+        .any(|param| param == format!("token={}", token))
+}
+
+fn reject(status: http::StatusCode, reason: &str) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(reason.to_owned()));
+    *response.status_mut() = status;
+    response
+}
+
+/// Accepts websocket connections one by one and runs a debot session for each of them.
+pub async fn serve_debot_browser(
+    listen: &str,
+    address: &str,
+    keys: Option<KeyPair>,
+    config: Config,
+) -> Result<(), String> {
+    let listen = parse_listen_addr(listen)?;
+    let token = generate_token()?;
+    let mut listener = TcpListener::bind(listen).await
+        .map_err(|e| format!("failed to listen on {}: {}", listen, e))?;
+    println!("{}", trf("Debot browser is listening on ws://{}/?token={}", &[&listen, &token]));
+    loop {
+        let (stream, peer) = listener.accept().await
+            .map_err(|e| format!("failed to accept connection: {}", e))?;
+        println!("{}", trf("Remote UI connected from {}", &[&peer]));
+        match run_session(stream, &token, address, keys.clone(), &config).await {
+            Ok(_) => println!("{}", tr("Session finished.")),
+            Err(e) => println!("{}", trf("Session failed: {}", &[&e])),
+        }
+    }
+}
+
+async fn run_session(
+    stream: TcpStream,
+    token: &str,
+    address: &str,
+    keys: Option<KeyPair>,
+    config: &Config,
+) -> Result<(), String> {
+    let ws = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        let origin = request.headers().get("origin").and_then(|o| o.to_str().ok());
+        if !is_local_origin(origin) {
+            return Err(reject(http::StatusCode::FORBIDDEN, "foreign origin"));
+        }
+        if !has_token(&request.uri().to_string(), token) {
+            return Err(reject(http::StatusCode::UNAUTHORIZED, "invalid token"));
+        }
+        Ok(response)
+    }).await
+    .map_err(|e| format!("websocket handshake failed: {}", e))?;
+    let (mut sink, mut source) = ws.split();
+    let (out, mut out_receiver) = mpsc::unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(text) = out_receiver.recv().await {
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+    let ui = RemoteUi::new(out);
+    let reader_ui = ui.clone();
+    tokio::spawn(async move {
+        while let Some(Ok(frame)) = source.next().await {
+            match frame {
+                Message::Text(text) => match serde_json::from_str::<Value>(&text) {
+                    Ok(value) => reader_ui.reply(value),
+                    Err(e) => reader_ui.notify("error", json!({ "text": format!("invalid frame: {}", e) })),
+                },
+                Message::Close(_) => break,
+                _ => {},
+            }
+        }
+        reader_ui.close();
+    });
+
+    let ton = create_client(config)?;
+    let interfaces = SupportedInterfaces::new(ton.clone(), config).wrap(|iface| {
+        match remote_interface_name(&iface.get_id()) {
+            Some(name) => Arc::new(RemoteInterface { ui: ui.clone(), name, inner: iface }) as Arc<dyn DebotInterface + Send + Sync>,
+            None => iface,
+        }
+    });
+    let state = Arc::new(RwLock::new(SessionState::default()));
+    let budget: SharedBudget = Arc::new(tokio::sync::Mutex::new(SessionBudget::new(config)));
+    let handles: Arc<Mutex<Vec<SigningBoxHandle>>> = Default::default();
+    let callbacks = Arc::new(WsCallbacks {
+        ui: ui.clone(),
+        client: ton.clone(),
+        address: address.to_owned(),
+        keys,
+        policy: SigningPolicy::new(config),
+        budget: budget.clone(),
+        state: state.clone(),
+        handles: handles.clone(),
+    });
+    let mut session = WsSession {
+        client: ton.clone(),
+        handles,
+        debot: Some(DEngine::new_with_client(address.to_owned(), None, ton.clone(), callbacks)),
+    };
+    let debot = session.debot.as_mut().unwrap();
+    debot.start().await?;
+    metrics::inc_debot_sessions();
+
+    let result = session_loop(ton, &ui, &interfaces, &state, debot, &budget, config.retries).await;
+    drop(session);
+    if let Err(e) = &result {
+        ui.notify("error", json!({ "text": e }));
+    }
+    ui.notify("finished", json!({}));
+    result
+}
+
+async fn session_loop(
+    ton: TonClient,
+    ui: &RemoteUi,
+    interfaces: &SupportedInterfaces,
+    state: &Arc<RwLock<SessionState>>,
+    debot: &mut DEngine,
+    budget: &SharedBudget,
+    retries: u8,
+) -> Result<(), String> {
+    loop {
+        let mut next_msg = state.write().unwrap().msg_queue.pop_front();
+        while let Some(msg) = next_msg {
+            if let Err(e) = handle_interface_call(ton.clone(), msg, debot, interfaces, ResendPolicy::new(budget.clone(), retries)).await {
+                ui.notify("error", json!({ "text": format!("debot call failed: {}", e) }));
+            }
+            next_msg = state.write().unwrap().msg_queue.pop_front();
+        }
+        let actions = {
+            let state = state.read().unwrap();
+            if state.state_id == STATE_EXIT || state.active_actions.is_empty() {
+                return Ok(());
+            }
+            state.active_actions.iter()
+                .enumerate()
+                .map(|(i, a)| json!({ "index": i + 1, "name": a.name, "desc": a.desc }))
+                .collect::<Vec<_>>()
+        };
+        let answer = ui.request("select", json!({ "actions": actions })).await?;
+        let act = {
+            let mut state = state.write().unwrap();
+            let act = answer["index"].as_u64()
+                .and_then(|n| state.active_actions.get((n as usize).wrapping_sub(1)))
+                .cloned();
+            if let Some(act) = &act {
+                state.current_action = Some(act.clone());
+            }
+            act
+        };
+        let act = match act {
+            Some(act) => act,
+            None => {
+                ui.notify("error", json!({ "text": "invalid action" }));
+                continue;
+            },
+        };
+        let mut resend = ResendPolicy::new(budget.clone(), retries);
+        let result = loop {
+            resend.start_attempt().await;
+            let result = debot.execute_action(&act).await;
+            metrics::inc_debot_actions(result.is_ok());
+            let e = match result {
+                Ok(value) => break Ok(value),
+                Err(e) => e,
+            };
+            match resend.next(&e).await {
+                Some(n) => ui.notify("log", json!({ "text": format!("message expired, resending ({} of {})", n, retries) })),
+                None => break Err(e),
+            }
+        };
+        if let Err(e) = result {
+            ui.notify("error", json!({ "text": format!("action failed: {}", e) }));
+        }
+    }
+}
+
+async fn handle_interface_call(
+    client: TonClient,
+    msg: String,
+    debot: &mut DEngine,
+    interfaces: &SupportedInterfaces,
+    mut resend: ResendPolicy,
+) -> Result<(), String> {
+    let parsed = parse_message(client, ParamsOfParse { boc: msg.clone() }).await
+        .map_err(|e| format!("{}", e))?;
+    let iface_addr = parsed.parsed["dst"].as_str()
+        .ok_or(format!("parsed message has no dst address"))?;
+    let interface_id = iface_addr.split(':').nth(1).unwrap_or("").to_string();
+    if let Some(result) = interfaces.try_execute(&msg, &interface_id).await {
+        let (func_id, return_args) = result?;
+        loop {
+            resend.start_attempt().await;
+            let e = match debot.send(iface_addr.to_owned(), func_id, return_args.to_string()).await {
+                Ok(_) => break,
+                Err(e) => e,
+            };
+            if resend.next(&e).await.is_none() {
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_checks() {
+        assert!(parse_listen_addr("127.0.0.1:8080").is_ok());
+        assert!(parse_listen_addr("[::1]:8080").is_ok());
+        assert!(parse_listen_addr("0.0.0.0:8080").is_err());
+
+        assert!(is_local_origin(None));
+        assert!(is_local_origin(Some("http://localhost:3000")));
+        assert!(is_local_origin(Some("http://[::1]:3000")));
+        assert!(!is_local_origin(Some("https://localhost.evil.com")));
+        assert!(!is_local_origin(Some("null")));
+
+        assert!(has_token("/?token=abc", "abc"));
+        assert!(has_token("/?x=1&token=abc", "abc"));
+        assert!(!has_token("/?token=abcd", "abc"));
+        assert!(!has_token("/", "abc"));
+    }
+
+    #[test]
+    fn test_remote_answer_id() {
+        let args = json!({ "answerId": "7", "prompt": "" });
+        assert_eq!(remote_answer_id(&args, &json!({ "value": "00" })).unwrap(), 7);
+        assert_eq!(remote_answer_id(&args, &json!({ "answer_id": 9, "value": "00" })).unwrap(), 9);
+
+        let menu = json!({ "items": [
+            { "title": "", "description": "", "handlerId": "16" },
+            { "title": "", "description": "", "handlerId": "32" },
+        ]});
+        assert_eq!(remote_answer_id(&menu, &json!({ "value": { "index": 1 } })).unwrap(), 32);
+        assert!(remote_answer_id(&menu, &json!({ "value": { "index": 2 } })).is_err());
+        assert!(remote_answer_id(&menu, &json!({ "value": {} })).is_err());
+
+        assert_eq!(remote_interface_name(terminal::ID), Some("Terminal"));
+        assert_eq!(remote_interface_name("00"), None);
+    }
+}
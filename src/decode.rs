@@ -12,11 +12,18 @@
  */
 use crate::{print_args, VERBOSE_MODE};
 use crate::config::Config;
-use crate::helpers::{decode_msg_body, create_client_local};
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{decode_msg_body, create_client_local, create_client_verbose, load_abi, now, query, TonClient};
+use crate::multisig::decode_transfer_comment;
+use chrono::{Local, TimeZone};
 use clap::{ArgMatches, SubCommand, Arg, App, AppSettings};
+use serde_json::Value;
 use ton_types::cells_serialization::serialize_tree_of_cells;
 use ton_types::Cell;
 use std::fmt::Write;
+use std::io::{BufRead, Write as IoWrite};
+use ton_client::abi::{decode_account_data, decode_message, Abi, ParamsOfDecodeAccountData, ParamsOfDecodeMessage};
+use ton_client::boc::{parse_message, ParamsOfParse};
 
 fn match_abi_path(matches: &ArgMatches, config: &Config) -> Option<String> {
     matches.value_of("ABI")
@@ -30,6 +37,15 @@ pub fn create_decode_command<'a, 'b>() -> App<'a, 'b> {
         .setting(AppSettings::AllowLeadingHyphen)
         .setting(AppSettings::TrailingVarArg)
         .setting(AppSettings::DontCollapseArgsInUsage)
+        .arg(Arg::with_name("STDIN_LINES")
+            .long("--stdin-lines")
+            .help("Reads message BOCs (base64) from stdin line by line and prints every decoded message as a json line."))
+        .arg(Arg::with_name("ABIS")
+            .long("--abi")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Path to ABI file used with --stdin-lines (can be repeated, the first ABI which decodes the message is used)."))
         .subcommand(SubCommand::with_name("body")
             .arg(Arg::with_name("BODY")
                 .required(true)
@@ -38,6 +54,15 @@ pub fn create_decode_command<'a, 'b>() -> App<'a, 'b> {
                 .long("--abi")
                 .takes_value(true)
                 .help("Path to ABI file.")))
+        .subcommand(SubCommand::with_name("payload")
+            .about("Decodes body of internal message (e.g. payload of multisig submitTransaction).")
+            .arg(Arg::with_name("PAYLOAD")
+                .required(true)
+                .help("Message body encoded as base64."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Path to ABI file of the destination contract.")))
         .subcommand(SubCommand::with_name("msg")
            .arg(Arg::with_name("MSG")
                     .required(true)
@@ -45,17 +70,228 @@ pub fn create_decode_command<'a, 'b>() -> App<'a, 'b> {
             .arg(Arg::with_name("ABI")
                     .long("--abi")
                     .takes_value(true)
-                    .help("Path to ABI file.")))
+                    .help("Path to ABI file."))
+            .arg(Arg::with_name("REPLAY")
+                    .long("--replay")
+                    .help("Checks replay protection of external message against the current state of the destination account: whether it would be accepted now.")))
+}
+
+/// Default replay protection window of Solidity contracts: message time
+/// may be at most 30 minutes ahead of the current time.
+const MAX_TIME_AHEAD_MS: u64 = 30 * 60 * 1000;
+
+/// Replay protection fields of external inbound message.
+#[derive(Default)]
+struct MsgReplayFields {
+    pubkey: Option<String>,
+    /// Milliseconds.
+    time: Option<u64>,
+    expire: Option<u32>,
+    seqno: Option<u64>,
+    has_state_init: bool,
+}
+
+/// Destination account state relevant to replay protection.
+#[derive(Default)]
+struct AccountReplayState {
+    acc_type: Option<String>,
+    pubkey: Option<String>,
+    /// Last accepted message time (milliseconds) of contracts with `time` header.
+    timestamp: Option<u64>,
+    seqno: Option<u64>,
+}
+
+/// Returns reasons why the account would reject the message now.
+fn replay_issues(msg: &MsgReplayFields, acc: &AccountReplayState, now_ms: u64) -> Vec<String> {
+    let mut issues = vec![];
+    match acc.acc_type.as_deref() {
+        None => issues.push("destination account doesn't exist".to_owned()),
+        Some("Active") => {},
+        Some("Uninit") | Some("NonExist") if msg.has_state_init => {},
+        Some(state) => issues.push(format!("destination account is {}, message without state init can't be processed", state)),
+    }
+    if let Some(expire) = msg.expire {
+        let now = now_ms / 1000;
+        if (expire as u64) <= now {
+            issues.push(format!("message expired {} seconds ago", now - expire as u64));
+        }
+    }
+    if let (Some(pubkey), Some(stored)) = (&msg.pubkey, &acc.pubkey) {
+        if pubkey != stored {
+            issues.push(format!("header pubkey {} doesn't match contract pubkey {}", pubkey, stored));
+        }
+    }
+    if let Some(time) = msg.time {
+        if let Some(stored) = acc.timestamp {
+            if time <= stored {
+                issues.push(format!(
+                    "stale timestamp: message time {} is not greater than the last accepted {} (already processed or replayed)",
+                    time, stored,
+                ));
+            }
+        }
+        if time > now_ms + MAX_TIME_AHEAD_MS {
+            issues.push(format!("message time {} is too far in the future (clock skew?)", time));
+        }
+    }
+    if let (Some(seqno), Some(stored)) = (msg.seqno, acc.seqno) {
+        if seqno != stored {
+            issues.push(format!("seqno {} doesn't match contract seqno {}", seqno, stored));
+        }
+    }
+    issues
+}
+
+fn format_time(secs: u64) -> String {
+    Local.timestamp(secs as i64, 0).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn seqno_field(value: &Value) -> Option<u64> {
+    ["seqno", "_seqno", "m_seqno"].iter()
+        .find_map(|name| value.get(*name))
+        .and_then(|v| v.as_str().map(|s| s.to_owned()).or(v.as_u64().map(|n| n.to_string())))
+        .and_then(|s| u64::from_str_radix(&s, 10).ok())
+}
+
+/// Reads pubkey and, if the ABI has `time` header, the last accepted timestamp
+/// which Solidity contracts store at the beginning of data.
+fn stored_replay_fields(data: &str, has_time: bool) -> (Option<String>, Option<u64>) {
+    let cell = match base64::decode(data).ok()
+        .and_then(|bytes| ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes)).ok()) {
+        Some(cell) => cell,
+        None => return (None, None),
+    };
+    let mut slice = ton_types::SliceData::from(cell);
+    let pubkey = slice.get_next_bytes(32).ok().map(hex::encode);
+    let timestamp = if has_time { slice.get_next_u64().ok() } else { None };
+    (pubkey, timestamp)
+}
+
+/// Reports replay protection header of external inbound message and, if `config`
+/// is set, whether the destination account would accept the message now.
+async fn replay_analysis(msg_boc: &[u8], abi: &str, config: Option<&Config>) -> Result<Option<Value>, String> {
+    let ton = create_client_local()?;
+    let boc = base64::encode(msg_boc);
+    let parsed = parse_message(ton.clone(), ParamsOfParse { boc: boc.clone() }).await
+        .map_err(|e| format!("failed to parse message: {}", e))?
+        .parsed;
+    if parsed["msg_type_name"].as_str() != Some("ExtIn") {
+        return Ok(None);
+    }
+    let dst = parsed["dst"].as_str().unwrap_or("").to_owned();
+    let abi_json: Value = serde_json::from_str(abi)
+        .map_err(|e| format!("failed to parse ABI: {}", e))?;
+    let has_time = abi_json["header"].as_array()
+        .map(|h| h.iter().any(|v| v == "time"))
+        .unwrap_or(false);
+    let abi = load_abi(abi)?;
+    let decoded = decode_message(ton.clone(), ParamsOfDecodeMessage { abi: abi.clone(), message: boc }).await
+        .map_err(|e| format!("failed to decode message: {}", e))?;
+    let header = decoded.header.unwrap_or_default();
+    let msg = MsgReplayFields {
+        pubkey: header.pubkey.clone(),
+        time: header.time,
+        expire: header.expire,
+        seqno: decoded.value.as_ref().and_then(seqno_field),
+        has_state_init: parsed["code"].is_string(),
+    };
+    let mut report = json!({
+        "destination": dst,
+        "function": decoded.name,
+        "pubkey": msg.pubkey,
+        "time": msg.time,
+        "time_str": msg.time.map(|t| format_time(t / 1000)),
+        "expire": msg.expire,
+        "expire_str": msg.expire.map(|e| format_time(e as u64)),
+    });
+    let config = match config {
+        Some(config) => config,
+        None => return Ok(Some(report)),
+    };
+
+    let ton = create_client_verbose(config)?;
+    let accounts = query(
+        ton.clone(),
+        "accounts",
+        json!({ "id": { "eq": dst } }),
+        "acc_type_name data",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query account: {}", e))?;
+    let mut acc = AccountReplayState::default();
+    if let Some(account) = accounts.first() {
+        acc.acc_type = account["acc_type_name"].as_str().map(|s| s.to_owned());
+        if let Some(data) = account["data"].as_str() {
+            let (pubkey, timestamp) = stored_replay_fields(data, has_time);
+            acc.pubkey = pubkey;
+            acc.timestamp = timestamp;
+            acc.seqno = decode_account_data(ton.clone(), ParamsOfDecodeAccountData { abi, data: data.to_owned() })
+                .await
+                .ok()
+                .and_then(|d| seqno_field(&d.data));
+        }
+    }
+    let issues = replay_issues(&msg, &acc, now() as u64 * 1000);
+    report["account"] = json!({
+        "acc_type": acc.acc_type,
+        "pubkey": acc.pubkey,
+        "last_timestamp": acc.timestamp,
+        "seqno": acc.seqno,
+    });
+    report["msg_seqno"] = json!(msg.seqno);
+    report["accepted"] = json!(issues.is_empty());
+    report["issues"] = json!(issues);
+    Ok(Some(report))
+}
+
+fn print_replay_report(report: &Value) {
+    println!("Replay protection:");
+    println!("  pubkey:  {}", report["pubkey"].as_str().unwrap_or("none"));
+    match report["time"].as_u64() {
+        Some(time) => println!("  time:    {} ({})", time, report["time_str"].as_str().unwrap_or("")),
+        None => println!("  time:    none"),
+    }
+    match report["expire"].as_u64() {
+        Some(expire) => println!("  expire:  {} ({})", expire, report["expire_str"].as_str().unwrap_or("")),
+        None => println!("  expire:  none"),
+    }
+    if report["accepted"].is_null() {
+        return;
+    }
+    let account = &report["account"];
+    println!("Destination account:");
+    println!("  state:   {}", account["acc_type"].as_str().unwrap_or("not found"));
+    println!("  pubkey:  {}", account["pubkey"].as_str().unwrap_or("unknown"));
+    if let Some(timestamp) = account["last_timestamp"].as_u64() {
+        println!("  last accepted time: {}", timestamp);
+    }
+    if let Some(seqno) = account["seqno"].as_u64() {
+        println!("  seqno:   {}", seqno);
+    }
+    if report["accepted"] == json!(true) {
+        println!("Message would be accepted now.");
+    } else {
+        println!("Message would be rejected:");
+        for issue in report["issues"].as_array().cloned().unwrap_or_default() {
+            println!("  - {}", issue.as_str().unwrap_or(""));
+        }
+    }
 }
 
-pub async fn decode_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+pub async fn decode_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if m.is_present("STDIN_LINES") {
+        return decode_stdin_lines(m, config).await.map_err(CliError::from);
+    }
     if let Some(m) = m.subcommand_matches("body") {
-        return decode_body_command(m, config).await;
+        return decode_body_command(m, config).await.map_err(CliError::from);
     }
     if let Some(m) = m.subcommand_matches("msg") {
-        return decode_message_command(m, config).await;
+        return decode_message_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("payload") {
+        return decode_payload_command(m, config).await.map_err(CliError::from);
     }
-    Err("unknown command".to_owned())
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown command"))
 }
 
 async fn decode_body_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
@@ -71,6 +307,40 @@ async fn decode_body_command(m: &ArgMatches<'_>, config: Config) -> Result<(), S
     Ok(())
 }
 
+async fn decode_payload_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let payload = m.value_of("PAYLOAD");
+    let abi = Some(
+        match_abi_path(m, &config)
+            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+    );
+    if !config.is_json {
+        print_args!(m, payload, abi);
+    }
+    let abi = std::fs::read_to_string(abi.unwrap())
+        .map_err(|e| format!("failed to read ABI file: {}", e))?;
+    let ton = create_client_local()?;
+    let payload = payload.unwrap();
+    match decode_msg_body(ton, &abi, payload, true).await {
+        Ok(decoded) => {
+            let value = decoded.value.unwrap_or(json!({}));
+            if config.is_json {
+                println!("{}", json!({ "function": decoded.name, "params": value }));
+            } else {
+                println!("{}: {}", decoded.name, serde_json::to_string_pretty(&value).unwrap());
+            }
+        },
+        Err(e) => {
+            let comment = decode_transfer_comment(payload).await.map_err(|_| e)?;
+            if config.is_json {
+                println!("{}", json!({ "comment": comment }));
+            } else {
+                println!("Comment: {}", comment);
+            }
+        },
+    }
+    Ok(())
+}
+
 async fn decode_message_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
     let msg = m.value_of("MSG");
     let abi = Some(
@@ -84,10 +354,98 @@ async fn decode_message_command(m: &ArgMatches<'_>, config: Config) -> Result<()
         .transpose()
         .map_err(|e| format!(" failed to read msg boc file: {}", e))?
         .unwrap();
-    println!("{}", decode_message(msg, abi, config.is_json).await?);
+    let abi_text = abi.as_ref()
+        .map(|path| std::fs::read_to_string(path))
+        .transpose()
+        .map_err(|e| format!("failed to read ABI file: {}", e))?;
+    let decoded = decode_message(msg.clone(), abi, config.is_json).await?;
+    let replay = match abi_text {
+        Some(abi) => {
+            let check = if m.is_present("REPLAY") { Some(&config) } else { None };
+            replay_analysis(&msg, &abi, check).await?
+        },
+        None => None,
+    };
+    match replay {
+        Some(report) if config.is_json => {
+            let mut value: Value = serde_json::from_str(&decoded)
+                .map_err(|e| format!("failed to parse decoded message: {}", e))?;
+            value["ReplayProtection"] = report;
+            println!("{}", serde_json::to_string_pretty(&value).unwrap());
+        },
+        Some(report) => {
+            println!("{}", decoded);
+            print_replay_report(&report);
+        },
+        None => println!("{}", decoded),
+    }
     Ok(())
 }
 
+/// Decodes messages from stdin until EOF. Client and ABIs are loaded once.
+async fn decode_stdin_lines(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let mut abi_paths: Vec<String> = m.values_of("ABIS")
+        .map(|v| v.map(|s| s.to_owned()).collect())
+        .unwrap_or_default();
+    if abi_paths.is_empty() {
+        abi_paths.extend(config.abi_path.clone());
+    }
+    let mut abis = vec![];
+    for path in abi_paths {
+        let abi = std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read ABI file {}: {}", path, e))?;
+        abis.push(load_abi(&abi)?);
+    }
+    let ton = create_client_local()?;
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| format!("failed to read stdin: {}", e))?;
+        let boc = line.trim();
+        if boc.is_empty() {
+            continue;
+        }
+        let result = decode_line(ton.clone(), &abis, boc).await;
+        let mut out = stdout.lock();
+        writeln!(out, "{}", result).map_err(|e| format!("failed to write output: {}", e))?;
+        out.flush().map_err(|e| format!("failed to write output: {}", e))?;
+    }
+    Ok(())
+}
+
+async fn decode_line(ton: TonClient, abis: &[Abi], boc: &str) -> serde_json::Value {
+    let parsed = match parse_message(ton.clone(), ParamsOfParse { boc: boc.to_owned() }).await {
+        Ok(parsed) => parsed.parsed,
+        Err(e) => return json!({ "error": format!("failed to parse message: {}", e) }),
+    };
+    let mut result = json!({
+        "id": parsed["id"],
+        "msg_type": parsed["msg_type_name"],
+        "src": parsed["src"],
+        "dst": parsed["dst"],
+        "value": parsed["value"],
+        "created_lt": parsed["created_lt"],
+    });
+    for abi in abis {
+        let decoded = decode_message(
+            ton.clone(),
+            ParamsOfDecodeMessage { abi: abi.clone(), message: boc.to_owned() },
+        ).await;
+        if let Ok(decoded) = decoded {
+            result["function"] = json!(decoded.name);
+            result["params"] = decoded.value.unwrap_or(json!({}));
+            return result;
+        }
+    }
+    if let Some(body) = parsed["body"].as_str() {
+        if let Ok(comment) = decode_transfer_comment(body).await {
+            result["comment"] = json!(comment);
+        }
+    }
+    result
+}
+
 async fn print_decoded_body(body_vec: Vec<u8>, abi: &str, is_json: bool) -> Result<String, String> {
     let ton = create_client_local()?;
     let mut empty_boc = vec![];
@@ -100,7 +458,18 @@ async fn print_decoded_body(body_vec: Vec<u8>, abi: &str, is_json: bool) -> Resu
     let mut res = {
         match decode_msg_body(ton.clone(), abi, &body_base64, false).await {
             Ok(res) => res,
-            Err(_) => decode_msg_body(ton.clone(), abi, &body_base64, true).await?,
+            Err(_) => match decode_msg_body(ton.clone(), abi, &body_base64, true).await {
+                Ok(res) => res,
+                Err(e) => {
+                    // plain transfer with text comment is not described in contract ABI
+                    let comment = decode_transfer_comment(&body_base64).await.map_err(|_| e)?;
+                    return Ok(if is_json {
+                        format!(" \"Comment\": {}", json!(comment))
+                    } else {
+                        format!("Comment: {}", comment)
+                    });
+                },
+            },
         }
     };
     let output = res.value.take().unwrap();
@@ -311,4 +680,36 @@ mod tests {
         let out = decode_body(body, "tests/samples/wallet.abi.json", true).await.unwrap();
         let _ : serde_json::Value = serde_json::from_str(&out).unwrap();
     }
+
+    #[test]
+    fn test_replay_issues() {
+        let now_ms = 1_600_000_000_000;
+        let msg = MsgReplayFields {
+            pubkey: Some("aa".to_owned()),
+            time: Some(now_ms - 1000),
+            expire: Some((now_ms / 1000) as u32 + 60),
+            seqno: Some(5),
+            has_state_init: false,
+        };
+        let mut acc = AccountReplayState {
+            acc_type: Some("Active".to_owned()),
+            pubkey: Some("aa".to_owned()),
+            timestamp: Some(now_ms - 5000),
+            seqno: Some(5),
+        };
+        assert!(replay_issues(&msg, &acc, now_ms).is_empty());
+
+        acc.timestamp = Some(now_ms);
+        acc.pubkey = Some("bb".to_owned());
+        let issues = replay_issues(&msg, &acc, now_ms);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("doesn't match contract pubkey"));
+        assert!(issues[1].starts_with("stale timestamp"));
+
+        let issues = replay_issues(&msg, &AccountReplayState::default(), now_ms + 120_000);
+        assert_eq!(issues, vec![
+            "destination account doesn't exist".to_owned(),
+            "message expired 60 seconds ago".to_owned(),
+        ]);
+    }
 }
\ No newline at end of file
@@ -10,13 +10,36 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
-use crate::helpers::{create_client_verbose, load_abi, calc_acc_address};
+use crate::helpers::{create_client_verbose, load_abi, calc_acc_address, fees_to_json, print_fees, query, skew_header, apply_header_options, TonClient};
+use crate::i18n::{tr, trf};
 use crate::config::Config;
+use crate::convert::nano_to_tokens;
 use crate::crypto::load_keypair;
+use crate::getconfig::check_workchain;
+use crate::history;
+use crate::multisig;
+use crate::output;
 use ton_client::processing::{ParamsOfProcessMessage};
-use ton_client::abi::{Signer, CallSet, DeploySet, ParamsOfEncodeMessage};
+use ton_client::abi::{encode_message, Signer, CallSet, DeploySet, ParamsOfEncodeMessage};
+use ton_client::net::{wait_for_collection, ParamsOfWaitForCollection};
+use ton_client::tvm::{run_executor, AccountForExecutor, ParamsOfRunExecutor, TransactionFees};
 
-pub async fn deploy_contract(conf: Config, tvc: &str, abi: &str, params: &str, keys_file: &str, wc: i32) -> Result<(), String> {
+/// Wallet used to fund contract address before deploy.
+pub struct Topup {
+    pub wallet: String,
+    pub keys: String,
+}
+
+pub async fn deploy_contract(
+    conf: Config,
+    tvc: &str,
+    abi: &str,
+    params: &str,
+    keys_file: &str,
+    wc: i32,
+    dry_run: bool,
+    topup: Option<Topup>,
+) -> Result<(), String> {
     let ton = create_client_verbose(&conf)?;
 
     let abi = std::fs::read_to_string(abi)
@@ -25,6 +48,8 @@ pub async fn deploy_contract(conf: Config, tvc: &str, abi: &str, params: &str, k
 
     let keys = load_keypair(keys_file)?;
 
+    check_workchain(ton.clone(), &conf, wc).await?;
+
     let tvc_bytes = &std::fs::read(tvc)
         .map_err(|e| format!("failed to read smart contract file: {}", e))?;
 
@@ -38,7 +63,6 @@ pub async fn deploy_contract(conf: Config, tvc: &str, abi: &str, params: &str, k
         abi.clone()
     ).await?;
 
-    println!("Deploying...");
     let dset = DeploySet {
         tvc: tvc_base64,
         workchain_id: Some(wc),
@@ -46,27 +70,182 @@ pub async fn deploy_contract(conf: Config, tvc: &str, abi: &str, params: &str, k
     };
     let params = serde_json::from_str(params)
         .map_err(|e| format!("function arguments is not a json: {}", e))?;
+    let (abi, header) = apply_header_options(&conf, abi, skew_header(ton.clone(), &conf).await)?;
+    let mut call_set = CallSet::some_with_function_and_input("constructor", params);
+    if let Some(call_set) = call_set.as_mut() {
+        call_set.header = header;
+    }
+    let encode_params = ParamsOfEncodeMessage {
+        abi,
+        address: Some(addr.clone()),
+        deploy_set: Some(dset),
+        call_set,
+        signer: Signer::Keys{ keys },
+        ..Default::default()
+    };
 
+    if dry_run {
+        return deploy_dry_run(ton, &conf, encode_params, &addr).await;
+    }
+    if let Some(topup) = topup {
+        topup_address(ton.clone(), &conf, encode_params.clone(), &addr, topup).await?;
+    }
+
+    println!("{}", tr("Deploying..."));
     let callback = |_event| { async move { } };
-    ton_client::processing::process_message(
+    let result = ton_client::processing::process_message(
         ton.clone(),
         ParamsOfProcessMessage {
-            message_encode_params: ParamsOfEncodeMessage {
-                abi,
-                address: Some(addr.clone()),
-                deploy_set: Some(dset),
-                call_set: CallSet::some_with_function_and_input("constructor", params),
-                signer: Signer::Keys{ keys },
-                ..Default::default()
-            },
+            message_encode_params: encode_params,
             send_events: true,
             ..Default::default()
         },
         callback,
     ).await
-    .map_err(|e| format!("deploy failed: {:#}", e))?;
+    .map_err(|e| format!("deploy failed: {:#}", e));
+    let message_id = result.as_ref()
+        .map(|r| r.transaction["in_msg"].as_str().unwrap_or("").to_owned())
+        .unwrap_or_default();
+    let status = result.as_ref().map(|_| ()).map_err(|e| e.clone());
+    history::record(&addr, Some("constructor".to_owned()), None, message_id, &status);
+    let result = result?;
+
+    println!("{}", tr("Transaction succeeded."));
+    let value = json!({ "address": addr, "fees": fees_to_json(&result.fees) });
+    output::print_value(&value, || {
+        if conf.is_json {
+            println!("{}", serde_json::to_string_pretty(&value).unwrap_or_default());
+        } else {
+            print_fees(&result.fees, false);
+            println!("{}", trf("Contract deployed at address: {}", &[&addr]));
+        }
+    })
+}
+struct DeployEstimate {
+    acc_type: String,
+    balance: u64,
+    fees: TransactionFees,
+}
+
+impl DeployEstimate {
+    fn required(&self) -> u64 {
+        self.fees.total_account_fees + self.fees.total_output
+    }
+}
+
+/// Queries current state of the future contract address and emulates deploy on it.
+async fn estimate_deploy(ton: TonClient, params: ParamsOfEncodeMessage, addr: &str) -> Result<DeployEstimate, String> {
+    let accounts = query(
+        ton.clone(),
+        "accounts",
+        json!({ "id": { "eq": addr } }),
+        "acc_type_name balance(format: DEC)",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query account: {}", e))?;
+    let (acc_type, balance) = match accounts.get(0) {
+        Some(acc) => (
+            acc["acc_type_name"].as_str().unwrap_or("unknown").to_owned(),
+            acc["balance"].as_str().and_then(|b| u64::from_str_radix(b, 10).ok()).unwrap_or(0),
+        ),
+        None => ("NonExist".to_owned(), 0),
+    };
+    if acc_type == "Active" || acc_type == "Frozen" {
+        return Err(format!("contract is already deployed (account status: {})", acc_type));
+    }
 
-    println!("Transaction succeeded.");
-    println!("Contract deployed at address: {}", addr);
+    let msg = encode_message(ton.clone(), params).await
+        .map_err(|e| format!("failed to create deploy message: {}", e))?;
+    let result = run_executor(
+        ton,
+        ParamsOfRunExecutor {
+            message: msg.message,
+            account: AccountForExecutor::Uninit,
+            skip_transaction_check: Some(true),
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| format!("deploy emulation failed: {:#}", e))?;
+
+    Ok(DeployEstimate { acc_type, balance, fees: result.fees })
+}
+
+/// Emulates deploy without sending the message and reports how many tokens
+/// must be sent to the future contract address before the deploy.
+async fn deploy_dry_run(ton: TonClient, conf: &Config, params: ParamsOfEncodeMessage, addr: &str) -> Result<(), String> {
+    let estimate = estimate_deploy(ton, params, addr).await?;
+    let required = estimate.required();
+    if conf.is_json {
+        println!("{}", serde_json::to_string_pretty(&json!({
+            "address": addr,
+            "acc_type": estimate.acc_type,
+            "balance": estimate.balance.to_string(),
+            "fees": fees_to_json(&estimate.fees),
+            "required": required.to_string(),
+        })).unwrap_or_default());
+        return Ok(());
+    }
+    println!("{}", trf("Contract address: {}", &[&addr]));
+    println!("{}", trf("Account status: {}", &[&estimate.acc_type]));
+    println!("{}", trf("Account balance: {}", &[&nano_to_tokens(estimate.balance)]));
+
+    print_fees(&estimate.fees, false);
+    println!("{}", trf("Required balance: {}", &[&nano_to_tokens(required)]));
+    if estimate.balance >= required {
+        println!("{}", tr("Account balance is sufficient for deploy."));
+    } else {
+        println!("{}", trf("Send at least {} tokens to {} before deploy.", &[&nano_to_tokens(required - estimate.balance), &addr]));
+    }
+    Ok(())
+}
+
+/// Sends missing amount from the wallet to the contract address and waits until it is credited.
+async fn topup_address(ton: TonClient, conf: &Config, params: ParamsOfEncodeMessage, addr: &str, topup: Topup) -> Result<(), String> {
+    let estimate = estimate_deploy(ton.clone(), params, addr).await?;
+    let required = estimate.required();
+    if estimate.balance >= required {
+        println!("{}", trf("Account balance {} is sufficient for deploy.", &[&nano_to_tokens(estimate.balance)]));
+        return Ok(());
+    }
+    let wallet_wc = topup.wallet.split(':').next()
+        .and_then(|wc| i32::from_str_radix(wc, 10).ok())
+        .ok_or(format!("invalid wallet address: {}", topup.wallet))?;
+    check_workchain(ton.clone(), conf, wallet_wc).await
+        .map_err(|e| format!("wallet {} can't send messages: {}", topup.wallet, e))?;
+    let wallet = query(
+        ton.clone(),
+        "accounts",
+        json!({ "id": { "eq": topup.wallet } }),
+        "acc_type_name balance(format: DEC)",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query wallet: {}", e))?;
+    let wallet_balance = wallet.get(0)
+        .filter(|w| w["acc_type_name"].as_str() == Some("Active"))
+        .and_then(|w| w["balance"].as_str())
+        .and_then(|b| u64::from_str_radix(b, 10).ok())
+        .ok_or(format!("wallet {} is not active", topup.wallet))?;
+    if wallet_balance <= required - estimate.balance {
+        return Err(format!("wallet {} doesn't have enough tokens: {}", topup.wallet, nano_to_tokens(wallet_balance)));
+    }
+    let missing = nano_to_tokens(required - estimate.balance);
+    println!("{}", trf("Sending {} tokens from {} to {}...", &[&missing, &topup.wallet, &addr]));
+    multisig::send_nobounce(conf.clone(), &topup.wallet, addr, &missing, &topup.keys).await?;
+
+    println!("{}", tr("Waiting for tokens to be credited..."));
+    wait_for_collection(
+        ton,
+        ParamsOfWaitForCollection {
+            collection: "accounts".to_owned(),
+            filter: Some(json!({
+                "id": { "eq": addr },
+                "balance": { "ge": format!("0x{:x}", required) },
+            })),
+            result: "balance(format: DEC)".to_owned(),
+            timeout: Some(conf.network_policy.timeout.unwrap_or(conf.wait_for_timeout)),
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| format!("tokens were not credited to {}: {}", addr, e))?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -13,13 +13,17 @@
 use crate::{print_args, VERBOSE_MODE};
 use crate::config::Config;
 use crate::convert;
-use crate::depool_abi::DEPOOL_ABI;
-use crate::helpers::{create_client_local, create_client_verbose, load_abi, load_ton_address, now, TonClient};
+use crate::depool_abi::PARTICIPANT_ABI;
+use crate::error::{CliError, ErrorKind};
+use crate::known_abi::{detect_abi, ContractFamily};
+use crate::helpers::{create_client, create_client_local, create_client_verbose, decode_msg_body, load_abi, load_ton_address, now, subscribe, TonClient, BulkQuery};
 use crate::multisig::send_with_body;
+use crate::wallet::prompt;
+use chrono::{Local, TimeZone};
 use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
 use serde_json;
 use ton_client::abi::{ParamsOfEncodeMessageBody, ParamsOfDecodeMessageBody, CallSet};
-use ton_client::net::{OrderBy, ParamsOfQueryCollection, ParamsOfWaitForCollection, SortDirection};
+use ton_client::net::{unsubscribe, ParamsOfWaitForCollection};
 
 pub fn create_depool_command<'a, 'b>() -> App<'a, 'b> {
     let wallet_arg = Arg::with_name("MSIG")
@@ -57,6 +61,16 @@ pub fn create_depool_command<'a, 'b>() -> App<'a, 'b> {
         .long("--donor")
         .short("-d")
         .help("Donor smart contract address.");
+    let schedule_arg = Arg::with_name("SCHEDULE")
+        .takes_value(true)
+        .long("--schedule")
+        .conflicts_with_all(&["TPERIOD", "WPERIOD"])
+        .help("Unlock schedule instead of --total and --withdrawal, e.g. \"24m total, monthly\" or \"6m cliff, 24m total, every 6m\". Units: d, w, m (30 days), y (365 days). The unlock timeline is previewed before the stake is sent.");
+    let yes_arg = Arg::with_name("YES")
+        .long("--yes")
+        .short("-y")
+        .requires("SCHEDULE")
+        .help("Sends the stake without asking for confirmation after the schedule preview.");
     let dest_arg = Arg::with_name("DEST")
         .takes_value(true)
         .long("--dest")
@@ -100,6 +114,8 @@ pub fn create_depool_command<'a, 'b>() -> App<'a, 'b> {
                 .arg(keys_arg.clone())
                 .arg(total_period_arg.clone())
                 .arg(withdrawal_period_arg.clone())
+                .arg(schedule_arg.clone())
+                .arg(yes_arg.clone())
                 .arg(beneficiary_arg.clone()))
             .subcommand(SubCommand::with_name("lock")
                 .about("Deposits lock stake in depool from multisignature wallet.")
@@ -109,6 +125,8 @@ pub fn create_depool_command<'a, 'b>() -> App<'a, 'b> {
                 .arg(keys_arg.clone())
                 .arg(total_period_arg.clone())
                 .arg(withdrawal_period_arg.clone())
+                .arg(schedule_arg.clone())
+                .arg(yes_arg.clone())
                 .arg(beneficiary_arg.clone()))
             .subcommand(SubCommand::with_name("transfer")
                 .about("Transfers ownership of wallet stake to another contract.")
@@ -163,6 +181,27 @@ pub fn create_depool_command<'a, 'b>() -> App<'a, 'b> {
                 .long("--wait-one")
                 .short("-w")
                 .help("Waits until new event will be emitted.")) )
+        .subcommand(SubCommand::with_name("watch")
+            .about("Watches depool events and round completion notifications of the participant and applies reinvest/withdraw policy after every completed round.")
+            .setting(AppSettings::AllowLeadingHyphen)
+            .arg(Arg::with_name("PARTICIPANT")
+                .takes_value(true)
+                .long("--participant")
+                .short("-p")
+                .required(true)
+                .help("Participant address."))
+            .arg(Arg::with_name("POLICY")
+                .takes_value(true)
+                .long("--policy")
+                .possible_values(&["none", "reinvest", "withdraw-reward", "withdraw-all"])
+                .help("Action after round completion: none (only report, default), reinvest (cancel requested withdrawal), withdraw-reward (withdraw the round reward) or withdraw-all (withdraw all stakes)."))
+            .arg(Arg::with_name("MIN_REWARD")
+                .takes_value(true)
+                .long("--min-reward")
+                .help("Applies policy only to rounds with reward not less than this value (in tons)."))
+            .arg(wallet_arg.clone()
+                .help("Multisig wallet of the participant used to apply policy (default: participant address)."))
+            .arg(keys_arg.clone()))
 }
 
 struct CommandData<'a> {
@@ -203,20 +242,20 @@ fn parse_stake_data<'a>(m: &'a ArgMatches, conf: &Config) -> Result<(String, &'a
     Ok((wallet, stake, keys))
 }
 
-pub async fn depool_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), String> {
+pub async fn depool_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), CliError> {
     let depool = m.value_of("ADDRESS")
         .map(|s| s.to_string())
         .or(conf.addr.clone())
-        .ok_or("depool address is not defined. Supply it in config file or in command line.".to_string())?;
+        .ok_or(CliError::new(ErrorKind::InvalidInput, "depool address is not defined. Supply it in config file or in command line."))?;
     let depool = load_ton_address(&depool, &conf)
-        .map_err(|e| format!("invalid depool address: {}", e))?;
+        .map_err(|e| CliError::new(ErrorKind::InvalidInput, format!("invalid depool address: {}", e)))?;
 
     if let Some(m) = m.subcommand_matches("donor") {
         let matches = m.subcommand_matches("vesting").or(m.subcommand_matches("lock"));
         if let Some(matches) = matches {
             let is_vesting = m.subcommand_matches("vesting").is_some();
             let (wallet, keys) = parse_wallet_data(&matches, &conf)?;
-            return set_donor_command(matches, conf, depool.as_str(), &wallet, &keys, is_vesting).await;
+            return set_donor_command(matches, conf, depool.as_str(), &wallet, &keys, is_vesting).await.map_err(CliError::from);
         }
     }
 
@@ -224,34 +263,34 @@ pub async fn depool_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), Stri
         if let Some(m) = m.subcommand_matches("ordinary") {
             return ordinary_stake_command(m,
                 CommandData::from_matches_and_conf(m, conf, depool)?,
-            ).await;
+            ).await.map_err(CliError::from);
         }
         if let Some(m) = m.subcommand_matches("vesting") {
             return exotic_stake_command(m,
                 CommandData::from_matches_and_conf(m, conf, depool)?,
                 true,
-            ).await;
+            ).await.map_err(CliError::from);
         }
         if let Some(m) = m.subcommand_matches("lock") {
             return exotic_stake_command(m,
                 CommandData::from_matches_and_conf(m, conf, depool)?,
                 false,
-            ).await;
+            ).await.map_err(CliError::from);
         }
         if let Some(m) = m.subcommand_matches("remove") {
             return remove_stake_command(m,
                 CommandData::from_matches_and_conf(m, conf, depool)?,
-            ).await;
+            ).await.map_err(CliError::from);
         }
         if let Some(m) = m.subcommand_matches("withdrawPart") {
             return withdraw_stake_command(m,
                 CommandData::from_matches_and_conf(m, conf, depool)?,
-            ).await;
+            ).await.map_err(CliError::from);
         }
         if let Some(m) = m.subcommand_matches("transfer") {
             return transfer_stake_command(m,
                 CommandData::from_matches_and_conf(m, conf, depool)?,
-            ).await;
+            ).await.map_err(CliError::from);
         }
     }
     if let Some(m) = m.subcommand_matches("withdraw") {
@@ -259,22 +298,25 @@ pub async fn depool_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), Stri
         if let Some(matches) = matches {
             let (wallet, keys) = parse_wallet_data(&matches, &conf)?;
             let enable_withdraw = m.subcommand_matches("on").is_some();
-            return set_withdraw_command(matches, conf, &depool, &wallet, &keys, enable_withdraw).await;
+            return set_withdraw_command(matches, conf, &depool, &wallet, &keys, enable_withdraw).await.map_err(CliError::from);
         }
     }
     if let Some(m) = m.subcommand_matches("events") {
-        return events_command(m, conf, &depool).await
+        return events_command(m, conf, &depool).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("watch") {
+        return watch_command(m, conf, &depool).await.map_err(CliError::from);
     }
     if let Some(m) = m.subcommand_matches("replenish") {
         return replenish_command(m,
             CommandData::from_matches_and_conf(m, conf, depool)?,
-        ).await;
+        ).await.map_err(CliError::from);
     }
     if let Some(m) = m.subcommand_matches("ticktock") {
         let (wallet, keys) = parse_wallet_data(&m, &conf)?;
-        return ticktock_command(m, conf, &depool, &wallet, &keys).await;
+        return ticktock_command(m, conf, &depool, &wallet, &keys).await.map_err(CliError::from);
     }
-    Err("unknown depool command".to_owned())
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown depool command"))
 }
 
 /*
@@ -307,14 +349,14 @@ fn events_filter(addr: &str, since: u32) -> serde_json::Value {
     })
 }
 
-async fn print_event(ton: TonClient, event: &serde_json::Value) {
+async fn print_event(ton: TonClient, abi: &str, event: &serde_json::Value) {
     println!("event {}", event["id"].as_str().unwrap());
 
     let body = event["body"].as_str().unwrap();
     let result = ton_client::abi::decode_message_body(
         ton.clone(),
         ParamsOfDecodeMessageBody {
-            abi: load_abi(DEPOOL_ABI).unwrap(),
+            abi: load_abi(abi).unwrap(),
             body: body.to_owned(),
             is_internal: false,
             ..Default::default()
@@ -339,19 +381,16 @@ async fn get_events(conf: Config, depool: &str, since: u32) -> Result<(), String
     let ton = create_client_verbose(&conf)?;
     let _addr = load_ton_address(depool, &conf)?;
 
-    let events = ton_client::net::query_collection(
-        ton.clone(),
-        ParamsOfQueryCollection {
-            collection: "messages".to_owned(),
-            filter: Some(events_filter(depool, since)),
-            result: "id body created_at created_at_string".to_owned(),
-            order: Some(vec![OrderBy{ path: "created_at".to_owned(), direction: SortDirection::DESC }]),
-            ..Default::default()
-        },
+    let events = BulkQuery::new(ton.clone(), &conf).query_all(
+        "messages",
+        events_filter(depool, since),
+        "id body created_at created_at_string",
+        "created_at",
     ).await.map_err(|e| format!("failed to query depool events: {}", e))?;
-    println!("{} events found", events.result.len());
-    for event in &events.result {
-        print_event(ton.clone(), event).await;
+    let abi = detect_abi(ton.clone(), depool, ContractFamily::DePool).await?;
+    println!("{} events found", events.len());
+    for event in events.iter().rev() {
+        print_event(ton.clone(), &abi, event).await;
     }
     println!("Done");
     Ok(())
@@ -360,6 +399,7 @@ async fn get_events(conf: Config, depool: &str, since: u32) -> Result<(), String
 async fn wait_for_event(conf: Config, depool: &str) -> Result<(), String> {
     let ton = create_client_verbose(&conf)?;
     let _addr = load_ton_address(depool, &conf)?;
+    let abi = detect_abi(ton.clone(), depool, ContractFamily::DePool).await?;
     println!("Waiting for a new event...");
     let event = ton_client::net::wait_for_collection(
         ton.clone(),
@@ -367,16 +407,203 @@ async fn wait_for_event(conf: Config, depool: &str) -> Result<(), String> {
             collection: "messages".to_owned(),
             filter: Some(events_filter(depool, now())),
             result: "id body created_at created_at_string".to_owned(),
-            timeout: Some(conf.timeout),
+            timeout: Some(conf.network_policy.timeout.unwrap_or(conf.timeout)),
             ..Default::default()
         },
 
     ).await.map_err(|e| println!("failed to query event: {}", e.to_string()));
     if event.is_ok() {
-        print_event(ton.clone(), &event.unwrap().result).await;
+        print_event(ton.clone(), &abi, &event.unwrap().result).await;
     }
     Ok(())
 }
+/*
+ * Watch command
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum WatchPolicy {
+    None,
+    Reinvest,
+    WithdrawReward,
+    WithdrawAll,
+}
+
+impl WatchPolicy {
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "none" => Ok(WatchPolicy::None),
+            "reinvest" => Ok(WatchPolicy::Reinvest),
+            "withdraw-reward" => Ok(WatchPolicy::WithdrawReward),
+            "withdraw-all" => Ok(WatchPolicy::WithdrawAll),
+            _ => Err(format!("unknown policy: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum PolicyAction {
+    CancelWithdrawal,
+    WithdrawPart(u64),
+    WithdrawAll,
+}
+
+/// Participant notification `onRoundComplete`.
+#[derive(Debug, Default)]
+struct RoundResult {
+    round_id: u64,
+    reward: u64,
+    ordinary_stake: u64,
+    vesting_stake: u64,
+    lock_stake: u64,
+    reinvest: bool,
+    reason: u64,
+}
+
+fn value_u64(value: &serde_json::Value) -> u64 {
+    let s = value.as_str().unwrap_or("0");
+    if s.starts_with("0x") {
+        u64::from_str_radix(&s[2..], 16).unwrap_or(0)
+    } else {
+        u64::from_str_radix(s, 10).unwrap_or(0)
+    }
+}
+
+impl RoundResult {
+    fn from_decoded(value: &serde_json::Value) -> Self {
+        RoundResult {
+            round_id: value_u64(&value["roundId"]),
+            reward: value_u64(&value["reward"]),
+            ordinary_stake: value_u64(&value["ordinaryStake"]),
+            vesting_stake: value_u64(&value["vestingStake"]),
+            lock_stake: value_u64(&value["lockStake"]),
+            reinvest: value["reinvest"].as_bool().unwrap_or(false),
+            reason: value_u64(&value["reason"]),
+        }
+    }
+}
+
+/// Decides what to do after the round is completed. Actions are idempotent:
+/// nothing is sent if the participant state already matches the policy.
+fn policy_action(policy: WatchPolicy, round: &RoundResult, min_reward: u64) -> Option<PolicyAction> {
+    if round.reward < min_reward {
+        return None;
+    }
+    match policy {
+        WatchPolicy::None => None,
+        WatchPolicy::Reinvest if !round.reinvest => Some(PolicyAction::CancelWithdrawal),
+        WatchPolicy::WithdrawReward if round.reinvest && round.reward > 0 =>
+            Some(PolicyAction::WithdrawPart(round.reward)),
+        WatchPolicy::WithdrawAll if round.reinvest => Some(PolicyAction::WithdrawAll),
+        _ => None,
+    }
+}
+
+async fn apply_policy(
+    conf: Config,
+    depool: &str,
+    wallet: &str,
+    keys: &str,
+    action: &PolicyAction,
+) -> Result<(), String> {
+    match action {
+        PolicyAction::CancelWithdrawal => set_withdraw(conf, depool, wallet, keys, false).await,
+        PolicyAction::WithdrawAll => set_withdraw(conf, depool, wallet, keys, true).await,
+        PolicyAction::WithdrawPart(value) => {
+            let abi = depool_abi(&conf, depool).await?;
+            let body = encode_withdraw_stake(&abi, *value).await?;
+            let fee = conf.depool_fee.to_string();
+            send_with_body(conf, wallet, depool, &fee, keys, &body).await
+        },
+    }
+}
+
+async fn watch_command(m: &ArgMatches<'_>, conf: Config, depool: &str) -> Result<(), String> {
+    let participant = load_ton_address(m.value_of("PARTICIPANT").unwrap(), &conf)
+        .map_err(|e| format!("invalid participant address: {}", e))?;
+    let policy_name = m.value_of("POLICY").unwrap_or("none");
+    let policy = WatchPolicy::from_str(policy_name)?;
+    let min_reward = m.value_of("MIN_REWARD")
+        .map(|v| {
+            convert::amount_to_nano(v)
+                .map_err(|e| format!("failed to parse minimal reward: {}", e))
+        })
+        .transpose()?
+        .unwrap_or(0);
+    let wallet = load_ton_address(m.value_of("MSIG").unwrap_or(&participant), &conf)
+        .map_err(|e| format!("invalid multisig address: {}", e))?;
+    let keys = m.value_of("SIGN")
+        .map(|s| s.to_string())
+        .or(conf.keys_path.clone());
+    if policy != WatchPolicy::None && keys.is_none() {
+        return Err("keypair is not defined, it is required to apply policy.".to_string());
+    }
+    let (depool_addr, participant_addr, policy_arg) = (Some(depool), Some(participant.as_str()), Some(policy_name));
+    print_args!(m, depool_addr, participant_addr, policy_arg);
+
+    let ton = create_client_verbose(&conf)?;
+    let abi = detect_abi(ton.clone(), depool, ContractFamily::DePool).await?;
+    let (subscription, mut receiver) = subscribe(
+        ton.clone(),
+        "messages",
+        json!({
+            "src": { "eq": depool },
+            "msg_type": { "in": [0, 2] },
+        }),
+        "id body created_at created_at_string msg_type dst",
+    ).await
+    .map_err(|e| format!("failed to subscribe to depool messages: {}", e))?;
+
+    println!("Watching depool {} for participant {} (policy: {})...", depool, participant, policy_name);
+    while let Some(msg) = receiver.recv().await {
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(_) => continue,
+        };
+        if msg["msg_type"].as_u64() == Some(2) {
+            print_event(ton.clone(), &abi, &msg).await;
+            continue;
+        }
+        if msg["dst"].as_str() != Some(participant.as_str()) {
+            continue;
+        }
+        let body = msg["body"].as_str().unwrap_or("");
+        let decoded = match decode_msg_body(ton.clone(), PARTICIPANT_ABI, body, true).await {
+            Ok(decoded) => decoded,
+            Err(_) => continue,
+        };
+        let value = decoded.value.unwrap_or(json!({}));
+        match decoded.name.as_str() {
+            "onRoundComplete" => {
+                let round = RoundResult::from_decoded(&value);
+                println!(
+                    "Round {} completed (reason {}): reward {}, ordinary stake {}, vesting {}, lock {}, reinvest: {}",
+                    round.round_id,
+                    round.reason,
+                    convert::nano_to_tokens(round.reward),
+                    convert::nano_to_tokens(round.ordinary_stake),
+                    convert::nano_to_tokens(round.vesting_stake),
+                    convert::nano_to_tokens(round.lock_stake),
+                    round.reinvest,
+                );
+                if let Some(action) = policy_action(policy, &round, min_reward) {
+                    println!("Applying policy: {:?}", action);
+                    let keys = keys.as_ref().unwrap();
+                    if let Err(e) = apply_policy(conf.clone(), depool, &wallet, keys, &action).await {
+                        println!("Failed to apply policy: {}", e);
+                    }
+                }
+            },
+            "receiveAnswer" => println!(
+                "DePool answer: errcode {}, comment {}", value_u64(&value["errcode"]), value_u64(&value["comment"]),
+            ),
+            _ => {},
+        }
+    }
+    let _ = unsubscribe(ton, subscription).await;
+    Ok(())
+}
+
 /*
  * Stake commands
  */
@@ -445,12 +672,29 @@ async fn exotic_stake_command(
     cmd: CommandData<'_>,
     is_vesting: bool,
 ) -> Result<(), String> {
+    let beneficiary = Some(m.value_of("BENEFICIARY")
+        .ok_or("beneficiary is not defined.".to_string())?);
+    if let Some(schedule) = m.value_of("SCHEDULE") {
+        let (depool, wallet, stake, keys) = (Some(&cmd.depool), Some(&cmd.wallet), Some(cmd.stake), Some(&cmd.keys));
+        let schedule = Some(schedule);
+        print_args!(m, depool, wallet, stake, keys, beneficiary, schedule);
+        let (wperiod, tperiod) = StakeSchedule::parse(schedule.unwrap())?.periods()?;
+        let stake_nano = convert::amount_to_nano(cmd.stake)
+            .map_err(|e| format!(r#"failed to parse stake value: {}"#, e))?;
+        print_unlock_timeline(stake_nano, wperiod, tperiod, is_vesting);
+        if !m.is_present("YES") {
+            let answer = prompt("Send the stake? (y/n): ")?;
+            if !["y", "yes"].contains(&answer.trim().to_lowercase().as_str()) {
+                println!("Cancelled.");
+                return Ok(());
+            }
+        }
+        return add_exotic_stake(cmd, beneficiary.unwrap(), wperiod, tperiod, is_vesting).await;
+    }
     let withdrawal_period = Some(m.value_of("WPERIOD")
         .ok_or("withdrawal period is not defined.".to_string())?);
     let total_period = Some(m.value_of("TPERIOD")
         .ok_or("total period is not defined.".to_string())?);
-    let beneficiary = Some(m.value_of("BENEFICIARY")
-        .ok_or("beneficiary is not defined.".to_string())?);
     let (depool, wallet, stake, keys) = (Some(&cmd.depool), Some(&cmd.wallet), Some(cmd.stake), Some(&cmd.keys));
     print_args!(m, depool, wallet, stake, keys, beneficiary, withdrawal_period, total_period);
     let period_checker = |v| {
@@ -471,6 +715,119 @@ async fn exotic_stake_command(
     add_exotic_stake(cmd, beneficiary.unwrap(), wperiod, tperiod, is_vesting).await
 }
 
+const DAY: u32 = 86400;
+const MAX_PERIOD_DAYS: u32 = 36500;
+
+/// Human-readable unlock schedule of vesting and lock stakes,
+/// e.g. "6m cliff, 24m total, every 6m". All values are in seconds.
+#[derive(Debug, PartialEq)]
+struct StakeSchedule {
+    cliff: Option<u32>,
+    total: u32,
+    interval: Option<u32>,
+}
+
+/// Parses duration like "6m", "2y", "90d", "1w" into seconds.
+fn parse_duration(s: &str) -> Result<u32, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = u32::from_str_radix(number, 10)
+        .map_err(|_| format!(r#"invalid duration "{}""#, s))?;
+    let days = match unit.trim() {
+        "d" | "day" | "days" => 1,
+        "w" | "week" | "weeks" => 7,
+        "m" | "month" | "months" => 30,
+        "y" | "year" | "years" => 365,
+        _ => return Err(format!(r#"unknown unit in duration "{}", use d, w, m or y"#, s)),
+    };
+    number.checked_mul(days)
+        .filter(|days| *days <= MAX_PERIOD_DAYS)
+        .map(|days| days * DAY)
+        .ok_or(format!("duration {} is more than {} days", s, MAX_PERIOD_DAYS))
+}
+
+impl StakeSchedule {
+    fn parse(schedule: &str) -> Result<Self, String> {
+        let mut result = StakeSchedule { cliff: None, total: 0, interval: None };
+        for part in schedule.split(',').map(|p| p.trim().to_lowercase()).filter(|p| !p.is_empty()) {
+            let interval = match part.as_str() {
+                "daily" => Some(DAY),
+                "weekly" => Some(7 * DAY),
+                "monthly" => Some(30 * DAY),
+                "quarterly" => Some(90 * DAY),
+                "yearly" | "annually" => Some(365 * DAY),
+                _ => None,
+            };
+            if let Some(interval) = interval {
+                result.interval = Some(interval);
+            } else if let Some(every) = part.strip_prefix("every ") {
+                result.interval = Some(parse_duration(every)?);
+            } else if let Some(cliff) = part.strip_suffix(" cliff") {
+                result.cliff = Some(parse_duration(cliff)?);
+            } else if let Some(total) = part.strip_suffix(" total") {
+                result.total = parse_duration(total)?;
+            } else {
+                return Err(format!(r#"unknown schedule part "{}""#, part));
+            }
+        }
+        if result.total == 0 {
+            return Err("schedule must contain total period, e.g. \"24m total\"".to_owned());
+        }
+        Ok(result)
+    }
+
+    /// Converts the schedule into DePool withdrawal and total periods.
+    fn periods(&self) -> Result<(u32, u32), String> {
+        let interval = match (self.cliff, self.interval) {
+            (None, None) => return Err("schedule must contain unlock interval or cliff, e.g. \"monthly\"".to_owned()),
+            (Some(cliff), Some(interval)) if cliff != interval => return Err(format!(
+                "DePool unlocks a part of the stake after every withdrawal period, so the cliff ({} days) must be equal to the unlock interval ({} days)",
+                cliff / DAY, interval / DAY,
+            )),
+            (Some(period), _) | (None, Some(period)) => period,
+        };
+        if interval > self.total || self.total % interval != 0 {
+            return Err(format!(
+                "total period ({} days) must be a multiple of the unlock interval ({} days)",
+                self.total / DAY, interval / DAY,
+            ));
+        }
+        Ok((interval, self.total))
+    }
+}
+
+/// Unlock times (seconds from now) and amounts of the exotic stake.
+fn unlock_timeline(stake: u64, wperiod: u32, tperiod: u32) -> Vec<(u32, u64)> {
+    let count = (tperiod / wperiod) as u64;
+    (1..=count)
+        .map(|i| {
+            let amount = if i == count { stake - stake / count * (count - 1) } else { stake / count };
+            (wperiod * i as u32, amount)
+        })
+        .collect()
+}
+
+fn print_unlock_timeline(stake: u64, wperiod: u32, tperiod: u32, is_vesting: bool) {
+    println!();
+    println!("{} stake {}: {} parts every {} days, total {} days.",
+        if is_vesting { "Vesting" } else { "Lock" },
+        convert::nano_to_tokens(stake), tperiod / wperiod, wperiod / DAY, tperiod / DAY,
+    );
+    println!("Parts are {}. Dates are approximate, periods start when the DePool accepts the stake.",
+        if is_vesting { "unlocked to the wallet" } else { "returned to the donor, rewards go to the beneficiary" },
+    );
+    println!("{:>4}  {:<26}  {:>20}  {:>20}", "#", "Unlock date", "Amount", "Unlocked total");
+    let mut unlocked = 0;
+    for (i, (offset, amount)) in unlock_timeline(stake, wperiod, tperiod).iter().enumerate() {
+        unlocked += amount;
+        let date = Local.timestamp((now() + offset) as i64, 0).format("%Y-%m-%d %H:%M");
+        println!("{:>4}  {:<26}  {:>20}  {:>20}", i + 1, date.to_string(),
+            convert::nano_to_tokens(*amount), convert::nano_to_tokens(unlocked));
+    }
+    println!();
+}
+
 async fn remove_stake_command(
     m: &ArgMatches<'_>,
     cmd: CommandData<'_>,
@@ -504,19 +861,22 @@ async fn set_withdraw_command(
 }
 
 async fn add_ordinary_stake(cmd: CommandData<'_>) -> Result<(), String> {
-    let stake = u64::from_str_radix(&convert::convert_token(cmd.stake)?, 10)
+    let stake = convert::amount_to_nano(cmd.stake)
         .map_err(|e| format!(r#"failed to parse stake value: {}"#, e))?;
-    let body = encode_add_ordinary_stake(stake).await?;
-    let fee = u64::from_str_radix(&convert::convert_token(&cmd.depool_fee)?, 10)
+    let abi = depool_abi(&cmd.conf, &cmd.depool).await?;
+    let body = encode_add_ordinary_stake(&abi, stake).await?;
+    let fee = u64::from_str_radix(&convert::convert_token(&cmd.depool_fee, false)?, 10)
         .map_err(|e| format!(r#"failed to parse depool fee value: {}"#, e))?;
-    let value = (fee + stake) as f64 * 1.0 / 1e9;
 
-    send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &format!("{}", value), &cmd.keys, &body).await
+    send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &format!("{} nano", fee + stake), &cmd.keys, &body).await
 }
 
 async fn replenish_stake(cmd: CommandData<'_>) -> Result<(), String> {
-    let body = encode_replenish_stake().await?;
-    send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, cmd.stake, &cmd.keys, &body).await
+    let abi = depool_abi(&cmd.conf, &cmd.depool).await?;
+    let body = encode_replenish_stake(&abi).await?;
+    let value = convert::amount_to_nano(cmd.stake)
+        .map_err(|e| format!(r#"failed to parse stake value: {}"#, e))?;
+    send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &format!("{} nano", value), &cmd.keys, &body).await
 }
 
 async fn call_ticktock(
@@ -525,7 +885,8 @@ async fn call_ticktock(
     wallet: &str,
     keys: &str,
 ) -> Result<(), String> {
-    let body = encode_ticktock().await?;
+    let abi = depool_abi(&conf, depool).await?;
+    let body = encode_ticktock(&abi).await?;
     send_with_body(conf, wallet, depool, "1", keys, &body).await
 }
 
@@ -537,45 +898,45 @@ async fn add_exotic_stake(
     is_vesting: bool,
 ) -> Result<(), String> {
     let beneficiary = load_ton_address(beneficiary, &cmd.conf)?;
-    let stake = u64::from_str_radix(&convert::convert_token(cmd.stake)?, 10)
+    let stake = convert::amount_to_nano(cmd.stake)
         .map_err(|e| format!(r#"failed to parse stake value: {}"#, e))?;
+    let abi = depool_abi(&cmd.conf, &cmd.depool).await?;
     let body = if is_vesting {
-        encode_add_vesting_stake(stake, beneficiary.as_str(), tp, wp).await?
+        encode_add_vesting_stake(&abi, stake, beneficiary.as_str(), tp, wp).await?
     } else {
-        encode_add_lock_stake(stake, beneficiary.as_str(), tp, wp).await?
+        encode_add_lock_stake(&abi, stake, beneficiary.as_str(), tp, wp).await?
     };
-    let fee = u64::from_str_radix(&convert::convert_token(&cmd.depool_fee)?, 10)
+    let fee = u64::from_str_radix(&convert::convert_token(&cmd.depool_fee, false)?, 10)
         .map_err(|e| format!(r#"failed to parse depool fee value: {}"#, e))?;
-    let value = (fee + stake) as f64 * 1.0 / 1e9;
-    send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &format!("{}", value), &cmd.keys, &body).await
+    send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &format!("{} nano", fee + stake), &cmd.keys, &body).await
 }
 
 async fn remove_stake(
     cmd: CommandData<'_>,
 ) -> Result<(), String> {
-    let stake = u64::from_str_radix(
-        &convert::convert_token(cmd.stake)?, 10,
-    ).unwrap();
-    let body = encode_remove_stake(stake).await?;
+    let stake = convert::amount_to_nano(cmd.stake)
+        .map_err(|e| format!(r#"failed to parse stake value: {}"#, e))?;
+    let abi = depool_abi(&cmd.conf, &cmd.depool).await?;
+    let body = encode_remove_stake(&abi, stake).await?;
     send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &cmd.depool_fee, &cmd.keys, &body).await
 }
 
 async fn withdraw_stake(
     cmd: CommandData<'_>,
 ) -> Result<(), String> {
-    let stake = u64::from_str_radix(
-        &convert::convert_token(cmd.stake)?, 10,
-    ).unwrap();
-    let body = encode_withdraw_stake(stake).await?;
+    let stake = convert::amount_to_nano(cmd.stake)
+        .map_err(|e| format!(r#"failed to parse stake value: {}"#, e))?;
+    let abi = depool_abi(&cmd.conf, &cmd.depool).await?;
+    let body = encode_withdraw_stake(&abi, stake).await?;
     send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &cmd.depool_fee, &cmd.keys, &body).await
 }
 
 async fn transfer_stake(cmd: CommandData<'_>, dest: &str) -> Result<(), String> {
     let dest = load_ton_address(dest, &cmd.conf)?;
-    let stake = u64::from_str_radix(
-        &convert::convert_token(cmd.stake)?, 10,
-    ).unwrap();
-    let body = encode_transfer_stake(dest.as_str(), stake).await?;
+    let stake = convert::amount_to_nano(cmd.stake)
+        .map_err(|e| format!(r#"failed to parse stake value: {}"#, e))?;
+    let abi = depool_abi(&cmd.conf, &cmd.depool).await?;
+    let body = encode_transfer_stake(&abi, dest.as_str(), stake).await?;
     send_with_body(cmd.conf, &cmd.wallet, &cmd.depool, &cmd.depool_fee, &cmd.keys, &body).await
 }
 
@@ -586,7 +947,8 @@ async fn set_withdraw(
     keys: &str,
     enable: bool,
 ) -> Result<(), String> {
-    let body = encode_set_withdraw(enable).await?;
+    let abi = depool_abi(&conf, depool).await?;
+    let body = encode_set_withdraw(&abi, enable).await?;
     let value = conf.depool_fee.to_string();
     send_with_body(conf, wallet, depool, &value, keys, &body).await
 }
@@ -599,17 +961,23 @@ async fn set_donor(
     is_vesting: bool,
     donor: &str,
 ) -> Result<(), String> {
-    let body = encode_set_donor(is_vesting, donor).await?;
+    let abi = depool_abi(&conf, depool).await?;
+    let body = encode_set_donor(&abi, is_vesting, donor).await?;
     let value = conf.depool_fee.to_string();
     send_with_body(conf, wallet, depool, &value, keys, &body).await
 }
 
-async fn encode_body(func: &str, params: serde_json::Value) -> Result<String, String> {
+/// ABI of the depool detected by its code hash.
+async fn depool_abi(conf: &Config, depool: &str) -> Result<String, String> {
+    detect_abi(create_client(conf)?, depool, ContractFamily::DePool).await
+}
+
+async fn encode_body(abi: &str, func: &str, params: serde_json::Value) -> Result<String, String> {
     let client = create_client_local()?;
     ton_client::abi::encode_message_body(
         client.clone(),
         ParamsOfEncodeMessageBody {
-            abi: load_abi(DEPOOL_ABI)?,
+            abi: load_abi(abi)?,
             call_set: CallSet::some_with_function_and_input(func, params).unwrap(),
             is_internal: true,
             ..Default::default()
@@ -619,35 +987,36 @@ async fn encode_body(func: &str, params: serde_json::Value) -> Result<String, St
     .map(|r| r.body)
 }
 
-async fn encode_set_withdraw(flag: bool) -> Result<String, String> {
+async fn encode_set_withdraw(abi: &str, flag: bool) -> Result<String, String> {
     if flag {
-        encode_body("withdrawAll", json!({}))
+        encode_body(abi, "withdrawAll", json!({}))
     } else {
-        encode_body("cancelWithdrawal", json!({}))
+        encode_body(abi, "cancelWithdrawal", json!({}))
     }.await
 }
 
-async fn encode_add_ordinary_stake(stake: u64) -> Result<String, String> {
-	encode_body("addOrdinaryStake", json!({
+async fn encode_add_ordinary_stake(abi: &str, stake: u64) -> Result<String, String> {
+	encode_body(abi, "addOrdinaryStake", json!({
         "stake": stake
     })).await
 }
 
-async fn encode_replenish_stake() -> Result<String, String> {
-	encode_body("receiveFunds", json!({})).await
+async fn encode_replenish_stake(abi: &str) -> Result<String, String> {
+	encode_body(abi, "receiveFunds", json!({})).await
 }
 
-async fn encode_ticktock() -> Result<String, String> {
-	encode_body("ticktock", json!({})).await
+async fn encode_ticktock(abi: &str) -> Result<String, String> {
+	encode_body(abi, "ticktock", json!({})).await
 }
 
 async fn encode_add_vesting_stake(
+    abi: &str,
     stake: u64,
     beneficiary: &str,
     tperiod: u32,
     wperiod: u32,
 ) -> Result<String, String> {
-	encode_body("addVestingStake", json!({
+	encode_body(abi, "addVestingStake", json!({
         "stake": stake,
         "beneficiary": beneficiary,
         "withdrawalPeriod": wperiod,
@@ -655,25 +1024,26 @@ async fn encode_add_vesting_stake(
     })).await
 }
 
-async fn encode_set_donor(is_vesting: bool, donor: &str) -> Result<String, String> {
+async fn encode_set_donor(abi: &str, is_vesting: bool, donor: &str) -> Result<String, String> {
     if is_vesting {
-        encode_body("setVestingDonor", json!({
+        encode_body(abi, "setVestingDonor", json!({
             "donor": donor
         }))
     } else {
-        encode_body("setLockDonor", json!({
+        encode_body(abi, "setLockDonor", json!({
             "donor": donor
         }))
     }.await
 }
 
 async fn encode_add_lock_stake(
+    abi: &str,
     stake: u64,
     beneficiary: &str,
     tperiod: u32,
     wperiod: u32,
 ) -> Result<String, String> {
-	encode_body("addLockStake", json!({
+	encode_body(abi, "addLockStake", json!({
         "stake": stake,
         "beneficiary": beneficiary,
         "withdrawalPeriod": wperiod,
@@ -681,21 +1051,57 @@ async fn encode_add_lock_stake(
     })).await
 }
 
-async fn encode_remove_stake(target_value: u64) -> Result<String, String> {
-	encode_body("withdrawFromPoolingRound", json!({
+async fn encode_remove_stake(abi: &str, target_value: u64) -> Result<String, String> {
+	encode_body(abi, "withdrawFromPoolingRound", json!({
         "withdrawValue": target_value
     })).await
 }
 
-async fn encode_withdraw_stake(target_value: u64) -> Result<String, String> {
-	encode_body("withdrawPart", json!({
+async fn encode_withdraw_stake(abi: &str, target_value: u64) -> Result<String, String> {
+	encode_body(abi, "withdrawPart", json!({
         "withdrawValue": target_value
     })).await
 }
 
-async fn encode_transfer_stake(dest: &str, amount: u64) -> Result<String, String> {
-	encode_body("transferStake", json!({
+async fn encode_transfer_stake(abi: &str, dest: &str, amount: u64) -> Result<String, String> {
+	encode_body(abi, "transferStake", json!({
         "dest": dest,
         "amount": amount
     })).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_action() {
+        let round = RoundResult { reward: 5_000_000_000, reinvest: true, ..Default::default() };
+        assert_eq!(policy_action(WatchPolicy::None, &round, 0), None);
+        assert_eq!(policy_action(WatchPolicy::Reinvest, &round, 0), None);
+        assert_eq!(policy_action(WatchPolicy::WithdrawReward, &round, 0), Some(PolicyAction::WithdrawPart(5_000_000_000)));
+        assert_eq!(policy_action(WatchPolicy::WithdrawReward, &round, 6_000_000_000), None);
+        assert_eq!(policy_action(WatchPolicy::WithdrawAll, &round, 0), Some(PolicyAction::WithdrawAll));
+
+        let round = RoundResult { reinvest: false, ..round };
+        assert_eq!(policy_action(WatchPolicy::Reinvest, &round, 0), Some(PolicyAction::CancelWithdrawal));
+        assert_eq!(policy_action(WatchPolicy::WithdrawAll, &round, 0), None);
+        assert_eq!(value_u64(&json!("0x10")), 16);
+    }
+
+    #[test]
+    fn test_stake_schedule() {
+        let schedule = StakeSchedule::parse("24m total, monthly").unwrap();
+        assert_eq!(schedule.periods().unwrap(), (30 * DAY, 720 * DAY));
+        assert_eq!(StakeSchedule::parse("6m cliff, 24m total").unwrap().periods().unwrap(), (180 * DAY, 720 * DAY));
+        assert!(StakeSchedule::parse("6m cliff, 2y total").unwrap().periods().is_err());
+        assert_eq!(StakeSchedule::parse("6m cliff, 24m total, every 6m").unwrap().periods().unwrap(), (180 * DAY, 720 * DAY));
+        assert!(StakeSchedule::parse("6m cliff, 24m total, monthly").unwrap().periods().is_err());
+        assert!(StakeSchedule::parse("monthly").is_err());
+        assert!(StakeSchedule::parse("10d total, weekly").unwrap().periods().is_err());
+        assert!(StakeSchedule::parse("24 parsecs total").is_err());
+
+        let timeline = unlock_timeline(10, 30 * DAY, 90 * DAY);
+        assert_eq!(timeline, vec![(30 * DAY, 3), (60 * DAY, 3), (90 * DAY, 4)]);
+    }
+}
@@ -0,0 +1,1081 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! TVM bytecode disassembler. Covers the opcodes emitted by the Solidity and
+//! C++ compilers (codepage 0); decoding of a continuation stops at the first
+//! unknown opcode and its remaining bits are printed raw.
+//!
+//! Integer constants and dictionary keys which match function ids of the
+//! supplied ABIs are annotated with the function name, which makes the
+//! selector (public method dictionary) of a contract readable.
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_verbose, load_ton_address, query};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use ton_abi::Contract;
+use ton_block::{Deserializable, StateInit};
+use ton_types::Cell;
+
+/// Limits nesting of continuations, code cells may be shared in a DAG.
+const MAX_DEPTH: usize = 64;
+const INDENT: &str = "  ";
+
+pub fn create_disasm_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("disasm")
+        .about("Disassembles contract code of the account or tvc file into TVM assembly.")
+        .arg(Arg::with_name("SOURCE")
+            .required(true)
+            .takes_value(true)
+            .help("Account address, tvc file or code boc file."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("ABI files used to annotate function selectors (can be repeated, default: abi from config)."))
+        .arg(Arg::with_name("OUTPUT")
+            .long("--output")
+            .short("-o")
+            .takes_value(true)
+            .help("Saves disassembly to the file instead of printing it."))
+}
+
+/// Bit reader over a cell (or a part of it) with its references.
+pub struct Reader {
+    data: Vec<u8>,
+    pos: usize,
+    len: usize,
+    refs: VecDeque<Cell>,
+}
+
+impl Reader {
+    pub fn new(data: Vec<u8>, len: usize, refs: Vec<Cell>) -> Self {
+        Reader { data, pos: 0, len, refs: refs.into() }
+    }
+
+    pub fn from_cell(cell: &Cell) -> Result<Self, String> {
+        let mut refs = vec![];
+        for i in 0..cell.references_count() {
+            refs.push(cell.reference(i).map_err(|e| format!("failed to load reference: {}", e))?);
+        }
+        Ok(Reader::new(cell.data().to_vec(), cell.bit_length(), refs))
+    }
+
+    fn remaining(&self) -> usize {
+        self.len - self.pos
+    }
+
+    fn bit(&self, index: usize) -> u64 {
+        ((self.data[index / 8] >> (7 - index % 8)) & 1) as u64
+    }
+
+    fn peek(&self, bits: usize) -> Option<u64> {
+        if bits > 64 || self.remaining() < bits {
+            return None;
+        }
+        Some((self.pos..self.pos + bits).fold(0, |acc, i| (acc << 1) | self.bit(i)))
+    }
+
+    fn take(&mut self, bits: usize) -> Result<u64, String> {
+        let value = self.peek(bits).ok_or("unexpected end of code".to_owned())?;
+        self.pos += bits;
+        Ok(value)
+    }
+
+    fn take_signed(&mut self, bits: usize) -> Result<i64, String> {
+        Ok(signed(self.take(bits)?, bits))
+    }
+
+    /// Reads bits packed into bytes.
+    fn take_bits(&mut self, bits: usize) -> Result<Vec<u8>, String> {
+        if self.remaining() < bits {
+            return Err("unexpected end of code".to_owned());
+        }
+        let mut data = vec![0u8; (bits + 7) / 8];
+        for i in 0..bits {
+            data[i / 8] |= (self.bit(self.pos + i) as u8) << (7 - i % 8);
+        }
+        self.pos += bits;
+        Ok(data)
+    }
+
+    fn take_ref(&mut self) -> Result<Cell, String> {
+        self.refs.pop_front().ok_or("instruction refers to missing cell reference".to_owned())
+    }
+
+    /// Cuts inline data of the instruction: `bits` of data and `refs` references.
+    fn sub(&mut self, bits: usize, refs: usize) -> Result<Reader, String> {
+        let data = self.take_bits(bits)?;
+        let refs = (0..refs).map(|_| self.take_ref()).collect::<Result<Vec<_>, String>>()?;
+        Ok(Reader::new(data, bits, refs))
+    }
+
+    /// Same as `sub` for data ending with a completion tag (`1` followed by zeroes).
+    fn sub_tagged(&mut self, bits: usize, refs: usize) -> Result<Reader, String> {
+        let mut sub = self.sub(bits, refs)?;
+        while sub.len > 0 && sub.bit(sub.len - 1) == 0 {
+            sub.len -= 1;
+        }
+        if sub.len == 0 {
+            return Err("inline slice has no completion tag".to_owned());
+        }
+        sub.len -= 1;
+        Ok(sub)
+    }
+
+    /// Remaining bits in `x{...}` notation, `_` marks the completion tag.
+    fn to_fift(&self) -> String {
+        let bits = self.remaining();
+        let mut nibbles: Vec<u8> = (0..(bits + 3) / 4)
+            .map(|n| (0..4).fold(0u8, |acc, i| {
+                let index = self.pos + n * 4 + i;
+                let bit = if index < self.len { self.bit(index) as u8 } else if index == self.len { 1 } else { 0 };
+                (acc << 1) | bit
+            }))
+            .collect();
+        let mut text: String = nibbles.drain(..).map(|n| format!("{:X}", n)).collect();
+        if bits % 4 != 0 {
+            text.push('_');
+        }
+        let refs = if self.refs.is_empty() { String::new() } else { format!(" refs:{}", self.refs.len()) };
+        format!("x{{{}}}{}", text, refs)
+    }
+}
+
+fn signed(value: u64, bits: usize) -> i64 {
+    if bits == 0 || bits >= 64 {
+        return value as i64;
+    }
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+/// Decoded instruction with its inline continuations and dictionaries.
+#[derive(Default)]
+struct Insn {
+    text: String,
+    constant: Option<i64>,
+    conts: Vec<Reader>,
+    dict: Option<(usize, Cell)>,
+}
+
+impl Insn {
+    fn new(text: impl Into<String>) -> Self {
+        Insn { text: text.into(), ..Default::default() }
+    }
+
+    fn with_conts(text: impl Into<String>, conts: Vec<Reader>) -> Self {
+        Insn { text: text.into(), conts, ..Default::default() }
+    }
+}
+
+fn simple(name: &str) -> Result<Insn, String> {
+    Ok(Insn::new(name))
+}
+
+fn arg(name: &str, value: impl std::fmt::Display) -> Result<Insn, String> {
+    Ok(Insn::new(format!("{} {}", name, value)))
+}
+
+fn push_int(value: i64) -> Result<Insn, String> {
+    Ok(Insn { text: format!("PUSHINT {}", value), constant: Some(value), ..Default::default() })
+}
+
+fn unknown() -> Result<Insn, String> {
+    Err("unknown opcode".to_owned())
+}
+
+fn cell_text(cell: &Cell) -> Result<String, String> {
+    Ok(Reader::from_cell(cell)?.to_fift())
+}
+
+fn decode(r: &mut Reader) -> Result<Insn, String> {
+    let op = r.take(8)?;
+    match op {
+        0x00 => simple("NOP"),
+        0x01 => simple("SWAP"),
+        0x02..=0x0F => arg("XCHG", format!("s{}", op)),
+        0x10 => { let ij = r.take(8)?; arg("XCHG", format!("s{},s{}", ij >> 4, ij & 0xF)) },
+        0x11 => arg("XCHG", format!("s0,s{}", r.take(8)?)),
+        0x12..=0x1F => arg("XCHG", format!("s1,s{}", op & 0xF)),
+        0x20 => simple("DUP"),
+        0x21 => simple("OVER"),
+        0x22..=0x2F => arg("PUSH", format!("s{}", op & 0xF)),
+        0x30 => simple("DROP"),
+        0x31 => simple("NIP"),
+        0x32..=0x3F => arg("POP", format!("s{}", op & 0xF)),
+        0x40..=0x4F => { let jk = r.take(8)?; arg("XCHG3", format!("s{},s{},s{}", op & 0xF, jk >> 4, jk & 0xF)) },
+        0x50..=0x53 => {
+            let ij = r.take(8)?;
+            let name = ["XCHG2", "XCPU", "PUXC", "PUSH2"][(op & 3) as usize];
+            let j = if op == 0x52 { (ij & 0xF) as i64 - 1 } else { (ij & 0xF) as i64 };
+            arg(name, format!("s{},s{}", ij >> 4, j))
+        },
+        0x54 => {
+            // 54 is followed by a 4-bit kind and three 4-bit indexes.
+            let names = ["XCHG3", "XC2PU", "XCPUXC", "XCPU2", "PUXC2", "PUXCPU", "PU2XC", "PUSH3"];
+            let kind = r.take(4)? as usize;
+            let (i, j, k) = (r.take(4)?, r.take(4)?, r.take(4)?);
+            match names.get(kind) {
+                Some(name) => arg(name, format!("s{},s{},s{}", i, j, k)),
+                None => unknown(),
+            }
+        },
+        0x55 => { let ij = r.take(8)?; arg("BLKSWAP", format!("{},{}", (ij >> 4) + 1, (ij & 0xF) + 1)) },
+        0x56 => arg("PUSH", format!("s{}", r.take(8)?)),
+        0x57 => arg("POP", format!("s{}", r.take(8)?)),
+        0x58 => simple("ROT"),
+        0x59 => simple("ROTREV"),
+        0x5A => simple("SWAP2"),
+        0x5B => simple("DROP2"),
+        0x5C => simple("DUP2"),
+        0x5D => simple("OVER2"),
+        0x5E => { let ij = r.take(8)?; arg("REVERSE", format!("{},{}", (ij >> 4) + 2, ij & 0xF)) },
+        0x5F => {
+            let ij = r.take(8)?;
+            if ij >> 4 == 0 {
+                arg("BLKDROP", ij & 0xF)
+            } else {
+                arg("BLKPUSH", format!("{},{}", ij >> 4, ij & 0xF))
+            }
+        },
+        0x60 => simple("PICK"),
+        0x61 => simple("ROLLX"),
+        0x62 => simple("-ROLLX"),
+        0x63 => simple("BLKSWX"),
+        0x64 => simple("REVX"),
+        0x65 => simple("DROPX"),
+        0x66 => simple("TUCK"),
+        0x67 => simple("XCHGX"),
+        0x68 => simple("DEPTH"),
+        0x69 => simple("CHKDEPTH"),
+        0x6A => simple("ONLYTOPX"),
+        0x6B => simple("ONLYX"),
+        0x6C => { let ij = r.take(8)?; arg("BLKDROP2", format!("{},{}", ij >> 4, ij & 0xF)) },
+        0x6D => simple("NULL"),
+        0x6E => simple("ISNULL"),
+        0x6F => decode_tuple(r),
+        0x70..=0x7F => {
+            let x = (op & 0xF) as i64;
+            push_int(if x > 10 { x - 16 } else { x })
+        },
+        0x80 => push_int(r.take_signed(8)?),
+        0x81 => push_int(r.take_signed(16)?),
+        0x82 => {
+            let len = r.take(5)? as usize * 8 + 19;
+            if len <= 64 {
+                push_int(r.take_signed(len)?)
+            } else {
+                let data = r.take_bits(len)?;
+                arg("PUSHINT", format!("0x{}", hex::encode(data)))
+            }
+        },
+        0x83 => match r.take(8)? {
+            0xFF => simple("PUSHNAN"),
+            x => arg("PUSHPOW2", x + 1),
+        },
+        0x84 => arg("PUSHPOW2DEC", r.take(8)? + 1),
+        0x85 => arg("PUSHNEGPOW2", r.take(8)? + 1),
+        0x88 => arg("PUSHREF", cell_text(&r.take_ref()?)?),
+        0x89 => arg("PUSHREFSLICE", cell_text(&r.take_ref()?)?),
+        0x8A => {
+            let cont = Reader::from_cell(&r.take_ref()?)?;
+            Ok(Insn::with_conts("PUSHREFCONT", vec![cont]))
+        },
+        0x8B => {
+            let x = r.take(4)? as usize;
+            arg("PUSHSLICE", r.sub_tagged(8 * x + 4, 0)?.to_fift())
+        },
+        0x8C => {
+            let refs = r.take(2)? as usize + 1;
+            let x = r.take(5)? as usize;
+            arg("PUSHSLICE", r.sub_tagged(8 * x + 1, refs)?.to_fift())
+        },
+        0x8D => {
+            let refs = r.take(3)? as usize;
+            let x = r.take(7)? as usize;
+            arg("PUSHSLICE", r.sub_tagged(8 * x + 6, refs)?.to_fift())
+        },
+        0x8E | 0x8F => {
+            let refs = ((op & 1) << 1 | r.take(1)?) as usize;
+            let bytes = r.take(7)? as usize;
+            let cont = r.sub(8 * bytes, refs)?;
+            Ok(Insn::with_conts("PUSHCONT", vec![cont]))
+        },
+        0x90..=0x9F => {
+            let cont = r.sub(8 * (op & 0xF) as usize, 0)?;
+            Ok(Insn::with_conts("PUSHCONT", vec![cont]))
+        },
+        0xA0 => simple("ADD"),
+        0xA1 => simple("SUB"),
+        0xA2 => simple("SUBR"),
+        0xA3 => simple("NEGATE"),
+        0xA4 => simple("INC"),
+        0xA5 => simple("DEC"),
+        0xA6 => arg("ADDCONST", r.take_signed(8)?),
+        0xA7 => arg("MULCONST", r.take_signed(8)?),
+        0xA8 => simple("MUL"),
+        0xA9 => match r.take(8)? {
+            0x04 => simple("DIV"),
+            0x05 => simple("DIVR"),
+            0x06 => simple("DIVC"),
+            0x08 => simple("MOD"),
+            0x0C => simple("DIVMOD"),
+            0x0D => simple("DIVMODR"),
+            0x0E => simple("DIVMODC"),
+            0x24 => simple("RSHIFTR"),
+            0x84 => simple("MULDIV"),
+            0x85 => simple("MULDIVR"),
+            0x88 => simple("MULMOD"),
+            0x8C => simple("MULDIVMOD"),
+            0xA4 => simple("MULRSHIFT"),
+            0xC4 => simple("LSHIFTDIV"),
+            _ => unknown(),
+        },
+        0xAA => arg("LSHIFT", r.take(8)? + 1),
+        0xAB => arg("RSHIFT", r.take(8)? + 1),
+        0xAC => simple("LSHIFT"),
+        0xAD => simple("RSHIFT"),
+        0xAE => simple("POW2"),
+        0xB0 => simple("AND"),
+        0xB1 => simple("OR"),
+        0xB2 => simple("XOR"),
+        0xB3 => simple("NOT"),
+        0xB4 => arg("FITS", r.take(8)? + 1),
+        0xB5 => arg("UFITS", r.take(8)? + 1),
+        0xB6 => match r.take(8)? {
+            0x00 => simple("FITSX"),
+            0x01 => simple("UFITSX"),
+            0x02 => simple("BITSIZE"),
+            0x03 => simple("UBITSIZE"),
+            0x08 => simple("MIN"),
+            0x09 => simple("MAX"),
+            0x0A => simple("MINMAX"),
+            0x0B => simple("ABS"),
+            _ => unknown(),
+        },
+        0xB7 => {
+            let insn = decode(r)?;
+            Ok(Insn { text: format!("Q{}", insn.text), ..insn })
+        },
+        0xB8 => simple("SGN"),
+        0xB9 => simple("LESS"),
+        0xBA => simple("EQUAL"),
+        0xBB => simple("LEQ"),
+        0xBC => simple("GREATER"),
+        0xBD => simple("NEQ"),
+        0xBE => simple("GEQ"),
+        0xBF => simple("CMP"),
+        0xC0 => arg("EQINT", r.take_signed(8)?),
+        0xC1 => arg("LESSINT", r.take_signed(8)?),
+        0xC2 => arg("GTINT", r.take_signed(8)?),
+        0xC3 => arg("NEQINT", r.take_signed(8)?),
+        0xC4 => simple("ISNAN"),
+        0xC5 => simple("CHKNAN"),
+        0xC7 => {
+            let names = [
+                "SEMPTY", "SDEMPTY", "SREMPTY", "SDFIRST", "SDLEXCMP", "SDEQ", "", "",
+                "SDPFX", "SDPFXREV", "SDPPFX", "SDPPFXREV", "SDSFX", "SDSFXREV", "SDPSFX", "SDPSFXREV",
+                "SDCNTLEAD0", "SDCNTLEAD1", "SDCNTTRAIL0", "SDCNTTRAIL1",
+            ];
+            match names.get(r.take(8)? as usize) {
+                Some(name) if !name.is_empty() => simple(name),
+                _ => unknown(),
+            }
+        },
+        0xC8 => simple("NEWC"),
+        0xC9 => simple("ENDC"),
+        0xCA => arg("STI", r.take(8)? + 1),
+        0xCB => arg("STU", r.take(8)? + 1),
+        0xCC => simple("STREF"),
+        0xCD => simple("STBREFR"),
+        0xCE => simple("STSLICE"),
+        0xCF => {
+            if r.take(1)? == 1 {
+                let refs = r.take(2)? as usize;
+                let y = r.take(3)? as usize;
+                return arg("STSLICECONST", r.sub_tagged(8 * y + 2, refs)?.to_fift());
+            }
+            match r.take(7)? {
+                0x00 => simple("STIX"),
+                0x01 => simple("STUX"),
+                0x02 => simple("STIXR"),
+                0x03 => simple("STUXR"),
+                0x23 => simple("ENDXC"),
+                0x31 => simple("BBITS"),
+                0x32 => simple("BREFS"),
+                0x33 => simple("BBITREFS"),
+                0x35 => simple("BREMBITS"),
+                0x36 => simple("BREMREFS"),
+                0x37 => simple("BREMBITREFS"),
+                0x40 => simple("STZEROES"),
+                0x41 => simple("STONES"),
+                0x42 => simple("STSAME"),
+                _ => unknown(),
+            }
+        },
+        0xD0 => simple("CTOS"),
+        0xD1 => simple("ENDS"),
+        0xD2 => arg("LDI", r.take(8)? + 1),
+        0xD3 => arg("LDU", r.take(8)? + 1),
+        0xD4 => simple("LDREF"),
+        0xD5 => simple("LDREFRTOS"),
+        0xD6 => arg("LDSLICE", r.take(8)? + 1),
+        0xD7 => decode_slice_ops(r),
+        0xD8 => simple("CALLX"),
+        0xD9 => simple("JMPX"),
+        0xDA => { let pr = r.take(8)?; arg("CALLXARGS", format!("{},{}", pr >> 4, pr & 0xF)) },
+        0xDB => {
+            let x = r.take(8)?;
+            match x {
+                0x00..=0x0F => arg("CALLXARGS", format!("{},-1", x)),
+                0x10..=0x1F => arg("JMPXARGS", x & 0xF),
+                0x20..=0x2F => arg("RETARGS", x & 0xF),
+                0x30 => simple("RET"),
+                0x31 => simple("RETALT"),
+                0x32 => simple("RETBOOL"),
+                0x34 => simple("CALLCC"),
+                0x35 => simple("JMPXDATA"),
+                0x36 => { let pr = r.take(8)?; arg("CALLCCARGS", format!("{},{}", pr >> 4, pr & 0xF)) },
+                0x38 => simple("CALLXVARARGS"),
+                0x39 => simple("RETVARARGS"),
+                0x3A => simple("JMPXVARARGS"),
+                0x3B => simple("CALLCCVARARGS"),
+                0x3C => Ok(Insn::with_conts("CALLREF", vec![Reader::from_cell(&r.take_ref()?)?])),
+                0x3D => Ok(Insn::with_conts("JMPREF", vec![Reader::from_cell(&r.take_ref()?)?])),
+                0x3E => Ok(Insn::with_conts("JMPREFDATA", vec![Reader::from_cell(&r.take_ref()?)?])),
+                0x3F => simple("RETDATA"),
+                _ => unknown(),
+            }
+        },
+        0xDC => simple("IFRET"),
+        0xDD => simple("IFNOTRET"),
+        0xDE => simple("IF"),
+        0xDF => simple("IFNOT"),
+        0xE0 => simple("IFJMP"),
+        0xE1 => simple("IFNOTJMP"),
+        0xE2 => simple("IFELSE"),
+        0xE3 => {
+            let x = r.take(8)?;
+            let refs = match x {
+                0x00 => ("IFREF", 1),
+                0x01 => ("IFNOTREF", 1),
+                0x02 => ("IFJMPREF", 1),
+                0x03 => ("IFNOTJMPREF", 1),
+                0x04 => ("CONDSEL", 0),
+                0x05 => ("CONDSELCHK", 0),
+                0x08 => ("IFRETALT", 0),
+                0x09 => ("IFNOTRETALT", 0),
+                0x0D => ("IFREFELSE", 1),
+                0x0E => ("IFELSEREF", 1),
+                0x0F => ("IFREFELSEREF", 2),
+                _ => return unknown(),
+            };
+            let conts = (0..refs.1)
+                .map(|_| r.take_ref().and_then(|cell| Reader::from_cell(&cell)))
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(Insn::with_conts(refs.0, conts))
+        },
+        0xE4 => simple("REPEAT"),
+        0xE5 => simple("REPEATEND"),
+        0xE6 => simple("UNTIL"),
+        0xE7 => simple("UNTILEND"),
+        0xE8 => simple("WHILE"),
+        0xE9 => simple("WHILEEND"),
+        0xEA => simple("AGAIN"),
+        0xEB => simple("AGAINEND"),
+        0xEC => { let rn = r.take(8)?; arg("SETCONTARGS", format!("{},{}", rn >> 4, signed(rn & 0xF, 4))) },
+        0xED => decode_continuation_ops(r),
+        0xEE => { let rn = r.take(8)?; arg("BLESSARGS", format!("{},{}", rn >> 4, signed(rn & 0xF, 4))) },
+        0xF0 => arg("CALL", r.take(8)?),
+        0xF1 => match r.take(2)? {
+            0 => arg("CALL", r.take(14)?),
+            1 => arg("JMPDICT", r.take(14)?),
+            2 => arg("PREPARE", r.take(14)?),
+            _ => unknown(),
+        },
+        0xF2 => decode_exceptions(r),
+        0xF3 => { let pr = r.take(8)?; arg("TRYARGS", format!("{},{}", pr >> 4, pr & 0xF)) },
+        0xF4 => decode_dictionary_ops(r),
+        0xF8 => decode_blockchain_ops(r),
+        0xF9 => match r.take(8)? {
+            0x00 => simple("HASHCU"),
+            0x01 => simple("HASHSU"),
+            0x02 => simple("SHA256U"),
+            0x10 => simple("CHKSIGNU"),
+            0x11 => simple("CHKSIGNS"),
+            0x40 => simple("CDATASIZEQ"),
+            0x41 => simple("CDATASIZE"),
+            0x42 => simple("SDATASIZEQ"),
+            0x43 => simple("SDATASIZE"),
+            _ => unknown(),
+        },
+        0xFA => {
+            let names = [
+                "LDGRAMS", "LDVARINT16", "STGRAMS", "STVARINT16",
+                "LDVARUINT32", "LDVARINT32", "STVARUINT32", "STVARINT32",
+            ];
+            let addr_names = [
+                "LDMSGADDR", "LDMSGADDRQ", "PARSEMSGADDR", "PARSEMSGADDRQ",
+                "REWRITESTDADDR", "REWRITESTDADDRQ", "REWRITEVARADDR", "REWRITEVARADDRQ",
+            ];
+            match r.take(8)? {
+                x @ 0x00..=0x07 => simple(names[x as usize]),
+                x @ 0x40..=0x47 => simple(addr_names[(x & 7) as usize]),
+                _ => unknown(),
+            }
+        },
+        0xFB => match r.take(8)? {
+            0x00 => simple("SENDRAWMSG"),
+            0x02 => simple("RAWRESERVE"),
+            0x03 => simple("RAWRESERVEX"),
+            0x04 => simple("SETCODE"),
+            0x06 => simple("SETLIBCODE"),
+            0x07 => simple("CHANGELIB"),
+            _ => unknown(),
+        },
+        0xFE => match r.take(8)? {
+            x @ 0xF0..=0xFF => {
+                let data = r.take_bits(8 * ((x & 0xF) as usize + 1))?;
+                arg("DEBUGSTR", format!("{:?}", String::from_utf8_lossy(&data)))
+            },
+            x => arg("DEBUG", x),
+        },
+        0xFF => match r.take(8)? {
+            0xF0 => simple("SETCPX"),
+            x => arg("SETCP", signed(x, 8)),
+        },
+        _ => unknown(),
+    }
+}
+
+fn decode_tuple(r: &mut Reader) -> Result<Insn, String> {
+    let x = r.take(8)?;
+    let n = x & 0xF;
+    match x >> 4 {
+        0x0 => arg("TUPLE", n),
+        0x1 => arg("INDEX", n),
+        0x2 => arg("UNTUPLE", n),
+        0x3 => arg("UNPACKFIRST", n),
+        0x4 => arg("EXPLODE", n),
+        0x5 => arg("SETINDEX", n),
+        0x6 => arg("INDEXQ", n),
+        0x7 => arg("SETINDEXQ", n),
+        0x8 => {
+            let names = [
+                "TUPLEVAR", "INDEXVAR", "UNTUPLEVAR", "UNPACKFIRSTVAR", "EXPLODEVAR", "SETINDEXVAR",
+                "INDEXVARQ", "SETINDEXVARQ", "TLEN", "QTLEN", "ISTUPLE", "LAST", "TPUSH", "TPOP",
+            ];
+            names.get(n as usize).map(|name| simple(name)).unwrap_or_else(unknown)
+        },
+        0xA => {
+            let names = [
+                "NULLSWAPIF", "NULLSWAPIFNOT", "NULLROTRIF", "NULLROTRIFNOT",
+                "NULLSWAPIF2", "NULLSWAPIFNOT2", "NULLROTRIF2", "NULLROTRIFNOT2",
+            ];
+            names.get(n as usize).map(|name| simple(name)).unwrap_or_else(unknown)
+        },
+        _ => unknown(),
+    }
+}
+
+fn decode_slice_ops(r: &mut Reader) -> Result<Insn, String> {
+    let x = r.take(8)?;
+    let int_names = ["LDI", "LDU", "PLDI", "PLDU", "LDIQ", "LDUQ", "PLDIQ", "PLDUQ"];
+    let slice_names = ["LDSLICE", "PLDSLICE", "LDSLICEQ", "PLDSLICEQ"];
+    match x {
+        0x00..=0x07 => simple(&format!("{}X", int_names[x as usize])),
+        0x08..=0x0F => arg(int_names[(x & 7) as usize], r.take(8)? + 1),
+        0x18..=0x1B => simple(&format!("{}X", slice_names[(x & 3) as usize])),
+        0x1C..=0x1F => arg(slice_names[(x & 3) as usize], r.take(8)? + 1),
+        0x20 => simple("SDCUTFIRST"),
+        0x21 => simple("SDSKIPFIRST"),
+        0x22 => simple("SDCUTLAST"),
+        0x23 => simple("SDSKIPLAST"),
+        0x24 => simple("SDSUBSTR"),
+        0x26 => simple("SDBEGINSX"),
+        0x27 => simple("SDBEGINSXQ"),
+        0x30 => simple("SCUTFIRST"),
+        0x31 => simple("SSKIPFIRST"),
+        0x32 => simple("SCUTLAST"),
+        0x33 => simple("SSKIPLAST"),
+        0x34 => simple("SUBSLICE"),
+        0x36 => simple("SPLIT"),
+        0x37 => simple("SPLITQ"),
+        0x39 => simple("XCTOS"),
+        0x3A => simple("XLOAD"),
+        0x3B => simple("XLOADQ"),
+        0x41 => simple("SCHKBITS"),
+        0x42 => simple("SCHKREFS"),
+        0x43 => simple("SCHKBITREFS"),
+        0x45 => simple("SCHKBITSQ"),
+        0x46 => simple("SCHKREFSQ"),
+        0x47 => simple("SCHKBITREFSQ"),
+        0x48 => simple("PLDREFVAR"),
+        0x49 => simple("SBITS"),
+        0x4A => simple("SREFS"),
+        0x4B => simple("SBITREFS"),
+        0x4C => simple("PLDREF"),
+        0x4D..=0x4F => arg("PLDREFIDX", x & 3),
+        0x60 => simple("LDZEROES"),
+        0x61 => simple("LDONES"),
+        0x62 => simple("LDSAME"),
+        0x64 => simple("SDEPTH"),
+        0x65 => simple("CDEPTH"),
+        _ => unknown(),
+    }
+}
+
+fn decode_continuation_ops(r: &mut Reader) -> Result<Insn, String> {
+    let x = r.take(8)?;
+    let i = x & 0xF;
+    match x >> 4 {
+        0x0 => arg("RETURNARGS", i),
+        0x1 => match i {
+            0x0 => simple("RETURNVARARGS"),
+            0x1 => simple("SETCONTVARARGS"),
+            0x2 => simple("SETNUMVARARGS"),
+            0xE => simple("BLESS"),
+            0xF => simple("BLESSVARARGS"),
+            _ => unknown(),
+        },
+        0x4 => arg("PUSHCTR", format!("c{}", i)),
+        0x5 => arg("POPCTR", format!("c{}", i)),
+        0x6 => arg("SETCONTCTR", format!("c{}", i)),
+        0x7 => arg("SETRETCTR", format!("c{}", i)),
+        0x8 => arg("SETALTCTR", format!("c{}", i)),
+        0x9 => arg("POPSAVE", format!("c{}", i)),
+        0xA => arg("SAVECTR", format!("c{}", i)),
+        0xB => arg("SAVEALTCTR", format!("c{}", i)),
+        0xC => arg("SAVEBOTHCTR", format!("c{}", i)),
+        0xE => match i {
+            0x0 => simple("PUSHCTRX"),
+            0x1 => simple("POPCTRX"),
+            0x2 => simple("SETCONTCTRX"),
+            _ => unknown(),
+        },
+        0xF => {
+            let names = [
+                "COMPOS", "COMPOSALT", "COMPOSBOTH", "ATEXIT", "ATEXITALT", "SETEXITALT",
+                "THENRET", "THENRETALT", "INVERT", "BOOLEVAL", "SAMEALT", "SAMEALTSAVE",
+            ];
+            names.get(i as usize).map(|name| simple(name)).unwrap_or_else(unknown)
+        },
+        _ => unknown(),
+    }
+}
+
+fn decode_exceptions(r: &mut Reader) -> Result<Insn, String> {
+    match r.take(2)? {
+        0 => return arg("THROW", r.take(6)?),
+        1 => return arg("THROWIF", r.take(6)?),
+        2 => return arg("THROWIFNOT", r.take(6)?),
+        _ => {},
+    }
+    let x = r.take(6)?;
+    match x {
+        0x00..=0x2F => {
+            let names = ["THROW", "THROWARG", "THROWIF", "THROWARGIF", "THROWIFNOT", "THROWARGIFNOT"];
+            let n = ((x & 7) << 8) | r.take(8)?;
+            arg(names[(x >> 3) as usize], n)
+        },
+        0x30..=0x35 => {
+            let names = ["THROWANY", "THROWARGANY", "THROWANYIF", "THROWARGANYIF", "THROWANYIFNOT", "THROWARGANYIFNOT"];
+            simple(names[(x - 0x30) as usize])
+        },
+        0x3F => simple("TRY"),
+        _ => unknown(),
+    }
+}
+
+fn decode_dictionary_ops(r: &mut Reader) -> Result<Insn, String> {
+    let x = r.take(8)?;
+    if (0xA4..=0xA7).contains(&x) {
+        let bits = (((x & 3) << 8) | r.take(8)?) as usize;
+        let dict = r.take_ref()?;
+        return Ok(Insn { text: format!("DICTPUSHCONST {}", bits), dict: Some((bits, dict)), ..Default::default() });
+    }
+    // Dictionary operations come in groups of key kinds: plain, signed and unsigned integer.
+    let kinded = |base: u64, name: &str, refs: bool| -> Option<String> {
+        let offset = x.checked_sub(base)?;
+        let (kind, is_ref) = if refs { (offset / 2, offset % 2 == 1) } else { (offset, false) };
+        let kind = ["", "I", "U"].get(kind as usize)?;
+        Some(format!("DICT{}{}{}", kind, name, if is_ref { "REF" } else { "" }))
+    };
+    let name = match x {
+        0x00 => Some("STDICT".to_owned()),
+        0x01 => Some("SKIPDICT".to_owned()),
+        0x02 => Some("LDDICTS".to_owned()),
+        0x03 => Some("PLDDICTS".to_owned()),
+        0x04 => Some("LDDICT".to_owned()),
+        0x05 => Some("PLDDICT".to_owned()),
+        0x06 => Some("LDDICTQ".to_owned()),
+        0x07 => Some("PLDDICTQ".to_owned()),
+        0x0A..=0x0F => kinded(0x0A, "GET", true),
+        0x12..=0x17 => kinded(0x12, "SET", true),
+        0x1A..=0x1F => kinded(0x1A, "SETGET", true),
+        0x22..=0x27 => kinded(0x22, "REPLACE", true),
+        0x2A..=0x2F => kinded(0x2A, "REPLACEGET", true),
+        0x32..=0x37 => kinded(0x32, "ADD", true),
+        0x3A..=0x3F => kinded(0x3A, "ADDGET", true),
+        0x41..=0x43 => kinded(0x41, "SETB", false),
+        0x59..=0x5B => kinded(0x59, "DEL", false),
+        0x62..=0x67 => kinded(0x62, "DELGET", true),
+        0x69..=0x6B => kinded(0x69, "GETOPTREF", false),
+        0x6D..=0x6F => kinded(0x6D, "SETGETOPTREF", false),
+        0x74..=0x7F => {
+            let kind = ["", "I", "U"][((x - 0x74) / 4) as usize];
+            let op = ["GETNEXT", "GETNEXTEQ", "GETPREV", "GETPREVEQ"][((x - 0x74) % 4) as usize];
+            Some(format!("DICT{}{}", kind, op))
+        },
+        0x82..=0x87 => kinded(0x82, "MIN", true),
+        0x8A..=0x8F => kinded(0x8A, "MAX", true),
+        0x92..=0x97 => kinded(0x92, "REMMIN", true),
+        0x9A..=0x9F => kinded(0x9A, "REMMAX", true),
+        0xA0 => Some("DICTIGETJMP".to_owned()),
+        0xA1 => Some("DICTUGETJMP".to_owned()),
+        0xA2 => Some("DICTIGETEXEC".to_owned()),
+        0xA3 => Some("DICTUGETEXEC".to_owned()),
+        0xA8 => Some("PFXDICTGETQ".to_owned()),
+        0xA9 => Some("PFXDICTGET".to_owned()),
+        0xAA => Some("PFXDICTGETJMP".to_owned()),
+        0xAB => Some("PFXDICTGETEXEC".to_owned()),
+        0xB1 => Some("SUBDICTGET".to_owned()),
+        0xB2 => Some("SUBDICTIGET".to_owned()),
+        0xB3 => Some("SUBDICTUGET".to_owned()),
+        0xB5 => Some("SUBDICTRPGET".to_owned()),
+        0xB6 => Some("SUBDICTIRPGET".to_owned()),
+        0xB7 => Some("SUBDICTURPGET".to_owned()),
+        0xBC => Some("DICTIGETJMPZ".to_owned()),
+        0xBD => Some("DICTUGETJMPZ".to_owned()),
+        0xBE => Some("DICTIGETEXECZ".to_owned()),
+        0xBF => Some("DICTUGETEXECZ".to_owned()),
+        _ => None,
+    };
+    name.map(|name| Ok(Insn::new(name))).unwrap_or_else(unknown)
+}
+
+fn decode_blockchain_ops(r: &mut Reader) -> Result<Insn, String> {
+    let x = r.take(8)?;
+    let params = [
+        "", "", "", "NOW", "BLOCKLT", "LTIME", "RANDSEED", "BALANCE", "MYADDR", "CONFIGROOT",
+    ];
+    match x {
+        0x00 => simple("ACCEPT"),
+        0x01 => simple("SETGASLIMIT"),
+        0x04 => simple("GRAMTOGAS"),
+        0x05 => simple("GASTOGRAM"),
+        0x0F => simple("COMMIT"),
+        0x10 => simple("RANDU256"),
+        0x11 => simple("RAND"),
+        0x14 => simple("SETRAND"),
+        0x15 => simple("ADDRAND"),
+        0x20..=0x2F => match params.get((x & 0xF) as usize) {
+            Some(name) if !name.is_empty() => simple(name),
+            _ => arg("GETPARAM", x & 0xF),
+        },
+        0x30 => simple("CONFIGDICT"),
+        0x32 => simple("CONFIGPARAM"),
+        0x33 => simple("CONFIGOPTPARAM"),
+        0x40 => simple("GETGLOBVAR"),
+        0x41..=0x5F => arg("GETGLOB", x & 0x1F),
+        0x60 => simple("SETGLOBVAR"),
+        0x61..=0x7F => arg("SETGLOB", x & 0x1F),
+        _ => unknown(),
+    }
+}
+
+/// Reads `#<= max` field of a hashmap label.
+fn label_len_bits(max: usize) -> usize {
+    let mut bits = 0;
+    while (1usize << bits) <= max {
+        bits += 1;
+    }
+    bits
+}
+
+/// Reads key bits of a label, only the lowest 64 bits are kept.
+fn take_key(r: &mut Reader, len: usize) -> Result<u64, String> {
+    if len > 64 {
+        r.take_bits(len - 64)?;
+    }
+    r.take(len.min(64))
+}
+
+/// Collects entries of a hashmap with `key_bits` keys: key bits packed to
+/// u64 (keys longer than 64 bits keep their lowest bits) and the value.
+pub fn dict_entries(root: &Cell, key_bits: usize) -> Result<Vec<(u64, Reader)>, String> {
+    let mut entries = vec![];
+    let mut stack = vec![(root.clone(), 0u64, key_bits)];
+    while let Some((cell, prefix, remaining)) = stack.pop() {
+        let mut r = Reader::from_cell(&cell)?;
+        let (len, key) = if r.take(1)? == 0 {
+            let mut len = 0;
+            while r.take(1)? == 1 {
+                len += 1;
+            }
+            (len, take_key(&mut r, len)?)
+        } else if r.take(1)? == 0 {
+            let len = r.take(label_len_bits(remaining))? as usize;
+            (len, take_key(&mut r, len)?)
+        } else {
+            let bit = r.take(1)?;
+            let len = r.take(label_len_bits(remaining))? as usize;
+            let key = match (bit, len) {
+                (0, _) | (_, 0) => 0,
+                (_, len) if len >= 64 => u64::MAX,
+                (_, len) => (1u64 << len) - 1,
+            };
+            (len, key)
+        };
+        if len > remaining {
+            return Err("invalid dictionary label".to_owned());
+        }
+        let prefix = if len >= 64 { key } else { (prefix << len) | key };
+        let remaining = remaining - len;
+        if remaining == 0 {
+            entries.push((prefix, r));
+            continue;
+        }
+        let left = r.take_ref()?;
+        let right = r.take_ref()?;
+        stack.push((right, (prefix << 1) | 1, remaining - 1));
+        stack.push((left, prefix << 1, remaining - 1));
+    }
+    Ok(entries)
+}
+
+/// Function ids of the ABIs: id -> "<abi>.<function> (input|output)".
+pub fn abi_selectors(name: &str, abi: &str) -> Result<BTreeMap<u32, String>, String> {
+    let contract = Contract::load(abi.as_bytes())
+        .map_err(|e| format!("failed to parse ABI {}: {}", name, e))?;
+    let mut selectors = BTreeMap::new();
+    for (fname, function) in contract.functions() {
+        selectors.insert(function.get_input_id(), format!("{}.{} (input)", name, fname));
+        selectors.insert(function.get_output_id(), format!("{}.{} (output)", name, fname));
+    }
+    Ok(selectors)
+}
+
+struct Disassembler {
+    selectors: BTreeMap<u32, String>,
+    lines: Vec<String>,
+}
+
+impl Disassembler {
+    fn annotate(&self, value: i64) -> String {
+        if value < 0 || value > u32::MAX as i64 {
+            return String::new();
+        }
+        self.selectors.get(&(value as u32))
+            .map(|name| format!("  ; {}", name))
+            .unwrap_or_default()
+    }
+
+    fn push(&mut self, depth: usize, line: String) {
+        self.lines.push(format!("{}{}", INDENT.repeat(depth), line));
+    }
+
+    fn code(&mut self, mut r: Reader, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            self.push(depth, "; nesting is too deep".to_owned());
+            return Ok(());
+        }
+        loop {
+            if r.remaining() == 0 {
+                match r.refs.len() {
+                    0 => return Ok(()),
+                    // Code continues in the last reference (implicit JMPREF).
+                    1 => {
+                        r = Reader::from_cell(&r.take_ref()?)?;
+                        continue;
+                    },
+                    n => {
+                        self.push(depth, format!("; {} unused references", n));
+                        return Ok(());
+                    },
+                }
+            }
+            let start = r.pos;
+            let insn = match decode(&mut r) {
+                Ok(insn) => insn,
+                Err(e) => {
+                    r.pos = start;
+                    self.push(depth, format!("; {}: {}", e, r.to_fift()));
+                    return Ok(());
+                },
+            };
+            let note = insn.constant.map(|value| self.annotate(value)).unwrap_or_default();
+            match (insn.conts.is_empty(), insn.dict) {
+                (true, None) => self.push(depth, format!("{}{}", insn.text, note)),
+                (true, Some((bits, dict))) => {
+                    self.push(depth, format!("{} {{", insn.text));
+                    self.dict(&dict, bits, depth + 1)?;
+                    self.push(depth, "}".to_owned());
+                },
+                (false, _) => {
+                    self.push(depth, format!("{} {{", insn.text));
+                    for (i, cont) in insn.conts.into_iter().enumerate() {
+                        if i > 0 {
+                            self.push(depth, "} {".to_owned());
+                        }
+                        self.code(cont, depth + 1)?;
+                    }
+                    self.push(depth, "}".to_owned());
+                },
+            }
+        }
+    }
+
+    fn dict(&mut self, root: &Cell, key_bits: usize, depth: usize) -> Result<(), String> {
+        for (key, value) in dict_entries(root, key_bits)? {
+            let id = signed(key, key_bits);
+            let note = if key_bits == 32 { self.annotate(key as i64) } else { String::new() };
+            self.push(depth, format!("{} => {{{}", id, note));
+            self.code(value, depth + 1)?;
+            self.push(depth, "}".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Disassembles code cell into lines of assembly.
+pub fn disassemble(code: &Cell, selectors: BTreeMap<u32, String>) -> Result<Vec<String>, String> {
+    let mut disasm = Disassembler { selectors, lines: vec![] };
+    disasm.code(Reader::from_cell(code)?, 0)?;
+    Ok(disasm.lines)
+}
+
+fn load_code_from_file(path: &str) -> Result<Cell, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("failed to read {}: {}", path, e))?;
+    if let Ok(state_init) = StateInit::construct_from_bytes(&bytes) {
+        if let Some(code) = state_init.code {
+            return Ok(code);
+        }
+    }
+    ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes))
+        .map_err(|e| format!("{} is neither tvc nor code boc: {}", path, e))
+}
+
+async fn load_code_from_account(address: &str, config: &Config) -> Result<Cell, String> {
+    let ton = create_client_verbose(config)?;
+    let accounts = query(
+        ton,
+        "accounts",
+        json!({ "id": { "eq": address } }),
+        "code",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query account code: {}", e))?;
+    let code = accounts.first()
+        .and_then(|acc| acc["code"].as_str())
+        .ok_or(format!("account {} not found or has no code", address))?;
+    let bytes = base64::decode(code)
+        .map_err(|e| format!("failed to decode account code: {}", e))?;
+    ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes))
+        .map_err(|e| format!("failed to deserialize account code: {}", e))
+}
+
+pub async fn disasm_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let source = m.value_of("SOURCE").unwrap();
+    let code = if Path::new(source).exists() {
+        load_code_from_file(source)?
+    } else {
+        let address = load_ton_address(source, &config)?;
+        load_code_from_account(&address, &config).await?
+    };
+
+    let abi_paths: Vec<String> = match m.values_of("ABI") {
+        Some(values) => values.map(|s| s.to_owned()).collect(),
+        None => config.abi_path.iter().cloned().collect(),
+    };
+    let mut selectors = BTreeMap::new();
+    for path in abi_paths {
+        let abi = std::fs::read_to_string(&path)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read ABI file {}: {}", path, e)))?;
+        let name = Path::new(&path).file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.trim_end_matches(".json").trim_end_matches(".abi").to_owned())
+            .unwrap_or(path.clone());
+        selectors.extend(abi_selectors(&name, &abi)?);
+    }
+
+    let mut text = format!("; code hash {}\n", code.repr_hash().to_hex_string());
+    for line in disassemble(&code, selectors)? {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    match m.value_of("OUTPUT") {
+        Some(path) => {
+            std::fs::write(path, text)
+                .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to write {}: {}", path, e)))?;
+            println!("Disassembly saved to {}", path);
+        },
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disasm_bytes(bytes: &[u8]) -> Vec<String> {
+        let mut disasm = Disassembler { selectors: BTreeMap::new(), lines: vec![] };
+        disasm.selectors.insert(0x1234, "Wallet.foo (input)".to_owned());
+        disasm.code(Reader::new(bytes.to_vec(), bytes.len() * 8, vec![]), 0).unwrap();
+        disasm.lines
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let lines = disasm_bytes(&[
+            0xFF, 0x00,             // SETCP 0
+            0x20,                   // DUP
+            0x81, 0x12, 0x34,       // PUSHINT 0x1234
+            0xBA,                   // EQUAL
+            0x91, 0xA0,             // PUSHCONT { ADD }
+            0xF2, 0xA5,             // THROWIFNOT 37
+            0xF2, 0xC8, 0x65,       // THROWARG 101
+            0x7F,                   // PUSHINT -1
+            0x00, 0xEE,             // NOP, truncated BLESSARGS
+        ]);
+        assert_eq!(lines, vec![
+            "SETCP 0",
+            "DUP",
+            "PUSHINT 4660  ; Wallet.foo (input)",
+            "EQUAL",
+            "PUSHCONT {",
+            "  ADD",
+            "}",
+            "THROWIFNOT 37",
+            "THROWARG 101",
+            "PUSHINT -1",
+            "NOP",
+            "; unexpected end of code: x{EE}",
+        ]);
+    }
+
+    #[test]
+    fn test_reader() {
+        let mut r = Reader::new(vec![0b1011_0000], 5, vec![]);
+        assert_eq!(r.peek(3), Some(0b101));
+        assert_eq!(r.take_signed(3).unwrap(), -3);
+        assert_eq!(r.to_fift(), "x{A_}");
+        assert_eq!(signed(0xFF, 8), -1);
+        assert_eq!(label_len_bits(32), 6);
+        assert_eq!(label_len_bits(0), 0);
+    }
+}
@@ -0,0 +1,263 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Crate-wide error type. Modules are migrated from `Result<_, String>`
+//! gradually: `CliError` converts from and into `String`, so `?` works across
+//! the boundary. Kind is set where the error is created (`CliError::new`,
+//! `ResultExt::kind`), errors converted from strings are `Internal`.
+use crate::exit_codes::find_exit_code;
+use serde_json::Value;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+static PARTIAL_FAILURE: AtomicI32 = AtomicI32::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Wrong command line arguments or user input.
+    InvalidInput,
+    Config,
+    /// File system errors.
+    Io,
+    /// Endpoint is unavailable, request failed or timed out.
+    Network,
+    /// Contract execution failed (exit code, expired message, etc).
+    Contract,
+    /// Operation was cancelled by the user.
+    Aborted,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::Config => "config",
+            ErrorKind::Io => "io",
+            ErrorKind::Network => "network",
+            ErrorKind::Contract => "contract",
+            ErrorKind::Aborted => "aborted",
+            ErrorKind::Internal => "internal",
+        }
+    }
+
+    /// Process exit code for errors of this kind.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Internal => 1,
+            ErrorKind::InvalidInput => 2,
+            ErrorKind::Config => 3,
+            ErrorKind::Io => 4,
+            ErrorKind::Network => 5,
+            ErrorKind::Contract => 6,
+            ErrorKind::Aborted => 130,
+        }
+    }
+
+    /// Kind of the client library error by the module which reported it:
+    /// error codes of each module start from its own hundred.
+    pub fn from_client_error(error: &ton_client::error::ClientError) -> Self {
+        match error.code {
+            // websocket and http errors of the client module
+            6..=12 => ErrorKind::Network,
+            // crypto, boc and abi
+            100..=399 => ErrorKind::InvalidInput,
+            // tvm and processing
+            400..=599 => ErrorKind::Contract,
+            600..=699 => ErrorKind::Network,
+            _ => ErrorKind::Internal,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CliError {
+    kind: ErrorKind,
+    message: String,
+    /// What was being done when the error occurred, innermost first.
+    context: Vec<String>,
+    source: Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), context: vec![], source: None }
+    }
+
+    pub fn with_source<E>(kind: ErrorKind, message: impl Into<String>, source: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self { kind, message: message.into(), context: vec![], source: Some(Box::new(source)) }
+    }
+
+    pub fn aborted(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Aborted, message)
+    }
+
+    /// Adds description of the operation which failed, e.g. "failed to deploy contract".
+    pub fn context(mut self, context: impl Into<String>) -> Self {
+        self.context.push(context.into());
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.kind.exit_code()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut context = self.context.clone();
+        context.reverse();
+        json!({
+            "kind": self.kind.as_str(),
+            "message": self.message,
+            "context": context,
+            "source": self.source.as_ref().map(|e| e.to_string()),
+            "contract_exit_code": find_exit_code(&self.to_string()),
+            "exit_code": self.exit_code(),
+        })
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for context in self.context.iter().rev() {
+            write!(f, "{}: ", context)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CliError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|e| e.as_ref() as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Internal, message)
+    }
+}
+
+impl From<&str> for CliError {
+    fn from(message: &str) -> Self {
+        Self::from(message.to_owned())
+    }
+}
+
+impl From<CliError> for String {
+    fn from(error: CliError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(error: std::io::Error) -> Self {
+        Self::with_source(ErrorKind::Io, "I/O error", error)
+    }
+}
+
+impl From<ton_client::error::ClientError> for CliError {
+    fn from(error: ton_client::error::ClientError) -> Self {
+        Self::new(ErrorKind::from_client_error(&error), error.to_string())
+    }
+}
+
+/// Sets kind of the `String` error explicitly instead of guessing it from the message.
+pub trait ResultExt<T> {
+    fn kind(self, kind: ErrorKind) -> Result<T, CliError>;
+}
+
+impl<T> ResultExt<T> for Result<T, String> {
+    fn kind(self, kind: ErrorKind) -> Result<T, CliError> {
+        self.map_err(|e| CliError::new(kind, e))
+    }
+}
+
+impl<T> ResultExt<T> for Result<T, CliError> {
+    /// Kind of the error is replaced, e.g. when failed parsing of a config value is a config error.
+    fn kind(self, kind: ErrorKind) -> Result<T, CliError> {
+        self.map_err(|e| CliError { kind, ..e })
+    }
+}
+
+/// Errors are printed as json when the command output is json.
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Marks the command as partially failed: it succeeded and printed its output,
+/// but the process exits with the code of `kind`. Failures are reported by the command.
+pub fn set_partial_failure(kind: ErrorKind) {
+    PARTIAL_FAILURE.store(kind.exit_code(), Ordering::Relaxed);
+}
+
+pub fn partial_failure_code() -> i32 {
+    PARTIAL_FAILURE.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_error() {
+        let err = CliError::new(ErrorKind::Network, "connection refused")
+            .context("failed to query account")
+            .context("balance");
+        assert_eq!(err.to_string(), "balance: failed to query account: connection refused");
+        assert_eq!(err.to_json()["context"], json!(["balance", "failed to query account"]));
+        assert_eq!(err.exit_code(), 5);
+
+        let err = CliError::with_source(ErrorKind::Io, "failed to read keys.json", std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(std::error::Error::source(&err).is_some());
+        assert_eq!(err.kind(), ErrorKind::Io);
+
+        assert_eq!(CliError::from("failed to parse stake value").kind(), ErrorKind::Internal);
+        assert_eq!(String::from(CliError::aborted("debot session aborted")), "debot session aborted");
+    }
+
+    #[test]
+    fn test_error_kind() {
+        use ton_client::error::ClientError;
+        let kind = |code| CliError::from(ClientError::with_code_message(code, "error".to_owned())).kind();
+        assert_eq!(kind(414), ErrorKind::Contract);
+        assert_eq!(kind(507), ErrorKind::Contract);
+        assert_eq!(kind(601), ErrorKind::Network);
+        assert_eq!(kind(11), ErrorKind::Network);
+        assert_eq!(kind(301), ErrorKind::InvalidInput);
+        assert_eq!(kind(33), ErrorKind::Internal);
+
+        let result: Result<(), String> = Err("something strange".to_owned());
+        assert_eq!(result.kind(ErrorKind::Io).unwrap_err().kind(), ErrorKind::Io);
+        let result: Result<(), CliError> = Err(CliError::new(ErrorKind::InvalidInput, "invalid value"));
+        assert_eq!(result.kind(ErrorKind::Config).unwrap_err().kind(), ErrorKind::Config);
+    }
+}
@@ -0,0 +1,140 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client, decode_msg_body, load_ton_address, subscribe, BulkQuery, PageCursor, TonClient};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use ton_client::net::unsubscribe;
+
+const EVENT_FIELDS: &str = "id body created_at created_lt";
+
+pub fn create_events_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("events")
+        .about("Contract events commands.")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .subcommand(SubCommand::with_name("tail")
+            .about("Prints decoded historical events of the contract and then waits for new ones. Every event is printed as a json line.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("FROM_LT")
+                .long("--from-lt")
+                .takes_value(true)
+                .help("Logical time of the first event to print (default: only new events)."))
+            .arg(Arg::with_name("RESUME")
+                .long("--resume")
+                .takes_value(true)
+                .help("Checkpoint file. Position of the last printed event is saved to it, if the file exists printing continues after that event (--from-lt is ignored then).")))
+}
+
+pub async fn events_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("tail") {
+        return tail_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown events command"))
+}
+
+fn events_filter(addr: &str) -> serde_json::Value {
+    json!({
+        "src": { "eq": addr },
+        "msg_type": { "eq": 2 },
+    })
+}
+
+async fn print_event(ton: TonClient, abi: &str, event: &serde_json::Value) {
+    let body = event["body"].as_str().unwrap_or("");
+    let (name, value) = match decode_msg_body(ton, abi, body, false).await {
+        Ok(decoded) => (decoded.name, decoded.value.unwrap_or(json!({}))),
+        Err(_) => ("unknown".to_owned(), json!({ "body": body })),
+    };
+    println!("{}", json!({
+        "id": event["id"],
+        "lt": event["created_lt"],
+        "created_at": event["created_at"],
+        "name": name,
+        "value": value,
+    }));
+}
+
+/// Parses logical time returned by GraphQL (hex string with 0x prefix or decimal).
+fn parse_lt(value: &serde_json::Value) -> u64 {
+    let s = value.as_str().unwrap_or("0");
+    if s.starts_with("0x") {
+        u64::from_str_radix(&s[2..], 16).unwrap_or(0)
+    } else {
+        u64::from_str_radix(s, 10).unwrap_or(0)
+    }
+}
+
+async fn tail_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let address = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+    let abi = m.value_of("ABI")
+        .map(|s| s.to_string())
+        .or(config.abi_path.clone())
+        .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?;
+    let abi = std::fs::read_to_string(abi)
+        .map_err(|e| format!("failed to read ABI file: {}", e))?;
+    let from_lt = m.value_of("FROM_LT")
+        .map(|lt| u64::from_str_radix(lt, 10))
+        .transpose()
+        .map_err(|e| format!("failed to parse logical time: {}", e))?;
+
+    // output must be a pure json stream, so the client is created silently
+    let ton = create_client(&config)?;
+
+    // Subscription is started before backfill so that no event is lost in between.
+    let (subscription, mut receiver) = subscribe(ton.clone(), "messages", events_filter(&address), EVENT_FIELDS).await
+        .map_err(|e| format!("failed to subscribe to events: {}", e))?;
+
+    let resume = m.value_of("RESUME");
+    let mut cursor = PageCursor::new("messages", events_filter(&address), "created_lt");
+    if let Some(from_lt) = from_lt {
+        cursor = cursor.starting_at(json!(format!("0x{:x}", from_lt)));
+    }
+    if let Some(path) = resume {
+        cursor = PageCursor::load_or(path, cursor)?.0;
+    }
+    if cursor.last().is_some() {
+        let bulk = BulkQuery::new(ton.clone(), &config);
+        while let Some(history) = bulk.next_page(&mut cursor, EVENT_FIELDS).await? {
+            for event in history {
+                print_event(ton.clone(), &abi, &event).await;
+            }
+            if let Some(path) = resume {
+                cursor.save(path)?;
+            }
+        }
+    }
+
+    while let Some(event) = receiver.recv().await {
+        let event = match event {
+            Ok(event) => event,
+            Err(_) => continue,
+        };
+        let last_lt = cursor.last().map(parse_lt).unwrap_or(0);
+        if parse_lt(&event["created_lt"]) < last_lt || !cursor.advance(&event) {
+            continue;
+        }
+        print_event(ton.clone(), &abi, &event).await;
+        if let Some(path) = resume {
+            cursor.save(path)?;
+        }
+    }
+    let _ = unsubscribe(ton, subscription).await;
+    Ok(())
+}
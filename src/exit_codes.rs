@@ -0,0 +1,155 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref CONTRACT_ERRORS: Mutex<BTreeMap<i32, String>> = Mutex::new(BTreeMap::new());
+}
+
+/// Returns path of the contract error table stored next to the ABI file:
+/// `Wallet.abi.json` -> `Wallet.errors.json`.
+pub fn error_table_path(abi_path: &str) -> String {
+    let base = abi_path.strip_suffix(".abi.json")
+        .or(abi_path.strip_suffix(".json"))
+        .unwrap_or(abi_path);
+    format!("{}.errors.json", base)
+}
+
+/// Loads contract specific error descriptions (json object `{"<code>": "<description>"}`)
+/// for the ABI if the table exists.
+pub fn load_error_table(abi_path: &str) -> Result<(), String> {
+    let path = error_table_path(abi_path);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) => return Ok(()),
+    };
+    let table: BTreeMap<String, String> = serde_json::from_str(&text)
+        .map_err(|e| format!("failed to parse error table {}: {}", path, e))?;
+    let mut errors = CONTRACT_ERRORS.lock().unwrap();
+    for (code, description) in table {
+        let code = i32::from_str_radix(code.trim(), 10)
+            .map_err(|e| format!("invalid exit code {} in {}: {}", code, path, e))?;
+        errors.insert(code, description);
+    }
+    Ok(())
+}
+
+/// Returns description of well-known TVM and Solidity runtime exit codes.
+pub fn describe_exit_code(code: i32) -> Option<&'static str> {
+    Some(match code {
+        0 | 1 => "success",
+        2 => "stack underflow",
+        3 => "stack overflow",
+        4 => "integer overflow",
+        5 => "range check error",
+        6 => "invalid opcode",
+        7 => "type check error",
+        8 => "cell overflow",
+        9 => "cell underflow",
+        10 => "dictionary error",
+        13 => "out of gas",
+        32 => "action list is invalid",
+        33 => "too many actions",
+        34 => "unsupported action",
+        35 => "invalid source address in outbound message",
+        36 => "invalid destination address in outbound message",
+        37 => "not enough balance to send the message (insufficient balance)",
+        38 => "not enough extra currencies to send the message",
+        -14 => "out of gas",
+        40 => "external inbound message has an invalid signature",
+        50 => "array index or index of <mapping>.at() is out of range",
+        51 => "contract's constructor has already been called",
+        52 => "replay protection exception",
+        53 => "address unpack error",
+        54 => "pop from empty array",
+        55 => "bad StateInit cell for tvm.insertPubkey",
+        57 => "external inbound message is expired",
+        58 => "external inbound message has no signature but has public key",
+        60 => "inbound message has wrong function id",
+        61 => "deploying StateInit has no public key in data field",
+        62 => "reserved for internal usage",
+        63 => "optional value is not set",
+        64 => "tvm.buildExtMSg() call with wrong parameters",
+        65 => "call of the unassigned variable of function type",
+        66 => "convert an integer to a string with width less than number length",
+        67 => "gasToValue/valueToGas call with wrong workchain id",
+        68 => "there is no config parameter 20 or 21",
+        69 => "zero to the power of zero calculation",
+        70 => "string method substr was called with substr longer than the whole string",
+        71 => "function marked by externalMsg was called by internal message",
+        72 => "function marked by internalMsg was called by external message",
+        100 => "message sender is not a custodian key (multisig)",
+        101 => "message sender is not the owner (depool)",
+        102 => "transaction does not exist (multisig)",
+        103 => "operation is already confirmed by this custodian (multisig)",
+        107 => "input value is too low",
+        113 => "too many pending transactions (multisig)",
+        _ => return None,
+    })
+}
+
+/// Tries to find exit code in SDK error message (e.g. "... exit code: 101").
+pub fn find_exit_code(error: &str) -> Option<i32> {
+    let pos = error.find("exit code")?;
+    let tail = error[pos + "exit code".len()..]
+        .trim_start_matches(|c: char| c == ':' || c == ' ' || c == '=');
+    let end = tail.char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+        .map(|(i, _)| i)
+        .unwrap_or(tail.len());
+    i32::from_str_radix(&tail[..end], 10).ok()
+}
+
+/// Returns human-readable explanation of the error if it contains a known exit code.
+pub fn explain_error(error: &str) -> Option<String> {
+    let code = find_exit_code(error)?;
+    if let Some(desc) = CONTRACT_ERRORS.lock().unwrap().get(&code) {
+        return Some(format!("exit code {}: {}", code, desc));
+    }
+    describe_exit_code(code).map(|desc| format!("exit code {}: {}", code, desc))
+}
+
+/// Checks if the error is caused by expiration of a message, i.e. the message
+/// can be built again with fresh timestamps and resent.
+pub fn is_expiration_error(error: &str) -> bool {
+    find_exit_code(error) == Some(57) || error.to_lowercase().contains("message expired")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_exit_code() {
+        assert_eq!(find_exit_code("Contract execution was terminated with error, exit code: 101"), Some(101));
+        assert_eq!(find_exit_code("exit code -14, gas used"), Some(-14));
+        assert_eq!(find_exit_code("exit code=52"), Some(52));
+        assert_eq!(find_exit_code("some other error"), None);
+        assert_eq!(explain_error("exit code: 57").unwrap(), "exit code 57: external inbound message is expired");
+        assert_eq!(explain_error("exit code: 101").unwrap(), "exit code 101: message sender is not the owner (depool)");
+    }
+
+    #[test]
+    fn test_is_expiration_error() {
+        assert!(is_expiration_error("Contract execution was terminated with error, exit code: 57"));
+        assert!(is_expiration_error("Message expired. Message id: 1234"));
+        assert!(!is_expiration_error("exit code: 101"));
+    }
+
+    #[test]
+    fn test_error_table_path() {
+        assert_eq!(error_table_path("SafeMultisigWallet.abi.json"), "SafeMultisigWallet.errors.json");
+        assert_eq!(error_table_path("wallet.json"), "wallet.errors.json");
+    }
+}
@@ -0,0 +1,401 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::convert::nano_to_tokens;
+use crate::helpers::{create_client_verbose, create_http_client, load_checkpoint, send_http, save_checkpoint, BulkQuery, PageCursor};
+use crate::vars::json_path;
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+
+const CURRENCY: &str = "TON";
+
+const TRANSACTION_FIELDS: &str = r#"
+    id now lt
+    total_fees(format: DEC)
+    in_message { src value(format: DEC) msg_type }
+    out_messages { dst value(format: DEC) msg_type }
+"#;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Koinly,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "csv" => Ok(ExportFormat::Csv),
+            "koinly" => Ok(ExportFormat::Koinly),
+            _ => Err(format!("unknown export format {} (supported: csv, koinly)", format)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Direction {
+    In,
+    Out,
+    Fee,
+}
+
+/// One line of the export. Transaction fee is attached to one line of the transaction.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub time: u32,
+    pub tx: String,
+    pub direction: Direction,
+    pub counterparty: String,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+fn parse_value(value: &Value) -> u64 {
+    value.as_str().and_then(|v| u64::from_str_radix(v, 10).ok()).unwrap_or(0)
+}
+
+/// Splits transaction into inbound and outbound transfers. Only internal
+/// messages (msg_type 0) carry value, bounced value is a separate outbound transfer.
+pub fn classify(tx: &Value) -> Vec<Entry> {
+    let time = tx["now"].as_u64().unwrap_or(0) as u32;
+    let id = tx["id"].as_str().unwrap_or("").to_owned();
+    let mut entries = vec![];
+    let make = |direction, counterparty: &Value, amount| Entry {
+        time,
+        tx: id.clone(),
+        direction,
+        counterparty: counterparty.as_str().unwrap_or("").to_owned(),
+        amount,
+        fee: 0,
+    };
+    let msg = &tx["in_message"];
+    if msg["msg_type"].as_u64() == Some(0) && parse_value(&msg["value"]) > 0 {
+        entries.push(make(Direction::In, &msg["src"], parse_value(&msg["value"])));
+    }
+    for msg in tx["out_messages"].as_array().cloned().unwrap_or_default() {
+        if msg["msg_type"].as_u64() == Some(0) && parse_value(&msg["value"]) > 0 {
+            entries.push(make(Direction::Out, &msg["dst"], parse_value(&msg["value"])));
+        }
+    }
+    let fee = parse_value(&tx["total_fees"]);
+    // fee goes to the outbound line if there is one, otherwise to the inbound one
+    let index = entries.iter()
+        .position(|e| e.direction == Direction::Out)
+        .or(if entries.is_empty() { None } else { Some(0) });
+    match index {
+        Some(index) => entries[index].fee = fee,
+        None if fee > 0 => entries.push(Entry { fee, ..make(Direction::Fee, &Value::Null, 0) }),
+        None => {},
+    }
+    entries
+}
+
+/// Fetches token price in fiat currency for the date from configured source.
+/// `price_url` may contain `{date}` (dd-mm-yyyy), `{iso_date}` (yyyy-mm-dd),
+/// `{timestamp}` and `{currency}`, `price_path` is a json path to the price
+/// in the response, it may contain `{currency}` too.
+struct PriceSource {
+    url: String,
+    path: String,
+    currency: String,
+    cache: HashMap<String, Option<f64>>,
+    client: reqwest::Client,
+}
+
+impl PriceSource {
+    fn substitute(template: &str, time: u32, currency: &str) -> String {
+        let date = Utc.timestamp(time as i64, 0);
+        template
+            .replace("{date}", &date.format("%d-%m-%Y").to_string())
+            .replace("{iso_date}", &date.format("%Y-%m-%d").to_string())
+            .replace("{timestamp}", &time.to_string())
+            .replace("{currency}", currency)
+    }
+
+    async fn price(&mut self, time: u32) -> Option<f64> {
+        let url = Self::substitute(&self.url, time, &self.currency.to_lowercase());
+        if let Some(price) = self.cache.get(&url) {
+            return *price;
+        }
+        let price = match self.fetch(&url).await {
+            Ok(response) => {
+                let path = Self::substitute(&self.path, time, &self.currency.to_lowercase());
+                json_path(&response, &path).ok().flatten().and_then(|price| {
+                    price.as_f64().or(price.as_str().and_then(|s| s.parse::<f64>().ok()))
+                })
+            },
+            Err(e) => {
+                log::warn!("failed to get price from {}: {}", url, e);
+                None
+            },
+        };
+        self.cache.insert(url, price);
+        price
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Value, String> {
+        send_http(|| self.client.get(url))
+            .await?
+            .json::<Value>()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Parses unix time or date (yyyy-mm-dd).
+pub fn parse_time(value: &str) -> Result<u32, String> {
+    if let Ok(time) = u32::from_str_radix(value, 10) {
+        return Ok(time);
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms(0, 0, 0).timestamp() as u32)
+        .map_err(|e| format!("failed to parse time \"{}\" (unix time or yyyy-mm-dd expected): {}", value, e))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+fn header(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Csv => "date,tx_hash,direction,counterparty,amount,fee,currency,fiat_value,fiat_currency",
+        ExportFormat::Koinly => "Date,Sent Amount,Sent Currency,Received Amount,Received Currency,Fee Amount,Fee Currency,Net Worth Amount,Net Worth Currency,Label,Description,TxHash",
+    }
+}
+
+pub fn format_entry(format: ExportFormat, entry: &Entry, price: Option<f64>, fiat: &str) -> String {
+    let date = Utc.timestamp(entry.time as i64, 0);
+    let worth = price
+        .map(|p| format!("{:.2}", if entry.amount > 0 { entry.amount } else { entry.fee } as f64 / 1e9 * p))
+        .unwrap_or_default();
+    let worth_currency = if price.is_some() { fiat } else { "" };
+    let amount = if entry.amount > 0 { nano_to_tokens(entry.amount) } else { String::new() };
+    let fee = if entry.fee > 0 { nano_to_tokens(entry.fee) } else { String::new() };
+    match format {
+        ExportFormat::Csv => {
+            let direction = match entry.direction {
+                Direction::In => "in",
+                Direction::Out => "out",
+                Direction::Fee => "fee",
+            };
+            vec![
+                date.to_rfc3339(), entry.tx.clone(), direction.to_owned(), entry.counterparty.clone(),
+                amount, fee, CURRENCY.to_owned(), worth, worth_currency.to_owned(),
+            ]
+        },
+        ExportFormat::Koinly => {
+            let currency = |value: &str| if value.is_empty() { "" } else { CURRENCY }.to_owned();
+            let (sent, received, label, description) = match entry.direction {
+                Direction::In => (String::new(), amount, "", format!("from {}", entry.counterparty)),
+                Direction::Out => (amount, String::new(), "", format!("to {}", entry.counterparty)),
+                Direction::Fee => (String::new(), String::new(), "cost", "transaction fee".to_owned()),
+            };
+            vec![
+                date.format("%Y-%m-%d %H:%M UTC").to_string(),
+                sent.clone(), currency(&sent),
+                received.clone(), currency(&received),
+                fee.clone(), currency(&fee),
+                worth, worth_currency.to_owned(),
+                label.to_owned(), description, entry.tx.clone(),
+            ]
+        },
+    }
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Export position: the paging cursor and length of the output file written
+/// up to it. Lines written after the last saved page are cut off on resume.
+#[derive(Serialize, Deserialize)]
+struct ExportCheckpoint {
+    cursor: PageCursor,
+    #[serde(default)]
+    output_len: u64,
+}
+
+/// Exports transactions page by page. With `resume` the position is saved to
+/// the checkpoint file after every page, so an interrupted export continues
+/// where it stopped and appends to the output file.
+pub async fn export_transactions(
+    conf: Config,
+    addr: &str,
+    format: ExportFormat,
+    since: Option<u32>,
+    until: Option<u32>,
+    fiat: &str,
+    output: Option<&str>,
+    resume: Option<&str>,
+) -> Result<(), String> {
+    let ton = create_client_verbose(&conf)?;
+    let bulk = BulkQuery::new(ton, &conf);
+    let mut filter = json!({ "account_addr": { "eq": addr } });
+    if let Some(since) = since {
+        filter["now"] = json!({ "ge": since });
+    }
+    let fresh = PageCursor::new("transactions", filter, "lt");
+    let saved = match resume {
+        Some(path) => load_checkpoint::<ExportCheckpoint>(path)?,
+        None => None,
+    };
+    if let Some(saved) = &saved {
+        if !saved.cursor.is_same_query(&fresh) {
+            return Err(format!("checkpoint {} was saved by another query", resume.unwrap()));
+        }
+    }
+    let resumed = saved.is_some();
+    let (mut cursor, mut output_len) = match saved {
+        Some(saved) => (saved.cursor, saved.output_len),
+        None => (fresh, 0),
+    };
+    if resumed {
+        eprintln!("Resuming export after {} transactions", cursor.count());
+    }
+
+    let mut prices = match (&conf.price_url, &conf.price_path) {
+        (Some(url), Some(path)) => Some(PriceSource {
+            url: url.clone(),
+            path: path.clone(),
+            currency: fiat.to_owned(),
+            cache: HashMap::new(),
+            client: create_http_client(&conf)?,
+        }),
+        _ => None,
+    };
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(!resumed)
+                .open(path)
+                .map_err(|e| format!("failed to open {}: {}", path, e))?;
+            if resumed {
+                let len = file.metadata()
+                    .map_err(|e| format!("failed to read {}: {}", path, e))?
+                    .len();
+                if len < output_len {
+                    return Err(format!("{} is shorter than saved in the checkpoint", path));
+                }
+                file.set_len(output_len)
+                    .and_then(|_| file.seek(SeekFrom::End(0)))
+                    .map_err(|e| format!("failed to truncate {}: {}", path, e))?;
+            }
+            Box::new(file)
+        },
+        None => Box::new(std::io::stdout()),
+    };
+    let write_error = |e: std::io::Error| format!("failed to write {}: {}", output.unwrap_or("stdout"), e);
+    if !resumed {
+        let line = header(format);
+        writeln!(out, "{}", line).map_err(write_error)?;
+        output_len += line.len() as u64 + 1;
+    }
+    let mut lines = 0;
+    while let Some(transactions) = bulk.next_page(&mut cursor, TRANSACTION_FIELDS).await? {
+        for tx in &transactions {
+            let time = tx["now"].as_u64().unwrap_or(0) as u32;
+            if until.map(|until| time >= until).unwrap_or(false) {
+                continue;
+            }
+            for entry in classify(tx) {
+                let price = match prices.as_mut() {
+                    Some(source) => source.price(entry.time).await,
+                    None => None,
+                };
+                let line = format_entry(format, &entry, price, fiat);
+                writeln!(out, "{}", line).map_err(write_error)?;
+                output_len += line.len() as u64 + 1;
+                lines += 1;
+            }
+        }
+        out.flush().map_err(write_error)?;
+        if let Some(path) = resume {
+            save_checkpoint(path, &ExportCheckpoint { cursor: cursor.clone(), output_len })?;
+        }
+    }
+    if let Some(path) = resume {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("failed to remove checkpoint {}: {}", path, e))?;
+    }
+    if let Some(path) = output {
+        println!("Exported {} lines to {}", lines, path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let tx = json!({
+            "id": "aa", "now": 1600000000, "aborted": false, "total_fees": "5000",
+            "in_message": { "src": "", "value": null, "msg_type": 1 },
+            "out_messages": [
+                { "dst": "0:01", "value": "1000000000", "msg_type": 0 },
+                { "dst": "", "value": null, "msg_type": 2 },
+            ],
+        });
+        let entries = classify(&tx);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].direction, Direction::Out);
+        assert_eq!(entries[0].counterparty, "0:01");
+        assert_eq!(entries[0].fee, 5000);
+
+        let tx = json!({
+            "id": "bb", "now": 1600000000, "aborted": true, "total_fees": "300",
+            "in_message": { "src": "0:02", "value": "700", "msg_type": 0 },
+            "out_messages": [{ "dst": "0:02", "value": "400", "msg_type": 0 }],
+        });
+        let entries = classify(&tx);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].direction, Direction::In);
+        assert_eq!((entries[0].amount, entries[0].fee), (700, 0));
+        assert_eq!((entries[1].amount, entries[1].fee), (400, 300));
+
+        let tx = json!({ "id": "cc", "now": 1, "total_fees": "10", "in_message": { "msg_type": 1 } });
+        assert_eq!(classify(&tx)[0].direction, Direction::Fee);
+    }
+
+    #[test]
+    fn test_format_entry() {
+        let entry = Entry {
+            time: 1600000000,
+            tx: "aa".to_owned(),
+            direction: Direction::Out,
+            counterparty: "0:01".to_owned(),
+            amount: 2_000_000_000,
+            fee: 0,
+        };
+        assert_eq!(
+            format_entry(ExportFormat::Koinly, &entry, Some(0.5), "USD"),
+            "2020-09-13 12:26 UTC,2.000000000,TON,,,,,1.00,USD,,to 0:01,aa"
+        );
+        assert_eq!(
+            format_entry(ExportFormat::Csv, &entry, None, "USD"),
+            "2020-09-13T12:26:40+00:00,aa,out,0:01,2.000000000,,TON,,"
+        );
+        assert_eq!(parse_time("2020-09-13").unwrap(), 1599955200);
+    }
+}
@@ -0,0 +1,133 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::{interpolate_env, Config};
+use crate::convert::{amount_to_nano, convert_token, nano_to_tokens};
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_verbose, create_http_client, load_ton_address, send_http};
+use crate::wallet::account_balance;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_AMOUNT: &str = "10";
+const BALANCE_POLL_INTERVAL: u64 = 5;
+/// Faucets never work with production networks, requests there are refused.
+const MAINNET_HOSTS: [&str; 2] = ["main.ton.dev", "main.ton.network"];
+
+/// Faucet of the network endpoint. Request is HTTP POST of
+/// `{"address": "<addr>", "amount": "<nanotokens>"}` with
+/// `Authorization: Bearer <api_key>` header if the key is set.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FaucetSettings {
+    pub url: String,
+    /// May refer environment variable as `${VAR}`.
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+pub fn create_faucet_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("faucet")
+        .about("Test network faucet. Faucet of the current endpoint is set with config --faucet_url/--faucet_key.")
+        .subcommand(SubCommand::with_name("request")
+            .about("Requests test tokens to the address and waits until the balance is credited.")
+            .arg(Arg::with_name("ADDRESS")
+                .required(true)
+                .takes_value(true)
+                .help("Address to fund."))
+            .arg(Arg::with_name("AMOUNT")
+                .long("--amount")
+                .takes_value(true)
+                .help("Amount in tokens (default: 10)."))
+            .arg(Arg::with_name("NO_WAIT")
+                .long("--no-wait")
+                .help("Doesn't wait for the balance to be credited.")))
+}
+
+pub async fn faucet_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("request") {
+        return request_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown faucet command"))
+}
+
+fn is_mainnet(url: &str) -> bool {
+    MAINNET_HOSTS.iter().any(|host| url.contains(host))
+}
+
+async fn request_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    if is_mainnet(&config.url) {
+        return Err(format!("{} is a production network, faucets are available only in test networks", config.url));
+    }
+    let faucet = config.faucets.get(&config.url)
+        .ok_or(format!("no faucet configured for endpoint {} (see config --faucet_url)", config.url))?;
+    let address = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+    let amount = match m.value_of("AMOUNT") {
+        Some(amount) => amount_to_nano(amount)?,
+        None => u64::from_str_radix(&convert_token(DEFAULT_AMOUNT, false)?, 10)
+            .map_err(|e| format!("failed to parse amount: {}", e))?,
+    };
+
+    let ton = create_client_verbose(&config)?;
+    let before = account_balance(ton.clone(), &address).await?;
+
+    println!("Requesting {} from faucet {}...", nano_to_tokens(amount), faucet.url);
+    let client = create_http_client(&config)?;
+    let api_key = faucet.api_key.as_ref().map(|key| interpolate_env(key)).transpose()?;
+    let response = send_http(|| {
+        let request = client
+            .post(&faucet.url)
+            .json(&json!({ "address": address, "amount": amount.to_string() }));
+        match &api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }).await
+    .map_err(|e| format!("faucet request failed: {}", e))?;
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("faucet rejected request: {} {}", status, text.trim()));
+    }
+    if m.is_present("NO_WAIT") {
+        println!("Request accepted.");
+        return Ok(());
+    }
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(config.wait_for_timeout as u64);
+    loop {
+        let balance = account_balance(ton.clone(), &address).await?;
+        if balance > before {
+            println!("Credited {}, balance: {}", nano_to_tokens(balance - before), nano_to_tokens(balance));
+            return Ok(());
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(format!(
+                "balance was not credited in {} seconds, check it later with account command",
+                config.wait_for_timeout / 1000,
+            ));
+        }
+        println!("Waiting for tokens...");
+        tokio::time::delay_for(std::time::Duration::from_secs(BALANCE_POLL_INTERVAL)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mainnet() {
+        assert!(is_mainnet("https://main.ton.dev"));
+        assert!(!is_mainnet("net.ton.dev"));
+        assert!(!is_mainnet("http://localhost"));
+    }
+}
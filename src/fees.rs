@@ -0,0 +1,220 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Fee estimation from network config prices. Forward fees are taken from
+//! config params 24/25 (masterchain/basechain message prices), gas fees from
+//! params 20/21. All prices in config are in 2^-16 nanotokens per unit except
+//! lump and flat prices.
+use crate::config::Config;
+use crate::convert::{amount_to_nano, nano_to_tokens};
+use crate::error::{CliError, ErrorKind};
+use crate::getconfig::query_config_param;
+use crate::helpers::{create_client_verbose, parse_wc};
+use crate::output;
+use crate::pretty::parse_uint;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+
+pub fn create_fees_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("fees")
+        .about("Fee estimation commands.")
+        .subcommand(SubCommand::with_name("simulate")
+            .about("Computes forward fee of a message of the given size and gas fees using current network config prices. Helps to budget message sizes and attached values before implementation.")
+            .arg(Arg::with_name("BITS")
+                .long("--bits")
+                .takes_value(true)
+                .required(true)
+                .help("Number of bits in the message cells except the root cell."))
+            .arg(Arg::with_name("CELLS")
+                .long("--cells")
+                .takes_value(true)
+                .required(true)
+                .help("Number of message cells except the root cell."))
+            .arg(Arg::with_name("VALUE")
+                .long("--value")
+                .takes_value(true)
+                .help("Value attached to the message. Gas it buys on the receiver side and the delivered value are printed."))
+            .arg(Arg::with_name("GAS")
+                .long("--gas")
+                .takes_value(true)
+                .help("Gas consumed by the receiver, its fee is printed."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .help("Workchain of the message (default from config), -1 uses masterchain prices.")))
+}
+
+pub async fn fees_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("simulate") {
+        return simulate_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown fees command"))
+}
+
+/// Message forwarding prices (config params 24, 25).
+struct MsgPrices {
+    lump_price: u128,
+    bit_price: u128,
+    cell_price: u128,
+    first_frac: u128,
+}
+
+impl MsgPrices {
+    fn from_config(param: &Value) -> Self {
+        Self {
+            lump_price: parse_uint(&param["lump_price"]).unwrap_or(0),
+            bit_price: parse_uint(&param["bit_price"]).unwrap_or(0),
+            cell_price: parse_uint(&param["cell_price"]).unwrap_or(0),
+            first_frac: parse_uint(&param["first_frac"]).unwrap_or(0),
+        }
+    }
+
+    fn forward_fee(&self, bits: u128, cells: u128) -> u128 {
+        self.lump_price + ((self.bit_price * bits + self.cell_price * cells + 0xffff) >> 16)
+    }
+
+    /// Part of the forward fee collected by validators of the sender's shard,
+    /// the rest is collected on delivery.
+    fn action_fee(&self, forward_fee: u128) -> u128 {
+        (forward_fee * self.first_frac) >> 16
+    }
+}
+
+/// Gas prices (config params 20, 21).
+struct GasPrices {
+    gas_price: u128,
+    gas_limit: u128,
+    flat_gas_limit: u128,
+    flat_gas_price: u128,
+}
+
+impl GasPrices {
+    fn from_config(param: &Value) -> Self {
+        Self {
+            gas_price: parse_uint(&param["gas_price"]).unwrap_or(0),
+            gas_limit: parse_uint(&param["gas_limit"]).unwrap_or(0),
+            flat_gas_limit: parse_uint(&param["flat_gas_limit"]).unwrap_or(0),
+            flat_gas_price: parse_uint(&param["flat_gas_price"]).unwrap_or(0),
+        }
+    }
+
+    fn gas_fee(&self, gas: u128) -> u128 {
+        if gas <= self.flat_gas_limit {
+            return self.flat_gas_price;
+        }
+        self.flat_gas_price + (((gas - self.flat_gas_limit) * self.gas_price + 0xffff) >> 16)
+    }
+
+    /// Max gas the receiver can spend paying with the message value.
+    fn gas_for_value(&self, value: u128) -> u128 {
+        if value < self.flat_gas_price || self.gas_price == 0 {
+            return 0;
+        }
+        let gas = self.flat_gas_limit + ((value - self.flat_gas_price) << 16) / self.gas_price;
+        gas.min(self.gas_limit)
+    }
+}
+
+fn parse_count(value: &str, name: &str) -> Result<u128, String> {
+    u128::from_str_radix(value, 10)
+        .map_err(|e| format!("failed to parse {}: {}", name, e))
+}
+
+async fn simulate_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let bits = parse_count(m.value_of("BITS").unwrap(), "bits")?;
+    let cells = parse_count(m.value_of("CELLS").unwrap(), "cells")?;
+    let value = m.value_of("VALUE").map(amount_to_nano).transpose()?.map(|v| v as u128);
+    let gas = m.value_of("GAS").map(|g| parse_count(g, "gas")).transpose()?;
+    let masterchain = parse_wc(m.value_of("WC"), &config)? == -1;
+
+    let ton = create_client_verbose(&config)?;
+    let msg_prices = query_config_param(ton.clone(), &config, if masterchain { "p24" } else { "p25" }).await?;
+    let msg_prices = MsgPrices::from_config(&msg_prices);
+    let gas_prices = query_config_param(ton, &config, if masterchain { "p20" } else { "p21" }).await?;
+    let gas_prices = GasPrices::from_config(&gas_prices);
+
+    let forward_fee = msg_prices.forward_fee(bits, cells);
+    let action_fee = msg_prices.action_fee(forward_fee);
+    let mut result = json!({
+        "bits": bits.to_string(),
+        "cells": cells.to_string(),
+        "forward_fee": forward_fee.to_string(),
+        "action_fee": action_fee.to_string(),
+        "delivery_fee": (forward_fee - action_fee).to_string(),
+        "gas_price": (gas_prices.gas_price >> 16).to_string(),
+    });
+    if let Some(value) = value {
+        let delivered = value.saturating_sub(forward_fee);
+        result["value"] = json!(value.to_string());
+        result["delivered_value"] = json!(delivered.to_string());
+        result["gas_for_value"] = json!(gas_prices.gas_for_value(delivered).to_string());
+    }
+    if let Some(gas) = gas {
+        result["gas"] = json!(gas.to_string());
+        result["gas_fee"] = json!(gas_prices.gas_fee(gas).to_string());
+        if gas > gas_prices.gas_limit {
+            result["exceeds_gas_limit"] = json!(true);
+        }
+    }
+
+    let tokens = |v: u128| nano_to_tokens(v.min(u64::MAX as u128) as u64);
+    output::print_value(&result, || {
+        if config.is_json {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default());
+            return;
+        }
+        println!("Message:         {} bits, {} cells{}", bits, cells, if masterchain { " (masterchain)" } else { "" });
+        println!("Forward fee:     {}", tokens(forward_fee));
+        println!("  on sending:    {}", tokens(action_fee));
+        println!("  on delivery:   {}", tokens(forward_fee - action_fee));
+        if let Some(value) = value {
+            let delivered = value.saturating_sub(forward_fee);
+            println!("Value:           {}", tokens(value));
+            println!("Delivered value: {} (forward fee is paid from value unless send flag 1 is set)", tokens(delivered));
+            println!("Gas for value:   {}", gas_prices.gas_for_value(delivered));
+        }
+        if let Some(gas) = gas {
+            println!("Gas fee:         {} for {} gas", tokens(gas_prices.gas_fee(gas)), gas);
+            if gas > gas_prices.gas_limit {
+                println!("WARNING: gas exceeds the limit {} of a transaction.", gas_prices.gas_limit);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fees() {
+        let msg_prices = MsgPrices::from_config(&json!({
+            "lump_price": "1000000", "bit_price": "65536000", "cell_price": "6553600000",
+            "ihr_price_factor": 98304, "first_frac": 21845, "next_frac": 21845,
+        }));
+        let fee = msg_prices.forward_fee(1000, 2);
+        assert_eq!(fee, 2_200_000);
+        assert_eq!(msg_prices.action_fee(fee), 733_322);
+        assert_eq!(msg_prices.forward_fee(0, 0), 1_000_000);
+
+        let gas_prices = GasPrices::from_config(&json!({
+            "gas_price": "65536000", "gas_limit": "1000000",
+            "flat_gas_limit": "100", "flat_gas_price": "100000",
+        }));
+        assert_eq!(gas_prices.gas_fee(50), 100_000);
+        assert_eq!(gas_prices.gas_fee(10_000), 10_000_000);
+        assert_eq!(gas_prices.gas_for_value(10_100_000), 10_100);
+        assert_eq!(gas_prices.gas_for_value(1_000_000_000_000), 1_000_000);
+        assert_eq!(gas_prices.gas_for_value(1000), 0);
+    }
+}
@@ -0,0 +1,232 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! On-chain blobs: a file is split into chunks which are stored by repeated
+//! calls to a contract implementing the storage interface (`STORAGE_ABI`):
+//! `storeChunk(index, data)` for every chunk and `setFile(chunks, size, hash)`
+//! at the end. `fetch-file` reads them back with get-methods and checks sha256.
+use crate::call::{call_contract_with_result, run_local};
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_verbose, load_ton_address};
+use crate::i18n::tr;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use sha2::{Digest, Sha256};
+
+/// Chunk is passed as `bytes` parameter (a chain of cells, 127 bytes each),
+/// the default keeps external message well below the size limit.
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+const MAX_CHUNK_SIZE: usize = 16 * 1024;
+
+pub const STORAGE_ABI: &str = r#"{
+	"ABI version": 2,
+	"header": ["pubkey", "time", "expire"],
+	"functions": [
+		{
+			"name": "storeChunk",
+			"inputs": [
+				{"name":"index","type":"uint32"},
+				{"name":"data","type":"bytes"}
+			],
+			"outputs": [
+			]
+		},
+		{
+			"name": "setFile",
+			"inputs": [
+				{"name":"chunks","type":"uint32"},
+				{"name":"size","type":"uint64"},
+				{"name":"hash","type":"uint256"}
+			],
+			"outputs": [
+			]
+		},
+		{
+			"name": "getFile",
+			"inputs": [
+			],
+			"outputs": [
+				{"name":"chunks","type":"uint32"},
+				{"name":"size","type":"uint64"},
+				{"name":"hash","type":"uint256"}
+			]
+		},
+		{
+			"name": "getChunk",
+			"inputs": [
+				{"name":"index","type":"uint32"}
+			],
+			"outputs": [
+				{"name":"data","type":"bytes"}
+			]
+		}
+	],
+	"events": [
+	],
+	"data": [
+	]
+}"#;
+
+pub fn create_store_file_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("store-file")
+        .about("Stores file on chain: splits it into chunks and sends them to the storage contract (storeChunk/setFile interface).")
+        .arg(Arg::with_name("FILE")
+            .required(true)
+            .takes_value(true)
+            .help("File to store."))
+        .arg(Arg::with_name("ADDRESS")
+            .long("--to")
+            .required(true)
+            .takes_value(true)
+            .help("Address of the storage contract."))
+        .arg(Arg::with_name("SIGN")
+            .long("--sign")
+            .takes_value(true)
+            .help("Keypair of the storage contract owner (default: keys from config)."))
+        .arg(Arg::with_name("CHUNK_SIZE")
+            .long("--chunk-size")
+            .takes_value(true)
+            .help("Chunk size in bytes (default: 4096)."))
+        .arg(Arg::with_name("FROM_CHUNK")
+            .long("--from-chunk")
+            .takes_value(true)
+            .help("Resumes interrupted upload from the chunk with this index."))
+}
+
+pub fn create_fetch_file_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("fetch-file")
+        .about("Reassembles file stored on chain by store-file and checks its hash.")
+        .arg(Arg::with_name("ADDRESS")
+            .required(true)
+            .takes_value(true)
+            .help("Address of the storage contract."))
+        .arg(Arg::with_name("OUTPUT")
+            .long("--output")
+            .short("-o")
+            .required(true)
+            .takes_value(true)
+            .help("Path to the output file."))
+}
+
+/// Splits data into chunks of the given size.
+pub fn split_chunks(data: &[u8], chunk_size: usize) -> Vec<&[u8]> {
+    if data.is_empty() {
+        return vec![];
+    }
+    data.chunks(chunk_size).collect()
+}
+
+fn parse_u64(value: &serde_json::Value, name: &str) -> Result<u64, String> {
+    let text = value[name].as_str()
+        .ok_or(format!("storage contract returned no {}", name))?;
+    if text.starts_with("0x") {
+        u64::from_str_radix(&text[2..], 16)
+    } else {
+        u64::from_str_radix(text, 10)
+    }
+    .map_err(|e| format!("failed to parse {}: {}", name, e))
+}
+
+pub async fn store_file_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let path = m.value_of("FILE").unwrap();
+    let address = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+    let keys = m.value_of("SIGN")
+        .map(|s| s.to_owned())
+        .or(config.keys_path.clone())
+        .ok_or(CliError::new(ErrorKind::InvalidInput, "keypair is not defined. Supply it with --sign or in config file."))?;
+    let chunk_size = m.value_of("CHUNK_SIZE")
+        .map(|s| usize::from_str_radix(s, 10).map_err(|e| format!("failed to parse chunk size: {}", e)))
+        .transpose()?
+        .unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(CliError::new(ErrorKind::InvalidInput, format!("chunk size must be from 1 to {} bytes", MAX_CHUNK_SIZE)));
+    }
+    let from_chunk = m.value_of("FROM_CHUNK")
+        .map(|s| usize::from_str_radix(s, 10).map_err(|e| format!("failed to parse chunk index: {}", e)))
+        .transpose()?
+        .unwrap_or(0);
+
+    let data = std::fs::read(path)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read file {}: {}", path, e)))?;
+    let hash = hex::encode(Sha256::digest(&data));
+    let chunks = split_chunks(&data, chunk_size);
+    println!("File: {} bytes, {} chunks, sha256 {}", data.len(), chunks.len(), hash);
+
+    for (index, chunk) in chunks.iter().enumerate().skip(from_chunk) {
+        println!("Storing chunk {} of {}...", index + 1, chunks.len());
+        let params = json!({ "index": index, "data": hex::encode(chunk) }).to_string();
+        call_contract_with_result(
+            config.clone(), &address, STORAGE_ABI.to_owned(), "storeChunk", &params, Some(keys.clone()), false,
+        ).await
+        .map_err(|e| format!("failed to store chunk {} (resume with --from-chunk {}): {}", index, index, e))?;
+    }
+    let params = json!({
+        "chunks": chunks.len(),
+        "size": data.len(),
+        "hash": format!("0x{}", hash),
+    }).to_string();
+    call_contract_with_result(
+        config.clone(), &address, STORAGE_ABI.to_owned(), "setFile", &params, Some(keys), false,
+    ).await?;
+    println!("{}", tr("Succeeded."));
+    Ok(())
+}
+
+pub async fn fetch_file_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let address = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+    let output = m.value_of("OUTPUT").unwrap();
+    let ton = create_client_verbose(&config)?;
+
+    let info = run_local(ton.clone(), &address, STORAGE_ABI, "getFile", "{}", true).await?;
+    let chunks = parse_u64(&info, "chunks")?;
+    let size = parse_u64(&info, "size")?;
+    let hash = info["hash"].as_str().unwrap_or("").trim_start_matches("0x").to_owned();
+    println!("File: {} bytes, {} chunks", size, chunks);
+
+    let mut data = Vec::with_capacity(size as usize);
+    for index in 0..chunks {
+        println!("Fetching chunk {} of {}...", index + 1, chunks);
+        let result = run_local(
+            ton.clone(), &address, STORAGE_ABI, "getChunk", &json!({ "index": index }).to_string(), true,
+        ).await?;
+        let chunk = hex::decode(result["data"].as_str().unwrap_or(""))
+            .map_err(|e| format!("invalid chunk {}: {}", index, e))?;
+        data.extend_from_slice(&chunk);
+    }
+    if data.len() as u64 != size {
+        return Err(CliError::new(ErrorKind::Contract, format!("file size mismatch: expected {} bytes, got {}", size, data.len())));
+    }
+    let actual = hex::encode(Sha256::digest(&data));
+    if format!("{:0>64}", hash) != actual {
+        return Err(CliError::new(ErrorKind::Contract, format!("file hash mismatch: expected {}, got {}", hash, actual)));
+    }
+    std::fs::write(output, &data)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to write file {}: {}", output, e)))?;
+    println!("File saved to {} (sha256 {})", output, actual);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks() {
+        let data = vec![1u8; 10];
+        let chunks = split_chunks(&data, 4);
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![4, 4, 2]);
+        assert_eq!(chunks.concat(), data);
+        assert!(split_chunks(&[], 4).is_empty());
+        let abi: serde_json::Value = serde_json::from_str(STORAGE_ABI).unwrap();
+        assert_eq!(abi["functions"].as_array().unwrap().len(), 4);
+    }
+}
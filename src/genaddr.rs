@@ -11,11 +11,12 @@
  * limitations under the License.
  */
 use crate::config::Config;
-use crate::helpers::{create_client_local, read_keys, load_abi, calc_acc_address};
+use crate::i18n::{tr, trf};
+use crate::helpers::{create_client_local, read_keys, load_abi, calc_acc_address, parse_wc};
 use ed25519_dalek::PublicKey;
 use std::fs::OpenOptions;
 use ton_sdk;
-use crate::crypto::{gen_seed_phrase, generate_keypair_from_mnemonic};
+use crate::crypto::{gen_seed_phrase, generate_keypair_from_mnemonic, has_mnemonic_passphrase, write_secret_file};
 use ton_client::utils::{convert_address, ParamsOfConvertAddress, AddressStringFormat};
 
 pub async fn generate_address(
@@ -44,10 +45,7 @@ pub async fn generate_address(
         (Some(seed_phr), pair)
     };
     
-    let wc = wc_str.map(|wc| i32::from_str_radix(wc, 10))
-        .transpose()
-        .map_err(|e| format!("failed to parse workchain id: {}", e))?
-        .unwrap_or(conf.wc);
+    let wc = parse_wc(wc_str, &conf)?;
         
     let addr = calc_acc_address(
         &contract,
@@ -60,9 +58,12 @@ pub async fn generate_address(
     println!();
     if let Some(phr) = phrase {
         println!(r#"Seed phrase: "{}""#, phr);
+        if has_mnemonic_passphrase() {
+            println!("{}", tr("Keys are derived with BIP-39 passphrase, it's required to restore them along with the seed phrase."));
+        }
         println!();
     }
-    println!("Raw address: {}", addr);
+    println!("{}", trf("Raw address: {}", &[&addr]));
         
     if update_tvc {
         let initial_data = initial_data.map(|s| s.to_string());
@@ -72,18 +73,19 @@ pub async fn generate_address(
     
     if new_keys && keys_file.is_some() {
         let keys_json = serde_json::to_string_pretty(&keys).unwrap();
-        std::fs::write(keys_file.unwrap(), &keys_json).unwrap();
+        write_secret_file(keys_file.unwrap(), keys_json.as_bytes())
+            .map_err(|e| format!("failed to create file with keys: {}", e))?;
     }
     
     
     println!("testnet:");
-    println!("Non-bounceable address (for init): {}", calc_userfriendly_address(&addr, false, true)?);
-    println!("Bounceable address (for later access): {}", calc_userfriendly_address(&addr, true, true)?);
+    println!("{}", trf("Non-bounceable address (for init): {}", &[&calc_userfriendly_address(&addr, false, true)?]));
+    println!("{}", trf("Bounceable address (for later access): {}", &[&calc_userfriendly_address(&addr, true, true)?]));
     println!("mainnet:");
-    println!("Non-bounceable address (for init): {}", calc_userfriendly_address(&addr, false, false)?);
-    println!("Bounceable address (for later access): {}", calc_userfriendly_address(&addr, true, false)?);
+    println!("{}", trf("Non-bounceable address (for init): {}", &[&calc_userfriendly_address(&addr, false, false)?]));
+    println!("{}", trf("Bounceable address (for later access): {}", &[&calc_userfriendly_address(&addr, true, false)?]));
 
-    println!("Succeeded");
+    println!("{}", tr("Succeeded"));
     Ok(())
 }
 
@@ -121,7 +123,7 @@ fn update_contract_state(tvc_file: &str, pubkey: &[u8], data: Option<String>, ab
 
     state_init.seek(std::io::SeekFrom::Start(0)).unwrap();
     state_init.write_all(&vec_bytes).unwrap();
-    println!("TVC file updated");
+    println!("{}", tr("TVC file updated"));
 
     Ok(())
 }
\ No newline at end of file
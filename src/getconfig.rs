@@ -10,10 +10,12 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
-use crate::helpers::{create_client_verbose, query};
+use crate::helpers::{create_client_verbose, query, subscribe, TonClient};
 use crate::config::Config;
-use serde_json::json;
-use ton_client::net::{OrderBy, SortDirection};
+use crate::output;
+use crate::cache::query_immutable;
+use serde_json::{json, Value};
+use ton_client::net::{unsubscribe, OrderBy, SortDirection};
 
 const QUERY_FIELDS: &str = r#"
 master { 
@@ -77,6 +79,13 @@ master {
         min_total_stake(format:DEC)
         max_stake_factor
       }
+      p18 {
+        utime_since
+        bit_price_ps
+        cell_price_ps
+        mc_bit_price_ps
+        mc_cell_price_ps
+      }
       p20 {
         gas_price
         gas_limit
@@ -226,12 +235,22 @@ master {
 
 pub async fn query_global_config(conf: Config, index: &str) -> Result<(), String> {
     let ton = create_client_verbose(&conf)?;
-
     let _i = i32::from_str_radix(index, 10)
         .map_err(|e| format!(r#"failed to parse "index": {}"#, e))?;
-    
     let config_name = format!("p{}", index);
+    let config = query_config_param(ton, &conf, &config_name).await?;
+    let config_str = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("failed to parse config body from sdk: {}", e))?;
+    output::print_value(&config, || println!("Config {}: {}", config_name, config_str))
+}
 
+/// Reads config parameter (e.g. "p12") from the last key block.
+pub async fn query_config_param(ton: TonClient, conf: &Config, config_name: &str) -> Result<serde_json::Value, String> {
+    Ok(query_config(ton, conf).await?[config_name].clone())
+}
+
+/// Reads all config parameters from the last key block.
+async fn query_config(ton: TonClient, conf: &Config) -> Result<serde_json::Value, String> {
     let last_key_block_query = query(
         ton.clone(),
         "blocks",
@@ -239,13 +258,13 @@ pub async fn query_global_config(conf: Config, index: &str) -> Result<(), String
         "id prev_key_block_seqno",
         Some(vec![OrderBy{ path: "seq_no".to_owned(), direction: SortDirection::DESC }]),
     ).await.map_err(|e| format!("failed to query last key block: {}", e))?;
-
     if last_key_block_query.len() == 0 {
         Err("Key block not found".to_string())?;
     }
-
-    let config_query = query(
+    // key block with the given seq_no never changes, so its config can be cached
+    let config_query = query_immutable(
         ton.clone(),
+        conf,
         "blocks",
         json!({
             "seq_no": {
@@ -256,16 +275,144 @@ pub async fn query_global_config(conf: Config, index: &str) -> Result<(), String
             }
         }),
         QUERY_FIELDS,
-        None,
     ).await.map_err(|e| format!("failed to query master block config: {}", e))?;
-
     if config_query.len() == 0 {
         Err("Config was not set".to_string())?;
     }
+    Ok(config_query[0]["master"]["config"].clone())
+}
 
-    let config = &config_query[0]["master"]["config"][&config_name];
-    let config_str = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("failed to parse config body from sdk: {}", e))?;
-    println!("Config {}: {}", config_name, config_str);
+/// Change of a config parameter field, `path` is like `p20.gas_price`.
+#[derive(Debug, PartialEq)]
+struct ConfigChange {
+    path: String,
+    old: Value,
+    new: Value,
+}
+
+/// Collects changed leaf values of two config (sub)trees.
+fn diff_config(path: &str, old: &Value, new: &Value, changes: &mut Vec<ConfigChange>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                diff_config(&child, &old[key.as_str()], &new[key.as_str()], changes);
+            }
+        },
+        (Value::Array(old_list), Value::Array(new_list)) if old_list.len() == new_list.len() => {
+            for (i, (old, new)) in old_list.iter().zip(new_list).enumerate() {
+                diff_config(&format!("{}[{}]", path, i), old, new, changes);
+            }
+        },
+        _ if old != new => changes.push(ConfigChange { path: path.to_owned(), old: old.clone(), new: new.clone() }),
+        _ => {},
+    }
+}
+
+fn select_param(config: &Value, param: Option<&str>) -> Value {
+    match param {
+        Some(name) => json!({ name: config[name].clone() }),
+        None => config.clone(),
+    }
+}
+
+/// Subscribes to key blocks and prints config parameters changed by them.
+pub async fn watch_config(conf: Config, index: Option<&str>) -> Result<(), String> {
+    let param = index
+        .map(|index| i32::from_str_radix(index, 10)
+            .map(|i| format!("p{}", i))
+            .map_err(|e| format!(r#"failed to parse "index": {}"#, e)))
+        .transpose()?;
+    let param = param.as_deref();
+    let ton = create_client_verbose(&conf)?;
+
+    // Subscription is started before the current config is read so that no key block is lost in between.
+    let (subscription, mut receiver) = subscribe(
+        ton.clone(),
+        "blocks",
+        json!({
+            "workchain_id": { "eq": -1 },
+            "key_block": { "eq": true },
+        }),
+        &format!("seq_no gen_utime {}", QUERY_FIELDS),
+    ).await
+    .map_err(|e| format!("failed to subscribe to key blocks: {}", e))?;
+    let mut current = select_param(&query_config(ton.clone(), &conf).await?, param);
+
+    if !conf.is_json {
+        println!("Watching {} for changes...", param.unwrap_or("config parameters"));
+    }
+    while let Some(block) = receiver.recv().await {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => {
+                eprintln!("Subscription error: {}", e);
+                continue;
+            },
+        };
+        let config = select_param(&block["master"]["config"], param);
+        let mut changes = vec![];
+        diff_config("", &current, &config, &mut changes);
+        current = config;
+        if changes.is_empty() {
+            continue;
+        }
+        let seq_no = block["seq_no"].as_u64().unwrap_or(0);
+        let gen_utime = block["gen_utime"].as_u64().unwrap_or(0);
+        if conf.is_json {
+            let changes: Vec<Value> = changes.iter()
+                .map(|c| json!({ "path": c.path, "old": c.old, "new": c.new }))
+                .collect();
+            println!("{}", json!({ "seq_no": seq_no, "gen_utime": gen_utime, "changes": changes }));
+            continue;
+        }
+        println!("Key block {} (gen_utime {}):", seq_no, gen_utime);
+        for change in &changes {
+            println!("  {}: {} -> {}", change.path, change.old, change.new);
+        }
+    }
+    let _ = unsubscribe(ton, subscription).await;
+    Ok(())
+}
+
+/// Checks that workchain exists and accepts messages according to config param 12.
+pub async fn check_workchain(ton: TonClient, conf: &Config, wc: i32) -> Result<(), String> {
+    if wc == -1 {
+        return Ok(());
+    }
+    let workchains = query_config_param(ton, conf, "p12").await?;
+    let workchain = workchains.as_array()
+        .and_then(|list| list.iter().find(|w| w["workchain_id"].as_i64() == Some(wc as i64)))
+        .ok_or(format!("workchain {} doesn't exist", wc))?;
+    if workchain["active"].as_bool() == Some(false) {
+        return Err(format!("workchain {} is not active", wc));
+    }
+    if workchain["accept_msgs"].as_bool() == Some(false) {
+        return Err(format!("workchain {} doesn't accept messages", wc));
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_config() {
+        let old = json!({ "p20": { "gas_price": 1000, "gas_limit": 100 }, "p34": { "list": [{ "weight": "1" }] } });
+        let new = json!({ "p20": { "gas_price": 2000, "gas_limit": 100 }, "p34": { "list": [{ "weight": "2" }] }, "p31": [] });
+        let mut changes = vec![];
+        diff_config("", &old, &new, &mut changes);
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["p20.gas_price", "p31", "p34.list[0].weight"]);
+        assert_eq!(changes[0].old, json!(1000));
+        assert_eq!(changes[0].new, json!(2000));
+
+        let mut changes = vec![];
+        diff_config("", &select_param(&old, Some("p20")), &select_param(&new, Some("p20")), &mut changes);
+        assert_eq!(changes.len(), 1);
+    }
+}
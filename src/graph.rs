@@ -0,0 +1,290 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind, ResultExt};
+use crate::helpers::{create_client_verbose, load_abi, load_ton_address, TonClient};
+use crate::wallet::query_accounts;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use ton_client::abi::{decode_account_data, Abi, ParamsOfDecodeAccountData};
+
+const DEFAULT_DEPTH: u32 = 2;
+
+pub fn create_graph_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("graph")
+        .about("Renders graph of contracts which refer to each other by addresses stored in their data.")
+        .arg(Arg::with_name("ADDRESS")
+            .required(true)
+            .takes_value(true)
+            .help("Root contract address."))
+        .arg(Arg::with_name("ABI")
+            .long("--abi")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Contract ABI used to decode account data (can be repeated). Every account is decoded with the first ABI that fits. Default: ABI from config."))
+        .arg(Arg::with_name("DEPTH")
+            .long("--depth")
+            .takes_value(true)
+            .help("Max number of hops from the root contract (default: 2)."))
+        .arg(Arg::with_name("FORMAT")
+            .long("--format")
+            .takes_value(true)
+            .possible_values(&["dot", "mermaid"])
+            .help("Output format (default: dot)."))
+}
+
+pub async fn graph_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let root = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+    let abi_paths: Vec<String> = match m.values_of("ABI") {
+        Some(values) => values.map(|s| s.to_owned()).collect(),
+        None => config.abi_path.clone()
+            .map(|path| vec![path])
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "ABI file is not defined. Supply it with --abi or in the config file."))?,
+    };
+    let depth = m.value_of("DEPTH")
+        .map(|s| u32::from_str_radix(s, 10).map_err(|e| format!("failed to parse depth: {}", e)))
+        .transpose()?
+        .unwrap_or(DEFAULT_DEPTH);
+    let mut abis = vec![];
+    for path in &abi_paths {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read ABI file {}: {}", path, e)))?;
+        abis.push((abi_name(path), load_abi(&text)?));
+    }
+
+    let ton = create_client_verbose(&config)?;
+    let graph = build_graph(ton, &abis, &root, depth).await.kind(ErrorKind::Network)?;
+    match m.value_of("FORMAT").unwrap_or("dot") {
+        "mermaid" => println!("{}", graph.to_mermaid()),
+        _ => println!("{}", graph.to_dot()),
+    }
+    Ok(())
+}
+
+/// Short name of the contract for node labels: `TokenRoot.abi.json` -> `TokenRoot`.
+fn abi_name(path: &str) -> String {
+    let file = std::path::Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path);
+    file.trim_end_matches(".json").trim_end_matches(".abi").to_owned()
+}
+
+#[derive(Default)]
+pub struct Graph {
+    /// Address -> label.
+    nodes: BTreeMap<String, String>,
+    /// (from, to, data field).
+    edges: BTreeSet<(String, String, String)>,
+}
+
+impl Graph {
+    fn short(address: &str) -> String {
+        match address.find(':') {
+            Some(pos) if address.len() > pos + 9 => {
+                format!("{}{}..{}", &address[..pos + 1], &address[pos + 1..pos + 5], &address[address.len() - 4..])
+            },
+            _ => address.to_owned(),
+        }
+    }
+
+    fn node_id(&self, address: &str) -> String {
+        let index = self.nodes.keys().position(|a| a == address).unwrap_or(0);
+        format!("n{}", index)
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = "digraph contracts {\n    node [shape=box];\n".to_owned();
+        for (address, label) in &self.nodes {
+            out.push_str(&format!(
+                "    {} [label=\"{}\\n{}\"];\n", self.node_id(address), label, Self::short(address)
+            ));
+        }
+        for (from, to, field) in &self.edges {
+            out.push_str(&format!(
+                "    {} -> {} [label=\"{}\"];\n", self.node_id(from), self.node_id(to), field
+            ));
+        }
+        out.push('}');
+        out
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut out = "graph TD\n".to_owned();
+        for (address, label) in &self.nodes {
+            out.push_str(&format!(
+                "    {}[\"{}<br/>{}\"]\n", self.node_id(address), label, Self::short(address)
+            ));
+        }
+        for (from, to, field) in &self.edges {
+            out.push_str(&format!(
+                "    {} -->|{}| {}\n", self.node_id(from), field, self.node_id(to)
+            ));
+        }
+        out.trim_end().to_owned()
+    }
+}
+
+fn is_address(value: &str) -> bool {
+    let mut parts = value.splitn(2, ':');
+    let wc = parts.next().unwrap_or("");
+    let hex = parts.next().unwrap_or("");
+    i32::from_str_radix(wc, 10).is_ok()
+        && hex.len() == 64
+        && hex.chars().all(|c| c.is_ascii_hexdigit())
+        && hex.chars().any(|c| c != '0')
+}
+
+/// Collects addresses stored in decoded data as (field path, address).
+/// Map keys are checked too, e.g. `mapping(address => uint128) wallets`.
+pub fn collect_addresses(path: &str, value: &Value, out: &mut Vec<(String, String)>) {
+    match value {
+        Value::String(s) if is_address(s) => out.push((path.to_owned(), s.to_owned())),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_addresses(&format!("{}[{}]", path, i), item, out);
+            }
+        },
+        Value::Object(fields) => {
+            for (key, item) in fields {
+                let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                if is_address(key) {
+                    out.push((path.to_owned(), key.clone()));
+                }
+                collect_addresses(&field, item, out);
+            }
+        },
+        _ => {},
+    }
+}
+
+async fn decode_data(ton: TonClient, abis: &[(String, Abi)], data: &str) -> Option<(String, Value)> {
+    for (name, abi) in abis {
+        let result = decode_account_data(
+            ton.clone(),
+            ParamsOfDecodeAccountData {
+                abi: abi.clone(),
+                data: data.to_owned(),
+            },
+        ).await;
+        if let Ok(result) = result {
+            return Some((name.clone(), result.data));
+        }
+    }
+    None
+}
+
+/// Walks contracts breadth-first starting from the root.
+async fn build_graph(ton: TonClient, abis: &[(String, Abi)], root: &str, depth: u32) -> Result<Graph, String> {
+    let mut graph = Graph::default();
+    let mut level = vec![root.to_owned()];
+    let mut visited = BTreeSet::new();
+    visited.insert(root.to_owned());
+    for hop in 0..=depth {
+        if level.is_empty() {
+            break;
+        }
+        let accounts = query_accounts(ton.clone(), &level, "acc_type_name data").await?;
+        let mut found = BTreeMap::new();
+        for acc in &accounts {
+            if let Some(id) = acc["id"].as_str() {
+                found.insert(id.to_owned(), acc);
+            }
+        }
+        let mut next = vec![];
+        for address in &level {
+            let acc = match found.get(address) {
+                Some(acc) => acc,
+                None => {
+                    graph.nodes.insert(address.clone(), "not found".to_owned());
+                    continue;
+                },
+            };
+            let decoded = match acc["data"].as_str() {
+                Some(data) => decode_data(ton.clone(), abis, data).await,
+                None => None,
+            };
+            let (label, data) = match decoded {
+                Some(decoded) => decoded,
+                None => {
+                    let state = acc["acc_type_name"].as_str().unwrap_or("unknown");
+                    graph.nodes.insert(address.clone(), format!("{} (unknown ABI)", state));
+                    continue;
+                },
+            };
+            graph.nodes.insert(address.clone(), label);
+            if hop == depth {
+                continue;
+            }
+            let mut refs = vec![];
+            collect_addresses("", &data, &mut refs);
+            for (field, target) in refs {
+                if target == *address {
+                    continue;
+                }
+                graph.edges.insert((address.clone(), target.clone(), field));
+                if visited.insert(target.clone()) {
+                    next.push(target);
+                }
+            }
+        }
+        level = next;
+    }
+    // referenced accounts beyond the depth limit are shown without expanding
+    for (_, target, _) in &graph.edges {
+        if !graph.nodes.contains_key(target) {
+            graph.nodes.insert(target.clone(), "...".to_owned());
+        }
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "0:1111111111111111111111111111111111111111111111111111111111111111";
+    const WALLET: &str = "0:2222222222222222222222222222222222222222222222222222222222222222";
+
+    #[test]
+    fn test_collect_addresses() {
+        let data = json!({
+            "root": ROOT,
+            "owner": "0:0000000000000000000000000000000000000000000000000000000000000000",
+            "balance": "100",
+            "wallets": { WALLET: "5" },
+            "pairs": [{ "token": ROOT }],
+        });
+        let mut refs = vec![];
+        collect_addresses("", &data, &mut refs);
+        assert_eq!(refs, vec![
+            ("pairs[0].token".to_owned(), ROOT.to_owned()),
+            ("root".to_owned(), ROOT.to_owned()),
+            ("wallets".to_owned(), WALLET.to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut graph = Graph::default();
+        graph.nodes.insert(ROOT.to_owned(), "TokenRoot".to_owned());
+        graph.nodes.insert(WALLET.to_owned(), "TokenWallet".to_owned());
+        graph.edges.insert((WALLET.to_owned(), ROOT.to_owned(), "root".to_owned()));
+        let dot = graph.to_dot();
+        assert!(dot.contains("n0 [label=\"TokenRoot\\n0:1111..1111\"];"));
+        assert!(dot.contains("n1 -> n0 [label=\"root\"];"));
+        assert!(graph.to_mermaid().ends_with("n1 -->|root| n0"));
+    }
+}
@@ -10,17 +10,24 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
-use crate::config::Config;
+use crate::config::{Config, NetworkPolicy};
+use crate::error::{CliError, ErrorKind};
+use crate::i18n::trf;
 use log;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use ton_client::abi::{
-    Abi, AbiConfig, AbiContract, DecodedMessageBody, DeploySet, ParamsOfDecodeMessageBody,
+    Abi, AbiConfig, AbiContract, DecodedMessageBody, DeploySet, FunctionHeader, ParamsOfDecodeMessageBody,
     ParamsOfEncodeMessage, Signer,
 };
 use ton_client::crypto::{CryptoConfig, KeyPair};
 use ton_client::error::ClientError;
-use ton_client::net::{query_collection, OrderBy, ParamsOfQueryCollection};
+use ton_client::tvm::TransactionFees;
+use ton_client::net::{
+    query_collection, subscribe_collection, OrderBy, ParamsOfQueryCollection, ParamsOfSubscribeCollection,
+    ResultOfSubscribeCollection, SortDirection,
+};
 use ton_client::{ClientConfig, ClientContext};
 
 
@@ -51,14 +58,16 @@ impl log::Log for SimpleLogger {
     fn flush(&self) {}
 }
 
-pub fn read_keys(filename: &str) -> Result<KeyPair, String> {
-    let keys_str = std::fs::read_to_string(filename)
-        .map_err(|e| format!("failed to read keypair file: {}", e.to_string()))?;
-    let keys: KeyPair = serde_json::from_str(&keys_str).unwrap();
-    Ok(keys)
+pub fn read_keys(filename: &str) -> Result<KeyPair, CliError> {
+    let keys_data = std::fs::read(filename)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read keypair file: {}", e.to_string())))?;
+    let format = crate::keys::KeyFormat::detect(&keys_data)
+        .map_err(|e| CliError::new(ErrorKind::InvalidInput, format!("failed to load keypair: {}", e)))?;
+    crate::keys::parse_keys(&keys_data, format)
+        .map_err(|e| CliError::new(ErrorKind::InvalidInput, format!("failed to load keypair: {}", e)))
 }
 
-pub fn load_ton_address(addr: &str, conf: &Config) -> Result<String, String> {
+pub fn load_ton_address(addr: &str, conf: &Config) -> Result<String, CliError> {
     use std::str::FromStr;
     let addr = if addr.find(':').is_none() {
         format!("{}:{}", conf.wc, addr)
@@ -66,10 +75,25 @@ pub fn load_ton_address(addr: &str, conf: &Config) -> Result<String, String> {
         addr.to_owned()
     };
     let _ = ton_block::MsgAddressInt::from_str(&addr)
-        .map_err(|e| format!("Address is specified in the wrong format. Error description: {}", e))?;
+        .map_err(|e| CliError::new(
+            ErrorKind::InvalidInput,
+            format!("Address is specified in the wrong format. Error description: {}", e),
+        ))?;
     Ok(addr)
 }
 
+/// Parses workchain id supplied by user or takes the default one from config.
+pub fn parse_wc(wc: Option<&str>, conf: &Config) -> Result<i32, CliError> {
+    let wc = wc.map(|wc| i32::from_str_radix(wc, 10))
+        .transpose()
+        .map_err(|e| CliError::new(ErrorKind::InvalidInput, format!("failed to parse workchain id: {}", e)))?
+        .unwrap_or(conf.wc);
+    if wc < -1 || wc > 255 {
+        return Err(CliError::new(ErrorKind::InvalidInput, format!("invalid workchain id: {}", wc)));
+    }
+    Ok(wc)
+}
+
 pub fn now() -> u32 {
     SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -79,13 +103,58 @@ pub fn now() -> u32 {
 
 pub type TonClient = Arc<ClientContext>;
 
-pub fn create_client_local() -> Result<TonClient, String> {
+pub fn create_client_local() -> Result<TonClient, CliError> {
     let cli = ClientContext::new(ClientConfig::default())
-        .map_err(|e| format!("failed to create tonclient: {}", e))?;
+        .map_err(|e| CliError::new(ErrorKind::Internal, format!("failed to create tonclient: {}", e)))?;
     Ok(Arc::new(cli))
 }
 
-pub fn create_client(conf: &Config) -> Result<TonClient, String> {
+pub fn check_online(conf: &Config) -> Result<(), CliError> {
+    if conf.offline {
+        return Err(CliError::new(
+            ErrorKind::InvalidInput,
+            "this operation requires network access which is disabled by --offline flag",
+        ));
+    }
+    Ok(())
+}
+
+/// Http client for requests made outside of the client library (price feeds, faucets, relays).
+pub fn create_http_client(conf: &Config) -> Result<reqwest::Client, CliError> {
+    check_online(conf)?;
+    Ok(reqwest::Client::new())
+}
+
+/// Returns access key for the client library from configured endpoint headers.
+/// The library sends only the access key with GraphQL requests, so `Authorization`
+/// and `X-API-Key` are the only headers which can be configured for an endpoint.
+fn endpoint_access_key(conf: &Config) -> Result<Option<String>, CliError> {
+    let headers = conf.headers()?;
+    let mut access_key = None;
+    for (name, value) in &headers {
+        if name.eq_ignore_ascii_case("authorization") {
+            let value = value.trim();
+            let key = value.strip_prefix("Bearer ")
+                .or(value.strip_prefix("Basic "))
+                .unwrap_or(value);
+            access_key = Some(key.trim().to_owned());
+        } else if name.eq_ignore_ascii_case("x-api-key") {
+            if access_key.is_none() {
+                access_key = Some(value.clone());
+            }
+        } else {
+            return Err(CliError::new(ErrorKind::Config, format!(
+                r#"header "{}" of endpoint {} is not supported, only Authorization and X-API-Key headers can be sent to the endpoint"#,
+                name, conf.url
+            )));
+        }
+    }
+    Ok(access_key)
+}
+
+pub fn create_client(conf: &Config) -> Result<TonClient, CliError> {
+    check_online(conf)?;
+    let access_key = endpoint_access_key(conf)?;
     let cli_conf = ClientConfig {
         abi: AbiConfig {
             workchain: conf.wc,
@@ -98,24 +167,27 @@ pub fn create_client(conf: &Config) -> Result<TonClient, String> {
             hdkey_derivation_path: HD_PATH.to_string(),
         },
         network: ton_client::net::NetworkConfig {
-            server_address: Some(conf.url.to_owned()),
-            network_retries_count: 3,
-            message_retries_count: conf.retries as i8,
-            message_processing_timeout: 30000,
-            wait_for_timeout: 30000,
+            server_address: Some(conf.url.clone()),
+            // with --retry requests are retried by `with_policy`, retries of the library would multiply them
+            network_retries_count: if conf.network_policy.retries.is_some() { 0 } else { 3 },
+            message_retries_count: conf.network_policy.retries.unwrap_or(conf.retries) as i8,
+            message_processing_timeout: conf.network_policy.timeout.unwrap_or(conf.processing_timeout),
+            wait_for_timeout: conf.network_policy.timeout.unwrap_or(conf.wait_for_timeout),
             out_of_sync_threshold: (conf.timeout / 2),
             max_reconnect_timeout: 1000,
+            access_key,
             ..Default::default()
         },
         boc: Default::default(),
     };
-    let cli =
-        ClientContext::new(cli_conf).map_err(|e| format!("failed to create tonclient: {}", e))?;
+    let cli = ClientContext::new(cli_conf)
+        .map_err(|e| CliError::new(ErrorKind::Config, format!("failed to create tonclient: {}", e)))?;
     Ok(Arc::new(cli))
 }
 
-pub fn create_client_verbose(conf: &Config) -> Result<TonClient, String> {
-    println!("Connecting to {}", conf.url);
+pub fn create_client_verbose(conf: &Config) -> Result<TonClient, CliError> {
+    check_online(conf)?;
+    println!("{}", trf("Connecting to {}", &[&conf.url]));
 
     let level = if std::env::var("RUST_LOG")
         .unwrap_or_default()
@@ -126,12 +198,123 @@ pub fn create_client_verbose(conf: &Config) -> Result<TonClient, String> {
         MAX_LEVEL
     };
     log::set_max_level(level);
-    log::set_boxed_logger(Box::new(SimpleLogger))
-        .map_err(|e| format!("failed to init logger: {}", e))?;
+    // logger may be already set if command creates several clients
+    let _ = log::set_boxed_logger(Box::new(SimpleLogger));
 
     create_client(conf)
 }
 
+lazy_static! {
+    static ref NETWORK_POLICY: std::sync::Mutex<NetworkPolicy> = std::sync::Mutex::new(NetworkPolicy::default());
+}
+
+/// Sets retry and timeout policy for network calls which don't receive config.
+pub fn set_network_policy(policy: NetworkPolicy) {
+    *NETWORK_POLICY.lock().unwrap() = policy;
+}
+
+pub fn network_policy() -> NetworkPolicy {
+    *NETWORK_POLICY.lock().unwrap()
+}
+
+/// Runs the network request according to the policy: each attempt is limited
+/// by the policy timeout, timed out and retryable failures are retried with
+/// exponential backoff.
+pub async fn with_policy<T, F, Fut>(policy: NetworkPolicy, request: F) -> Result<T, ClientError>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, ClientError>>,
+{
+    with_policy_if(policy, is_retryable, request).await
+}
+
+/// Same as `with_policy`, but only failures accepted by `retryable` are retried.
+/// Timed out attempts fail with error code 0.
+pub async fn with_policy_if<T, F, Fut, R>(policy: NetworkPolicy, retryable: R, request: F) -> Result<T, ClientError>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, ClientError>>,
+    R: Fn(&ClientError) -> bool,
+{
+    let retries = policy.retries.unwrap_or(0) as u32;
+    let mut attempt = 0;
+    loop {
+        let res = match policy.timeout {
+            Some(timeout) => tokio::time::timeout(Duration::from_millis(timeout as u64), request()).await
+                .unwrap_or_else(|_| Err(ClientError::with_code_message(
+                    0, format!("request timeout: no response in {} ms", timeout),
+                ))),
+            None => request().await,
+        };
+        match res {
+            Err(e) if attempt < retries && retryable(&e) => {
+                attempt += 1;
+                let delay = Duration::from_secs(1 << std::cmp::min(attempt, 5));
+                log::warn!("request failed: {}. Retrying in {} sec ({} of {})...", e, delay.as_secs(), attempt, retries);
+                tokio::time::delay_for(delay).await;
+            },
+            res => return res,
+        }
+    }
+}
+
+/// Error codes of http requests sent with `send_http`.
+const HTTP_RETRYABLE: u32 = 1;
+const HTTP_FAILED: u32 = 2;
+
+/// Sends http request according to the network policy: timeouts, 429 and 5xx
+/// responses are retried. The request is built anew for every attempt.
+pub async fn send_http<F>(request: F) -> Result<reqwest::Response, CliError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let request = &request;
+    // policy timeouts have code 0 and are retried as well
+    with_policy_if(network_policy(), |e| e.code != HTTP_FAILED, || async move {
+        let response = request().send().await
+            .map_err(|e| ClientError::with_code_message(
+                if e.is_timeout() { HTTP_RETRYABLE } else { HTTP_FAILED },
+                e.to_string(),
+            ))?;
+        let status = response.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ClientError::with_code_message(HTTP_RETRYABLE, format!("server responded with {}", status)));
+        }
+        Ok(response)
+    })
+    .await
+    .map_err(|e| CliError::new(ErrorKind::Network, e.message))
+}
+
+/// Subscribes to the collection according to the network policy. Returns the subscription
+/// handle and the channel which receives the records and subscription errors.
+pub async fn subscribe(
+    ton: TonClient,
+    collection: &str,
+    filter: serde_json::Value,
+    result: &str,
+) -> Result<(ResultOfSubscribeCollection, tokio::sync::mpsc::UnboundedReceiver<Result<serde_json::Value, ClientError>>), ClientError> {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let handle = with_policy(network_policy(), || {
+        let sender = sender.clone();
+        subscribe_collection(
+            ton.clone(),
+            ParamsOfSubscribeCollection {
+                collection: collection.to_owned(),
+                filter: Some(filter.clone()),
+                result: result.to_owned(),
+            },
+            move |event| {
+                let sender = sender.clone();
+                async move {
+                    let _ = sender.send(event.map(|e| e.result));
+                }
+            },
+        )
+    }).await?;
+    Ok((handle, receiver))
+}
+
 pub async fn query(
     ton: TonClient,
     collection: &str,
@@ -139,20 +322,403 @@ pub async fn query(
     result: &str,
     order: Option<Vec<OrderBy>>,
 ) -> Result<Vec<serde_json::Value>, ClientError> {
-    query_collection(
-        ton,
+    with_policy(network_policy(), || query_collection(
+        ton.clone(),
         ParamsOfQueryCollection {
             collection: collection.to_owned(),
-            filter: Some(filter),
+            filter: Some(filter.clone()),
             result: result.to_owned(),
-            order,
+            order: order.clone(),
             ..Default::default()
         },
-    )
+    ))
     .await
     .map(|r| r.result)
 }
 
+/// Allowed difference in seconds between local clock and the time of the last masterchain block.
+const CLOCK_SKEW_THRESHOLD: i64 = 15;
+
+lazy_static! {
+    static ref CLOCK_SKEW: std::sync::Mutex<Option<i64>> = std::sync::Mutex::new(None);
+}
+
+/// Compares local time with the time of the last masterchain block once per process.
+/// Returns clock skew (network time minus local time) in seconds if it exceeds
+/// the threshold, otherwise 0.
+pub async fn check_clock_skew(ton: TonClient) -> i64 {
+    if let Some(skew) = *CLOCK_SKEW.lock().unwrap() {
+        return skew;
+    }
+    let block_time = query_collection(
+        ton,
+        ParamsOfQueryCollection {
+            collection: "blocks".to_owned(),
+            filter: Some(json!({ "workchain_id": { "eq": -1 } })),
+            result: "gen_utime".to_owned(),
+            order: Some(vec![OrderBy { path: "seq_no".to_owned(), direction: SortDirection::DESC }]),
+            limit: Some(1),
+        },
+    ).await
+    .ok()
+    .and_then(|r| r.result.get(0).and_then(|b| b["gen_utime"].as_i64()));
+    let skew = match block_time {
+        Some(block_time) if (block_time - now() as i64).abs() > CLOCK_SKEW_THRESHOLD => {
+            let skew = block_time - now() as i64;
+            eprintln!(
+                "Local clock differs from network time by {} sec, message time is adjusted. \
+                Synchronize the clock to disable compensation: tonos-cli config --clock_compensation false",
+                skew,
+            );
+            skew
+        },
+        _ => 0,
+    };
+    *CLOCK_SKEW.lock().unwrap() = Some(skew);
+    skew
+}
+
+/// Returns message header with creation and expiration time shifted by the clock skew
+/// if compensation is enabled in config.
+pub async fn skew_header(ton: TonClient, conf: &Config) -> Option<FunctionHeader> {
+    if !conf.clock_compensation {
+        return None;
+    }
+    let skew = check_clock_skew(ton).await;
+    if skew == 0 {
+        return None;
+    }
+    let now_ms = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64 + skew * 1000;
+    Some(FunctionHeader {
+        time: Some(now_ms as u64),
+        expire: Some((now_ms / 1000 + (conf.timeout / 1000) as i64) as u32),
+        pubkey: None,
+    })
+}
+
+/// Applies header flags from command line: adds or removes `pubkey` and `time`
+/// in the ABI header list and sets `expire` header value. Fails if a header is
+/// removed or set while the ABI doesn't declare it.
+pub fn apply_header_options(
+    conf: &Config,
+    abi: Abi,
+    header: Option<FunctionHeader>,
+) -> Result<(Abi, Option<FunctionHeader>), String> {
+    let options = &conf.header_options;
+    if options.pubkey.is_none() && options.time.is_none() && options.expire.is_none() {
+        return Ok((abi, header));
+    }
+    let mut contract = match abi {
+        Abi::Contract(contract) => contract,
+        _ => return Err("header flags require ABI to be loaded from json".to_owned()),
+    };
+    for &(name, flag) in [("pubkey", options.pubkey), ("time", options.time)].iter() {
+        let declared = contract.header.iter().any(|h| h.as_str() == name);
+        match flag {
+            Some(true) if !declared => contract.header.insert(0, name.to_string()),
+            Some(false) if !declared => {
+                return Err(format!("ABI doesn't declare {} header, nothing to exclude", name));
+            },
+            Some(false) => contract.header.retain(|h| h.as_str() != name),
+            _ => {},
+        }
+    }
+    let mut header = header.unwrap_or_default();
+    if let Some(expire) = options.expire {
+        if !contract.header.iter().any(|h| h.as_str() == "expire") {
+            return Err("ABI doesn't declare expire header".to_owned());
+        }
+        if expire <= now() {
+            return Err(format!("expire time {} is in the past", expire));
+        }
+        header.expire = Some(expire);
+    }
+    if options.time == Some(false) {
+        header.time = None;
+    }
+    if options.pubkey == Some(false) {
+        header.pubkey = None;
+    }
+    Ok((Abi::Contract(contract), Some(header)))
+}
+
+const BULK_PAGE_SIZE: u32 = 50;
+const BULK_RETRIES: u32 = 5;
+
+fn is_retryable(e: &ClientError) -> bool {
+    let text = e.to_string();
+    ["429", "Too Many Requests", "500", "502", "503", "504", "timeout", "network"]
+        .iter()
+        .any(|pattern| text.contains(pattern))
+}
+
+/// Query executor for commands which page through many records.
+/// Limits request rate and number of simultaneous requests and retries
+/// requests rejected by the endpoint with HTTP 429/5xx errors.
+pub struct BulkQuery {
+    ton: TonClient,
+    retries: u32,
+    interval: Option<Duration>,
+    timeout: Option<Duration>,
+    last_request: tokio::sync::Mutex<Option<Instant>>,
+    permits: tokio::sync::Semaphore,
+}
+
+impl BulkQuery {
+    pub fn new(ton: TonClient, conf: &Config) -> Self {
+        let query_timeout = conf.network_policy.timeout.unwrap_or(conf.query_timeout);
+        Self {
+            ton,
+            retries: conf.network_policy.retries.map(|r| r as u32).unwrap_or(BULK_RETRIES),
+            interval: if conf.rate_limit == 0 {
+                None
+            } else {
+                Some(Duration::from_millis(1000 / conf.rate_limit as u64))
+            },
+            timeout: if query_timeout == 0 {
+                None
+            } else {
+                Some(Duration::from_millis(query_timeout as u64))
+            },
+            last_request: tokio::sync::Mutex::new(None),
+            permits: tokio::sync::Semaphore::new(std::cmp::max(conf.concurrency, 1) as usize),
+        }
+    }
+
+    async fn throttle(&self) {
+        if let Some(interval) = self.interval {
+            let mut last = self.last_request.lock().await;
+            if let Some(time) = *last {
+                let elapsed = time.elapsed();
+                if elapsed < interval {
+                    tokio::time::delay_for(interval - elapsed).await;
+                }
+            }
+            *last = Some(Instant::now());
+        }
+    }
+
+    pub async fn query(
+        &self,
+        collection: &str,
+        filter: serde_json::Value,
+        result: &str,
+        order: Option<Vec<OrderBy>>,
+        limit: Option<u32>,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let _permit = self.permits.acquire().await;
+        let mut attempt = 0;
+        loop {
+            self.throttle().await;
+            let request = query_collection(
+                self.ton.clone(),
+                ParamsOfQueryCollection {
+                    collection: collection.to_owned(),
+                    filter: Some(filter.clone()),
+                    result: result.to_owned(),
+                    order: order.clone(),
+                    limit,
+                },
+            );
+            let res = match self.timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, request).await {
+                    Ok(res) => res,
+                    Err(_) if attempt < self.retries => {
+                        attempt += 1;
+                        log::warn!("query timed out. Retrying...");
+                        continue;
+                    },
+                    Err(_) => return Err(format!("query timed out after {} ms", timeout.as_millis())),
+                },
+                None => request.await,
+            };
+            match res {
+                Ok(r) => return Ok(r.result),
+                Err(e) if attempt < self.retries && is_retryable(&e) => {
+                    attempt += 1;
+                    let delay = Duration::from_secs(1 << attempt);
+                    log::warn!("query failed: {}. Retrying in {} sec...", e, delay.as_secs());
+                    tokio::time::delay_for(delay).await;
+                },
+                Err(e) => return Err(format!("query failed: {}", e)),
+            }
+        }
+    }
+
+    /// Queries the next page of records after the cursor and advances it.
+    /// Returns `None` when all records are returned.
+    pub async fn next_page(
+        &self,
+        cursor: &mut PageCursor,
+        result: &str,
+    ) -> Result<Option<Vec<serde_json::Value>>, String> {
+        if cursor.done {
+            return Ok(None);
+        }
+        let order = Some(vec![
+            OrderBy { path: cursor.field.clone(), direction: SortDirection::ASC },
+            OrderBy { path: "id".to_owned(), direction: SortDirection::ASC },
+        ]);
+        let page = self.query(&cursor.collection, cursor.page_filter(), result, order, Some(BULK_PAGE_SIZE)).await?;
+        let page_len = page.len();
+        let records: Vec<serde_json::Value> = page.into_iter()
+            .filter(|record| cursor.advance(record))
+            .collect();
+        if page_len < BULK_PAGE_SIZE as usize || records.is_empty() {
+            cursor.done = true;
+        }
+        Ok(Some(records))
+    }
+
+    /// Queries all records matching the filter page by page in ascending order of `field`.
+    /// `result` must contain `id` and `field`.
+    pub async fn query_all(
+        &self,
+        collection: &str,
+        filter: serde_json::Value,
+        result: &str,
+        field: &str,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let mut cursor = PageCursor::new(collection, filter, field);
+        let mut records = vec![];
+        while let Some(page) = self.next_page(&mut cursor, result).await? {
+            records.extend(page);
+        }
+        Ok(records)
+    }
+}
+
+/// Position of paging through a collection in ascending order of (`field`, `id`).
+/// It can be saved to a checkpoint file, so that an interrupted export
+/// continues from the last saved page instead of the beginning.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PageCursor {
+    collection: String,
+    filter: serde_json::Value,
+    field: String,
+    /// Value of `field` of the last returned record.
+    last: Option<serde_json::Value>,
+    /// Ids of returned records with `field` equal to `last`.
+    seen: Vec<String>,
+    /// Number of records returned so far.
+    count: u64,
+    // not saved: a resumed cursor checks for new records once more
+    #[serde(skip)]
+    done: bool,
+}
+
+impl PageCursor {
+    pub fn new(collection: &str, filter: serde_json::Value, field: &str) -> Self {
+        Self {
+            collection: collection.to_owned(),
+            filter,
+            field: field.to_owned(),
+            last: None,
+            seen: vec![],
+            count: 0,
+            done: false,
+        }
+    }
+
+    /// Starts paging from records with `field` greater or equal to `value`.
+    pub fn starting_at(mut self, value: serde_json::Value) -> Self {
+        self.last = Some(value);
+        self
+    }
+
+    pub fn last(&self) -> Option<&serde_json::Value> {
+        self.last.as_ref()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn filter(&self) -> &serde_json::Value {
+        &self.filter
+    }
+
+    /// Filter of records after the last returned one: with greater `field`
+    /// or with the same `field` and greater `id`.
+    fn page_filter(&self) -> serde_json::Value {
+        let mut filter = self.filter.clone();
+        match (&self.last, self.seen.iter().max()) {
+            (Some(last), Some(last_id)) => {
+                let mut same = self.filter.clone();
+                same[&self.field] = json!({ "eq": last });
+                same["id"] = json!({ "gt": last_id });
+                filter[&self.field] = json!({ "gt": last });
+                filter["OR"] = same;
+            },
+            (Some(last), None) => filter[&self.field] = json!({ "ge": last }),
+            (None, _) => {},
+        }
+        filter
+    }
+
+    /// Moves the cursor past the record. Returns false if the record was already returned.
+    /// Records must come in ascending order of `field`.
+    pub fn advance(&mut self, record: &serde_json::Value) -> bool {
+        let id = record["id"].as_str().unwrap_or("").to_owned();
+        if self.last.as_ref() == Some(&record[&self.field]) {
+            if self.seen.contains(&id) {
+                return false;
+            }
+        } else {
+            self.last = Some(record[&self.field].clone());
+            self.seen.clear();
+        }
+        self.seen.push(id);
+        self.count += 1;
+        true
+    }
+
+    /// Checks that both cursors page through the same records.
+    pub fn is_same_query(&self, other: &PageCursor) -> bool {
+        self.collection == other.collection && self.filter == other.filter && self.field == other.field
+    }
+
+    /// Loads the cursor saved in the checkpoint file. Returns `fresh` cursor if
+    /// the file doesn't exist yet. The second value is true if the checkpoint is resumed.
+    pub fn load_or(path: &str, fresh: PageCursor) -> Result<(PageCursor, bool), String> {
+        match load_checkpoint::<PageCursor>(path)? {
+            Some(saved) if !saved.is_same_query(&fresh) =>
+                Err(format!("checkpoint {} was saved by another query", path)),
+            Some(saved) => Ok((saved, true)),
+            None => Ok((fresh, false)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        save_checkpoint(path, self)
+    }
+}
+
+/// Reads checkpoint of a resumable command, returns `None` if the file doesn't exist.
+pub fn load_checkpoint<T: serde::de::DeserializeOwned>(path: &str) -> Result<Option<T>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("failed to read checkpoint {}: {}", path, e)),
+    };
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|e| format!("failed to parse checkpoint {}: {}", path, e))
+}
+
+/// Writes checkpoint of a resumable command. The file is replaced atomically,
+/// so an interrupted save doesn't corrupt the previous checkpoint.
+pub fn save_checkpoint<T: Serialize>(path: &str, checkpoint: &T) -> Result<(), String> {
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, serde_json::to_string_pretty(checkpoint).unwrap())
+        .map_err(|e| format!("failed to write checkpoint {}: {}", tmp, e))?;
+    std::fs::rename(&tmp, path)
+        .map_err(|e| format!("failed to save checkpoint {}: {}", path, e))
+}
+
 pub async fn decode_msg_body(
     ton: TonClient,
     abi: &str,
@@ -173,10 +739,41 @@ pub async fn decode_msg_body(
     .map_err(|e| format!("failed to decode body: {}", e))
 }
 
-pub fn load_abi(abi: &str) -> Result<Abi, String> {
+/// Prints fees of the transaction caused by external inbound message.
+pub fn fees_to_json(fees: &TransactionFees) -> serde_json::Value {
+    let balance_delta = -((fees.total_account_fees + fees.total_output) as i128);
+    json!({
+        "storage_fee": fees.storage_fee.to_string(),
+        "gas_fee": fees.gas_fee.to_string(),
+        "in_msg_fwd_fee": fees.in_msg_fwd_fee.to_string(),
+        "out_msgs_fwd_fee": fees.out_msgs_fwd_fee.to_string(),
+        "total_account_fees": fees.total_account_fees.to_string(),
+        "total_output": fees.total_output.to_string(),
+        "balance_delta": balance_delta.to_string(),
+    })
+}
+
+pub fn print_fees(fees: &TransactionFees, is_json: bool) {
+    let balance_delta = -((fees.total_account_fees + fees.total_output) as i128);
+    if is_json {
+        println!("{}", json!({ "fees": fees_to_json(fees) }));
+        return;
+    }
+    let tokens = crate::convert::nano_to_tokens;
+    println!("Fees:");
+    println!("  storage:       {}", tokens(fees.storage_fee));
+    println!("  gas:           {}", tokens(fees.gas_fee));
+    println!("  forward (in):  {}", tokens(fees.in_msg_fwd_fee));
+    println!("  forward (out): {}", tokens(fees.out_msgs_fwd_fee));
+    println!("  total:         {}", tokens(fees.total_account_fees));
+    println!("Sent value:      {}", tokens(fees.total_output));
+    println!("Balance delta:   -{}", tokens(balance_delta.abs() as u64));
+}
+
+pub fn load_abi(abi: &str) -> Result<Abi, CliError> {
     Ok(Abi::Contract(
         serde_json::from_str::<AbiContract>(abi)
-            .map_err(|e| format!("ABI is not a valid json: {}", e))?,
+            .map_err(|e| CliError::new(ErrorKind::InvalidInput, format!("ABI is not a valid json: {}", e)))?,
     ))
 }
 
@@ -193,6 +790,11 @@ pub async fn calc_acc_address(
         .map(|d| serde_json::from_str(d))
         .transpose()
         .map_err(|e| format!("initial data is not in json: {}", e))?;
+    if let (Some(data), Abi::Contract(contract)) = (&init_data_json, &abi) {
+        let contract = serde_json::to_value(contract)
+            .map_err(|e| format!("failed to serialize ABI: {}", e))?;
+        crate::abi::validate_initial_data(&contract, data)?;
+    }
 
     let dset = DeploySet {
         tvc: base64::encode(tvc),
@@ -215,3 +817,53 @@ pub async fn calc_acc_address(
     .map_err(|e| format!("cannot generate address: {}", e))?;
     Ok(result.address)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_with_policy() {
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let policy = NetworkPolicy { retries: Some(1), timeout: Some(50) };
+        let res: Result<(), ClientError> = with_policy(policy, || async {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::delay_for(Duration::from_millis(200)).await;
+            Ok(())
+        }).await;
+        assert!(res.unwrap_err().to_string().contains("timeout"));
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let res = with_policy(NetworkPolicy::default(), || async { Ok::<u32, ClientError>(1) }).await;
+        assert_eq!(res.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_page_cursor() {
+        let mut cursor = PageCursor::new("transactions", json!({ "account_addr": { "eq": "0:01" } }), "lt");
+        assert_eq!(cursor.page_filter(), json!({ "account_addr": { "eq": "0:01" } }));
+        assert!(cursor.advance(&json!({ "id": "a", "lt": "0x1" })));
+        assert!(cursor.advance(&json!({ "id": "b", "lt": "0x2" })));
+        assert!(cursor.advance(&json!({ "id": "c", "lt": "0x2" })));
+        assert!(!cursor.advance(&json!({ "id": "b", "lt": "0x2" })));
+        assert_eq!(cursor.count(), 3);
+        assert_eq!(cursor.page_filter(), json!({
+            "account_addr": { "eq": "0:01" },
+            "lt": { "gt": "0x2" },
+            "OR": { "account_addr": { "eq": "0:01" }, "lt": { "eq": "0x2" }, "id": { "gt": "c" } },
+        }));
+        let start = PageCursor::new("transactions", json!({}), "lt").starting_at(json!("0x5"));
+        assert_eq!(start.page_filter(), json!({ "lt": { "ge": "0x5" } }));
+
+        let path = std::env::temp_dir().join("tonos-cli-test-checkpoint.json");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let fresh = PageCursor::new("transactions", json!({ "account_addr": { "eq": "0:01" } }), "lt");
+        assert_eq!(PageCursor::load_or(path, fresh.clone()).unwrap(), (fresh.clone(), false));
+        cursor.save(path).unwrap();
+        assert_eq!(PageCursor::load_or(path, fresh).unwrap(), (cursor, true));
+        let other = PageCursor::new("transactions", json!({ "account_addr": { "eq": "0:02" } }), "lt");
+        assert!(PageCursor::load_or(path, other).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{now, TonClient};
+use chrono::{Local, TimeZone};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::Mutex;
+use ton_client::abi::{decode_message, Abi, ParamsOfDecodeMessage};
+use ton_client::boc::{parse_message, ParamsOfParse};
+
+/// Options whose values are secrets and must not get into the history.
+const SECRET_OPTIONS: [&str; 7] = ["--sign", "--keys", "--setkey", "--phrase", "--passphrase", "--topup-sign", "--data"];
+
+lazy_static! {
+    static ref HISTORY_FILE: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Record about message broadcasted to the network.
+#[derive(Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub time: u32,
+    pub command: String,
+    pub dst: String,
+    pub method: Option<String>,
+    pub value: Option<String>,
+    pub message_id: String,
+    pub result: String,
+}
+
+pub fn history_file_name(config_file: &str) -> String {
+    format!("{}.history", config_file)
+}
+
+pub fn set_history_file(path: &str) {
+    *HISTORY_FILE.lock().unwrap() = Some(path.to_owned());
+}
+
+/// Returns command line with values of secret options and seed phrases replaced by `***`.
+pub fn redacted_command_line() -> String {
+    redact(std::env::args().collect())
+}
+
+fn redact(args: Vec<String>) -> String {
+    let mut result = vec![];
+    let mut hide_next = false;
+    for arg in args {
+        let is_phrase = arg.split_whitespace().count() >= 12;
+        let is_secret_key = arg.len() >= 64 && arg.chars().all(|c| c.is_ascii_hexdigit());
+        // value may be attached to the option as --sign=keys.json
+        let mut parts = arg.splitn(2, '=');
+        let option = parts.next().unwrap_or("");
+        let attached = parts.next().is_some() && SECRET_OPTIONS.contains(&option);
+        if hide_next || is_phrase || is_secret_key {
+            result.push("***".to_owned());
+        } else if attached {
+            result.push(format!("{}=***", option));
+        } else {
+            result.push(arg.clone());
+        }
+        hide_next = SECRET_OPTIONS.contains(&arg.as_str());
+    }
+    result.join(" ")
+}
+
+/// Appends record about the broadcasted message to the history file.
+/// Failures are reported but never break the command.
+pub async fn record_message(ton: TonClient, abi: Option<Abi>, message: &str, result: &Result<(), String>) {
+    if HISTORY_FILE.lock().unwrap().is_none() {
+        return;
+    }
+    let parsed = parse_message(ton.clone(), ParamsOfParse { boc: message.to_owned() })
+        .await
+        .map(|r| r.parsed)
+        .unwrap_or(json!({}));
+    let decoded = match abi {
+        Some(abi) => decode_message(ton, ParamsOfDecodeMessage { abi, message: message.to_owned() })
+            .await
+            .ok(),
+        None => None,
+    };
+    let value = decoded.as_ref()
+        .and_then(|d| d.value.as_ref())
+        .and_then(|v| v.get("value").cloned())
+        .or(parsed.get("value").cloned())
+        .map(|v| v.as_str().map(|s| s.to_owned()).unwrap_or(v.to_string()));
+    record(
+        parsed["dst"].as_str().unwrap_or(""),
+        decoded.map(|d| d.name),
+        value,
+        parsed["id"].as_str().unwrap_or("").to_owned(),
+        result,
+    );
+}
+
+/// Appends record to the history file if it is set.
+pub fn record(
+    dst: &str,
+    method: Option<String>,
+    value: Option<String>,
+    message_id: String,
+    result: &Result<(), String>,
+) {
+    let path = match HISTORY_FILE.lock().unwrap().clone() {
+        Some(path) => path,
+        None => return,
+    };
+    let record = HistoryRecord {
+        time: now(),
+        command: redacted_command_line(),
+        dst: dst.to_owned(),
+        method,
+        value,
+        message_id,
+        result: match result {
+            Ok(_) => "ok".to_owned(),
+            Err(e) => format!("error: {}", e),
+        },
+    };
+    let line = serde_json::to_string(&record).unwrap();
+    let res = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = res {
+        eprintln!("failed to write history file {}: {}", path, e);
+    }
+}
+
+fn load_history(path: &str) -> Result<Vec<HistoryRecord>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("failed to read history file: {}", e)),
+    };
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).map_err(|e| format!("history file is corrupted: {}", e)))
+        .collect()
+}
+
+pub fn create_history_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("history")
+        .about("Audit log of messages sent to the network.")
+        .subcommand(SubCommand::with_name("list")
+            .about("Prints sent messages.")
+            .arg(Arg::with_name("LIMIT")
+                .long("--limit")
+                .short("-n")
+                .takes_value(true)
+                .help("Number of last records to print (default: all).")))
+        .subcommand(SubCommand::with_name("show")
+            .about("Prints details of the record.")
+            .arg(Arg::with_name("ID")
+                .required(true)
+                .takes_value(true)
+                .help("Record number (see `history list`) or message id.")))
+}
+
+pub async fn history_command(m: &ArgMatches<'_>, config_file: &str) -> Result<(), CliError> {
+    let records = load_history(&history_file_name(config_file))?;
+    if let Some(m) = m.subcommand_matches("list") {
+        let limit = m.value_of("LIMIT")
+            .map(|l| usize::from_str_radix(l, 10))
+            .transpose()
+            .map_err(|e| format!("failed to parse limit: {}", e))?
+            .unwrap_or(records.len());
+        let skip = records.len().saturating_sub(limit);
+        for (i, record) in records.iter().enumerate().skip(skip) {
+            println!(
+                "{:>4}  {}  {:<6}  {}  {}  {}",
+                i + 1,
+                Local.timestamp(record.time as i64, 0).format("%Y-%m-%d %H:%M:%S"),
+                if record.result == "ok" { "ok" } else { "failed" },
+                record.dst,
+                record.method.as_deref().unwrap_or("-"),
+                record.value.as_deref().unwrap_or("-"),
+            );
+        }
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("show") {
+        let id = m.value_of("ID").unwrap();
+        let record = match usize::from_str_radix(id, 10) {
+            Ok(n) if n >= 1 && n <= records.len() => &records[n - 1],
+            _ => records.iter()
+                .find(|r| r.message_id == id)
+                .ok_or(CliError::new(ErrorKind::InvalidInput, format!("record {} not found", id)))?,
+        };
+        println!("{}", serde_json::to_string_pretty(record).unwrap());
+        return Ok(());
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown history command"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let args = vec!["tonos-cli", "call", "0:1", "submit", "{}", "--sign", "keys.json"]
+            .into_iter().map(|s| s.to_owned()).collect();
+        assert_eq!(redact(args), "tonos-cli call 0:1 submit {} --sign ***");
+        let phrase = "a b c d e f g h i j k l".to_owned();
+        assert_eq!(redact(vec!["genpubkey".to_owned(), phrase]), "genpubkey ***");
+        let args = vec!["tonos-cli", "--passphrase", "hidden", "genpubkey"]
+            .into_iter().map(|s| s.to_owned()).collect();
+        assert_eq!(redact(args), "tonos-cli --passphrase *** genpubkey");
+        let args = vec!["tonos-cli", "call", "0:1", "submit", "{}", "--sign=keys.json", "--phrase=\"a b c\"", "--abi=x.json"]
+            .into_iter().map(|s| s.to_owned()).collect();
+        assert_eq!(redact(args), "tonos-cli call 0:1 submit {} --sign=*** --phrase=*** --abi=x.json");
+    }
+}
@@ -0,0 +1,214 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lang {
+    En,
+    Ru,
+}
+
+pub const SUPPORTED_LANGS: [&str; 2] = ["en", "ru"];
+
+lazy_static! {
+    static ref LANG: RwLock<Lang> = RwLock::new(Lang::En);
+}
+
+pub fn parse_lang(lang: &str) -> Result<Lang, String> {
+    // accept locale names like "ru_RU.UTF-8"
+    match lang.to_lowercase().split(|c| c == '_' || c == '-' || c == '.').next().unwrap_or("") {
+        "en" => Ok(Lang::En),
+        "ru" => Ok(Lang::Ru),
+        _ => Err(format!("unsupported language \"{}\" (supported: {})", lang, SUPPORTED_LANGS.join(", "))),
+    }
+}
+
+/// Sets language of user-facing messages from `--lang` option or config.
+pub fn set_lang(lang: Option<&str>) -> Result<(), String> {
+    let lang = lang.map(parse_lang).transpose()?.unwrap_or(Lang::En);
+    *LANG.write().unwrap() = lang;
+    Ok(())
+}
+
+/// Message catalog. English text is used as the key, so untranslated
+/// messages are printed as is.
+const RU: &[(&str, &str)] = &[
+    ("Error: {}", "Ошибка: {}"),
+    ("Reason: {}", "Причина: {}"),
+    ("Config: {}", "Конфигурация: {}"),
+    ("Config: default", "Конфигурация: по умолчанию"),
+    ("Processing...", "Обработка..."),
+    ("Succeeded.", "Успешно."),
+    ("Connecting to {}", "Подключение к {}"),
+    ("Debot {}: {}", "Дебот {}: {}"),
+    ("Debot Browser shutdown", "Браузер деботов завершил работу"),
+    ("Invoking debot {}", "Вызов дебота {}"),
+    ("Error. {}", "Ошибка. {}"),
+    ("Invalid action. Try again.", "Неверное действие. Попробуйте ещё раз."),
+    ("Oops! Invalid action. Try again, please.", "Упс! Неверное действие. Попробуйте ещё раз."),
+    ("Auch! Invalid action. Try again, please.", "Ой! Неверное действие. Попробуйте ещё раз."),
+    ("Oops! Invalid debot number. Try again, please.", "Упс! Неверный номер дебота. Попробуйте ещё раз."),
+    ("Auch! Invalid debot number. Try again, please.", "Ой! Неверный номер дебота. Попробуйте ещё раз."),
+    ("Select action as <debot>.<action>, e.g. 1.3", "Выберите действие в виде <дебот>.<действие>, например 1.3"),
+    ("Debot {} has finished. Select another one.", "Дебот {} завершил работу. Выберите другой."),
+    ("Message expired, resending ({} of {})...", "Срок действия сообщения истёк, повторная отправка ({} из {})..."),
+    ("(r)etry, (c)ontinue or (a)bort session? ", "(r) повторить, (c) продолжить или (a) прервать сессию? "),
+    ("Invalid answer. Try again.", "Неверный ответ. Попробуйте ещё раз."),
+    ("{}. Try again.", "{}. Попробуйте ещё раз."),
+    ("Invalid menu. Try again.", "Неверный пункт меню. Попробуйте ещё раз."),
+    ("Invalid address: {}", "Неверный адрес: {}"),
+    ("invalid enter", "неверный ввод"),
+    ("(Ctrl+Z to exit)", "(Ctrl+Z для выхода)"),
+    ("(Ctrl+D to exit)", "(Ctrl+D для выхода)"),
+    ("Debot requests a signature:", "Дебот запрашивает подпись:"),
+    ("  debot:      {}", "  дебот:      {}"),
+    ("  action:     {}", "  действие:   {}"),
+    ("  function:   {}", "  функция:    {}"),
+    ("  destination: {}", "  получатель: {}"),
+    ("unknown (not found in debot ABI)", "неизвестно (нет в ABI дебота)"),
+    ("  public key: {}", "  публичный ключ: {}"),
+    ("  code hash:  {}", "  хеш кода:   {}"),
+    ("  data hash:  {}", "  хеш данных: {}"),
+    ("unknown", "неизвестно"),
+    ("Sign? (y/n) ", "Подписать? (y/n) "),
+    ("signature rejected by user", "подпись отклонена пользователем"),
+    ("enter seed phrase or path to keypair file", "введите сид-фразу или путь к файлу ключей"),
+    ("enter path to keypair file (leave empty to enter seed phrase without echo)",
+        "введите путь к файлу ключей (оставьте пустым, чтобы ввести сид-фразу без отображения)"),
+    ("Seed phrase: ", "Сид-фраза: "),
+    ("Invalid keys: {}. Try again.", "Неверные ключи: {}. Попробуйте ещё раз."),
+    ("Trust this debot? (y/n) ", "Доверять этому деботу? (y/n) "),
+    ("Debot {} (code hash {}) requests signing keys for the first time.",
+        "Дебот {} (хеш кода {}) впервые запрашивает ключи для подписи."),
+    ("WARNING! Code of debot {} has changed since you approved it.\n  approved code hash: {}\n  current code hash:  {}\nThe debot may have been upgraded by its owner or replaced by a malicious one. It gets no signing keys until approved again.",
+        "ВНИМАНИЕ! Код дебота {} изменился после вашего одобрения.\n  одобренный хеш кода: {}\n  текущий хеш кода:    {}\nДебот мог быть обновлён владельцем или подменён злоумышленником. Он не получит ключи для подписи, пока не будет одобрен снова."),
+    ("WARNING! Debot session limit is set, but wallet is not configured (see config --wallet), spendings are not tracked.",
+        "ВНИМАНИЕ! Лимит сессии деботов задан, но кошелёк не настроен (см. config --wallet), расходы не отслеживаются."),
+    ("WARNING! Debots have spent {} from wallet {} in this session, limit is {}.",
+        "ВНИМАНИЕ! Деботы потратили {} с кошелька {} в этой сессии, лимит {}."),
+    ("Account: {} | Balance: {}", "Аккаунт: {} | Баланс: {}"),
+    ("Account: not configured", "Аккаунт: не настроен"),
+    (" Network: {} | {} | PgUp/PgDn scroll log, Ctrl+D exit", " Сеть: {} | {} | PgUp/PgDn прокрутка журнала, Ctrl+D выход"),
+    ("Debot log", "Журнал дебота"),
+    ("Debot log (scrolled, PgDn to return)", "Журнал дебота (прокручен, PgDn для возврата)"),
+    ("Menu", "Меню"),
+    ("Pending messages ({})", "Ожидающие сообщения ({})"),
+    ("Debot browser is listening on ws://{}/?token={}", "Браузер деботов ожидает подключений на ws://{}/?token={}"),
+    ("Remote UI connected from {}", "Подключён удалённый интерфейс {}"),
+    ("Session finished.", "Сессия завершена."),
+    ("Session failed: {}", "Сессия завершилась с ошибкой: {}"),
+    ("Debot plugin {} is skipped: {}", "Плагин деботов {} пропущен: {}"),
+    ("Debot plugin interface {} is skipped: it is implemented by tonos-cli",
+        "Интерфейс плагина деботов {} пропущен: он реализован в tonos-cli"),
+    ("Succeeded", "Успешно"),
+    ("Succeded.", "Успешно."),
+    ("Generating external inbound message...", "Создание внешнего входящего сообщения..."),
+    ("MessageId: {}", "Идентификатор сообщения: {}"),
+    ("Expire at: ", "Истекает: "),
+    ("Running get-method...", "Выполнение get-метода..."),
+    ("Using seqno {}", "Используется seqno {}"),
+    ("Seqno has changed, retrying...", "Seqno изменился, повтор..."),
+    ("Message expired without transaction. Re-reading seqno and retrying...",
+        "Срок действия сообщения истёк без транзакции. Повторное чтение seqno и повтор..."),
+    ("Result: {}", "Результат: {}"),
+    ("Message saved to file {}", "Сообщение сохранено в файл {}"),
+    ("Message: {}", "Сообщение: {}"),
+    ("Calling method {} with parameters:", "Вызов метода {} с параметрами:"),
+    ("Deploying...", "Развёртывание..."),
+    ("Transaction succeeded.", "Транзакция выполнена."),
+    ("Contract deployed at address: {}", "Контракт развёрнут по адресу: {}"),
+    ("Contract address: {}", "Адрес контракта: {}"),
+    ("Account status: {}", "Статус аккаунта: {}"),
+    ("Account balance: {}", "Баланс аккаунта: {}"),
+    ("Required balance: {}", "Необходимый баланс: {}"),
+    ("Account balance is sufficient for deploy.", "Баланса аккаунта достаточно для развёртывания."),
+    ("Send at least {} tokens to {} before deploy.", "Перед развёртыванием отправьте не менее {} токенов на {}."),
+    ("Account balance {} is sufficient for deploy.", "Баланса аккаунта {} достаточно для развёртывания."),
+    ("Sending {} tokens from {} to {}...", "Отправка {} токенов с {} на {}..."),
+    ("Waiting for tokens to be credited...", "Ожидание зачисления токенов..."),
+    ("Keys are derived with BIP-39 passphrase, it's required to restore them along with the seed phrase.",
+        "Ключи получены с парольной фразой BIP-39, для их восстановления она нужна вместе с сид-фразой."),
+    ("Raw address: {}", "Адрес: {}"),
+    ("Non-bounceable address (for init): {}", "Адрес без возврата (для инициализации): {}"),
+    ("Bounceable address (for later access): {}", "Адрес с возвратом (для дальнейшего доступа): {}"),
+    ("TVC file updated", "Файл TVC обновлён"),
+    ("Account does not exist.", "Аккаунт не существует."),
+    ("Account not found.", "Аккаунт не найден."),
+    ("Account is frozen because of unpaid storage fees.", "Аккаунт заморожен из-за неоплаченной платы за хранение."),
+    ("To unfreeze it send at least {} tokens and a message with its original state init:",
+        "Для разморозки отправьте не менее {} токенов и сообщение с исходным state init:"),
+    ("Storage debt: {}, sending {} from {}", "Долг за хранение: {}, отправка {} с {}"),
+    ("Debt is paid. Account stays frozen until it receives a message with its original state init (use --tvc).",
+        "Долг оплачен. Аккаунт останется замороженным, пока не получит сообщение с исходным state init (используйте --tvc)."),
+    ("Account state: {}", "Состояние аккаунта: {}"),
+    ("Account state saved to file {}", "Состояние аккаунта сохранено в файл {}"),
+    ("Sweeping {}...", "Вывод средств с {}..."),
+    ("{}: swept to {}", "{}: средства переведены на {}"),
+    ("{}: failed: {}", "{}: ошибка: {}"),
+    ("Swept {} of {} accounts.", "Средства выведены с {} из {} аккаунтов."),
+];
+
+fn catalog(lang: Lang) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        Lang::En => &[],
+        Lang::Ru => RU,
+    }
+}
+
+fn translate(lang: Lang, text: &'static str) -> &'static str {
+    catalog(lang).iter()
+        .find(|(key, _)| *key == text)
+        .map(|(_, value)| *value)
+        .unwrap_or(text)
+}
+
+/// Translates message to the current language.
+pub fn tr(text: &'static str) -> &'static str {
+    translate(*LANG.read().unwrap(), text)
+}
+
+/// Translates message and substitutes `{}` placeholders with arguments in order.
+pub fn trf(text: &'static str, args: &[&dyn Display]) -> String {
+    let mut result = String::new();
+    let mut args = args.iter();
+    let mut parts = tr(text).split("{}").peekable();
+    while let Some(part) = parts.next() {
+        result.push_str(part);
+        if parts.peek().is_some() {
+            if let Some(arg) = args.next() {
+                result.push_str(&arg.to_string());
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog() {
+        assert_eq!(parse_lang("ru_RU.UTF-8").unwrap(), Lang::Ru);
+        assert!(parse_lang("de").is_err());
+        assert_eq!(translate(Lang::Ru, "Succeeded."), "Успешно.");
+        assert_eq!(translate(Lang::En, "Succeeded."), "Succeeded.");
+        assert_eq!(translate(Lang::Ru, "untranslated"), "untranslated");
+        assert_eq!(trf("Error: {}", &[&"x"]), "Error: x");
+        // translations must keep all placeholders
+        for (key, value) in RU {
+            assert_eq!(key.matches("{}").count(), value.matches("{}").count(), "{}", key);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! ABIs of standard contracts bundled with tonos-cli. Multisig and depool
+//! commands pick the ABI by code hash of the account, so `--abi` is never needed
+//! for them. ABIs of other versions can be added as
+//! `~/.tonos-cli/abi/<code hash>.abi.json`, such files take precedence.
+use crate::depool_abi::DEPOOL_ABI;
+use crate::helpers::{query, TonClient};
+use crate::multisig::{MSIG_ABI, SETCODE_UPDATE_ABI};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const ABI_DIR_NAME: &str = ".tonos-cli/abi";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContractFamily {
+    Multisig,
+    DePool,
+}
+
+impl ContractFamily {
+    fn default_abi(&self) -> &'static str {
+        match self {
+            ContractFamily::Multisig => MSIG_ABI,
+            ContractFamily::DePool => DEPOOL_ABI,
+        }
+    }
+}
+
+struct KnownAbi {
+    family: ContractFamily,
+    name: &'static str,
+    code_hash: &'static str,
+    abi: fn() -> &'static str,
+}
+
+fn safemultisig_abi() -> &'static str {
+    MSIG_ABI
+}
+
+fn setcode_abi() -> &'static str {
+    SETCODE_MSIG_ABI.as_str()
+}
+
+fn depool_abi() -> &'static str {
+    DEPOOL_ABI
+}
+
+/// Several versions of a contract may share the same ABI. Multisig entries must
+/// cover code hashes of `WALLET_TYPES`.
+const KNOWN_ABIS: [KnownAbi; 4] = [
+    KnownAbi {
+        family: ContractFamily::Multisig,
+        name: "SafeMultisigWallet",
+        code_hash: "80d6c47c4a25543c9b397b71716f3fae1e2c5d247174c52e2c19bd896442b105",
+        abi: safemultisig_abi,
+    },
+    KnownAbi {
+        family: ContractFamily::Multisig,
+        name: "SetcodeMultisigWallet",
+        code_hash: "e2b60b6b602c10ced7ea8ede4bdf96342c97570a3798066f3fb50a4b2b27a208",
+        abi: setcode_abi,
+    },
+    KnownAbi {
+        family: ContractFamily::Multisig,
+        name: "SetcodeMultisigWallet2 (Surf)",
+        code_hash: "207dc560c5956de1a2c1479356f8f3ee70a59767db2bf4788b1d61ad42cdad82",
+        abi: setcode_abi,
+    },
+    KnownAbi {
+        family: ContractFamily::DePool,
+        name: "DePool",
+        code_hash: "14e20e304f53e6da152eb95fffc993dbd28245a775d847eed043f7c78a503885",
+        abi: depool_abi,
+    },
+];
+
+lazy_static! {
+    /// SetcodeMultisig ABI: SafeMultisig functions plus custodian updates.
+    static ref SETCODE_MSIG_ABI: String = merge_abi(MSIG_ABI, SETCODE_UPDATE_ABI);
+    /// ABIs detected in this run by account address.
+    static ref DETECTED: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+/// Adds functions of `extra` to `base`, replacing functions with the same name.
+fn merge_abi(base: &str, extra: &str) -> String {
+    let mut base: Value = serde_json::from_str(base).unwrap();
+    let extra: Value = serde_json::from_str(extra).unwrap();
+    let extra_functions = extra["functions"].as_array().cloned().unwrap_or_default();
+    let functions = base["functions"].as_array_mut().unwrap();
+    for function in extra_functions {
+        functions.retain(|f| f["name"] != function["name"]);
+        functions.push(function);
+    }
+    base.to_string()
+}
+
+fn abi_dir() -> PathBuf {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(ABI_DIR_NAME)
+}
+
+/// Finds bundled ABI of the contract family by code hash, returns contract name and ABI.
+fn find_bundled(family: ContractFamily, code_hash: &str) -> Option<(&'static str, &'static str)> {
+    KNOWN_ABIS.iter()
+        .find(|known| known.family == family && known.code_hash == code_hash)
+        .map(|known| (known.name, (known.abi)()))
+}
+
+/// Returns ABI for the code hash: user provided file first, then bundled ABIs.
+pub fn find_known_abi(family: ContractFamily, code_hash: &str) -> Option<String> {
+    if code_hash.is_empty() {
+        return None;
+    }
+    let path = abi_dir().join(format!("{}.abi.json", code_hash));
+    if let Ok(abi) = std::fs::read_to_string(&path) {
+        debug!("ABI for code hash {} is loaded from {}", code_hash, path.display());
+        return Some(abi);
+    }
+    find_bundled(family, code_hash).map(|(name, abi)| {
+        debug!("code hash {} is {}", code_hash, name);
+        abi.to_owned()
+    })
+}
+
+/// Detects ABI of the contract by its code hash. Falls back to the default ABI
+/// of the family if the account has no code yet or the code hash is unknown.
+pub async fn detect_abi(ton: TonClient, addr: &str, family: ContractFamily) -> Result<String, String> {
+    if let Some(abi) = DETECTED.lock().unwrap().get(addr) {
+        return Ok(abi.clone());
+    }
+    let accounts = query(
+        ton,
+        "accounts",
+        json!({ "id": { "eq": addr } }),
+        "code_hash",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query account code hash: {}", e))?;
+    let code_hash = accounts.first()
+        .and_then(|acc| acc["code_hash"].as_str())
+        .unwrap_or("");
+    let abi = match find_known_abi(family, code_hash) {
+        Some(abi) => abi,
+        None => {
+            if !code_hash.is_empty() {
+                debug!("contract {} has unknown code hash {}, using default ABI", addr, code_hash);
+            }
+            family.default_abi().to_owned()
+        },
+    };
+    DETECTED.lock().unwrap().insert(addr.to_owned(), abi.clone());
+    Ok(abi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::WALLET_TYPES;
+
+    fn code_hash_of(wallet_type: &str) -> &'static str {
+        WALLET_TYPES.iter().find(|(t, _)| *t == wallet_type).map(|(_, h)| *h).unwrap()
+    }
+
+    #[test]
+    fn test_known_abi() {
+        let (name, abi) = find_bundled(ContractFamily::Multisig, code_hash_of("safemultisig")).unwrap();
+        assert_eq!(name, "SafeMultisigWallet");
+        assert_eq!(abi, MSIG_ABI);
+
+        let (_, abi) = find_bundled(ContractFamily::Multisig, code_hash_of("setcode")).unwrap();
+        let abi: Value = serde_json::from_str(abi).unwrap();
+        let names: Vec<&str> = abi["functions"].as_array().unwrap().iter()
+            .map(|f| f["name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"submitTransaction"));
+        assert!(names.contains(&"submitUpdate"));
+        assert_eq!(names.iter().filter(|n| **n == "getParameters").count(), 1);
+
+        assert!(find_bundled(ContractFamily::DePool, code_hash_of("safemultisig")).is_none());
+        assert!(find_bundled(ContractFamily::Multisig, "00").is_none());
+
+        for (wallet_type, code_hash) in WALLET_TYPES.iter() {
+            assert!(find_bundled(ContractFamily::Multisig, code_hash).is_some(), "{}", wallet_type);
+        }
+        let (name, abi) = find_bundled(ContractFamily::DePool, KNOWN_ABIS[3].code_hash).unwrap();
+        assert_eq!(name, "DePool");
+        assert_eq!(abi, DEPOOL_ABI);
+        assert!(find_known_abi(ContractFamily::DePool, "").is_none());
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! User-defined command macros stored in config:
+//! `tonos-cli config --macro 'topup(dest,amount)=call {wallet} sendTransaction ...'`
+//! and invoked as `tonos-cli x topup <dest> <amount>`.
+//!
+//! Macro command is split into arguments like in shell (quotes group words),
+//! then `{param}`, `{1}`, `{2}`... and config values `{wallet}`, `{addr}`,
+//! `{keys}`, `{abi}`, `{url}`, `{wc}` are substituted. Extra arguments are
+//! appended to the expanded command. Expansion happens before arguments are parsed.
+use crate::config::Config;
+use crate::error::CliError;
+use clap::{App, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const MACRO_COMMAND: &str = "x";
+
+/// Global options which take a value, they may precede the subcommand.
+const GLOBAL_OPTIONS_WITH_VALUE: [&str; 14] = [
+    "-u", "--url", "-c", "--config", "--phrase-file", "--phrase-fd", "--passphrase",
+    "--format", "--format-file", "--out", "--expire", "--lang", "--retry", "--timeout",
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MacroDef {
+    #[serde(default)]
+    pub params: Vec<String>,
+    pub command: String,
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parses `name(param1,param2)=command` (parameter list is optional).
+/// Empty command means removal of the macro.
+pub fn parse_definition(definition: &str) -> Result<(String, Option<MacroDef>), String> {
+    let mut parts = definition.splitn(2, '=');
+    let head = parts.next().unwrap_or("").trim();
+    let command = parts.next()
+        .ok_or(r#"macro must be specified as <name>(<params>)=<command>"#.to_string())?
+        .trim();
+    let (name, params) = match head.find('(') {
+        Some(pos) => {
+            let list = head[pos + 1..].strip_suffix(')')
+                .ok_or(format!(r#"unclosed parameter list in "{}""#, head))?;
+            let params: Vec<String> = list.split(',')
+                .map(|p| p.trim().to_owned())
+                .filter(|p| !p.is_empty())
+                .collect();
+            (head[..pos].trim(), params)
+        },
+        None => (head, vec![]),
+    };
+    if !is_identifier(name) {
+        return Err(format!(r#"invalid macro name "{}""#, name));
+    }
+    if let Some(param) = params.iter().find(|p| !is_identifier(p)) {
+        return Err(format!(r#"invalid macro parameter name "{}""#, param));
+    }
+    if command.is_empty() {
+        return Ok((name.to_owned(), None));
+    }
+    split_command(command)?;
+    Ok((name.to_owned(), Some(MacroDef { params, command: command.to_owned() })))
+}
+
+/// Splits command line into arguments. Single and double quotes group words,
+/// backslash escapes the next character outside of single quotes.
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                current.push(chars.next().ok_or("trailing backslash in macro".to_owned())?);
+                in_word = true;
+            },
+            (Some(_), c) => current.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                in_word = true;
+            },
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            },
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            },
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote in macro".to_owned());
+    }
+    if in_word {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+fn config_value(conf: &Config, name: &str) -> Option<String> {
+    match name {
+        "wallet" => conf.wallet.clone(),
+        "addr" => conf.addr.clone(),
+        "keys" => conf.keys_path.clone(),
+        "abi" => conf.abi_path.clone(),
+        "url" => Some(conf.url.clone()),
+        "wc" => Some(conf.wc.to_string()),
+        _ => None,
+    }
+}
+
+/// Replaces `{name}` placeholders in the argument. Braces with anything but
+/// an identifier inside (e.g. json objects) are kept as is.
+fn substitute(arg: &str, values: &BTreeMap<String, String>, conf: &Config) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) if is_identifier(&after[..end]) => {
+                let name = &after[..end];
+                let value = values.get(name).cloned()
+                    .or_else(|| config_value(conf, name))
+                    .ok_or(format!("macro placeholder {{{}}} has no value", name))?;
+                result.push_str(&value);
+                rest = &after[end + 1..];
+            },
+            _ => {
+                result.push('{');
+                rest = after;
+            },
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+pub fn expand(name: &str, def: &MacroDef, args: &[String], conf: &Config) -> Result<Vec<String>, String> {
+    if args.len() < def.params.len() {
+        return Err(format!(
+            "macro {} expects {} arguments: {}", name, def.params.len(), def.params.join(" ")
+        ));
+    }
+    let mut values = BTreeMap::new();
+    for (i, arg) in args.iter().enumerate() {
+        values.insert((i + 1).to_string(), arg.clone());
+        if let Some(param) = def.params.get(i) {
+            values.insert(param.clone(), arg.clone());
+        }
+    }
+    let mut expanded = split_command(&def.command)?
+        .iter()
+        .map(|arg| substitute(arg, &values, conf))
+        .collect::<Result<Vec<_>, String>>()?;
+    if expanded.first().map(|cmd| cmd == MACRO_COMMAND).unwrap_or(false) {
+        return Err(format!("macro {} can't invoke other macros", name));
+    }
+    expanded.extend(args.iter().skip(def.params.len()).cloned());
+    Ok(expanded)
+}
+
+/// Position of the subcommand in process arguments (global options may precede it).
+fn subcommand_position(args: &[String]) -> Option<usize> {
+    let mut i = 1;
+    while i < args.len() {
+        let arg = &args[i];
+        if !arg.starts_with('-') {
+            return Some(i);
+        }
+        if GLOBAL_OPTIONS_WITH_VALUE.contains(&arg.as_str()) {
+            i += 1;
+        }
+        i += 1;
+    }
+    None
+}
+
+fn macro_position(args: &[String]) -> Option<usize> {
+    subcommand_position(args).filter(|pos| args[*pos] == MACRO_COMMAND && pos + 1 < args.len())
+}
+
+/// Checks if arguments invoke a macro, config is loaded only in this case.
+pub fn is_macro_invocation(args: &[String]) -> bool {
+    macro_position(args).is_some()
+}
+
+/// Replaces `x <macro> <args>` with the expanded macro command.
+pub fn expand_args(args: Vec<String>, conf: &Config) -> Result<Vec<String>, String> {
+    let pos = match macro_position(&args) {
+        Some(pos) => pos,
+        None => return Ok(args),
+    };
+    let name = &args[pos + 1];
+    let def = conf.macros.get(name)
+        .ok_or(format!("macro {} is not defined (see config --macro)", name))?;
+    let mut result = args[..pos].to_vec();
+    result.extend(expand(name, def, &args[pos + 2..], conf)?);
+    Ok(result)
+}
+
+pub fn create_macro_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(MACRO_COMMAND)
+        .about("Runs command macro defined in config (config --macro). Without arguments prints defined macros.")
+}
+
+pub fn macro_command(_m: &ArgMatches, conf: Config) -> Result<(), CliError> {
+    if conf.macros.is_empty() {
+        println!("No macros defined. Add one with config --macro '<name>(<params>)=<command>'.");
+    }
+    for (name, def) in &conf.macros {
+        println!("{}({}) = {}", name, def.params.join(","), def.command);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expand_macro() {
+        let (name, def) = parse_definition(
+            r#"topup(dest,amount)=call {wallet} sendTransaction '{"dest":"{dest}","value":{amount},"bounce":false}' --abi msig.abi.json"#
+        ).unwrap();
+        assert_eq!(name, "topup");
+        let mut conf = Config::new();
+        conf.wallet = Some("0:1111".to_owned());
+        conf.macros.insert(name, def.unwrap());
+
+        let expanded = expand_args(args(&["tonos-cli", "-j", "--url", "net", "x", "topup", "0:2222", "1000", "--sign", "k.json"]), &conf).unwrap();
+        assert_eq!(expanded, args(&[
+            "tonos-cli", "-j", "--url", "net",
+            "call", "0:1111", "sendTransaction", r#"{"dest":"0:2222","value":1000,"bounce":false}"#,
+            "--abi", "msig.abi.json", "--sign", "k.json",
+        ]));
+
+        let expanded = expand_args(args(&["tonos-cli", "--passphrase", "x", "x", "topup", "0:2222", "1"]), &conf).unwrap();
+        assert_eq!(expanded[3], "call");
+
+        assert!(expand_args(args(&["tonos-cli", "x", "topup", "0:2222"]), &conf).is_err());
+        assert!(expand_args(args(&["tonos-cli", "x", "unknown"]), &conf).is_err());
+        let unchanged = args(&["tonos-cli", "call", "x", "topup"]);
+        assert_eq!(expand_args(unchanged.clone(), &conf).unwrap(), unchanged);
+    }
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(split_command(r#"a "b c" 'd "e"' f\ g"#).unwrap(), args(&["a", "b c", r#"d "e""#, "f g"]));
+        assert!(split_command("a 'b").is_err());
+    }
+}
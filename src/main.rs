@@ -11,42 +11,107 @@
  * limitations under the License.
  */
 #[macro_use] extern crate clap;
+#[macro_use] extern crate lazy_static;
 #[macro_use] extern crate log;
 #[macro_use] extern crate serde_json;
 
+mod abi;
 mod account;
+mod block;
+mod boc;
+mod cache;
 mod call;
 mod config;
+mod config_vote;
+mod contract_test;
 mod convert;
 mod crypto;
 mod decode;
 mod debot;
+mod disasm;
 mod deploy;
 mod depool;
 mod depool_abi;
+mod error;
+mod events;
+mod exit_codes;
+mod export;
+mod faucet;
+mod fees;
+mod filestore;
 mod genaddr;
 mod getconfig;
+mod graph;
 mod helpers;
+mod history;
+mod i18n;
+mod keys;
+mod known_abi;
+mod macros;
+mod metrics;
+mod monitor;
 mod multisig;
+mod output;
+mod pretty;
+mod relay;
+mod sandbox;
+mod schedule;
 mod sendfile;
+mod sign;
+mod stake;
+mod stateinit;
+mod system;
+mod tvm;
+mod tx;
 mod voting;
+mod uri;
+mod vars;
+mod verify;
+mod wallet;
+mod whois;
 
-use account::get_account;
-use call::{call_contract, call_contract_with_msg, generate_message, parse_params, run_get_method};
+use abi::{create_abi_command, create_fixture_command, abi_command, fixture_command};
+use account::{account_fees, get_account, get_accounts, dump_account, load_account, load_sweep_csv, parse_duration, sweep_accounts, unfreeze_account};
+use call::{call_contract, call_contract_with_msg, generate_message, parse_addr_list, parse_params, run_get_method, run_contract, run_contract_on_boc, run_local_batch};
 use clap::{ArgMatches, SubCommand, Arg, AppSettings};
-use config::{Config, set_config, clear_config};
-use crypto::{generate_mnemonic, extract_pubkey, generate_keypair};
+use config::{Config, HeaderOptions, NetworkPolicy, set_config, clear_config, encrypt_config_file, undo_config, CLEAR_OPTIONS, SET_OPTIONS};
+use contract_test::{create_test_command, test_command};
+use crypto::{create_crypto_command, crypto_command, generate_mnemonic, extract_pubkey, generate_keypair};
 use debot::{create_debot_command, debot_command};
 use decode::{create_decode_command, decode_command};
-use deploy::deploy_contract;
+use deploy::{deploy_contract, Topup};
 use depool::{create_depool_command, depool_command};
-use helpers::{load_ton_address, load_abi, create_client_local};
+use error::{CliError, ErrorKind};
+use helpers::{create_client, load_ton_address, parse_wc};
+use keys::{create_keys_command, keys_command};
+use macros::{create_macro_command, macro_command};
+use filestore::{create_fetch_file_command, create_store_file_command, fetch_file_command, store_file_command};
 use genaddr::generate_address;
-use getconfig::query_global_config;
+use getconfig::{query_global_config, watch_config};
+use events::{create_events_command, events_command};
+use boc::{boc_command, create_boc_command};
+use disasm::{create_disasm_command, disasm_command};
+use faucet::{create_faucet_command, faucet_command};
+use graph::{create_graph_command, graph_command};
+use monitor::{create_monitor_command, monitor_command};
+use history::{create_history_command, history_command};
 use multisig::{create_multisig_command, multisig_command};
+use sandbox::{create_sandbox_command, sandbox_command};
+use schedule::{create_schedule_command, schedule_command, schedule_call, schedule_file_name, parse_send_at};
+use sign::{create_sign_command, sign_command};
+use stake::{create_stake_command, stake_command};
+use stateinit::{create_stateinit_command, stateinit_command};
+use system::{create_system_command, system_command};
+use tvm::{create_tvm_command, tvm_command};
+use block::{block_command, create_block_command};
+use tx::{create_tx_command, tx_command};
+use verify::{create_verify_command, verify_command};
+use config_vote::{create_config_vote_command, config_vote_command};
+use wallet::{create_wallet_command, wallet_command};
+use whois::{create_whois_command, whois_command};
+use fees::{create_fees_command, fees_command};
 use std::{env, path::PathBuf};
 use voting::{create_proposal, decode_proposal, vote};
-use ton_client::abi::{ParamsOfEncodeMessageBody, CallSet};
 
 pub const VERBOSE_MODE: bool = true;
 const DEF_MSG_LIFETIME: u32 = 30;
@@ -85,14 +150,42 @@ fn default_config_name() -> Result<String, String> {
 }
 
 #[tokio::main]
-async fn main() -> Result<(), i32> {
-    main_internal().await.map_err(|err_str| {
-        println!("Error: {}", err_str);
-        1
-    })
+async fn main() {
+    let result = main_internal().await
+        .and_then(|_| vars::check_captured().map_err(CliError::from));
+    if let Err(err) = result {
+        if error::is_json_output() {
+            println!("{}", serde_json::to_string_pretty(&json!({ "error": err.to_json() })).unwrap_or_default());
+        } else {
+            let err_str = err.to_string();
+            println!("{}", i18n::trf("Error: {}", &[&err_str]));
+            if let Some(explanation) = exit_codes::explain_error(&err_str) {
+                println!("{}", i18n::trf("Reason: {}", &[&explanation]));
+            }
+        }
+        std::process::exit(err.exit_code());
+    }
+    let code = error::partial_failure_code();
+    if code != 0 {
+        std::process::exit(code);
+    }
 }
 
-async fn main_internal() -> Result <(), String> {
+async fn main_internal() -> Result <(), CliError> {
+    // values captured with --out are substituted before arguments are parsed
+    let args: Vec<String> = env::args().collect();
+    let args_config_file = vars::config_file_from_args(&args)
+        .or(env::var("TONOSCLI_CONFIG").ok())
+        .unwrap_or(default_config_name()?);
+    let vars_file = vars::vars_file_name(&args_config_file);
+    let args = vars::substitute_args(args, &vars::load_vars(&vars_file)?)?;
+    let args = if macros::is_macro_invocation(&args) {
+        let conf = Config::load(&args_config_file)?.unwrap_or(Config::new());
+        macros::expand_args(args, &conf)?
+    } else {
+        args
+    };
+
     let callex_sub_command = SubCommand::with_name("callex")
         .about("Sends external message to contract with encoded function call (alternative syntax).")
         .setting(AppSettings::AllowMissingPositional)
@@ -139,14 +232,30 @@ async fn main_internal() -> Result <(), String> {
         (@arg NETWORK: -u --url +takes_value "Network to connect.")
         (@arg CONFIG: -c --config +takes_value "Path to tonos-cli configuration file.")
         (@arg JSON: -j --json "Cli prints output in json format.")
+        (@arg PHRASE_FILE: --("phrase-file") +takes_value conflicts_with[PHRASE_FD] "File with seed phrase used wherever keys are specified as \"-\".")
+        (@arg PHRASE_FD: --("phrase-fd") +takes_value "File descriptor to read seed phrase from, used wherever keys are specified as \"-\".")
+        (@arg PASSPHRASE: --passphrase +takes_value "BIP-39 passphrase (\"25th word\") applied to seed phrases wherever keys are derived from them. \"-\" means it's entered without echo.")
+        (@arg FORMAT: --format +takes_value conflicts_with[FORMAT_FILE] "Handlebars template for structured command results, e.g. '{{balance}} {{acc_type}}'.")
+        (@arg FORMAT_FILE: --("format-file") +takes_value "File with handlebars template for structured command results.")
+        (@arg OUT: --out +takes_value +multiple number_of_values(1) "Stores value from structured command result as variable, e.g. addr=$.address. Variables are substituted into arguments of next commands as '${addr}'.")
+        (@arg WITH_PUBKEY: --("with-pubkey") conflicts_with[NO_PUBKEY] "Adds pubkey header to external messages even if the ABI doesn't declare it.")
+        (@arg NO_PUBKEY: --("no-pubkey") "Excludes pubkey header declared in the ABI from external messages.")
+        (@arg WITH_TIME: --("with-time") conflicts_with[NO_TIME] "Adds time header to external messages even if the ABI doesn't declare it.")
+        (@arg NO_TIME: --("no-time") "Excludes time header declared in the ABI from external messages.")
+        (@arg EXPIRE: --expire +takes_value "Unix time used as expire header of external messages (ABI must declare expire header).")
+        (@arg LANG: --lang +takes_value "Language of messages (en, ru). Default is taken from config.")
+        (@arg NET_RETRY: --retry +takes_value "Number of retries of failed network requests and expired messages (overrides config).")
+        (@arg NET_TIMEOUT: --timeout +takes_value "Timeout in ms of network requests and of waiting for messages and records (overrides config).")
+        (@arg OFFLINE: --offline "Forbids network access. Commands which require network fail immediately, while message generation, signing, decoding and local runs keep working.")
         (@subcommand version =>
             (about: "Prints build and version info.")
         )
         (@subcommand convert =>
             (about: "Converts tokens to nanotokens.")
             (@subcommand tokens =>
-                (about: "Converts tokens to nanotokens.")
+                (about: "Converts amount to nanotokens. Amount may have a unit: 1.5 EVER, 10T, 1e9 nano or 50% (percent of balance of --addr).")
                 (@arg AMOUNT: +required +takes_value "Token amount value")
+                (@arg ADDRESS: --addr +takes_value "Account whose balance is used for amounts in percent.")
             )
         )
         (@subcommand genphrase =>
@@ -156,13 +265,13 @@ async fn main_internal() -> Result <(), String> {
         (@subcommand genpubkey =>
             (about: "Generates public key.")
             (author: "TONLabs")
-            (@arg PHRASE: +required +takes_value "Seed phrase (12 words).")
+            (@arg PHRASE: +takes_value "Seed phrase (12 words). If omitted or \"-\", it's read from --phrase-file/--phrase-fd or entered without echo.")
         )
         (@subcommand getkeypair =>
             (about: "Generates keypair from seed phrase and saves it to file.")
             (author: "TONLabs")
             (@arg KEY_FILE: +required +takes_value "Path to file where to store keypair.")
-            (@arg PHRASE: +required +takes_value "Seed phrase (12 words)")
+            (@arg PHRASE: +takes_value "Seed phrase (12 words). If omitted or \"-\", it's read from --phrase-file/--phrase-fd or entered without echo.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
         (@subcommand genaddr =>
@@ -172,7 +281,7 @@ async fn main_internal() -> Result <(), String> {
             (author: "TONLabs")
             (@arg TVC: +required +takes_value "Compiled smart contract (tvc file).")
             (@arg ABI: +required +takes_value "Json file with contract ABI.")
-            (@arg WC: --wc +takes_value "Workchain id used to generate user-friendly addresses (default 0).")
+            (@arg WC: --wc +takes_value "Workchain id of the smart contract, e.g. -1 for masterchain (default: from config, 0).")
             (@arg GENKEY: --genkey +takes_value conflicts_with[SETKEY] "Generates new keypair for the contract and saves it to the file.")
             (@arg SETKEY: --setkey +takes_value conflicts_with[GENKEY] "Loads existing keypair from the file.")
             (@arg DATA: --data +takes_value "Supplies initial data to insert into contract.")
@@ -189,7 +298,11 @@ async fn main_internal() -> Result <(), String> {
             (@arg PARAMS: +required +takes_value "Constructor arguments. Can be passed via a filename.")
             (@arg ABI: --abi +takes_value "Json file with contract ABI.")
             (@arg SIGN: --sign +takes_value "Keypair used to sign 'constructor message'.")
-            (@arg WC: --wc +takes_value "Workchain id of the smart contract (default 0).")
+            (@arg WC: --wc +takes_value "Workchain id of the smart contract, e.g. -1 for masterchain (default: from config, 0).")
+            (@arg TOPUP_FROM: --("topup-from") +takes_value "Multisig wallet which sends the missing amount to the contract address before deploy.")
+            (@arg TOPUP_SIGN: --("topup-sign") +takes_value "Keypair used to sign transfer from the --topup-from wallet (default: --sign keypair).")
+            (@arg DRY_RUN: --("dry-run") "Doesn't deploy the contract. Prints its address, current balance, estimated deploy fees and amount of tokens to send to the address before deploy.")
+            (@arg NETWORKS: --networks +takes_value "Comma-separated list of networks (names from config or urls) where the contract is deployed one by one.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
         (subcommand: callex_sub_command)
@@ -201,8 +314,10 @@ async fn main_internal() -> Result <(), String> {
             (@arg ADDRESS: +required +takes_value "Contract address.")
             (@arg METHOD: +required +takes_value "Name of calling contract method.")
             (@arg PARAMS: +required +takes_value "Arguments for the contract method. Can be passed via a filename.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
+            (@arg ABI: --abi +takes_value "Json file or http(s) URL with contract ABI. Downloaded ABIs are cached.")
             (@arg SIGN: --sign +takes_value "Keypair used to sign message.")
+            (@arg SEND_AT: --("send-at") +takes_value "Unix time when the message must be sent. Message is built and sent at that time (see `schedule` command).")
+            (@arg NETWORKS: --networks +takes_value conflicts_with[SEND_AT] "Comma-separated list of networks (names from config or urls) where the call is executed one by one.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
         (@subcommand send =>
@@ -210,7 +325,7 @@ async fn main_internal() -> Result <(), String> {
             (version: &*format!("{}", env!("CARGO_PKG_VERSION")))
             (author: "TONLabs")
             (@arg MESSAGE: +required +takes_value "Message to send.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
+            (@arg ABI: --abi +takes_value "Json file or http(s) URL with contract ABI. Downloaded ABIs are cached.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
         (@subcommand message =>
@@ -220,7 +335,7 @@ async fn main_internal() -> Result <(), String> {
             (@arg ADDRESS: +required +takes_value "Contract address.")
             (@arg METHOD: +required +takes_value "Name of calling contract method.")
             (@arg PARAMS: +required +takes_value "Arguments for the contract method. Can be passed via a filename.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
+            (@arg ABI: --abi +takes_value "Json file or http(s) URL with contract ABI. Downloaded ABIs are cached.")
             (@arg SIGN: --sign +takes_value "Keypair used to sign message.")
             (@arg LIFETIME: --lifetime +takes_value "Period of time in seconds while message is valid.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
@@ -242,10 +357,30 @@ async fn main_internal() -> Result <(), String> {
             (@arg ADDRESS: +required +takes_value "Contract address.")
             (@arg METHOD: +required +takes_value "Name of calling contract method.")
             (@arg PARAMS: +required +takes_value "Arguments for the contract method. Can be passed via a filename.")
-            (@arg ABI: --abi +takes_value "Json file with contract ABI.")
+            (@arg ABI: --abi +takes_value "Json file or http(s) URL with contract ABI. Downloaded ABIs are cached.")
+            (@arg RAW: --raw "Prints result as returned by SDK, without formatting values according to ABI types.")
+            (@arg BOC: --boc +takes_value "Runs the method against account state snapshot from the file (see `account dump`) instead of the current network state.")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
         )
+        (@subcommand runx =>
+            (@setting AllowLeadingHyphen)
+            (about: "Runs contract getter locally on a list of contracts concurrently. Prints json map of address to result, a summary of failures goes to stderr. Exits with a non-zero code if the getter failed on any contract.")
+            (@arg ADDRESS: --addr +takes_value conflicts_with[ADDR_FILE] "Contract address.")
+            (@arg ADDR_FILE: --("addr-file") +takes_value "File with contract addresses, one per line (# starts a comment).")
+            (@arg METHOD: -m --method +required +takes_value "Name of the getter.")
+            (@arg PARAMS: +takes_value "Arguments for the getter (default: {}). Can be passed via a filename.")
+            (@arg ABI: --abi +takes_value "Json file or http(s) URL with contract ABI. Downloaded ABIs are cached.")
+            (@arg CONCURRENCY: --concurrency +takes_value "Number of contracts processed at once (default: 16).")
+            (@arg RAW: --raw "Prints results as returned by SDK, without formatting values according to ABI types.")
+        )
         (subcommand: runget_sub_command)
+        (@subcommand ("run-raw") =>
+            (@setting AllowLeadingHyphen)
+            (about: "Runs contract get-method without ABI, passing raw TVM stack. Useful for elector, config and other FunC contracts.")
+            (@arg ADDRESS: +required +takes_value "Contract address.")
+            (@arg METHOD: +required +takes_value "Name or numeric id (decimal or 0x-prefixed hex) of the get-method.")
+            (@arg STACK: --stack +takes_value "Input TVM stack as json array, e.g. '[\"0x1\", 2]'.")
+        )
         (@subcommand config =>
             (@setting AllowLeadingHyphen)
             (about: "Saves certain default values for options into config file.")
@@ -256,11 +391,33 @@ async fn main_internal() -> Result <(), String> {
             (@arg KEYS: --keys +takes_value "File with keypair.")
             (@arg ADDR: --addr +takes_value "Contract address.")
             (@arg WALLET: --wallet +takes_value "Multisig wallet address. Used in commands which send internal messages through multisig wallets.")
-            (@arg WC: --wc +takes_value "Workchain id.")
+            (@arg WC: --wc +takes_value "Default workchain id used by genaddr and deploy (-1 for masterchain).")
             (@arg RETRIES: --retries +takes_value "Number of attempts to call smart contract function if previous attempt was unsuccessful.")
-            (@arg TIMEOUT: --timeout +takes_value "Contract call timeout in ms.")
+            (@arg TIMEOUT: --timeout +takes_value "Message expiration timeout in ms. Message which is not processed until expiration is resent (see --retries).")
+            (@arg PROCESSING_TIMEOUT: --processing_timeout +takes_value "Time in ms to wait for processing of a message without expiration time.")
+            (@arg WAIT_FOR_TIMEOUT: --wait_for_timeout +takes_value "Default time in ms to wait for a new record in blockchain (event, balance change, etc.).")
+            (@arg QUERY_TIMEOUT: --query_timeout +takes_value "Timeout in ms of a single query when commands page through many records (0 - no limit).")
+            (@arg CLOCK_COMPENSATION: --clock_compensation +takes_value "Shift time and expiration of messages by the difference between local clock and network time if it exceeds 15 sec (true/false).")
+            (@arg STRICT_UNITS: --strict_units +takes_value "Requires a unit in every amount (EVER, T, nano or % of balance) and rejects amounts without one (true/false).")
             (@arg LIST: --list conflicts_with[URL ABI KEYS ADDR RETRIES TIMEOUT WC] "Prints all config parameters.")
             (@arg DEPOOL_FEE: --depool_fee +takes_value "Value added to message sent to depool to cover it's fees (change will be returned).")
+            (@arg ALIAS: --alias +takes_value "Adds address alias as <name>=<address> (empty address removes alias). Aliases can be used in debot address input.")
+            (@arg RATE_LIMIT: --rate_limit +takes_value "Max number of queries per second when commands page through many records (0 - unlimited).")
+            (@arg CONCURRENCY: --concurrency +takes_value "Max number of simultaneous queries when commands page through many records.")
+            (@arg CACHE_DIR: --cache_dir +takes_value "Directory for cached results of immutable queries (blocks, transactions, etc.).")
+            (@arg CACHE_TTL: --cache_ttl +takes_value "Lifetime of cached query results in seconds (0 disables cache).")
+            (@arg CACHE_SIZE: --cache_size +takes_value "Max size of query cache in megabytes.")
+            (@arg HEADER: --header +takes_value "Adds http header for the endpoint as \"<name>: <value>\" (empty value removes header). GraphQL endpoints accept only Authorization and X-API-Key headers, relay urls accept any. Value may refer environment variables as ${VAR}.")
+            (@arg LANG: --lang +takes_value "Language of messages (en, ru).")
+            (@arg NETWORK: --network +takes_value "Adds named network as <name>=<url> (empty url removes it). Names are used in --networks option of call and deploy.")
+            (@arg PRICE_URL: --price_url +takes_value "Url of token price history used by account export, may contain {date} (dd-mm-yyyy), {iso_date}, {timestamp} and {currency}.")
+            (@arg PRICE_PATH: --price_path +takes_value "Json path to the price in the response of price_url, e.g. market_data.current_price.{currency}.")
+            (@arg DRY_RUN: --("dry-run") "Shows changes of the config file without saving them.")
+            (@arg FAUCET_URL: --faucet_url +takes_value "Test tokens faucet of the current endpoint used by faucet request (empty url removes it).")
+            (@arg FAUCET_KEY: --faucet_key +takes_value "Api key of the faucet of the current endpoint. Value may refer environment variables as ${VAR}.")
+            (@arg MACRO: --macro +takes_value "Adds command macro as <name>(<params>)=<command> (empty command removes it). Command may contain {param}, {1}, {2}... and {wallet}, {addr}, {keys}, {abi}, {url}, {wc}. Invoked as: tonos-cli x <name> <args>.")
+            (@arg RELAY_URL: --relay_url +takes_value "REST endpoint which broadcasts messages (HTTP POST of {\"id\", \"boc\"}) instead of GraphQL endpoint. Http headers for it are taken from endpoint_headers of this url.")
+            (@arg DEBOT_SESSION_LIMIT: --debot_session_limit +takes_value "Max value in tokens debots may spend from the wallet during one browser run before every further signature needs explicit confirmation.")
             (@subcommand clear =>
                 (@setting AllowLeadingHyphen)
                 (about: "Resets certain default values for options in the config file. Resets all values if used without options.")
@@ -271,17 +428,92 @@ async fn main_internal() -> Result <(), String> {
                 (@arg WALLET: --wallet "Multisig wallet address. Used in commands which send internal messages through multisig wallets.")
                 (@arg WC: --wc "Workchain id.")
                 (@arg RETRIES: --retries "Number of attempts to call smart contract function if previous attempt was unsuccessful.")
-                (@arg TIMEOUT: --timeout "Contract call timeout in ms.")
+                (@arg TIMEOUT: --timeout "All timeouts (timeout, processing_timeout, wait_for_timeout, query_timeout).")
                 (@arg DEPOOL_FEE: --depool_fee "Value added to message sent to depool to cover it's fees (change will be returned).")
+                (@arg ALIAS: --alias "Address aliases.")
+                (@arg RATE_LIMIT: --rate_limit "Max number of queries per second in bulk query mode.")
+                (@arg CONCURRENCY: --concurrency "Max number of simultaneous queries in bulk query mode.")
+                (@arg CACHE: --cache "Query cache settings.")
+                (@arg CLOCK_COMPENSATION: --clock_compensation "Clock skew compensation.")
+                (@arg STRICT_UNITS: --strict_units "Strict amount units mode.")
+                (@arg HEADER: --header "Http headers of the current endpoint.")
+                (@arg NETWORK: --network "Named networks.")
+                (@arg DEBOT_SESSION_LIMIT: --debot_session_limit "Debot session spending limit.")
+                (@arg PRICE_SOURCE: --price_source "Token price source (price_url and price_path).")
+                (@arg RELAY_URL: --relay_url "Message relay endpoint.")
+                (@arg MACRO: --macro "Command macros.")
+                (@arg FAUCET: --faucet "Faucet of the current endpoint.")
+                (@arg DRY_RUN: --("dry-run") "Shows changes of the config file without saving them.")
+                (@arg LANG: --lang "Language of messages.")
+            )
+            (@subcommand undo =>
+                (about: "Restores the config file version saved before the last change.")
+            )
+            (@subcommand encrypt =>
+                (about: "Encrypts the config file. Encryption key is stored in OS keychain, config is decrypted transparently at startup.")
+            )
+            (@subcommand decrypt =>
+                (about: "Stores the config file unencrypted and removes its key from OS keychain.")
             )
         )
         (@subcommand account =>
             (@setting AllowLeadingHyphen)
-            (about: "Gets account information.")
+            (about: "Gets account information. If several addresses are given, prints a table of their balances.")
             (version: &*format!("{}", env!("CARGO_PKG_VERSION")))
             (author: "TONLabs")
-            (@arg ADDRESS: +required +takes_value "Smart contract address.")
+            (@setting SubcommandsNegateReqs)
+            (@arg ADDRESS: +required +takes_value ... "Smart contract address(es).")
             (@arg VERBOSE: -v --verbose "Prints additional information about command execution.")
+            (@subcommand dump =>
+                (about: "Saves account state snapshot (account boc) to the file.")
+                (@arg ADDRESS: +required +takes_value "Smart contract address.")
+                (@arg OUTPUT: -o --out +required +takes_value "Path to file where to store account state.")
+            )
+            (@subcommand load =>
+                (about: "Prints information about account state snapshot from the file. The state is not uploaded to the network, run contract methods against it locally with `run --boc`.")
+                (@arg BOC: +required +takes_value "Path to file with account state.")
+            )
+            (@subcommand fees =>
+                (about: "Projects storage fees of the account from its size and current config prices and warns if the account would be frozen within the horizon.")
+                (@arg ADDRESS: +required +takes_value "Smart contract address.")
+                (@arg HORIZON: --horizon +takes_value "Projection period, e.g. 365d, 12h, 30m or seconds (default: 365d).")
+            )
+            (@subcommand unfreeze =>
+                (about: "Pays off storage debt of the frozen account from multisig wallet. The transfer carries original state init of the account, which unfreezes it. Attaching state init requires a wallet with stateInit parameter of submitTransaction (Multisig 2.0).")
+                (@arg ADDRESS: +required +takes_value "Frozen account address.")
+                (@arg FROM: --from +takes_value "Multisig wallet which pays the debt (default: wallet from config).")
+                (@arg SIGN: --sign +takes_value "Keypair of the wallet custodian (default: keys from config).")
+                (@arg TVC: --tvc +takes_value "File with original state init of the account. Without it only the debt is paid.")
+            )
+            (@subcommand export =>
+                (about: "Exports transactions of the account in formats accepted by accounting tools. Inbound and outbound transfers are separate lines, transaction fee is attached to the outbound line. Fiat value is taken from the price source set in config (price_url, price_path).")
+                (@arg ADDRESS: +required +takes_value "Smart contract address.")
+                (@arg FORMAT: --format +takes_value possible_value[csv koinly] "Export format (default: csv).")
+                (@arg SINCE: --since +takes_value "Exports transactions since the time (unix time or yyyy-mm-dd).")
+                (@arg UNTIL: --until +takes_value "Exports transactions before the time (unix time or yyyy-mm-dd).")
+                (@arg FIAT: --fiat +takes_value "Fiat currency of the valuation (default: USD).")
+                (@arg OUTPUT: -o --output +takes_value "Output file (default: stdout).")
+                (@arg RESUME: --resume +takes_value "Checkpoint file. Export position is saved to it after every page, if the file exists the export continues from the saved position and appends to the output file. The file is removed when the export is complete.")
+            )
+            (@subcommand sweep =>
+                (@setting AllowLeadingHyphen)
+                (about: "Sends all remaining balance of the accounts to the destination and deletes them (sendTransaction with flags 160 or selfdestruct function).")
+                (@arg ADDRESS: +takes_value ... "Addresses of accounts to sweep.")
+                (@arg TO: --to +required +takes_value "Destination address.")
+                (@arg SIGN: --sign +takes_value "Keypair used to sign messages (default: keys from config).")
+                (@arg ABI: --abi +takes_value "Json file with ABI of the accounts (default: multisig wallet ABI).")
+                (@arg CSV: --csv +takes_value "CSV file with lines <address>[,<keypair>] of accounts to sweep.")
+            )
+        )
+        (@subcommand transfer =>
+            (@setting AllowLeadingHyphen)
+            (about: "Transfers tokens from multisignature wallet with optional text comment.")
+            (@arg DEST: +required +takes_value "Recipient address or ton://transfer/<address>?amount=<nanotokens>&text=<comment> link.")
+            (@arg VALUE: +takes_value "Amount of tokens to transfer (required unless given in the link).")
+            (@arg FROM: --from +takes_value "Wallet address (default: wallet address from config).")
+            (@arg COMMENT: --comment +takes_value "Text comment attached to the transfer.")
+            (@arg SIGN: --sign +takes_value "Seed phrase or path to keypair file.")
+            (@arg LINK: --link "Prints ton:// link and QR code of the transfer instead of sending it.")
         )
         (@subcommand proposal =>
             (about: "Submits proposal transaction in multisignature wallet with text comment.")
@@ -307,13 +539,48 @@ async fn main_internal() -> Result <(), String> {
                 (@arg ID: +required +takes_value "Proposal transaction id.")
             )
         )
+        (subcommand: create_abi_command())
+        (subcommand: create_fixture_command())
+        (subcommand: create_crypto_command())
+        (subcommand: create_keys_command())
+        (subcommand: create_sign_command())
+        (subcommand: create_schedule_command())
+        (subcommand: create_sandbox_command())
+        (subcommand: create_test_command())
+        (subcommand: create_history_command())
+        (subcommand: create_wallet_command())
+        (subcommand: create_faucet_command())
+        (subcommand: create_monitor_command())
+        (subcommand: create_events_command())
+        (subcommand: create_stake_command())
+        (subcommand: create_stateinit_command())
+        (subcommand: create_boc_command())
+        (subcommand: create_disasm_command())
+        (subcommand: create_tvm_command())
+        (subcommand: create_block_command())
+        (subcommand: create_whois_command())
+        (subcommand: create_fees_command())
+        (subcommand: create_tx_command())
+        (subcommand: create_graph_command())
+        (subcommand: create_macro_command())
+        (subcommand: create_store_file_command())
+        (subcommand: create_fetch_file_command())
+        (subcommand: create_verify_command())
+        (subcommand: create_system_command())
+        (subcommand: create_config_vote_command())
         (subcommand: create_multisig_command())
         (subcommand: create_depool_command())
         (subcommand: create_decode_command())
         (subcommand: create_debot_command())
         (@subcommand getconfig =>
             (about: "Reads global configuration parameter with defined index.")
+            (@setting SubcommandsNegateReqs)
+            (@setting ArgsNegateSubcommands)
             (@arg INDEX: +required +takes_value "Parameter index.")
+            (@subcommand watch =>
+                (about: "Subscribes to key blocks and prints changed fields of config parameters (old -> new) whenever a key block changes them.")
+                (@arg PARAM: --param +takes_value "Watch only parameter with this index (e.g. 20 for masterchain gas prices).")
+            )
         )
         (@subcommand nodeid =>
             (about: "Calculates node ID from validator public key")
@@ -324,26 +591,57 @@ async fn main_internal() -> Result <(), String> {
             (about: "Sends boc file with external inbound message to account.")
             (@arg BOC: +required +takes_value "Boc file with message.")
         )
+        (@subcommand ("send-boc") =>
+            (about: "Broadcasts externally built message from file (binary boc, base64 or hex) and optionally waits for the transaction.")
+            (@arg BOC: +required +takes_value "File with message.")
+            (@arg WAIT: --wait "Waits for the transaction and prints its id, fees and decoded output.")
+            (@arg ABI: --abi +takes_value "Json file with ABI of the destination contract used to decode output.")
+        )
         (@setting SubcommandRequired)
-    ).get_matches();
+    ).get_matches_from(args);
 
     let is_json = matches.is_present("JSON");
+    error::set_json_output(is_json);
 
     let config_file = matches.value_of("CONFIG").map(|v| v.to_string())
         .or(env::var("TONOSCLI_CONFIG").ok())
         .unwrap_or(default_config_name()?);
 
-    let mut conf = match Config::from_file(&config_file) {
-        Some(c) => {
-            if !is_json { println!("Config: {}", config_file); }
-            c
-        },
-        None => {
-            if !is_json { println!("Config: default"); }
-            Config::new()
+    let (mut conf, loaded) = match Config::load(&config_file) {
+        Ok(Some(c)) => (c, true),
+        Ok(None) => (Config::new(), false),
+        Err(e) if can_repair_config(&matches) => {
+            eprintln!("Warning: {}, default config is used", e);
+            (Config::new(), false)
         },
+        Err(e) => return Err(e),
     };
+    i18n::set_lang(matches.value_of("LANG").or(conf.lang.as_deref()))?;
+    if !is_json {
+        if loaded {
+            println!("{}", i18n::trf("Config: {}", &[&config_file]));
+        } else {
+            println!("{}", i18n::tr("Config: default"));
+        }
+    }
     conf.is_json = is_json;
+    conf.offline = matches.is_present("OFFLINE");
+    conf.header_options = header_options(&matches)?;
+    conf.network_policy = network_policy(&matches)?;
+    helpers::set_network_policy(conf.network_policy);
+    crypto::set_secret_phrase_source(matches.value_of("PHRASE_FILE"), matches.value_of("PHRASE_FD"))?;
+    crypto::set_mnemonic_passphrase(matches.value_of("PASSPHRASE"));
+    relay::init(&conf)?;
+    convert::set_strict_units(conf.strict_units);
+    output::set_template(matches.value_of("FORMAT"), matches.value_of("FORMAT_FILE"))?;
+    vars::set_outputs(&vars_file, matches.values_of("OUT").map(|v| v.collect()).unwrap_or_default())?;
+    history::set_history_file(&history::history_file_name(&config_file));
+    let abi_path = matches.subcommand().1
+        .and_then(|m| m.value_of("ABI").map(|s| s.to_owned()))
+        .or(conf.abi_path.clone());
+    if let Some(abi_path) = abi_path {
+        exit_codes::load_error_table(&abi_path)?;
+    }
 
     if let Some(url) = matches.value_of("NETWORK") {
         conf.url = url.to_string();
@@ -351,31 +649,53 @@ async fn main_internal() -> Result <(), String> {
 
     if let Some(m) = matches.subcommand_matches("convert") {
         if let Some(m) = m.subcommand_matches("tokens") {
-            return convert_tokens(m);
+            return convert_tokens(m, conf).await;
         }
     }
     if let Some(m) = matches.subcommand_matches("callex") {
         return callex_command(m, conf).await;
     }
     if let Some(m) = matches.subcommand_matches("call") {
-        return call_command(m, conf, CallType::Call).await;
+        if let Some(networks) = m.value_of("NETWORKS") {
+            let mut results = vec![];
+            for (name, conf) in conf.for_networks(networks)? {
+                println!("Network {} ({}):", name, conf.url);
+                results.push((name, call_command(m, conf, CallType::Call, &config_file).await));
+            }
+            return report_networks(results);
+        }
+        return call_command(m, conf, CallType::Call, &config_file).await;
     }
     if let Some(m) = matches.subcommand_matches("run") {
-        return call_command(m, conf, CallType::Run).await;
+        return call_command(m, conf, CallType::Run, &config_file).await;
+    }
+    if let Some(m) = matches.subcommand_matches("runx") {
+        return runx_command(m, conf).await;
     }
     if let Some(m) = matches.subcommand_matches("runget") {
         return runget_command(m, conf).await;
     }
+    if let Some(m) = matches.subcommand_matches("run-raw") {
+        return run_raw_command(m, conf).await;
+    }
     if let Some(m) = matches.subcommand_matches("body") {
         return body_command(m, conf).await;
     }
     if let Some(m) = matches.subcommand_matches("message") {
-        return call_command(m, conf, CallType::Msg).await;
+        return call_command(m, conf, CallType::Msg, &config_file).await;
     }
     if let Some(m) = matches.subcommand_matches("send") {
         return send_command(m, conf).await;
     }
     if let Some(m) = matches.subcommand_matches("deploy") {
+        if let Some(networks) = m.value_of("NETWORKS") {
+            let mut results = vec![];
+            for (name, conf) in conf.for_networks(networks)? {
+                println!("Network {} ({}):", name, conf.url);
+                results.push((name, deploy_command(m, conf).await));
+            }
+            return report_networks(results);
+        }
         return deploy_command(m, conf).await;
     }
     if let Some(m) = matches.subcommand_matches("config") {
@@ -396,6 +716,9 @@ async fn main_internal() -> Result <(), String> {
     if let Some(m) = matches.subcommand_matches("genpubkey") {
         return genpubkey_command(m, conf);
     }
+    if let Some(m) = matches.subcommand_matches("transfer") {
+        return transfer_command(m, conf).await;
+    }
     if let Some(m) = matches.subcommand_matches("proposal") {
         if let Some(m) = m.subcommand_matches("create") {
             return proposal_create_command(m, conf).await;
@@ -407,8 +730,95 @@ async fn main_internal() -> Result <(), String> {
             return proposal_decode_command(m, conf).await;
         }
     }
+    if let Some(m) = matches.subcommand_matches("crypto") {
+        return crypto_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("keys") {
+        return keys_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("sign") {
+        return sign_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("config-vote") {
+        return config_vote_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("stake") {
+        return stake_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("stateinit") {
+        return stateinit_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("tx") {
+        return tx_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("boc") {
+        return boc_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("disasm") {
+        return disasm_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("tvm") {
+        return tvm_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("block") {
+        return block_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("whois") {
+        return whois_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("fees") {
+        return fees_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("faucet") {
+        return faucet_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches(macros::MACRO_COMMAND) {
+        return macro_command(m, conf);
+    }
+    if let Some(m) = matches.subcommand_matches("store-file") {
+        return store_file_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("fetch-file") {
+        return fetch_file_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("graph") {
+        return graph_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("verify") {
+        return verify_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("sandbox") {
+        return sandbox_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("test") {
+        return test_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("system") {
+        return system_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("abi") {
+        return abi_command(m).await;
+    }
+    if let Some(m) = matches.subcommand_matches("fixture") {
+        return fixture_command(m).await;
+    }
+    if let Some(m) = matches.subcommand_matches("events") {
+        return events_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("monitor") {
+        return monitor_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("wallet") {
+        return wallet_command(m, conf).await;
+    }
+    if let Some(m) = matches.subcommand_matches("history") {
+        return history_command(m, &config_file).await;
+    }
+    if let Some(m) = matches.subcommand_matches("schedule") {
+        return schedule_command(m, conf, &config_file).await;
+    }
     if let Some(m) = matches.subcommand_matches("multisig") {
-        return multisig_command(m, conf).await;
+        return multisig_command(m, conf, &config_file).await;
     }
     if let Some(m) = matches.subcommand_matches("depool") {
         return depool_command(m, conf).await;
@@ -422,11 +832,14 @@ async fn main_internal() -> Result <(), String> {
     if let Some(m) = matches.subcommand_matches("sendfile") {
         return sendfile_command(m, conf).await;
     }
+    if let Some(m) = matches.subcommand_matches("send-boc") {
+        return send_boc_command(m, conf).await;
+    }
     if let Some(m) = matches.subcommand_matches("decode") {
         return decode_command(m, conf).await;
     }
     if let Some(m) = matches.subcommand_matches("debot") {
-        return debot_command(m, conf).await;
+        return debot_command(m, conf, &config_file).await;
     }
     if let Some(_) = matches.subcommand_matches("version") {
         println!(
@@ -439,50 +852,65 @@ async fn main_internal() -> Result <(), String> {
         );
         return Ok(());
     }
-    Err("invalid arguments".to_string())
+    Err(CliError::new(ErrorKind::InvalidInput, "invalid arguments"))
 }
 
-fn convert_tokens(matches: &ArgMatches) -> Result<(), String> {
+async fn convert_tokens(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let amount = matches.value_of("AMOUNT").unwrap();
-    let result = convert::convert_token(amount)?;
+    let result = match convert::parse_amount(amount)? {
+        convert::Amount::Nano(nano) => nano,
+        percent => {
+            let address = matches.value_of("ADDRESS")
+                .ok_or(CliError::new(ErrorKind::InvalidInput, "percent of balance requires account address (--addr)."))?;
+            let address = load_ton_address(address, &config)?;
+            let balance = wallet::account_balance(create_client(&config)?, &address).await?;
+            percent.to_nano(balance)
+        },
+    };
     println!("{}", result);
     Ok(())
 }
 
-fn genphrase_command(_matches: &ArgMatches, _config: Config) -> Result<(), String> {
-    generate_mnemonic()
+fn genphrase_command(_matches: &ArgMatches, _config: Config) -> Result<(), CliError> {
+    generate_mnemonic().map_err(CliError::from)
+}
+
+fn phrase_arg(matches: &ArgMatches) -> Result<String, String> {
+    match matches.value_of("PHRASE") {
+        Some(phrase) if phrase != crypto::SECRET_PHRASE_INPUT => Ok(phrase.to_owned()),
+        _ => crypto::secret_phrase(),
+    }
 }
 
-fn genpubkey_command(matches: &ArgMatches, _config: Config) -> Result<(), String> {
-    let mnemonic = matches.value_of("PHRASE").unwrap();
-    extract_pubkey(mnemonic)
+fn genpubkey_command(matches: &ArgMatches, _config: Config) -> Result<(), CliError> {
+    let mnemonic = phrase_arg(matches)?;
+    extract_pubkey(&mnemonic).map_err(CliError::from)
 }
 
-fn getkeypair_command(matches: &ArgMatches, _config: Config) -> Result<(), String> {
+fn getkeypair_command(matches: &ArgMatches, _config: Config) -> Result<(), CliError> {
     let key_file = matches.value_of("KEY_FILE");
-    let phrase = matches.value_of("PHRASE");
-    print_args!(matches, key_file, phrase);
-    generate_keypair(key_file.unwrap(), phrase.unwrap())
+    print_args!(matches, key_file);
+    let phrase = phrase_arg(matches)?;
+    generate_keypair(key_file.unwrap(), &phrase).map_err(CliError::from)
 }
 
-async fn send_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn send_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let message = matches.value_of("MESSAGE");
     let abi = Some(
         matches.value_of("ABI")
             .map(|s| s.to_string())
             .or(config.abi_path.clone())
-            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "ABI file not defined. Supply it in config file or command line."))?
     );
 
     print_args!(matches, message, abi);
 
-    let abi = std::fs::read_to_string(abi.unwrap())
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+    let abi = cache::read_abi(&config, &abi.unwrap()).await?;
 
     call_contract_with_msg(config, message.unwrap().to_owned(), abi).await
 }
 
-fn load_params(params: &str) -> Result<String, String> {
+pub fn load_params(params: &str) -> Result<String, String> {
     Ok(if params.find('{').is_none() {
         std::fs::read_to_string(params)
             .map_err(|e| format!("failed to load params from file: {}", e))?
@@ -491,7 +919,7 @@ fn load_params(params: &str) -> Result<String, String> {
     })
 }
 
-async fn body_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn body_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let method = matches.value_of("METHOD");
     let params = matches.value_of("PARAMS");
     let output = matches.value_of("OUTPUT");
@@ -499,37 +927,22 @@ async fn body_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), St
         matches.value_of("ABI")
             .map(|s| s.to_string())
             .or(config.abi_path.clone())
-            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "ABI file not defined. Supply it in config file or command line."))?
     );
     let params = Some(load_params(params.unwrap())?);
     print_args!(matches, method, params, abi, output);
 
-    let params = serde_json::from_str(&params.unwrap())
-        .map_err(|e| format!("arguments are not in json format: {}", e))?;
-
     let abi = std::fs::read_to_string(abi.unwrap())
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read ABI file: {}", e.to_string())))?;
 
-
-    let client = create_client_local()?;
-    let body = ton_client::abi::encode_message_body(
-        client.clone(),
-        ParamsOfEncodeMessageBody {
-            abi: load_abi(&abi)?,
-            call_set: CallSet::some_with_function_and_input(&method.unwrap(), params).unwrap(),
-            is_internal: true,
-            ..Default::default()
-        },
-    ).await
-    .map_err(|e| format!("failed to encode body: {}", e))
-    .map(|r| r.body)?;
+    let body = call::encode_body(&abi, method.unwrap(), &params.unwrap()).await?;
 
     println!("Message body: {}", body);
 
     Ok(())
 }
 
-async fn call_command(matches: &ArgMatches<'_>, config: Config, call: CallType) -> Result<(), String> {
+async fn call_command(matches: &ArgMatches<'_>, config: Config, call: CallType, config_file: &str) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let method = matches.value_of("METHOD");
     let params = matches.value_of("PARAMS");
@@ -541,7 +954,7 @@ async fn call_command(matches: &ArgMatches<'_>, config: Config, call: CallType)
         matches.value_of("ABI")
             .map(|s| s.to_string())
             .or(config.abi_path.clone())
-            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "ABI file not defined. Supply it in config file or command line."))?
     );
 
     let keys = match call {
@@ -558,13 +971,44 @@ async fn call_command(matches: &ArgMatches<'_>, config: Config, call: CallType)
     let params = Some(load_params(params.unwrap())?);
     print_args!(matches, address, method, params, abi, keys, lifetime, output);
 
-    let abi = std::fs::read_to_string(abi.unwrap())
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+    let abi = cache::read_abi(&config, &abi.unwrap()).await?;
     let address = load_ton_address(address.unwrap(), &config)?;
 
-    match call {
-        CallType::Call | CallType::Run => {
-            let local = if let CallType::Call = call { false } else { true };
+    let result = match call {
+        CallType::Run if matches.is_present("BOC") => {
+            run_contract_on_boc(
+                address.as_str(),
+                abi,
+                method.unwrap(),
+                &params.unwrap(),
+                matches.value_of("BOC").unwrap(),
+                raw,
+            ).await
+        },
+        CallType::Run => {
+            run_contract(
+                config,
+                address.as_str(),
+                abi,
+                method.unwrap(),
+                &params.unwrap(),
+                raw,
+            ).await
+        },
+        CallType::Call if matches.is_present("SEND_AT") => {
+            let send_at = parse_send_at(matches.value_of("SEND_AT").unwrap())?;
+            schedule_call(
+                config,
+                &schedule_file_name(config_file),
+                send_at,
+                address.as_str(),
+                abi,
+                method.unwrap(),
+                &params.unwrap(),
+                keys,
+            ).await.map_err(CliError::from)
+        },
+        CallType::Call => {
             call_contract(
                 config,
                 address.as_str(),
@@ -572,7 +1016,7 @@ async fn call_command(matches: &ArgMatches<'_>, config: Config, call: CallType)
                 method.unwrap(),
                 &params.unwrap(),
                 keys,
-                local
+                false
             ).await
         },
         CallType::Msg => {
@@ -595,25 +1039,25 @@ async fn call_command(matches: &ArgMatches<'_>, config: Config, call: CallType)
                 output,
             ).await
         },
-    }
+    };
+    result
 }
 
-async fn callex_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn callex_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let method = matches.value_of("METHOD");
     let address = Some(
         matches.value_of("ADDRESS")
             .map(|s| s.to_string())
             .or(config.addr.clone())
-            .ok_or("ADDRESS is not defined. Supply it in config file or in command line.".to_string())?
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "ADDRESS is not defined. Supply it in config file or in command line."))?
     );
     let abi = Some(
         matches.value_of("ABI")
         .map(|s| s.to_string())
         .or(config.abi_path.clone())
-        .ok_or("ABI is not defined. Supply it in config file or in command line.".to_string())?
+        .ok_or(CliError::new(ErrorKind::InvalidInput, "ABI is not defined. Supply it in config file or in command line."))?
     );
-    let loaded_abi = std::fs::read_to_string(abi.as_ref().unwrap())
-        .map_err(|e| format!("failed to read ABI file: {}", e.to_string()))?;
+    let loaded_abi = cache::read_abi(&config, abi.as_ref().unwrap()).await?;
     let params = Some(parse_params(
         matches.values_of("PARAMS").unwrap().collect::<Vec<_>>(), &loaded_abi, method.clone().unwrap()
     )?);
@@ -635,7 +1079,62 @@ async fn callex_command(matches: &ArgMatches<'_>, config: Config) -> Result<(),
     ).await
 }
 
-async fn runget_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn runx_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS");
+    let addr_file = matches.value_of("ADDR_FILE");
+    let method = matches.value_of("METHOD").unwrap();
+    let params = load_params(matches.value_of("PARAMS").unwrap_or("{}"))?;
+    let abi = matches.value_of("ABI")
+        .map(|s| s.to_string())
+        .or(config.abi_path.clone())
+        .ok_or(CliError::new(ErrorKind::InvalidInput, "ABI file not defined. Supply it in config file or command line."))?;
+    let concurrency = matches.value_of("CONCURRENCY")
+        .map(|s| usize::from_str_radix(s, 10).map_err(|e| format!("failed to parse concurrency: {}", e)))
+        .transpose()?
+        .unwrap_or(16);
+    print_args!(matches, address, addr_file, method, params, abi);
+
+    let addrs = match (address, addr_file) {
+        (Some(address), _) => vec![address.to_owned()],
+        (None, Some(file)) => parse_addr_list(
+            &std::fs::read_to_string(file).map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read {}: {}", file, e)))?
+        ),
+        (None, None) => return Err(CliError::new(ErrorKind::InvalidInput, "either --addr or --addr-file must be specified")),
+    };
+    let mut addrs = addrs.iter()
+        .map(|addr| load_ton_address(addr, &config))
+        .collect::<Result<Vec<_>, _>>()?;
+    addrs.sort();
+    addrs.dedup();
+    let abi_text = cache::read_abi(&config, &abi).await?;
+
+    let ton = create_client(&config)?;
+    let results = run_local_batch(ton, &addrs, &abi_text, method, &params, concurrency).await?;
+    let mut output = serde_json::Map::new();
+    let mut failed = vec![];
+    for (addr, result) in results {
+        let value = match result {
+            Ok(value) if matches.is_present("RAW") => value,
+            Ok(value) => pretty::format_output(&abi_text, method, &value),
+            Err(e) => {
+                failed.push(addr.clone());
+                json!({ "error": e })
+            },
+        };
+        output.insert(addr, value);
+    }
+    println!("{}", serde_json::to_string_pretty(&output).unwrap_or_default());
+    eprintln!("Succeeded: {}, failed: {}", output.len() - failed.len(), failed.len());
+    for addr in &failed {
+        eprintln!("  {}: {}", addr, output[addr]["error"].as_str().unwrap_or(""));
+    }
+    if !failed.is_empty() {
+        error::set_partial_failure(ErrorKind::Contract);
+    }
+    Ok(())
+}
+
+async fn runget_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let method = matches.value_of("METHOD");
     let params = matches.values_of("PARAMS");
@@ -647,7 +1146,24 @@ async fn runget_command(matches: &ArgMatches<'_>, config: Config) -> Result<(),
     run_get_method(config, address.as_str(), method.unwrap(), params).await
 }
 
-async fn deploy_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn run_raw_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let address = matches.value_of("ADDRESS");
+    let method = matches.value_of("METHOD");
+    let stack = matches.value_of("STACK");
+    print_args!(matches, address, method, stack);
+    let method = call::resolve_get_method(method.unwrap())?;
+    if let Some(stack) = stack {
+        let stack: serde_json::Value = serde_json::from_str(stack)
+            .map_err(|e| format!("stack is not in json format: {}", e))?;
+        if !stack.is_array() {
+            return Err(CliError::new(ErrorKind::InvalidInput, "stack must be a json array"));
+        }
+    }
+    let address = load_ton_address(address.unwrap(), &config)?;
+    run_get_method(config, address.as_str(), &method, stack.map(|s| s.to_owned())).await
+}
+
+async fn deploy_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let tvc = matches.value_of("TVC");
     let params = matches.value_of("PARAMS");
     let wc = matches.value_of("WC");
@@ -655,51 +1171,115 @@ async fn deploy_command(matches: &ArgMatches<'_>, config: Config) -> Result<(),
         matches.value_of("ABI")
             .map(|s| s.to_string())
             .or(config.abi_path.clone())
-            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "ABI file not defined. Supply it in config file or command line."))?
     );
     let keys = Some(
         matches.value_of("SIGN")
             .map(|s| s.to_string())
             .or(config.keys_path.clone())
-            .ok_or("keypair file not defined. Supply it in config file or command line.".to_string())?
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "keypair file not defined. Supply it in config file or command line."))?
     );
     let params = Some(load_params(params.unwrap())?);
     print_args!(matches, tvc, params, abi, keys, wc);
 
-    let wc = wc.map(|v| i32::from_str_radix(v, 10))
-        .transpose()
-        .map_err(|e| format!("failed to parse workchain id: {}", e))?
-        .unwrap_or(config.wc);
-    deploy_contract(config, tvc.unwrap(), &abi.unwrap(), &params.unwrap(), &keys.unwrap(), wc).await
+    let wc = parse_wc(wc, &config)?;
+    let dry_run = matches.is_present("DRY_RUN");
+    let topup = matches.value_of("TOPUP_FROM")
+        .map(|wallet| -> Result<Topup, String> {
+            Ok(Topup {
+                wallet: load_ton_address(wallet, &config)?,
+                keys: matches.value_of("TOPUP_SIGN").map(|s| s.to_owned()).unwrap_or(keys.clone().unwrap()),
+            })
+        })
+        .transpose()?;
+    deploy_contract(config, tvc.unwrap(), &abi.unwrap(), &params.unwrap(), &keys.unwrap(), wc, dry_run, topup).await.map_err(CliError::from)
 }
 
-fn config_command(matches: &ArgMatches, config: Config, config_file: String) -> Result<(), String> {
+/// Prints per-network results of the operation executed with `--networks`.
+fn report_networks(results: Vec<(String, Result<(), CliError>)>) -> Result<(), CliError> {
+    println!();
+    println!("Summary:");
+    for (name, result) in &results {
+        match result {
+            Ok(_) => println!("  {}: succeeded", name),
+            Err(e) => println!("  {}: failed: {}", name, e),
+        }
+    }
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    if let Some(kind) = results.iter().find_map(|(_, r)| r.as_ref().err().map(|e| e.kind())) {
+        return Err(CliError::new(kind, format!("operation failed in {} of {} networks", failed, results.len())));
+    }
+    Ok(())
+}
+
+fn network_policy(matches: &ArgMatches) -> Result<NetworkPolicy, String> {
+    Ok(NetworkPolicy {
+        retries: matches.value_of("NET_RETRY")
+            .map(|v| u8::from_str_radix(v, 10))
+            .transpose()
+            .map_err(|e| format!("failed to parse number of retries: {}", e))?,
+        timeout: matches.value_of("NET_TIMEOUT")
+            .map(|v| u32::from_str_radix(v, 10))
+            .transpose()
+            .map_err(|e| format!("failed to parse timeout: {}", e))?
+            .filter(|timeout| *timeout > 0),
+    })
+}
+
+fn header_options(matches: &ArgMatches) -> Result<HeaderOptions, String> {
+    let flag = |with: &str, without: &str| {
+        if matches.is_present(with) {
+            Some(true)
+        } else if matches.is_present(without) {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    Ok(HeaderOptions {
+        pubkey: flag("WITH_PUBKEY", "NO_PUBKEY"),
+        time: flag("WITH_TIME", "NO_TIME"),
+        expire: matches.value_of("EXPIRE")
+            .map(|v| u32::from_str_radix(v, 10))
+            .transpose()
+            .map_err(|e| format!("failed to parse expire time: {}", e))?,
+    })
+}
+
+/// Commands which are able to inspect or reset malformed config file.
+fn can_repair_config(matches: &ArgMatches) -> bool {
+    matches.subcommand_matches("config")
+        .map(|m| m.is_present("LIST") || m.subcommand_matches("clear").is_some())
+        .unwrap_or(false)
+}
+
+fn config_command(matches: &ArgMatches, config: Config, config_file: String) -> Result<(), CliError> {
     let mut result = Ok(());
+    let mut dry_run = matches.is_present("DRY_RUN");
     if !matches.is_present("LIST") {
-        if let Some(clear_matches) = matches.subcommand_matches("clear") {
-            let url = clear_matches.is_present("URL");
-            let address = clear_matches.is_present("ADDR");
-            let wallet = clear_matches.is_present("WALLET");
-            let keys = clear_matches.is_present("KEYS");
-            let abi = clear_matches.is_present("ABI");
-            let wc = clear_matches.is_present("WC");
-            let retries = clear_matches.is_present("RETRIES");
-            let timeout = clear_matches.is_present("TIMEOUT");
-            let depool_fee = clear_matches.is_present("DEPOOL_FEE");
-            result = clear_config(config, config_file.as_str(), url, address, wallet, abi, keys, wc, retries, timeout, depool_fee);
+        if matches.subcommand_matches("undo").is_some() {
+            result = undo_config(config_file.as_str());
+        } else if matches.subcommand_matches("encrypt").is_some() {
+            result = encrypt_config_file(config, config_file.as_str(), true);
+        } else if matches.subcommand_matches("decrypt").is_some() {
+            result = encrypt_config_file(config, config_file.as_str(), false);
+        } else if let Some(clear_matches) = matches.subcommand_matches("clear") {
+            let options: Vec<&str> = CLEAR_OPTIONS.iter()
+                .filter(|option| clear_matches.is_present(*option))
+                .cloned()
+                .collect();
+            dry_run = dry_run || clear_matches.is_present("DRY_RUN");
+            result = clear_config(config, config_file.as_str(), &options, dry_run);
         } else {
-            let url = matches.value_of("URL");
-            let address = matches.value_of("ADDR");
-            let wallet = matches.value_of("WALLET");
-            let keys = matches.value_of("KEYS");
-            let abi = matches.value_of("ABI");
-            let wc = matches.value_of("WC");
-            let retries = matches.value_of("RETRIES");
-            let timeout = matches.value_of("TIMEOUT");
-            let depool_fee = matches.value_of("DEPOOL_FEE");
-            result = set_config(config, config_file.as_str(), url, address, wallet, abi, keys, wc, retries, timeout, depool_fee);
+            let values: Vec<(&str, &str)> = SET_OPTIONS.iter()
+                .filter_map(|option| matches.value_of(*option).map(|value| (*option, value)))
+                .collect();
+            result = set_config(config, config_file.as_str(), &values, dry_run);
         }
     }
+    if dry_run {
+        return result.map_err(CliError::from);
+    }
     let config = match Config::from_file(config_file.as_str()) {
         Some(c) => {
             c
@@ -713,10 +1293,10 @@ fn config_command(matches: &ArgMatches, config: Config, config_file: String) ->
         serde_json::to_string_pretty(&config)
             .map_err(|e| format!("failed to print config parameters: {}", e))?
     );
-    result
+    result.map_err(CliError::from)
 }
 
-async fn genaddr_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn genaddr_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let tvc = matches.value_of("TVC");
     let wc = matches.value_of("WC");
     let keys = matches.value_of("GENKEY").or(matches.value_of("SETKEY"));
@@ -726,17 +1306,152 @@ async fn genaddr_command(matches: &ArgMatches<'_>, config: Config) -> Result<(),
     let abi = matches.value_of("ABI");
     let is_update_tvc = if update_tvc { Some("true") } else { None };
     print_args!(matches, tvc, wc, keys, init_data, is_update_tvc);
-    generate_address(config, tvc.unwrap(), abi.unwrap(), wc, keys, new_keys, init_data, update_tvc).await
+    generate_address(config, tvc.unwrap(), abi.unwrap(), wc, keys, new_keys, init_data, update_tvc).await.map_err(CliError::from)
 }
 
-async fn account_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn account_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = matches.subcommand_matches("dump") {
+        let address = m.value_of("ADDRESS");
+        let output = m.value_of("OUTPUT");
+        print_args!(m, address, output);
+        let address = load_ton_address(address.unwrap(), &config)?;
+        return dump_account(config, address.as_str(), output.unwrap()).await.map_err(CliError::from);
+    }
+    if let Some(m) = matches.subcommand_matches("load") {
+        let boc = m.value_of("BOC");
+        print_args!(m, boc);
+        return load_account(boc.unwrap()).await.map_err(CliError::from);
+    }
+    if let Some(m) = matches.subcommand_matches("fees") {
+        let address = m.value_of("ADDRESS");
+        let horizon = m.value_of("HORIZON");
+        print_args!(m, address, horizon);
+        let address = load_ton_address(address.unwrap(), &config)?;
+        let horizon = parse_duration(horizon.unwrap_or("365d"))?;
+        return account_fees(config, address.as_str(), horizon).await.map_err(CliError::from);
+    }
+    if let Some(m) = matches.subcommand_matches("export") {
+        let address = m.value_of("ADDRESS");
+        let format = m.value_of("FORMAT");
+        let since = m.value_of("SINCE");
+        let until = m.value_of("UNTIL");
+        let output = m.value_of("OUTPUT");
+        let resume = m.value_of("RESUME");
+        print_args!(m, address, format, since, until, output, resume);
+        let address = load_ton_address(address.unwrap(), &config)?;
+        let format = export::ExportFormat::parse(format.unwrap_or("csv"))?;
+        let since = since.map(export::parse_time).transpose()?;
+        let until = until.map(export::parse_time).transpose()?;
+        let fiat = m.value_of("FIAT").unwrap_or("USD");
+        return export::export_transactions(config, &address, format, since, until, fiat, output, resume).await.map_err(CliError::from);
+    }
+    if let Some(m) = matches.subcommand_matches("unfreeze") {
+        let address = m.value_of("ADDRESS");
+        let wallet = m.value_of("FROM").map(|s| s.to_owned()).or(config.wallet.clone());
+        let keys = m.value_of("SIGN").map(|s| s.to_owned()).or(config.keys_path.clone());
+        let tvc = m.value_of("TVC");
+        print_args!(m, address, wallet, keys, tvc);
+        let address = load_ton_address(address.unwrap(), &config)?;
+        let wallet = wallet
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "wallet address is not defined. Supply it in config file or command line."))?;
+        let wallet = load_ton_address(&wallet, &config)?;
+        let keys = keys
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "keypair is not defined. Supply it in config file or command line."))?;
+        return unfreeze_account(config, &address, &wallet, &keys, tvc).await.map_err(CliError::from);
+    }
+    if let Some(m) = matches.subcommand_matches("sweep") {
+        let dest = m.value_of("TO");
+        let keys = m.value_of("SIGN").map(|s| s.to_owned()).or(config.keys_path.clone());
+        let abi = m.value_of("ABI");
+        let csv = m.value_of("CSV");
+        print_args!(m, dest, keys, abi, csv);
+        let abi = match abi {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read ABI file: {}", e)))?,
+            None => multisig::MSIG_ABI.to_owned(),
+        };
+        let mut accounts = match csv {
+            Some(path) => load_sweep_csv(path, keys.as_deref())?,
+            None => vec![],
+        };
+        for addr in m.values_of("ADDRESS").map(|v| v.collect::<Vec<_>>()).unwrap_or_default() {
+            let keys = keys.clone()
+                .ok_or(CliError::new(ErrorKind::InvalidInput, "keypair is not defined. Supply it in config file or command line."))?;
+            accounts.push((addr.to_owned(), keys));
+        }
+        if accounts.is_empty() {
+            return Err(CliError::new(ErrorKind::InvalidInput, "no accounts to sweep"));
+        }
+        let accounts = accounts.into_iter()
+            .map(|(addr, keys)| Ok((load_ton_address(&addr, &config)?, keys)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let dest = load_ton_address(dest.unwrap(), &config)?;
+        return sweep_accounts(config, accounts, &dest, abi).await.map_err(CliError::from);
+    }
+    let addresses = matches.values_of("ADDRESS").unwrap().collect::<Vec<_>>();
+    if addresses.len() > 1 {
+        let addresses = addresses.iter()
+            .map(|a| load_ton_address(a, &config))
+            .collect::<Result<Vec<_>, _>>()?;
+        return get_accounts(config, addresses).await.map_err(CliError::from);
+    }
     let address = matches.value_of("ADDRESS");
     print_args!(matches, address);
     let address = load_ton_address(address.unwrap(), &config)?;
-    get_account(config, address.as_str()).await
+    get_account(config, address.as_str()).await.map_err(CliError::from)
+}
+
+async fn transfer_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let (dest, value, comment) = transfer_args(matches)?;
+    if matches.is_present("LINK") {
+        let amount = value.as_ref()
+            .map(|v| convert::amount_to_nano(v))
+            .transpose()?;
+        let link = uri::transfer_uri(&dest, amount, comment.as_deref());
+        println!("{}", link);
+        qr2term::print_qr(&link).map_err(|e| format!("failed to print QR code: {}", e))?;
+        return Ok(());
+    }
+    let dest = Some(dest.as_str());
+    let value = Some(value.ok_or(CliError::new(ErrorKind::InvalidInput, "amount of tokens is not defined."))?);
+    let value = value.as_deref();
+    let comment = comment.as_deref();
+    let wallet = Some(
+        matches.value_of("FROM")
+            .map(|s| s.to_string())
+            .or(config.wallet.clone())
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "wallet address is not defined. Supply it in config file or command line."))?
+    );
+    let keys = Some(
+        matches.value_of("SIGN")
+            .map(|s| s.to_string())
+            .or(config.keys_path.clone())
+            .ok_or(CliError::new(ErrorKind::InvalidInput, "keypair is not defined. Supply it in config file or command line."))?
+    );
+    print_args!(matches, wallet, dest, value, comment, keys);
+    let wallet = load_ton_address(&wallet.unwrap(), &config)?;
+    let dest = load_ton_address(dest.unwrap(), &config)?;
+    let value = multisig::resolve_value(&config, &wallet, value.unwrap()).await?;
+    multisig::send(config, &wallet, &dest, &value, &keys.unwrap(), comment).await.map_err(CliError::from)
+}
+
+/// Takes destination, amount and comment from arguments or from ton:// link given as destination.
+fn transfer_args(matches: &ArgMatches<'_>) -> Result<(String, Option<String>, Option<String>), String> {
+    let dest = matches.value_of("DEST").unwrap();
+    let value = matches.value_of("VALUE").map(|s| s.to_owned());
+    let comment = matches.value_of("COMMENT").map(|s| s.to_owned());
+    if !uri::is_transfer_uri(dest) {
+        return Ok((dest.to_owned(), value, comment));
+    }
+    let link = uri::parse_transfer_uri(dest)?;
+    Ok((
+        link.dest,
+        value.or(link.amount.map(convert::nano_to_tokens)),
+        comment.or(link.text),
+    ))
 }
 
-async fn proposal_create_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn proposal_create_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let dest = matches.value_of("DEST");
     let keys = matches.value_of("KEYS");
@@ -764,7 +1479,7 @@ async fn proposal_create_command(matches: &ArgMatches<'_>, config: Config) -> Re
     ).await
 }
 
-async fn proposal_vote_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn proposal_vote_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let keys = matches.value_of("KEYS");
     let id = matches.value_of("ID");
@@ -783,22 +1498,27 @@ async fn proposal_vote_command(matches: &ArgMatches<'_>, config: Config) -> Resu
     vote(config, address.as_str(), keys, id.unwrap(), lifetime, offline).await
 }
 
-async fn proposal_decode_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn proposal_decode_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
     let address = matches.value_of("ADDRESS");
     let id = matches.value_of("ID");
     print_args!(matches, address, id);
 
     let address = load_ton_address(address.unwrap(), &config)?;
-    decode_proposal(config, address.as_str(), id.unwrap()).await
+    decode_proposal(config, address.as_str(), id.unwrap()).await.map_err(CliError::from)
 }
 
-async fn getconfig_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn getconfig_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = matches.subcommand_matches("watch") {
+        let param = m.value_of("PARAM");
+        print_args!(m, param);
+        return watch_config(config, param).await.map_err(CliError::from);
+    }
     let index = matches.value_of("INDEX");
     print_args!(matches, index);
-    query_global_config(config, index.unwrap()).await
+    query_global_config(config, index.unwrap()).await.map_err(CliError::from)
 }
 
-fn nodeid_command(matches: &ArgMatches) -> Result<(), String> {
+fn nodeid_command(matches: &ArgMatches) -> Result<(), CliError> {
     let key = matches.value_of("KEY");
     let keypair = matches.value_of("KEY_PAIR");
     print_args!(matches, key, keypair);
@@ -810,14 +1530,24 @@ fn nodeid_command(matches: &ArgMatches) -> Result<(), String> {
         let pair = crypto::load_keypair(pair)?;
         convert::nodeid_from_pubkey(&hex::decode(&pair.public).unwrap())?
     } else {
-        return Err("Either public key or key pair parameter should be provided".to_owned());
+        return Err(CliError::new(ErrorKind::InvalidInput, "Either public key or key pair parameter should be provided"));
     };
     println!("{}", nodeid);
     Ok(())
 }
 
-async fn sendfile_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), String> {
+async fn sendfile_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), CliError> {
     let boc = m.value_of("BOC");
     print_args!(m, boc);
-    sendfile::sendfile(conf, boc.unwrap()).await
-}
\ No newline at end of file
+    sendfile::sendfile(conf, boc.unwrap()).await.map_err(CliError::from)
+}
+
+async fn send_boc_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), CliError> {
+    let boc = m.value_of("BOC");
+    let abi = m.value_of("ABI");
+    print_args!(m, boc, abi);
+    let abi = abi
+        .map(|path| std::fs::read_to_string(path).map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read ABI file: {}", e))))
+        .transpose()?;
+    sendfile::send_boc(conf, boc.unwrap(), m.is_present("WAIT"), abi).await.map_err(CliError::from)
+}
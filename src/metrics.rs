@@ -0,0 +1,122 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Metrics collected by long-running commands (monitor, debot browser)
+/// and exposed in Prometheus text format.
+#[derive(Default)]
+struct Metrics {
+    balances: BTreeMap<String, u64>,
+    endpoint_latency: Option<f64>,
+    debot_sessions: u64,
+    debot_actions: u64,
+    debot_action_errors: u64,
+}
+
+lazy_static! {
+    static ref METRICS: Mutex<Metrics> = Mutex::new(Metrics::default());
+}
+
+pub fn set_balance(addr: &str, balance: u64) {
+    METRICS.lock().unwrap().balances.insert(addr.to_owned(), balance);
+}
+
+pub fn set_endpoint_latency(seconds: f64) {
+    METRICS.lock().unwrap().endpoint_latency = Some(seconds);
+}
+
+pub fn inc_debot_sessions() {
+    METRICS.lock().unwrap().debot_sessions += 1;
+}
+
+pub fn inc_debot_actions(success: bool) {
+    let mut metrics = METRICS.lock().unwrap();
+    metrics.debot_actions += 1;
+    if !success {
+        metrics.debot_action_errors += 1;
+    }
+}
+
+fn render() -> String {
+    let metrics = METRICS.lock().unwrap();
+    let mut out = String::new();
+    let s = &mut out;
+    writeln!(s, "# HELP tonos_account_balance_nanotokens Account balance.").unwrap();
+    writeln!(s, "# TYPE tonos_account_balance_nanotokens gauge").unwrap();
+    for (addr, balance) in &metrics.balances {
+        writeln!(s, "tonos_account_balance_nanotokens{{address=\"{}\"}} {}", addr, balance).unwrap();
+    }
+    if let Some(latency) = metrics.endpoint_latency {
+        writeln!(s, "# HELP tonos_endpoint_latency_seconds Duration of the last endpoint query.").unwrap();
+        writeln!(s, "# TYPE tonos_endpoint_latency_seconds gauge").unwrap();
+        writeln!(s, "tonos_endpoint_latency_seconds {}", latency).unwrap();
+    }
+    writeln!(s, "# HELP tonos_debot_sessions_total Started debot sessions.").unwrap();
+    writeln!(s, "# TYPE tonos_debot_sessions_total counter").unwrap();
+    writeln!(s, "tonos_debot_sessions_total {}", metrics.debot_sessions).unwrap();
+    writeln!(s, "# HELP tonos_debot_actions_total Executed debot actions.").unwrap();
+    writeln!(s, "# TYPE tonos_debot_actions_total counter").unwrap();
+    writeln!(s, "tonos_debot_actions_total{{result=\"success\"}} {}", metrics.debot_actions - metrics.debot_action_errors).unwrap();
+    writeln!(s, "tonos_debot_actions_total{{result=\"failure\"}} {}", metrics.debot_action_errors).unwrap();
+    out
+}
+
+/// Starts http server which serves `/metrics` endpoint in background.
+pub async fn start_metrics_server(addr: &str) -> Result<(), String> {
+    let mut listener = TcpListener::bind(addr).await
+        .map_err(|e| format!("failed to start metrics server on {}: {}", addr, e))?;
+    println!("Metrics are available at http://{}/metrics", addr);
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(_) => continue,
+            };
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let len = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..len]);
+                let response = if request.starts_with("GET /metrics") {
+                    let body = render();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body,
+                    )
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned()
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        set_balance("0:1111", 1000);
+        inc_debot_actions(false);
+        let text = render();
+        assert!(text.contains("tonos_account_balance_nanotokens{address=\"0:1111\"} 1000"));
+        assert!(text.contains("tonos_debot_actions_total{result=\"failure\"}"));
+    }
+}
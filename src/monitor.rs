@@ -0,0 +1,198 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::convert;
+use crate::error::CliError;
+use crate::helpers::{create_client_verbose, create_http_client, load_ton_address, query, send_http, TonClient};
+use crate::metrics;
+use chrono::Local;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const DEFAULT_INTERVAL: u64 = 10;
+
+#[derive(Clone, PartialEq)]
+struct AccountState {
+    balance: u64,
+    last_trans_lt: u64,
+}
+
+struct Hooks {
+    command: Option<String>,
+    /// Webhook url and the client to post events with.
+    webhook: Option<(String, reqwest::Client)>,
+}
+
+pub fn create_monitor_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("monitor")
+        .about("Watches account balances and transactions and runs hooks when they change.")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .arg(Arg::with_name("ADDRESS")
+            .required(true)
+            .takes_value(true)
+            .multiple(true)
+            .help("Addresses of accounts to watch."))
+        .arg(Arg::with_name("ON_CHANGE")
+            .long("--on-change")
+            .takes_value(true)
+            .help("Shell command executed on every event. Event is passed in TONOS_EVENT, TONOS_ADDRESS, TONOS_BALANCE, TONOS_LT environment variables and as json on stdin."))
+        .arg(Arg::with_name("WEBHOOK")
+            .long("--webhook")
+            .takes_value(true)
+            .help("Url where events are posted as json."))
+        .arg(Arg::with_name("BALANCE_BELOW")
+            .long("--balance-below")
+            .takes_value(true)
+            .help("Emits event when account balance falls below this value (in tokens)."))
+        .arg(Arg::with_name("METRICS")
+            .long("--metrics")
+            .takes_value(true)
+            .help("Address (host:port) where Prometheus metrics endpoint /metrics is served."))
+        .arg(Arg::with_name("INTERVAL")
+            .long("--interval")
+            .takes_value(true)
+            .help("Polling interval in seconds (default 10)."))
+}
+
+pub async fn monitor_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let addresses = m.values_of("ADDRESS").unwrap()
+        .map(|a| load_ton_address(a, &config))
+        .collect::<Result<Vec<_>, _>>()?;
+    let threshold = m.value_of("BALANCE_BELOW")
+        .map(|v| convert::amount_to_nano(v))
+        .transpose()
+        .map_err(|e| format!("failed to parse balance threshold: {}", e))?;
+    let interval = m.value_of("INTERVAL")
+        .map(|v| u64::from_str_radix(v, 10))
+        .transpose()
+        .map_err(|e| format!("failed to parse interval: {}", e))?
+        .unwrap_or(DEFAULT_INTERVAL);
+    let hooks = Hooks {
+        command: m.value_of("ON_CHANGE").map(|s| s.to_owned()),
+        webhook: m.value_of("WEBHOOK")
+            .map(|url| create_http_client(&config).map(|client| (url.to_owned(), client)))
+            .transpose()?,
+    };
+
+    let ton = create_client_verbose(&config)?;
+    if let Some(addr) = m.value_of("METRICS") {
+        metrics::start_metrics_server(addr).await?;
+    }
+    println!("Monitoring {} account(s)...", addresses.len());
+    let mut states: HashMap<String, AccountState> = HashMap::new();
+    loop {
+        match query_states(ton.clone(), &addresses).await {
+            Ok(new_states) => {
+                for (addr, state) in new_states {
+                    metrics::set_balance(&addr, state.balance);
+                    let prev = states.get(&addr);
+                    for event in detect_events(prev, &state, threshold) {
+                        emit_event(&hooks, event, &addr, &state).await;
+                    }
+                    states.insert(addr, state);
+                }
+            },
+            Err(e) => println!("{}", e),
+        }
+        tokio::time::delay_for(Duration::from_secs(interval)).await;
+    }
+}
+
+async fn query_states(ton: TonClient, addresses: &[String]) -> Result<Vec<(String, AccountState)>, String> {
+    let started = Instant::now();
+    let accounts = query(
+        ton,
+        "accounts",
+        json!({ "id": { "in": addresses } }),
+        "id balance(format: DEC) last_trans_lt(format: DEC)",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query accounts: {}", e))?;
+    metrics::set_endpoint_latency(started.elapsed().as_secs_f64());
+    Ok(accounts.iter().map(|acc| {
+        let parse = |field: &str| acc[field].as_str()
+            .and_then(|s| u64::from_str_radix(s, 10).ok())
+            .unwrap_or(0);
+        (
+            acc["id"].as_str().unwrap_or("").to_owned(),
+            AccountState { balance: parse("balance"), last_trans_lt: parse("last_trans_lt") },
+        )
+    }).collect())
+}
+
+fn detect_events(prev: Option<&AccountState>, state: &AccountState, threshold: Option<u64>) -> Vec<&'static str> {
+    let mut events = vec![];
+    if let Some(threshold) = threshold {
+        let was_below = prev.map(|p| p.balance < threshold).unwrap_or(false);
+        if state.balance < threshold && !was_below {
+            events.push("balance_below");
+        }
+    }
+    if let Some(prev) = prev {
+        if state.last_trans_lt > prev.last_trans_lt {
+            events.push("new_transaction");
+        }
+    }
+    events
+}
+
+async fn emit_event(hooks: &Hooks, event: &str, addr: &str, state: &AccountState) {
+    let event_json = json!({
+        "event": event,
+        "address": addr,
+        "balance": state.balance.to_string(),
+        "last_trans_lt": state.last_trans_lt.to_string(),
+        "time": Local::now().to_rfc3339(),
+    });
+    println!("{}", event_json);
+
+    if let Some(cmd) = &hooks.command {
+        if let Err(e) = run_hook_command(cmd, event, addr, state, &event_json) {
+            println!("hook command failed: {}", e);
+        }
+    }
+    if let Some((url, client)) = &hooks.webhook {
+        let result = send_http(|| client.post(url).json(&event_json)).await;
+        if let Err(e) = result {
+            println!("webhook failed: {}", e);
+        }
+    }
+}
+
+fn run_hook_command(
+    cmd: &str,
+    event: &str,
+    addr: &str,
+    state: &AccountState,
+    event_json: &serde_json::Value,
+) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .arg(flag)
+        .arg(cmd)
+        .env("TONOS_EVENT", event)
+        .env("TONOS_ADDRESS", addr)
+        .env("TONOS_BALANCE", state.balance.to_string())
+        .env("TONOS_LT", state.last_trans_lt.to_string())
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to start: {}", e))?;
+    if let Some(stdin) = child.stdin.as_mut() {
+        let _ = stdin.write_all(event_json.to_string().as_bytes());
+    }
+    child.wait().map_err(|e| format!("{}", e))?;
+    Ok(())
+}
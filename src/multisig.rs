@@ -13,8 +13,20 @@
 use crate::call;
 use crate::config::Config;
 use crate::convert;
-use crate::helpers::{create_client_local, load_abi, load_ton_address};
+use crate::crypto::load_keypair;
+use crate::deploy::deploy_contract;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{calc_acc_address, create_client, create_client_local, create_client_verbose, create_http_client, decode_msg_body, load_abi, load_ton_address, now, parse_wc, query, send_http, TonClient};
+use crate::keys::default_wallets_dir;
+use crate::known_abi::{detect_abi, ContractFamily};
+use crate::pretty::parse_uint;
+use crate::schedule;
+use crate::wallet::{account_balance, code_hash, prompt, WALLET_TYPES};
 use clap::{App, ArgMatches, SubCommand, Arg, AppSettings};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
 use ton_client::abi::{encode_message_body, ParamsOfEncodeMessageBody, CallSet};
 
 pub const MSIG_ABI: &str = r#"{
@@ -155,6 +167,75 @@ pub const TRANSFER_WITH_COMMENT: &str = r#"{
 	"data": []
 }"#;
 
+/// Custodian set update functions of SetcodeMultisig.
+pub const SETCODE_UPDATE_ABI: &str = r#"{
+	"ABI version": 2,
+	"header": ["pubkey", "time", "expire"],
+	"functions": [
+		{
+			"name": "submitUpdate",
+			"inputs": [
+				{"name":"codeHash","type":"uint256"},
+				{"name":"owners","type":"uint256[]"},
+				{"name":"reqConfirms","type":"uint8"}
+			],
+			"outputs": [
+				{"name":"updateId","type":"uint64"}
+			]
+		},
+		{
+			"name": "confirmUpdate",
+			"inputs": [
+				{"name":"updateId","type":"uint64"}
+			],
+			"outputs": [
+			]
+		},
+		{
+			"name": "executeUpdate",
+			"inputs": [
+				{"name":"updateId","type":"uint64"},
+				{"name":"code","type":"cell"}
+			],
+			"outputs": [
+			]
+		},
+		{
+			"name": "getParameters",
+			"inputs": [
+			],
+			"outputs": [
+				{"name":"maxQueuedTransactions","type":"uint8"},
+				{"name":"maxCustodianCount","type":"uint8"},
+				{"name":"expirationTime","type":"uint64"},
+				{"name":"minValue","type":"uint128"},
+				{"name":"requiredTxnConfirms","type":"uint8"},
+				{"name":"requiredUpdConfirms","type":"uint8"}
+			]
+		},
+		{
+			"name": "getUpdateRequests",
+			"inputs": [
+			],
+			"outputs": [
+				{"components":[{"name":"id","type":"uint64"},{"name":"index","type":"uint8"},{"name":"signs","type":"uint8"},{"name":"confirmationsMask","type":"uint32"},{"name":"creator","type":"uint256"},{"name":"codeHash","type":"uint256"},{"name":"custodians","type":"uint256[]"},{"name":"reqConfirms","type":"uint8"}],"name":"updates","type":"tuple[]"}
+			]
+		},
+		{
+			"name": "getCustodians",
+			"inputs": [
+			],
+			"outputs": [
+				{"components":[{"name":"index","type":"uint8"},{"name":"pubkey","type":"uint256"}],"name":"custodians","type":"tuple[]"}
+			]
+		}
+	],
+	"data": [
+	],
+	"events": [
+	]
+}"#;
+
 pub fn create_multisig_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name("multisig")
         .about("Multisignature wallet commands.")        
@@ -177,22 +258,136 @@ pub fn create_multisig_command<'a, 'b>() -> App<'a, 'b> {
                 .help("Amount of funds to transfer."))
             .arg(Arg::with_name("PURPOSE")
                 .long("--purpose")
+                .alias("comment")
                 .takes_value(true)
-                .help("Purpose of payment."))
+                .help("Purpose of payment (text comment attached to the transfer)."))
             .arg(Arg::with_name("SIGN")
                 .long("--sign")
                 .takes_value(true)
-                .help("Path to keys or seed phrase.")))
+                .help("Path to keys or seed phrase."))
+            .arg(Arg::with_name("SEND_AT")
+                .long("--send-at")
+                .takes_value(true)
+                .help("Unix time when the transfer must be submitted (see `schedule` command).")))
+        .subcommand(SubCommand::with_name("pending")
+            .about("Lists unconfirmed transactions of multisignature wallets with their remaining lifetime and missing confirmations.")
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Wallet address (can be repeated, default: wallet address from config)."))
+            .arg(Arg::with_name("WATCH")
+                .long("--watch")
+                .help("Keeps polling wallets and prints the list on every change."))
+            .arg(Arg::with_name("INTERVAL")
+                .long("--interval")
+                .takes_value(true)
+                .default_value("60")
+                .help("Polling interval in seconds for --watch mode."))
+            .arg(Arg::with_name("WEBHOOK")
+                .long("--webhook")
+                .takes_value(true)
+                .help("Url which receives a json POST request when a transaction is about to expire (--watch mode)."))
+            .arg(Arg::with_name("REMIND_BEFORE")
+                .long("--remind-before")
+                .takes_value(true)
+                .default_value("600")
+                .help("Sends reminder when transaction expires in less than this number of seconds.")))
+        .subcommand(SubCommand::with_name("confirm-all")
+            .about("Lists pending transactions of the wallet not yet confirmed by the key, shows their details and confirms the approved ones in one session.")
+            .arg(Arg::with_name("ADDRESS")
+                .long("--wallet")
+                .alias("addr")
+                .takes_value(true)
+                .help("Wallet address (default: wallet address from config)."))
+            .arg(Arg::with_name("SIGN")
+                .long("--sign")
+                .takes_value(true)
+                .help("Path to keys or seed phrase of the custodian."))
+            .arg(Arg::with_name("FILTER")
+                .long("--filter")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Selects transactions: dest=<address>, creator=<pubkey>, min-value=<tokens> or max-value=<tokens> (can be repeated, all conditions must match)."))
+            .arg(Arg::with_name("YES")
+                .long("--yes")
+                .short("-y")
+                .help("Confirms all selected transactions without asking.")))
+        .subcommand(SubCommand::with_name("migrate")
+            .about("Migrates SafeMultisig wallet to a new SetcodeMultisig with the same custodians: deploys the new wallet, moves the balance and prints remaining manual steps. Rerun it to continue after other custodians confirm the transfers.")
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .help("Address of the old wallet (default: wallet address from config)."))
+            .arg(Arg::with_name("SIGN")
+                .long("--sign")
+                .takes_value(true)
+                .help("Path to keys or seed phrase of a custodian. This key also becomes the deploy key of the new wallet."))
+            .arg(Arg::with_name("TVC")
+                .long("--tvc")
+                .takes_value(true)
+                .help("SetcodeMultisig tvc file (default: ~/.tonos-cli/wallets/setcode.tvc)."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("SetcodeMultisig ABI file (default: ~/.tonos-cli/wallets/setcode.abi.json)."))
+            .arg(Arg::with_name("DEPLOY_VALUE")
+                .long("--deploy-value")
+                .takes_value(true)
+                .default_value("1")
+                .help("Amount sent to the new address to pay for deploy."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .help("Workchain id of the new wallet (default: from config).")))
+        .subcommand(SubCommand::with_name("rotate-key")
+            .about("Changes custodians of SetcodeMultisig wallet. With --owners submits the update request, otherwise confirms the tracked request or executes it when it has enough confirmations. Update id is kept in a local state file next to the config, so every custodian just reruns the command.")
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .help("Wallet address (default: wallet address from config)."))
+            .arg(Arg::with_name("SIGN")
+                .long("--sign")
+                .takes_value(true)
+                .help("Path to keys or seed phrase of the custodian."))
+            .arg(Arg::with_name("OWNERS")
+                .long("--owners")
+                .takes_value(true)
+                .help("Comma-separated public keys of the new custodian set. Submits a new update request."))
+            .arg(Arg::with_name("REQ_CONFIRMS")
+                .long("--req-confirms")
+                .takes_value(true)
+                .requires("OWNERS")
+                .help("Number of confirmations required for transactions by the new custodian set (default: current value)."))
+            .arg(Arg::with_name("UPDATE_ID")
+                .long("--update-id")
+                .takes_value(true)
+                .conflicts_with("OWNERS")
+                .help("Update request id, if it isn't tracked in the local state file (e.g. submitted on another machine).")))
 }
 
-pub async fn multisig_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+pub async fn multisig_command(m: &ArgMatches<'_>, config: Config, config_file: &str) -> Result<(), CliError> {
     if let Some(m) = m.subcommand_matches("send") {
-        return multisig_send_command(m, config).await;
+        return multisig_send_command(m, config, config_file).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("pending") {
+        return multisig_pending_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("confirm-all") {
+        return multisig_confirm_all_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("migrate") {
+        return multisig_migrate_command(m, config).await.map_err(CliError::from);
     }
-    Err("unknown multisig command".to_owned())
+    if let Some(m) = m.subcommand_matches("rotate-key") {
+        return multisig_rotate_key_command(m, config, config_file).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown multisig command"))
 }
 
-async fn multisig_send_command(matches: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+async fn multisig_send_command(matches: &ArgMatches<'_>, config: Config, config_file: &str) -> Result<(), String> {
     let address = matches.value_of("ADDRESS")
         .ok_or(format!("--addr parameter is not defined"))?;
     let dest = matches.value_of("DEST")
@@ -204,9 +399,750 @@ async fn multisig_send_command(matches: &ArgMatches<'_>, config: Config) -> Resu
     let comment = matches.value_of("PURPOSE");
 
     let address = load_ton_address(address, &config)?;
+    let value = &resolve_value(&config, address.as_str(), value).await?;
+    if let Some(send_at) = matches.value_of("SEND_AT") {
+        let send_at = schedule::parse_send_at(send_at)?;
+        let body = transfer_body(comment).await?;
+        return schedule::schedule_call(
+            config,
+            &schedule::schedule_file_name(config_file),
+            send_at,
+            address.as_str(),
+            MSIG_ABI.to_string(),
+            "submitTransaction",
+            &transfer_params(dest, value, &body, true)?,
+            Some(keys.to_owned()),
+        ).await;
+    }
     send(config, address.as_str(), dest, value, keys, comment).await
 }
 
+struct PendingTransaction {
+    wallet: String,
+    id: String,
+    dest: String,
+    value: u128,
+    expires_at: u64,
+    confirmations: u128,
+    required: u128,
+    /// Public keys of custodians who haven't confirmed the transaction yet.
+    unconfirmed: Vec<String>,
+    creator: String,
+    /// Message body attached to the transfer (base64 boc, may be empty).
+    payload: String,
+    /// Send flags of the transfer, 128 means the whole balance is sent.
+    send_flags: u128,
+}
+
+impl PendingTransaction {
+    fn to_json(&self) -> Value {
+        json!({
+            "wallet": self.wallet,
+            "transactionId": self.id,
+            "dest": self.dest,
+            "value": self.value.to_string(),
+            "expiresAt": self.expires_at,
+            "confirmations": self.confirmations.to_string(),
+            "required": self.required.to_string(),
+            "unconfirmed": self.unconfirmed,
+        })
+    }
+}
+
+async fn multisig_pending_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let mut wallets = vec![];
+    for addr in m.values_of("ADDRESS").map(|v| v.collect::<Vec<_>>()).unwrap_or_default() {
+        wallets.push(load_ton_address(addr, &config)?);
+    }
+    if wallets.is_empty() {
+        let wallet = config.wallet.clone()
+            .ok_or("wallet address is not defined. Supply it in config file or command line.".to_string())?;
+        wallets.push(load_ton_address(&wallet, &config)?);
+    }
+    let interval = u64::from_str_radix(m.value_of("INTERVAL").unwrap(), 10)
+        .map_err(|e| format!("failed to parse interval: {}", e))?;
+    let remind_before = u64::from_str_radix(m.value_of("REMIND_BEFORE").unwrap(), 10)
+        .map_err(|e| format!("failed to parse --remind-before: {}", e))?;
+    let webhook = m.value_of("WEBHOOK")
+        .map(|url| create_http_client(&config).map(|client| (url, client)))
+        .transpose()?;
+    let ton = create_client_verbose(&config)?;
+
+    let mut last_ids: Option<Vec<String>> = None;
+    let mut reminded = HashSet::new();
+    loop {
+        let mut pending = vec![];
+        for wallet in &wallets {
+            match pending_transactions(ton.clone(), wallet).await {
+                Ok(list) => pending.extend(list),
+                Err(e) => println!("Wallet {}: {}", wallet, e),
+            }
+        }
+        let ids: Vec<String> = pending.iter().map(|t| format!("{}:{}:{}", t.wallet, t.id, t.confirmations)).collect();
+        if last_ids.as_ref() != Some(&ids) {
+            print_pending(&pending, config.is_json);
+            last_ids = Some(ids);
+        }
+        if !m.is_present("WATCH") {
+            return Ok(());
+        }
+        if let Some((url, client)) = &webhook {
+            for trans in &pending {
+                let key = format!("{}:{}", trans.wallet, trans.id);
+                if trans.expires_at.saturating_sub(now() as u64) <= remind_before && !reminded.contains(&key) {
+                    send_reminder(client, url, trans).await;
+                    reminded.insert(key);
+                }
+            }
+        }
+        tokio::time::delay_for(std::time::Duration::from_secs(interval)).await;
+    }
+}
+
+async fn pending_transactions(ton: TonClient, wallet: &str) -> Result<Vec<PendingTransaction>, String> {
+    let abi = detect_abi(ton.clone(), wallet, ContractFamily::Multisig).await?;
+    let params = call::run_local(ton.clone(), wallet, &abi, "getParameters", "{}", true).await?;
+    let lifetime = parse_uint(&params["expirationTime"]).unwrap_or(0) as u64;
+    let custodians = call::run_local(ton.clone(), wallet, &abi, "getCustodians", "{}", true).await?;
+    let custodians = custodians["custodians"].as_array().cloned().unwrap_or_default();
+    let transactions = call::run_local(ton, wallet, &abi, "getTransactions", "{}", true).await?;
+
+    let mut result = vec![];
+    for trans in transactions["transactions"].as_array().cloned().unwrap_or_default() {
+        let id = parse_uint(&trans["id"]).unwrap_or(0);
+        let mask = parse_uint(&trans["confirmationsMask"]).unwrap_or(0);
+        let unconfirmed = custodians.iter()
+            .filter(|c| {
+                let index = parse_uint(&c["index"]).unwrap_or(0);
+                mask & (1 << index) == 0
+            })
+            .map(|c| c["pubkey"].as_str().unwrap_or("").to_owned())
+            .collect();
+        result.push(PendingTransaction {
+            wallet: wallet.to_owned(),
+            id: id.to_string(),
+            dest: trans["dest"].as_str().unwrap_or("").to_owned(),
+            value: parse_uint(&trans["value"]).unwrap_or(0),
+            // upper 32 bits of transaction id contain its creation time
+            expires_at: (id >> 32) as u64 + lifetime,
+            confirmations: parse_uint(&trans["signsReceived"]).unwrap_or(0),
+            required: parse_uint(&trans["signsRequired"]).unwrap_or(0),
+            unconfirmed,
+            creator: trans["creator"].as_str().unwrap_or("").to_owned(),
+            payload: trans["payload"].as_str().unwrap_or("").to_owned(),
+            send_flags: parse_uint(&trans["sendFlags"]).unwrap_or(0),
+        });
+    }
+    Ok(result)
+}
+
+/// Finds transfer to `dest` submitted earlier and still waiting for confirmations:
+/// transfer of `value` or of the whole balance if `value` is None.
+fn find_submitted<'a>(pending: &'a [PendingTransaction], dest: &str, value: Option<u128>) -> Option<&'a PendingTransaction> {
+    pending.iter().find(|t| {
+        let all_balance = t.send_flags & 128 != 0;
+        t.dest == dest && match value {
+            Some(value) => !all_balance && t.value == value,
+            None => all_balance,
+        }
+    })
+}
+
+fn print_pending(pending: &[PendingTransaction], is_json: bool) {
+    if is_json {
+        let list: Vec<Value> = pending.iter().map(|t| t.to_json()).collect();
+        println!("{}", serde_json::to_string_pretty(&list).unwrap_or_default());
+        return;
+    }
+    if pending.is_empty() {
+        println!("No pending transactions.");
+        return;
+    }
+    let now = now() as u64;
+    for trans in pending {
+        let remaining = trans.expires_at.saturating_sub(now);
+        println!("Wallet {} transaction {}", trans.wallet, trans.id);
+        println!("  dest:          {}", trans.dest);
+        println!("  value:         {}", convert::nano128_to_tokens(trans.value));
+        println!("  confirmations: {}/{}", trans.confirmations, trans.required);
+        if remaining == 0 {
+            println!("  expired");
+        } else {
+            println!("  expires in:    {}h {}m {}s", remaining / 3600, remaining % 3600 / 60, remaining % 60);
+        }
+        for pubkey in &trans.unconfirmed {
+            println!("  not confirmed by {}", pubkey);
+        }
+    }
+}
+
+async fn send_reminder(client: &reqwest::Client, url: &str, trans: &PendingTransaction) {
+    let mut body = trans.to_json();
+    body["event"] = json!("expiring");
+    let result = send_http(|| client.post(url).json(&body)).await;
+    if let Err(e) = result {
+        println!("webhook failed: {}", e);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TransactionFilter {
+    Dest(String),
+    Creator(String),
+    MinValue(u128),
+    MaxValue(u128),
+}
+
+fn normalize_pubkey(key: &str) -> String {
+    format!("{:0>64}", key.trim_start_matches("0x").to_lowercase())
+}
+
+impl TransactionFilter {
+    fn parse(filter: &str, config: &Config) -> Result<Self, String> {
+        let mut parts = filter.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next()
+            .ok_or(format!(r#"filter must be <key>=<value>, got "{}""#, filter))?
+            .trim();
+        let tokens = |value: &str| -> Result<u128, String> {
+            convert::amount_to_nano(value)
+                .map(|nano| nano as u128)
+                .map_err(|e| format!("failed to parse value in filter: {}", e))
+        };
+        match key {
+            "dest" => Ok(TransactionFilter::Dest(load_ton_address(value, config)?)),
+            "creator" => Ok(TransactionFilter::Creator(normalize_pubkey(value))),
+            "min-value" => Ok(TransactionFilter::MinValue(tokens(value)?)),
+            "max-value" => Ok(TransactionFilter::MaxValue(tokens(value)?)),
+            _ => Err(format!("unknown filter {}, expected dest, creator, min-value or max-value", key)),
+        }
+    }
+
+    fn matches(&self, trans: &PendingTransaction) -> bool {
+        match self {
+            TransactionFilter::Dest(dest) => &trans.dest == dest,
+            TransactionFilter::Creator(creator) => &normalize_pubkey(&trans.creator) == creator,
+            TransactionFilter::MinValue(min) => trans.value >= *min,
+            TransactionFilter::MaxValue(max) => trans.value <= *max,
+        }
+    }
+}
+
+/// Parses answer to the approval prompt: `all`, `none` or list of numbers
+/// and ranges, e.g. `1,3-5`. Numbers start from 1.
+fn parse_selection(answer: &str, count: usize) -> Result<Vec<usize>, String> {
+    let answer = answer.trim().to_lowercase();
+    match answer.as_str() {
+        "all" | "a" | "y" | "yes" => return Ok((0..count).collect()),
+        "" | "none" | "n" | "no" => return Ok(vec![]),
+        _ => {},
+    }
+    let parse = |s: &str| -> Result<usize, String> {
+        let n = usize::from_str_radix(s.trim(), 10)
+            .map_err(|_| format!(r#"invalid transaction number "{}""#, s.trim()))?;
+        if n == 0 || n > count {
+            return Err(format!("transaction number {} is out of range 1..{}", n, count));
+        }
+        Ok(n - 1)
+    };
+    let mut selected = vec![];
+    for item in answer.split(',') {
+        match item.find('-') {
+            Some(pos) => {
+                let (from, to) = (parse(&item[..pos])?, parse(&item[pos + 1..])?);
+                selected.extend(from..=to);
+            },
+            None => selected.push(parse(item)?),
+        }
+    }
+    selected.sort();
+    selected.dedup();
+    Ok(selected)
+}
+
+async fn describe_payload(payload: &str) -> String {
+    if payload.is_empty() {
+        return "none".to_owned();
+    }
+    match decode_transfer_comment(payload).await {
+        Ok(comment) => format!("comment \"{}\"", comment),
+        Err(_) => format!("{} bytes boc", base64::decode(payload).map(|b| b.len()).unwrap_or(0)),
+    }
+}
+
+async fn multisig_confirm_all_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let wallet = m.value_of("ADDRESS")
+        .map(|s| s.to_owned())
+        .or(config.wallet.clone())
+        .ok_or("wallet address is not defined. Supply it in config file or command line.".to_string())?;
+    let wallet = load_ton_address(&wallet, &config)?;
+    let keys = m.value_of("SIGN")
+        .map(|s| s.to_owned())
+        .or(config.keys_path.clone())
+        .ok_or("keypair is not defined. Supply it in config file or command line.".to_string())?;
+    let filters = m.values_of("FILTER").map(|v| v.collect::<Vec<_>>()).unwrap_or_default()
+        .iter()
+        .map(|f| TransactionFilter::parse(f, &config))
+        .collect::<Result<Vec<_>, String>>()?;
+    let pubkey = normalize_pubkey(&load_keypair(&keys)?.public);
+
+    let ton = create_client_verbose(&config)?;
+    let now = now() as u64;
+    let pending: Vec<PendingTransaction> = pending_transactions(ton.clone(), &wallet).await?
+        .into_iter()
+        .filter(|t| t.expires_at > now)
+        .filter(|t| t.unconfirmed.iter().any(|key| normalize_pubkey(key) == pubkey))
+        .filter(|t| filters.iter().all(|f| f.matches(t)))
+        .collect();
+    if pending.is_empty() {
+        println!("No transactions waiting for confirmation by this key.");
+        return Ok(());
+    }
+
+    for (i, trans) in pending.iter().enumerate() {
+        println!("{}. Transaction {}", i + 1, trans.id);
+        println!("   dest:          {}", trans.dest);
+        println!("   value:         {}", convert::nano128_to_tokens(trans.value));
+        println!("   payload:       {}", describe_payload(&trans.payload).await);
+        println!("   creator:       {}", trans.creator);
+        println!("   confirmations: {}/{}", trans.confirmations, trans.required);
+        println!("   expires in:    {}s", trans.expires_at - now);
+    }
+    let selected = if m.is_present("YES") {
+        (0..pending.len()).collect()
+    } else {
+        let answer = prompt(&format!("Confirm transactions (all, none or numbers like 1,3-{}): ", pending.len()))?;
+        parse_selection(&answer, pending.len())?
+    };
+    if selected.is_empty() {
+        println!("Nothing confirmed.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for index in &selected {
+        let trans = &pending[*index];
+        println!("Confirming transaction {}...", trans.id);
+        let params = json!({ "transactionId": trans.id }).to_string();
+        let abi = detect_abi(ton.clone(), &wallet, ContractFamily::Multisig).await?;
+        let result = call::call_contract(
+            config.clone(), &wallet, abi, "confirmTransaction", &params, Some(keys.clone()), false,
+        ).await;
+        if let Err(e) = result {
+            println!("Failed to confirm transaction {}: {}", trans.id, e);
+            failed += 1;
+        }
+    }
+    println!("Confirmed {} of {} transactions.", selected.len() - failed, selected.len());
+    if failed > 0 {
+        return Err(format!("{} confirmations failed", failed));
+    }
+    Ok(())
+}
+
+/// Polling interval while waiting for the deploy value to reach the new wallet.
+const MIGRATE_POLL_INTERVAL: u64 = 5;
+
+struct WalletState {
+    acc_type: String,
+    code_hash: String,
+    balance: u64,
+}
+
+async fn wallet_state(ton: TonClient, addr: &str) -> Result<WalletState, String> {
+    let accounts = query(
+        ton,
+        "accounts",
+        json!({ "id": { "eq": addr } }),
+        "acc_type_name code_hash balance(format: DEC)",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query account {}: {}", addr, e))?;
+    let acc = accounts.get(0).cloned().unwrap_or(json!({}));
+    Ok(WalletState {
+        acc_type: acc["acc_type_name"].as_str().unwrap_or("NonExist").to_owned(),
+        code_hash: acc["code_hash"].as_str().unwrap_or("").to_owned(),
+        balance: acc["balance"].as_str().and_then(|b| u64::from_str_radix(b, 10).ok()).unwrap_or(0),
+    })
+}
+
+fn wallet_type_hash(wallet_type: &str) -> &'static str {
+    WALLET_TYPES.iter().find(|(t, _)| *t == wallet_type).map(|(_, h)| *h).unwrap_or("")
+}
+
+/// Manual steps which can't be done by the tool after the migration.
+fn migration_checklist(old: &str, new: &str, pending: usize) -> Vec<String> {
+    let mut steps = vec![
+        format!("DePool stakes of {} can't be moved by the wallet itself: transfer them with `depool stake transfer --dest {}` or withdraw them with `depool withdraw on`.", old, new),
+        format!("Update subscriptions, recurring payments and services which send funds to {} or expect messages from it.", old),
+        format!("Update tonos-cli config and aliases: `tonos-cli config --wallet {}`.", new),
+        "Update address books of counterparties and DeNS records pointing to the old wallet.".to_owned(),
+        format!("Keep keys of the old wallet until {} has no funds and no incoming transfers.", old),
+    ];
+    if pending > 0 {
+        steps.push(format!("The old wallet has {} pending transactions: confirm or let them expire, they are executed from the old wallet.", pending));
+    }
+    steps
+}
+
+async fn multisig_migrate_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let old = m.value_of("ADDRESS")
+        .map(|s| s.to_owned())
+        .or(config.wallet.clone())
+        .ok_or("wallet address is not defined. Supply it in config file or command line.".to_string())?;
+    let old = load_ton_address(&old, &config)?;
+    let keys = m.value_of("SIGN")
+        .map(|s| s.to_owned())
+        .or(config.keys_path.clone())
+        .ok_or("keypair is not defined. Supply it in config file or command line.".to_string())?;
+    let tvc_path = m.value_of("TVC").map(PathBuf::from)
+        .unwrap_or_else(|| default_wallets_dir().join("setcode.tvc"));
+    let abi_path = m.value_of("ABI").map(PathBuf::from)
+        .unwrap_or_else(|| default_wallets_dir().join("setcode.abi.json"));
+    let wc = parse_wc(m.value_of("WC"), &config)?;
+    let deploy_value = m.value_of("DEPLOY_VALUE").unwrap();
+    let deploy_nano = convert::amount_to_nano(deploy_value)
+        .map_err(|e| format!("failed to parse deploy value: {}", e))?;
+    let ton = create_client_verbose(&config)?;
+
+    println!("Step 1. Old wallet");
+    let old_state = wallet_state(ton.clone(), &old).await?;
+    if old_state.acc_type != "Active" {
+        return Err(format!("wallet {} is not active", old));
+    }
+    if old_state.code_hash != wallet_type_hash("safemultisig") {
+        println!("Warning: {} is not an official SafeMultisig wallet.", old);
+    }
+    let params = call::run_local(ton.clone(), &old, MSIG_ABI, "getParameters", "{}", false).await?;
+    let required = parse_uint(&params["requiredTxnConfirms"]).unwrap_or(1);
+    let custodians = call::run_local(ton.clone(), &old, MSIG_ABI, "getCustodians", "{}", false).await?;
+    let owners: Vec<String> = custodians["custodians"].as_array().cloned().unwrap_or_default()
+        .iter()
+        .map(|c| c["pubkey"].as_str().unwrap_or("").to_owned())
+        .collect();
+    let pending = pending_transactions(ton.clone(), &old).await?;
+    println!("Balance:       {}", convert::nano_to_tokens(old_state.balance));
+    println!("Custodians:    {} (confirmations required: {})", owners.len(), required);
+    for owner in &owners {
+        println!("               {}", owner);
+    }
+
+    println!();
+    println!("Step 2. New wallet address");
+    let tvc = std::fs::read(&tvc_path)
+        .map_err(|e| format!("failed to read tvc file {}: {}", tvc_path.display(), e))?;
+    let abi = std::fs::read_to_string(&abi_path)
+        .map_err(|e| format!("failed to read ABI file {}: {}", abi_path.display(), e))?;
+    if code_hash(&tvc)? != wallet_type_hash("setcode") {
+        println!("Warning: {} doesn't contain the official SetcodeMultisig code.", tvc_path.display());
+    }
+    let keypair = load_keypair(&keys)?;
+    let new = calc_acc_address(&tvc, wc, keypair.public.clone(), None, load_abi(&abi)?).await?;
+    println!("Address: {}", new);
+
+    let mut new_state = wallet_state(ton.clone(), &new).await?;
+    if new_state.acc_type != "Active" {
+        println!();
+        println!("Step 3. Funding");
+        if new_state.balance >= deploy_nano {
+            println!("New address already holds the deploy value.");
+        } else if let Some(trans) = find_submitted(&pending, &new, Some(deploy_nano as u128)) {
+            println!("Transfer of {} to the new address is already submitted (transaction {}), {} of {} confirmations received.",
+                deploy_value, trans.id, trans.confirmations, trans.required);
+            println!("Ask other custodians to confirm it (see `multisig pending --addr {}`) and rerun migrate.", old);
+            return Ok(());
+        } else {
+            send_nobounce(config.clone(), &old, &new, deploy_value, &keys).await?;
+            if required > 1 {
+                println!("Transfer of {} to the new address is submitted and needs {} confirmations.", deploy_value, required);
+                println!("Ask other custodians to confirm it (see `multisig pending --addr {}`) and rerun migrate.", old);
+                return Ok(());
+            }
+            let deadline = std::time::Instant::now() + std::time::Duration::from_millis(config.wait_for_timeout as u64);
+            while new_state.balance < deploy_nano {
+                if std::time::Instant::now() >= deadline {
+                    return Err("deploy value has not reached the new address yet, rerun migrate later".to_owned());
+                }
+                tokio::time::delay_for(std::time::Duration::from_secs(MIGRATE_POLL_INTERVAL)).await;
+                new_state = wallet_state(ton.clone(), &new).await?;
+            }
+        }
+        println!("Balance: {}", convert::nano_to_tokens(new_state.balance));
+
+        println!();
+        println!("Step 4. Deploy");
+        let owners: Vec<String> = owners.iter()
+            .map(|o| if o.starts_with("0x") { o.clone() } else { format!("0x{}", o) })
+            .collect();
+        let params = json!({ "owners": owners, "reqConfirms": required as u64 }).to_string();
+        deploy_contract(
+            config.clone(),
+            &tvc_path.to_string_lossy(),
+            &abi_path.to_string_lossy(),
+            &params,
+            &keys,
+            wc,
+            false,
+            None,
+        ).await?;
+    } else {
+        println!("New wallet is already deployed.");
+    }
+
+    println!();
+    println!("Step 5. Balance transfer");
+    let old_state = wallet_state(ton.clone(), &old).await?;
+    if old_state.balance == 0 {
+        println!("Old wallet is empty.");
+    } else if let Some(trans) = find_submitted(&pending, &new, None) {
+        println!("Transfer of the whole balance is already submitted (transaction {}), {} of {} confirmations received.",
+            trans.id, trans.confirmations, trans.required);
+    } else {
+        let params = json!({
+            "dest": new,
+            "value": old_state.balance.to_string(),
+            "bounce": true,
+            "allBalance": true,
+            "payload": "",
+        }).to_string();
+        call::call_contract(config.clone(), &old, MSIG_ABI.to_string(), "submitTransaction", &params, Some(keys.clone()), false).await?;
+        if required > 1 {
+            println!("Transfer of the whole balance is submitted and needs {} confirmations from other custodians.", required);
+        } else {
+            println!("Balance {} is sent to the new wallet.", convert::nano_to_tokens(old_state.balance));
+        }
+    }
+
+    println!();
+    println!("Remaining manual steps:");
+    for (i, step) in migration_checklist(&old, &new, pending.len()).iter().enumerate() {
+        println!("  {}. {}", i + 1, step);
+    }
+    Ok(())
+}
+
+/// Update requests being tracked by rotate-key, by wallet address.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct TrackedUpdate {
+    update_id: String,
+    owners: Vec<String>,
+    req_confirms: u8,
+    submitted_at: u32,
+}
+
+fn rotate_file_name(config_file: &str) -> String {
+    format!("{}.rotate", config_file.trim_end_matches(".json"))
+}
+
+fn load_tracked_updates(path: &str) -> Result<BTreeMap<String, TrackedUpdate>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => serde_json::from_str(&data)
+            .map_err(|e| format!("failed to parse key rotation state file: {}", e)),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+fn save_tracked_updates(path: &str, updates: &BTreeMap<String, TrackedUpdate>) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(updates)
+        .map_err(|e| format!("failed to serialize key rotation state: {}", e))?;
+    std::fs::write(path, data)
+        .map_err(|e| format!("failed to write key rotation state file: {}", e))
+}
+
+fn parse_owners(owners: &str) -> Result<Vec<String>, String> {
+    let owners: Vec<String> = owners.split(',')
+        .map(|key| key.trim().trim_start_matches("0x").to_lowercase())
+        .filter(|key| !key.is_empty())
+        .collect();
+    if owners.is_empty() {
+        return Err("custodian list is empty".to_owned());
+    }
+    for key in &owners {
+        if key.len() != 64 || hex::decode(key).is_err() {
+            return Err(format!("invalid custodian public key {}", key));
+        }
+    }
+    if owners.iter().collect::<HashSet<_>>().len() != owners.len() {
+        return Err("custodian list contains duplicates".to_owned());
+    }
+    Ok(owners)
+}
+
+#[derive(Debug, PartialEq)]
+enum RotateStep {
+    Confirm,
+    Execute,
+    /// Signer has confirmed, other custodians have to confirm too.
+    Wait(u8),
+}
+
+fn rotate_step(signs: u8, mask: u32, required: u8, signer_index: u8) -> RotateStep {
+    if signs >= required {
+        RotateStep::Execute
+    } else if mask & (1 << signer_index) == 0 {
+        RotateStep::Confirm
+    } else {
+        RotateStep::Wait(required - signs)
+    }
+}
+
+async fn multisig_rotate_key_command(m: &ArgMatches<'_>, config: Config, config_file: &str) -> Result<(), String> {
+    let wallet = m.value_of("ADDRESS")
+        .map(|s| s.to_owned())
+        .or(config.wallet.clone())
+        .ok_or("wallet address is not defined. Supply it in config file or command line.".to_string())?;
+    let wallet = load_ton_address(&wallet, &config)?;
+    let keys = m.value_of("SIGN")
+        .map(|s| s.to_owned())
+        .or(config.keys_path.clone())
+        .ok_or("keypair is not defined. Supply it in config file or command line.".to_string())?;
+    let state_path = rotate_file_name(config_file);
+    let mut tracked = load_tracked_updates(&state_path)?;
+
+    let ton = create_client_verbose(&config)?;
+    let state = wallet_state(ton.clone(), &wallet).await?;
+    if state.acc_type != "Active" {
+        return Err(format!("wallet {} is not active", wallet));
+    }
+    if state.code_hash != wallet_type_hash("setcode") {
+        println!("Warning: {} is not an official SetcodeMultisig wallet.", wallet);
+    }
+    let pubkey = normalize_pubkey(&load_keypair(&keys)?.public);
+    let custodians = call::run_local(ton.clone(), &wallet, SETCODE_UPDATE_ABI, "getCustodians", "{}", false).await?;
+    let signer_index = custodians["custodians"].as_array().cloned().unwrap_or_default()
+        .iter()
+        .find(|c| normalize_pubkey(c["pubkey"].as_str().unwrap_or("")) == pubkey)
+        .and_then(|c| parse_uint(&c["index"]))
+        .ok_or(format!("key {} is not a custodian of {}", pubkey, wallet))? as u8;
+
+    if let Some(owners) = m.value_of("OWNERS") {
+        if let Some(update) = tracked.get(&wallet) {
+            println!("Warning: update request {} for this wallet is already tracked and will be replaced.", update.update_id);
+        }
+        let owners = parse_owners(owners)?;
+        let params = call::run_local(ton.clone(), &wallet, SETCODE_UPDATE_ABI, "getParameters", "{}", false).await?;
+        let req_confirms = match m.value_of("REQ_CONFIRMS") {
+            Some(n) => u8::from_str_radix(n, 10)
+                .map_err(|e| format!("failed to parse number of confirmations: {}", e))?,
+            None => parse_uint(&params["requiredTxnConfirms"]).unwrap_or(1) as u8,
+        };
+        if req_confirms == 0 || req_confirms as usize > owners.len() {
+            return Err(format!("number of confirmations must be between 1 and {}", owners.len()));
+        }
+        let params = json!({
+            "codeHash": format!("0x{}", state.code_hash),
+            "owners": owners.iter().map(|o| format!("0x{}", o)).collect::<Vec<_>>(),
+            "reqConfirms": req_confirms,
+        }).to_string();
+        let result = call::call_contract_with_result(
+            config.clone(), &wallet, SETCODE_UPDATE_ABI.to_string(), "submitUpdate", &params, Some(keys.clone()), false,
+        ).await?;
+        let update_id = result["updateId"].as_str()
+            .ok_or("submitUpdate didn't return update id".to_owned())?
+            .to_owned();
+        println!("Update request {} is submitted and confirmed by this key.", update_id);
+        tracked.insert(wallet.clone(), TrackedUpdate { update_id, owners, req_confirms, submitted_at: now() });
+        save_tracked_updates(&state_path, &tracked)?;
+        println!("Other custodians confirm it by running rotate-key for the wallet, the request is tracked in {}.", state_path);
+        return Ok(());
+    }
+
+    let update_id = match (m.value_of("UPDATE_ID"), tracked.get(&wallet)) {
+        (Some(id), _) => id.to_owned(),
+        (None, Some(update)) => update.update_id.clone(),
+        (None, None) => return Err("no update request is tracked for the wallet, submit one with --owners or pass --update-id".to_owned()),
+    };
+    let updates = call::run_local(ton.clone(), &wallet, SETCODE_UPDATE_ABI, "getUpdateRequests", "{}", false).await?;
+    let update = updates["updates"].as_array().cloned().unwrap_or_default()
+        .into_iter()
+        .find(|u| parse_uint(&u["id"]) == parse_uint(&json!(update_id)));
+    let update = match update {
+        Some(update) => update,
+        None => {
+            if tracked.remove(&wallet).is_some() {
+                save_tracked_updates(&state_path, &tracked)?;
+            }
+            return Err(format!("update request {} is not found: it has expired or was executed already", update_id));
+        },
+    };
+    let params = call::run_local(ton.clone(), &wallet, SETCODE_UPDATE_ABI, "getParameters", "{}", false).await?;
+    let required = parse_uint(&params["requiredUpdConfirms"]).unwrap_or(1) as u8;
+    let signs = parse_uint(&update["signs"]).unwrap_or(0) as u8;
+    let mask = parse_uint(&update["confirmationsMask"]).unwrap_or(0) as u32;
+    println!("Update request {}", update_id);
+    println!("New custodians:");
+    for key in update["custodians"].as_array().cloned().unwrap_or_default() {
+        println!("  {}", key.as_str().unwrap_or(""));
+    }
+    println!("Confirmations required for transactions: {}", parse_uint(&update["reqConfirms"]).unwrap_or(0));
+    println!("Update confirmations: {}/{}", signs, required);
+
+    match rotate_step(signs, mask, required, signer_index) {
+        RotateStep::Confirm => {
+            let params = json!({ "updateId": update_id }).to_string();
+            call::call_contract(
+                config.clone(), &wallet, SETCODE_UPDATE_ABI.to_string(), "confirmUpdate", &params, Some(keys.clone()), false,
+            ).await?;
+            if signs + 1 >= required {
+                println!("Update has enough confirmations, run rotate-key again to execute it.");
+            }
+        },
+        RotateStep::Execute => {
+            let accounts = query(ton.clone(), "accounts", json!({ "id": { "eq": wallet } }), "code", None).await
+                .map_err(|e| format!("failed to query wallet code: {}", e))?;
+            let code = accounts.get(0).and_then(|acc| acc["code"].as_str())
+                .ok_or("wallet code is not available".to_owned())?
+                .to_owned();
+            let params = json!({ "updateId": update_id, "code": code }).to_string();
+            call::call_contract(
+                config.clone(), &wallet, SETCODE_UPDATE_ABI.to_string(), "executeUpdate", &params, Some(keys.clone()), false,
+            ).await?;
+            println!("Custodians of {} are updated.", wallet);
+            if tracked.remove(&wallet).is_some() {
+                save_tracked_updates(&state_path, &tracked)?;
+            }
+            return Ok(());
+        },
+        RotateStep::Wait(left) => {
+            println!("This key has already confirmed the update, {} more confirmations are needed.", left);
+        },
+    }
+    if !tracked.contains_key(&wallet) {
+        let owners = update["custodians"].as_array().cloned().unwrap_or_default()
+            .iter()
+            .map(|key| normalize_pubkey(key.as_str().unwrap_or("")))
+            .collect();
+        tracked.insert(wallet.clone(), TrackedUpdate {
+            update_id,
+            owners,
+            req_confirms: parse_uint(&update["reqConfirms"]).unwrap_or(0) as u8,
+            submitted_at: now(),
+        });
+        save_tracked_updates(&state_path, &tracked)?;
+    }
+    Ok(())
+}
+
+async fn transfer_body(comment: Option<&str>) -> Result<String, String> {
+    if let Some(text) = comment {
+        encode_transfer_body(text).await
+    } else {
+        Ok("".to_owned())
+    }
+}
+
+fn transfer_params(dest: &str, value: &str, body: &str, bounce: bool) -> Result<String, String> {
+    let all_balance = convert::parse_amount(value).map(|amount| amount.is_all()).unwrap_or(false);
+    let value = if all_balance { "0".to_owned() } else { convert::convert_token(value, convert::strict_units())? };
+    Ok(json!({
+        "dest": dest,
+        "value": value,
+        "bounce": bounce,
+        "allBalance": all_balance,
+        "payload": body,
+    }).to_string())
+}
+
 pub async fn encode_transfer_body(text: &str) -> Result<String, String> {
 	let text = hex::encode(text.as_bytes());
 	let client = create_client_local()?;
@@ -227,7 +1163,44 @@ pub async fn encode_transfer_body(text: &str) -> Result<String, String> {
 	.map(|r| r.body)
 }
 
-async fn send(
+pub async fn decode_transfer_comment(body: &str) -> Result<String, String> {
+	let client = create_client_local()?;
+	let result = decode_msg_body(client, TRANSFER_WITH_COMMENT, body, true).await?;
+	let comment = result.value
+		.as_ref()
+		.and_then(|v| v["comment"].as_str())
+		.ok_or("payload doesn't contain comment".to_string())?;
+	String::from_utf8(
+		hex::decode(comment)
+			.map_err(|e| format!("failed to parse comment from transaction payload: {}", e))?
+	).map_err(|e| format!("failed to convert comment to string: {}", e))
+}
+
+/// Converts transfer value given by user (tokens with optional unit or percent
+/// of the wallet balance) to nanotokens accepted by `send`. 100% is kept as is,
+/// it's sent with `allBalance` flag so that fees are paid from the balance.
+pub async fn resolve_value(conf: &Config, wallet: &str, value: &str) -> Result<String, String> {
+    let nano = match convert::parse_amount(value)? {
+        convert::Amount::Nano(nano) => nano,
+        all if all.is_all() => {
+            if !conf.is_json {
+                println!("Whole balance is sent, fees are paid from it.");
+            }
+            return Ok(value.to_owned());
+        },
+        percent => {
+            let balance = account_balance(create_client(conf)?, wallet).await?;
+            let nano = percent.to_nano(balance);
+            if !conf.is_json {
+                println!("{} of balance {} is {}", value, convert::nano_to_tokens(balance), convert::nano_to_tokens(nano));
+            }
+            nano
+        },
+    };
+    Ok(format!("{} nano", nano))
+}
+
+pub async fn send(
     conf: Config,
     addr: &str,
     dest: &str,
@@ -235,15 +1208,76 @@ async fn send(
     keys: &str,
     comment: Option<&str>
 ) -> Result<(), String> {
-    let body = if let Some(text) = comment {
-        encode_transfer_body(text).await?
-    } else {
-        "".to_owned()
-	};
-	
+    let body = transfer_body(comment).await?;
 	send_with_body(conf, addr, dest, value, keys, &body).await
 }
 
+/// Sends non-bounceable transfer, e.g. to fund an address before deploy.
+pub async fn send_nobounce(
+    conf: Config,
+    addr: &str,
+    dest: &str,
+    value: &str,
+    keys: &str,
+) -> Result<(), String> {
+    let params = transfer_params(dest, value, "", false)?;
+    let abi = detect_abi(create_client(&conf)?, addr, ContractFamily::Multisig).await?;
+    call::call_contract(
+        conf,
+        addr,
+        abi,
+        "submitTransaction",
+        &params,
+        Some(keys.to_owned()),
+        false
+    ).await
+    .map_err(|e| e.to_string())
+}
+
+/// Sends non-bounceable transfer with state init attached to the message. Only
+/// wallets with `stateInit` parameter of `submitTransaction` (Multisig 2.0) support it.
+pub async fn send_with_state_init(
+    conf: Config,
+    addr: &str,
+    dest: &str,
+    value: &str,
+    keys: &str,
+    state_init: &str,
+) -> Result<(), String> {
+    let abi = detect_abi(create_client(&conf)?, addr, ContractFamily::Multisig).await?;
+    if !supports_state_init(&abi) {
+        return Err(format!(
+            "wallet {} can't attach state init to a transfer: its submitTransaction has no stateInit parameter. \
+            Use Multisig 2.0 wallet or put its ABI to ~/.tonos-cli/abi/<code hash>.abi.json",
+            addr,
+        ));
+    }
+    let mut params: Value = serde_json::from_str(&transfer_params(dest, value, "", false)?)
+        .map_err(|e| format!("failed to build transfer parameters: {}", e))?;
+    params["stateInit"] = json!(state_init);
+    call::call_contract(
+        conf,
+        addr,
+        abi,
+        "submitTransaction",
+        &params.to_string(),
+        Some(keys.to_owned()),
+        false
+    ).await
+    .map_err(|e| e.to_string())
+}
+
+/// Checks that `submitTransaction` of the wallet ABI accepts state init.
+pub fn supports_state_init(abi: &str) -> bool {
+    serde_json::from_str::<Value>(abi).ok()
+        .and_then(|abi| abi["functions"].as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter(|f| f["name"] == "submitTransaction")
+        .flat_map(|f| f["inputs"].as_array().cloned().unwrap_or_default())
+        .any(|input| input["name"] == "stateInit")
+}
+
 pub async fn send_with_body(
 	conf: Config,
     addr: &str,
@@ -252,21 +1286,97 @@ pub async fn send_with_body(
 	keys: &str,
 	body: &str,
 ) -> Result<(), String> {
-	let params = json!({
-        "dest": dest,
-        "value": convert::convert_token(value)?,
-        "bounce": true,
-        "allBalance": false,
-        "payload": body,
-    }).to_string();
+	let params = transfer_params(dest, value, body, true)?;
+    let abi = detect_abi(create_client(&conf)?, addr, ContractFamily::Multisig).await?;
 
     call::call_contract(
         conf,
         addr,
-        MSIG_ABI.to_string(),
+        abi,
         "submitTransaction",
         &params,
         Some(keys.to_owned()),
         false
     ).await
-}
\ No newline at end of file
+    .map_err(|e| e.to_string())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_submitted() {
+        let transfer = |dest: &str, value: u128, send_flags: u128| PendingTransaction {
+            wallet: "0:01".to_owned(),
+            id: "1".to_owned(),
+            dest: dest.to_owned(),
+            value,
+            expires_at: 0,
+            confirmations: 1,
+            required: 2,
+            unconfirmed: vec![],
+            creator: String::new(),
+            payload: String::new(),
+            send_flags,
+        };
+        let pending = vec![transfer("0:02", 5, 3), transfer("0:03", 7, 131)];
+        assert!(find_submitted(&pending, "0:02", Some(5)).is_some());
+        assert!(find_submitted(&pending, "0:02", Some(6)).is_none());
+        assert!(find_submitted(&pending, "0:02", None).is_none());
+        assert!(find_submitted(&pending, "0:03", None).is_some());
+        assert!(find_submitted(&pending, "0:03", Some(7)).is_none());
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("all", 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(parse_selection("", 3).unwrap(), Vec::<usize>::new());
+        assert_eq!(parse_selection("3, 1-2, 2", 3).unwrap(), vec![0, 1, 2]);
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("x", 3).is_err());
+        assert_eq!(normalize_pubkey("0xABC"), format!("{:0>64}", "abc"));
+    }
+
+    #[test]
+    fn test_transfer_params() {
+        let params: Value = serde_json::from_str(&transfer_params("0:01", "1.5", "", true).unwrap()).unwrap();
+        assert_eq!(params["value"], "1500000000");
+        assert_eq!(params["allBalance"], false);
+        let params: Value = serde_json::from_str(&transfer_params("0:01", "100%", "", true).unwrap()).unwrap();
+        assert_eq!(params["value"], "0");
+        assert_eq!(params["allBalance"], true);
+        assert!(transfer_params("0:01", "50%", "", true).is_err());
+    }
+
+    #[test]
+    fn test_supports_state_init() {
+        assert!(!supports_state_init(MSIG_ABI));
+        let abi = json!({ "functions": [{ "name": "submitTransaction", "inputs": [
+            { "name": "dest", "type": "address" },
+            { "name": "stateInit", "type": "optional(cell)" },
+        ]}]});
+        assert!(supports_state_init(&abi.to_string()));
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        let key = "a".repeat(64);
+        assert_eq!(parse_owners(&format!("0x{}, {}", key.to_uppercase(), "b".repeat(64))).unwrap(), vec![key.clone(), "b".repeat(64)]);
+        assert!(parse_owners(&format!("{},{}", key, key)).is_err());
+        assert!(parse_owners("0x1234").is_err());
+        assert!(parse_owners(" , ").is_err());
+
+        assert_eq!(rotate_step(1, 0b01, 2, 1), RotateStep::Confirm);
+        assert_eq!(rotate_step(1, 0b01, 2, 0), RotateStep::Wait(1));
+        assert_eq!(rotate_step(2, 0b11, 2, 0), RotateStep::Execute);
+    }
+
+    #[test]
+    fn test_migration_checklist() {
+        let steps = migration_checklist("0:1111", "0:2222", 0);
+        assert!(steps.iter().any(|s| s.contains("depool stake transfer --dest 0:2222")));
+        assert!(steps.iter().all(|s| !s.contains("pending")));
+        let steps = migration_checklist("0:1111", "0:2222", 2);
+        assert!(steps.last().unwrap().contains("2 pending transactions"));
+    }
+}
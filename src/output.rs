@@ -0,0 +1,75 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use handlebars::Handlebars;
+use serde_json::Value;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref TEMPLATE: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Sets output template from `--format` or `--format-file` global options.
+pub fn set_template(format: Option<&str>, format_file: Option<&str>) -> Result<(), String> {
+    let template = match (format, format_file) {
+        (Some(format), _) => Some(format.to_owned()),
+        (None, Some(path)) => Some(
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read template file: {}", e))?
+        ),
+        (None, None) => None,
+    };
+    *TEMPLATE.lock().unwrap() = template;
+    Ok(())
+}
+
+pub fn has_template() -> bool {
+    TEMPLATE.lock().unwrap().is_some()
+}
+
+/// Renders handlebars template with the value. Values are not html-escaped.
+pub fn render(template: &str, value: &Value) -> Result<String, String> {
+    let mut hb = Handlebars::new();
+    hb.register_escape_fn(handlebars::no_escape);
+    hb.render_template(template, value)
+        .map_err(|e| format!("failed to render output template: {}", e))
+}
+
+/// Prints structured result of the command with the user template if it is set,
+/// otherwise uses the default printer of the command. Values requested with
+/// `--out` are captured here.
+pub fn print_value<F: FnOnce()>(value: &Value, default: F) -> Result<(), String> {
+    crate::vars::capture(value)?;
+    let template = TEMPLATE.lock().unwrap().clone();
+    match template {
+        Some(template) => {
+            println!("{}", render(&template, value)?);
+            Ok(())
+        },
+        None => {
+            default();
+            Ok(())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let value = json!({ "balance": "100", "acc_type": "Active", "data": { "owner": "<key>" } });
+        assert_eq!(render("{{balance}} {{acc_type}}", &value).unwrap(), "100 Active");
+        assert_eq!(render("{{data.owner}}", &value).unwrap(), "<key>");
+    }
+}
@@ -0,0 +1,195 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::convert::nano_to_tokens;
+use chrono::{Local, TimeZone};
+use serde_json::Value;
+use ton_abi::{Contract, Param, ParamType};
+
+/// Words of parameter names which mark unixtime values (`expireAt`, `created_time`).
+/// Durations like `lifetime` or `timeout` are single words and don't match.
+const TIME_WORDS: [&str; 11] = [
+    "time", "timestamp", "date", "expire", "expires", "expiration", "until", "since", "deadline", "created", "at",
+];
+/// Words of `uint128` parameter names which mark nanotoken values.
+const TOKEN_WORDS: [&str; 11] = [
+    "value", "balance", "amount", "fee", "fees", "stake", "reward", "rewards", "deposit", "price", "grams",
+];
+
+pub fn parse_uint(value: &Value) -> Option<u128> {
+    match value {
+        Value::Number(n) => n.as_u64().map(|n| n as u128),
+        Value::String(s) if s.starts_with("0x") => u128::from_str_radix(&s[2..], 16).ok(),
+        Value::String(s) => u128::from_str_radix(s, 10).ok(),
+        _ => None,
+    }
+}
+
+/// Splits camelCase and snake_case name into lowercase words.
+fn name_words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    for c in name.chars() {
+        if c == '_' || c.is_uppercase() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        if c != '_' {
+            word.extend(c.to_lowercase());
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn is_time_name(name: &str) -> bool {
+    let words = name_words(name);
+    match words.last() {
+        // `at` only as a suffix: `createdAt`, `expire_at`
+        Some(last) if last == "at" => words.len() > 1,
+        _ => words.iter().any(|w| w != "at" && TIME_WORDS.contains(&w.as_str())),
+    }
+}
+
+fn is_token_name(name: &str) -> bool {
+    name_words(name).iter().any(|w| TOKEN_WORDS.contains(&w.as_str()))
+}
+
+fn format_tokens(value: &Value) -> Value {
+    match parse_uint(value) {
+        Some(nano) if nano <= u64::max_value() as u128 => json!(nano_to_tokens(nano as u64)),
+        _ => value.clone(),
+    }
+}
+
+fn format_time(value: &Value) -> Value {
+    match parse_uint(value) {
+        Some(time) if time > 0 && time <= u32::max_value() as u128 => json!(format!(
+            "{} ({})",
+            Local.timestamp(time as i64, 0).format("%Y-%m-%d %H:%M:%S"),
+            time
+        )),
+        _ => value.clone(),
+    }
+}
+
+fn format_bytes(value: &Value) -> Value {
+    let text = value.as_str()
+        .and_then(|s| hex::decode(s).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    match text {
+        Some(text) if !text.is_empty() && !text.chars().any(|c| c.is_control() && c != '\n') => json!(text),
+        _ => value.clone(),
+    }
+}
+
+fn format_cell(value: &Value) -> Value {
+    let hash = value.as_str()
+        .and_then(|s| base64::decode(s).ok())
+        .and_then(|bytes| ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes)).ok())
+        .map(|cell| cell.repr_hash().to_hex_string());
+    match hash {
+        Some(hash) => json!(format!("cell:{}…", &hash[..16])),
+        None => value.clone(),
+    }
+}
+
+fn format_value(name: &str, kind: &ParamType, value: &Value) -> Value {
+    match kind {
+        ParamType::Tuple(params) => format_params(params, value),
+        ParamType::Array(inner) | ParamType::FixedArray(inner, _) => match value.as_array() {
+            Some(items) => Value::Array(items.iter().map(|v| format_value(name, inner, v)).collect()),
+            None => value.clone(),
+        },
+        ParamType::Cell => format_cell(value),
+        ParamType::Bytes => format_bytes(value),
+        _ => {
+            let signature = kind.type_signature();
+            if signature == "gram" || signature == "token" || (signature == "uint128" && is_token_name(name)) {
+                format_tokens(value)
+            } else if signature == "time" || ((signature == "uint32" || signature == "uint64") && is_time_name(name)) {
+                format_time(value)
+            } else {
+                value.clone()
+            }
+        }
+    }
+}
+
+fn format_params(params: &[Param], value: &Value) -> Value {
+    let mut result = value.clone();
+    if let Some(object) = result.as_object_mut() {
+        for param in params {
+            if let Some(v) = object.get_mut(&param.name) {
+                *v = format_value(&param.name, &param.kind, v);
+            }
+        }
+    }
+    result
+}
+
+/// Formats decoded function output using types of output parameters from ABI:
+/// tokens as decimal values, unixtimes as dates, printable bytes as strings and cells as hashes.
+pub fn format_output(abi: &str, method: &str, output: &Value) -> Value {
+    let contract = match Contract::load(abi.as_bytes()) {
+        Ok(contract) => contract,
+        Err(_) => return output.clone(),
+    };
+    match contract.functions().get(method) {
+        Some(function) => format_params(&function.outputs, output),
+        None => output.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_values() {
+        assert_eq!(format_value("balance", &ParamType::Uint(128), &json!("1500000000")), json!("1.500000000"));
+        assert_eq!(format_value("count", &ParamType::Uint(32), &json!("10")), json!("10"));
+        assert_eq!(format_value("comment", &ParamType::Bytes, &json!(hex::encode("hello"))), json!("hello"));
+        assert_eq!(format_value("data", &ParamType::Bytes, &json!("00ff")), json!("00ff"));
+        assert_eq!(format_value("minValue", &ParamType::Uint(128), &json!("1000000000")), json!("1.000000000"));
+    }
+
+    #[test]
+    fn test_name_classification() {
+        assert!(is_time_name("expireAt"));
+        assert!(is_time_name("created_time"));
+        assert!(is_time_name("unlockDate"));
+        assert!(is_time_name("validUntil"));
+        assert!(!is_time_name("lifetime"));
+        assert!(!is_time_name("timeout"));
+        assert!(!is_time_name("format"));
+        assert!(!is_time_name("heartbeat"));
+        assert!(!is_time_name("at"));
+        assert!(!is_time_name("updatesCount"));
+
+        assert!(is_token_name("balance"));
+        assert!(is_token_name("total_stake"));
+        assert!(!is_token_name("totalSupply"));
+        assert!(!is_token_name("id"));
+    }
+
+    #[test]
+    fn test_no_false_positives() {
+        assert_eq!(format_value("lifetime", &ParamType::Uint(32), &json!("3600")), json!("3600"));
+        assert_eq!(format_value("repeat", &ParamType::Uint(32), &json!("5")), json!("5"));
+        assert_eq!(format_value("totalSupply", &ParamType::Uint(128), &json!("1000")), json!("1000"));
+        assert_eq!(format_value("nonce", &ParamType::Uint(128), &json!("7")), json!("7"));
+    }
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Message relay backend: external messages are broadcast by HTTP POST to
+//! a REST endpoint instead of GraphQL `postRequests`. Queries (shard block
+//! lookup and transaction waiting) still go to the GraphQL endpoint.
+//!
+//! Request body is `{"id": "<message hash>", "boc": "<base64>"}`, any 2xx
+//! response means the message is accepted by the relay. Requests are retried
+//! on 5xx responses and timeouts according to the network policy.
+use crate::config::{Config, NetworkPolicy};
+use crate::helpers::{create_http_client, with_policy_if, TonClient};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use ton_client::abi::Abi;
+use ton_client::error::ClientError;
+use ton_client::boc::{parse_message, ParamsOfParse};
+use ton_client::net::{find_last_shard_block, ParamsOfFindLastShardBlock};
+use ton_client::processing::{send_message, ParamsOfSendMessage};
+
+/// Error code of relay responses which are worth retrying (5xx, timeouts).
+const RELAY_UNAVAILABLE: u32 = 1;
+/// Error code of messages rejected by the relay and failed requests.
+const RELAY_REJECTED: u32 = 2;
+
+#[derive(Clone)]
+struct Relay {
+    url: String,
+    headers: BTreeMap<String, String>,
+    client: reqwest::Client,
+    policy: NetworkPolicy,
+    quiet: bool,
+}
+
+lazy_static! {
+    static ref RELAY: Mutex<Option<Relay>> = Mutex::new(None);
+}
+
+/// Enables relay backend if `relay_url` is set in config. Http headers are
+/// taken from `endpoint_headers` of the relay url.
+pub fn init(conf: &Config) -> Result<(), String> {
+    let relay = match &conf.relay_url {
+        // nothing is sent in offline mode
+        Some(_) if conf.offline => None,
+        Some(url) => Some(Relay {
+            url: url.clone(),
+            headers: conf.headers_for(url)?,
+            client: create_http_client(conf)?,
+            policy: NetworkPolicy {
+                retries: Some(conf.network_policy.retries.unwrap_or(conf.retries)),
+                timeout: conf.network_policy.timeout,
+            },
+            quiet: conf.is_json,
+        }),
+        None => None,
+    };
+    *RELAY.lock().unwrap() = relay;
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    RELAY.lock().unwrap().is_some()
+}
+
+async fn post(relay: &Relay, id: &str, boc: &str) -> Result<(), ClientError> {
+    let mut request = relay.client
+        .post(&relay.url)
+        .json(&json!({ "id": id, "boc": boc }));
+    for (name, value) in &relay.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = request.send().await
+        .map_err(|e| ClientError::with_code_message(
+            if e.is_timeout() { RELAY_UNAVAILABLE } else { RELAY_REJECTED },
+            format!("relay request failed: {}", e),
+        ))?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        let code = if status.is_server_error() { RELAY_UNAVAILABLE } else { RELAY_REJECTED };
+        return Err(ClientError::with_code_message(
+            code, format!("relay rejected message: {} {}", status, text.trim()),
+        ));
+    }
+    Ok(())
+}
+
+/// Sends message through the relay if it's configured or through GraphQL endpoint.
+/// Returns id of the last shard block before sending, it is used to wait for the transaction.
+pub async fn broadcast(ton: TonClient, message: &str, abi: Option<Abi>) -> Result<String, String> {
+    if !is_enabled() {
+        let callback = |_| async move {};
+        return send_message(
+            ton,
+            ParamsOfSendMessage { message: message.to_owned(), abi, send_events: false, ..Default::default() },
+            callback,
+        ).await
+        .map(|sent| sent.shard_block_id)
+        .map_err(|e| format!("{:#}", e));
+    }
+    send_through_relay(ton, message).await
+}
+
+/// Broadcasts message through the relay with retries.
+async fn send_through_relay(ton: TonClient, message: &str) -> Result<String, String> {
+    let relay = RELAY.lock().unwrap().clone()
+        .ok_or("relay is not configured".to_owned())?;
+    let parsed = parse_message(ton.clone(), ParamsOfParse { boc: message.to_owned() }).await
+        .map_err(|e| format!("failed to parse message: {}", e))?
+        .parsed;
+    let id = parsed["id"].as_str().unwrap_or("").to_owned();
+    let address = parsed["dst"].as_str().unwrap_or("").to_owned();
+    let shard_block_id = find_last_shard_block(ton, ParamsOfFindLastShardBlock { address }).await
+        .map_err(|e| format!("failed to find last shard block: {}", e))?
+        .block_id;
+
+    // policy timeouts have code 0 and are retried as well
+    let retryable = |e: &ClientError| {
+        let retry = e.code != RELAY_REJECTED;
+        if retry && !relay.quiet {
+            println!("{}, retrying...", e.message);
+        }
+        retry
+    };
+    with_policy_if(relay.policy, retryable, || post(&relay, &id, message)).await
+        .map_err(|e| e.message)?;
+    Ok(shard_block_id)
+}
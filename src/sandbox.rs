@@ -0,0 +1,572 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::query_account_boc;
+use crate::config::Config;
+use crate::convert::nano_to_tokens;
+use crate::crypto::load_keypair;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_local, create_client_verbose, load_abi, load_ton_address, now, TonClient};
+use crate::pretty::parse_uint;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::path::Path;
+use ton_client::abi::{encode_message, Abi, CallSet, FunctionHeader, MessageBodyType, ParamsOfEncodeMessage, Signer};
+use ton_client::boc::{parse_account, parse_message, ParamsOfParse};
+use ton_client::tvm::{run_executor, run_tvm, AccountForExecutor, ExecutionOptions, ParamsOfRunExecutor, ParamsOfRunTvm};
+
+/// Max number of messages processed within one scenario step, protects from
+/// endless message loops between sandbox accounts.
+const MAX_MESSAGES_PER_STEP: usize = 100;
+
+/// Logical time reserved for one transaction in sandbox.
+const LT_PER_TRANSACTION: u64 = 1000;
+
+/// Lifetime of external messages generated by sandbox (in seconds of sandbox time).
+const MESSAGE_LIFETIME: u32 = 60;
+
+pub fn create_sandbox_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("sandbox")
+        .about("Local emulation of message chains between several accounts.")
+        .subcommand(SubCommand::with_name("run")
+            .about("Executes scenario calls locally, routing internal messages between sandbox accounts, and reports state changes.")
+            .arg(Arg::with_name("SCENARIO")
+                .required(true)
+                .takes_value(true)
+                .help("Json file with scenario: {\"accounts\": {<name>: {\"address\", \"abi\", \"boc\"?}}, \"steps\": [{\"account\", \"method\", \"params\", \"keys\"?}]}. Accounts without boc file are loaded from the network. Step {\"advance\": {\"seconds\", \"lt\"?}} moves sandbox time forward."))
+            .arg(Arg::with_name("SAVE_DIR")
+                .long("--save-dir")
+                .takes_value(true)
+                .help("Directory where to store final account states (<name>.boc)."))
+            .arg(Arg::with_name("NOW")
+                .long("--now")
+                .takes_value(true)
+                .help("Unix time of the first step (default: scenario \"now\" field or current time).")))
+}
+
+pub async fn sandbox_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("run") {
+        return run_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown sandbox command"))
+}
+
+struct SandboxAccount {
+    name: String,
+    abi: Abi,
+    boc: String,
+    initial_balance: u128,
+}
+
+/// Result of a transaction executed in sandbox.
+pub struct SandboxTransaction {
+    pub account: String,
+    pub aborted: bool,
+    pub exit_code: Value,
+    pub output: Option<Value>,
+    /// Events emitted by the transaction as `{"name", "value"}`.
+    pub events: Vec<Value>,
+}
+
+pub struct Sandbox {
+    ton: TonClient,
+    /// Accounts by address.
+    accounts: BTreeMap<String, SandboxAccount>,
+    /// Prints every executed transaction.
+    pub verbose: bool,
+    /// Sandbox block time, moved only by `advance` directives.
+    now: u32,
+    /// Logical time of the next transaction.
+    lt: u64,
+    /// Number of external messages generated at the current time, used to keep
+    /// message timestamps unique for replay protection.
+    messages_at_now: u64,
+    /// Max number of messages processed within one step.
+    max_messages: usize,
+}
+
+impl Sandbox {
+    /// Loads accounts described as `{<name>: {"address", "abi", "boc"?}}`, file paths are
+    /// relative to `dir`. Accounts without boc file are loaded from the network.
+    pub async fn load(config: &Config, accounts: &Value, dir: &Path) -> Result<Self, String> {
+        let ton = create_client_local()?;
+        let mut sandbox = Sandbox {
+            ton: ton.clone(),
+            accounts: BTreeMap::new(),
+            verbose: true,
+            now: now(),
+            lt: LT_PER_TRANSACTION,
+            messages_at_now: 0,
+            max_messages: MAX_MESSAGES_PER_STEP,
+        };
+        let accounts = accounts.as_object()
+            .ok_or("accounts are not defined")?;
+        let mut network = None;
+        for (name, account) in accounts {
+            let address = load_ton_address(account["address"].as_str().unwrap_or(""), config)?;
+            let abi = std::fs::read_to_string(file(dir, account["abi"].as_str().unwrap_or("")))
+                .map_err(|e| format!("failed to read ABI of {}: {}", name, e))?;
+            let boc = match account["boc"].as_str() {
+                Some(boc_file) => base64::encode(&std::fs::read(file(dir, boc_file))
+                    .map_err(|e| format!("failed to read state of {}: {}", name, e))?),
+                None => {
+                    if network.is_none() {
+                        network = Some(create_client_verbose(config)?);
+                    }
+                    query_account_boc(network.clone().unwrap(), &address).await
+                        .map_err(|e| format!("failed to load {} from network: {}", name, e))?
+                },
+            };
+            let initial_balance = account_balance(ton.clone(), &boc).await?;
+            sandbox.accounts.insert(address, SandboxAccount {
+                name: name.clone(),
+                abi: load_abi(&abi)?,
+                boc,
+                initial_balance,
+            });
+        }
+        Ok(sandbox)
+    }
+
+    /// Sets sandbox block time.
+    pub fn set_now(&mut self, now: u32) {
+        self.now = now;
+        self.messages_at_now = 0;
+    }
+
+    /// Moves sandbox time forward. Directive is either number of seconds or
+    /// `{"seconds"?, "lt"?}`.
+    pub fn advance(&mut self, directive: &Value) -> Result<(), String> {
+        let (seconds, lt) = match directive {
+            Value::Object(_) => (
+                parse_uint(&directive["seconds"]).unwrap_or(0),
+                parse_uint(&directive["lt"]).unwrap_or(0),
+            ),
+            seconds => (parse_uint(seconds).ok_or(format!("invalid advance directive: {}", directive))?, 0),
+        };
+        let now = u32::try_from(seconds).ok()
+            .and_then(|seconds| self.now.checked_add(seconds))
+            .ok_or(format!("advance by {} seconds overflows block time", seconds))?;
+        let lt = u64::try_from(lt).ok()
+            .and_then(|lt| self.lt.checked_add(lt))
+            .ok_or(format!("advance by {} lt overflows logical time", lt))?;
+        if now != self.now {
+            self.set_now(now);
+        }
+        self.lt = lt;
+        if self.verbose {
+            println!("  time advanced to {} (lt {})", self.now, self.lt);
+        }
+        Ok(())
+    }
+
+    fn execution_options(&self) -> ExecutionOptions {
+        ExecutionOptions {
+            block_time: Some(self.now),
+            block_lt: Some(self.lt),
+            transaction_lt: Some(self.lt + 1),
+            ..Default::default()
+        }
+    }
+
+    fn find(&self, name: &str) -> Result<String, String> {
+        self.accounts.iter()
+            .find(|(_, acc)| acc.name == name)
+            .map(|(addr, _)| addr.clone())
+            .ok_or(format!("account {} is not defined", name))
+    }
+
+    fn name(&self, addr: &str) -> String {
+        self.accounts.get(addr).map(|acc| acc.name.clone()).unwrap_or(addr.to_owned())
+    }
+
+    /// Encodes external message for the call `{"account", "method", "params", "keys"?}`.
+    async fn encode_call(&self, call: &Value, dir: &Path) -> Result<(String, String), String> {
+        let name = call["account"].as_str().unwrap_or("");
+        let method = call["method"].as_str().unwrap_or("");
+        let addr = self.find(name)?;
+        let signer = match call["keys"].as_str() {
+            Some(keys) => Signer::Keys { keys: load_keypair(&file(dir, keys)).or(load_keypair(keys))? },
+            None => Signer::None,
+        };
+        let params = if call["params"].is_null() { json!({}) } else { call["params"].clone() };
+        // header follows sandbox time, otherwise messages expire after time travel
+        let header = FunctionHeader {
+            time: Some(self.now as u64 * 1000 + self.messages_at_now),
+            expire: Some(self.now + MESSAGE_LIFETIME),
+            pubkey: None,
+        };
+        let message = encode_message(
+            self.ton.clone(),
+            ParamsOfEncodeMessage {
+                abi: self.accounts[&addr].abi.clone(),
+                address: Some(addr.clone()),
+                call_set: Some(CallSet {
+                    function_name: method.to_owned(),
+                    header: Some(header),
+                    input: Some(params),
+                }),
+                signer,
+                ..Default::default()
+            },
+        ).await
+        .map_err(|e| format!("failed to encode message for {}.{}: {}", name, method, e))?;
+        Ok((addr, message.message))
+    }
+
+    /// Executes scenario step: either `{"advance": ...}` directive or a call.
+    pub async fn step(&mut self, step: &Value, dir: &Path) -> Result<Vec<SandboxTransaction>, String> {
+        if !step["advance"].is_null() {
+            self.advance(&step["advance"])?;
+            return Ok(vec![]);
+        }
+        self.call(step, dir).await
+    }
+
+    /// Executes the call and all internal messages it causes between sandbox accounts.
+    pub async fn call(&mut self, call: &Value, dir: &Path) -> Result<Vec<SandboxTransaction>, String> {
+        let (_, message) = self.encode_call(call, dir).await?;
+        self.messages_at_now += 1;
+        self.process(message).await
+    }
+
+    /// Runs getter locally on the current account state without changing it.
+    pub async fn run_getter(&self, call: &Value, dir: &Path) -> Result<Value, String> {
+        let (addr, message) = self.encode_call(call, dir).await?;
+        let account = &self.accounts[&addr];
+        let result = run_tvm(
+            self.ton.clone(),
+            ParamsOfRunTvm {
+                message,
+                account: account.boc.clone(),
+                abi: Some(account.abi.clone()),
+                execution_options: Some(self.execution_options()),
+                ..Default::default()
+            },
+        ).await
+        .map_err(|e| format!("getter failed on {}: {:#}", account.name, e))?;
+        Ok(result.decoded.and_then(|d| d.output).unwrap_or(json!({})))
+    }
+
+    async fn process(&mut self, message: String) -> Result<Vec<SandboxTransaction>, String> {
+        let mut transactions = vec![];
+        let mut queue = VecDeque::new();
+        queue.push_back(message);
+        let mut processed = 0;
+        while let Some(message) = queue.pop_front() {
+            processed += 1;
+            if processed > self.max_messages {
+                return Err(format!("more than {} messages in one step, possible message loop", self.max_messages));
+            }
+            let parsed = parse_message(self.ton.clone(), ParamsOfParse { boc: message.clone() }).await
+                .map_err(|e| format!("failed to parse message: {}", e))?
+                .parsed;
+            let dst = parsed["dst"].as_str().unwrap_or("").to_owned();
+            let src = parsed["src"].as_str().unwrap_or("");
+            let account = match self.accounts.get(&dst) {
+                Some(account) => account,
+                None => {
+                    if self.verbose && !dst.is_empty() {
+                        println!("  message {} -> {} leaves sandbox (value {})", self.name(src), dst,
+                            nano_to_tokens(parse_uint(&parsed["value"]).unwrap_or(0) as u64));
+                    }
+                    continue;
+                },
+            };
+            let result = run_executor(
+                self.ton.clone(),
+                ParamsOfRunExecutor {
+                    message,
+                    account: AccountForExecutor::Account { boc: account.boc.clone(), unlimited_balance: None },
+                    abi: Some(account.abi.clone()),
+                    return_updated_account: Some(true),
+                    execution_options: Some(self.execution_options()),
+                    // failed transactions are reported as results like on the network
+                    skip_transaction_check: Some(true),
+                    ..Default::default()
+                },
+            ).await
+            .map_err(|e| format!("execution on {} failed: {:#}", account.name, e))?;
+
+            let tx = &result.transaction;
+            let executed = SandboxTransaction {
+                account: account.name.clone(),
+                aborted: tx["aborted"].as_bool().unwrap_or(false),
+                exit_code: tx["compute"]["exit_code"].clone(),
+                output: result.decoded.as_ref().and_then(|d| d.output.clone()),
+                events: result.decoded.as_ref()
+                    .map(|d| d.out_messages.iter()
+                        .flatten()
+                        .filter(|m| matches!(m.body_type, MessageBodyType::Event))
+                        .map(|m| json!({ "name": m.name, "value": m.value }))
+                        .collect())
+                    .unwrap_or_default(),
+            };
+            if self.verbose || executed.aborted {
+                let from = if src.is_empty() { "external".to_owned() } else { self.name(src) };
+                println!(
+                    "  {} -> {}: aborted: {}, exit code: {}, fees: {}",
+                    from,
+                    account.name,
+                    executed.aborted,
+                    executed.exit_code,
+                    nano_to_tokens(result.fees.total_account_fees),
+                );
+                if let Some(output) = &executed.output {
+                    println!("    output: {}", output);
+                }
+            }
+            self.accounts.get_mut(&dst).unwrap().boc = result.account;
+            self.lt += LT_PER_TRANSACTION;
+            queue.extend(result.out_messages);
+            transactions.push(executed);
+        }
+        Ok(transactions)
+    }
+}
+
+fn file(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().into_owned()
+}
+
+async fn account_balance(ton: TonClient, boc: &str) -> Result<u128, String> {
+    let parsed = parse_account(ton, ParamsOfParse { boc: boc.to_owned() }).await
+        .map_err(|e| format!("failed to parse account: {}", e))?
+        .parsed;
+    Ok(parse_uint(&parsed["balance"]).unwrap_or(0))
+}
+
+async fn run_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let path = m.value_of("SCENARIO").unwrap();
+    let scenario: Value = serde_json::from_str(
+        &std::fs::read_to_string(path).map_err(|e| format!("failed to read scenario file: {}", e))?
+    ).map_err(|e| format!("failed to parse scenario file: {}", e))?;
+    let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+
+    let mut sandbox = Sandbox::load(&config, &scenario["accounts"], dir).await?;
+    let start = match m.value_of("NOW") {
+        Some(now) => Some(u32::from_str_radix(now, 10)
+            .map_err(|e| format!("failed to parse --now: {}", e))?),
+        None => scenario["now"].as_u64().map(|now| now as u32),
+    };
+    if let Some(now) = start {
+        sandbox.set_now(now);
+    }
+    let steps = scenario["steps"].as_array().cloned().unwrap_or_default();
+    for (i, step) in steps.iter().enumerate() {
+        if step["advance"].is_null() {
+            println!("Step {}: {}.{}", i + 1, step["account"].as_str().unwrap_or(""), step["method"].as_str().unwrap_or(""));
+        } else {
+            println!("Step {}: advance {}", i + 1, step["advance"]);
+        }
+        sandbox.step(step, dir).await?;
+    }
+
+    println!();
+    println!("State changes:");
+    for (addr, account) in &sandbox.accounts {
+        let balance = account_balance(sandbox.ton.clone(), &account.boc).await?;
+        let delta = balance as i128 - account.initial_balance as i128;
+        println!(
+            "  {} ({}): balance {} -> {} ({}{})",
+            account.name,
+            addr,
+            nano_to_tokens(account.initial_balance as u64),
+            nano_to_tokens(balance as u64),
+            if delta < 0 { "-" } else { "+" },
+            nano_to_tokens(delta.abs() as u64),
+        );
+    }
+    if let Some(dir) = m.value_of("SAVE_DIR") {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create directory {}: {}", dir, e))?;
+        for account in sandbox.accounts.values() {
+            let path = Path::new(dir).join(format!("{}.boc", account.name));
+            let boc = base64::decode(&account.boc)
+                .map_err(|e| format!("failed to decode account state: {}", e))?;
+            std::fs::write(&path, boc)
+                .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        }
+        println!("Final states saved to {}", dir);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use ton_block::{CurrencyCollection, InternalMessageHeader, Message, MsgAddressInt, Serializable};
+    use ton_client::abi::{
+        decode_message, encode_account, encode_message_body, ParamsOfDecodeMessage, ParamsOfEncodeAccount,
+        ParamsOfEncodeMessageBody,
+    };
+
+    const WALLET_ABI: &str = "tests/samples/wallet.abi.json";
+    const WALLET_TVC: &str = "tests/samples/wallet.tvc";
+    const WALLET_KEYS: &str = "tests/samples/test.keys.json";
+    const NOW: u32 = 1_600_000_000;
+    const OUTSIDE: &str = "0:1111111111111111111111111111111111111111111111111111111111111111";
+
+    /// Sandbox with accounts of tests/samples/wallet.sol: "alice" with keys
+    /// from tests/samples/test.keys.json and "bob". Returns their addresses.
+    async fn wallets() -> (Sandbox, String, String) {
+        let ton = create_client_local().unwrap();
+        let abi = load_abi(&std::fs::read_to_string(WALLET_ABI).unwrap()).unwrap();
+        let tvc = base64::encode(&std::fs::read(WALLET_TVC).unwrap());
+        let alice_key = load_keypair(WALLET_KEYS).unwrap().public;
+        let bob_key = "2222222222222222222222222222222222222222222222222222222222222222";
+        let mut accounts = BTreeMap::new();
+        let mut addresses = vec![];
+        for (name, public) in &[("alice", alice_key.as_str()), ("bob", bob_key)] {
+            let params: ParamsOfEncodeAccount = serde_json::from_value(json!({
+                "state_init": { "type": "Tvc", "tvc": tvc, "public_key": public },
+                "balance": 10_000_000_000u64,
+                "last_paid": NOW,
+            })).unwrap();
+            let account = encode_account(ton.clone(), params).await.unwrap();
+            accounts.insert(account.id.clone(), SandboxAccount {
+                name: name.to_string(),
+                abi: abi.clone(),
+                boc: account.account,
+                initial_balance: 10_000_000_000,
+            });
+            addresses.push(account.id);
+        }
+        let sandbox = Sandbox {
+            ton,
+            accounts,
+            verbose: false,
+            now: NOW,
+            lt: LT_PER_TRANSACTION,
+            messages_at_now: 0,
+            max_messages: MAX_MESSAGES_PER_STEP,
+        };
+        (sandbox, addresses.remove(0), addresses.remove(0))
+    }
+
+    fn transfer(dest: &str) -> Value {
+        json!({
+            "account": "alice",
+            "method": "sendTransaction",
+            "params": { "dest": dest, "value": "1000000000", "bounce": false },
+            "keys": WALLET_KEYS,
+        })
+    }
+
+    /// Internal message from `src` to `dst` calling wallet function `sendTransaction`.
+    async fn internal_call(sandbox: &Sandbox, src: &str, dst: &str) -> String {
+        let body = encode_message_body(
+            sandbox.ton.clone(),
+            ParamsOfEncodeMessageBody {
+                abi: sandbox.accounts[dst].abi.clone(),
+                call_set: CallSet {
+                    function_name: "sendTransaction".to_owned(),
+                    header: None,
+                    input: Some(json!({ "dest": src, "value": "1000000000", "bounce": false })),
+                },
+                is_internal: true,
+                signer: Signer::None,
+                ..Default::default()
+            },
+        ).await.unwrap().body;
+        let mut header = InternalMessageHeader::with_addresses(
+            MsgAddressInt::from_str(src).unwrap(),
+            MsgAddressInt::from_str(dst).unwrap(),
+            CurrencyCollection::with_grams(1_000_000_000),
+        );
+        header.bounce = false;
+        let mut message = Message::with_int_header(header);
+        let body = ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(base64::decode(&body).unwrap())).unwrap();
+        message.set_body(body.into());
+        base64::encode(&ton_types::serialize_toc(&message.serialize().unwrap()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_process_internal_messages() {
+        let (mut sandbox, _, bob) = wallets().await;
+        let transactions = sandbox.call(&transfer(&bob), Path::new(".")).await.unwrap();
+        let accounts: Vec<&str> = transactions.iter().map(|tx| tx.account.as_str()).collect();
+        assert_eq!(accounts, vec!["alice", "bob"]);
+        assert!(transactions.iter().all(|tx| !tx.aborted));
+        let balance = account_balance(sandbox.ton.clone(), &sandbox.accounts[&bob].boc).await.unwrap();
+        assert!(balance > sandbox.accounts[&bob].initial_balance);
+        assert_eq!(sandbox.lt, LT_PER_TRANSACTION * 3);
+    }
+
+    #[tokio::test]
+    async fn test_message_limit() {
+        let (mut sandbox, _, bob) = wallets().await;
+        sandbox.max_messages = 1;
+        let err = sandbox.call(&transfer(&bob), Path::new(".")).await.err().unwrap();
+        assert!(err.contains("possible message loop"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_message_leaves_sandbox() {
+        let (mut sandbox, _, _) = wallets().await;
+        let transactions = sandbox.call(&transfer(OUTSIDE), Path::new(".")).await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].account, "alice");
+        assert!(!transactions[0].aborted);
+    }
+
+    #[tokio::test]
+    async fn test_aborted_transaction() {
+        let (mut sandbox, alice, bob) = wallets().await;
+        // internal call has no signature, so the owner check of the wallet fails
+        let message = internal_call(&sandbox, &bob, &alice).await;
+        let transactions = sandbox.process(message).await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].account, "alice");
+        assert!(transactions[0].aborted);
+        assert_eq!(transactions[0].exit_code, json!(100));
+    }
+
+    /// Returns `expire` header of the external message generated for the call.
+    async fn message_expire(sandbox: &Sandbox, call: &Value) -> u32 {
+        let (addr, message) = sandbox.encode_call(call, Path::new(".")).await.unwrap();
+        let decoded = decode_message(
+            sandbox.ton.clone(),
+            ParamsOfDecodeMessage { abi: sandbox.accounts[&addr].abi.clone(), message },
+        ).await.unwrap();
+        decoded.header.and_then(|h| h.expire).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_advance() {
+        let (mut sandbox, _, bob) = wallets().await;
+        assert_eq!(message_expire(&sandbox, &transfer(&bob)).await, NOW + MESSAGE_LIFETIME);
+
+        sandbox.advance(&json!(100)).unwrap();
+        assert_eq!(sandbox.execution_options().block_time, Some(NOW + 100));
+        assert_eq!(message_expire(&sandbox, &transfer(&bob)).await, NOW + 100 + MESSAGE_LIFETIME);
+
+        sandbox.advance(&json!({ "seconds": 10, "lt": 500 })).unwrap();
+        assert_eq!(sandbox.execution_options().block_time, Some(NOW + 110));
+        assert_eq!(sandbox.execution_options().block_lt, Some(LT_PER_TRANSACTION + 500));
+
+        sandbox.set_now(NOW + 1000);
+        assert_eq!(sandbox.execution_options().block_time, Some(NOW + 1000));
+        assert_eq!(message_expire(&sandbox, &transfer(&bob)).await, NOW + 1000 + MESSAGE_LIFETIME);
+
+        assert!(sandbox.advance(&json!(u32::MAX)).is_err());
+        assert!(sandbox.advance(&json!({ "lt": u64::MAX })).is_err());
+        assert!(sandbox.advance(&json!({ "lt": "340282366920938463463374607431768211455" })).is_err());
+        assert!(sandbox.advance(&json!("x")).is_err());
+        // failed directives don't move the time
+        assert_eq!(sandbox.execution_options().block_time, Some(NOW + 1000));
+        assert_eq!(sandbox.execution_options().block_lt, Some(LT_PER_TRANSACTION + 500));
+    }
+}
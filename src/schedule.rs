@@ -0,0 +1,267 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::call_contract;
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::now;
+use chrono::{Local, TimeZone};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Contract call which must be sent at the specified time.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledCall {
+    pub id: u32,
+    pub send_at: u32,
+    pub address: String,
+    pub abi: String,
+    pub method: String,
+    pub params: String,
+    pub keys: Option<String>,
+}
+
+pub fn schedule_file_name(config_file: &str) -> String {
+    format!("{}.schedule", config_file.trim_end_matches(".json"))
+}
+
+/// Lock file which serializes access to the schedule file between running commands.
+/// Lock older than `STALE_LOCK_SECS` is considered left by a crashed process.
+struct QueueLock {
+    path: String,
+}
+
+const LOCK_RETRIES: u32 = 100;
+const LOCK_RETRY_DELAY_MS: u64 = 100;
+const STALE_LOCK_SECS: u64 = 30;
+
+impl QueueLock {
+    fn acquire(queue_path: &str) -> Result<Self, String> {
+        let path = format!("{}.lock", queue_path);
+        for _ in 0..LOCK_RETRIES {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|t| t.elapsed().ok())
+                        .map(|age| age.as_secs() > STALE_LOCK_SECS)
+                        .unwrap_or(false);
+                    if stale {
+                        let _ = std::fs::remove_file(&path);
+                    } else {
+                        std::thread::sleep(Duration::from_millis(LOCK_RETRY_DELAY_MS));
+                    }
+                },
+                Err(e) => return Err(format!("failed to lock schedule file: {}", e)),
+            }
+        }
+        Err(format!("failed to lock schedule file: {} is held by another process", path))
+    }
+}
+
+impl Drop for QueueLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Loads the queue, updates it and saves it back while holding the lock.
+fn update_queue<T>(
+    path: &str,
+    update: impl FnOnce(&mut Vec<ScheduledCall>) -> Result<T, String>,
+) -> Result<T, String> {
+    let _lock = QueueLock::acquire(path)?;
+    let mut queue = load_queue(path)?;
+    let result = update(&mut queue)?;
+    save_queue(path, &queue)?;
+    Ok(result)
+}
+
+fn read_queue(path: &str) -> Result<Vec<ScheduledCall>, String> {
+    let _lock = QueueLock::acquire(path)?;
+    load_queue(path)
+}
+
+fn load_queue(path: &str) -> Result<Vec<ScheduledCall>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => serde_json::from_str(&data)
+            .map_err(|e| format!("failed to parse schedule file: {}", e)),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+fn save_queue(path: &str, queue: &[ScheduledCall]) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(queue)
+        .map_err(|e| format!("failed to serialize schedule: {}", e))?;
+    std::fs::write(path, data)
+        .map_err(|e| format!("failed to write schedule file: {}", e))
+}
+
+fn format_time(time: u32) -> String {
+    Local.timestamp(time as i64, 0).to_rfc2822()
+}
+
+pub fn parse_send_at(value: &str) -> Result<u32, String> {
+    let send_at = u32::from_str_radix(value, 10)
+        .map_err(|e| format!("failed to parse send-at timestamp: {}", e))?;
+    if send_at <= now() {
+        return Err("send-at time is in the past".to_owned());
+    }
+    Ok(send_at)
+}
+
+/// Adds the call to the persisted queue and waits until it's time to send it.
+pub async fn schedule_call(
+    conf: Config,
+    queue_path: &str,
+    send_at: u32,
+    address: &str,
+    abi: String,
+    method: &str,
+    params: &str,
+    keys: Option<String>,
+) -> Result<(), String> {
+    if keys.as_ref().map(|k| k.contains(' ')).unwrap_or(false) {
+        return Err("scheduled calls require path to keypair file, seed phrase is not stored".to_owned());
+    }
+    let job = update_queue(queue_path, |queue| {
+        let job = ScheduledCall {
+            id: queue.iter().map(|j| j.id).max().unwrap_or(0) + 1,
+            send_at,
+            address: address.to_owned(),
+            abi,
+            method: method.to_owned(),
+            params: params.to_owned(),
+            keys,
+        };
+        queue.push(job.clone());
+        Ok(job)
+    })?;
+    println!("Call scheduled with id {} at {}", job.id, format_time(send_at));
+    println!("Keep this command running or execute `schedule run` later to send it.");
+
+    run_job(conf, queue_path, job).await
+}
+
+/// Waits for the job time and sends the message unless the job was cancelled meanwhile.
+/// Message is built right before sending so that its expiration starts at the target time.
+async fn run_job(conf: Config, queue_path: &str, job: ScheduledCall) -> Result<(), String> {
+    let now = now();
+    if job.send_at > now {
+        tokio::time::delay_for(Duration::from_secs((job.send_at - now) as u64)).await;
+    }
+    let taken = update_queue(queue_path, |queue| {
+        let len = queue.len();
+        queue.retain(|j| j.id != job.id);
+        Ok(queue.len() != len)
+    })?;
+    if !taken {
+        println!("Scheduled call {} was cancelled.", job.id);
+        return Ok(());
+    }
+
+    println!("Sending scheduled call {}...", job.id);
+    call_contract(conf, &job.address, job.abi, &job.method, &job.params, job.keys, false).await
+        .map_err(|e| e.to_string())
+}
+
+pub fn create_schedule_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("schedule")
+        .about("Manages queue of scheduled calls (see --send-at option of call and multisig send).")
+        .subcommand(SubCommand::with_name("list")
+            .about("Prints scheduled calls."))
+        .subcommand(SubCommand::with_name("cancel")
+            .about("Removes call from the queue.")
+            .arg(Arg::with_name("ID")
+                .required(true)
+                .takes_value(true)
+                .help("Scheduled call id.")))
+        .subcommand(SubCommand::with_name("run")
+            .about("Waits for scheduled calls and sends them at the target time."))
+}
+
+pub async fn schedule_command(m: &ArgMatches<'_>, config: Config, config_file: &str) -> Result<(), CliError> {
+    let queue_path = schedule_file_name(config_file);
+    if m.subcommand_matches("list").is_some() {
+        let queue = read_queue(&queue_path)?;
+        if queue.is_empty() {
+            println!("No scheduled calls.");
+        }
+        for job in queue {
+            println!("{}: {} {}.{} {}", job.id, format_time(job.send_at), job.address, job.method, job.params);
+        }
+        return Ok(());
+    }
+    if let Some(m) = m.subcommand_matches("cancel") {
+        let id = u32::from_str_radix(m.value_of("ID").unwrap(), 10)
+            .map_err(|e| format!("failed to parse id: {}", e))?;
+        update_queue(&queue_path, |queue| {
+            let len = queue.len();
+            queue.retain(|j| j.id != id);
+            if queue.len() == len {
+                return Err(format!("scheduled call {} not found", id));
+            }
+            Ok(())
+        })?;
+        println!("Scheduled call {} cancelled.", id);
+        return Ok(());
+    }
+    if m.subcommand_matches("run").is_some() {
+        let mut queue = read_queue(&queue_path)?;
+        queue.sort_by_key(|j| j.send_at);
+        for job in queue {
+            println!("Next call {} at {}", job.id, format_time(job.send_at));
+            if let Err(e) = run_job(config.clone(), &queue_path, job).await {
+                println!("{}", e);
+            }
+        }
+        return Ok(());
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown schedule command"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_queue() {
+        let path = std::env::temp_dir().join(format!("tonos-cli-{}.schedule", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let id = update_queue(&path, |queue| {
+            queue.push(ScheduledCall {
+                id: 1,
+                send_at: 0,
+                address: "0:00".to_owned(),
+                abi: "{}".to_owned(),
+                method: "send".to_owned(),
+                params: "{}".to_owned(),
+                keys: None,
+            });
+            Ok(1)
+        }).unwrap();
+        assert_eq!(id, 1);
+        assert!(!std::path::Path::new(&format!("{}.lock", path)).exists());
+        assert_eq!(read_queue(&path).unwrap().len(), 1);
+
+        let err = update_queue(&path, |queue| -> Result<(), String> {
+            queue.clear();
+            Err("cancelled".to_owned())
+        });
+        assert!(err.is_err());
+        assert_eq!(read_queue(&path).unwrap().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
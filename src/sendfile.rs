@@ -10,31 +10,82 @@
  * See the License for the specific TON DEV software governing permissions and
  * limitations under the License.
  */
-use crate::helpers::create_client_verbose;
+use crate::helpers::{create_client_verbose, load_abi, print_fees};
 use crate::config::Config;
-use ton_client::processing::{ParamsOfSendMessage, send_message};
+use crate::history;
+use crate::relay;
+use ton_client::processing::{ParamsOfWaitForTransaction, wait_for_transaction};
 
 pub async fn sendfile(conf: Config, msg_boc: &str) -> Result<(), String> {
-    let ton = create_client_verbose(&conf)?;
-    let boc_vec = std::fs::read(msg_boc)
+    send_boc(conf, msg_boc, false, None).await
+}
+
+/// Reads message from file with binary boc or its base64/hex text representation.
+fn read_message_boc(path: &str) -> Result<Vec<u8>, String> {
+    let boc_vec = std::fs::read(path)
         .map_err(|e| format!("failed to read boc file: {}", e))?;
+    if ton_sdk::Contract::deserialize_message(&boc_vec[..]).is_ok() {
+        return Ok(boc_vec);
+    }
+    let text = String::from_utf8_lossy(&boc_vec);
+    let text = text.trim();
+    base64::decode(text)
+        .ok()
+        .or(hex::decode(text).ok())
+        .ok_or("file contains neither boc nor its base64 or hex representation".to_owned())
+}
+
+/// Broadcasts message from file and optionally waits for the transaction
+/// (decoding its output if ABI is specified).
+pub async fn send_boc(conf: Config, msg_boc: &str, wait: bool, abi: Option<String>) -> Result<(), String> {
+    let ton = create_client_verbose(&conf)?;
+    let boc_vec = read_message_boc(msg_boc)?;
     let tvm_msg = ton_sdk::Contract::deserialize_message(&boc_vec[..])
         .map_err(|e| format!("failed to parse message from boc: {}", e))?;
     let dst = tvm_msg.dst()
         .ok_or(format!("failed to parse dst address"))?;
+    let abi = abi.map(|abi| load_abi(&abi)).transpose()?;
 
     let callback = |_| {
         async move {}
     };
 
     println!("Sending message to account {}", dst);
-    let msg = ParamsOfSendMessage {
-        message: base64::encode(&boc_vec),
-        send_events: false,
-        ..Default::default()
+    let message = base64::encode(&boc_vec);
+    let sent = relay::broadcast(ton.clone(), &message, abi.clone()).await
+        .map_err(|e| format!("Failed: {}", e));
+    let shard_block_id = match (&sent, wait) {
+        (Ok(shard_block_id), true) => shard_block_id.clone(),
+        _ => {
+            let result = sent.map(|_| ());
+            history::record_message(ton, None, &message, &result).await;
+            result?;
+            println!("Succeded.");
+            return Ok(());
+        },
     };
-    send_message(ton, msg, callback).await
-        .map_err(|e| format!("Failed: {}", e))?;
+
+    println!("Waiting for transaction...");
+    let result = wait_for_transaction(
+        ton.clone(),
+        ParamsOfWaitForTransaction {
+            abi,
+            message: message.clone(),
+            shard_block_id,
+            send_events: false,
+            ..Default::default()
+        },
+        callback,
+    ).await
+    .map_err(|e| format!("Failed: {:#}", e));
+    history::record_message(ton, None, &message, &result.as_ref().map(|_| ()).map_err(|e| e.clone())).await;
+    let result = result?;
+
     println!("Succeded.");
+    println!("Transaction: {}", result.transaction["id"].as_str().unwrap_or(""));
+    if let Some(output) = result.decoded.and_then(|d| d.output) {
+        println!("Result: {}", serde_json::to_string_pretty(&output).unwrap_or_default());
+    }
+    print_fees(&result.fees, conf.is_json);
     Ok(())
-}
\ No newline at end of file
+}
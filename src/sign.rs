@@ -0,0 +1,804 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::{print_args, VERBOSE_MODE};
+use crate::call::send_message_and_wait;
+use crate::config::Config;
+use crate::crypto::load_keypair;
+use crate::convert::nano_to_tokens;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_local, create_client_verbose, load_abi, load_ton_address, now, query, TonClient};
+use crate::i18n::tr;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+use crate::wallet::prompt;
+use ton_client::abi::{
+    attach_signature, encode_message, CallSet, FunctionHeader, ParamsOfAttachSignature,
+    ParamsOfEncodeMessage, ResultOfEncodeMessage, Signer,
+};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use ton_client::crypto::{sign, verify_signature, KeyPair, ParamsOfSign, ParamsOfVerifySignature};
+
+const SIGNING_REQUEST_VERSION: u32 = 3;
+
+/// Batch directory layout shared by hot and cold machines: the hot machine puts
+/// requests to `unsigned/`, the cold one moves fully signed requests to `signed/`,
+/// the hot one moves broadcast requests to `sent/`.
+const UNSIGNED_DIR: &str = "unsigned";
+const SIGNED_DIR: &str = "signed";
+const SENT_DIR: &str = "sent";
+/// Watch-only addresses with public keys of their cold signers and ABIs.
+const WATCH_LIST_FILE: &str = "watchlist.json";
+/// Prepended to the data signed by `sign data`, so that the signature can never
+/// be taken for a signature of a message.
+const SIGNED_DATA_PREFIX: &[u8] = b"tonos-cli signed data:";
+
+/// Portable signing request which is passed from the machine that creates it to the
+/// holder of the submitter key (e.g. a cold machine) and back.
+///
+/// The request holds a single unsigned message, only the submitter signature can be
+/// attached to it. A message of a multisig wallet carries one custodian signature, so
+/// the other custodians still have to confirm the submitted transaction on-chain.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SigningRequest {
+    pub version: u32,
+    pub address: String,
+    pub method: String,
+    pub params: serde_json::Value,
+    pub abi: String,
+    pub expire: u32,
+    /// Public key of the signer whose signature is attached to the message.
+    pub submitter: String,
+    pub message: String,
+    pub data_to_sign: String,
+    /// Message time header in milliseconds. Signers re-encode the message with it
+    /// to check that the hash they sign belongs to the shown method and params.
+    #[serde(default)]
+    pub time: Option<u64>,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl SigningRequest {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read signing request: {}", e))?;
+        let request: SigningRequest = serde_json::from_str(&data)
+            .map_err(|e| format!("failed to parse signing request: {}", e))?;
+        if request.version != SIGNING_REQUEST_VERSION {
+            return Err(format!("unsupported signing request version: {}", request.version));
+        }
+        Ok(request)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize signing request: {}", e))?;
+        std::fs::write(path, data)
+            .map_err(|e| format!("failed to write signing request: {}", e))
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+}
+
+pub fn create_sign_command<'a, 'b>() -> App<'a, 'b> {
+    let request_arg = Arg::with_name("REQUEST")
+        .required(true)
+        .takes_value(true)
+        .help("Path to the signing request file.");
+    SubCommand::with_name("sign")
+        .about("Offline signing commands.")
+        .setting(AppSettings::AllowLeadingHyphen)
+        .subcommand(SubCommand::with_name("new")
+            .about("Creates signing request with unsigned message.")
+            .setting(AppSettings::AllowLeadingHyphen)
+            .arg(request_arg.clone().required_unless("DIR"))
+            .arg(Arg::with_name("DIR")
+                .long("--dir")
+                .takes_value(true)
+                .conflicts_with("REQUEST")
+                .help("Batch directory. The request is put to its unsigned/ subdirectory, submitter and ABI default to the watch list entry of the address."))
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .required(true)
+                .help("Contract address."))
+            .arg(Arg::with_name("METHOD")
+                .long("--method")
+                .takes_value(true)
+                .required(true)
+                .help("Name of the calling method."))
+            .arg(Arg::with_name("PARAMS")
+                .long("--params")
+                .takes_value(true)
+                .required(true)
+                .help("Arguments for the contract method. Can be passed via a filename."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Json file with contract ABI."))
+            .arg(Arg::with_name("SUBMITTER")
+                .long("--submitter")
+                .takes_value(true)
+                .required_unless("DIR")
+                .help("Public key of the signer whose signature is attached to the message (default: the first key of the watch list entry). Other multisig custodians confirm the transaction on-chain."))
+            .arg(Arg::with_name("LIFETIME")
+                .long("--lifetime")
+                .takes_value(true)
+                .help("Period of time in seconds while messages are valid (default 86400).")))
+        .subcommand(SubCommand::with_name("add")
+            .about("Signs the message of the signing request.")
+            .arg(request_arg.clone())
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .help("Seed phrase or path to keypair file."))
+            .arg(Arg::with_name("YES")
+                .long("--yes")
+                .short("-y")
+                .help("Signs without asking for confirmation after the call is shown.")))
+        .subcommand(SubCommand::with_name("finalize")
+            .about("Checks the submitter signature and sends the signed message.")
+            .arg(request_arg.clone())
+            .arg(Arg::with_name("OFFLINE")
+                .long("--offline")
+                .short("-f")
+                .help("Prints signed message instead of sending it.")))
+        .subcommand(SubCommand::with_name("watch")
+            .about("Adds watch-only address to the watch list of the batch directory or prints the list with balances.")
+            .arg(Arg::with_name("DIR")
+                .required(true)
+                .takes_value(true)
+                .help("Batch directory."))
+            .arg(Arg::with_name("ADDRESS")
+                .takes_value(true)
+                .help("Watch-only address to add."))
+            .arg(Arg::with_name("PUBKEYS")
+                .long("--pubkeys")
+                .takes_value(true)
+                .requires("ADDRESS")
+                .help("Comma-separated list of public keys of the cold signers."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .requires("ADDRESS")
+                .help("Json file with contract ABI.")))
+        .subcommand(SubCommand::with_name("batch")
+            .about("Signs all requests of the key in unsigned/ subdirectory of the batch directory (cold machine) and moves them to signed/.")
+            .arg(Arg::with_name("DIR")
+                .required(true)
+                .takes_value(true)
+                .help("Batch directory."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .help("Seed phrase or path to keypair file."))
+            .arg(Arg::with_name("YES")
+                .long("--yes")
+                .short("-y")
+                .help("Signs without asking for confirmation after the call is shown.")))
+        .subcommand(SubCommand::with_name("broadcast")
+            .about("Sends all requests from signed/ subdirectory of the batch directory (hot machine) and moves them to sent/.")
+            .arg(Arg::with_name("DIR")
+                .required(true)
+                .takes_value(true)
+                .help("Batch directory.")))
+        .subcommand(SubCommand::with_name("data")
+            .about("Signs sha256 hash of arbitrary data prefixed with \"tonos-cli signed data:\". The signature can be checked on-chain with tvm.checkSign(hash, signature, pubkey).")
+            .arg(Arg::with_name("FILE")
+                .long("--file")
+                .takes_value(true)
+                .conflicts_with("DATA")
+                .help("File with data to sign."))
+            .arg(Arg::with_name("DATA")
+                .long("--data")
+                .takes_value(true)
+                .help("Data to sign as hex string."))
+            .arg(Arg::with_name("RAW")
+                .long("--raw")
+                .help("Signs hash of the data without prefix. Such signature is valid for a message with the same hash, sign only data you trust."))
+            .arg(Arg::with_name("KEYS")
+                .long("--keys")
+                .takes_value(true)
+                .help("Seed phrase or path to keypair file.")))
+        .subcommand(SubCommand::with_name("verify")
+            .about("Verifies detached signature of the data created by `sign data`.")
+            .arg(Arg::with_name("FILE")
+                .long("--file")
+                .takes_value(true)
+                .conflicts_with("DATA")
+                .help("File with signed data."))
+            .arg(Arg::with_name("DATA")
+                .long("--data")
+                .takes_value(true)
+                .help("Signed data as hex string."))
+            .arg(Arg::with_name("RAW")
+                .long("--raw")
+                .help("Data was signed with --raw option."))
+            .arg(Arg::with_name("SIGNATURE")
+                .long("--signature")
+                .takes_value(true)
+                .required(true)
+                .help("Signature as hex string."))
+            .arg(Arg::with_name("PUBKEY")
+                .long("--pubkey")
+                .takes_value(true)
+                .required(true)
+                .help("Public key of the signer as hex string.")))
+}
+
+pub async fn sign_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("new") {
+        return new_request_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("add") {
+        return add_signature_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("finalize") {
+        return finalize_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("watch") {
+        return watch_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("batch") {
+        return batch_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("broadcast") {
+        return broadcast_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("data") {
+        return sign_data_command(m, config).map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("verify") {
+        return verify_data_command(m).map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown sign command"))
+}
+
+async fn new_request_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let request = m.value_of("REQUEST");
+    let dir = m.value_of("DIR");
+    let address = m.value_of("ADDRESS");
+    let method = m.value_of("METHOD");
+    let submitter = m.value_of("SUBMITTER");
+    let lifetime = m.value_of("LIFETIME");
+    let full_address = load_ton_address(address.unwrap(), &config)?;
+    let watched = match dir {
+        Some(dir) => WatchList::load(dir)?.entries.remove(&full_address),
+        None => None,
+    };
+    let submitter = submitter
+        .map(|s| s.to_owned())
+        .or(watched.as_ref().and_then(|w| w.pubkeys.first().cloned()));
+    let abi = Some(
+        m.value_of("ABI")
+            .map(|s| s.to_string())
+            .or(watched.and_then(|w| w.abi))
+            .or(config.abi_path.clone())
+            .ok_or("ABI file not defined. Supply it in config file or command line.".to_string())?
+    );
+    let params = Some(crate::load_params(m.value_of("PARAMS").unwrap())?);
+    print_args!(m, request, dir, address, method, params, abi, submitter, lifetime);
+
+    let submitter = submitter.ok_or(format!("address {} is not in the watch list, supply the signer with --submitter", full_address))?;
+    let address = full_address;
+    let abi_str = std::fs::read_to_string(abi.unwrap())
+        .map_err(|e| format!("failed to read ABI file: {}", e))?;
+    let params: serde_json::Value = serde_json::from_str(&params.unwrap())
+        .map_err(|e| format!("arguments are not in json format: {}", e))?;
+    let lifetime = lifetime
+        .map(|l| u32::from_str_radix(l, 10))
+        .transpose()
+        .map_err(|e| format!("failed to parse lifetime: {}", e))?
+        .unwrap_or(86400);
+    let expire = now() + lifetime;
+
+    let mut signing_request = SigningRequest {
+        version: SIGNING_REQUEST_VERSION,
+        address,
+        method: method.unwrap().to_owned(),
+        params,
+        abi: abi_str,
+        expire,
+        submitter,
+        message: String::new(),
+        data_to_sign: String::new(),
+        time: Some(chrono::Utc::now().timestamp_millis() as u64),
+        signature: None,
+    };
+    let msg = encode_request_message(&signing_request).await
+        .map_err(|e| format!("failed to create message: {}", e))?;
+    signing_request.message = msg.message;
+    signing_request.data_to_sign = msg.data_to_sign.ok_or("message has no data to sign".to_string())?;
+    let path = match (request, dir) {
+        (Some(request), _) => request.to_owned(),
+        (None, Some(dir)) => {
+            let unsigned = batch_subdir(dir, UNSIGNED_DIR)?;
+            let name = format!(
+                "{}-{}-{}.json",
+                signing_request.address.replace(':', "_"), signing_request.method, now(),
+            );
+            unsigned.join(name).to_string_lossy().into_owned()
+        },
+        (None, None) => return Err("request file or batch directory must be specified".to_owned()),
+    };
+    signing_request.save(&path)?;
+    println!("Signing request saved to file {}", path);
+    Ok(())
+}
+
+async fn add_signature_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let path = m.value_of("REQUEST").unwrap();
+    let keys = m.value_of("KEYS")
+        .map(|s| s.to_string())
+        .or(config.keys_path.clone())
+        .ok_or("keypair is not defined. Supply it in config file or command line.".to_string())?;
+    let keys = load_keypair(&keys)?;
+
+    let mut request = SigningRequest::load(path)?;
+    add_signature(&mut request, keys, m.is_present("YES")).await?;
+    request.save(path)?;
+    println!("Request is signed by {}.", request.submitter);
+    Ok(())
+}
+
+/// Encodes unsigned message of the request from its address, method, params and headers.
+async fn encode_request_message(request: &SigningRequest) -> Result<ResultOfEncodeMessage, String> {
+    encode_message(
+        create_client_local()?,
+        ParamsOfEncodeMessage {
+            abi: load_abi(&request.abi)?,
+            address: Some(request.address.clone()),
+            call_set: Some(CallSet {
+                function_name: request.method.clone(),
+                input: Some(request.params.clone()),
+                header: Some(FunctionHeader {
+                    expire: Some(request.expire),
+                    time: request.time,
+                    pubkey: Some(request.submitter.clone()),
+                }),
+            }),
+            signer: Signer::External { public_key: request.submitter.clone() },
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| format!("{}", e))
+}
+
+/// Checks that the message and the hash to sign are encoded from the method and params
+/// of the request, so the signer doesn't sign a call other than the one shown.
+async fn check_request_message(request: &SigningRequest) -> Result<(), String> {
+    if request.time.is_none() {
+        return Err("signing request has no message time and can't be checked, create it again".to_owned());
+    }
+    let msg = encode_request_message(request).await
+        .map_err(|e| format!("failed to encode message of the request: {}", e))?;
+    if msg.message != request.message || msg.data_to_sign.as_ref() != Some(&request.data_to_sign) {
+        return Err("message of the signing request doesn't match its method and params".to_owned());
+    }
+    Ok(())
+}
+
+async fn add_signature(request: &mut SigningRequest, keys: KeyPair, yes: bool) -> Result<(), String> {
+    if request.expire <= now() {
+        return Err("signing request is expired".to_string());
+    }
+    if keys.public != request.submitter {
+        return Err(format!(
+            "public key {} is not the submitter of the request, other custodians confirm the transaction on-chain",
+            keys.public,
+        ));
+    }
+    check_request_message(request).await?;
+    println!("Contract: {}", request.address);
+    println!("Method:   {}", request.method);
+    println!("Params:   {}", serde_json::to_string_pretty(&request.params).unwrap());
+    if !yes {
+        let answer = prompt("Sign the message? (y/n): ")?;
+        if !["y", "yes"].contains(&answer.to_lowercase().as_str()) {
+            return Err("signing is cancelled".to_owned());
+        }
+    }
+
+    let public = keys.public.clone();
+    let signature = sign(
+        create_client_local()?,
+        ParamsOfSign {
+            unsigned: request.data_to_sign.clone(),
+            keys,
+        },
+    )
+    .map_err(|e| format!("failed to sign message: {}", e))?
+    .signature;
+    debug!("request is signed by {}", public);
+    request.signature = Some(signature);
+    Ok(())
+}
+
+async fn finalize_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let path = m.value_of("REQUEST").unwrap();
+    let offline = m.is_present("OFFLINE");
+    let request = SigningRequest::load(path)?;
+    let ton = if offline { create_client_local()? } else { create_client_verbose(&config)? };
+    finalize_request(ton, &request, offline).await
+}
+
+async fn finalize_request(ton: TonClient, request: &SigningRequest, offline: bool) -> Result<(), String> {
+    let signature = request.signature.as_ref()
+        .ok_or(format!("submitter {} has not signed the request yet", request.submitter))?;
+    if request.expire <= now() {
+        return Err("signing request is expired".to_string());
+    }
+
+    let data = base64::decode(&request.data_to_sign)
+        .map_err(|e| format!("failed to decode data to sign: {}", e))?;
+    check_signature(&data, signature, &request.submitter)?;
+
+    let abi = load_abi(&request.abi)?;
+    let signed = attach_signature(
+        ton.clone(),
+        ParamsOfAttachSignature {
+            abi: abi.clone(),
+            public_key: request.submitter.clone(),
+            message: request.message.clone(),
+            signature: signature.clone(),
+        },
+    )
+    .map_err(|e| format!("failed to attach signature: {}", e))?;
+    println!("MessageId: {}", signed.message_id);
+    if offline {
+        println!("Message: {}", signed.message);
+    } else {
+        send_message_and_wait(ton, &request.address, abi, signed.message, false).await?;
+        println!("{}", tr("Succeeded."));
+    }
+    if request.method == "submitTransaction" {
+        println!("The transaction is confirmed by the submitter only, other custodians have to confirm it with confirmTransaction.");
+    }
+    Ok(())
+}
+
+/// Checks detached signature of the data.
+fn check_signature(data: &[u8], signature: &str, public: &str) -> Result<(), String> {
+    let mut signed = hex::decode(signature)
+        .map_err(|e| format!("signature of {} is not a valid hex string: {}", public, e))?;
+    if signed.len() != 64 {
+        return Err(format!("signature of {} must be 64 bytes long", public));
+    }
+    signed.extend_from_slice(data);
+    verify_signature(
+        create_client_local()?,
+        ParamsOfVerifySignature {
+            signed: base64::encode(&signed),
+            public: public.to_owned(),
+        },
+    )
+    .map(|_| ())
+    .map_err(|e| format!("signature of {} is invalid: {}", public, e))
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct WatchedAddress {
+    pubkeys: Vec<String>,
+    #[serde(default)]
+    abi: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct WatchList {
+    entries: BTreeMap<String, WatchedAddress>,
+}
+
+impl WatchList {
+    fn load(dir: &str) -> Result<Self, String> {
+        let path = Path::new(dir).join(WATCH_LIST_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| format!("failed to parse watch list {}: {}", path.display(), e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    fn save(&self, dir: &str) -> Result<(), String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create batch directory {}: {}", dir, e))?;
+        let path = Path::new(dir).join(WATCH_LIST_FILE);
+        std::fs::write(&path, serde_json::to_string_pretty(self).unwrap_or_default())
+            .map_err(|e| format!("failed to write watch list {}: {}", path.display(), e))
+    }
+}
+
+/// Returns subdirectory of the batch directory, creates it if needed.
+fn batch_subdir(dir: &str, name: &str) -> Result<PathBuf, String> {
+    let path = Path::new(dir).join(name);
+    std::fs::create_dir_all(&path)
+        .map_err(|e| format!("failed to create directory {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Lists request files in the subdirectory in name order.
+fn batch_requests(dir: &str, name: &str) -> Result<Vec<PathBuf>, String> {
+    let path = batch_subdir(dir, name)?;
+    let mut files = std::fs::read_dir(&path)
+        .map_err(|e| format!("failed to read directory {}: {}", path.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map(|ext| ext == "json").unwrap_or(false))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn move_request(file: &Path, dir: &str, name: &str) -> Result<(), String> {
+    let target = batch_subdir(dir, name)?.join(file.file_name().unwrap_or_default());
+    std::fs::rename(file, &target)
+        .map_err(|e| format!("failed to move {} to {}: {}", file.display(), target.display(), e))
+}
+
+async fn watch_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let dir = m.value_of("DIR").unwrap();
+    let mut list = WatchList::load(dir)?;
+    if let Some(address) = m.value_of("ADDRESS") {
+        let address = load_ton_address(address, &config)?;
+        let pubkeys: Vec<String> = m.value_of("PUBKEYS")
+            .ok_or("public keys of the signers are required (--pubkeys)".to_owned())?
+            .split(',')
+            .map(|k| k.trim().to_owned())
+            .filter(|k| !k.is_empty())
+            .collect();
+        let abi = m.value_of("ABI").map(|s| s.to_owned());
+        list.entries.insert(address.clone(), WatchedAddress { pubkeys, abi });
+        list.save(dir)?;
+        println!("Address {} added to the watch list.", address);
+        return Ok(());
+    }
+    let ton = create_client_verbose(&config)?;
+    for (address, watched) in &list.entries {
+        let balance = query(
+            ton.clone(),
+            "accounts",
+            json!({ "id": { "eq": address } }),
+            "balance(format: DEC)",
+            None,
+        ).await
+        .ok()
+        .and_then(|accounts| accounts.first().and_then(|acc| acc["balance"].as_str().map(|b| b.to_owned())))
+        .and_then(|b| u64::from_str_radix(&b, 10).ok())
+        .map(nano_to_tokens)
+        .unwrap_or("not found".to_owned());
+        println!("{} {:>24}  signers: {}", address, balance, watched.pubkeys.join(","));
+    }
+    Ok(())
+}
+
+/// Cold machine: signs every request for the key. Offline, no network access required.
+async fn batch_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let dir = m.value_of("DIR").unwrap();
+    let keys = m.value_of("KEYS")
+        .map(|s| s.to_string())
+        .or(config.keys_path.clone())
+        .ok_or("keypair is not defined. Supply it in config file or command line.".to_string())?;
+    let keys = load_keypair(&keys)?;
+    let (mut signed, mut skipped) = (0, 0);
+    for file in batch_requests(dir, UNSIGNED_DIR)? {
+        let path = file.to_string_lossy().into_owned();
+        println!("Request {}", path);
+        let result = sign_request_file(&path, &keys, m.is_present("YES")).await;
+        match result {
+            Ok(complete) => {
+                signed += 1;
+                if complete {
+                    move_request(&file, dir, SIGNED_DIR)?;
+                }
+            },
+            Err(e) => {
+                skipped += 1;
+                println!("  skipped: {}", e);
+            },
+        }
+    }
+    println!("Signed: {}, skipped: {}", signed, skipped);
+    Ok(())
+}
+
+/// Signs the request file if it's not signed yet, returns true if the request is signed.
+async fn sign_request_file(path: &str, keys: &KeyPair, yes: bool) -> Result<bool, String> {
+    let mut request = SigningRequest::load(path)?;
+    if !request.is_signed() {
+        add_signature(&mut request, keys.clone(), yes).await?;
+        request.save(path)?;
+    }
+    Ok(request.is_signed())
+}
+
+/// Hot machine: sends all signed requests.
+async fn broadcast_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let dir = m.value_of("DIR").unwrap();
+    let ton = create_client_verbose(&config)?;
+    let (mut sent, mut failed) = (0, 0);
+    for file in batch_requests(dir, SIGNED_DIR)? {
+        let path = file.to_string_lossy().into_owned();
+        println!("Request {}", path);
+        let result = match SigningRequest::load(&path) {
+            Ok(request) => finalize_request(ton.clone(), &request, false).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => {
+                sent += 1;
+                move_request(&file, dir, SENT_DIR)?;
+            },
+            Err(e) => {
+                failed += 1;
+                println!("  failed: {}", e);
+            },
+        }
+    }
+    println!("Sent: {}, failed: {}", sent, failed);
+    if failed > 0 {
+        return Err(format!("{} requests were not sent", failed));
+    }
+    Ok(())
+}
+
+/// Reads data from `--file` or hex `--data` option and returns its sha256 hash,
+/// the data is prefixed with `SIGNED_DATA_PREFIX` unless `--raw` is given.
+fn data_hash(m: &ArgMatches) -> Result<Vec<u8>, String> {
+    let data = match (m.value_of("FILE"), m.value_of("DATA")) {
+        (Some(path), _) => std::fs::read(path)
+            .map_err(|e| format!("failed to read data file: {}", e))?,
+        (None, Some(data)) => hex::decode(data)
+            .map_err(|e| format!("data is not a valid hex string: {}", e))?,
+        (None, None) => return Err("data is not defined. Use --file or --data option.".to_owned()),
+    };
+    Ok(signed_data_hash(&data, m.is_present("RAW")))
+}
+
+fn signed_data_hash(data: &[u8], raw: bool) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    if !raw {
+        hasher.input(SIGNED_DATA_PREFIX);
+    }
+    hasher.input(data);
+    hasher.result().to_vec()
+}
+
+fn sign_data_command(m: &ArgMatches, config: Config) -> Result<(), String> {
+    let keys = m.value_of("KEYS")
+        .map(|s| s.to_string())
+        .or(config.keys_path.clone())
+        .ok_or("keypair is not defined. Supply it in config file or command line.".to_string())?;
+    let keys = load_keypair(&keys)?;
+    let hash = data_hash(m)?;
+    if m.is_present("RAW") {
+        eprintln!("Warning: data is signed without prefix, the signature is valid for any message with the same hash.");
+    }
+    let public = keys.public.clone();
+    let signature = sign(
+        create_client_local()?,
+        ParamsOfSign {
+            unsigned: base64::encode(&hash),
+            keys,
+        },
+    )
+    .map_err(|e| format!("failed to sign data: {}", e))?
+    .signature;
+
+    println!("{}", serde_json::to_string_pretty(&json!({
+        "hash": hex::encode(&hash),
+        "signature": signature,
+        "pubkey": public,
+    })).unwrap());
+    Ok(())
+}
+
+fn verify_data_command(m: &ArgMatches) -> Result<(), String> {
+    let hash = data_hash(m)?;
+    check_signature(&hash, m.value_of("SIGNATURE").unwrap(), m.value_of("PUBKEY").unwrap())?;
+    println!("Signature is valid.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_requests() {
+        let dir = std::env::temp_dir().join(format!("tonos-cli-batch-{}", std::process::id()));
+        let dir = dir.to_string_lossy().into_owned();
+        let unsigned = batch_subdir(&dir, UNSIGNED_DIR).unwrap();
+        std::fs::write(unsigned.join("b.json"), "{}").unwrap();
+        std::fs::write(unsigned.join("a.json"), "{}").unwrap();
+        std::fs::write(unsigned.join("notes.txt"), "").unwrap();
+        let files = batch_requests(&dir, UNSIGNED_DIR).unwrap();
+        assert_eq!(files, vec![unsigned.join("a.json"), unsigned.join("b.json")]);
+
+        move_request(&files[0], &dir, SIGNED_DIR).unwrap();
+        assert_eq!(batch_requests(&dir, UNSIGNED_DIR).unwrap().len(), 1);
+        assert_eq!(batch_requests(&dir, SIGNED_DIR).unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_add_signature() {
+        let keys = KeyPair {
+            public: "9711a04f0b19474272bc7bae5472a8fbbb6ef71ce9c193f5ec3f5af808069a41".to_owned(),
+            secret: "cdf2a820517fa783b9b6094d15e650af92d485084ab217fc2c859f02d49623f3".to_owned(),
+        };
+        let mut request = SigningRequest {
+            version: SIGNING_REQUEST_VERSION,
+            address: format!("0:{}", "1".repeat(64)),
+            method: "confirmTransaction".to_owned(),
+            params: json!({ "transactionId": "1" }),
+            abi: crate::multisig::MSIG_ABI.to_owned(),
+            expire: now() + 3600,
+            submitter: "2".repeat(64),
+            message: String::new(),
+            data_to_sign: String::new(),
+            time: Some(1_600_000_000_000),
+            signature: None,
+        };
+        assert!(add_signature(&mut request, keys.clone(), true).await.is_err());
+        assert!(!request.is_signed());
+
+        request.submitter = keys.public.clone();
+        let msg = encode_request_message(&request).await.unwrap();
+        request.message = msg.message;
+        request.data_to_sign = msg.data_to_sign.unwrap();
+        add_signature(&mut request, keys.clone(), true).await.unwrap();
+        assert!(request.is_signed());
+        let data = base64::decode(&request.data_to_sign).unwrap();
+        assert!(check_signature(&data, request.signature.as_ref().unwrap(), &keys.public).is_ok());
+    }
+
+    #[test]
+    fn test_signed_data_hash() {
+        assert_eq!(SIGNED_DATA_PREFIX, b"tonos-cli signed data:");
+        assert_eq!(
+            hex::encode(signed_data_hash(b"abc", false)),
+            "238ebca81f17ca0bec168632f806ba8471826b7548ebf2f7ffb0039133f708d2",
+        );
+        assert_eq!(signed_data_hash(b"abc", true), Sha256::digest(b"abc").to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_check_request_message() {
+        let submitter = "9711a04f0b19474272bc7bae5472a8fbbb6ef71ce9c193f5ec3f5af808069a41".to_owned();
+        let mut request = SigningRequest {
+            version: SIGNING_REQUEST_VERSION,
+            address: format!("0:{}", "1".repeat(64)),
+            method: "confirmTransaction".to_owned(),
+            params: json!({ "transactionId": "1" }),
+            abi: crate::multisig::MSIG_ABI.to_owned(),
+            expire: now() + 3600,
+            submitter,
+            message: String::new(),
+            data_to_sign: String::new(),
+            time: Some(1_600_000_000_000),
+            signature: None,
+        };
+        let msg = encode_request_message(&request).await.unwrap();
+        request.message = msg.message;
+        request.data_to_sign = msg.data_to_sign.unwrap();
+        assert!(check_request_message(&request).await.is_ok());
+
+        let mut forged = request.clone();
+        forged.params = json!({ "transactionId": "2" });
+        assert!(check_request_message(&forged).await.is_err());
+        request.time = None;
+        assert!(check_request_message(&request).await.is_err());
+    }
+}
@@ -0,0 +1,186 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::run_local;
+use crate::config::Config;
+use crate::convert::nano_to_tokens;
+use crate::depool_abi::DEPOOL_ABI;
+use crate::error::{CliError, ErrorKind};
+use crate::known_abi::{detect_abi, ContractFamily};
+use crate::helpers::{create_client_verbose, decode_msg_body, load_ton_address, BulkQuery, TonClient};
+use crate::pretty::parse_uint;
+use chrono::{Local, TimeZone};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// DePool functions which create or change participant's stake.
+const STAKE_FUNCTIONS: [&str; 4] = [
+    "addOrdinaryStake",
+    "addVestingStake",
+    "addLockStake",
+    "transferStake",
+];
+
+pub fn create_stake_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("stake")
+        .about("Commands for stakes in depools.")
+        .subcommand(SubCommand::with_name("status")
+            .about("Finds all depools the wallet participates in and prints its stakes, vestings, locks and unfreeze times.")
+            .arg(Arg::with_name("MSIG")
+                .long("--wallet")
+                .short("-w")
+                .takes_value(true)
+                .help("Multisig wallet address (default: wallet from config)."))
+            .arg(Arg::with_name("DEPOOL")
+                .long("--depool")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Additional depool address to check (can be repeated).")))
+}
+
+pub async fn stake_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("status") {
+        return status_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown stake command"))
+}
+
+async fn status_command(m: &ArgMatches<'_>, conf: Config) -> Result<(), String> {
+    let wallet = m.value_of("MSIG").map(|s| s.to_owned())
+        .or(conf.wallet.clone())
+        .ok_or("multisig wallet address is not defined.".to_owned())?;
+    let wallet = load_ton_address(&wallet, &conf)?;
+    let ton = create_client_verbose(&conf)?;
+
+    let mut depools = find_depools(ton.clone(), &conf, &wallet).await?;
+    for depool in m.values_of("DEPOOL").map(|v| v.collect::<Vec<_>>()).unwrap_or_default() {
+        depools.insert(load_ton_address(depool, &conf)?);
+    }
+    if depools.is_empty() {
+        println!("Wallet {} doesn't participate in any depool.", wallet);
+        return Ok(());
+    }
+
+    let mut total = 0u128;
+    let mut reward = 0u128;
+    for depool in depools {
+        let params = json!({ "addr": wallet }).to_string();
+        let abi = detect_abi(ton.clone(), &depool, ContractFamily::DePool).await?;
+        let info = match run_local(ton.clone(), &depool, &abi, "getParticipantInfo", &params, false).await {
+            Ok(info) => info,
+            Err(e) => {
+                println!("DePool {}: failed to get participant info: {}", depool, e);
+                continue;
+            },
+        };
+        let participant_total = parse_uint(&info["total"]).unwrap_or(0);
+        if participant_total == 0 {
+            continue;
+        }
+        let rounds = run_local(ton.clone(), &depool, &abi, "getRounds", "{}", false).await
+            .map(|r| r["rounds"].clone())
+            .unwrap_or(json!({}));
+        total += participant_total;
+        reward += parse_uint(&info["reward"]).unwrap_or(0);
+        print_participant(&depool, &info, &rounds);
+    }
+    println!();
+    println!("Total stake:  {}", tokens(total));
+    println!("Total reward: {}", tokens(reward));
+    Ok(())
+}
+
+/// Collects destinations of internal messages from the wallet which decode as depool stake calls.
+async fn find_depools(ton: TonClient, conf: &Config, wallet: &str) -> Result<BTreeSet<String>, String> {
+    println!("Searching for depools...");
+    let messages = BulkQuery::new(ton.clone(), conf).query_all(
+        "messages",
+        json!({
+            "src": { "eq": wallet },
+            "msg_type": { "eq": 0 },
+        }),
+        "id dst body created_lt",
+        "created_lt",
+    ).await.map_err(|e| format!("failed to query wallet messages: {}", e))?;
+
+    let mut depools = BTreeSet::new();
+    for msg in messages {
+        let dst = msg["dst"].as_str().unwrap_or("");
+        let body = msg["body"].as_str().unwrap_or("");
+        if dst.is_empty() || body.is_empty() || depools.contains(dst) {
+            continue;
+        }
+        if let Ok(decoded) = decode_msg_body(ton.clone(), DEPOOL_ABI, body, true).await {
+            if STAKE_FUNCTIONS.contains(&decoded.name.as_str()) {
+                depools.insert(dst.to_owned());
+            }
+        }
+    }
+    Ok(depools)
+}
+
+fn print_participant(depool: &str, info: &Value, rounds: &Value) {
+    println!();
+    println!("DePool {}", depool);
+    println!("  total:          {}", tokens(parse_uint(&info["total"]).unwrap_or(0)));
+    println!("  reward:         {}", tokens(parse_uint(&info["reward"]).unwrap_or(0)));
+    println!("  withdraw value: {}", tokens(parse_uint(&info["withdrawValue"]).unwrap_or(0)));
+    println!("  reinvest:       {}", info["reinvest"].as_bool().unwrap_or(false));
+
+    if let Some(stakes) = info["stakes"].as_object() {
+        if !stakes.is_empty() {
+            println!("  ordinary stakes:");
+        }
+        for (round, value) in stakes {
+            println!("    round {}: {}{}", round, tokens(parse_uint(value).unwrap_or(0)), unfreeze(rounds, round));
+        }
+    }
+    print_withdrawals("vesting", &info["vestings"], rounds);
+    print_withdrawals("lock", &info["locks"], rounds);
+}
+
+fn print_withdrawals(kind: &str, stakes: &Value, rounds: &Value) {
+    let stakes = match stakes.as_object() {
+        Some(stakes) if !stakes.is_empty() => stakes,
+        _ => return,
+    };
+    println!("  {} stakes:", kind);
+    for (round, stake) in stakes {
+        let remaining = parse_uint(&stake["remainingAmount"]).unwrap_or(0);
+        let last = parse_uint(&stake["lastWithdrawalTime"]).unwrap_or(0);
+        let period = parse_uint(&stake["withdrawalPeriod"]).unwrap_or(0);
+        let value = parse_uint(&stake["withdrawalValue"]).unwrap_or(0);
+        println!("    round {}: {}{}", round, tokens(remaining), unfreeze(rounds, round));
+        println!("      withdrawal: {} every {} sec, next at {}", tokens(value), period, time(last + period));
+        println!("      owner: {}", stake["owner"].as_str().unwrap_or(""));
+    }
+}
+
+fn unfreeze(rounds: &Value, round: &str) -> String {
+    let id = parse_uint(&json!(round));
+    let round = rounds.as_object()
+        .and_then(|r| r.values().find(|r| id.is_some() && parse_uint(&r["id"]) == id));
+    match round.and_then(|r| parse_uint(&r["unfreeze"])) {
+        Some(t) if t != 0 && t != u32::max_value() as u128 => format!(" (unfreeze at {})", time(t)),
+        _ => String::new(),
+    }
+}
+
+fn tokens(value: u128) -> String {
+    nano_to_tokens(value as u64)
+}
+
+fn time(t: u128) -> String {
+    Local.timestamp(t as i64, 0).to_rfc2822()
+}
@@ -0,0 +1,135 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::abi::{read_abi, validate_initial_data};
+use crate::config::Config;
+use crate::crypto::load_keypair;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_local, load_abi, parse_wc};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use ton_block::{Serializable, StateInit};
+use ton_client::abi::{encode_initial_data, ParamsOfEncodeInitialData};
+use ton_types::Cell;
+
+pub fn create_stateinit_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("stateinit")
+        .about("State init commands.")
+        .subcommand(SubCommand::with_name("build")
+            .about("Builds state init (tvc) from contract code and initial data and prints the resulting address.")
+            .arg(Arg::with_name("CODE")
+                .long("--code")
+                .takes_value(true)
+                .required(true)
+                .help("File with contract code cell (binary boc or base64)."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .required(true)
+                .help("Json file with contract ABI describing initial data."))
+            .arg(Arg::with_name("DATA")
+                .long("--data")
+                .takes_value(true)
+                .help("Initial data in json, e.g. '{\"owner\":\"0:...\"}'."))
+            .arg(Arg::with_name("PUBKEY")
+                .long("--pubkey")
+                .takes_value(true)
+                .help("Public key (hex) stored in contract data or path to keypair file / seed phrase."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .help("Workchain id of the address (default from config)."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--out")
+                .short("-o")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the resulting tvc file.")))
+}
+
+pub async fn stateinit_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("build") {
+        return build_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown stateinit command"))
+}
+
+fn read_cell(path: &str) -> Result<Cell, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("failed to read code file: {}", e))?;
+    if let Ok(cell) = ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(&bytes)) {
+        return Ok(cell);
+    }
+    let bytes = base64::decode(String::from_utf8_lossy(&bytes).trim())
+        .map_err(|_| "code file contains neither boc nor base64 boc".to_owned())?;
+    ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes))
+        .map_err(|e| format!("failed to deserialize code cell: {}", e))
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<String, String> {
+    if pubkey.len() == 64 && hex::decode(pubkey).is_ok() {
+        return Ok(pubkey.to_owned());
+    }
+    Ok(load_keypair(pubkey)?.public)
+}
+
+async fn build_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let abi_path = m.value_of("ABI").unwrap();
+    let output = m.value_of("OUTPUT").unwrap();
+    let wc = parse_wc(m.value_of("WC"), &config)?;
+    let code = read_cell(m.value_of("CODE").unwrap())?;
+
+    let abi_json = read_abi(abi_path)?;
+    let data = m.value_of("DATA")
+        .map(|d| serde_json::from_str::<serde_json::Value>(d))
+        .transpose()
+        .map_err(|e| format!("initial data is not in json: {}", e))?;
+    if let Some(data) = &data {
+        validate_initial_data(&abi_json, data)?;
+    }
+    let pubkey = m.value_of("PUBKEY").map(parse_pubkey).transpose()?;
+
+    let abi = std::fs::read_to_string(abi_path)
+        .map_err(|e| format!("failed to read ABI file: {}", e))?;
+    let encoded = encode_initial_data(
+        create_client_local()?,
+        ParamsOfEncodeInitialData {
+            abi: Some(load_abi(&abi)?),
+            initial_data: data,
+            initial_pubkey: pubkey,
+            ..Default::default()
+        },
+    ).await
+    .map_err(|e| format!("failed to encode initial data: {}", e))?;
+    let data = base64::decode(&encoded.data)
+        .map_err(|e| format!("failed to decode initial data: {}", e))?;
+    let data = ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(data))
+        .map_err(|e| format!("failed to deserialize data cell: {}", e))?;
+
+    let state_init = StateInit {
+        code: Some(code),
+        data: Some(data),
+        ..Default::default()
+    };
+    let hash = state_init.serialize()
+        .map_err(|e| format!("failed to serialize state init: {}", e))?
+        .repr_hash();
+    let bytes = state_init.write_to_bytes()
+        .map_err(|e| format!("failed to serialize state init: {}", e))?;
+    std::fs::write(output, bytes)
+        .map_err(|e| format!("failed to write tvc file: {}", e))?;
+
+    println!("State init saved to {}", output);
+    println!("Code hash: {}", state_init.code.as_ref().unwrap().repr_hash().to_hex_string());
+    println!("Raw address: {}:{}", wc, hash.to_hex_string());
+    Ok(())
+}
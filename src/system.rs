@@ -0,0 +1,153 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::call::run_get_with_result;
+use crate::config::Config;
+use crate::convert::nano_to_tokens;
+use crate::error::{CliError, ErrorKind};
+use crate::pretty::parse_uint;
+use chrono::{Local, TimeZone};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+
+pub const ELECTOR_ADDR: &str = "-1:3333333333333333333333333333333333333333333333333333333333333333";
+pub const CONFIG_ADDR: &str = "-1:5555555555555555555555555555555555555555555555555555555555555555";
+
+pub fn create_system_command<'a, 'b>() -> App<'a, 'b> {
+    let addr_arg = |default: &'static str| Arg::with_name("ADDRESS")
+        .long("--addr")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+        .help(default);
+    SubCommand::with_name("system")
+        .about("Decoded information from system elector and config contracts.")
+        .subcommand(SubCommand::with_name("elector")
+            .about("Elector contract commands.")
+            .subcommand(SubCommand::with_name("active")
+                .about("Prints id of the active election (0 if there is no election).")
+                .arg(addr_arg("Elector address (default: -1:3333...3333).")))
+            .subcommand(SubCommand::with_name("participants")
+                .about("Prints participants of the active election with their stakes.")
+                .arg(addr_arg("Elector address (default: -1:3333...3333).")))
+            .subcommand(SubCommand::with_name("elections")
+                .about("Prints past elections.")
+                .arg(addr_arg("Elector address (default: -1:3333...3333)."))))
+        .subcommand(SubCommand::with_name("config")
+            .about("Config contract commands.")
+            .subcommand(SubCommand::with_name("proposals")
+                .about("Prints active proposals to change config parameters.")
+                .arg(addr_arg("Config contract address (default: -1:5555...5555)."))))
+}
+
+pub async fn system_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("elector") {
+        let addr = |m: &ArgMatches| m.value_of("ADDRESS").unwrap_or(ELECTOR_ADDR).to_owned();
+        if let Some(m) = m.subcommand_matches("active") {
+            let result = run_get_with_result(&config, &addr(m), "active_election_id", None).await?;
+            println!("Active election id: {}", uint(&result[0]));
+            return Ok(());
+        }
+        if let Some(m) = m.subcommand_matches("participants") {
+            let result = run_get_with_result(&config, &addr(m), "participant_list", None).await?;
+            return print_participants(&result[0]).map_err(CliError::from);
+        }
+        if let Some(m) = m.subcommand_matches("elections") {
+            let result = run_get_with_result(&config, &addr(m), "past_elections", None).await?;
+            return print_past_elections(&result[0]).map_err(CliError::from);
+        }
+    }
+    if let Some(m) = m.subcommand_matches("config") {
+        if let Some(m) = m.subcommand_matches("proposals") {
+            let addr = m.value_of("ADDRESS").unwrap_or(CONFIG_ADDR);
+            let result = run_get_with_result(&config, addr, "list_proposals", None).await?;
+            return print_proposals(&result[0]).map_err(CliError::from);
+        }
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown system command"))
+}
+
+/// Converts TVM list (nested tuples `[head, tail]` terminated by null) into vector.
+pub fn tvm_list(value: &Value) -> Vec<Value> {
+    let mut items = vec![];
+    let mut next = value;
+    while let Some(pair) = next.as_array() {
+        if pair.len() != 2 {
+            break;
+        }
+        items.push(pair[0].clone());
+        next = &pair[1];
+    }
+    items
+}
+
+fn uint(value: &Value) -> u128 {
+    parse_uint(value).unwrap_or(0)
+}
+
+fn tokens(value: &Value) -> String {
+    nano_to_tokens(uint(value) as u64)
+}
+
+fn date(value: &Value) -> String {
+    Local.timestamp(uint(value) as i64, 0).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn print_participants(list: &Value) -> Result<(), String> {
+    let mut participants = tvm_list(list);
+    participants.sort_by_key(|p| std::cmp::Reverse(uint(&p[1])));
+    println!("{} participants", participants.len());
+    for p in participants {
+        println!("{} {:>20}", p[0].as_str().unwrap_or(""), tokens(&p[1]));
+    }
+    Ok(())
+}
+
+fn print_past_elections(list: &Value) -> Result<(), String> {
+    for e in tvm_list(list) {
+        println!("election {}:", uint(&e[0]));
+        println!("  unfreeze at: {}", date(&e[1]));
+        println!("  stake held:  {} sec", uint(&e[2]));
+        println!("  total stake: {}", tokens(&e[5]));
+        println!("  bonuses:     {}", tokens(&e[6]));
+    }
+    Ok(())
+}
+
+fn print_proposals(list: &Value) -> Result<(), String> {
+    let proposals = tvm_list(list);
+    println!("{} proposals", proposals.len());
+    for p in proposals {
+        let info = &p[1];
+        let param = &info[2];
+        println!("proposal {}:", p[0].as_str().unwrap_or(""));
+        println!("  expire at:  {}", date(&info[0]));
+        println!("  critical:   {}", uint(&info[1]) != 0);
+        println!("  param:      {}", param[0]);
+        println!("  voters:     {}", tvm_list(&info[4]).len());
+        println!("  weight remaining: {}", info[5]);
+        println!("  rounds remaining: {}", uint(&info[6]));
+        println!("  wins/losses: {}/{}", uint(&info[8]), uint(&info[7]));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tvm_list() {
+        let list = json!(["1", ["2", ["3", null]]]);
+        assert_eq!(tvm_list(&list), vec![json!("1"), json!("2"), json!("3")]);
+        assert!(tvm_list(&Value::Null).is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Local TVM playground: assembles a code snippet and runs it in a bare VM
+//! with the given initial stack and registers, no account or message needed.
+//!
+//! Stack is passed as json array from bottom to top: numbers or decimal/hex
+//! strings are integers, `null` is null, nested arrays are tuples,
+//! `{"cell": "<base64 boc>"}`, `{"slice": "<base64 boc>"}` and
+//! `{"builder": "<base64 boc>"}` are cells, slices and builders.
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::now;
+use crate::output;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+use ton_types::{BuilderData, Cell, SliceData};
+use ton_vm::executor::{gas::gas_state::Gas, Engine};
+use ton_vm::stack::{integer::IntegerData, savelist::SaveList, Stack, StackItem};
+
+const DEFAULT_GAS_LIMIT: i64 = 1_000_000;
+/// Tag of the SmartContractInfo tuple in c7.
+const SMC_INFO_TAG: i64 = 0x076ef1ea;
+
+pub fn create_tvm_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("tvm")
+        .about("TVM playground.")
+        .subcommand(SubCommand::with_name("exec")
+            .about("Assembles TVM code snippet and executes it in a local VM, prints exit code, gas and the resulting stack.")
+            .arg(Arg::with_name("ASM")
+                .long("--asm")
+                .required(true)
+                .takes_value(true)
+                .help("File with TVM assembly or the snippet itself (e.g. \"PUSHINT 2 PUSHINT 3 ADD\")."))
+            .arg(Arg::with_name("STACK")
+                .long("--stack")
+                .takes_value(true)
+                .help("Initial stack as json array from bottom to top, e.g. '[1, \"0xff\", null, [2, 3], {\"cell\": \"te6...\"}]'."))
+            .arg(Arg::with_name("C4")
+                .long("--c4")
+                .takes_value(true)
+                .help("Persistent data cell (c4): boc file or base64 boc (default: empty cell)."))
+            .arg(Arg::with_name("C7")
+                .long("--c7")
+                .takes_value(true)
+                .help("Temporary data (c7) as json tuple in --stack format (default: SmartContractInfo with current time and zero balance)."))
+            .arg(Arg::with_name("GAS_LIMIT")
+                .long("--gas-limit")
+                .takes_value(true)
+                .help("Gas limit (default: 1000000).")))
+}
+
+pub async fn tvm_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("exec") {
+        return exec_command(m, config).map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown tvm command"))
+}
+
+/// Reads value given either as a file path or inline.
+fn file_or_value(value: &str) -> Result<String, String> {
+    if Path::new(value).is_file() {
+        std::fs::read_to_string(value).map_err(|e| format!("failed to read {}: {}", value, e))
+    } else {
+        Ok(value.to_owned())
+    }
+}
+
+fn parse_cell(value: &str) -> Result<Cell, String> {
+    let bytes = if Path::new(value).is_file() {
+        let bytes = std::fs::read(value).map_err(|e| format!("failed to read {}: {}", value, e))?;
+        if let Ok(cell) = ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(&bytes)) {
+            return Ok(cell);
+        }
+        base64::decode(String::from_utf8_lossy(&bytes).trim())
+    } else {
+        base64::decode(value.trim())
+    }
+    .map_err(|e| format!("cell must be a boc file or base64 boc: {}", e))?;
+    ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes))
+        .map_err(|e| format!("failed to deserialize cell: {}", e))
+}
+
+fn parse_integer(text: &str) -> Result<StackItem, String> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (radix, digits) = match digits.strip_prefix("0x") {
+        Some(hex) => (16, hex),
+        None => (10, digits),
+    };
+    let sign = if negative { "-" } else { "" };
+    let value = IntegerData::from_str_radix(&format!("{}{}", sign, digits), radix)
+        .map_err(|e| format!("invalid integer {}: {}", text, e))?;
+    Ok(StackItem::integer(value))
+}
+
+/// Converts json value into a stack item, see module docs for the format.
+pub fn json_to_item(value: &Value) -> Result<StackItem, String> {
+    match value {
+        Value::Null => Ok(StackItem::None),
+        Value::Bool(b) => Ok(StackItem::int(if *b { -1 } else { 0 })),
+        Value::Number(n) => parse_integer(&n.to_string()),
+        Value::String(s) => parse_integer(s),
+        Value::Array(items) => Ok(StackItem::tuple(
+            items.iter().map(json_to_item).collect::<Result<Vec<_>, String>>()?
+        )),
+        Value::Object(map) => {
+            if let Some(boc) = map.get("cell").and_then(|v| v.as_str()) {
+                return Ok(StackItem::Cell(parse_cell(boc)?));
+            }
+            if let Some(boc) = map.get("slice").and_then(|v| v.as_str()) {
+                return Ok(StackItem::Slice(SliceData::from(parse_cell(boc)?)));
+            }
+            if let Some(boc) = map.get("builder").and_then(|v| v.as_str()) {
+                let builder = BuilderData::from(&parse_cell(boc)?);
+                return Ok(StackItem::Builder(Arc::new(builder)));
+            }
+            Err(format!("unsupported stack item: {}", value))
+        },
+    }
+}
+
+fn serialize_cell(cell: &Cell) -> String {
+    ton_types::serialize_toc(cell)
+        .map(|bytes| base64::encode(&bytes))
+        .unwrap_or_else(|e| format!("<failed to serialize cell: {}>", e))
+}
+
+/// Converts stack item into json value, the inverse of `json_to_item`.
+pub fn item_to_json(item: &StackItem) -> Value {
+    match item {
+        StackItem::None => Value::Null,
+        StackItem::Integer(value) => json!(value.to_string()),
+        StackItem::Cell(cell) => json!({ "cell": serialize_cell(cell) }),
+        StackItem::Slice(slice) => json!({ "slice": serialize_cell(&slice.into_cell()) }),
+        StackItem::Builder(builder) => match builder.as_ref().clone().into_cell() {
+            Ok(cell) => json!({ "builder": serialize_cell(&cell) }),
+            Err(e) => json!({ "builder": format!("<{}>", e) }),
+        },
+        StackItem::Tuple(items) => Value::Array(items.iter().map(item_to_json).collect()),
+        StackItem::Continuation(_) => json!("<continuation>"),
+    }
+}
+
+fn default_c7() -> StackItem {
+    StackItem::tuple(vec![StackItem::tuple(vec![
+        StackItem::int(SMC_INFO_TAG),
+        StackItem::int(0),
+        StackItem::int(0),
+        StackItem::int(now() as i64),
+        StackItem::int(0),
+        StackItem::int(0),
+        StackItem::int(0),
+        StackItem::tuple(vec![StackItem::int(0), StackItem::None]),
+        StackItem::None,
+        StackItem::None,
+    ])])
+}
+
+fn exec_command(m: &ArgMatches, config: Config) -> Result<(), String> {
+    let asm = file_or_value(m.value_of("ASM").unwrap())?;
+    let code = ton_labs_assembler::compile_code(&asm)
+        .map_err(|e| format!("failed to assemble code: {}", e))?;
+
+    let mut stack = Stack::new();
+    if let Some(json) = m.value_of("STACK") {
+        let items: Vec<Value> = serde_json::from_str(&file_or_value(json)?)
+            .map_err(|e| format!("stack must be a json array: {}", e))?;
+        for item in &items {
+            stack.push(json_to_item(item)?);
+        }
+    }
+
+    let data = m.value_of("C4").map(parse_cell).transpose()?.unwrap_or_default();
+    let mut c7 = match m.value_of("C7") {
+        Some(json) => {
+            let value: Value = serde_json::from_str(&file_or_value(json)?)
+                .map_err(|e| format!("c7 must be a json array: {}", e))?;
+            json_to_item(&value)?
+        },
+        None => default_c7(),
+    };
+    let mut ctrls = SaveList::new();
+    ctrls.put(4, &mut StackItem::Cell(data))
+        .map_err(|e| format!("failed to set c4: {}", e))?;
+    ctrls.put(7, &mut c7)
+        .map_err(|e| format!("failed to set c7: {}", e))?;
+
+    let gas_limit = m.value_of("GAS_LIMIT")
+        .map(|s| i64::from_str_radix(s, 10).map_err(|e| format!("failed to parse gas limit: {}", e)))
+        .transpose()?
+        .unwrap_or(DEFAULT_GAS_LIMIT);
+    let mut engine = Engine::new().setup_with_libraries(
+        code, Some(ctrls), Some(stack), Some(Gas::test_with_limit(gas_limit)), vec![],
+    );
+    let (exit_code, error) = match engine.execute() {
+        Ok(code) => (code, None),
+        Err(e) => (
+            ton_vm::error::tvm_exception_full(&e)
+                .map(|ex| ex.exception_or_custom_code())
+                .unwrap_or(-1),
+            Some(e.to_string()),
+        ),
+    };
+    let stack: Vec<Value> = engine.stack().iter().map(item_to_json).collect();
+    let result = json!({
+        "exit_code": exit_code,
+        "error": error,
+        "gas_used": engine.gas_used(),
+        "stack": stack,
+    });
+    if config.is_json {
+        println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default());
+        return Ok(());
+    }
+    output::print_value(&result, || {
+        println!("Exit code: {}", exit_code);
+        if let Some(error) = &error {
+            println!("Error: {}", error);
+        }
+        println!("Gas used: {}", engine.gas_used());
+        println!("Stack (top last):");
+        for (i, item) in stack.iter().enumerate() {
+            println!("  s{}: {}", stack.len() - 1 - i, item);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack_json() {
+        let input = json!(["5", -3, "0xff", null, [1, [2]]]);
+        let items: Vec<StackItem> = input.as_array().unwrap().iter().map(|v| json_to_item(v).unwrap()).collect();
+        let output: Vec<Value> = items.iter().map(item_to_json).collect();
+        assert_eq!(Value::Array(output), json!(["5", "-3", "255", null, ["1", ["2"]]]));
+        assert!(json_to_item(&json!({"unknown": 1})).is_err());
+        assert!(json_to_item(&json!("12a")).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::cache::cached_records;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_verbose, load_checkpoint, load_ton_address, now, save_checkpoint, BulkQuery, PageCursor};
+use crate::output;
+use chrono::{Local, TimeZone};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const TRANSACTION_FIELDS: &str = "id lt now aborted in_msg account_addr compute { exit_code } total_fees(format: DEC)";
+
+pub fn create_tx_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("tx")
+        .about("Transaction commands.")
+        .subcommand(SubCommand::with_name("find")
+            .about("Finds transactions caused by inbound messages with the given hash or body prefix.")
+            .arg(Arg::with_name("IN_MSG_HASH")
+                .long("--in-msg-hash")
+                .takes_value(true)
+                .help("Hash (id) of the inbound message."))
+            .arg(Arg::with_name("BODY_PREFIX")
+                .long("--body-prefix")
+                .takes_value(true)
+                .requires("ADDRESS")
+                .help("Hex prefix of the inbound message body (data of the root cell)."))
+            .arg(Arg::with_name("ADDRESS")
+                .long("--addr")
+                .takes_value(true)
+                .help("Account which received the messages (required with --body-prefix)."))
+            .arg(Arg::with_name("SINCE")
+                .long("--since")
+                .takes_value(true)
+                .help("Searches messages created after this unix time (default: last 24 hours)."))
+            .arg(Arg::with_name("RESUME")
+                .long("--resume")
+                .takes_value(true)
+                .requires("BODY_PREFIX")
+                .help("Checkpoint file for the body prefix scan. Scan position and matches are saved to it after every page, if the file exists the scan continues from the saved position (--since is ignored then). The file is removed when the scan is complete."))
+            .group(ArgGroup::with_name("FILTER")
+                .args(&["IN_MSG_HASH", "BODY_PREFIX"])
+                .required(true)))
+}
+
+pub async fn tx_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("find") {
+        return find_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown tx command"))
+}
+
+async fn find_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let ton = create_client_verbose(&config)?;
+    let bulk = BulkQuery::new(ton.clone(), &config);
+
+    let message_ids = match m.value_of("IN_MSG_HASH") {
+        Some(hash) => vec![hash.trim_start_matches("0x").to_lowercase()],
+        None => {
+            let addr = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+            let since = m.value_of("SINCE")
+                .map(|s| u32::from_str_radix(s, 10))
+                .transpose()
+                .map_err(|e| format!("failed to parse --since: {}", e))?
+                .unwrap_or(now() - 24 * 3600);
+            let prefix = m.value_of("BODY_PREFIX").unwrap().trim_start_matches("0x").to_lowercase();
+            find_messages_by_body(&bulk, &addr, since, &prefix, m.value_of("RESUME")).await?
+        },
+    };
+
+    let mut found = vec![];
+    for id in message_ids {
+        // transaction of the message never changes once it is found
+        let filter = json!({ "in_msg": { "eq": id } });
+        let transactions = cached_records(&config, "transactions", &filter, TRANSACTION_FIELDS, || bulk.query(
+            "transactions",
+            filter.clone(),
+            TRANSACTION_FIELDS,
+            None,
+            None,
+        )).await?;
+        if transactions.is_empty() && !config.is_json {
+            println!("Message {}: no transaction found (message may be expired or not delivered yet).", id);
+        }
+        found.extend(transactions);
+    }
+    let found = json!(found);
+    output::print_value(&found, || print_transactions(&found, config.is_json))
+}
+
+/// State of the body prefix scan saved to the checkpoint file.
+#[derive(Serialize, Deserialize)]
+struct ScanCheckpoint {
+    prefix: String,
+    cursor: PageCursor,
+    matches: Vec<String>,
+}
+
+/// Scans inbound external messages of the account and returns ids of those
+/// whose body data starts with the prefix. GraphQL has no prefix filter for
+/// bodies, so they are compared locally.
+async fn find_messages_by_body(
+    bulk: &BulkQuery,
+    addr: &str,
+    since: u32,
+    prefix: &str,
+    resume: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let filter = json!({
+        "dst": { "eq": addr },
+        "msg_type": { "eq": 1 },
+        "created_at": { "ge": since },
+    });
+    let fresh = ScanCheckpoint {
+        prefix: prefix.to_owned(),
+        cursor: PageCursor::new("messages", filter, "created_at"),
+        matches: vec![],
+    };
+    let mut scan = match resume.map(load_checkpoint::<ScanCheckpoint>).transpose()?.flatten() {
+        Some(saved) => {
+            // since defaults to the current time, so only address and prefix are compared
+            let saved_addr = saved.cursor.filter()["dst"]["eq"].as_str().unwrap_or("");
+            if saved.prefix != prefix || saved_addr != addr {
+                return Err(format!("checkpoint {} was saved by another query", resume.unwrap()));
+            }
+            saved
+        },
+        None => fresh,
+    };
+    while let Some(messages) = bulk.next_page(&mut scan.cursor, "id body created_at").await? {
+        scan.matches.extend(messages.iter()
+            .filter(|msg| body_hex(&msg["body"]).map(|b| b.starts_with(prefix)).unwrap_or(false))
+            .filter_map(|msg| msg["id"].as_str().map(|s| s.to_owned())));
+        if let Some(path) = resume {
+            save_checkpoint(path, &scan)?;
+        }
+    }
+    if let Some(path) = resume {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("failed to remove checkpoint {}: {}", path, e))?;
+    }
+    Ok(scan.matches)
+}
+
+fn body_hex(body: &Value) -> Option<String> {
+    let bytes = base64::decode(body.as_str()?).ok()?;
+    let cell = ton_types::deserialize_tree_of_cells(&mut std::io::Cursor::new(bytes)).ok()?;
+    Some(hex::encode(cell.data()))
+}
+
+fn print_transactions(value: &Value, is_json: bool) {
+    if is_json {
+        println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+        return;
+    }
+    let transactions = value.as_array().cloned().unwrap_or_default();
+    if transactions.is_empty() {
+        println!("No transactions found.");
+    }
+    for tx in transactions {
+        let time = tx["now"].as_u64().unwrap_or(0);
+        println!("Transaction {}", tx["id"].as_str().unwrap_or(""));
+        println!("  account:    {}", tx["account_addr"].as_str().unwrap_or(""));
+        println!("  in_msg:     {}", tx["in_msg"].as_str().unwrap_or(""));
+        println!("  lt:         {}", tx["lt"].as_str().unwrap_or(""));
+        println!("  time:       {}", Local.timestamp(time as i64, 0).to_rfc2822());
+        println!("  aborted:    {}", tx["aborted"].as_bool().unwrap_or(false));
+        println!("  exit code:  {}", tx["compute"]["exit_code"]);
+    }
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+
+const TRANSFER_PREFIX: &str = "ton://transfer/";
+
+/// Transfer request encoded as `ton://transfer/<addr>?amount=<nanotokens>&text=<comment>`.
+#[derive(Debug, PartialEq)]
+pub struct TransferUri {
+    pub dest: String,
+    /// Amount in nanotokens.
+    pub amount: Option<u64>,
+    pub text: Option<String>,
+}
+
+pub fn is_transfer_uri(s: &str) -> bool {
+    s.starts_with(TRANSFER_PREFIX)
+}
+
+pub fn parse_transfer_uri(uri: &str) -> Result<TransferUri, String> {
+    let rest = uri.strip_prefix(TRANSFER_PREFIX)
+        .ok_or(format!("not a ton transfer link: {}", uri))?;
+    let mut parts = rest.splitn(2, '?');
+    let dest = percent_decode(parts.next().unwrap_or(""))?;
+    if dest.is_empty() {
+        return Err("transfer link doesn't contain destination address".to_owned());
+    }
+    let mut result = TransferUri { dest, amount: None, text: None };
+    for pair in parts.next().unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+        let mut kv = pair.splitn(2, '=');
+        let key = kv.next().unwrap_or("");
+        let value = percent_decode(kv.next().unwrap_or(""))?;
+        match key {
+            "amount" => {
+                result.amount = Some(u64::from_str_radix(&value, 10)
+                    .map_err(|e| format!("failed to parse amount in transfer link: {}", e))?);
+            },
+            "text" => result.text = Some(value),
+            // unknown parameters (e.g. "bin") are ignored like mobile wallets do
+            _ => {},
+        }
+    }
+    Ok(result)
+}
+
+pub fn transfer_uri(dest: &str, amount: Option<u64>, text: Option<&str>) -> String {
+    let mut params = vec![];
+    if let Some(amount) = amount {
+        params.push(format!("amount={}", amount));
+    }
+    if let Some(text) = text {
+        params.push(format!("text={}", percent_encode(text)));
+    }
+    let mut uri = format!("{}{}", TRANSFER_PREFIX, dest);
+    if !params.is_empty() {
+        uri.push('?');
+        uri.push_str(&params.join("&"));
+    }
+    uri
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut result = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => result.push(b as char),
+            _ => result.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    result
+}
+
+fn percent_decode(s: &str) -> Result<String, String> {
+    let bytes = s.as_bytes();
+    let mut result = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = s.get(i + 1..i + 3)
+                    .ok_or(format!("invalid escape sequence in \"{}\"", s))?;
+                result.push(u8::from_str_radix(hex, 16)
+                    .map_err(|_| format!("invalid escape sequence in \"{}\"", s))?);
+                i += 3;
+            },
+            b'+' => {
+                result.push(b' ');
+                i += 1;
+            },
+            b => {
+                result.push(b);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8(result).map_err(|e| format!("invalid utf-8 in transfer link: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_uri() {
+        let addr = "0:2bb4a0e8391e7ea8877f4825064924bd41ce110fce97e939d3323999e1efbb13";
+        let uri = transfer_uri(addr, Some(1_500_000_000), Some("for coffee & cake"));
+        assert_eq!(
+            uri,
+            format!("ton://transfer/{}?amount=1500000000&text=for%20coffee%20%26%20cake", addr),
+        );
+        let parsed = parse_transfer_uri(&uri).unwrap();
+        assert_eq!(parsed, TransferUri {
+            dest: addr.to_owned(),
+            amount: Some(1_500_000_000),
+            text: Some("for coffee & cake".to_owned()),
+        });
+
+        let parsed = parse_transfer_uri(&format!("ton://transfer/{}", addr)).unwrap();
+        assert_eq!(parsed.amount, None);
+        assert!(parse_transfer_uri("ton://transfer/").is_err());
+        assert!(parse_transfer_uri("https://example.com").is_err());
+    }
+}
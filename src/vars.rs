@@ -0,0 +1,200 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use serde_json::{Map, Value};
+use std::sync::Mutex;
+
+/// Values captured with `--out` are stored in this file next to the config
+/// and substituted into arguments of subsequent commands as `${name}`.
+pub fn vars_file_name(config_file: &str) -> String {
+    format!("{}.vars", config_file)
+}
+
+struct Outputs {
+    file: Option<String>,
+    /// Requested outputs as (name, json path).
+    specs: Vec<(String, String)>,
+    captured: bool,
+}
+
+lazy_static! {
+    static ref OUTPUTS: Mutex<Outputs> = Mutex::new(Outputs { file: None, specs: vec![], captured: false });
+}
+
+/// Finds config file in raw command line the same way as clap does after parsing,
+/// needed to substitute variables before arguments are parsed.
+pub fn config_file_from_args(args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "-c" || arg == "--config" {
+            return iter.next().cloned();
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_owned());
+        }
+    }
+    None
+}
+
+pub fn load_vars(path: &str) -> Result<Map<String, Value>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|e| format!("failed to parse variables file {}: {}", path, e)),
+        Err(_) => Ok(Map::new()),
+    }
+}
+
+fn var_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Replaces `${name}` in the argument with the captured value. Names which were
+/// not captured are left for environment interpolation.
+pub fn substitute(arg: &str, vars: &Map<String, Value>) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find('}')
+            .ok_or(format!(r#"unterminated variable in "{}""#, arg))?;
+        let name = &rest[start + 2..start + end];
+        match vars.get(name) {
+            Some(value) => result.push_str(&var_to_string(value)),
+            None => result.push_str(&rest[start..start + end + 1]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+pub fn substitute_args(args: Vec<String>, vars: &Map<String, Value>) -> Result<Vec<String>, String> {
+    args.iter().map(|arg| substitute(arg, vars)).collect()
+}
+
+/// Parses `--out <name>=<jsonpath>` options.
+pub fn set_outputs(file: &str, specs: Vec<&str>) -> Result<(), String> {
+    let specs = specs.iter()
+        .map(|spec| {
+            let mut parts = spec.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let path = parts.next()
+                .ok_or(format!(r#"output "{}" must be in form <name>=<jsonpath>"#, spec))?
+                .trim();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!(r#"invalid output variable name "{}""#, name));
+            }
+            Ok((name.to_owned(), path.to_owned()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut outputs = OUTPUTS.lock().unwrap();
+    outputs.file = Some(file.to_owned());
+    outputs.specs = specs;
+    Ok(())
+}
+
+/// Extracts value by simple json path like `$.a.b[0]`, `a.b` or `$["a"]`.
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Result<Option<&'a Value>, String> {
+    let mut current = value;
+    let mut rest = path.trim().strip_prefix('$').unwrap_or(path.trim());
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']').ok_or(format!(r#"unterminated "[" in json path "{}""#, path))?;
+            let key = &r[..end];
+            rest = &r[end + 1..];
+            let quoted = key.strip_prefix('"').and_then(|k| k.strip_suffix('"'))
+                .or(key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')));
+            let next = match quoted {
+                Some(key) => current.get(key),
+                None => {
+                    let index = usize::from_str_radix(key, 10)
+                        .map_err(|_| format!(r#"invalid index "{}" in json path "{}""#, key, path))?;
+                    current.get(index)
+                },
+            };
+            match next {
+                Some(next) => current = next,
+                None => return Ok(None),
+            }
+        } else {
+            let r = rest.strip_prefix('.').unwrap_or(rest);
+            let end = r.find(|c| c == '.' || c == '[').unwrap_or(r.len());
+            if end == 0 {
+                return Err(format!(r#"empty key in json path "{}""#, path));
+            }
+            match current.get(&r[..end]) {
+                Some(next) => current = next,
+                None => return Ok(None),
+            }
+            rest = &r[end..];
+        }
+    }
+    Ok(Some(current))
+}
+
+/// Captures requested outputs from structured command result and stores them.
+pub fn capture(value: &Value) -> Result<(), String> {
+    let mut outputs = OUTPUTS.lock().unwrap();
+    if outputs.specs.is_empty() {
+        return Ok(());
+    }
+    let file = outputs.file.clone().unwrap_or_default();
+    let mut vars = load_vars(&file)?;
+    for (name, path) in &outputs.specs {
+        let found = json_path(value, path)?
+            .ok_or(format!(r#"command result has no value at "{}""#, path))?;
+        vars.insert(name.clone(), found.clone());
+    }
+    std::fs::write(&file, serde_json::to_string_pretty(&Value::Object(vars)).unwrap_or_default())
+        .map_err(|e| format!("failed to write variables file {}: {}", file, e))?;
+    outputs.captured = true;
+    Ok(())
+}
+
+/// Fails if outputs were requested but the command didn't produce structured result.
+pub fn check_captured() -> Result<(), String> {
+    let outputs = OUTPUTS.lock().unwrap();
+    if !outputs.specs.is_empty() && !outputs.captured {
+        return Err("--out is not supported by this command (it has no structured result)".to_owned());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_path() {
+        let value = json!({ "address": "0:1", "list": [{ "id": 1 }, { "id": 2 }], "a b": true });
+        assert_eq!(json_path(&value, "$.address").unwrap(), Some(&json!("0:1")));
+        assert_eq!(json_path(&value, "address").unwrap(), Some(&json!("0:1")));
+        assert_eq!(json_path(&value, "$.list[1].id").unwrap(), Some(&json!(2)));
+        assert_eq!(json_path(&value, "$[\"a b\"]").unwrap(), Some(&json!(true)));
+        assert_eq!(json_path(&value, "$.missing").unwrap(), None);
+        assert!(json_path(&value, "$.list[x]").is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let mut vars = Map::new();
+        vars.insert("addr".to_owned(), json!("0:1"));
+        vars.insert("count".to_owned(), json!(5));
+        assert_eq!(substitute("${addr}", &vars).unwrap(), "0:1");
+        assert_eq!(substitute("{\"n\":${count}}", &vars).unwrap(), "{\"n\":5}");
+        assert_eq!(substitute("${HOME}", &vars).unwrap(), "${HOME}");
+        assert!(substitute("${addr", &vars).is_err());
+    }
+}
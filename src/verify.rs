@@ -0,0 +1,216 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{create_client_verbose, load_ton_address, query};
+use crate::output;
+use crate::wallet::code_hash;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const TOOLCHAIN_DIR_NAME: &str = ".tonos-cli/compilers";
+
+pub fn create_verify_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("verify")
+        .about("Verifies that contract source code matches the code of the account: recompiles the source with the specified compiler version and compares code hashes.")
+        .arg(Arg::with_name("ADDRESS")
+            .required(true)
+            .takes_value(true)
+            .help("Contract address."))
+        .arg(Arg::with_name("SOURCE")
+            .long("--source")
+            .required(true)
+            .takes_value(true)
+            .help("Solidity source file of the contract."))
+        .arg(Arg::with_name("COMPILER")
+            .long("--compiler")
+            .required(true)
+            .takes_value(true)
+            .help("Compiler version the contract was built with (e.g. 0.42.0)."))
+        .arg(Arg::with_name("TOOLCHAIN")
+            .long("--toolchain")
+            .takes_value(true)
+            .help("Directory with solc, tvm_linker and stdlib_sol.tvm of the compiler version (default: ~/.tonos-cli/compilers/<version>)."))
+        .arg(Arg::with_name("REPORT")
+            .long("--report")
+            .takes_value(true)
+            .help("Saves verification report (json) to the file."))
+}
+
+struct Toolchain {
+    solc: PathBuf,
+    linker: PathBuf,
+    stdlib: PathBuf,
+}
+
+impl Toolchain {
+    fn new(dir: &Path) -> Result<Self, String> {
+        let toolchain = Self {
+            solc: dir.join("solc"),
+            linker: dir.join("tvm_linker"),
+            stdlib: dir.join("stdlib_sol.tvm"),
+        };
+        for path in &[&toolchain.solc, &toolchain.linker, &toolchain.stdlib] {
+            if !path.exists() {
+                return Err(format!("{} not found in toolchain directory {}", path.display(), dir.display()));
+            }
+        }
+        Ok(toolchain)
+    }
+}
+
+fn default_toolchain_dir(version: &str) -> PathBuf {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(TOOLCHAIN_DIR_NAME)
+        .join(version)
+}
+
+fn run(program: &Path, args: &[&str], dir: &Path) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", program.display(), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}{}",
+            program.display(),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Checks that `solc --version` output names the requested version.
+pub fn version_matches(output: &str, version: &str) -> bool {
+    output.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .any(|word| word == version || (version.matches('.').count() < 2 && word.starts_with(&format!("{}.", version))))
+}
+
+/// Compiles the source and returns code hash of the contract and compiler versions.
+fn compile(toolchain: &Toolchain, source: &Path, version: &str) -> Result<(String, String, String), String> {
+    let solc_version = run(&toolchain.solc, &["--version"], Path::new("."))?;
+    if !version_matches(&solc_version, version) {
+        return Err(format!("compiler in toolchain is not {}: {}", version, solc_version.trim()));
+    }
+    let linker_version = run(&toolchain.linker, &["--version"], Path::new("."))
+        .unwrap_or_default();
+    let work_dir = std::env::temp_dir().join(format!("tonos-cli-verify-{}", std::process::id()));
+    std::fs::create_dir_all(&work_dir)
+        .map_err(|e| format!("failed to create work directory: {}", e))?;
+    let result = (|| {
+        let name = source.file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or(format!("invalid source file name {}", source.display()))?;
+        let source = source.canonicalize()
+            .map_err(|e| format!("failed to open source {}: {}", source.display(), e))?;
+        run(&toolchain.solc, &[&source.to_string_lossy()], &work_dir)?;
+        let code = format!("{}.code", name);
+        let tvc = format!("{}.tvc", name);
+        run(
+            &toolchain.linker,
+            &["compile", &code, "--lib", &toolchain.stdlib.to_string_lossy(), "-o", &tvc],
+            &work_dir,
+        )?;
+        let tvc = std::fs::read(work_dir.join(&tvc))
+            .map_err(|e| format!("failed to read compiled tvc: {}", e))?;
+        code_hash(&tvc)
+    })();
+    let _ = std::fs::remove_dir_all(&work_dir);
+    Ok((result?, solc_version.trim().to_owned(), linker_version.trim().to_owned()))
+}
+
+pub async fn verify_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let address = load_ton_address(m.value_of("ADDRESS").unwrap(), &config)?;
+    let source = Path::new(m.value_of("SOURCE").unwrap());
+    let version = m.value_of("COMPILER").unwrap();
+    let toolchain_dir = m.value_of("TOOLCHAIN")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_toolchain_dir(version));
+    let toolchain = Toolchain::new(&toolchain_dir)?;
+    let source_text = std::fs::read(source)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("failed to read source {}: {}", source.display(), e)))?;
+
+    let ton = create_client_verbose(&config)?;
+    let accounts = query(
+        ton,
+        "accounts",
+        json!({ "id": { "eq": address } }),
+        "code_hash",
+        None,
+    ).await
+    .map_err(|e| CliError::new(ErrorKind::Network, format!("failed to query account: {}", e)))?;
+    let onchain_hash = accounts.first()
+        .and_then(|acc| acc["code_hash"].as_str())
+        .map(|hash| hash.to_owned())
+        .ok_or(CliError::new(ErrorKind::Contract, format!("account {} not found or has no code", address)))?;
+
+    let (compiled_hash, solc_version, linker_version) = compile(&toolchain, source, version)?;
+    let verified = compiled_hash == onchain_hash;
+    let report = json!({
+        "address": address,
+        "verified": verified,
+        "code_hash": onchain_hash,
+        "compiled_code_hash": compiled_hash,
+        "source": {
+            "file": source.file_name().and_then(|s| s.to_str()).unwrap_or(""),
+            "sha256": hex::encode(Sha256::digest(&source_text)),
+        },
+        "compiler": {
+            "version": version,
+            "solc": solc_version,
+            "tvm_linker": linker_version,
+        },
+        "verified_at": chrono::Utc::now().to_rfc3339(),
+    });
+    if let Some(path) = m.value_of("REPORT") {
+        std::fs::write(path, serde_json::to_string_pretty(&report).unwrap_or_default())
+            .map_err(|e| format!("failed to save report {}: {}", path, e))?;
+    }
+    output::print_value(&report, || {
+        if config.is_json {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            return;
+        }
+        println!("Account code hash:  {}", onchain_hash);
+        println!("Compiled code hash: {}", compiled_hash);
+        if verified {
+            println!("Verified: source {} compiled with {} matches the account code.", source.display(), version);
+        } else {
+            println!("NOT verified: code hashes differ.");
+        }
+    })?;
+    if !verified {
+        return Err(CliError::new(ErrorKind::Contract, "source doesn't match the account code"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_matches() {
+        let output = "solc, the solidity compiler commandline interface\nVersion: 0.42.0+commit.e0b2e8d8.Linux.g++\n";
+        assert!(version_matches(output, "0.42.0"));
+        assert!(version_matches(output, "0.42"));
+        assert!(!version_matches(output, "0.4"));
+        assert!(!version_matches(output, "0.41.0"));
+    }
+}
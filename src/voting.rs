@@ -12,8 +12,8 @@
  */
 use crate::config::Config;
 use crate::call;
-use crate::helpers::{create_client_local, decode_msg_body};
-use crate::multisig::{encode_transfer_body, MSIG_ABI, TRANSFER_WITH_COMMENT};
+use crate::error::CliError;
+use crate::multisig::{decode_transfer_comment, encode_transfer_body, MSIG_ABI};
 
 pub async fn create_proposal(
 	conf: Config,
@@ -23,7 +23,7 @@ pub async fn create_proposal(
 	text: &str,
 	lifetime: u32,
 	offline: bool,
-) -> Result<(), String> {
+) -> Result<(), CliError> {
 
 	let payload = encode_transfer_body(text).await?;
 
@@ -69,7 +69,7 @@ pub async fn vote(
 	trid: &str,
 	lifetime: u32,
 	offline: bool,
-) -> Result<(), String> {
+) -> Result<(), CliError> {
 
 	let params = json!({
 		"transactionId": trid,
@@ -128,21 +128,9 @@ pub async fn decode_proposal(
 		if txn_id == proposal_id {
 			let body = txn["payload"].as_str()
 				.ok_or(format!(r#"failed to parse transaction in list: "payload" not found"#))?;
-			let ton = create_client_local()?;
-			let result = decode_msg_body(
-				ton.clone(),
-				TRANSFER_WITH_COMMENT,
-				body,
-				true,
-			)
-            .await
-            .map_err(|e| format!("failed to decode proposal payload: {}", e))?;
-
-			let comment = String::from_utf8(
-				hex::decode(
-					result.value.unwrap()["comment"].as_str().unwrap()
-				).map_err(|e| format!("failed to parse comment from transaction payload: {}", e))?
-			).map_err(|e| format!("failed to convert comment to string: {}", e))?;
+			let comment = decode_transfer_comment(body)
+				.await
+				.map_err(|e| format!("failed to decode proposal payload: {}", e))?;
 
 			println!("Comment: {}", comment);
 			return Ok(());
@@ -0,0 +1,517 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+use crate::config::Config;
+use crate::convert::{convert_token, nano_to_tokens};
+use crate::crypto::{derive_keypairs, gen_seed_phrase, generate_keypair_from_mnemonic, secret_phrase, write_secret_file, SECRET_PHRASE_INPUT};
+use crate::deploy::deploy_contract;
+use crate::error::{CliError, ErrorKind};
+use crate::helpers::{calc_acc_address, create_client_verbose, load_abi, parse_wc, query, TonClient};
+use crate::keys::wallet_tvcs;
+use crate::multisig::MSIG_ABI;
+use crate::output;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde_json::Value;
+use std::io::{BufRead, Write};
+use ton_block::{Deserializable, StateInit};
+
+/// Wallet types supported by the wizard with code hashes of their official builds.
+pub const WALLET_TYPES: [(&str, &str); 3] = [
+    ("surf", "207dc560c5956de1a2c1479356f8f3ee70a59767db2bf4788b1d61ad42cdad82"),
+    ("safemultisig", "80d6c47c4a25543c9b397b71716f3fae1e2c5d247174c52e2c19bd896442b105"),
+    ("setcode", "e2b60b6b602c10ced7ea8ede4bdf96342c97570a3798066f3fb50a4b2b27a208"),
+];
+
+/// Interval between balance checks while waiting for the wallet to be funded.
+const FUNDING_POLL_INTERVAL: u64 = 10;
+/// Derivation path of wallet keys without the last index.
+const DISCOVER_PATH: &str = "m/44'/396'/0'/0";
+const DISCOVER_GAP: &str = "10";
+/// Max number of ids in one accounts query.
+const ACCOUNTS_PAGE: usize = 50;
+/// Attempts to repeat the word of the seed phrase before the wizard gives up.
+const CONFIRM_ATTEMPTS: usize = 3;
+
+pub fn create_wallet_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("wallet")
+        .about("Wallet commands.")
+        .setting(AppSettings::AllowNegativeNumbers)
+        .subcommand(SubCommand::with_name("paper")
+            .about("Generates printable backup sheet (html) with seed phrase, public key, wallet address and QR codes. Works offline.")
+            .arg(Arg::with_name("PHRASE")
+                .long("--phrase")
+                .takes_value(true)
+                .help("Seed phrase (12 words). If omitted, it's read from --phrase-file/--phrase-fd or entered without echo."))
+            .arg(Arg::with_name("OUTPUT")
+                .long("--out")
+                .short("-o")
+                .takes_value(true)
+                .required(true)
+                .help("Path to the html file. Print it (or save as pdf) from a browser."))
+            .arg(Arg::with_name("TVC")
+                .long("--tvc")
+                .takes_value(true)
+                .help("Wallet contract tvc file used to calculate wallet address."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .help("Wallet contract ABI file (default: multisig ABI)."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .help("Workchain id of the wallet address (default from config).")))
+        .subcommand(SubCommand::with_name("create")
+            .about("Creates a new wallet step by step: seed phrase, address, funding and deploy.")
+            .arg(Arg::with_name("TYPE")
+                .long("--type")
+                .takes_value(true)
+                .required(true)
+                .possible_values(&["surf", "safemultisig", "setcode"])
+                .help("Wallet contract type."))
+            .arg(Arg::with_name("TVC")
+                .long("--tvc")
+                .takes_value(true)
+                .required(true)
+                .help("Wallet contract tvc file."))
+            .arg(Arg::with_name("ABI")
+                .long("--abi")
+                .takes_value(true)
+                .required(true)
+                .help("Wallet contract ABI file."))
+            .arg(Arg::with_name("PHRASE")
+                .long("--phrase")
+                .takes_value(true)
+                .help("Existing seed phrase to use instead of generating a new one."))
+            .arg(Arg::with_name("KEYS_OUT")
+                .long("--keys-out")
+                .takes_value(true)
+                .help("Saves wallet keypair to the file."))
+            .arg(Arg::with_name("MIN_BALANCE")
+                .long("--min-balance")
+                .takes_value(true)
+                .default_value("1T")
+                .help("Balance (in tokens) the address must have before deploy."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .help("Workchain id of the wallet (default from config).")))
+        .subcommand(SubCommand::with_name("discover")
+            .about("Finds wallets of the seed phrase in the network, e.g. after reinstall. Derives keys by consecutive HD path indexes, calculates addresses of well-known wallets and prints those which exist with their balances. Scanning stops when --gap indexes in a row have no accounts.")
+            .arg(Arg::with_name("PHRASE")
+                .long("--phrase")
+                .takes_value(true)
+                .help("Seed phrase (12 words). If omitted or \"-\", it's read from --phrase-file/--phrase-fd or entered without echo."))
+            .arg(Arg::with_name("PATH")
+                .long("--path")
+                .takes_value(true)
+                .help("Derivation path without the last index (default: m/44'/396'/0'/0)."))
+            .arg(Arg::with_name("GAP")
+                .long("--gap")
+                .takes_value(true)
+                .default_value(DISCOVER_GAP)
+                .help("Number of indexes in a row without accounts after which scanning stops."))
+            .arg(Arg::with_name("TVC")
+                .long("--tvc")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Additional wallet tvc file to calculate address for (can be repeated)."))
+            .arg(Arg::with_name("WALLETS_DIR")
+                .long("--wallets-dir")
+                .takes_value(true)
+                .help("Directory with tvc files of well-known wallets: surf.tvc, safemultisig.tvc, setcode.tvc (default: ~/.tonos-cli/wallets)."))
+            .arg(Arg::with_name("WC")
+                .long("--wc")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .help("Workchain id of the wallets (default from config).")))
+}
+
+pub async fn wallet_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    if let Some(m) = m.subcommand_matches("paper") {
+        return paper_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("create") {
+        return create_command(m, config).await.map_err(CliError::from);
+    }
+    if let Some(m) = m.subcommand_matches("discover") {
+        return discover_command(m, config).await.map_err(CliError::from);
+    }
+    Err(CliError::new(ErrorKind::InvalidInput, "unknown wallet command"))
+}
+
+fn qr_svg(data: &str) -> Result<String, String> {
+    let code = QrCode::new(data.as_bytes())
+        .map_err(|e| format!("failed to generate QR code: {}", e))?;
+    Ok(code.render::<svg::Color>()
+        .min_dimensions(180, 180)
+        .build())
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_paper_wallet(phrase: &str, public: &str, address: Option<&str>) -> Result<String, String> {
+    let words: String = phrase.split_whitespace()
+        .enumerate()
+        .map(|(i, w)| format!("<li><b>{}.</b> {}</li>", i + 1, html_escape(w)))
+        .collect();
+    let address_block = match address {
+        Some(addr) => format!(
+            "<h2>Wallet address</h2><p class=\"mono\">{}</p>{}",
+            html_escape(addr),
+            qr_svg(addr)?,
+        ),
+        None => String::new(),
+    };
+    Ok(format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>TON wallet backup</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+.mono {{ font-family: monospace; word-break: break-all; }}
+ol {{ columns: 3; font-size: 1.2em; }}
+.secret {{ border: 2px dashed #000; padding: 1em; }}
+</style>
+</head>
+<body>
+<h1>TON wallet backup</h1>
+<div class="secret">
+<h2>Seed phrase (keep secret!)</h2>
+<ol>{}</ol>
+{}
+</div>
+<h2>Public key</h2>
+<p class="mono">{}</p>
+{}
+{}
+<p>Generated by tonos-cli {}</p>
+</body>
+</html>
+"#,
+        words,
+        qr_svg(phrase)?,
+        html_escape(public),
+        qr_svg(public)?,
+        address_block,
+        env!("CARGO_PKG_VERSION"),
+    ))
+}
+
+async fn paper_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let output = m.value_of("OUTPUT").unwrap();
+    if !output.ends_with(".html") && !output.ends_with(".htm") {
+        return Err("only html output is supported, save it as pdf from a browser if needed".to_owned());
+    }
+    let phrase = match m.value_of("PHRASE") {
+        Some(phrase) => phrase.to_owned(),
+        None => secret_phrase()?,
+    };
+    let wc = m.value_of("WC")
+        .map(|wc| i32::from_str_radix(wc, 10))
+        .transpose()
+        .map_err(|e| format!("failed to parse workchain id: {}", e))?
+        .unwrap_or(config.wc);
+
+    let keys = generate_keypair_from_mnemonic(&phrase)?;
+    let address = match m.value_of("TVC") {
+        Some(tvc) => {
+            let tvc = std::fs::read(tvc)
+                .map_err(|e| format!("failed to read smart contract file: {}", e))?;
+            let abi = match m.value_of("ABI") {
+                Some(path) => std::fs::read_to_string(path)
+                    .map_err(|e| format!("failed to read ABI file: {}", e))?,
+                None => MSIG_ABI.to_owned(),
+            };
+            Some(calc_acc_address(&tvc, wc, keys.public.clone(), None, load_abi(&abi)?).await?)
+        },
+        None => None,
+    };
+
+    let html = render_paper_wallet(&phrase, &keys.public, address.as_deref())?;
+    write_secret_file(output, html.as_bytes())
+        .map_err(|e| format!("failed to write backup sheet: {}", e))?;
+    println!("Backup sheet saved to file {}", output);
+    if let Some(addr) = address {
+        println!("Wallet address: {}", addr);
+    }
+    Ok(())
+}
+
+pub fn prompt(text: &str) -> Result<String, String> {
+    print!("{}", text);
+    std::io::stdout().flush().map_err(|e| format!("failed to write to terminal: {}", e))?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)
+        .map_err(|e| format!("failed to read from terminal: {}", e))?;
+    Ok(answer.trim().to_owned())
+}
+
+pub fn code_hash(tvc: &[u8]) -> Result<String, String> {
+    let state_init = StateInit::construct_from_bytes(tvc)
+        .map_err(|e| format!("failed to load StateInit from tvc: {}", e))?;
+    state_init.code
+        .map(|code| code.repr_hash().to_hex_string())
+        .ok_or("tvc file doesn't contain contract code".to_owned())
+}
+
+/// Asks the user to repeat a random word of the new seed phrase.
+fn confirm_phrase(phrase: &str) -> Result<(), String> {
+    let words = phrase.split_whitespace().count();
+    let index = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as usize)
+        .unwrap_or(0) % words;
+    let stdin = std::io::stdin();
+    let mut reader = stdin.lock();
+    confirm_phrase_from(&mut reader, phrase, index)
+}
+
+/// Reads the word `index` of the phrase from `reader`. Fails on end of input,
+/// empty answer or after `CONFIRM_ATTEMPTS` wrong words.
+fn confirm_phrase_from<R: BufRead>(reader: &mut R, phrase: &str, index: usize) -> Result<(), String> {
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    for _ in 0..CONFIRM_ATTEMPTS {
+        print!("Enter word #{} of the seed phrase to confirm you saved it: ", index + 1);
+        std::io::stdout().flush().map_err(|e| format!("failed to write to terminal: {}", e))?;
+        let mut answer = String::new();
+        let read = reader.read_line(&mut answer)
+            .map_err(|e| format!("failed to read from terminal: {}", e))?;
+        let answer = answer.trim();
+        if read == 0 || answer.is_empty() {
+            return Err("seed phrase is not confirmed: no word entered".to_owned());
+        }
+        if answer == words[index] {
+            return Ok(());
+        }
+        println!("Wrong word, check your backup.");
+    }
+    Err(format!("seed phrase is not confirmed after {} attempts", CONFIRM_ATTEMPTS))
+}
+
+pub async fn account_balance(ton: TonClient, addr: &str) -> Result<u64, String> {
+    let accounts = query(
+        ton,
+        "accounts",
+        json!({ "id": { "eq": addr } }),
+        "balance(format: DEC)",
+        None,
+    ).await
+    .map_err(|e| format!("failed to query account balance: {}", e))?;
+    Ok(accounts.get(0)
+        .and_then(|acc| acc["balance"].as_str())
+        .and_then(|b| u64::from_str_radix(b, 10).ok())
+        .unwrap_or(0))
+}
+
+/// Queries accounts by address, addresses of missing accounts are skipped.
+pub async fn query_accounts(ton: TonClient, addrs: &[String], result: &str) -> Result<Vec<Value>, String> {
+    let mut accounts = vec![];
+    for chunk in addrs.chunks(ACCOUNTS_PAGE) {
+        let page = query(
+            ton.clone(),
+            "accounts",
+            json!({ "id": { "in": chunk } }),
+            &format!("id {}", result),
+            None,
+        ).await
+        .map_err(|e| format!("failed to query accounts: {}", e))?;
+        accounts.extend(page);
+    }
+    Ok(accounts)
+}
+
+async fn discover_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let phrase = match m.value_of("PHRASE") {
+        Some(phrase) if phrase != SECRET_PHRASE_INPUT => phrase.to_owned(),
+        _ => secret_phrase()?,
+    };
+    let prefix = m.value_of("PATH").unwrap_or(DISCOVER_PATH).trim_end_matches('/');
+    let gap = u32::from_str_radix(m.value_of("GAP").unwrap(), 10)
+        .map_err(|e| format!("failed to parse gap: {}", e))?;
+    if gap == 0 {
+        return Err("gap must be greater than zero".to_owned());
+    }
+    let wc = parse_wc(m.value_of("WC"), &config)?;
+    let (wallets_dir, tvcs) = wallet_tvcs(m)?;
+    if tvcs.is_empty() {
+        return Err(format!("no wallet tvc files found in {}, put them there or use --tvc", wallets_dir.display()));
+    }
+    let abi = load_abi(MSIG_ABI)?;
+    let ton = create_client_verbose(&config)?;
+
+    let mut found = vec![];
+    let mut start = 0;
+    loop {
+        let paths: Vec<String> = (start..start + gap).map(|i| format!("{}/{}", prefix, i)).collect();
+        if !config.is_json {
+            println!("Scanning {} .. {}", paths[0], paths[paths.len() - 1]);
+        }
+        let keys = derive_keypairs(&phrase, &paths)?;
+        let mut candidates = vec![];
+        for (index, (path, keys)) in (start..).zip(paths.iter().zip(keys.iter())) {
+            for (name, tvc) in &tvcs {
+                let address = calc_acc_address(tvc, wc, keys.public.clone(), None, abi.clone()).await?;
+                candidates.push(json!({
+                    "index": index,
+                    "path": path,
+                    "public": keys.public,
+                    "type": name,
+                    "address": address,
+                }));
+            }
+        }
+        let addrs: Vec<String> = candidates.iter()
+            .map(|c| c["address"].as_str().unwrap_or("").to_owned())
+            .collect();
+        let accounts = query_accounts(ton.clone(), &addrs, "acc_type_name balance(format: DEC)").await?;
+        let before = found.len();
+        for mut candidate in candidates {
+            if let Some(acc) = accounts.iter().find(|acc| acc["id"] == candidate["address"]) {
+                candidate["status"] = acc["acc_type_name"].clone();
+                candidate["balance"] = acc["balance"].clone();
+                found.push(candidate);
+            }
+        }
+        if found.len() == before {
+            break;
+        }
+        start += gap;
+    }
+
+    let result = json!(found);
+    output::print_value(&result, || {
+        if config.is_json {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default());
+            return;
+        }
+        if found.is_empty() {
+            println!("No wallets of the seed phrase found in the network.");
+            return;
+        }
+        println!();
+        for wallet in &found {
+            let balance = wallet["balance"].as_str()
+                .and_then(|b| u64::from_str_radix(b, 10).ok())
+                .unwrap_or(0);
+            println!(
+                "{:<20} {:<14} {} {:<8} {}",
+                wallet["path"].as_str().unwrap_or(""),
+                wallet["type"].as_str().unwrap_or(""),
+                wallet["address"].as_str().unwrap_or(""),
+                wallet["status"].as_str().unwrap_or(""),
+                nano_to_tokens(balance),
+            );
+        }
+    })
+}
+
+async fn create_command(m: &ArgMatches<'_>, config: Config) -> Result<(), String> {
+    let wallet_type = m.value_of("TYPE").unwrap();
+    let tvc_path = m.value_of("TVC").unwrap();
+    let abi_path = m.value_of("ABI").unwrap();
+    let wc = parse_wc(m.value_of("WC"), &config)?;
+    let min_balance = u64::from_str_radix(&convert_token(m.value_of("MIN_BALANCE").unwrap(), config.strict_units)?, 10)
+        .map_err(|e| format!("failed to parse minimal balance: {}", e))?;
+
+    let tvc = std::fs::read(tvc_path)
+        .map_err(|e| format!("failed to read smart contract file: {}", e))?;
+    let abi = std::fs::read_to_string(abi_path)
+        .map_err(|e| format!("failed to read ABI file: {}", e))?;
+    let expected_hash = WALLET_TYPES.iter().find(|(t, _)| *t == wallet_type).map(|(_, h)| *h).unwrap();
+    if code_hash(&tvc)? != expected_hash {
+        println!("Warning: contract code doesn't match the official {} wallet.", wallet_type);
+    }
+
+    println!("Step 1. Seed phrase");
+    let phrase = match m.value_of("PHRASE") {
+        Some(phrase) => phrase.to_owned(),
+        None => {
+            let phrase = gen_seed_phrase()?;
+            println!("Write down the seed phrase and keep it in a safe place:");
+            println!();
+            println!("    {}", phrase);
+            println!();
+            confirm_phrase(&phrase)?;
+            phrase
+        },
+    };
+    let keys = generate_keypair_from_mnemonic(&phrase)?;
+    println!("Public key: {}", keys.public);
+    if let Some(path) = m.value_of("KEYS_OUT") {
+        let keys_json = serde_json::to_string_pretty(&keys)
+            .map_err(|e| format!("failed to serialize the keypair: {}", e))?;
+        write_secret_file(path, keys_json.as_bytes())
+            .map_err(|e| format!("failed to create file with keys: {}", e))?;
+        println!("Keypair saved to {}", path);
+    }
+
+    println!();
+    println!("Step 2. Wallet address");
+    let addr = calc_acc_address(&tvc, wc, keys.public.clone(), None, load_abi(&abi)?).await?;
+    println!("Address: {}", addr);
+    qr2term::print_qr(&addr).map_err(|e| format!("failed to print QR code: {}", e))?;
+
+    println!();
+    println!("Step 3. Funding");
+    let ton = create_client_verbose(&config)?;
+    loop {
+        let balance = account_balance(ton.clone(), &addr).await?;
+        if balance >= min_balance {
+            println!("Balance: {}", nano_to_tokens(balance));
+            break;
+        }
+        println!(
+            "Balance {} is less than {}. Send tokens to the address, waiting...",
+            nano_to_tokens(balance),
+            nano_to_tokens(min_balance),
+        );
+        tokio::time::delay_for(std::time::Duration::from_secs(FUNDING_POLL_INTERVAL)).await;
+    }
+
+    println!();
+    println!("Step 4. Deploy");
+    let params = json!({
+        "owners": [format!("0x{}", keys.public)],
+        "reqConfirms": 1,
+    }).to_string();
+    deploy_contract(config, tvc_path, abi_path, &params, &phrase, wc, false, None).await?;
+    println!("Wallet {} is ready.", addr);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm_phrase() {
+        let phrase = "word1 word2 word3";
+        assert!(confirm_phrase_from(&mut "word2\n".as_bytes(), phrase, 1).is_ok());
+        assert!(confirm_phrase_from(&mut "wrong\nword2\n".as_bytes(), phrase, 1).is_ok());
+        assert!(confirm_phrase_from(&mut "".as_bytes(), phrase, 1).is_err());
+        assert!(confirm_phrase_from(&mut "wrong\n".as_bytes(), phrase, 1).is_err());
+        assert!(confirm_phrase_from(&mut "\nword2\n".as_bytes(), phrase, 1).is_err());
+        let err = confirm_phrase_from(&mut "a\nb\nc\nword2\n".as_bytes(), phrase, 1).unwrap_err();
+        assert!(err.contains("3 attempts"));
+    }
+}
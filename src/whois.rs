@@ -0,0 +1,192 @@
+/*
+ * Copyright 2018-2020 TON DEV SOLUTIONS LTD.
+ *
+ * Licensed under the SOFTWARE EVALUATION License (the "License"); you may not use
+ * this file except in compliance with the License.
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific TON DEV software governing permissions and
+ * limitations under the License.
+ */
+//! Reverse lookup of wallets by public key: addresses of all well-known wallet
+//! contracts with the key are calculated and checked in the network.
+//! DeNS names are not resolved, only config aliases are shown as names.
+use crate::config::Config;
+use crate::convert::nano_to_tokens;
+use crate::error::{CliError, ErrorKind, ResultExt};
+use crate::helpers::{calc_acc_address, create_client_verbose, load_abi, parse_wc};
+use crate::keys::wallet_tvcs;
+use crate::known_abi::{find_known_abi, ContractFamily};
+use crate::multisig::MSIG_ABI;
+use crate::output;
+use crate::wallet::{code_hash, query_accounts, WALLET_TYPES};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::collections::BTreeMap;
+
+pub fn create_whois_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("whois")
+        .about("Finds which addresses belong to the public key: calculates addresses of well-known wallets with the key and checks which of them exist in the network. Official tvc files of the wallets are not bundled: put them into the wallets directory or pass with --tvc. Address aliases from config are printed as names, DeNS names are not supported.")
+        .arg(Arg::with_name("PUBKEY")
+            .long("--pubkey")
+            .takes_value(true)
+            .required(true)
+            .help("Public key in hex."))
+        .arg(Arg::with_name("TVC")
+            .long("--tvc")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Additional wallet tvc file to calculate address for (can be repeated)."))
+        .arg(Arg::with_name("WALLETS_DIR")
+            .long("--wallets-dir")
+            .takes_value(true)
+            .help("Directory with tvc files of well-known wallets: surf.tvc, safemultisig.tvc, setcode.tvc (default: ~/.tonos-cli/wallets)."))
+        .arg(Arg::with_name("WC")
+            .long("--wc")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .help("Workchain id of the addresses (default from config)."))
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<String, String> {
+    let pubkey = pubkey.trim_start_matches("0x").to_lowercase();
+    match hex::decode(&pubkey) {
+        Ok(bytes) if bytes.len() == 32 => Ok(pubkey),
+        _ => Err(format!("invalid public key {}: must be 32 bytes in hex", pubkey)),
+    }
+}
+
+/// Detects wallet type and ABI by the code hash of the tvc. Unknown code is
+/// treated as a custom multisig wallet.
+fn wallet_kind(code_hash: &str) -> (Option<&'static str>, String) {
+    let wallet_type = WALLET_TYPES.iter()
+        .find(|(_, hash)| *hash == code_hash)
+        .map(|(name, _)| *name);
+    let abi = find_known_abi(ContractFamily::Multisig, code_hash)
+        .unwrap_or_else(|| MSIG_ABI.to_owned());
+    (wallet_type, abi)
+}
+
+/// Names of config aliases pointing to the address.
+fn alias_names(aliases: &BTreeMap<String, String>, addr: &str) -> Vec<String> {
+    aliases.iter()
+        .filter(|(_, alias_addr)| alias_addr.as_str() == addr)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+pub async fn whois_command(m: &ArgMatches<'_>, config: Config) -> Result<(), CliError> {
+    let pubkey = parse_pubkey(m.value_of("PUBKEY").unwrap())?;
+    let wc = parse_wc(m.value_of("WC"), &config)?;
+    let (wallets_dir, tvcs) = wallet_tvcs(m)?;
+    let missing: Vec<&str> = WALLET_TYPES.iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !tvcs.iter().any(|(tvc_name, _)| tvc_name == name))
+        .collect();
+    if tvcs.is_empty() {
+        return Err(CliError::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "no wallet tvc files found in {}: tvc files of the official wallets are not bundled, put {} there or use --tvc",
+                wallets_dir.display(),
+                missing.iter().map(|name| format!("{}.tvc", name)).collect::<Vec<_>>().join(", "),
+            ),
+        ));
+    }
+    let mut wallets = vec![];
+    for (name, tvc) in &tvcs {
+        let hash = code_hash(tvc).kind(ErrorKind::InvalidInput)
+            .map_err(|e| e.context(format!("tvc {}", name)))?;
+        let (wallet_type, abi) = wallet_kind(&hash);
+        let address = calc_acc_address(tvc, wc, pubkey.clone(), None, load_abi(&abi)?).await?;
+        wallets.push(json!({
+            "type": wallet_type.unwrap_or("custom"),
+            "tvc": name,
+            "code_hash": hash,
+            "address": address,
+        }));
+    }
+
+    let ton = create_client_verbose(&config)?;
+    let addrs: Vec<String> = wallets.iter()
+        .map(|w| w["address"].as_str().unwrap_or("").to_owned())
+        .collect();
+    let accounts = query_accounts(ton, &addrs, "acc_type_name balance(format: DEC)").await
+        .kind(ErrorKind::Network)?;
+    for wallet in wallets.iter_mut() {
+        let addr = wallet["address"].as_str().unwrap_or("").to_owned();
+        match accounts.iter().find(|acc| acc["id"] == wallet["address"]) {
+            Some(acc) => {
+                wallet["status"] = acc["acc_type_name"].clone();
+                wallet["balance"] = acc["balance"].clone();
+            },
+            None => {
+                wallet["status"] = json!("NonExist");
+                wallet["balance"] = json!("0");
+            },
+        }
+        wallet["names"] = json!(alias_names(&config.aliases, &addr));
+    }
+
+    let result = json!({ "public": pubkey, "wallets": wallets, "missing_tvc": missing });
+    output::print_value(&result, || {
+        if config.is_json {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap_or_default());
+            return;
+        }
+        println!("Public key: {}", pubkey);
+        for wallet in &wallets {
+            let balance = wallet["balance"].as_str()
+                .and_then(|b| u64::from_str_radix(b, 10).ok())
+                .unwrap_or(0);
+            let names = wallet["names"].as_array().cloned().unwrap_or_default();
+            let names: Vec<&str> = names.iter().filter_map(|n| n.as_str()).collect();
+            println!(
+                "{:<14} {} {:<8} {}{}",
+                wallet["type"].as_str().unwrap_or(""),
+                wallet["address"].as_str().unwrap_or(""),
+                wallet["status"].as_str().unwrap_or(""),
+                nano_to_tokens(balance),
+                if names.is_empty() { String::new() } else { format!(" ({})", names.join(", ")) },
+            );
+        }
+        if !missing.is_empty() {
+            println!("Not checked (no tvc in {}): {}", wallets_dir.display(), missing.join(", "));
+        }
+        if wallets.iter().all(|w| w["status"] == "NonExist") {
+            println!("None of the addresses exists in the network.");
+        }
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whois_helpers() {
+        let key = "9711a04f0b19474272bc7bae5472a8fbbb6ef71ce9c193f5ec3f5af808069a41";
+        assert_eq!(parse_pubkey(&format!("0x{}", key.to_uppercase())).unwrap(), key);
+        assert!(parse_pubkey("9711a04f").is_err());
+        assert!(parse_pubkey("xyz").is_err());
+
+        let mut aliases = BTreeMap::new();
+        aliases.insert("main".to_owned(), "0:01".to_owned());
+        aliases.insert("savings".to_owned(), "0:02".to_owned());
+        aliases.insert("old".to_owned(), "0:01".to_owned());
+        assert_eq!(alias_names(&aliases, "0:01"), vec!["main", "old"]);
+        assert!(alias_names(&aliases, "0:03").is_empty());
+
+        for (name, hash) in WALLET_TYPES.iter() {
+            let (wallet_type, abi) = wallet_kind(hash);
+            assert_eq!(wallet_type, Some(*name));
+            assert_eq!(abi, find_known_abi(ContractFamily::Multisig, hash).unwrap());
+        }
+        let (wallet_type, abi) = wallet_kind("00");
+        assert_eq!(wallet_type, None);
+        assert_eq!(abi, MSIG_ABI);
+    }
+}